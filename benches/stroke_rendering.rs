@@ -0,0 +1,40 @@
+//! Compare tessellating strokes into meshes with rasterizing them into textures
+//!
+//! Run with `cargo bench --bench stroke_rendering`.
+
+use criterion::{BenchmarkId, Criterion, criterion_group, criterion_main};
+use eframe_paint::Element;
+use eframe_paint::element::{ElementId, factory};
+use eframe_paint::tessellation::stroke_mesh;
+use egui::{Color32, Pos2};
+use std::hint::black_box;
+
+/// A wavy stroke of `count` points, like one drawn by hand
+fn wavy_points(count: usize) -> Vec<Pos2> {
+    (0..count)
+        .map(|i| {
+            let t = i as f32;
+            Pos2::new(t * 2.0, 100.0 + (t * 0.1).sin() * 50.0)
+        })
+        .collect()
+}
+
+fn stroke_rendering(c: &mut Criterion) {
+    let ctx = egui::Context::default();
+    let mut group = c.benchmark_group("stroke_rendering");
+    for count in [10, 100, 1000] {
+        let points = wavy_points(count);
+        group.bench_with_input(BenchmarkId::new("mesh", count), &points, |b, points| {
+            b.iter(|| stroke_mesh(black_box(points), 8.0, Color32::BLACK));
+        });
+        group.bench_with_input(BenchmarkId::new("texture", count), &points, |b, points| {
+            let mut stroke =
+                factory::create_stroke(ElementId::default(), points.clone(), 8.0, Color32::BLACK);
+            b.iter(|| stroke.generate_texture(black_box(&ctx)));
+        });
+    }
+    group.finish();
+}
+
+criterion_group!(benches, stroke_rendering);
+criterion_main!(benches);
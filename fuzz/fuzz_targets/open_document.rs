@@ -0,0 +1,23 @@
+//! Open arbitrary bytes as a document file, as the app does, and draw it
+//!
+//! Run with `cargo +nightly fuzz run open_document`. Opening may fail, but
+//! only with a `DocumentError`; whatever opens must be drawn without panicking
+//! or running out of memory.
+
+#![no_main]
+
+use eframe_paint::export::{ExportSettings, rasterize_document};
+use eframe_paint::file_handler::decode_document;
+use eframe_paint::state::EditorModel;
+use libfuzzer_sys::fuzz_target;
+
+fuzz_target!(|data: &[u8]| {
+    // Without a passphrase, so encrypted files stop before the slow key derivation
+    let Ok(file) = decode_document(data.to_vec(), None) else {
+        return;
+    };
+    let mut editor_model = EditorModel::new();
+    editor_model.load_document(file);
+    let ctx = egui::Context::default();
+    let _ = rasterize_document(&editor_model, &ExportSettings::default(), &ctx);
+});
@@ -0,0 +1,17 @@
+//! Open arbitrary text as the fragment of a share link
+//!
+//! Run with `cargo +nightly fuzz run open_share_link`. Links are unpacked
+//! before they're read, so this also looks for ones that blow up too far.
+
+#![no_main]
+
+use eframe_paint::share_link::decode_fragment;
+use eframe_paint::state::EditorModel;
+use libfuzzer_sys::fuzz_target;
+
+fuzz_target!(|fragment: &str| {
+    // Most inputs would fail the prefix check, so every one is given it
+    if let Ok(file) = decode_fragment(&format!("doc={}", fragment)) {
+        EditorModel::new().load_document(file);
+    }
+});
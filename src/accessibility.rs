@@ -0,0 +1,219 @@
+//! Descriptions of the canvas for screen readers
+//!
+//! The canvas is one big painted area, so assistive tech can't see the elements
+//! on it. This describes the selected and hovered elements in words
+//! ("Stroke, 42 points, red, at 120×80") for the canvas's accessibility node, and
+//! announces tool changes and command results through a live region.
+
+use crate::element::{Element, ElementType};
+use crate::events::EditorEvent;
+use crate::state::EditorModel;
+use egui::{Color32, Pos2};
+use std::sync::mpsc::Receiver;
+
+/// Colors with names, matched by nearest distance in RGB
+const NAMED_COLORS: [(&str, [u8; 3]); 13] = [
+    ("black", [0, 0, 0]),
+    ("white", [255, 255, 255]),
+    ("gray", [128, 128, 128]),
+    ("red", [220, 30, 30]),
+    ("orange", [255, 150, 0]),
+    ("yellow", [255, 230, 0]),
+    ("green", [30, 170, 50]),
+    ("cyan", [0, 200, 220]),
+    ("blue", [30, 80, 230]),
+    ("purple", [130, 40, 180]),
+    ("pink", [255, 120, 190]),
+    ("brown", [140, 80, 30]),
+    ("dark blue", [20, 30, 110]),
+];
+
+/// The everyday name closest to a color
+pub fn color_name(color: Color32) -> &'static str {
+    if color.a() < 16 {
+        return "transparent";
+    }
+    let [r, g, b, _] = color.to_srgba_unmultiplied();
+    let distance = |[nr, ng, nb]: [u8; 3]| {
+        let dr = r as i32 - nr as i32;
+        let dg = g as i32 - ng as i32;
+        let db = b as i32 - nb as i32;
+        dr * dr + dg * dg + db * db
+    };
+    NAMED_COLORS
+        .iter()
+        .min_by_key(|(_, rgb)| distance(*rgb))
+        .map_or("unknown", |(name, _)| name)
+}
+
+/// Describe an element in a phrase, like "Stroke, 42 points, red, at 120×80"
+///
+/// Named elements start with their name, and locked ones end saying so.
+pub fn describe_element(element: &ElementType) -> String {
+    let rect = element.rect();
+    let mut parts = Vec::new();
+    if !element.name().is_empty() {
+        parts.push(format!("\"{}\"", element.name()));
+    }
+    parts.push(match element {
+        ElementType::Stroke(stroke) => format!("Stroke, {} points", stroke.points().len()),
+        ElementType::Image(_) => format!("Image, {:.0}×{:.0}", rect.width(), rect.height()),
+        ElementType::Shape(shape) => match shape.polygon() {
+            Some(params) if params.star_ratio < 1.0 => format!("Star, {} points", params.sides),
+            Some(params) => format!("Polygon, {} sides", params.sides),
+            None => format!("Shape, {} corners", shape.points().len()),
+        },
+        ElementType::Path(path) => format!("Path, {} nodes", path.nodes().len()),
+        ElementType::StepBadge(badge) => format!("Step {} badge", badge.number()),
+        ElementType::StickyNote(note) => match note.text().lines().next() {
+            Some(line) if !line.trim().is_empty() => format!("Sticky note \"{}\"", line.trim()),
+            _ => "Empty sticky note".to_string(),
+        },
+    });
+    if let Some(color) = element.color() {
+        parts.push(color_name(color).to_string());
+    }
+    parts.push(format!("at {:.0}×{:.0}", rect.min.x, rect.min.y));
+    if element.metadata().is_locked() {
+        parts.push("locked".to_string());
+    }
+    parts.join(", ")
+}
+
+/// Describe the canvas: how many elements it has, what's selected and what's
+/// under the pointer at `hover_pos`
+pub fn describe_canvas(editor_model: &EditorModel, hover_pos: Option<Pos2>) -> String {
+    let mut description = match editor_model.elements.len() {
+        0 => "Canvas, empty".to_string(),
+        1 => "Canvas, 1 element".to_string(),
+        count => format!("Canvas, {} elements", count),
+    };
+
+    let selected: Vec<String> = editor_model
+        .elements
+        .iter()
+        .filter(|element| editor_model.is_element_selected(element.id()))
+        .map(describe_element)
+        .collect();
+    match selected.as_slice() {
+        [] => {}
+        [only] => description.push_str(&format!(". Selected: {}", only)),
+        many => description.push_str(&format!(". {} selected: {}", many.len(), many.join("; "))),
+    }
+
+    let hovered = hover_pos.and_then(|pos| editor_model.element_at_position(pos));
+    if let Some(element) = hovered.filter(|element| !editor_model.is_element_selected(element.id()))
+    {
+        description.push_str(&format!(
+            ". Under the pointer: {}",
+            describe_element(element)
+        ));
+    }
+    description
+}
+
+/// Live region that screen readers read out whenever its message changes
+///
+/// Executed, undone and redone commands are announced as they happen; other
+/// news, like a tool change or a failure, is passed to [`Announcer::announce`].
+#[derive(Debug)]
+pub struct Announcer {
+    events: Receiver<EditorEvent>,
+    message: String,
+    // Bumped for every announcement, so saying the same thing twice is heard twice
+    count: u64,
+}
+
+impl Announcer {
+    pub fn new(events: Receiver<EditorEvent>) -> Self {
+        Self {
+            events,
+            message: String::new(),
+            count: 0,
+        }
+    }
+
+    pub fn announce(&mut self, message: impl Into<String>) {
+        self.message = message.into();
+        self.count += 1;
+    }
+
+    /// The last announcement
+    pub fn message(&self) -> &str {
+        &self.message
+    }
+
+    /// Announce the commands run since the last call
+    fn announce_events(&mut self) {
+        while let Ok(event) = self.events.try_recv() {
+            let label = event.command().label();
+            match event {
+                EditorEvent::CommandExecuted(_) => self.announce(label),
+                EditorEvent::CommandUndone(_) => self.announce(format!("Undid {}", label)),
+                EditorEvent::CommandRedone(_) => self.announce(format!("Redid {}", label)),
+            }
+        }
+    }
+
+    /// Add the live region to this frame's accessibility tree
+    pub fn show(&mut self, ctx: &egui::Context) {
+        self.announce_events();
+        if self.message.is_empty() {
+            return;
+        }
+        let id = egui::Id::new("announcement").with(self.count);
+        ctx.accesskit_node_builder(id, |node| {
+            node.set_role(egui::accesskit::Role::Status);
+            node.set_live(egui::accesskit::Live::Polite);
+            node.set_value(self.message.clone());
+        });
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::command::{Command, CommandHistory};
+    use crate::element::{ElementId, factory};
+
+    #[test]
+    fn test_elements_and_canvas_are_described_in_words() {
+        let stroke = factory::create_stroke(
+            ElementId::new(1),
+            vec![Pos2::new(120.0, 80.0), Pos2::new(200.0, 90.0)],
+            0.0,
+            Color32::from_rgb(230, 20, 20),
+        );
+        assert_eq!(
+            describe_element(&stroke),
+            "Stroke, 2 points, red, at 120×80"
+        );
+        assert_eq!(color_name(Color32::from_rgb(10, 10, 10)), "black");
+        assert_eq!(color_name(Color32::TRANSPARENT), "transparent");
+
+        let mut model = EditorModel::new();
+        assert_eq!(describe_canvas(&model, None), "Canvas, empty");
+        model.add_element(stroke).unwrap();
+        model.select_element(ElementId::new(1));
+        assert_eq!(
+            describe_canvas(&model, None),
+            "Canvas, 1 element. Selected: Stroke, 2 points, red, at 120×80"
+        );
+    }
+
+    #[test]
+    fn test_announcer_reads_out_commands() {
+        let mut history = CommandHistory::new();
+        let mut announcer = Announcer::new(history.subscribe());
+        let mut model = EditorModel::new();
+        history
+            .execute(Command::new_clear_selection(&model), &mut model)
+            .unwrap();
+        history.undo(&mut model).unwrap();
+        announcer.announce_events();
+        assert_eq!(announcer.message(), "Undid Clear Selection");
+
+        announcer.announce("Pen tool");
+        assert_eq!(announcer.message(), "Pen tool");
+    }
+}
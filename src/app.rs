@@ -1,21 +1,202 @@
+use crate::accessibility::Announcer;
 use crate::command::{Command, CommandHistory};
-use crate::element::{ElementType};
+use crate::document::{DOCUMENT_EXTENSION, DocumentError, DocumentFile};
+use crate::element::{Element, ElementId, ElementType};
 use crate::file_handler::FileHandler;
-use crate::panels::{central_panel, tools_panel};
+use crate::memory::{COMPACT_HISTORY_STEPS, MemoryUsage};
+use crate::export::ExportPreset;
+use crate::new_document::new_document;
+use crate::panels::{
+    ArrayDialog, BugReportDialog, DocumentPropertiesDialog, ExportDialog, LogConsole,
+    NewDocumentDialog, PanelLayout, PassphraseChoice, PassphrasePrompt, PassphrasePurpose,
+    PreferencesDialog, QuickSketch, RelinkChoice, RelinkDialog, ReplaceColorsDialog,
+    ReplayControls, TransformDialog, UnsavedChoice, central_panel, docked_panels, status_bar,
+    tutorial_window, unsaved_changes_prompt,
+};
 use crate::renderer::Renderer;
+use crate::repeat_action::{DUPLICATE_OFFSET, RepeatAction};
+use crate::settings::Settings;
 use crate::state::EditorModel;
-use crate::tools::{Tool, ToolType, new_draw_stroke_tool, new_selection_tool};
+use crate::tools::{
+    BRUSH_LARGER_CTRL_KEY, BRUSH_LARGER_KEY, BRUSH_SMALLER_CTRL_KEY, BRUSH_SMALLER_KEY, ColorPair,
+    DrawStrokeConfig, KeyboardTransform, PAN_KEY, QuickToggle, SWAP_COLORS_KEY, TOOL_HOTKEYS, Tool,
+    ToolType, new_annotate_tool, new_draw_stroke_tool, new_export_slice_tool, new_laser_tool,
+    new_pen_tool, new_pixel_tool, new_polygon_tool, new_selection_tool, new_slice_tool,
+    new_sticky_note_tool, step_brush_size,
+};
+use crate::tutorial::{Tutorial, tutorial_document};
+use crate::view::View;
+use crate::widgets::Toasts;
 use eframe::egui;
 
+/// Storage key for the user's saved export presets
+const EXPORT_PRESETS_KEY: &str = "export_presets";
+/// Storage key for the user's preferences
+const SETTINGS_KEY: &str = "settings";
+/// Storage key for the arrangement of the docked panels
+const PANEL_LAYOUT_KEY: &str = "panel_layout";
+/// Storage key for the foreground and background colors
+const COLORS_KEY: &str = "colors";
+
+/// Name shown in the window title; also names the app's data folder
+const APP_NAME: &str = "Paint App";
+/// Folder inside the app's data folder that quick sketches go to by default
+#[cfg(not(target_arch = "wasm32"))]
+const SCRATCH_DIR_NAME: &str = "scratch";
+/// Gap between a newly placed artboard and the canvas panel edge or other artboards
+const ARTBOARD_INSET: f32 = 20.0;
+
+/// Undo the last command (Cmd+Z on macOS)
+const UNDO_SHORTCUT: egui::KeyboardShortcut =
+    egui::KeyboardShortcut::new(egui::Modifiers::COMMAND, egui::Key::Z);
+/// Redo the last undone command; Ctrl+Y works too
+const REDO_SHORTCUT: egui::KeyboardShortcut = egui::KeyboardShortcut::new(
+    egui::Modifiers::COMMAND.plus(egui::Modifiers::SHIFT),
+    egui::Key::Z,
+);
+const REDO_SHORTCUT_ALT: egui::KeyboardShortcut =
+    egui::KeyboardShortcut::new(egui::Modifiers::COMMAND, egui::Key::Y);
+
+/// Canvas zoom, taking over egui's own shortcuts for zooming the whole window
+pub const ZOOM_IN_SHORTCUT: egui::KeyboardShortcut =
+    egui::KeyboardShortcut::new(egui::Modifiers::COMMAND, egui::Key::Equals);
+const ZOOM_IN_SHORTCUT_ALT: egui::KeyboardShortcut =
+    egui::KeyboardShortcut::new(egui::Modifiers::COMMAND, egui::Key::Plus);
+pub const ZOOM_OUT_SHORTCUT: egui::KeyboardShortcut =
+    egui::KeyboardShortcut::new(egui::Modifiers::COMMAND, egui::Key::Minus);
+pub const ZOOM_TO_FIT_SHORTCUT: egui::KeyboardShortcut =
+    egui::KeyboardShortcut::new(egui::Modifiers::COMMAND, egui::Key::Num0);
+pub const ZOOM_ACTUAL_SIZE_SHORTCUT: egui::KeyboardShortcut =
+    egui::KeyboardShortcut::new(egui::Modifiers::COMMAND, egui::Key::Num1);
+pub const ZOOM_TO_SELECTION_SHORTCUT: egui::KeyboardShortcut =
+    egui::KeyboardShortcut::new(egui::Modifiers::COMMAND, egui::Key::Num2);
+/// Degrees the canvas turns per point scrolled with Alt held; a wheel notch
+/// turns it by about 15°
+const WHEEL_ROTATION_DEGREES: f32 = 0.3;
+/// Copy the selection to the clipboard as an image
+///
+/// egui turns Ctrl+C into a copy event whatever else is held, so this arrives
+/// as a copy event with Shift down rather than as the key.
+pub const COPY_SELECTION_SHORTCUT: egui::KeyboardShortcut = egui::KeyboardShortcut::new(
+    egui::Modifiers::COMMAND.plus(egui::Modifiers::SHIFT),
+    egui::Key::C,
+);
+/// Copy the selection, a little down and to the right
+pub const DUPLICATE_SELECTION_SHORTCUT: egui::KeyboardShortcut =
+    egui::KeyboardShortcut::new(egui::Modifiers::COMMAND, egui::Key::J);
+/// Do the last move, transform or duplicate of the selection again
+pub const REPEAT_SHORTCUT: egui::KeyboardShortcut =
+    egui::KeyboardShortcut::new(egui::Modifiers::COMMAND, egui::Key::D);
+/// Open the Transform window on the selection
+pub const TRANSFORM_SELECTION_SHORTCUT: egui::KeyboardShortcut = egui::KeyboardShortcut::new(
+    egui::Modifiers::COMMAND.plus(egui::Modifiers::SHIFT),
+    egui::Key::M,
+);
+
+/// Something that replaces or closes the current document
+///
+/// When the document has unsaved changes these wait for the user to answer
+/// the unsaved-changes prompt.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum DocumentAction {
+    /// Start a document from the settings in the New Document window
+    New,
+    Open,
+    /// Open the last screen capture as a new document for annotating
+    AnnotateScreenshot,
+    /// Open the bundled tutorial document and start its step panel
+    Tutorial,
+    Quit,
+}
+
 /// Main application state
 pub struct PaintApp {
     renderer: Renderer,
     editor_model: EditorModel,
     command_history: CommandHistory,
     central_panel_rect: egui::Rect,
+    // Zoom and scroll position of the canvas
+    view: View,
     available_tools: Vec<ToolType>,
     file_handler: FileHandler,
     last_rendered_version: u64,
+    export_dialog: ExportDialog,
+    quick_toggle: QuickToggle,
+    keyboard_transform: KeyboardTransform,
+    // Tab presses taken from egui's focus navigation for cycling elements, with Shift held
+    canvas_tabs: Vec<egui::Modifiers>,
+    settings: Settings,
+    // Foreground and background colors the painting tools share
+    colors: ColorPair,
+    // Which side each docked panel is on, in what order, and which are collapsed
+    panel_layout: PanelLayout,
+    preferences_dialog: PreferencesDialog,
+    new_document_dialog: NewDocumentDialog,
+    document_properties_dialog: DocumentPropertiesDialog,
+    transform_dialog: TransformDialog,
+    array_dialog: ArrayDialog,
+    replace_colors_dialog: ReplaceColorsDialog,
+    // What Repeat does again, and the elements it last left selected
+    repeat: Option<(RepeatAction, Vec<ElementId>)>,
+    relink_dialog: RelinkDialog,
+    // Scales and folder the Slices panel exports with
+    slice_export: crate::export_slice::SliceExportOptions,
+    replay_controls: ReplayControls,
+    // Path the document is saved to and opened from
+    document_path: String,
+    // Document version at the last save or load
+    saved_version: usize,
+    // Action waiting on the unsaved-changes prompt
+    pending_action: Option<DocumentAction>,
+    // Last failed save or open, shown in the unsaved-changes prompt until the next attempt
+    document_error: Option<String>,
+    // Messages about failed commands, saves and opens, shown over the canvas
+    toasts: Toasts,
+    // Reads out tool changes and command results to screen readers
+    announcer: Announcer,
+    // Copies of the document kept in the browser, reopened when the page reloads
+    #[cfg(target_arch = "wasm32")]
+    autosaves: crate::autosave::WebAutosaves,
+    // Document version last autosaved in the browser
+    #[cfg(target_arch = "wasm32")]
+    autosaved_version: usize,
+    // Passphrase the document is encrypted with when saved, if it is protected
+    document_passphrase: Option<String>,
+    // Open prompt asking for a passphrase to open or protect the document with
+    passphrase_prompt: Option<PassphrasePrompt>,
+    // Set once the user agreed to quit, so the next close request goes through
+    allow_close: bool,
+    // Title last sent to the window, to avoid resending it every frame
+    window_title: String,
+    quick_sketch: QuickSketch,
+    bug_report_dialog: BugReportDialog,
+    // Frame timings and cache stats window, toggled with F12
+    show_diagnostics: bool,
+    // Recent log records, toggled with Shift+F12
+    log_console: LogConsole,
+    // Running tutorial, if the tutorial document is open
+    tutorial: Option<Tutorial>,
+    // Tray icon, added on the first frame if enabled in the settings
+    #[cfg(all(feature = "tray", not(target_arch = "wasm32")))]
+    tray: Option<crate::tray::Tray>,
+    #[cfg(all(feature = "tray", not(target_arch = "wasm32")))]
+    tray_started: bool,
+    // Captured screen waiting to be opened by `DocumentAction::AnnotateScreenshot`
+    pending_screenshot: Option<Vec<u8>>,
+    // Outcome of the last screenshot or clipboard copy, shown in the tools panel
+    screenshot_status: Option<Result<String, String>>,
+    #[cfg(not(target_arch = "wasm32"))]
+    screen_capture: Option<crate::screenshot::ScreenCapture>,
+    // Screenshot shortcut, registered on the first frame if enabled in the settings
+    #[cfg(all(feature = "global-hotkey", not(target_arch = "wasm32")))]
+    screenshot_hotkey: Option<crate::screenshot::ScreenshotHotkey>,
+    #[cfg(all(feature = "global-hotkey", not(target_arch = "wasm32")))]
+    screenshot_hotkey_started: bool,
+    // Kept alive so copied images stay on the clipboard
+    #[cfg(not(target_arch = "wasm32"))]
+    clipboard: Option<crate::export::Clipboard>,
+    #[cfg(not(target_arch = "wasm32"))]
+    print_dialog: crate::panels::PrintDialog,
 }
 
 impl PaintApp {
@@ -24,17 +205,1097 @@ impl PaintApp {
         let available_tools = vec![
             ToolType::DrawStroke(new_draw_stroke_tool()),
             ToolType::Selection(new_selection_tool()),
+            ToolType::Slice(new_slice_tool()),
+            ToolType::ExportSlice(new_export_slice_tool()),
+            ToolType::Annotate(new_annotate_tool()),
+            ToolType::Pen(new_pen_tool()),
+            ToolType::Polygon(new_polygon_tool()),
+            ToolType::Pixel(new_pixel_tool()),
+            ToolType::StickyNote(new_sticky_note_tool()),
+            ToolType::Laser(new_laser_tool()),
         ];
 
-        Self {
+        // Restore saved export presets, falling back to the built-in ones
+        let export_presets: Vec<ExportPreset> = cc
+            .storage
+            .and_then(|storage| eframe::get_value(storage, EXPORT_PRESETS_KEY))
+            .unwrap_or_else(crate::export::default_presets);
+        let settings: Settings = cc
+            .storage
+            .and_then(|storage| eframe::get_value(storage, SETTINGS_KEY))
+            .unwrap_or_default();
+        let panel_layout = cc
+            .storage
+            .and_then(|storage| eframe::get_value::<PanelLayout>(storage, PANEL_LAYOUT_KEY))
+            .map(PanelLayout::sanitized)
+            .unwrap_or_default();
+        let colors: ColorPair = cc
+            .storage
+            .and_then(|storage| eframe::get_value(storage, COLORS_KEY))
+            .unwrap_or_default();
+
+        let mut command_history = CommandHistory::new();
+        let announcer = Announcer::new(command_history.subscribe());
+
+        let mut app = Self {
             renderer: Renderer::new(cc),
             editor_model: EditorModel::new(),
-            command_history: CommandHistory::new(),
+            command_history,
             central_panel_rect: egui::Rect::NOTHING,
+            view: View::default(),
             available_tools,
             file_handler: FileHandler::new(),
             last_rendered_version: 0,
+            export_dialog: ExportDialog::new(export_presets),
+            quick_toggle: QuickToggle::default(),
+            keyboard_transform: KeyboardTransform::default(),
+            canvas_tabs: Vec::new(),
+            settings,
+            colors,
+            panel_layout,
+            preferences_dialog: PreferencesDialog::default(),
+            new_document_dialog: NewDocumentDialog::default(),
+            document_properties_dialog: DocumentPropertiesDialog::default(),
+            transform_dialog: TransformDialog::default(),
+            array_dialog: ArrayDialog::default(),
+            replace_colors_dialog: ReplaceColorsDialog::default(),
+            repeat: None,
+            relink_dialog: RelinkDialog::default(),
+            slice_export: Default::default(),
+            replay_controls: ReplayControls::new(),
+            document_path: default_document_path(),
+            saved_version: 0,
+            pending_action: None,
+            document_error: None,
+            toasts: Toasts::default(),
+            announcer,
+            #[cfg(target_arch = "wasm32")]
+            autosaves: crate::autosave::WebAutosaves::open(&cc.egui_ctx),
+            #[cfg(target_arch = "wasm32")]
+            autosaved_version: 0,
+            document_passphrase: None,
+            passphrase_prompt: None,
+            allow_close: false,
+            window_title: String::new(),
+            quick_sketch: QuickSketch::default(),
+            bug_report_dialog: BugReportDialog::default(),
+            show_diagnostics: false,
+            log_console: LogConsole::default(),
+            tutorial: None,
+            #[cfg(all(feature = "tray", not(target_arch = "wasm32")))]
+            tray: None,
+            #[cfg(all(feature = "tray", not(target_arch = "wasm32")))]
+            tray_started: false,
+            pending_screenshot: None,
+            screenshot_status: None,
+            #[cfg(not(target_arch = "wasm32"))]
+            screen_capture: None,
+            #[cfg(all(feature = "global-hotkey", not(target_arch = "wasm32")))]
+            screenshot_hotkey: None,
+            #[cfg(all(feature = "global-hotkey", not(target_arch = "wasm32")))]
+            screenshot_hotkey_started: false,
+            #[cfg(not(target_arch = "wasm32"))]
+            clipboard: None,
+            #[cfg(not(target_arch = "wasm32"))]
+            print_dialog: crate::panels::PrintDialog::default(),
+        };
+        app.apply_settings(&cc.egui_ctx, None);
+        #[cfg(target_arch = "wasm32")]
+        app.open_share_link();
+        app
+    }
+
+    pub fn settings(&self) -> &Settings {
+        &self.settings
+    }
+
+    pub fn panel_layout(&self) -> &PanelLayout {
+        &self.panel_layout
+    }
+
+    pub fn panel_layout_mut(&mut self) -> &mut PanelLayout {
+        &mut self.panel_layout
+    }
+
+    /// The canvas color: the document's own, or the one in the preferences
+    fn canvas_background(&self) -> egui::Color32 {
+        self.editor_model
+            .background
+            .unwrap_or(self.settings.background_color)
+    }
+
+    /// Push the current settings into the theme, renderer, history and tools
+    ///
+    /// The default stroke settings reach the tools only if they changed from
+    /// `previous`, so a brush size picked since isn't lost to an edit of some
+    /// other preference.
+    fn apply_settings(&mut self, ctx: &egui::Context, previous: Option<&Settings>) {
+        self.settings.apply_theme(ctx);
+        self.renderer.apply_settings(&self.settings);
+        self.view.set_animated(self.settings.animate_view);
+        self.export_dialog
+            .set_background_color(self.canvas_background());
+        self.export_dialog
+            .set_post_export_hook(&self.settings.post_export_command);
+        self.command_history.set_undo_limit(self.settings.undo_limit);
+        self.command_history
+            .set_keep_branches(self.settings.keep_undo_branches);
+        self.command_history
+            .set_undo_granularity(self.settings.undo_granularity, self.settings.undo_window());
+        self.file_handler
+            .set_link_dropped_images(self.settings.link_dropped_images);
+        self.keyboard_transform
+            .set_steps(self.settings.snap.nudge, self.settings.snap.large_nudge);
+
+        let stroke_defaults_changed = previous.is_none_or(|previous| {
+            previous.stroke_thickness != self.settings.stroke_thickness
+                || previous.auto_shape != self.settings.auto_shape
+                || previous.curve_fitting != self.settings.curve_fitting
+        });
+        if stroke_defaults_changed {
+            let stroke_config = DrawStrokeConfig {
+                color: self.colors.foreground,
+                thickness: self.settings.stroke_thickness,
+                auto_shape: self.settings.auto_shape,
+                curve_fitting: self.settings.curve_fitting,
+            };
+            for tool in &mut self.available_tools {
+                tool.apply_config(&stroke_config);
+            }
+            self.editor_model
+                .active_tool_mut()
+                .apply_config(&stroke_config);
+        }
+        self.apply_colors();
+
+        let active_tool = self.editor_model.active_tool_mut();
+        for tool in self.available_tools.iter_mut().chain([active_tool]) {
+            if let Some(response) = self.settings.pressure.get(tool.name()) {
+                tool.apply_config(response);
+            }
+            tool.apply_config(&self.settings.snap);
+            tool.apply_config(&self.settings.guides);
+        }
+    }
+
+    pub fn colors(&self) -> ColorPair {
+        self.colors
+    }
+
+    pub fn set_colors(&mut self, colors: ColorPair) {
+        if colors != self.colors {
+            self.colors = colors;
+            self.apply_colors();
+        }
+    }
+
+    pub fn swap_colors(&mut self) {
+        let mut colors = self.colors;
+        colors.swap();
+        self.set_colors(colors);
+    }
+
+    /// Push the foreground and background colors into the tools
+    fn apply_colors(&mut self) {
+        for tool in &mut self.available_tools {
+            tool.apply_config(&self.colors);
+        }
+        self.editor_model
+            .active_tool_mut()
+            .apply_config(&self.colors);
+    }
+
+    /// Whether the document changed since it was last saved or loaded
+    pub fn is_dirty(&self) -> bool {
+        self.editor_model.version() != self.saved_version
+    }
+
+    pub fn document_path_mut(&mut self) -> &mut String {
+        &mut self.document_path
+    }
+
+    pub fn slice_export_mut(&mut self) -> &mut crate::export_slice::SliceExportOptions {
+        &mut self.slice_export
+    }
+
+    /// File name of the document, used in the title and prompts
+    fn document_name(&self) -> String {
+        std::path::Path::new(&self.document_path)
+            .file_name()
+            .map(|name| name.to_string_lossy().into_owned())
+            .unwrap_or_else(default_document_path)
+    }
+
+    /// Whether the document is saved encrypted with a passphrase
+    pub fn is_document_protected(&self) -> bool {
+        self.document_passphrase.is_some()
+    }
+
+    /// Ask for a passphrase to encrypt the document with, then save it encrypted
+    pub fn protect_document(&mut self) {
+        self.passphrase_prompt = Some(PassphrasePrompt::new(PassphrasePurpose::Protect));
+    }
+
+    /// Stop encrypting the document and save it in the clear
+    pub fn unprotect_document(&mut self) {
+        self.document_passphrase = None;
+        self.save_document();
+    }
+
+    /// Save the document to its path, returning whether it worked
+    pub fn save_document(&mut self) -> bool {
+        let path = std::path::Path::new(&self.document_path);
+        let passphrase = self.document_passphrase.as_deref();
+        match self
+            .file_handler
+            .save_document(path, &self.editor_model, passphrase)
+        {
+            Ok(()) => {
+                self.saved_version = self.editor_model.version();
+                self.document_error = None;
+                #[cfg(not(target_arch = "wasm32"))]
+                self.save_session(path);
+                self.toasts
+                    .success(format!("Saved {}", self.document_name()));
+                true
+            }
+            Err(err) => {
+                log::warn!(error:% = err; "Failed to save document");
+                self.toasts.error(format!("Couldn't save: {}", err));
+                self.document_error = Some(err.to_string());
+                false
+            }
+        }
+    }
+
+    /// Keep the undo history beside the document just saved to `path`, so it
+    /// comes back when the document is reopened
+    ///
+    /// Encrypted documents get none, since the file beside them isn't encrypted.
+    #[cfg(not(target_arch = "wasm32"))]
+    fn save_session(&self, path: &std::path::Path) {
+        if self.document_passphrase.is_some() {
+            // Nor may one saved before the document was protected stay behind
+            std::fs::remove_file(crate::autosave::session_path(path)).ok();
+            return;
+        }
+        let written =
+            crate::autosave::AutosavedSession::capture(&self.editor_model, &self.command_history)
+                .and_then(|session| crate::autosave::write_session(path, &session));
+        if let Err(err) = written {
+            log::warn!(error:% = err; "Failed to save undo history");
+        }
+    }
+
+    /// Start a document action, asking first if unsaved changes would be lost
+    pub fn request_document_action(&mut self, action: DocumentAction, ctx: &egui::Context) {
+        if self.is_dirty() {
+            self.pending_action = Some(action);
+        } else {
+            self.perform_document_action(action, ctx);
+        }
+    }
+
+    fn perform_document_action(&mut self, action: DocumentAction, ctx: &egui::Context) {
+        log::debug!(action:? = action; "Performing document action");
+        match action {
+            DocumentAction::New => {
+                self.replace_document(new_document(
+                    &self.new_document_dialog.settings,
+                    self.visible_canvas_rect().shrink(ARTBOARD_INSET).min,
+                ));
+                self.document_path = default_document_path();
+            }
+            DocumentAction::Open => {
+                #[cfg(not(target_arch = "wasm32"))]
+                self.open_document(None);
+                // Web pages can't read paths; open the file the user picks once it's read
+                #[cfg(target_arch = "wasm32")]
+                self.file_handler.pick_document(ctx);
+            }
+            DocumentAction::AnnotateScreenshot => {
+                let Some(bytes) = self.pending_screenshot.take() else {
+                    return;
+                };
+                match self
+                    .file_handler
+                    .image_document(bytes, self.visible_canvas_rect().shrink(ARTBOARD_INSET))
+                {
+                    Ok(file) => {
+                        self.replace_document(file);
+                        self.document_path = default_document_path();
+                        self.set_active_tool_by_name("Annotate");
+                        self.screenshot_status = None;
+                    }
+                    Err(err) => {
+                        log::warn!(error:% = err; "Failed to open screenshot");
+                        self.screenshot_status = Some(Err(err));
+                    }
+                }
+            }
+            DocumentAction::Tutorial => {
+                self.replace_document(tutorial_document(
+                    self.visible_canvas_rect().shrink(ARTBOARD_INSET),
+                ));
+                self.document_path = default_document_path();
+                self.set_active_tool_by_name("Draw Stroke");
+                self.tutorial = Some(Tutorial::new(self.command_history.subscribe()));
+            }
+            DocumentAction::Quit => {
+                self.allow_close = true;
+                ctx.send_viewport_cmd(egui::ViewportCommand::Close);
+            }
+        }
+    }
+
+    /// Open the document at the document path, asking for its passphrase if it
+    /// is encrypted and none was given
+    fn open_document(&mut self, passphrase: Option<String>) {
+        let path = std::path::Path::new(&self.document_path);
+        if crate::file_handler::is_image_path(path) {
+            self.open_image_document();
+            return;
+        }
+        match self.file_handler.open_document(path, passphrase.as_deref()) {
+            Ok(file) => {
+                #[cfg(not(target_arch = "wasm32"))]
+                let session = crate::autosave::read_session(path).filter(|_| passphrase.is_none());
+                self.replace_document(file);
+                // Take up the history saved beside the document, if it's of this version
+                #[cfg(not(target_arch = "wasm32"))]
+                if let Some(session) = session {
+                    let restored =
+                        session.restore_history(&self.editor_model, &mut self.command_history);
+                    if let Err(err) = restored {
+                        log::warn!(error:% = err; "Failed to restore undo history");
+                    }
+                }
+                self.document_passphrase = passphrase;
+                self.passphrase_prompt = None;
+            }
+            Err(DocumentError::PassphraseRequired) => {
+                self.passphrase_prompt = Some(PassphrasePrompt::new(PassphrasePurpose::Open));
+            }
+            Err(DocumentError::WrongPassphrase) if self.passphrase_prompt.is_some() => {
+                log::warn!(path:% = path.display(); "Wrong passphrase");
+                if let Some(prompt) = &mut self.passphrase_prompt {
+                    prompt.set_error(DocumentError::WrongPassphrase.to_string());
+                }
+            }
+            Err(err) => {
+                log::warn!(error:% = err; "Failed to open document");
+                self.toasts.error(format!("Couldn't open: {}", err));
+                self.document_error = Some(err.to_string());
+                self.passphrase_prompt = None;
+            }
+        }
+    }
+
+    /// Start a new document over the image at the document path, sized to the
+    /// image, which becomes its locked background
+    ///
+    /// The document is saved next to the image rather than over it.
+    fn open_image_document(&mut self) {
+        let path = std::path::PathBuf::from(&self.document_path);
+        let origin = self.visible_canvas_rect().shrink(ARTBOARD_INSET).min;
+        match self.file_handler.open_image_document(&path, origin) {
+            Ok(file) => {
+                self.replace_document(file);
+                self.document_path = path
+                    .with_extension(DOCUMENT_EXTENSION)
+                    .to_string_lossy()
+                    .into_owned();
+                self.passphrase_prompt = None;
+            }
+            Err(err) => {
+                log::warn!(error:% = err; "Failed to open image");
+                self.toasts.error(format!("Couldn't open: {}", err));
+                self.document_error = Some(err.to_string());
+            }
+        }
+    }
+
+    /// Open the document picked in the browser once it has been read
+    #[cfg(target_arch = "wasm32")]
+    fn handle_picked_document(&mut self) {
+        match self.file_handler.poll_picked_document() {
+            Some(Ok(name)) => {
+                self.document_path = name;
+                self.open_document(None);
+            }
+            Some(Err(err)) => {
+                log::warn!(error:% = err; "Failed to read the picked document");
+                self.toasts.error(format!("Couldn't open: {}", err));
+            }
+            None => {}
+        }
+    }
+
+    /// Copy the document into the browser's storage if it changed since last time
+    #[cfg(target_arch = "wasm32")]
+    fn autosave_document(&mut self) {
+        if self.editor_model.version() == self.autosaved_version {
+            return;
+        }
+        let document = match DocumentFile::from_model(&self.editor_model).to_ron() {
+            Ok(document) => document,
+            Err(err) => {
+                log::warn!(error:% = err; "Failed to autosave document");
+                return;
+            }
+        };
+        // Without its history the document is still worth keeping
+        let history = self
+            .command_history
+            .to_session(&self.editor_model)
+            .map_err(|err| log::warn!(error:% = err; "Failed to autosave undo history"))
+            .ok();
+        match (crate::autosave::AutosavedSession { document, history }).to_ron() {
+            Ok(text) => {
+                self.autosaves.store(&self.document_name(), text);
+                self.autosaved_version = self.editor_model.version();
+            }
+            Err(err) => log::warn!(error:% = err; "Failed to autosave document"),
+        }
+    }
+
+    /// Reopen the document autosaved last session once it has been read, unless
+    /// work on another one has already started
+    #[cfg(target_arch = "wasm32")]
+    fn restore_autosave(&mut self) {
+        let Some((name, text)) = self.autosaves.take_restored() else {
+            return;
+        };
+        if self.is_dirty() || !self.editor_model.elements.is_empty() {
+            return;
+        }
+        let session = crate::autosave::AutosavedSession::from_ron(&text);
+        match DocumentFile::from_ron(&session.document) {
+            Ok(file) => {
+                self.replace_document(file);
+                if let Some(history) = &session.history {
+                    let restored = self
+                        .command_history
+                        .restore_session(history, &self.editor_model);
+                    if let Err(err) = restored {
+                        log::warn!(error:% = err; "Failed to restore undo history");
+                    }
+                }
+                self.autosaved_version = self.editor_model.version();
+                self.toasts
+                    .info(format!("Restored {} from the last session", name));
+                self.document_path = name;
+            }
+            Err(err) => log::warn!(error:% = err; "Failed to restore autosaved document"),
+        }
+    }
+
+    /// Show the passphrase prompt, if open, and act on the answer
+    fn show_passphrase_prompt(&mut self, ctx: &egui::Context) {
+        let name = self.document_name();
+        let Some(prompt) = &mut self.passphrase_prompt else {
+            return;
+        };
+        let purpose = prompt.purpose;
+        match prompt.show(ctx, &name) {
+            Some(PassphraseChoice::Submit(passphrase)) => match purpose {
+                PassphrasePurpose::Open => self.open_document(Some(passphrase)),
+                PassphrasePurpose::Protect => {
+                    self.passphrase_prompt = None;
+                    self.document_passphrase = Some(passphrase);
+                    self.save_document();
+                }
+            },
+            Some(PassphraseChoice::Cancel) => self.passphrase_prompt = None,
+            None => {}
+        }
+    }
+
+    /// Swap in another document, starting with a fresh history
+    fn replace_document(&mut self, file: DocumentFile) {
+        self.editor_model.load_document(file);
+        self.command_history.clear();
+        self.renderer.reset_state();
+        self.last_rendered_version = 0;
+        self.saved_version = self.editor_model.version();
+        self.document_error = None;
+        self.document_passphrase = None;
+        self.tutorial = None;
+
+        // Offer to relink images whose files couldn't be read
+        let missing = crate::assets::linked_images(&self.editor_model.elements)
+            .iter()
+            .filter(|image| image.missing)
+            .count();
+        if missing > 0 {
+            self.toasts
+                .error(format!("{} linked image(s) couldn't be found", missing));
+            self.relink_dialog.open = true;
+        }
+    }
+
+    /// Hold a window close back while there are unsaved changes
+    fn handle_close_request(&mut self, ctx: &egui::Context) {
+        if ctx.input(|i| i.viewport().close_requested()) && self.is_dirty() && !self.allow_close {
+            ctx.send_viewport_cmd(egui::ViewportCommand::CancelClose);
+            self.pending_action = Some(DocumentAction::Quit);
+        }
+    }
+
+    /// Show the unsaved-changes prompt for a pending action and act on the answer
+    fn show_unsaved_changes_prompt(&mut self, ctx: &egui::Context) {
+        let Some(action) = self.pending_action else {
+            return;
+        };
+
+        let name = self.document_name();
+        let proceed = match unsaved_changes_prompt(ctx, &name, self.document_error.as_deref()) {
+            Some(UnsavedChoice::Save) => self.save_document(),
+            Some(UnsavedChoice::Discard) => true,
+            Some(UnsavedChoice::Cancel) => {
+                self.pending_action = None;
+                self.document_error = None;
+                false
+            }
+            None => false,
+        };
+        if proceed {
+            self.pending_action = None;
+            self.perform_document_action(action, ctx);
+        }
+    }
+
+    /// Show the document name in the window title, marked while there are unsaved changes
+    fn update_window_title(&mut self, ctx: &egui::Context) {
+        let marker = if self.is_dirty() { "• " } else { "" };
+        let title = format!("{}{} – {}", marker, self.document_name(), APP_NAME);
+        if title != self.window_title {
+            ctx.send_viewport_cmd(egui::ViewportCommand::Title(title.clone()));
+            self.window_title = title;
+        }
+    }
+
+    /// Where a new artboard of `size` goes: right of the existing artboards if
+    /// there are any, else centered on the content, else at the top left of the canvas panel
+    pub fn place_artboard(&self, size: egui::Vec2) -> egui::Rect {
+        if let Some(right) = self
+            .editor_model
+            .artboards
+            .iter()
+            .map(|artboard| artboard.rect)
+            .reduce(|acc, rect| acc.union(rect))
+        {
+            return egui::Rect::from_min_size(
+                egui::pos2(right.max.x + ARTBOARD_INSET, right.min.y),
+                size,
+            );
+        }
+        match crate::export::document_bounds(&self.editor_model) {
+            Some(bounds) => egui::Rect::from_center_size(bounds.center(), size),
+            None => egui::Rect::from_min_size(
+                self.visible_canvas_rect().min + egui::Vec2::splat(ARTBOARD_INSET),
+                size,
+            ),
+        }
+    }
+
+    /// Open the quick sketch window
+    pub fn open_quick_sketch(&mut self) {
+        self.quick_sketch.open = true;
+    }
+
+    /// Folder quick sketches are saved into
+    fn scratch_dir(&self) -> Option<std::path::PathBuf> {
+        if !self.settings.scratch_dir.trim().is_empty() {
+            return Some(self.settings.scratch_dir.trim().into());
+        }
+        #[cfg(not(target_arch = "wasm32"))]
+        return eframe::storage_dir(APP_NAME).map(|dir| dir.join(SCRATCH_DIR_NAME));
+        #[cfg(target_arch = "wasm32")]
+        None
+    }
+
+    /// Save a quick sketch as a new document in the scratch folder
+    fn save_quick_sketch(&mut self, sketch: &EditorModel) -> Result<String, String> {
+        let dir = self
+            .scratch_dir()
+            .ok_or_else(|| "No scratch folder set".to_string())?;
+        #[cfg(not(target_arch = "wasm32"))]
+        std::fs::create_dir_all(&dir).map_err(|err| err.to_string())?;
+
+        let timestamp = crate::state::unix_time_now() as u64;
+        let path = dir.join(format!("sketch-{}.{}", timestamp, DOCUMENT_EXTENSION));
+        self.file_handler
+            .save_document(&path, sketch, None)
+            .map(|()| format!("Saved to {}", path.display()))
+            .map_err(|err| err.to_string())
+    }
+
+    /// Add the tray icon once, if enabled, and act on its menu
+    #[cfg(all(feature = "tray", not(target_arch = "wasm32")))]
+    fn handle_tray(&mut self, ctx: &egui::Context) {
+        if self.settings.tray_icon && !self.tray_started {
+            self.tray_started = true;
+            self.tray = crate::tray::Tray::new(ctx, APP_NAME)
+                .map_err(|err| log::warn!(error:% = err; "Failed to add tray icon"))
+                .ok();
+        }
+
+        while let Some(action) = self.tray.as_ref().and_then(|tray| tray.poll()) {
+            match action {
+                crate::tray::TrayAction::QuickSketch => self.open_quick_sketch(),
+                crate::tray::TrayAction::ShowWindow => {
+                    ctx.send_viewport_cmd(egui::ViewportCommand::Visible(true));
+                    ctx.send_viewport_cmd(egui::ViewportCommand::Minimized(false));
+                    ctx.send_viewport_cmd(egui::ViewportCommand::Focus);
+                }
+                crate::tray::TrayAction::Quit => {
+                    self.request_document_action(DocumentAction::Quit, ctx)
+                }
+            }
+        }
+    }
+
+    /// Capture the screen with the app window out of the way
+    ///
+    /// The capture opens as a new document once it is done.
+    #[cfg(not(target_arch = "wasm32"))]
+    pub fn start_screenshot(&mut self, ctx: &egui::Context) {
+        if self.screen_capture.is_some() {
+            return;
+        }
+        ctx.send_viewport_cmd(egui::ViewportCommand::Minimized(true));
+        self.screen_capture = Some(crate::screenshot::ScreenCapture::start(
+            &self.settings.screenshot_command,
+            ctx,
+        ));
+        self.screenshot_status = None;
+    }
+
+    /// Whether a screen capture is running
+    #[cfg(not(target_arch = "wasm32"))]
+    pub fn is_capturing_screenshot(&self) -> bool {
+        self.screen_capture.is_some()
+    }
+
+    /// React to the screenshot shortcut and open finished captures
+    #[cfg(not(target_arch = "wasm32"))]
+    fn handle_screenshot(&mut self, ctx: &egui::Context) {
+        #[cfg(feature = "global-hotkey")]
+        {
+            if self.settings.screenshot_hotkey && !self.screenshot_hotkey_started {
+                self.screenshot_hotkey_started = true;
+                self.screenshot_hotkey = crate::screenshot::ScreenshotHotkey::register(ctx)
+                    .map_err(
+                        |err| log::warn!(error:% = err; "Failed to register screenshot shortcut"),
+                    )
+                    .ok();
+            }
+            if self
+                .screenshot_hotkey
+                .as_ref()
+                .is_some_and(|hotkey| hotkey.pressed())
+            {
+                self.start_screenshot(ctx);
+            }
+        }
+
+        let Some(result) = self
+            .screen_capture
+            .as_ref()
+            .and_then(|capture| capture.poll())
+        else {
+            return;
+        };
+        self.screen_capture = None;
+        ctx.send_viewport_cmd(egui::ViewportCommand::Minimized(false));
+        ctx.send_viewport_cmd(egui::ViewportCommand::Focus);
+        match result {
+            Ok(bytes) => {
+                self.pending_screenshot = Some(bytes);
+                self.request_document_action(DocumentAction::AnnotateScreenshot, ctx);
+            }
+            Err(err) => {
+                log::warn!(error:% = err; "Screenshot failed");
+                self.screenshot_status = Some(Err(err));
+            }
+        }
+    }
+
+    /// Put the document, rendered with the current export settings, on the clipboard
+    #[cfg(not(target_arch = "wasm32"))]
+    pub fn copy_image_to_clipboard(&mut self, ctx: &egui::Context) {
+        self.copy_to_clipboard(ctx, crate::export::copy_image_to_clipboard);
+    }
+
+    /// Put the selected elements, rendered with the current export settings and
+    /// cropped to them, on the clipboard
+    #[cfg(not(target_arch = "wasm32"))]
+    pub fn copy_selection_to_clipboard(&mut self, ctx: &egui::Context) {
+        self.copy_to_clipboard(ctx, crate::export::copy_selection_to_clipboard);
+    }
+
+    #[cfg(not(target_arch = "wasm32"))]
+    fn copy_to_clipboard(
+        &mut self,
+        ctx: &egui::Context,
+        copy: fn(
+            &mut crate::export::Clipboard,
+            &EditorModel,
+            &crate::export::ExportSettings,
+            &egui::Context,
+        ) -> Result<(), crate::export::ExportError>,
+    ) {
+        let settings = self.export_dialog.export_settings();
+        let clipboard = match self.clipboard.take() {
+            Some(clipboard) => Ok(clipboard),
+            None => crate::export::Clipboard::new().map_err(|err| err.to_string()),
+        };
+        let result = clipboard.and_then(|mut clipboard| {
+            let result = copy(&mut clipboard, &self.editor_model, &settings, ctx);
+            self.clipboard = Some(clipboard);
+            result.map_err(|err| err.to_string())
+        });
+        if let Err(err) = &result {
+            log::warn!(error:% = err; "Failed to copy image");
+        }
+        self.screenshot_status = Some(result.map(|()| "Copied to the clipboard".to_string()));
+    }
+
+    /// Copy a link to the page with the document encoded in it
+    #[cfg(target_arch = "wasm32")]
+    pub fn copy_share_link(&mut self, ctx: &egui::Context) {
+        let page_url = web_sys::window()
+            .and_then(|window| window.location().href().ok())
+            .unwrap_or_default();
+        let file = DocumentFile::from_model(&self.editor_model);
+        match crate::share_link::share_link(&page_url, &file) {
+            Ok(link) => {
+                ctx.copy_text(link);
+                self.toasts.success("Copied a share link");
+            }
+            Err(err) => {
+                log::warn!(error:% = err; "Failed to make a share link");
+                self.toasts.error(format!("Couldn't share: {}", err));
+            }
+        }
+    }
+
+    /// Open the document in the page's share link, if it was opened from one
+    #[cfg(target_arch = "wasm32")]
+    fn open_share_link(&mut self) {
+        let Some(fragment) = web_sys::window()
+            .and_then(|window| window.location().hash().ok())
+            .filter(|fragment| !fragment.is_empty())
+        else {
+            return;
+        };
+        match crate::share_link::decode_fragment(&fragment) {
+            Ok(file) => self.replace_document(file),
+            Err(crate::share_link::ShareLinkError::NotALink) => {}
+            Err(err) => {
+                log::warn!(error:% = err; "Failed to open share link");
+                self.toasts
+                    .error(format!("Couldn't open the link: {}", err));
+            }
+        }
+    }
+
+    /// Outcome of the last screenshot or clipboard copy
+    pub fn screenshot_status(&self) -> Option<&Result<String, String>> {
+        self.screenshot_status.as_ref()
+    }
+
+    /// Replay the document in drawing order
+    pub fn start_replay(&mut self) {
+        self.replay_controls.start(&self.editor_model);
+    }
+
+    /// Open the New Document window, which starts the document once confirmed
+    pub fn open_new_document_dialog(&mut self) {
+        self.new_document_dialog.open = true;
+    }
+
+    /// Whether any image in the document is linked to a file rather than embedded
+    pub fn has_linked_images(&self) -> bool {
+        !crate::assets::linked_images(&self.editor_model.elements).is_empty()
+    }
+
+    /// Open the window listing linked images, to relink or embed them
+    pub fn open_relink_dialog(&mut self) {
+        self.relink_dialog.open = true;
+    }
+
+    /// Folder the document is saved in, which relative image links start from
+    fn document_dir(&self) -> std::path::PathBuf {
+        std::path::Path::new(&self.document_path)
+            .parent()
+            .map(std::path::Path::to_path_buf)
+            .unwrap_or_default()
+    }
+
+    /// Relink or embed an image as asked in the Linked Images window
+    fn apply_relink_choice(&mut self, choice: RelinkChoice) {
+        let (id, description) = match &choice {
+            RelinkChoice::Relink(id, _) => (*id, "Relink Image"),
+            RelinkChoice::Embed(id) => (*id, "Embed Image"),
+        };
+        let Some(ElementType::Image(image)) = self.editor_model.find_element_by_id(id).cloned()
+        else {
+            return;
+        };
+        let mut changed = image.clone();
+        match choice {
+            RelinkChoice::Relink(_, link) => {
+                match crate::assets::read_link(&link, &self.document_dir()) {
+                    Ok(data) => changed.set_source(Some(link), data),
+                    Err(err) => {
+                        self.toasts
+                            .error(format!("Couldn't read {}: {}", link, err));
+                        return;
+                    }
+                }
+            }
+            RelinkChoice::Embed(_) => {
+                let data = changed.original_data().to_vec();
+                changed.set_source(None, data);
+            }
+        }
+        self.execute_command(Command::new_replace_elements(
+            &self.editor_model,
+            description,
+            vec![ElementType::Image(image)],
+            vec![ElementType::Image(changed)],
+        ));
+    }
+
+    /// Copy linked images into an assets folder next to the document, link
+    /// them there, and save, so the document and folder travel together
+    #[cfg(not(target_arch = "wasm32"))]
+    pub fn collect_assets(&mut self) {
+        let path = std::path::PathBuf::from(&self.document_path);
+        let collected = match crate::assets::collect_assets(&self.editor_model.elements, &path) {
+            Ok(collected) => collected,
+            Err(err) => {
+                log::warn!(error:% = err; "Failed to collect assets");
+                self.toasts
+                    .error(format!("Couldn't collect assets: {}", err));
+                return;
+            }
+        };
+        let removed: Vec<ElementType> = collected
+            .images
+            .iter()
+            .filter_map(|image| self.editor_model.find_element_by_id(image.id()).cloned())
+            .collect();
+        if !removed.is_empty() {
+            self.execute_command(Command::new_replace_elements(
+                &self.editor_model,
+                "Collect Assets",
+                removed,
+                collected.images,
+            ));
+        }
+        if self.save_document() && collected.missing > 0 {
+            self.toasts.error(format!(
+                "{} missing linked image(s) weren't collected",
+                collected.missing
+            ));
+        }
+    }
+
+    /// Write every slice at the chosen scales into the slice folder, taken
+    /// from the document's folder when relative
+    #[cfg(not(target_arch = "wasm32"))]
+    pub fn export_slices(&mut self, ctx: &egui::Context) {
+        let folder = crate::assets::link_path(&self.slice_export.folder, &self.document_dir());
+        let settings = self.export_dialog.export_settings();
+        match crate::export::export_slices_to_folder(
+            &folder,
+            &self.editor_model,
+            &self.slice_export.chosen_scales(),
+            &settings,
+            ctx,
+        ) {
+            Ok(written) => self.toasts.success(format!(
+                "Exported {} file(s) to {}",
+                written.len(),
+                folder.display()
+            )),
+            Err(err) => {
+                log::warn!(error:% = err; "Failed to export slices");
+                self.toasts
+                    .error(format!("Couldn't export slices: {}", err));
+            }
+        }
+    }
+
+    /// Open the Document Properties window on the current document
+    pub fn open_document_properties(&mut self) {
+        self.document_properties_dialog.open_for(
+            &self.editor_model,
+            self.visible_canvas_rect().shrink(ARTBOARD_INSET).min,
+        );
+    }
+
+    /// Open the Transform window on the selection, if there is one
+    pub fn open_transform_dialog(&mut self) {
+        self.transform_dialog.open_for(&self.editor_model);
+    }
+
+    /// Open the Replace Colors window, replacing with the foreground color
+    pub fn open_replace_colors_dialog(&mut self) {
+        self.replace_colors_dialog
+            .open_for(&self.editor_model.elements, self.colors.foreground);
+    }
+
+    /// Open the Array window on the selection, if there is one
+    pub fn open_array_dialog(&mut self) {
+        self.array_dialog.open_for(&self.editor_model);
+    }
+
+    /// The copies the open Array window would add, with the ids they'd be given
+    fn array_preview(&self) -> Option<Vec<ElementType>> {
+        let params = self.array_dialog.preview()?;
+        let selected: Vec<ElementId> = self.editor_model.selected_ids().iter().copied().collect();
+        let mut next_id = self.editor_model.next_id();
+        let copies = crate::array::array_copies(&self.editor_model, &selected, params, || {
+            let id = next_id;
+            next_id = id.next();
+            id
+        });
+        Some(copies)
+    }
+
+    /// Copy the selection and select the copies
+    pub fn duplicate_selection(&mut self) {
+        self.apply_repeat_action(RepeatAction::duplicate(DUPLICATE_OFFSET));
+    }
+
+    /// Whether there's a last action for [`Self::repeat_last_action`] to do again
+    pub fn can_repeat(&self) -> bool {
+        self.repeat.is_some() && !self.editor_model.selected_ids().is_empty()
+    }
+
+    /// Do the last move, transform or duplicate again, to the selection
+    pub fn repeat_last_action(&mut self) {
+        if let Some((action, _)) = self.repeat {
+            self.apply_repeat_action(action);
+        }
+    }
+
+    /// Do `action` to the selection and remember it for Repeat
+    fn apply_repeat_action(&mut self, action: RepeatAction) {
+        let mut selected: Vec<ElementId> =
+            self.editor_model.selected_ids().iter().copied().collect();
+        selected.sort();
+        let Some((command, result)) = action.command(&self.editor_model, &selected) else {
+            return;
+        };
+        // Moves it makes are remembered below, as part of the action
+        let last = self.repeat.take();
+        self.execute_command(command);
+        self.repeat = last;
+        self.editor_model.clear_selection();
+        for &id in &result {
+            self.editor_model.select_element(id);
         }
+        self.remember_repeat(action, &selected, result);
+    }
+
+    /// Remember `action`, done to `acted_on` and leaving `result` selected, for Repeat
+    ///
+    /// Following a duplicate on its copies, it's folded into the duplicate.
+    fn remember_repeat(
+        &mut self,
+        action: RepeatAction,
+        acted_on: &[ElementId],
+        mut result: Vec<ElementId>,
+    ) {
+        result.sort();
+        let action = match &self.repeat {
+            Some((last, last_result)) if last.duplicate && last_result == acted_on => {
+                last.then(action).unwrap_or(action)
+            }
+            _ => action,
+        };
+        self.repeat = Some((action, result));
+    }
+
+    /// Open the preferences window
+    pub fn open_preferences(&mut self) {
+        self.preferences_dialog.open = true;
+    }
+
+    pub fn open_bug_report(&mut self) {
+        self.bug_report_dialog.open = true;
+    }
+
+    /// Show or hide the diagnostics window
+    pub fn toggle_diagnostics(&mut self) {
+        self.show_diagnostics = !self.show_diagnostics;
+    }
+
+    /// Show or hide the Log console
+    pub fn toggle_log_console(&mut self) {
+        self.log_console.open = !self.log_console.open;
+    }
+
+    /// Frame timings, element counts, texture cache stats and memory use
+    fn show_diagnostics(&mut self, ctx: &egui::Context) {
+        if ctx.input_mut(|i| i.consume_key(egui::Modifiers::NONE, egui::Key::F12)) {
+            self.toggle_diagnostics();
+        }
+        if ctx.input_mut(|i| i.consume_key(egui::Modifiers::SHIFT, egui::Key::F12)) {
+            self.toggle_log_console();
+        }
+        self.log_console.show(ctx);
+        if !self.show_diagnostics {
+            return;
+        }
+        let mut open = true;
+        egui::Window::new("Diagnostics")
+            .open(&mut open)
+            .resizable(false)
+            .show(ctx, |ui| {
+                self.renderer.draw_debug_overlay(ui, &self.editor_model);
+                ui.separator();
+                crate::panels::memory_panel(self, ui);
+            });
+        self.show_diagnostics = open;
+        // Keep the graph moving while it's on screen
+        ctx.request_repaint();
+    }
+
+    /// Estimated memory of the document, texture cache and undo history
+    pub fn memory_usage(&self) -> MemoryUsage {
+        MemoryUsage::measure(
+            &self.editor_model,
+            &self.command_history,
+            self.renderer.texture_cache_bytes(),
+        )
+    }
+
+    /// Free what's rebuilt on demand: textures of elements off screen and
+    /// the decoded pixels images keep
+    pub fn trim_caches(&mut self) {
+        let textures = self.renderer.trim_texture_cache();
+        for element in &mut self.editor_model.elements {
+            if let ElementType::Image(image) = element {
+                image.drop_decoded();
+            }
+        }
+        log::info!(textures = textures; "Trimmed cached textures");
+    }
+
+    /// Keep only the most recent undo steps, freeing what older ones hold
+    pub fn compact_history(&mut self) {
+        self.command_history.compact(COMPACT_HISTORY_STEPS);
+    }
+
+    /// Make an artboard the one edited and exported by default
+    pub fn set_active_artboard(&mut self, artboard_id: Option<usize>) {
+        self.editor_model.set_active_artboard(artboard_id);
     }
 
     pub fn command_history(&self) -> &CommandHistory {
@@ -67,7 +1328,11 @@ impl PaintApp {
         new_tool_clone.activate(&self.editor_model);
 
         // Update the editor_model with the new tool
+        let changed = current_tool.name() != tool.name();
         self.editor_model.update_tool(|_| new_tool_clone.clone());
+        if changed {
+            self.announcer.announce(format!("{} tool", tool.name()));
+        }
 
         Ok(())
     }
@@ -75,7 +1340,7 @@ impl PaintApp {
     pub fn set_active_tool_by_name(&mut self, tool_name: &str) {
         // This is a wrapper around set_active_tool that ignores errors
         if let Err(err) = self.set_active_tool(tool_name) {
-            log::warn!("Failed to set active tool: {}", err);
+            log::warn!(error:% = err; "Failed to set active tool");
         }
     }
 
@@ -93,8 +1358,6 @@ impl PaintApp {
 
     /// Execute a command and update tool state
     pub fn execute_command(&mut self, command: Command) {
-        log::info!("Executing command: {:?}", command);
-
         // Remember the element ID for selection update
         let element_id = match &command {
             Command::ResizeElement { element_id, .. } => Some(*element_id),
@@ -103,16 +1366,21 @@ impl PaintApp {
         };
 
         // Step 1: Reset the active tool's interaction state
-        let mut tool = self.editor_model.active_tool().clone();
-        tool.reset_interaction_state();
-        tool.clear_preview(&mut self.renderer);
-        self.editor_model.update_tool(|_| tool);
+        self.reset_tool_interaction();
 
         // Step 2: Execute the command on editor_model and handle any errors
-        let _ = self
+        if let Err(err) = self
             .command_history
             .execute(command.clone(), &mut self.editor_model)
-            .map_err(|err| log::warn!("Command execution failed: {}", err));
+        {
+            log::warn!(error:% = err; "Command execution failed");
+            self.announcer
+                .announce(format!("{} failed: {}", command.label(), err));
+            self.toasts.error(err);
+        } else if let Some((action, mut moved)) = RepeatAction::from_moves(&command) {
+            moved.sort();
+            self.remember_repeat(action, &moved, moved.clone());
+        }
 
         // Step 3: Update selection state to track the transformed element
         if let Some(id) = element_id {
@@ -120,44 +1388,321 @@ impl PaintApp {
             if self.editor_model.contains_element(id) {
                 self.editor_model.with_selected_element_id(Some(id));
             }
+
+            // An element dragged onto another artboard makes that artboard active
+            if let Some(artboard_id) = self.editor_model.element_artboard(id).map(|a| a.id) {
+                self.editor_model.set_active_artboard(Some(artboard_id));
+            }
         }
 
         // Step 4: Invalidate textures in the renderer
         command.invalidate_textures(&mut self.renderer);
     }
 
+    /// Open the export window
+    pub fn open_export_dialog(&mut self) {
+        self.export_dialog.open_for(&self.editor_model.metadata);
+    }
+
+    /// Open the print window, which prints what an export would cover
+    #[cfg(not(target_arch = "wasm32"))]
+    pub fn open_print_dialog(&mut self) {
+        self.print_dialog.open();
+    }
+
     pub fn set_central_panel_rect(&mut self, rect: egui::Rect) {
         self.central_panel_rect = rect;
     }
 
+    /// The part of the document showing in the central panel, in document coordinates
+    pub fn visible_canvas_rect(&self) -> egui::Rect {
+        self.view.screen_rect_to_document(self.central_panel_rect)
+    }
+
+    /// The canvas zoom the view is at or headed for, 1.0 being actual size
+    pub fn zoom(&self) -> f32 {
+        self.view.zoom()
+    }
+
+    /// Zoom smoothly to `zoom` around the middle of the canvas
+    pub fn set_zoom(&mut self, zoom: f32) {
+        self.view.zoom_to(zoom, self.central_panel_rect.center());
+    }
+
+    /// Zoom in (`steps` > 0) or out through the zoom presets around the middle of the canvas
+    pub fn zoom_step(&mut self, steps: i32) {
+        self.view.zoom_step(steps, self.central_panel_rect.center());
+    }
+
+    /// Zoom and scroll to show every element, or the artboards of an empty document
+    pub fn zoom_to_fit(&mut self) {
+        let artboards = self
+            .editor_model
+            .artboards
+            .iter()
+            .map(|artboard| artboard.rect)
+            .reduce(|acc, rect| acc.union(rect));
+        match crate::export::document_bounds(&self.editor_model).or(artboards) {
+            Some(bounds) => self.view.zoom_to_rect(bounds, self.central_panel_rect),
+            None => self.zoom_to_actual_size(),
+        }
+    }
+
+    /// Zoom and scroll to show the selected elements
+    pub fn zoom_to_selection(&mut self) {
+        if let Some(bounds) = self.editor_model.selection_rect() {
+            self.view.zoom_to_rect(bounds, self.central_panel_rect);
+        }
+    }
+
+    /// Show the document at actual size, without scrolling
+    pub fn zoom_to_actual_size(&mut self) {
+        self.view.animate_to(egui::emath::TSTransform::IDENTITY);
+    }
+
+    /// How far the canvas view is turned, clockwise in radians
+    pub fn view_rotation(&self) -> f32 {
+        self.view.rotation()
+    }
+
+    /// Snap positions on the canvas to pixels when zoomed in on them
+    pub fn set_snap_to_pixels(&mut self, snap: bool) {
+        self.settings.snap_to_pixels = snap;
+        self.renderer.apply_settings(&self.settings);
+    }
+
+    /// Show or hide the rulers along the canvas
+    pub fn set_show_rulers(&mut self, show: bool) {
+        self.settings.rulers = show;
+        self.renderer.apply_settings(&self.settings);
+    }
+
+    /// Show or hide the ruler guides; hidden guides aren't snapped to either
+    pub fn set_show_ruler_guides(&mut self, show: bool) {
+        self.settings.ruler_guides = show;
+        self.renderer.apply_settings(&self.settings);
+    }
+
+    /// Keep the ruler guides from being moved or removed with the pointer
+    pub fn set_lock_ruler_guides(&mut self, lock: bool) {
+        self.settings.lock_ruler_guides = lock;
+        self.renderer.apply_settings(&self.settings);
+    }
+
+    /// Remove every ruler guide from the document, as one undoable step
+    pub fn clear_ruler_guides(&mut self) {
+        if let Some(command) =
+            Command::new_set_ruler_guides(&self.editor_model, "Clear Guides", Vec::new())
+        {
+            self.execute_command(command);
+        }
+    }
+
+    /// Turn the canvas view back upright around the middle of the canvas
+    pub fn reset_rotation(&mut self) {
+        self.view
+            .set_rotation(0.0, self.central_panel_rect.center());
+    }
+
+    /// Scroll to what the last undo or redo changed, if it's out of sight
+    fn reveal_change(&mut self, command: Option<&Command>) {
+        if let Some(rect) = command.and_then(|command| command.changed_rect(&self.editor_model)) {
+            self.view.reveal(rect, self.central_panel_rect);
+        }
+    }
+
+    /// Zoom from the keyboard, and scroll or (with Ctrl or a pinch) zoom with
+    /// the wheel over the canvas, turning it with Alt or two fingers
+    fn handle_view_input(&mut self, ctx: &egui::Context) {
+        if !ctx.wants_keyboard_input() {
+            let shortcut = |shortcut| ctx.input_mut(|i| i.consume_shortcut(&shortcut));
+            if shortcut(ZOOM_IN_SHORTCUT) || shortcut(ZOOM_IN_SHORTCUT_ALT) {
+                self.zoom_step(1);
+            } else if shortcut(ZOOM_OUT_SHORTCUT) {
+                self.zoom_step(-1);
+            } else if shortcut(ZOOM_TO_FIT_SHORTCUT) {
+                self.zoom_to_fit();
+            } else if shortcut(ZOOM_ACTUAL_SIZE_SHORTCUT) {
+                self.zoom_to_actual_size();
+            } else if shortcut(ZOOM_TO_SELECTION_SHORTCUT) {
+                self.zoom_to_selection();
+            }
+        }
+
+        // Only over the canvas itself, not a window or panel on top of it
+        let Some(pos) = ctx.input(|i| i.pointer.hover_pos()) else {
+            return;
+        };
+        let over_canvas = self.central_panel_rect.contains(pos)
+            && ctx.layer_id_at(pos).is_none_or(|layer| {
+                layer == egui::LayerId::background() || layer == crate::view::canvas_layer()
+            });
+        if !over_canvas {
+            return;
+        }
+        let (zoom, scroll, alt, touch) = ctx.input(|i| {
+            (
+                i.zoom_delta(),
+                i.smooth_scroll_delta,
+                i.modifiers.alt,
+                i.multi_touch(),
+            )
+        });
+        if let Some(touch) = touch {
+            self.view.rotate_by(touch.rotation_delta, touch.center_pos);
+        }
+        if zoom != 1.0 {
+            self.view.zoom_by(zoom, pos);
+        } else if alt && scroll != egui::Vec2::ZERO {
+            let degrees = (scroll.x + scroll.y) * WHEEL_ROTATION_DEGREES;
+            self.view.rotate_by(degrees.to_radians(), pos);
+        } else if scroll != egui::Vec2::ZERO {
+            self.view.pan_by(scroll);
+        }
+    }
+
     pub fn undo(&mut self) {
+        // Drop whatever the tool was in the middle of; it may refer to undone elements
+        self.reset_tool_interaction();
+
         // Reset the renderer's state completely
         self.renderer.reset_state();
 
         // Undo the command on editor_model and handle any errors
-        let _ = self
-            .command_history
-            .undo(&mut self.editor_model)
-            .map_err(|err| log::info!("Undo operation: {}", err));
+        if let Err(err) = self.command_history.undo(&mut self.editor_model) {
+            log::info!(error:% = err; "Undo operation");
+            self.announcer.announce(format!("Couldn't undo: {}", err));
+            self.toasts.error(format!("Couldn't undo: {}", err));
+        }
+        let undone = self.command_history.redo_stack().last().cloned();
+        self.reveal_change(undone.as_ref());
 
         // Force a render update
         self.last_rendered_version = 0;
     }
 
     pub fn redo(&mut self) {
+        self.reset_tool_interaction();
+
         // Reset the renderer's state completely
         self.renderer.reset_state();
 
         // Redo the command on editor_model and handle any errors
-        let _ = self
+        if let Err(err) = self.command_history.redo(&mut self.editor_model) {
+            log::info!(error:% = err; "Redo operation");
+            self.announcer.announce(format!("Couldn't redo: {}", err));
+            self.toasts.error(format!("Couldn't redo: {}", err));
+        }
+        let redone = self.command_history.undo_stack().last().cloned();
+        self.reveal_change(redone.as_ref());
+
+        // Force a render update
+        self.last_rendered_version = 0;
+    }
+
+    /// Snapshot the document under `name`, to revert to later in one step
+    pub fn add_checkpoint(&mut self, name: &str) {
+        self.command_history
+            .add_checkpoint(name, &self.editor_model);
+        self.toasts.info(format!("Added checkpoint {}", name));
+    }
+
+    /// Put the document back as it was at checkpoint `index`, as one undo step
+    pub fn revert_to_checkpoint(&mut self, index: usize) {
+        let Some(command) = self
+            .command_history
+            .revert_command(index, &self.editor_model)
+        else {
+            return;
+        };
+        self.renderer.reset_state();
+        self.execute_command(command);
+
+        // Force a render update
+        self.last_rendered_version = 0;
+    }
+
+    pub fn remove_checkpoint(&mut self, index: usize) {
+        self.command_history.remove_checkpoint(index);
+    }
+
+    /// Go to the end of an undo branch, undoing back to where it forks first
+    pub fn switch_undo_branch(&mut self, index: usize) {
+        self.reset_tool_interaction();
+        self.renderer.reset_state();
+
+        let switched = self
             .command_history
-            .redo(&mut self.editor_model)
-            .map_err(|err| log::info!("Redo operation: {}", err));
+            .switch_branch(index, &mut self.editor_model);
+        if let Err(err) = switched {
+            log::info!(error:% = err; "Switch branch operation");
+            self.announcer
+                .announce(format!("Couldn't switch branch: {}", err));
+            self.toasts
+                .error(format!("Couldn't switch branch: {}", err));
+        }
+        let redone = self.command_history.undo_stack().last().cloned();
+        self.reveal_change(redone.as_ref());
 
         // Force a render update
         self.last_rendered_version = 0;
     }
 
+    /// Cancel the active tool's gesture in progress and clear its preview
+    fn reset_tool_interaction(&mut self) {
+        let mut tool = self.editor_model.active_tool().clone();
+        tool.reset_interaction_state();
+        tool.clear_preview(&mut self.renderer);
+        self.editor_model.update_tool(|_| tool);
+    }
+
+    /// Undo and redo from the keyboard anywhere in the app
+    ///
+    /// A focused text field keeps the shortcuts for undoing its own edits.
+    fn handle_undo_shortcuts(&mut self, ctx: &egui::Context) {
+        if ctx.wants_keyboard_input() {
+            return;
+        }
+        let (undo, redo) = ctx.input_mut(|i| {
+            // Redo first: the plain Ctrl+Z shortcut would also match Ctrl+Shift+Z
+            let redo = i.consume_shortcut(&REDO_SHORTCUT) || i.consume_shortcut(&REDO_SHORTCUT_ALT);
+            (i.consume_shortcut(&UNDO_SHORTCUT), redo)
+        });
+        if redo && self.command_history.can_redo() {
+            self.redo();
+        } else if undo && self.command_history.can_undo() {
+            self.undo();
+        }
+    }
+
+    /// Copy the selection as an image from the keyboard
+    #[cfg(not(target_arch = "wasm32"))]
+    fn handle_copy_shortcut(&mut self, ctx: &egui::Context) {
+        if ctx.wants_keyboard_input() || self.editor_model.selected_ids().is_empty() {
+            return;
+        }
+        let copy = ctx.input_mut(|i| {
+            i.consume_shortcut(&COPY_SELECTION_SHORTCUT)
+                || (i.modifiers.shift && i.events.contains(&egui::Event::Copy))
+        });
+        if copy {
+            self.copy_selection_to_clipboard(ctx);
+        }
+    }
+
+    fn handle_selection_shortcuts(&mut self, ctx: &egui::Context) {
+        if ctx.wants_keyboard_input() || self.editor_model.selected_ids().is_empty() {
+            return;
+        }
+        if ctx.input_mut(|i| i.consume_shortcut(&TRANSFORM_SELECTION_SHORTCUT)) {
+            self.open_transform_dialog();
+        } else if ctx.input_mut(|i| i.consume_shortcut(&DUPLICATE_SELECTION_SHORTCUT)) {
+            self.duplicate_selection();
+        } else if ctx.input_mut(|i| i.consume_shortcut(&REPEAT_SHORTCUT)) {
+            self.repeat_last_action();
+        }
+    }
+
     pub fn handle_tool_ui(&mut self, ui: &mut egui::Ui) -> Option<Command> {
         // Clone the editor_model to avoid borrowing issues
         let editor_model_clone = self.editor_model.clone();
@@ -170,13 +1715,189 @@ impl PaintApp {
         cmd
     }
 
+    /// Switch tools from single-key hotkeys and the Ctrl quick toggle, swap
+    /// the colors with X and pan the view while Space is held
+    fn handle_tool_hotkeys(&mut self, ctx: &egui::Context) {
+        // Leave the keyboard alone while a text field has focus
+        let typing = ctx.wants_keyboard_input();
+        let (hotkey_tool, swap_colors, ctrl_held, interrupted, now) = ctx.input(|i| {
+            let hotkey_tool = TOOL_HOTKEYS
+                .iter()
+                .find(|(key, _)| i.modifiers.is_none() && i.key_pressed(*key))
+                .map(|(_, tool_name)| *tool_name);
+            let swap_colors = i.modifiers.is_none() && i.key_pressed(SWAP_COLORS_KEY);
+            // Anything else done with Ctrl down makes it a shortcut, not a hold
+            let key_pressed = i
+                .events
+                .iter()
+                .any(|event| matches!(event, egui::Event::Key { pressed: true, .. }));
+            let interrupted = key_pressed
+                || i.pointer.any_pressed()
+                || i.raw_scroll_delta != egui::Vec2::ZERO
+                || i.zoom_delta() != 1.0
+                || i.modifiers.alt
+                || i.modifiers.shift
+                || i.modifiers.mac_cmd;
+            (
+                hotkey_tool,
+                swap_colors,
+                i.modifiers.ctrl,
+                interrupted,
+                i.time,
+            )
+        });
+
+        self.update_panning(ctx, typing);
+
+        if swap_colors && !typing {
+            self.swap_colors();
+        }
+
+        if let Some(tool_name) = hotkey_tool.filter(|_| !typing) {
+            if self.available_tools.iter().any(|tool| tool.name() == tool_name) {
+                log::debug!(tool = tool_name; "Tool selected from hotkey");
+                self.quick_toggle.cancel();
+                self.set_active_tool_by_name(tool_name);
+            }
+            return;
+        }
+
+        let tool_idle = self.active_tool().current_state_name() == "Idle";
+        if let Some(tool_name) = self.quick_toggle.update(
+            ctrl_held && !typing,
+            interrupted,
+            now,
+            self.active_tool().name(),
+            tool_idle,
+        ) {
+            log::debug!(tool = tool_name; "Quick-toggling to tool");
+            self.set_active_tool_by_name(tool_name);
+        }
+        // Wake up to switch even if nothing else happens while Ctrl is held
+        if let Some(delay) = self.quick_toggle.remaining_delay(now) {
+            ctx.request_repaint_after(std::time::Duration::from_secs_f64(delay));
+        }
+    }
+
+    /// Pan the view by dragging while Space is held
+    ///
+    /// Panning starts only once the tool is idle, so a stroke isn't cut short,
+    /// and lasts until the drag ends, so the tool never sees half of one.
+    fn update_panning(&mut self, ctx: &egui::Context, typing: bool) {
+        let (space_held, dragging, delta, origin) = ctx.input(|i| {
+            (
+                i.key_down(PAN_KEY),
+                i.pointer.primary_down(),
+                i.pointer.delta(),
+                i.pointer.press_origin(),
+            )
+        });
+        let panning = self.view.is_panning();
+        let tool_idle = self.active_tool().current_state_name() == "Idle";
+        let panning = (space_held && !typing && (panning || (tool_idle && !dragging)))
+            || (panning && dragging);
+        self.view.set_panning(panning);
+        if !panning {
+            return;
+        }
+
+        let from_canvas = origin.is_some_and(|origin| self.central_panel_rect.contains(origin));
+        if dragging && from_canvas {
+            self.view.pan_by(delta);
+        }
+        let over_canvas = ctx
+            .input(|i| i.pointer.hover_pos())
+            .is_some_and(|pos| self.central_panel_rect.contains(pos));
+        if over_canvas {
+            ctx.set_cursor_icon(if dragging {
+                egui::CursorIcon::Grabbing
+            } else {
+                egui::CursorIcon::Grab
+            });
+        }
+    }
+
+    /// Shrink and grow the active tool's brush with the bracket keys or
+    /// Ctrl+↑/↓, showing the new size on the canvas
+    fn handle_brush_size_keys(&mut self, ctx: &egui::Context) {
+        // Ctrl+arrows resize the selection while transforming from the keyboard
+        if ctx.wants_keyboard_input() || self.keyboard_transform.is_transforming() {
+            return;
+        }
+        let (steps, hover_pos) = ctx.input(|i| {
+            let presses =
+                |larger, smaller| i.num_presses(larger) as i32 - i.num_presses(smaller) as i32;
+            let steps = if i.modifiers.command {
+                presses(BRUSH_LARGER_CTRL_KEY, BRUSH_SMALLER_CTRL_KEY)
+            } else {
+                presses(BRUSH_LARGER_KEY, BRUSH_SMALLER_KEY)
+            };
+            (steps, i.pointer.hover_pos())
+        });
+        if steps == 0 {
+            return;
+        }
+        let Some((size, range)) = self.active_tool().brush_size() else {
+            return;
+        };
+        let size = step_brush_size(size, steps, &range);
+        self.active_tool_mut().set_brush_size(size);
+
+        // Show the brush where it would paint, or mid-canvas with the pointer elsewhere
+        let center = hover_pos
+            .filter(|pos| self.central_panel_rect.contains(*pos))
+            .map_or_else(
+                || self.visible_canvas_rect().center(),
+                |pos| self.view.screen_to_document(pos),
+            );
+        self.renderer.show_brush_size_hud(center, size);
+    }
+
+    /// Select and transform elements without a mouse
+    ///
+    /// Only runs while no widget has keyboard focus, so Esc out of a panel first.
+    fn handle_keyboard_transform(&mut self, ctx: &egui::Context) {
+        let mut keys: Vec<(egui::Key, egui::Modifiers)> = self
+            .canvas_tabs
+            .drain(..)
+            .map(|modifiers| (egui::Key::Tab, modifiers))
+            .collect();
+        if ctx.memory(|memory| memory.focused().is_some()) {
+            return;
+        }
+
+        ctx.input(|i| {
+            for event in &i.events {
+                if let egui::Event::Key {
+                    key,
+                    pressed: true,
+                    modifiers,
+                    ..
+                } = event
+                {
+                    keys.push((*key, *modifiers));
+                }
+            }
+        });
+        for (key, modifiers) in keys {
+            let command = self
+                .keyboard_transform
+                .handle_key(key, &modifiers, &self.editor_model);
+            if let Some(command) = command {
+                self.execute_command(command);
+            }
+        }
+    }
+
     fn handle_dropped_files(&mut self, ctx: &egui::Context) {
         // Use the file handler to check for and process dropped files
         if self.file_handler.check_for_dropped_files(ctx) {
             // Process dropped files and get commands to execute
-            let commands = self
-                .file_handler
-                .process_dropped_files(ctx, self.central_panel_rect);
+            let commands = self.file_handler.process_dropped_files(
+                ctx,
+                &self.editor_model,
+                self.visible_canvas_rect(),
+            );
 
             // Execute each command
             for command in commands {
@@ -195,28 +1916,167 @@ impl PaintApp {
     }
 }
 
+fn default_document_path() -> String {
+    format!("untitled.{}", DOCUMENT_EXTENSION)
+}
+
 impl eframe::App for PaintApp {
+    fn save(&mut self, storage: &mut dyn eframe::Storage) {
+        eframe::set_value(storage, EXPORT_PRESETS_KEY, &self.export_dialog.presets);
+        eframe::set_value(storage, SETTINGS_KEY, &self.settings);
+        eframe::set_value(storage, PANEL_LAYOUT_KEY, &self.panel_layout);
+        eframe::set_value(storage, COLORS_KEY, &self.colors);
+        #[cfg(target_arch = "wasm32")]
+        self.autosave_document();
+    }
+
+    fn auto_save_interval(&self) -> std::time::Duration {
+        self.settings.autosave_interval()
+    }
+
+    fn raw_input_hook(&mut self, ctx: &egui::Context, raw_input: &mut egui::RawInput) {
+        // Tab cycles elements unless a widget has focus, so keep egui from moving focus
+        let nothing_focused = ctx.memory(|memory| memory.focused().is_none());
+        if !nothing_focused || !KeyboardTransform::claims_tab(&self.editor_model) {
+            return;
+        }
+        raw_input.events.retain(|event| match event {
+            egui::Event::Key {
+                key: egui::Key::Tab,
+                pressed,
+                modifiers,
+                ..
+            } => {
+                if *pressed {
+                    self.canvas_tabs.push(*modifiers);
+                }
+                false
+            }
+            _ => true,
+        });
+    }
+
     fn update(&mut self, ctx: &egui::Context, _frame: &mut eframe::Frame) {
         // Begin frame - prepare renderer for tracking what elements are rendered
         self.renderer.begin_frame();
 
+        // Ask before closing the window with unsaved changes
+        self.handle_close_request(ctx);
+
         // Handle file drops
         self.handle_dropped_files(ctx);
         self.preview_files_being_dropped(ctx);
 
-        // Show the tools panel
-        tools_panel(self, ctx);
+        // Switch tools from the keyboard before any panel sees the input
+        self.handle_undo_shortcuts(ctx);
+        #[cfg(not(target_arch = "wasm32"))]
+        self.handle_copy_shortcut(ctx);
+        self.handle_selection_shortcuts(ctx);
+        self.handle_tool_hotkeys(ctx);
+        self.handle_brush_size_keys(ctx);
+        self.handle_keyboard_transform(ctx);
+
+        #[cfg(all(feature = "tray", not(target_arch = "wasm32")))]
+        self.handle_tray(ctx);
+        #[cfg(not(target_arch = "wasm32"))]
+        self.handle_screenshot(ctx);
+        #[cfg(target_arch = "wasm32")]
+        self.handle_picked_document();
+        #[cfg(target_arch = "wasm32")]
+        self.restore_autosave();
+
+        // Show the status bar and the docked panels around the canvas
+        status_bar(self, ctx);
+        docked_panels(self, ctx);
+
+        // Limit drawing to the replayed elements while a replay runs
+        let visible_elements = self.replay_controls.update(ctx, &self.editor_model);
+        self.renderer.set_visible_elements(visible_elements);
+        let array_preview = self.array_preview();
+        self.renderer.set_array_preview(array_preview);
 
         // Show the central panel for editing
+        self.handle_view_input(ctx);
+        self.view.update(ctx);
         let panel_rect = central_panel(
             &mut self.editor_model,
             &mut self.command_history,
             &mut self.renderer,
+            &self.view,
             ctx,
         );
 
         // Store the panel rect for future use
         self.set_central_panel_rect(panel_rect);
+        tutorial_window(ctx, &mut self.tutorial);
+
+        // Show the export window on top of the panels, exporting the current
+        // document's background
+        self.export_dialog
+            .set_background_color(self.canvas_background());
+        self.export_dialog.show(ctx, &self.editor_model);
+        let export_settings = self.export_dialog.export_settings();
+        self.replay_controls.show(ctx, &self.editor_model, &export_settings);
+        #[cfg(not(target_arch = "wasm32"))]
+        self.print_dialog
+            .show(ctx, &self.editor_model, &export_settings);
+        if self.new_document_dialog.show(ctx) {
+            self.request_document_action(DocumentAction::New, ctx);
+        }
+        let linked_images = crate::assets::linked_images(&self.editor_model.elements);
+        if let Some(choice) = self.relink_dialog.show(ctx, &linked_images) {
+            self.apply_relink_choice(choice);
+        }
+        if let Some(properties) = self.document_properties_dialog.show(ctx) {
+            if let Some(command) =
+                Command::new_set_document_properties(&self.editor_model, properties)
+            {
+                self.execute_command(command);
+            }
+        }
+        if let Some(values) = self.transform_dialog.show(ctx) {
+            self.apply_repeat_action(RepeatAction::transform(
+                values.translation,
+                values.scale_factors(),
+                values.rotation.to_radians(),
+            ));
+        }
+        if let Some(params) = self.array_dialog.show(ctx) {
+            let selected: Vec<ElementId> =
+                self.editor_model.selected_ids().iter().copied().collect();
+            if let Some(command) = Command::new_array(&self.editor_model, &selected, &params) {
+                self.execute_command(command);
+            }
+        }
+        if let Some(replacement) = self
+            .replace_colors_dialog
+            .show(ctx, &self.editor_model.elements)
+        {
+            if let Some(command) = Command::new_replace_colors(&self.editor_model, &replacement) {
+                self.execute_command(command);
+            }
+        }
+        let previous_settings = self.settings.clone();
+        if self.preferences_dialog.show(ctx, &mut self.settings) {
+            self.apply_settings(ctx, Some(&previous_settings));
+        }
+        self.bug_report_dialog
+            .show(ctx, &self.settings, &self.editor_model);
+        let sketch_stroke =
+            egui::Stroke::new(self.settings.stroke_thickness, self.colors.foreground);
+        if let Some(sketch) = self.quick_sketch.show(ctx, sketch_stroke) {
+            let result = self.save_quick_sketch(&sketch);
+            self.quick_sketch.set_saved(result);
+        }
+        self.show_diagnostics(ctx);
+        self.show_unsaved_changes_prompt(ctx);
+        self.show_passphrase_prompt(ctx);
+        self.toasts.show(ctx);
+        self.announcer.show(ctx);
+        self.update_window_title(ctx);
+
+        // Everything's drawn on the canvas now, so it can be turned with the view
+        self.view.turn_drawn(ctx, self.central_panel_rect);
 
         // End frame - process rendered elements and cleanup orphaned textures
         self.renderer.end_frame(ctx);
@@ -0,0 +1,206 @@
+//! Arrays: copies of the selection laid out along a line, in a grid or around
+//! a circle
+//!
+//! The Array window previews the copies on the canvas while its settings
+//! change; applying adds them all as one undoable step.
+
+use crate::command::Command;
+use crate::element::{Element, ElementId, ElementType};
+use crate::state::EditorModel;
+use egui::Vec2;
+use std::f32::consts::TAU;
+
+/// Most items an array makes, the original included, so a typo can't lay out
+/// millions of copies
+pub const MAX_ARRAY_ITEMS: usize = 1000;
+
+/// How the copies are laid out
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub enum ArrayLayout {
+    /// One after another, each `spacing` on from the last
+    #[default]
+    Line,
+    /// In columns and rows `spacing` apart
+    Grid,
+    /// Evenly around a circle through the original
+    Circle,
+}
+
+impl ArrayLayout {
+    pub const ALL: [ArrayLayout; 3] = [ArrayLayout::Line, ArrayLayout::Grid, ArrayLayout::Circle];
+
+    pub fn label(self) -> &'static str {
+        match self {
+            ArrayLayout::Line => "Line",
+            ArrayLayout::Grid => "Grid",
+            ArrayLayout::Circle => "Circle",
+        }
+    }
+}
+
+/// Settings of an array of the selection
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct ArrayParams {
+    pub layout: ArrayLayout,
+    /// How many along the line or around the circle, the original included
+    pub count: usize,
+    /// Grid columns and rows, the original making the top left
+    pub columns: usize,
+    pub rows: usize,
+    /// From one item to the next, along the line or across and down the grid
+    pub spacing: Vec2,
+    /// From the middle of the circle to the middle of each item
+    pub radius: f32,
+    /// Turn the items around the circle to face out from it, like the original
+    pub rotate_items: bool,
+}
+
+impl Default for ArrayParams {
+    fn default() -> Self {
+        Self {
+            layout: ArrayLayout::Line,
+            count: 5,
+            columns: 3,
+            rows: 3,
+            spacing: Vec2::new(100.0, 100.0),
+            radius: 100.0,
+            rotate_items: true,
+        }
+    }
+}
+
+impl ArrayParams {
+    /// Where each copy goes, as a move of the selection and a clockwise turn
+    /// about its middle in radians, leaving out the original
+    pub fn placements(&self) -> Vec<(Vec2, f32)> {
+        match self.layout {
+            ArrayLayout::Line => (1..self.count.min(MAX_ARRAY_ITEMS))
+                .map(|i| (self.spacing * i as f32, 0.0))
+                .collect(),
+            ArrayLayout::Grid => {
+                let columns = self.columns.clamp(1, MAX_ARRAY_ITEMS);
+                let rows = self.rows.clamp(1, MAX_ARRAY_ITEMS / columns);
+                (0..rows)
+                    .flat_map(|row| (0..columns).map(move |column| (column, row)))
+                    .skip(1)
+                    .map(|(column, row)| {
+                        let step = Vec2::new(column as f32, row as f32);
+                        (self.spacing * step, 0.0)
+                    })
+                    .collect()
+            }
+            ArrayLayout::Circle => {
+                // The original sits at the top, the circle's middle below it
+                let count = self.count.min(MAX_ARRAY_ITEMS);
+                (1..count)
+                    .map(|i| {
+                        let angle = TAU * i as f32 / count as f32;
+                        let (sin, cos) = angle.sin_cos();
+                        let offset = Vec2::new(sin, 1.0 - cos) * self.radius;
+                        let rotation = if self.rotate_items { angle } else { 0.0 };
+                        (offset, rotation)
+                    })
+                    .collect()
+            }
+        }
+    }
+}
+
+/// The copies an array of `element_ids` adds, in stacking order, each given
+/// an id from `next_id`
+pub fn array_copies(
+    editor_model: &EditorModel,
+    element_ids: &[ElementId],
+    params: &ArrayParams,
+    mut next_id: impl FnMut() -> ElementId,
+) -> Vec<ElementType> {
+    let originals: Vec<&ElementType> = editor_model
+        .elements
+        .iter()
+        .filter(|element| element_ids.contains(&element.id()))
+        .collect();
+    if originals.is_empty() {
+        return Vec::new();
+    }
+
+    let mut copies = Vec::new();
+    for (translation, rotation) in params.placements() {
+        // Place each set of copies on its own, so they turn about their own middle
+        let mut placed = EditorModel::new();
+        let mut ids = Vec::new();
+        for original in &originals {
+            let copy = original.with_id(next_id());
+            ids.push(copy.id());
+            if placed.add_element(copy).is_err() {
+                return Vec::new();
+            }
+        }
+        let transform = Command::new_transform_selection(
+            &placed,
+            &ids,
+            translation,
+            Vec2::splat(1.0),
+            rotation,
+        );
+        if let Some(transform) = transform {
+            if let Err(err) = transform.execute(&mut placed) {
+                log::warn!("Couldn't place array copies: {}", err);
+                continue;
+            }
+        }
+        copies.append(&mut placed.elements);
+    }
+    copies
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_layouts_place_copies_around_the_original() {
+        let line = ArrayParams {
+            count: 3,
+            spacing: Vec2::new(20.0, 5.0),
+            ..Default::default()
+        };
+        let offsets: Vec<Vec2> = line.placements().into_iter().map(|(at, _)| at).collect();
+        assert_eq!(offsets, [Vec2::new(20.0, 5.0), Vec2::new(40.0, 10.0)]);
+
+        let grid = ArrayParams {
+            layout: ArrayLayout::Grid,
+            columns: 2,
+            rows: 2,
+            spacing: Vec2::new(30.0, 10.0),
+            ..Default::default()
+        };
+        let offsets: Vec<Vec2> = grid.placements().into_iter().map(|(at, _)| at).collect();
+        assert_eq!(
+            offsets,
+            [
+                Vec2::new(30.0, 0.0),
+                Vec2::new(0.0, 10.0),
+                Vec2::new(30.0, 10.0)
+            ]
+        );
+
+        // Halfway around, the copy is at the bottom of the circle, upside down
+        let circle = ArrayParams {
+            layout: ArrayLayout::Circle,
+            count: 2,
+            radius: 50.0,
+            ..Default::default()
+        };
+        let [(offset, rotation)] = circle.placements()[..] else {
+            panic!("expected one copy");
+        };
+        assert!((offset - Vec2::new(0.0, 100.0)).length() < 1e-3);
+        assert_eq!(rotation, std::f32::consts::PI);
+
+        let huge = ArrayParams {
+            count: usize::MAX,
+            ..Default::default()
+        };
+        assert_eq!(huge.placements().len(), MAX_ARRAY_ITEMS - 1);
+    }
+}
@@ -0,0 +1,223 @@
+//! Arrowheads at the ends of strokes and paths
+//!
+//! Heads are sized by the line's thickness and point along the line's last
+//! stretch, so a hand-drawn wobble at the very end doesn't throw them off.
+//! Lines stop short under triangle heads, so their caps don't poke out past
+//! the tip.
+
+use egui::{Pos2, Vec2};
+use serde::{Deserialize, Serialize};
+
+/// What an end of a line is finished with
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq, Serialize, Deserialize)]
+pub enum Arrowhead {
+    #[default]
+    None,
+    Triangle,
+    Circle,
+    /// A bar across the line
+    Bar,
+}
+
+/// A head's outline, to fill in the line's color
+#[derive(Clone, Debug, PartialEq)]
+pub enum ArrowheadShape {
+    /// A convex polygon
+    Polygon(Vec<Pos2>),
+    Circle {
+        center: Pos2,
+        radius: f32,
+    },
+}
+
+impl Arrowhead {
+    pub const ALL: [Arrowhead; 4] = [
+        Arrowhead::None,
+        Arrowhead::Triangle,
+        Arrowhead::Circle,
+        Arrowhead::Bar,
+    ];
+
+    pub fn label(self) -> &'static str {
+        match self {
+            Arrowhead::None => "None",
+            Arrowhead::Triangle => "Triangle",
+            Arrowhead::Circle => "Circle",
+            Arrowhead::Bar => "Bar",
+        }
+    }
+
+    /// Length of the head along the line, in thicknesses
+    fn length(self) -> f32 {
+        match self {
+            Arrowhead::None => 0.0,
+            Arrowhead::Triangle => 3.0,
+            Arrowhead::Circle => 2.5,
+            Arrowhead::Bar => 1.0,
+        }
+    }
+
+    /// The head at `tip`, pointing along the unit vector `direction`
+    pub fn shape(self, tip: Pos2, direction: Vec2, thickness: f32) -> Option<ArrowheadShape> {
+        let across = direction.rot90();
+        match self {
+            Arrowhead::None => None,
+            Arrowhead::Triangle => {
+                let base = tip - direction * 3.0 * thickness;
+                let half_width = across * 1.5 * thickness;
+                Some(ArrowheadShape::Polygon(vec![
+                    tip,
+                    base + half_width,
+                    base - half_width,
+                ]))
+            }
+            Arrowhead::Circle => Some(ArrowheadShape::Circle {
+                center: tip,
+                radius: 1.25 * thickness,
+            }),
+            Arrowhead::Bar => {
+                let along = direction * 0.5 * thickness;
+                let half_length = across * 1.5 * thickness;
+                Some(ArrowheadShape::Polygon(vec![
+                    tip + along + half_length,
+                    tip - along + half_length,
+                    tip - along - half_length,
+                    tip + along - half_length,
+                ]))
+            }
+        }
+    }
+}
+
+/// The line through `points` with room left for its heads, and the heads
+///
+/// Lines too short for their heads keep at least half their length at each
+/// end; a single point gets no heads.
+pub fn with_arrowheads(
+    points: &[Pos2],
+    thickness: f32,
+    start: Arrowhead,
+    end: Arrowhead,
+) -> (Vec<Pos2>, Vec<ArrowheadShape>) {
+    let mut line = points.to_vec();
+    let mut heads = Vec::new();
+    let length: f32 = points
+        .windows(2)
+        .map(|pair| pair[0].distance(pair[1]))
+        .sum();
+    if length <= 0.0 {
+        return (line, heads);
+    }
+
+    for (head, at_end) in [(end, true), (start, false)] {
+        if head == Arrowhead::None {
+            continue;
+        }
+        if !at_end {
+            line.reverse();
+        }
+        let reach = head.length() * thickness;
+        let tip = *line.last().unwrap_or(&points[0]);
+        let direction = (tip - point_back(&line, reach.max(thickness))).normalized();
+        if direction != Vec2::ZERO && direction.is_finite() {
+            heads.extend(head.shape(tip, direction, thickness));
+            if head == Arrowhead::Triangle {
+                trim_end(&mut line, reach.min(length / 2.0));
+            }
+        }
+        if !at_end {
+            line.reverse();
+        }
+    }
+    (line, heads)
+}
+
+/// The point `distance` back along the line from its end, or its start if the
+/// line is shorter
+fn point_back(points: &[Pos2], distance: f32) -> Pos2 {
+    let mut left = distance;
+    for pair in points.windows(2).rev() {
+        let segment = pair[0].distance(pair[1]);
+        if segment >= left {
+            return pair[1].lerp(pair[0], left / segment);
+        }
+        left -= segment;
+    }
+    points[0]
+}
+
+/// Shorten the line by `distance` from its end
+fn trim_end(points: &mut Vec<Pos2>, distance: f32) {
+    let end = point_back(points, distance);
+    let mut left = distance;
+    while let [.., from, to] = points[..] {
+        let segment = from.distance(to);
+        points.pop();
+        if segment >= left {
+            break;
+        }
+        left -= segment;
+    }
+    points.push(end);
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_triangle_heads_stop_the_line_short() {
+        let points = [
+            Pos2::new(0.0, 0.0),
+            Pos2::new(50.0, 0.0),
+            Pos2::new(100.0, 0.0),
+        ];
+        let (line, heads) = with_arrowheads(&points, 2.0, Arrowhead::Bar, Arrowhead::Triangle);
+
+        // The line ends at the triangle's base; the bar sits on the start
+        assert_eq!(
+            line,
+            [
+                Pos2::new(0.0, 0.0),
+                Pos2::new(50.0, 0.0),
+                Pos2::new(94.0, 0.0)
+            ]
+        );
+        let ArrowheadShape::Polygon(triangle) = &heads[0] else {
+            panic!("expected a triangle");
+        };
+        assert_eq!(triangle[0], Pos2::new(100.0, 0.0));
+        assert!(
+            triangle[1..]
+                .iter()
+                .all(|corner| corner.x == 94.0 && corner.y.abs() == 3.0)
+        );
+        let ArrowheadShape::Polygon(bar) = &heads[1] else {
+            panic!("expected a bar");
+        };
+        assert!(
+            bar.iter()
+                .all(|corner| corner.x.abs() == 1.0 && corner.y.abs() == 3.0)
+        );
+
+        // Heads follow the line's last stretch, not its final jitter
+        let hooked = [
+            Pos2::new(0.0, 0.0),
+            Pos2::new(100.0, 0.0),
+            Pos2::new(100.5, 0.5),
+        ];
+        let (_, heads) = with_arrowheads(&hooked, 4.0, Arrowhead::None, Arrowhead::Circle);
+        assert_eq!(heads.len(), 1);
+        let (_, heads) = with_arrowheads(&hooked, 4.0, Arrowhead::None, Arrowhead::Triangle);
+        let ArrowheadShape::Polygon(triangle) = &heads[0] else {
+            panic!("expected a triangle");
+        };
+        assert!(triangle[1].x < 100.0 - 10.0);
+
+        assert!(
+            with_arrowheads(&points[..1], 2.0, Arrowhead::Triangle, Arrowhead::Triangle)
+                .1
+                .is_empty()
+        );
+    }
+}
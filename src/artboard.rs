@@ -0,0 +1,141 @@
+//! Fixed canvas sizes for documents
+//!
+//! A document without artboards grows with its content. Otherwise each artboard
+//! is a named canvas rectangle that exports cover exactly. An element belongs to
+//! the topmost artboard containing its center (or, failing that, the one it
+//! overlaps most), so dragging it onto another artboard moves it there.
+
+use crate::units::{Unit, in_to_px, mm_to_px};
+use egui::{Pos2, Rect};
+use serde::{Deserialize, Serialize};
+
+/// A named canvas rectangle in document coordinates
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
+pub struct Artboard {
+    pub id: usize,
+    pub name: String,
+    pub rect: Rect,
+}
+
+impl Artboard {
+    pub fn new(name: impl Into<String>, rect: Rect) -> Self {
+        Self {
+            id: crate::id_generator::generate_id(),
+            name: name.into(),
+            rect,
+        }
+    }
+}
+
+/// Default name for a new artboard: "Artboard N" with the first unused N
+pub fn next_artboard_name(artboards: &[Artboard]) -> String {
+    (1..)
+        .map(|number| format!("Artboard {}", number))
+        .find(|name| artboards.iter().all(|artboard| artboard.name != *name))
+        .unwrap_or_default()
+}
+
+/// The artboard an element with bounds `rect` belongs to, if any
+pub fn artboard_for_rect(artboards: &[Artboard], rect: Rect) -> Option<&Artboard> {
+    artboard_at(artboards, rect.center()).or_else(|| {
+        artboards
+            .iter()
+            .filter(|artboard| artboard.rect.intersects(rect))
+            .map(|artboard| (artboard, artboard.rect.intersect(rect).area()))
+            .max_by(|a, b| a.1.total_cmp(&b.1))
+            .map(|(artboard, _)| artboard)
+    })
+}
+
+/// The topmost artboard containing `pos`
+pub fn artboard_at(artboards: &[Artboard], pos: Pos2) -> Option<&Artboard> {
+    artboards
+        .iter()
+        .rev()
+        .find(|artboard| artboard.rect.contains(pos))
+}
+
+/// A named canvas size
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct ArtboardPreset {
+    pub name: &'static str,
+    pub width: f32,
+    pub height: f32,
+    /// Unit `width` and `height` are given in; paper sizes depend on the document DPI
+    pub unit: Unit,
+}
+
+impl ArtboardPreset {
+    const fn new(name: &'static str, width: f32, height: f32, unit: Unit) -> Self {
+        Self {
+            name,
+            width,
+            height,
+            unit,
+        }
+    }
+
+    /// Size in pixels at the given resolution
+    pub fn size_px(&self, dpi: f32) -> egui::Vec2 {
+        let to_px = |value: f32| match self.unit {
+            Unit::Px => value,
+            Unit::Mm => mm_to_px(value, dpi),
+            Unit::In => in_to_px(value, dpi),
+        };
+        egui::vec2(to_px(self.width), to_px(self.height))
+    }
+}
+
+pub const ARTBOARD_PRESETS: [ArtboardPreset; 9] = [
+    ArtboardPreset::new("A4 portrait", 210.0, 297.0, Unit::Mm),
+    ArtboardPreset::new("A4 landscape", 297.0, 210.0, Unit::Mm),
+    ArtboardPreset::new("US Letter", 8.5, 11.0, Unit::In),
+    ArtboardPreset::new("1080p", 1920.0, 1080.0, Unit::Px),
+    ArtboardPreset::new("720p", 1280.0, 720.0, Unit::Px),
+    ArtboardPreset::new("Square post", 1080.0, 1080.0, Unit::Px),
+    ArtboardPreset::new("Portrait post", 1080.0, 1350.0, Unit::Px),
+    ArtboardPreset::new("Story", 1080.0, 1920.0, Unit::Px),
+    ArtboardPreset::new("Banner", 1500.0, 500.0, Unit::Px),
+];
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_paper_presets_follow_dpi() {
+        let a4 = ARTBOARD_PRESETS[0];
+        let size = a4.size_px(300.0);
+        assert_eq!(size.x.round(), 2480.0);
+        assert_eq!(size.y.round(), 3508.0);
+        assert_eq!(
+            ARTBOARD_PRESETS[3].size_px(300.0),
+            egui::vec2(1920.0, 1080.0)
+        );
+    }
+
+    #[test]
+    fn test_elements_belong_to_artboard_under_their_center() {
+        let left = Artboard::new(
+            "Left",
+            Rect::from_min_size(Pos2::ZERO, egui::vec2(100.0, 100.0)),
+        );
+        let right = Artboard::new(
+            "Right",
+            Rect::from_min_size(Pos2::new(120.0, 0.0), egui::vec2(100.0, 100.0)),
+        );
+        let artboards = vec![left.clone(), right.clone()];
+
+        let inside_right = Rect::from_center_size(Pos2::new(150.0, 50.0), egui::vec2(10.0, 10.0));
+        assert_eq!(artboard_for_rect(&artboards, inside_right), Some(&right));
+
+        // Centered in the gap but mostly over the left artboard
+        let straddling = Rect::from_min_max(Pos2::new(60.0, 10.0), Pos2::new(125.0, 20.0));
+        assert_eq!(artboard_for_rect(&artboards, straddling), Some(&left));
+
+        let outside = Rect::from_center_size(Pos2::new(500.0, 500.0), egui::vec2(10.0, 10.0));
+        assert_eq!(artboard_for_rect(&artboards, outside), None);
+
+        assert_eq!(next_artboard_name(&artboards), "Artboard 1");
+    }
+}
@@ -0,0 +1,222 @@
+//! Images linked to files outside the document rather than embedded in it
+//!
+//! A linked image saves only its link, a file path relative to the document's
+//! folder or absolute, or a URL, and its data is read from the link when the
+//! document is opened. There's no HTTP client, so URLs are kept but never read,
+//! and on the web no link can be read; such images show as missing until they
+//! are relinked to a file or embedded.
+
+use crate::document::DocumentError;
+use crate::element::{Element, ElementId, ElementType};
+use std::path::{Path, PathBuf};
+
+/// A linked image, as listed in the relink dialog
+#[derive(Clone, Debug, PartialEq)]
+pub struct LinkedImage {
+    pub id: ElementId,
+    pub name: String,
+    pub link: String,
+    /// The image's data couldn't be read from its link
+    pub missing: bool,
+}
+
+/// The linked images among `elements`, in document order
+pub fn linked_images(elements: &[ElementType]) -> Vec<LinkedImage> {
+    elements
+        .iter()
+        .filter_map(|element| match element {
+            ElementType::Image(image) => image.link().map(|link| LinkedImage {
+                id: element.id(),
+                name: element.display_name(),
+                link: link.to_owned(),
+                missing: image.is_missing(),
+            }),
+            _ => None,
+        })
+        .collect()
+}
+
+pub fn is_url(link: &str) -> bool {
+    link.contains("://")
+}
+
+/// The file a link points at, with relative links taken from `document_dir`
+pub fn link_path(link: &str, document_dir: &Path) -> PathBuf {
+    document_dir.join(link)
+}
+
+/// Read the data a link points at
+pub fn read_link(link: &str, document_dir: &Path) -> Result<Vec<u8>, DocumentError> {
+    if is_url(link) {
+        return Err(unreadable("Linked URLs can't be downloaded"));
+    }
+    if cfg!(target_arch = "wasm32") {
+        return Err(unreadable("Linked files can't be read on the web"));
+    }
+    Ok(std::fs::read(link_path(link, document_dir))?)
+}
+
+fn unreadable(reason: &str) -> DocumentError {
+    std::io::Error::new(std::io::ErrorKind::Unsupported, reason.to_owned()).into()
+}
+
+/// Read the data of every linked image, returning how many couldn't be read
+pub fn load_linked_images(elements: &mut [ElementType], document_dir: &Path) -> usize {
+    let mut missing = 0;
+    for element in elements {
+        let ElementType::Image(image) = element else {
+            continue;
+        };
+        let Some(link) = image.link().map(str::to_owned) else {
+            continue;
+        };
+        match read_link(&link, document_dir) {
+            Ok(data) => image.set_source(Some(link), data),
+            Err(err) => {
+                log::warn!("Failed to read linked image {}: {}", link, err);
+                missing += 1;
+            }
+        }
+    }
+    missing
+}
+
+/// Linked images copied into a folder next to the document
+#[cfg(not(target_arch = "wasm32"))]
+#[derive(Debug)]
+pub struct CollectedAssets {
+    /// The copied images, linked to their copies by paths relative to the document
+    pub images: Vec<ElementType>,
+    /// Missing images, which keep their links
+    pub missing: usize,
+    pub folder: PathBuf,
+}
+
+/// Copy the data of every linked image into an assets folder beside the
+/// document at `document_path`, so the document and folder can be moved or
+/// shared together
+#[cfg(not(target_arch = "wasm32"))]
+pub fn collect_assets(
+    elements: &[ElementType],
+    document_path: &Path,
+) -> Result<CollectedAssets, DocumentError> {
+    let stem = document_path
+        .file_stem()
+        .map_or_else(|| "untitled".into(), |stem| stem.to_string_lossy());
+    let folder_name = format!("{}_assets", stem);
+    let folder = link_path(
+        &folder_name,
+        document_path.parent().unwrap_or(Path::new("")),
+    );
+    std::fs::create_dir_all(&folder)?;
+
+    let mut images = Vec::new();
+    let mut missing = 0;
+    let mut names = std::collections::HashSet::new();
+    for element in elements {
+        let ElementType::Image(image) = element else {
+            continue;
+        };
+        let Some(link) = image.link() else {
+            continue;
+        };
+        if image.is_missing() {
+            missing += 1;
+            continue;
+        }
+        // Keep the linked file's name, made unique with the element id if taken
+        let mut file_name = link
+            .rsplit(['/', '\\'])
+            .next()
+            .filter(|name| !name.is_empty())
+            .map_or_else(|| format!("image-{}", element.id()), str::to_owned);
+        if !names.insert(file_name.clone()) {
+            file_name = format!("{}-{}", element.id(), file_name);
+            names.insert(file_name.clone());
+        }
+        std::fs::write(folder.join(&file_name), image.original_data())?;
+
+        let mut collected = image.clone();
+        collected.set_source(
+            Some(format!("{}/{}", folder_name, file_name)),
+            image.original_data().to_vec(),
+        );
+        images.push(ElementType::Image(collected));
+    }
+    Ok(CollectedAssets {
+        images,
+        missing,
+        folder,
+    })
+}
+
+#[cfg(all(test, not(target_arch = "wasm32")))]
+mod tests {
+    use super::*;
+    use crate::document::DocumentFile;
+    use crate::element::factory;
+    use crate::state::EditorModel;
+    use egui::{Pos2, Vec2};
+
+    #[test]
+    fn test_linked_images_load_and_collect() {
+        let dir = std::env::temp_dir().join(format!("paint-assets-{}", std::process::id()));
+        std::fs::create_dir_all(dir.join("photos")).unwrap();
+        std::fs::write(dir.join("photos/cat.png"), b"cat").unwrap();
+
+        let linked = |id, link: &str| {
+            factory::create_linked_image(
+                ElementId::new(id),
+                link.to_owned(),
+                Vec::new(),
+                Vec2::new(10.0, 10.0),
+                Pos2::ZERO,
+            )
+        };
+        let mut elements = vec![
+            linked(1, "photos/cat.png"),
+            linked(2, "photos/dog.png"),
+            linked(3, "https://example.com/cat.png"),
+            factory::create_image(
+                ElementId::new(4),
+                b"embedded".to_vec(),
+                Vec2::splat(1.0),
+                Pos2::ZERO,
+            ),
+        ];
+
+        // Relative links are read from the document's folder; the rest go missing
+        assert_eq!(load_linked_images(&mut elements, &dir), 2);
+        let images = linked_images(&elements);
+        assert_eq!(images.len(), 3);
+        assert_eq!(
+            images.iter().map(|image| image.missing).collect::<Vec<_>>(),
+            [false, true, true]
+        );
+
+        // Saving keeps only the links of linked images
+        let mut editor_model = EditorModel::new();
+        editor_model.load_document(DocumentFile {
+            elements: elements.clone(),
+            ..Default::default()
+        });
+        let saved = DocumentFile::from_model(&editor_model);
+        let missing: Vec<bool> = saved
+            .elements
+            .iter()
+            .map(|element| matches!(element, ElementType::Image(image) if image.is_missing()))
+            .collect();
+        assert_eq!(missing, [true, true, true, false]);
+
+        let collected = collect_assets(&elements, &dir.join("drawing.paint")).unwrap();
+        assert_eq!(collected.missing, 2);
+        assert_eq!(collected.folder, dir.join("drawing_assets"));
+        let [ElementType::Image(image)] = collected.images.as_slice() else {
+            panic!("expected the one readable image");
+        };
+        assert_eq!(image.link(), Some("drawing_assets/cat.png"));
+        assert_eq!(read_link(image.link().unwrap(), &dir).unwrap(), b"cat");
+
+        std::fs::remove_dir_all(&dir).unwrap();
+    }
+}
@@ -0,0 +1,499 @@
+//! Autosaved copies of the open document in the browser, and undo history
+//! kept beside saved documents
+//!
+//! Web pages can't write next to the document, so on the web the document is
+//! copied into IndexedDB (or localStorage, where IndexedDB isn't available)
+//! every autosave interval, undo history and all, and reopened when the page is
+//! loaded again. Older copies are pruned so autosaves stay well inside the
+//! site's storage quota.
+//!
+//! Native builds save the document to its file, and the session to a sidecar
+//! file next to it. Reopening the document takes the undo history back up, as
+//! long as the document hasn't changed since.
+
+use crate::command::CommandHistory;
+use crate::document::DocumentFile;
+use crate::state::EditorModel;
+use serde::{Deserialize, Serialize};
+
+/// Most autosaves kept, newest first
+pub const MAX_AUTOSAVES: usize = 5;
+/// Share of the storage quota autosaves may fill before older ones are pruned
+pub const QUOTA_SHARE: f64 = 0.5;
+
+/// An autosave as stored: its key and how many bytes it takes
+#[derive(Clone, Debug, PartialEq)]
+pub struct AutosaveEntry {
+    pub key: String,
+    pub size: u64,
+}
+
+/// Key an autosave of the document called `name` is stored under
+///
+/// Keys start with the save time, zero-padded, so they sort oldest first.
+pub fn autosave_key(saved_at_ms: u64, name: &str) -> String {
+    format!("{:015}/{}", saved_at_ms, name)
+}
+
+/// Name of the document an autosave key was made for
+pub fn autosave_name(key: &str) -> &str {
+    key.split_once('/').map_or(key, |(_, name)| name)
+}
+
+/// Keys of the autosaves to delete, oldest first
+///
+/// The newest autosave is always kept. Beyond that, only [`MAX_AUTOSAVES`] are
+/// kept, and the oldest go while storage `usage` is over [`QUOTA_SHARE`] of
+/// `quota` (when the browser reports one).
+pub fn autosaves_to_prune(
+    entries: &[AutosaveEntry],
+    usage: u64,
+    quota: Option<u64>,
+) -> Vec<String> {
+    let mut entries: Vec<&AutosaveEntry> = entries.iter().collect();
+    entries.sort_by(|a, b| a.key.cmp(&b.key));
+    let limit = quota.map(|quota| (quota as f64 * QUOTA_SHARE) as u64);
+
+    let mut usage = usage;
+    let mut pruned = Vec::new();
+    let mut remaining = entries.len();
+    for entry in entries {
+        let over_count = remaining > MAX_AUTOSAVES;
+        let over_quota = limit.is_some_and(|limit| usage > limit);
+        if remaining == 1 || !(over_count || over_quota) {
+            break;
+        }
+        pruned.push(entry.key.clone());
+        usage = usage.saturating_sub(entry.size);
+        remaining -= 1;
+    }
+    pruned
+}
+
+/// What an autosave holds: the document, and its undo history when that could be packed
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
+pub struct AutosavedSession {
+    /// The document, as written to document files
+    pub document: String,
+    /// The undo history, packed by `CommandHistory::to_session`
+    #[serde(default)]
+    pub history: Option<String>,
+}
+
+impl AutosavedSession {
+    pub fn to_ron(&self) -> Result<String, ron::Error> {
+        ron::ser::to_string(self)
+    }
+
+    /// Read an autosave; older ones hold just the document
+    pub fn from_ron(text: &str) -> Self {
+        ron::from_str(text).unwrap_or_else(|_| Self {
+            document: text.to_string(),
+            history: None,
+        })
+    }
+
+    /// The session of the document in `editor_model`, with `command_history`
+    pub fn capture(
+        editor_model: &EditorModel,
+        command_history: &CommandHistory,
+    ) -> Result<Self, String> {
+        Ok(Self {
+            document: DocumentFile::from_model(editor_model)
+                .to_ron()
+                .map_err(|err| err.to_string())?,
+            history: Some(command_history.to_session(editor_model)?),
+        })
+    }
+
+    /// Take up the session's undo history in `command_history`, if the session
+    /// is of the document now in `editor_model`, returning whether it was
+    pub fn restore_history(
+        &self,
+        editor_model: &EditorModel,
+        command_history: &mut CommandHistory,
+    ) -> Result<bool, String> {
+        let Some(history) = &self.history else {
+            return Ok(false);
+        };
+        let document = DocumentFile::from_model(editor_model)
+            .to_ron()
+            .map_err(|err| err.to_string())?;
+        if document != self.document {
+            return Ok(false);
+        }
+        command_history.restore_session(history, editor_model)?;
+        Ok(true)
+    }
+}
+
+/// Where the session of the document at `document_path` is kept: beside it,
+/// with `.session` added to its name
+#[cfg(not(target_arch = "wasm32"))]
+pub fn session_path(document_path: &std::path::Path) -> std::path::PathBuf {
+    let mut name = document_path.file_name().unwrap_or_default().to_os_string();
+    name.push(".session");
+    document_path.with_file_name(name)
+}
+
+/// Write the session of the document at `document_path` beside it
+#[cfg(not(target_arch = "wasm32"))]
+pub fn write_session(
+    document_path: &std::path::Path,
+    session: &AutosavedSession,
+) -> Result<(), String> {
+    let text = session.to_ron().map_err(|err| err.to_string())?;
+    std::fs::write(session_path(document_path), text).map_err(|err| err.to_string())
+}
+
+/// The session kept beside the document at `document_path`, if there is one
+#[cfg(not(target_arch = "wasm32"))]
+pub fn read_session(document_path: &std::path::Path) -> Option<AutosavedSession> {
+    let text = std::fs::read_to_string(session_path(document_path)).ok()?;
+    Some(AutosavedSession::from_ron(&text))
+}
+
+#[cfg(target_arch = "wasm32")]
+pub use web::WebAutosaves;
+
+/// IndexedDB and localStorage access
+#[cfg(target_arch = "wasm32")]
+mod web {
+    use super::{AutosaveEntry, autosave_key, autosave_name, autosaves_to_prune};
+    use eframe::wasm_bindgen::closure::Closure;
+    use eframe::wasm_bindgen::{JsCast as _, JsValue};
+    use std::cell::RefCell;
+    use std::rc::Rc;
+    use wasm_bindgen_futures::JsFuture;
+    use web_sys::{IdbDatabase, IdbRequest, IdbTransactionMode};
+
+    const DATABASE_NAME: &str = "eframe_paint";
+    const STORE_NAME: &str = "autosaves";
+    /// Prefix of autosave keys in localStorage, which is shared with eframe
+    const LOCAL_STORAGE_PREFIX: &str = "eframe_paint_autosave/";
+    /// What browsers typically allow a site in localStorage
+    const LOCAL_STORAGE_QUOTA: u64 = 5 * 1024 * 1024;
+
+    /// The document last autosaved in a previous session, read while the page loads
+    type Restored = Rc<RefCell<Option<(String, String)>>>;
+
+    /// Where the document is autosaved in the browser
+    pub struct WebAutosaves {
+        // None until the database has opened, and for good if it can't be
+        database: Rc<RefCell<Option<IdbDatabase>>>,
+        // Whether opening the database has finished, one way or the other
+        ready: Rc<RefCell<bool>>,
+        restored: Restored,
+    }
+
+    impl WebAutosaves {
+        /// Open the autosave database and start reading the newest autosave
+        pub fn open(ctx: &egui::Context) -> Self {
+            let autosaves = Self {
+                database: Default::default(),
+                ready: Default::default(),
+                restored: Default::default(),
+            };
+            let database = autosaves.database.clone();
+            let ready = autosaves.ready.clone();
+            let restored = autosaves.restored.clone();
+            let ctx = ctx.clone();
+            wasm_bindgen_futures::spawn_local(async move {
+                match open_database().await {
+                    Ok(db) => {
+                        match newest_in_database(&db).await {
+                            Ok(newest) => *restored.borrow_mut() = newest,
+                            Err(err) => log::warn!("Failed to read autosaves: {:?}", err),
+                        }
+                        *database.borrow_mut() = Some(db);
+                    }
+                    Err(err) => {
+                        log::warn!(
+                            "IndexedDB unavailable, autosaving to localStorage: {:?}",
+                            err
+                        );
+                        *restored.borrow_mut() = newest_in_local_storage();
+                    }
+                }
+                *ready.borrow_mut() = true;
+                ctx.request_repaint();
+            });
+            autosaves
+        }
+
+        /// The name and contents of the document autosaved last session, once read
+        pub fn take_restored(&self) -> Option<(String, String)> {
+            self.restored.borrow_mut().take()
+        }
+
+        /// Autosave `text`, the document called `name`, pruning older autosaves
+        pub fn store(&self, name: &str, text: String) {
+            if !*self.ready.borrow() {
+                // Don't write over the autosave that's still being restored
+                return;
+            }
+            let key = autosave_key(js_sys::Date::now() as u64, name);
+            let Some(db) = self.database.borrow().clone() else {
+                if let Err(err) = store_in_local_storage(&key, &text) {
+                    log::warn!("Failed to autosave to localStorage: {:?}", err);
+                }
+                return;
+            };
+            wasm_bindgen_futures::spawn_local(async move {
+                if let Err(err) = store_in_database(&db, &key, &text).await {
+                    log::warn!("Failed to autosave to IndexedDB: {:?}", err);
+                }
+            });
+        }
+    }
+
+    /// Wait for an IndexedDB request to finish
+    async fn finished(request: &IdbRequest) -> Result<JsValue, JsValue> {
+        let promise = js_sys::Promise::new(&mut |resolve, reject| {
+            let on_success = {
+                let request = request.clone();
+                Closure::once_into_js(move || {
+                    let result = request.result().unwrap_or(JsValue::UNDEFINED);
+                    let _ = resolve.call1(&JsValue::NULL, &result);
+                })
+            };
+            let on_error = Closure::once_into_js(move || {
+                let _ = reject.call1(&JsValue::NULL, &"IndexedDB request failed".into());
+            });
+            request.set_onsuccess(Some(on_success.unchecked_ref()));
+            request.set_onerror(Some(on_error.unchecked_ref()));
+        });
+        JsFuture::from(promise).await
+    }
+
+    async fn open_database() -> Result<IdbDatabase, JsValue> {
+        let factory = web_sys::window()
+            .ok_or("No window")?
+            .indexed_db()?
+            .ok_or("No IndexedDB")?;
+        let request = factory.open_with_u32(DATABASE_NAME, 1)?;
+        let on_upgrade = {
+            let request = request.clone();
+            Closure::once_into_js(move || {
+                let created = request
+                    .result()
+                    .and_then(|db| db.dyn_into::<IdbDatabase>())
+                    .and_then(|db| db.create_object_store(STORE_NAME));
+                if let Err(err) = created {
+                    log::warn!("Failed to create the autosave store: {:?}", err);
+                }
+            })
+        };
+        request.set_onupgradeneeded(Some(on_upgrade.unchecked_ref()));
+        finished(&request).await?.dyn_into::<IdbDatabase>()
+    }
+
+    /// Keys and contents of the autosaves in the database, oldest first
+    async fn database_entries(db: &IdbDatabase) -> Result<Vec<(String, String)>, JsValue> {
+        let store = db
+            .transaction_with_str_and_mode(STORE_NAME, IdbTransactionMode::Readonly)?
+            .object_store(STORE_NAME)?;
+        let keys = js_sys::Array::from(&finished(&store.get_all_keys()?).await?);
+        let values = js_sys::Array::from(&finished(&store.get_all()?).await?);
+        Ok(keys
+            .iter()
+            .zip(values.iter())
+            .filter_map(|(key, value)| Some((key.as_string()?, value.as_string()?)))
+            .collect())
+    }
+
+    async fn newest_in_database(db: &IdbDatabase) -> Result<Option<(String, String)>, JsValue> {
+        let newest = database_entries(db).await?.pop();
+        Ok(newest.map(|(key, text)| (autosave_name(&key).to_string(), text)))
+    }
+
+    async fn store_in_database(db: &IdbDatabase, key: &str, text: &str) -> Result<(), JsValue> {
+        let store = db
+            .transaction_with_str_and_mode(STORE_NAME, IdbTransactionMode::Readwrite)?
+            .object_store(STORE_NAME)?;
+        finished(&store.put_with_key(&text.into(), &key.into())?).await?;
+
+        let entries: Vec<AutosaveEntry> = database_entries(db)
+            .await?
+            .into_iter()
+            .map(|(key, text)| AutosaveEntry {
+                key,
+                size: text.len() as u64,
+            })
+            .collect();
+        let (usage, quota) = storage_estimate()
+            .await
+            .unwrap_or_else(|| (entries.iter().map(|entry| entry.size).sum(), None));
+        let pruned = autosaves_to_prune(&entries, usage, quota);
+        if pruned.is_empty() {
+            return Ok(());
+        }
+        let store = db
+            .transaction_with_str_and_mode(STORE_NAME, IdbTransactionMode::Readwrite)?
+            .object_store(STORE_NAME)?;
+        for key in pruned {
+            log::info!("Pruning autosave {}", key);
+            finished(&store.delete(&key.into())?).await?;
+        }
+        Ok(())
+    }
+
+    /// How much storage the site uses and may use, if the browser says
+    async fn storage_estimate() -> Option<(u64, Option<u64>)> {
+        let manager = web_sys::window()?.navigator().storage();
+        let estimate = JsFuture::from(manager.estimate().ok()?).await.ok()?;
+        let field = |name: &str| {
+            js_sys::Reflect::get(&estimate, &name.into())
+                .ok()
+                .and_then(|value| value.as_f64())
+                .map(|value| value as u64)
+        };
+        Some((field("usage")?, field("quota")))
+    }
+
+    fn local_storage() -> Result<web_sys::Storage, JsValue> {
+        web_sys::window()
+            .ok_or("No window")?
+            .local_storage()?
+            .ok_or_else(|| "No localStorage".into())
+    }
+
+    /// Keys and contents of the autosaves in localStorage, oldest first
+    fn local_storage_entries(storage: &web_sys::Storage) -> Vec<(String, String)> {
+        let count = storage.length().unwrap_or(0);
+        let mut entries: Vec<(String, String)> = (0..count)
+            .filter_map(|index| storage.key(index).ok().flatten())
+            .filter_map(|key| Some(key.strip_prefix(LOCAL_STORAGE_PREFIX)?.to_string()))
+            .filter_map(|key| {
+                let text = storage
+                    .get_item(&format!("{}{}", LOCAL_STORAGE_PREFIX, key))
+                    .ok()??;
+                Some((key, text))
+            })
+            .collect();
+        entries.sort();
+        entries
+    }
+
+    fn newest_in_local_storage() -> Option<(String, String)> {
+        let (key, text) = local_storage_entries(&local_storage().ok()?).pop()?;
+        Some((autosave_name(&key).to_string(), text))
+    }
+
+    fn store_in_local_storage(key: &str, text: &str) -> Result<(), JsValue> {
+        let storage = local_storage()?;
+        let entries: Vec<AutosaveEntry> = local_storage_entries(&storage)
+            .into_iter()
+            .map(|(key, text)| AutosaveEntry {
+                key,
+                size: text.len() as u64,
+            })
+            .chain([AutosaveEntry {
+                key: key.to_string(),
+                size: text.len() as u64,
+            }])
+            .collect();
+        let usage = entries.iter().map(|entry| entry.size).sum();
+        // Make room first: localStorage refuses writes over its quota
+        for pruned in autosaves_to_prune(&entries, usage, Some(LOCAL_STORAGE_QUOTA)) {
+            storage.remove_item(&format!("{}{}", LOCAL_STORAGE_PREFIX, pruned))?;
+        }
+        storage.set_item(&format!("{}{}", LOCAL_STORAGE_PREFIX, key), text)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_pruning_keeps_newest_autosaves_within_quota() {
+        let entries: Vec<AutosaveEntry> = (0..7)
+            .map(|i| AutosaveEntry {
+                key: autosave_key(1_000 + i, "sketch.paint"),
+                size: 100,
+            })
+            .collect();
+        assert_eq!(autosave_name(&entries[0].key), "sketch.paint");
+
+        // Only the newest MAX_AUTOSAVES are kept
+        let pruned = autosaves_to_prune(&entries, 700, None);
+        assert_eq!(pruned, vec![entries[0].key.clone(), entries[1].key.clone()]);
+
+        // Over the quota share, old ones go until usage fits
+        let pruned = autosaves_to_prune(&entries, 700, Some(800));
+        assert_eq!(pruned.len(), 3);
+
+        // The newest stays even when it alone is too big
+        let pruned = autosaves_to_prune(&entries[6..], 10_000, Some(100));
+        assert!(pruned.is_empty());
+    }
+
+    #[test]
+    fn test_sessions_read_back_and_older_autosaves_are_documents() {
+        let session = AutosavedSession {
+            document: "(elements: [])".to_string(),
+            history: Some("packed".to_string()),
+        };
+        let text = session.to_ron().unwrap();
+        assert_eq!(AutosavedSession::from_ron(&text), session);
+
+        let older = AutosavedSession::from_ron("(version: 3, elements: [])");
+        assert_eq!(older.document, "(version: 3, elements: [])");
+        assert_eq!(older.history, None);
+    }
+
+    #[cfg(not(target_arch = "wasm32"))]
+    #[test]
+    fn test_undo_history_kept_beside_the_document_comes_back() {
+        use crate::command::Command;
+        use crate::element::{ElementId, factory};
+        use egui::{Color32, pos2};
+
+        let stroke = |id| {
+            let points = vec![pos2(10.0, 10.0), pos2(40.0, id as f32 * 10.0)];
+            factory::create_stroke(ElementId::new(id), points, 2.0, Color32::RED)
+        };
+        let mut editor_model = EditorModel::new();
+        let mut command_history = CommandHistory::new();
+        for id in 1..=2 {
+            let command = Command::AddElement {
+                element: stroke(id),
+            };
+            command_history.execute(command, &mut editor_model).unwrap();
+        }
+        command_history.undo(&mut editor_model).unwrap();
+
+        let dir = tempfile::tempdir().unwrap();
+        let document_path = dir.path().join("sketch.paint");
+        let session = AutosavedSession::capture(&editor_model, &command_history).unwrap();
+        write_session(&document_path, &session).unwrap();
+        assert!(dir.path().join("sketch.paint.session").exists());
+
+        // Reopened, the document can be undone and redone as before
+        let read = read_session(&document_path).unwrap();
+        assert_eq!(read, session);
+        let mut reopened = EditorModel::new();
+        reopened.load_document(DocumentFile::from_ron(&read.document).unwrap());
+        let mut reopened_history = CommandHistory::new();
+        assert!(
+            read.restore_history(&reopened, &mut reopened_history)
+                .unwrap()
+        );
+        reopened_history.redo(&mut reopened).unwrap();
+        assert_eq!(reopened.elements.len(), 2);
+        reopened_history.undo(&mut reopened).unwrap();
+        reopened_history.undo(&mut reopened).unwrap();
+        assert!(reopened.elements.is_empty());
+
+        // A document changed since the history was kept doesn't take it up
+        editor_model.add_element(stroke(3)).unwrap();
+        let mut other_history = CommandHistory::new();
+        assert!(
+            !read
+                .restore_history(&editor_model, &mut other_history)
+                .unwrap()
+        );
+        assert!(!other_history.can_undo());
+    }
+}
@@ -0,0 +1,366 @@
+//! Bug report bundles: app version, settings, recent logs and, optionally, an
+//! anonymized copy of the document, zipped up for attaching to a GitHub issue
+
+use crate::document::{DOCUMENT_EXTENSION, DocumentError, DocumentFile};
+use crate::element::{Element, ElementType, factory};
+use crate::settings::Settings;
+use thiserror::Error;
+
+/// Where users are sent to file the issue the report is attached to
+pub const ISSUES_URL: &str = "https://github.com/dbtreasure/eframe-paint/issues/new";
+
+/// Number of recent log lines kept for reports
+const MAX_LOG_LINES: usize = 500;
+/// Width and height blurred images are shrunk into; scaled back up on the canvas they
+/// keep their colors and layout but none of their detail
+const BLUR_SIZE: u32 = 16;
+/// Stands in for settings that may hold paths or commands
+const REDACTED: &str = "<redacted>";
+
+/// Errors that can occur while putting a bug report together
+#[derive(Error, Debug)]
+pub enum BugReportError {
+    #[error("Failed to write the document: {0}")]
+    Document(#[from] DocumentError),
+    #[error("Failed to write the settings: {0}")]
+    Settings(#[from] ron::Error),
+    #[error("Failed to write file: {0}")]
+    Io(#[from] std::io::Error),
+    #[error("Saving files is not supported on the web yet")]
+    Unsupported,
+}
+
+/// What happens to images in the attached document
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub enum ImageRedaction {
+    /// Leave images and image pattern fills out entirely
+    #[default]
+    Strip,
+    /// Keep images, blurred beyond recognition
+    Blur,
+}
+
+impl ImageRedaction {
+    pub const ALL: [ImageRedaction; 2] = [ImageRedaction::Strip, ImageRedaction::Blur];
+
+    pub fn label(self) -> &'static str {
+        match self {
+            ImageRedaction::Strip => "Strip images",
+            ImageRedaction::Blur => "Blur images",
+        }
+    }
+}
+
+/// The most recent log lines, oldest first
+pub fn recent_logs() -> Vec<String> {
+    let records = crate::log_console::records();
+    let skipped = records.len().saturating_sub(MAX_LOG_LINES);
+    records[skipped..]
+        .iter()
+        .map(|record| record.format())
+        .collect()
+}
+
+/// App version and platform, as put at the top of a report
+pub fn system_info() -> String {
+    format!(
+        "{} {}\nOS: {} ({})\nBuild: {}\n",
+        env!("CARGO_PKG_NAME"),
+        env!("CARGO_PKG_VERSION"),
+        std::env::consts::OS,
+        std::env::consts::ARCH,
+        if cfg!(debug_assertions) {
+            "debug"
+        } else {
+            "release"
+        }
+    )
+}
+
+/// Settings with paths and shell commands, which may name the user, redacted
+pub fn anonymize_settings(settings: &Settings) -> Settings {
+    let redact = |value: &str| {
+        if value.is_empty() {
+            String::new()
+        } else {
+            REDACTED.to_string()
+        }
+    };
+    Settings {
+        post_export_command: redact(&settings.post_export_command),
+        scratch_dir: redact(&settings.scratch_dir),
+        screenshot_command: redact(&settings.screenshot_command),
+        ..settings.clone()
+    }
+}
+
+/// A copy of the document without anything identifying: no title, author or
+/// names, and images stripped or blurred
+///
+/// Geometry, colors and element properties stay as they are, since they are
+/// usually what a bug is about.
+pub fn anonymize_document(file: &DocumentFile, images: ImageRedaction) -> DocumentFile {
+    let mut elements = Vec::with_capacity(file.elements.len());
+    for element in &file.elements {
+        let mut element = match (element, images) {
+            (ElementType::Image(_), ImageRedaction::Strip) => continue,
+            (ElementType::Image(image), ImageRedaction::Blur) => {
+                let Some(blurred) = blur_image(image.original_data()) else {
+                    continue;
+                };
+                let mut element =
+                    factory::create_image(image.id(), blurred, image.size(), image.position());
+                *element.metadata_mut() = image.metadata().clone();
+                element
+            }
+            (ElementType::Shape(shape), _) => {
+                let mut shape = shape.clone();
+                let pattern = shape.pattern().cloned().and_then(|mut pattern| {
+                    pattern.image_data = match images {
+                        ImageRedaction::Strip => None,
+                        ImageRedaction::Blur => blur_image(&pattern.image_data),
+                    }?;
+                    Some(pattern)
+                });
+                shape.set_pattern(pattern);
+                ElementType::Shape(shape)
+            }
+            (element, _) => element.clone(),
+        };
+        element.metadata_mut().name.clear();
+        elements.push(element);
+    }
+
+    let mut artboards = file.artboards.clone();
+    for (index, artboard) in artboards.iter_mut().enumerate() {
+        artboard.name = format!("Artboard {}", index + 1);
+    }
+    let mut slices = file.slices.clone();
+    for (index, slice) in slices.iter_mut().enumerate() {
+        slice.name = format!("Slice {}", index + 1);
+    }
+
+    let reference_image = match images {
+        ImageRedaction::Strip => None,
+        ImageRedaction::Blur => file
+            .reference_image
+            .as_ref()
+            .and_then(|reference| Some(reference.with_data(blur_image(reference.data())?))),
+    };
+
+    DocumentFile {
+        version: file.version,
+        metadata: Default::default(),
+        units: file.units,
+        artboards,
+        elements,
+        reference_image,
+        background: file.background,
+        slices,
+        ruler_guides: file.ruler_guides.clone(),
+    }
+}
+
+/// Shrink an image down to a few pixels, re-encoded as PNG
+fn blur_image(bytes: &[u8]) -> Option<Vec<u8>> {
+    let thumbnail = image::load_from_memory(bytes)
+        .ok()?
+        .thumbnail(BLUR_SIZE, BLUR_SIZE);
+    let mut png = std::io::Cursor::new(Vec::new());
+    thumbnail
+        .write_to(&mut png, image::ImageOutputFormat::Png)
+        .ok()?;
+    Some(png.into_inner())
+}
+
+/// The zipped report
+///
+/// Holds `system.txt`, `settings.ron`, `log.txt` and, when given, the document,
+/// which should already be anonymized.
+pub fn build_report(
+    settings: &Settings,
+    document: Option<&DocumentFile>,
+) -> Result<Vec<u8>, BugReportError> {
+    let settings = ron::ser::to_string_pretty(
+        &anonymize_settings(settings),
+        ron::ser::PrettyConfig::default(),
+    )?;
+    let mut log = recent_logs().join("\n");
+    log.push('\n');
+
+    let mut entries = vec![
+        ("system.txt".to_string(), system_info().into_bytes()),
+        ("settings.ron".to_string(), settings.into_bytes()),
+        ("log.txt".to_string(), log.into_bytes()),
+    ];
+    if let Some(document) = document {
+        entries.push((
+            format!("document.{}", DOCUMENT_EXTENSION),
+            document.to_ron()?.into_bytes(),
+        ));
+    }
+    Ok(zip(&entries))
+}
+
+/// Write the report to `path`
+#[cfg(not(target_arch = "wasm32"))]
+pub fn save_report(
+    path: &std::path::Path,
+    settings: &Settings,
+    document: Option<&DocumentFile>,
+) -> Result<(), BugReportError> {
+    log::info!("Saving bug report to {}", path.display());
+    std::fs::write(path, build_report(settings, document)?)?;
+    Ok(())
+}
+
+#[cfg(target_arch = "wasm32")]
+pub fn save_report(
+    _path: &std::path::Path,
+    _settings: &Settings,
+    _document: Option<&DocumentFile>,
+) -> Result<(), BugReportError> {
+    log::warn!("Saving files is not supported on the web yet");
+    Err(BugReportError::Unsupported)
+}
+
+/// A zip archive of `entries`, stored without compression
+///
+/// Reports are small and mostly text, so a plain store keeps this short and
+/// every unzip tool can read it.
+fn zip(entries: &[(String, Vec<u8>)]) -> Vec<u8> {
+    // MS-DOS date for 1980-01-01, the earliest a zip can hold
+    const DOS_DATE: u16 = 0x0021;
+
+    let mut out = Vec::new();
+    let mut central = Vec::new();
+    for (name, data) in entries {
+        let offset = out.len() as u32;
+        let crc = crc32fast::hash(data);
+        let mut header = Vec::new();
+        header.extend_from_slice(&20u16.to_le_bytes()); // version needed
+        header.extend_from_slice(&0u16.to_le_bytes()); // flags
+        header.extend_from_slice(&0u16.to_le_bytes()); // method: stored
+        header.extend_from_slice(&0u16.to_le_bytes()); // time
+        header.extend_from_slice(&DOS_DATE.to_le_bytes());
+        header.extend_from_slice(&crc.to_le_bytes());
+        header.extend_from_slice(&(data.len() as u32).to_le_bytes()); // compressed size
+        header.extend_from_slice(&(data.len() as u32).to_le_bytes()); // size
+        header.extend_from_slice(&(name.len() as u16).to_le_bytes());
+        header.extend_from_slice(&0u16.to_le_bytes()); // extra field length
+
+        out.extend_from_slice(b"PK\x03\x04");
+        out.extend_from_slice(&header);
+        out.extend_from_slice(name.as_bytes());
+        out.extend_from_slice(data);
+
+        central.extend_from_slice(b"PK\x01\x02");
+        central.extend_from_slice(&20u16.to_le_bytes()); // version made by
+        central.extend_from_slice(&header);
+        central.extend_from_slice(&0u16.to_le_bytes()); // comment length
+        central.extend_from_slice(&0u16.to_le_bytes()); // disk number
+        central.extend_from_slice(&0u16.to_le_bytes()); // internal attributes
+        central.extend_from_slice(&0u32.to_le_bytes()); // external attributes
+        central.extend_from_slice(&offset.to_le_bytes());
+        central.extend_from_slice(name.as_bytes());
+    }
+
+    let central_offset = out.len() as u32;
+    out.extend_from_slice(&central);
+    out.extend_from_slice(b"PK\x05\x06");
+    out.extend_from_slice(&0u16.to_le_bytes()); // this disk
+    out.extend_from_slice(&0u16.to_le_bytes()); // disk with the central directory
+    out.extend_from_slice(&(entries.len() as u16).to_le_bytes());
+    out.extend_from_slice(&(entries.len() as u16).to_le_bytes());
+    out.extend_from_slice(&(central.len() as u32).to_le_bytes());
+    out.extend_from_slice(&central_offset.to_le_bytes());
+    out.extend_from_slice(&0u16.to_le_bytes()); // comment length
+    out
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::artboard::Artboard;
+    use crate::document::DocumentMetadata;
+    use crate::element::ElementId;
+    use crate::export::{ExportMetadata, encode_png};
+    use egui::{Color32, ColorImage, Pos2, Rect, Vec2};
+
+    fn document_with_image() -> DocumentFile {
+        let png = encode_png(
+            &ColorImage::new([64, 32], Color32::RED),
+            &ExportMetadata::default(),
+        )
+        .unwrap();
+        let mut image =
+            factory::create_image(ElementId::new(1), png, Vec2::new(64.0, 32.0), Pos2::ZERO);
+        image.metadata_mut().name = "Bank statement".to_string();
+        let points = vec![Pos2::ZERO, Pos2::new(10.0, 0.0), Pos2::new(10.0, 10.0)];
+        let shape = factory::create_shape(ElementId::new(2), points, Color32::BLUE);
+
+        DocumentFile {
+            metadata: DocumentMetadata {
+                title: "Taxes".to_string(),
+                author: "Someone".to_string(),
+                ..DocumentMetadata::default()
+            },
+            artboards: vec![Artboard::new(
+                "Home",
+                Rect::from_min_size(Pos2::ZERO, Vec2::splat(100.0)),
+            )],
+            elements: vec![image, shape],
+            ..DocumentFile::default()
+        }
+    }
+
+    #[test]
+    fn test_anonymize_document_strips_or_blurs_images_and_names() {
+        let file = document_with_image();
+
+        let stripped = anonymize_document(&file, ImageRedaction::Strip);
+        assert_eq!(stripped.metadata, DocumentMetadata::default());
+        assert_eq!(stripped.artboards[0].name, "Artboard 1");
+        assert_eq!(stripped.elements.len(), 1);
+        assert_eq!(stripped.elements[0].id(), ElementId::new(2));
+
+        let blurred = anonymize_document(&file, ImageRedaction::Blur);
+        let ElementType::Image(image) = &blurred.elements[0] else {
+            panic!("Blurred image should be kept");
+        };
+        assert!(image.metadata().name.is_empty());
+        assert_eq!(image.size(), Vec2::new(64.0, 32.0));
+        let thumbnail = image::load_from_memory(image.original_data()).unwrap();
+        assert_eq!(
+            (thumbnail.width(), thumbnail.height()),
+            (BLUR_SIZE, BLUR_SIZE / 2)
+        );
+    }
+
+    #[test]
+    fn test_build_report_zips_entries_and_redacts_settings() {
+        crate::log_console::push_record(crate::log_console::LogRecord {
+            time: 0.0,
+            level: log::Level::Info,
+            target: "eframe_paint::app".to_string(),
+            message: "Opened document".to_string(),
+            fields: Vec::new(),
+        });
+        let settings = Settings {
+            scratch_dir: "/home/someone/sketches".to_string(),
+            ..Settings::default()
+        };
+        let report = build_report(&settings, Some(&document_with_image())).unwrap();
+
+        assert!(report.starts_with(b"PK\x03\x04"));
+        let end = &report[report.len() - 22..];
+        assert!(end.starts_with(b"PK\x05\x06"));
+        assert_eq!(u16::from_le_bytes([end[10], end[11]]), 4);
+
+        let text = String::from_utf8_lossy(&report);
+        assert!(text.contains(env!("CARGO_PKG_VERSION")));
+        assert!(text.contains("Opened document"));
+        assert!(text.contains(REDACTED));
+        assert!(!text.contains("/home/someone"));
+    }
+}
@@ -0,0 +1,79 @@
+//! Finding a color across the document and replacing it with another
+//!
+//! Used to re-theme diagrams: every element whose main color is close enough
+//! to the one searched for is changed at once, as one undoable step.
+
+use crate::element::{Element, ElementType};
+use egui::Color32;
+
+/// A color to find, how close counts, and what to replace it with
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct ColorReplacement {
+    pub find: Color32,
+    pub replace: Color32,
+    /// How far each channel, alpha included, may differ and still match
+    pub tolerance: u8,
+}
+
+impl ColorReplacement {
+    /// Whether `color` counts as the one searched for
+    pub fn matches(&self, color: Color32) -> bool {
+        let [r, g, b, a] = self.find.to_srgba_unmultiplied();
+        let [r2, g2, b2, a2] = color.to_srgba_unmultiplied();
+        [(r, r2), (g, g2), (b, b2), (a, a2)]
+            .into_iter()
+            .all(|(x, y)| x.abs_diff(y) <= self.tolerance)
+    }
+
+    /// The elements the replacement would change, skipping locked ones
+    pub fn matching<'a>(
+        &'a self,
+        elements: &'a [ElementType],
+    ) -> impl Iterator<Item = &'a ElementType> + 'a {
+        elements.iter().filter(|element| {
+            !element.metadata().is_locked()
+                && element
+                    .color()
+                    .is_some_and(|color| color != self.replace && self.matches(color))
+        })
+    }
+}
+
+/// The colors used in `elements`, most used first, to pick one to replace
+pub fn document_colors(elements: &[ElementType]) -> Vec<Color32> {
+    let mut counts: Vec<(Color32, usize)> = Vec::new();
+    for color in elements.iter().filter_map(ElementType::color) {
+        match counts.iter_mut().find(|(counted, _)| *counted == color) {
+            Some((_, count)) => *count += 1,
+            None => counts.push((color, 1)),
+        }
+    }
+    // Stable, so equally used colors keep the order they first appear in
+    counts.sort_by_key(|&(_, count)| std::cmp::Reverse(count));
+    counts.into_iter().map(|(color, _)| color).collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_colors_match_within_the_tolerance() {
+        let replacement = ColorReplacement {
+            find: Color32::from_rgb(200, 40, 40),
+            replace: Color32::BLUE,
+            tolerance: 10,
+        };
+        assert!(replacement.matches(Color32::from_rgb(200, 40, 40)));
+        assert!(replacement.matches(Color32::from_rgb(190, 50, 35)));
+        assert!(!replacement.matches(Color32::from_rgb(189, 40, 40)));
+        // A see-through version of the color is a different color
+        assert!(!replacement.matches(Color32::from_rgba_unmultiplied(200, 40, 40, 128)));
+
+        let exact = ColorReplacement {
+            tolerance: 0,
+            ..replacement
+        };
+        assert!(!exact.matches(Color32::from_rgb(201, 40, 40)));
+    }
+}
@@ -1,42 +1,264 @@
-use crate::element::{Element, ElementType};
+use crate::artboard::Artboard;
+use crate::document::{DocumentFile, DocumentMetadata, DocumentProperties};
+use crate::element::{
+    Element, ElementType, MIN_ELEMENT_SIZE, PathNode, PatternFill, PixelRegion, PolygonParams,
+    StrokeStyle, Transform,
+};
+use crate::events::{EditorEvent, EventBus};
+use crate::export_slice::ExportSlice;
+use crate::reference_image::ReferenceImage;
 use crate::renderer::Renderer;
-use crate::state::EditorModel;
-use crate::widgets::resize_handle::Corner;
+use crate::ruler_guides::RulerGuide;
+use crate::settings::UndoGranularity;
+use crate::state::{EditorModel, ElementId};
+use crate::undo_tree::{self, Branch, SessionHistory};
+use crate::units::DocumentUnits;
+use crate::widgets::{Corner, TransformHandle};
 use egui;
 use log;
+use serde::{Deserialize, Deserializer, Serialize};
+use std::collections::BTreeSet;
+use std::sync::{Mutex, PoisonError};
+use std::time::Duration;
+use web_time::Instant;
 
 // Image resizing functionality has been moved to the element implementation
 
-#[derive(Clone, Debug)]
+// How far past the edge of a stroke a click still splits it
+const SPLIT_TOLERANCE: f32 = 4.0;
+
+#[derive(Clone, Debug, Serialize, Deserialize)]
 pub enum Command {
     AddElement {
         element: ElementType,
     },
     RemoveElement {
-        element_id: usize,
+        element_id: ElementId,
         old_element: ElementType, // Store removed element for undo
+        /// Place in the stack and whether it was selected, to put it back as it was
+        #[serde(default)]
+        old_index: Option<usize>,
+        #[serde(default)]
+        was_selected: bool,
     },
     MoveElement {
-        element_id: usize,
+        element_id: ElementId,
         _element_type: String,
-        _old_position: egui::Pos2,
+        old_position: egui::Pos2,
         new_position: egui::Pos2,
     },
     ResizeElement {
-        element_id: usize,
+        element_id: ElementId,
         _element_type: String,
-        _old_rect: egui::Rect,
+        old_rect: egui::Rect,
         new_rect: egui::Rect,
-        _scaling_corner: Corner,
+        #[serde(skip, default = "default_scaling_handle")]
+        _scaling_handle: TransformHandle,
+        #[serde(skip, default = "placeholder_image")]
         _original_image: egui::Image<'static>,
     },
+    RenameElement {
+        element_id: ElementId,
+        old_name: String,
+        new_name: String,
+    },
+    SetDocumentMetadata {
+        old_metadata: DocumentMetadata,
+        new_metadata: DocumentMetadata,
+    },
+    SetDocumentUnits {
+        old_units: DocumentUnits,
+        new_units: DocumentUnits,
+    },
+    /// Resize the canvas and change the resolution or background in one step
+    SetDocumentProperties {
+        old_properties: DocumentProperties,
+        new_properties: DocumentProperties,
+    },
+    /// Set, change or remove the document's reference image
+    SetReferenceImage {
+        old_reference: Option<ReferenceImage>,
+        new_reference: Option<ReferenceImage>,
+    },
+    /// Replace the document's artboards (adding, removing, renaming, resizing, ...)
+    SetArtboards {
+        /// What the change was for, shown in the history
+        #[serde(deserialize_with = "static_description")]
+        description: Description,
+        old_artboards: Vec<Artboard>,
+        new_artboards: Vec<Artboard>,
+    },
+    /// Replace the document's export slices (adding, removing, renaming, resizing, ...)
+    SetExportSlices {
+        /// What the change was for, shown in the history
+        #[serde(deserialize_with = "static_description")]
+        description: Description,
+        old_slices: Vec<ExportSlice>,
+        new_slices: Vec<ExportSlice>,
+    },
+    /// Replace the document's ruler guides (adding, moving, removing, ...)
+    SetRulerGuides {
+        /// What the change was for, shown in the history
+        #[serde(deserialize_with = "static_description")]
+        description: Description,
+        old_guides: Vec<RulerGuide>,
+        new_guides: Vec<RulerGuide>,
+    },
+    /// Move an artboard together with the elements on it
+    MoveArtboard {
+        artboard_id: usize,
+        element_ids: Vec<ElementId>,
+        delta: egui::Vec2,
+    },
+    /// Swap a set of elements for another in one undoable step (slicing, merging, ...)
+    ReplaceElements {
+        /// What the replacement was for, shown in the history
+        #[serde(deserialize_with = "static_description")]
+        description: Description,
+        removed: Vec<ElementType>,
+        added: Vec<ElementType>,
+        /// Place in the stack of each removed element, to put them back where
+        /// they were; empty if that's not known
+        #[serde(default)]
+        old_indices: Vec<usize>,
+        /// Which removed elements were selected, to select them again
+        #[serde(default)]
+        was_selected: Vec<bool>,
+    },
+    /// Change the points of strokes in place (simplifying, smoothing, ...)
+    SetStrokePoints {
+        /// What the change was for, shown in the history
+        #[serde(deserialize_with = "static_description")]
+        description: Description,
+        element_ids: Vec<ElementId>,
+        old_points: Vec<Vec<egui::Pos2>>,
+        new_points: Vec<Vec<egui::Pos2>>,
+    },
+    /// Move the anchor or handles of one node of a path
+    SetPathNode {
+        element_id: ElementId,
+        index: usize,
+        old_node: PathNode,
+        new_node: PathNode,
+    },
+    /// Change the sides, star ratio, corner radius or placement of a polygon shape
+    SetPolygon {
+        element_id: ElementId,
+        old_params: PolygonParams,
+        new_params: PolygonParams,
+    },
+    /// Change the taper, caps, joins or dashes of a stroke or path
+    SetStrokeStyle {
+        element_id: ElementId,
+        old_style: StrokeStyle,
+        new_style: StrokeStyle,
+    },
+    /// Change the text or color of a sticky note
+    SetStickyNote {
+        element_id: ElementId,
+        old_text: String,
+        new_text: String,
+        old_color: egui::Color32,
+        new_color: egui::Color32,
+    },
+    /// Fill a shape with an image pattern, change the pattern, or go back to the solid fill
+    SetPatternFill {
+        element_id: ElementId,
+        old_pattern: Option<PatternFill>,
+        new_pattern: Option<PatternFill>,
+    },
+    /// Paint on an image's pixels, keeping the changed rectangle from before and after
+    PaintPixels {
+        element_id: ElementId,
+        before: PixelRegion,
+        after: PixelRegion,
+    },
+    /// Change where an element's stored geometry is drawn, e.g. turn it
+    SetTransform {
+        element_id: ElementId,
+        old_transform: Transform,
+        new_transform: Transform,
+    },
+    /// Bake the transforms of elements into their points and pixels, each
+    /// keeping its place in the stack
+    FlattenTransforms {
+        old_elements: Vec<ElementType>,
+        new_elements: Vec<ElementType>,
+    },
+    /// Change the numbers shown on step badges (renumbering, reordering, ...)
+    SetStepNumbers {
+        /// What the change was for, shown in the history
+        #[serde(deserialize_with = "static_description")]
+        description: Description,
+        element_ids: Vec<ElementId>,
+        old_numbers: Vec<u32>,
+        new_numbers: Vec<u32>,
+    },
+    /// Change the main color of elements (finding and replacing a color, ...)
+    SetColors {
+        /// What the change was for, shown in the history
+        #[serde(deserialize_with = "static_description")]
+        description: Description,
+        element_ids: Vec<ElementId>,
+        old_colors: Vec<egui::Color32>,
+        new_colors: Vec<egui::Color32>,
+    },
+    /// Several commands applied, and undone, as a single step
+    Batch {
+        /// What the batch was for, shown in the history
+        #[serde(deserialize_with = "static_description")]
+        description: Description,
+        commands: Vec<Command>,
+    },
+    /// Swap the whole document for one saved earlier, e.g. at a checkpoint
+    RestoreDocument {
+        /// What the restore was for, shown in the history
+        #[serde(deserialize_with = "static_description")]
+        description: Description,
+        old_document: Box<DocumentFile>,
+        new_document: Box<DocumentFile>,
+    },
     // Selection commands remain mostly unchanged
-    SelectElement(usize),
-    DeselectElement(usize),
+    SelectElement(ElementId),
+    DeselectElement(ElementId),
     ClearSelection {
-        previous_selection: std::collections::HashSet<usize>, // Store previous selection for undo
+        previous_selection: std::collections::HashSet<ElementId>, // Store previous selection for undo
     },
-    ToggleSelection(usize),
+    ToggleSelection(ElementId),
+}
+
+/// What a step was for, shown in the history
+///
+/// Spelled as an alias so serde doesn't take the field for one borrowed from
+/// the input; [`static_description`] reads it back instead.
+pub type Description = &'static str;
+
+/// Read a description back as the `&'static str` commands carry
+///
+/// Each distinct description is leaked once, so restoring histories over and
+/// over doesn't keep growing memory.
+fn static_description<'de, D: Deserializer<'de>>(
+    deserializer: D,
+) -> Result<&'static str, D::Error> {
+    static DESCRIPTIONS: Mutex<BTreeSet<&'static str>> = Mutex::new(BTreeSet::new());
+    let description = String::deserialize(deserializer)?;
+    let mut descriptions = DESCRIPTIONS.lock().unwrap_or_else(PoisonError::into_inner);
+    if let Some(&known) = descriptions.get(description.as_str()) {
+        return Ok(known);
+    }
+    let leaked: &'static str = Box::leak(description.into_boxed_str());
+    descriptions.insert(leaked);
+    Ok(leaked)
+}
+
+// Resizes read back from a saved history don't need the handle or image they
+// were made with; neither takes part in executing or undoing them
+fn default_scaling_handle() -> TransformHandle {
+    TransformHandle::Corner(Corner::BottomRight)
+}
+
+fn placeholder_image() -> egui::Image<'static> {
+    egui::Image::new((egui::TextureId::default(), egui::Vec2::ZERO))
 }
 
 impl Command {
@@ -47,14 +269,917 @@ impl Command {
         }
     }
 
+    /// Stamp the elements the command adds as [`EditorModel::stamp_element`]
+    /// does, so redoing it adds them just as they were first added
+    pub fn stamp_new_elements(&mut self, editor_model: &EditorModel) {
+        match self {
+            Command::AddElement { element } => editor_model.stamp_element(element),
+            Command::ReplaceElements { added, .. } => {
+                for element in added {
+                    editor_model.stamp_element(element);
+                }
+            }
+            Command::Batch { commands, .. } => {
+                for command in commands {
+                    command.stamp_new_elements(editor_model);
+                }
+            }
+            _ => {}
+        }
+    }
+
+    /// Create a command removing an element, or None if there's no such element
+    ///
+    /// Undo puts the element back in its place in the stack, selected if it was.
+    pub fn new_remove_element(editor_model: &EditorModel, element_id: ElementId) -> Option<Self> {
+        let old_index = editor_model
+            .elements
+            .iter()
+            .position(|element| element.id() == element_id)?;
+        Some(Command::RemoveElement {
+            element_id,
+            old_element: editor_model.elements[old_index].clone(),
+            old_index: Some(old_index),
+            was_selected: editor_model.is_element_selected(element_id),
+        })
+    }
+
+    /// Create a command swapping `removed` for `added` in one step
+    ///
+    /// The added elements take the place in the stack of the first removed
+    /// one. Undo puts the removed elements back where they were, selected if
+    /// they were, or in place of the added ones if they're not in the model yet.
+    pub fn new_replace_elements(
+        editor_model: &EditorModel,
+        description: Description,
+        removed: Vec<ElementType>,
+        added: Vec<ElementType>,
+    ) -> Self {
+        let old_indices = removed
+            .iter()
+            .map(|element| {
+                editor_model
+                    .elements
+                    .iter()
+                    .position(|other| other.id() == element.id())
+            })
+            .collect::<Option<Vec<_>>>()
+            .unwrap_or_default();
+        let was_selected = removed
+            .iter()
+            .map(|element| editor_model.is_element_selected(element.id()))
+            .collect();
+        Command::ReplaceElements {
+            description,
+            removed,
+            added,
+            old_indices,
+            was_selected,
+        }
+    }
+
+    /// Create a new RenameElement command that remembers the element's current name for undo
+    ///
+    /// Returns None if the element doesn't exist or already has the requested name.
+    pub fn new_rename_element(
+        editor_model: &EditorModel,
+        element_id: ElementId,
+        new_name: impl Into<String>,
+    ) -> Option<Self> {
+        let element = editor_model.find_element_by_id(element_id)?;
+        let new_name = new_name.into();
+        if element.name() == new_name {
+            return None;
+        }
+
+        Some(Command::RenameElement {
+            element_id,
+            old_name: element.name().to_string(),
+            new_name,
+        })
+    }
+
+    /// Create a new SetDocumentMetadata command, or None if nothing would change
+    pub fn new_set_document_metadata(
+        editor_model: &EditorModel,
+        new_metadata: DocumentMetadata,
+    ) -> Option<Self> {
+        if editor_model.metadata == new_metadata {
+            return None;
+        }
+
+        Some(Command::SetDocumentMetadata {
+            old_metadata: editor_model.metadata.clone(),
+            new_metadata,
+        })
+    }
+
+    /// Create a new SetDocumentUnits command, or None if nothing would change
+    pub fn new_set_document_units(
+        editor_model: &EditorModel,
+        new_units: DocumentUnits,
+    ) -> Option<Self> {
+        if editor_model.units == new_units {
+            return None;
+        }
+
+        Some(Command::SetDocumentUnits {
+            old_units: editor_model.units,
+            new_units,
+        })
+    }
+
+    /// Create a new SetDocumentProperties command, or None if nothing would change
+    pub fn new_set_document_properties(
+        editor_model: &EditorModel,
+        new_properties: DocumentProperties,
+    ) -> Option<Self> {
+        let old_properties = editor_model.properties();
+        if old_properties == new_properties {
+            return None;
+        }
+
+        Some(Command::SetDocumentProperties {
+            old_properties,
+            new_properties,
+        })
+    }
+
+    /// Create a command putting back `document`, saved earlier, in one step
+    pub fn new_restore_document(
+        editor_model: &EditorModel,
+        description: &'static str,
+        document: DocumentFile,
+    ) -> Self {
+        Command::RestoreDocument {
+            description,
+            old_document: Box::new(DocumentFile::from_model(editor_model)),
+            new_document: Box::new(document),
+        }
+    }
+
+    /// Create a new SetReferenceImage command, or None if nothing would change
+    pub fn new_set_reference_image(
+        editor_model: &EditorModel,
+        new_reference: Option<ReferenceImage>,
+    ) -> Option<Self> {
+        if editor_model.reference_image == new_reference {
+            return None;
+        }
+
+        Some(Command::SetReferenceImage {
+            old_reference: editor_model.reference_image.clone(),
+            new_reference,
+        })
+    }
+
+    /// Create a new SetArtboards command, or None if nothing would change
+    pub fn new_set_artboards(
+        editor_model: &EditorModel,
+        description: &'static str,
+        new_artboards: Vec<Artboard>,
+    ) -> Option<Self> {
+        if editor_model.artboards == new_artboards {
+            return None;
+        }
+
+        Some(Command::SetArtboards {
+            description,
+            old_artboards: editor_model.artboards.clone(),
+            new_artboards,
+        })
+    }
+
+    /// Create a new SetExportSlices command, or None if nothing would change
+    pub fn new_set_export_slices(
+        editor_model: &EditorModel,
+        description: &'static str,
+        new_slices: Vec<ExportSlice>,
+    ) -> Option<Self> {
+        if editor_model.slices == new_slices {
+            return None;
+        }
+
+        Some(Command::SetExportSlices {
+            description,
+            old_slices: editor_model.slices.clone(),
+            new_slices,
+        })
+    }
+
+    /// Create a new SetRulerGuides command, or None if nothing would change
+    pub fn new_set_ruler_guides(
+        editor_model: &EditorModel,
+        description: &'static str,
+        new_guides: Vec<RulerGuide>,
+    ) -> Option<Self> {
+        if editor_model.ruler_guides == new_guides {
+            return None;
+        }
+
+        Some(Command::SetRulerGuides {
+            description,
+            old_guides: editor_model.ruler_guides.clone(),
+            new_guides,
+        })
+    }
+
+    /// Create a command moving an artboard by `delta`, carrying the elements on it along
+    ///
+    /// Returns None if the artboard doesn't exist or the move is empty.
+    pub fn new_move_artboard(
+        editor_model: &EditorModel,
+        artboard_id: usize,
+        delta: egui::Vec2,
+    ) -> Option<Self> {
+        if delta == egui::Vec2::ZERO {
+            return None;
+        }
+        editor_model.find_artboard(artboard_id)?;
+
+        Some(Command::MoveArtboard {
+            artboard_id,
+            element_ids: editor_model.artboard_element_ids(artboard_id),
+            delta,
+        })
+    }
+
+    /// Create a command replacing one node of a path
+    ///
+    /// Returns None if the element isn't a path with that node, or the node wouldn't change.
+    pub fn new_set_path_node(
+        editor_model: &EditorModel,
+        element_id: ElementId,
+        index: usize,
+        new_node: PathNode,
+    ) -> Option<Self> {
+        let Some(ElementType::Path(path)) = editor_model.find_element_by_id(element_id) else {
+            return None;
+        };
+        let old_node = *path.nodes().get(index)?;
+        if old_node == new_node {
+            return None;
+        }
+
+        Some(Command::SetPathNode {
+            element_id,
+            index,
+            old_node,
+            new_node,
+        })
+    }
+
+    /// Create a command regenerating a polygon shape from new parameters
+    ///
+    /// Returns None if the element isn't a polygon shape, or the parameters wouldn't change.
+    pub fn new_set_polygon(
+        editor_model: &EditorModel,
+        element_id: ElementId,
+        new_params: PolygonParams,
+    ) -> Option<Self> {
+        let Some(ElementType::Shape(shape)) = editor_model.find_element_by_id(element_id) else {
+            return None;
+        };
+        let old_params = shape.polygon()?;
+        if old_params == new_params {
+            return None;
+        }
+
+        Some(Command::SetPolygon {
+            element_id,
+            old_params,
+            new_params,
+        })
+    }
+
+    /// Create a command turning an element to `rotation` radians about its center
+    ///
+    /// Returns None if there's no such element, or the rotation wouldn't change.
+    pub fn new_set_rotation(
+        editor_model: &EditorModel,
+        element_id: ElementId,
+        rotation: f32,
+    ) -> Option<Self> {
+        let old_transform = editor_model.find_element_by_id(element_id)?.transform();
+        if old_transform.rotation == rotation {
+            return None;
+        }
+
+        Some(Command::SetTransform {
+            element_id,
+            old_transform,
+            new_transform: Transform {
+                rotation,
+                ..old_transform
+            },
+        })
+    }
+
+    /// Create a command baking the transforms of elements into their stored
+    /// points and pixels, as if they had been drawn where they're shown
+    ///
+    /// Elements with nothing to flatten are left out. Fails if an element is
+    /// missing, none has a transform to flatten, or an image can't be resampled.
+    pub fn new_flatten_transforms(
+        editor_model: &EditorModel,
+        element_ids: &[ElementId],
+    ) -> Result<Self, String> {
+        let mut old_elements = Vec::new();
+        let mut new_elements = Vec::new();
+        for &element_id in element_ids {
+            let element = editor_model
+                .find_element_by_id(element_id)
+                .ok_or_else(|| format!("Element with id {} not found", element_id))?;
+            if !element.can_flatten() {
+                continue;
+            }
+            let mut flattened = element.clone();
+            flattened.flatten_transform()?;
+            old_elements.push(element.clone());
+            new_elements.push(flattened);
+        }
+        if old_elements.is_empty() {
+            return Err("Nothing selected has a transform to flatten".to_string());
+        }
+
+        Ok(Command::FlattenTransforms {
+            old_elements,
+            new_elements,
+        })
+    }
+
+    /// Create a command changing the taper, caps, joins and dashes of a stroke or path
+    ///
+    /// Returns None if the element isn't a stroke or path, or the style wouldn't change.
+    pub fn new_set_stroke_style(
+        editor_model: &EditorModel,
+        element_id: ElementId,
+        new_style: StrokeStyle,
+    ) -> Option<Self> {
+        let old_style = match editor_model.find_element_by_id(element_id)? {
+            ElementType::Stroke(stroke) => stroke.style(),
+            ElementType::Path(path) => path.style(),
+            _ => return None,
+        };
+        if old_style == new_style {
+            return None;
+        }
+
+        Some(Command::SetStrokeStyle {
+            element_id,
+            old_style,
+            new_style,
+        })
+    }
+
+    /// Create a command setting the text and color of a sticky note
+    ///
+    /// Returns None if the element isn't a sticky note, or nothing would change.
+    pub fn new_set_sticky_note(
+        editor_model: &EditorModel,
+        element_id: ElementId,
+        new_text: String,
+        new_color: egui::Color32,
+    ) -> Option<Self> {
+        let Some(ElementType::StickyNote(note)) = editor_model.find_element_by_id(element_id)
+        else {
+            return None;
+        };
+        if note.text() == new_text && note.color() == new_color {
+            return None;
+        }
+
+        Some(Command::SetStickyNote {
+            element_id,
+            old_text: note.text().to_string(),
+            new_text,
+            old_color: note.color(),
+            new_color,
+        })
+    }
+
+    /// Create a command setting the image pattern of a shape, None for its solid fill
+    ///
+    /// Returns None if the element isn't a shape, or the pattern wouldn't change.
+    pub fn new_set_pattern_fill(
+        editor_model: &EditorModel,
+        element_id: ElementId,
+        new_pattern: Option<PatternFill>,
+    ) -> Option<Self> {
+        let Some(ElementType::Shape(shape)) = editor_model.find_element_by_id(element_id) else {
+            return None;
+        };
+        let old_pattern = shape.pattern().cloned();
+        if old_pattern == new_pattern {
+            return None;
+        }
+
+        Some(Command::SetPatternFill {
+            element_id,
+            old_pattern,
+            new_pattern,
+        })
+    }
+
+    /// Create a command numbering the given step badges 1, 2, 3, … in order
+    ///
+    /// IDs that aren't step badges are skipped. Returns None if no badge would change.
+    pub fn new_number_steps(
+        editor_model: &EditorModel,
+        description: &'static str,
+        order: &[ElementId],
+    ) -> Option<Self> {
+        let mut element_ids = Vec::new();
+        let mut old_numbers = Vec::new();
+        let mut new_numbers = Vec::new();
+        let badges = order.iter().filter_map(|&element_id| {
+            match editor_model.find_element_by_id(element_id) {
+                Some(ElementType::StepBadge(badge)) => Some(badge),
+                _ => None,
+            }
+        });
+        for (badge, number) in badges.zip(1..) {
+            if badge.number() != number {
+                element_ids.push(badge.id());
+                old_numbers.push(badge.number());
+                new_numbers.push(number);
+            }
+        }
+        if element_ids.is_empty() {
+            return None;
+        }
+
+        Some(Command::SetStepNumbers {
+            description,
+            element_ids,
+            old_numbers,
+            new_numbers,
+        })
+    }
+
+    /// Create a command replacing a color in every element using it
+    ///
+    /// Locked elements are left alone. Returns None if no element matches.
+    pub fn new_replace_colors(
+        editor_model: &EditorModel,
+        replacement: &crate::color_replace::ColorReplacement,
+    ) -> Option<Self> {
+        let mut element_ids = Vec::new();
+        let mut old_colors = Vec::new();
+        for element in replacement.matching(&editor_model.elements) {
+            element_ids.push(element.id());
+            old_colors.extend(element.color());
+        }
+        if element_ids.is_empty() {
+            return None;
+        }
+
+        Some(Command::SetColors {
+            description: "Replace Colors",
+            new_colors: vec![replacement.replace; element_ids.len()],
+            element_ids,
+            old_colors,
+        })
+    }
+
+    /// Create a command scaling several elements as one, by the box around them
+    ///
+    /// Each element's position and size follow the box from `old_bounds` to
+    /// `new_bounds`. Elements too thin to resize, like straight lines, or that
+    /// would end up smaller than their type allows keep their size and move with
+    /// the box. Returns None if nothing would change.
+    pub fn new_resize_selection(
+        editor_model: &EditorModel,
+        element_ids: &[ElementId],
+        handle: TransformHandle,
+        old_bounds: egui::Rect,
+        new_bounds: egui::Rect,
+    ) -> Option<Self> {
+        let transform = egui::emath::RectTransform::from_to(old_bounds, new_bounds);
+        let commands: Vec<Command> = element_ids
+            .iter()
+            .filter_map(|&element_id| editor_model.find_element_by_id(element_id))
+            .filter_map(|element| {
+                let old_rect = element.rect();
+                let new_rect = transform.transform_rect(old_rect);
+                if new_rect == old_rect {
+                    None
+                } else if old_rect.width().min(old_rect.height()) >= MIN_ELEMENT_SIZE
+                    && new_rect.width().min(new_rect.height())
+                        >= element.resize_constraints().min_size
+                {
+                    Some(Command::ResizeElement {
+                        element_id: element.id(),
+                        _element_type: element.element_type().to_string(),
+                        old_rect,
+                        new_rect,
+                        _scaling_handle: handle,
+                        _original_image: egui::Image::new((
+                            egui::TextureId::default(),
+                            egui::Vec2::new(10.0, 10.0),
+                        )),
+                    })
+                } else {
+                    Some(Command::MoveElement {
+                        element_id: element.id(),
+                        _element_type: element.element_type().to_string(),
+                        old_position: old_rect.min,
+                        new_position: new_rect.center() - old_rect.size() / 2.0,
+                    })
+                }
+            })
+            .collect();
+        if commands.is_empty() {
+            return None;
+        }
+
+        Some(Command::Batch {
+            description: "Resize Selection",
+            commands,
+        })
+    }
+
+    /// Create a command moving, scaling and turning several elements as one,
+    /// e.g. by values typed into the Transform window
+    ///
+    /// The elements scale by `scale` and turn clockwise by `rotation` radians
+    /// about the middle of the box around them, then move by `translation`.
+    /// Elements too thin to scale, like straight lines, or that would end up
+    /// smaller than their type allows keep their size and move along. Returns
+    /// None if nothing would change.
+    pub fn new_transform_selection(
+        editor_model: &EditorModel,
+        element_ids: &[ElementId],
+        translation: egui::Vec2,
+        scale: egui::Vec2,
+        rotation: f32,
+    ) -> Option<Self> {
+        let elements: Vec<&ElementType> = element_ids
+            .iter()
+            .filter_map(|&element_id| editor_model.find_element_by_id(element_id))
+            .collect();
+        let pivot = elements
+            .iter()
+            .map(|element| element.rect())
+            .reduce(egui::Rect::union)?
+            .center();
+        let (sin, cos) = rotation.sin_cos();
+
+        let mut commands = Vec::new();
+        for element in elements {
+            // Where the element's middle goes: scaled and turned about the
+            // pivot, then moved
+            let old_rect = element.rect();
+            let offset = (old_rect.center() - pivot) * scale;
+            let turned = egui::vec2(
+                offset.x * cos - offset.y * sin,
+                offset.x * sin + offset.y * cos,
+            );
+            let center = pivot + turned + translation;
+            let scaled = old_rect.size() * scale;
+            let scales = old_rect.width().min(old_rect.height()) >= MIN_ELEMENT_SIZE
+                && scaled.min_elem() >= element.resize_constraints().min_size;
+
+            // Turn what the resize or move leaves, so undoing the turn keeps it
+            let mut placed = element.clone();
+            if scales {
+                let new_rect = egui::Rect::from_center_size(center, scaled);
+                if new_rect != old_rect {
+                    placed.resize(new_rect).ok()?;
+                    commands.push(Command::ResizeElement {
+                        element_id: element.id(),
+                        _element_type: element.element_type().to_string(),
+                        old_rect,
+                        new_rect,
+                        _scaling_handle: TransformHandle::Corner(Corner::BottomRight),
+                        _original_image: egui::Image::new((
+                            egui::TextureId::default(),
+                            egui::Vec2::new(10.0, 10.0),
+                        )),
+                    });
+                }
+            } else {
+                let new_position = center - old_rect.size() / 2.0;
+                if new_position != old_rect.min {
+                    placed.translate(new_position - old_rect.min).ok()?;
+                    commands.push(Command::MoveElement {
+                        element_id: element.id(),
+                        _element_type: element.element_type().to_string(),
+                        old_position: old_rect.min,
+                        new_position,
+                    });
+                }
+            }
+            if rotation != 0.0 {
+                let old_transform = placed.transform();
+                commands.push(Command::SetTransform {
+                    element_id: element.id(),
+                    old_transform,
+                    new_transform: Transform {
+                        rotation: old_transform.rotation + rotation,
+                        ..old_transform
+                    },
+                });
+            }
+        }
+        if commands.is_empty() {
+            return None;
+        }
+
+        Some(Command::Batch {
+            description: "Transform Selection",
+            commands,
+        })
+    }
+
+    /// Create a command adding an array of copies of the given elements
+    ///
+    /// Returns None if there's nothing to copy or `params` makes no copies.
+    pub fn new_array(
+        editor_model: &EditorModel,
+        element_ids: &[ElementId],
+        params: &crate::array::ArrayParams,
+    ) -> Option<Self> {
+        let copies = crate::array::array_copies(editor_model, element_ids, params, || {
+            editor_model.reserve_id()
+        });
+        if copies.is_empty() {
+            return None;
+        }
+        Some(Command::Batch {
+            description: "Array",
+            commands: copies
+                .into_iter()
+                .map(|element| Command::AddElement { element })
+                .collect(),
+        })
+    }
+
+    /// Create a command deleting a step badge and renumbering the badges after it
+    ///
+    /// Returns None if the element isn't a step badge.
+    pub fn new_remove_step_badge(
+        editor_model: &EditorModel,
+        element_id: ElementId,
+    ) -> Option<Self> {
+        if !matches!(
+            editor_model.find_element_by_id(element_id),
+            Some(ElementType::StepBadge(_))
+        ) {
+            return None;
+        }
+        let remaining: Vec<ElementId> = editor_model
+            .step_badges()
+            .iter()
+            .map(|badge| badge.id())
+            .filter(|&id| id != element_id)
+            .collect();
+
+        let mut commands = vec![Command::new_remove_element(editor_model, element_id)?];
+        commands.extend(Command::new_number_steps(
+            editor_model,
+            "Renumber Steps",
+            &remaining,
+        ));
+        Some(Command::Batch {
+            description: "Delete Step",
+            commands,
+        })
+    }
+
+    /// Create a command joining strokes end to end into a single stroke
+    ///
+    /// The joined stroke takes the color, thickness and name of the earliest
+    /// stroke. Fails unless there are at least two strokes and each one has an
+    /// endpoint within `tolerance` of the growing chain.
+    pub fn new_join_strokes(
+        editor_model: &EditorModel,
+        element_ids: &[ElementId],
+        tolerance: f32,
+    ) -> Result<Self, String> {
+        let mut element_ids = element_ids.to_vec();
+        element_ids.sort_unstable();
+
+        let mut strokes = Vec::new();
+        for &element_id in &element_ids {
+            match editor_model.find_element_by_id(element_id) {
+                Some(ElementType::Stroke(stroke)) => strokes.push(stroke),
+                Some(_) => return Err(format!("Element {} is not a stroke", element_id)),
+                None => return Err(format!("Element with id {} not found", element_id)),
+            }
+        }
+        if strokes.len() < 2 {
+            return Err("Select at least two strokes to join".to_string());
+        }
+
+        // Joined where they're drawn, whatever their transforms
+        let polylines: Vec<Vec<egui::Pos2>> = strokes
+            .iter()
+            .map(|stroke| stroke.flattened().points().to_vec())
+            .collect();
+        let points = crate::geometry::join_polylines(&polylines, tolerance)
+            .ok_or_else(|| "Stroke endpoints are too far apart to join".to_string())?;
+
+        let joined = strokes[0]
+            .flattened()
+            .with_points(editor_model.reserve_id(), points);
+        Ok(Command::new_replace_elements(
+            editor_model,
+            "Join Strokes",
+            strokes
+                .iter()
+                .map(|stroke| ElementType::Stroke((*stroke).clone()))
+                .collect(),
+            vec![ElementType::Stroke(joined)],
+        ))
+    }
+
+    /// Create a command splitting a stroke in two where `pos` is on it
+    ///
+    /// Both pieces keep the stroke's color, thickness and name. Fails if `pos`
+    /// isn't on the stroke's line or is at one of its ends.
+    pub fn new_split_stroke(
+        editor_model: &EditorModel,
+        element_id: ElementId,
+        pos: egui::Pos2,
+    ) -> Result<Self, String> {
+        let stroke = match editor_model.find_element_by_id(element_id) {
+            Some(ElementType::Stroke(stroke)) => stroke,
+            Some(_) => return Err(format!("Element {} is not a stroke", element_id)),
+            None => return Err(format!("Element with id {} not found", element_id)),
+        };
+
+        let flat = stroke.flattened();
+        let tolerance = flat.thickness() / 2.0 + SPLIT_TOLERANCE;
+        let split = crate::geometry::split_polyline_at(flat.points(), pos, tolerance);
+        let Some((first, second)) = split else {
+            return Err("Click on the stroke, away from its ends, to split it".to_string());
+        };
+
+        Ok(Command::new_replace_elements(
+            editor_model,
+            "Split Stroke",
+            vec![ElementType::Stroke(stroke.clone())],
+            [first, second]
+                .into_iter()
+                .map(|points| {
+                    let id = editor_model.reserve_id();
+                    ElementType::Stroke(flat.with_points(id, points))
+                })
+                .collect(),
+        ))
+    }
+
+    /// Create a command replacing strokes with filled shapes of their outlines
+    ///
+    /// Each shape is filled with its stroke's color and keeps its name.
+    pub fn new_outline_strokes(
+        editor_model: &EditorModel,
+        element_ids: &[ElementId],
+    ) -> Result<Self, String> {
+        let mut element_ids = element_ids.to_vec();
+        element_ids.sort_unstable();
+
+        let mut removed = Vec::new();
+        let mut added = Vec::new();
+        for element_id in element_ids {
+            let stroke = match editor_model.find_element_by_id(element_id) {
+                Some(ElementType::Stroke(stroke)) => stroke,
+                Some(_) => return Err(format!("Element {} is not a stroke", element_id)),
+                None => return Err(format!("Element with id {} not found", element_id)),
+            };
+
+            let flat = stroke.flattened();
+            let outline = crate::geometry::outline_polyline(flat.points(), flat.thickness());
+            if outline.len() < 3 {
+                return Err(format!("Stroke {} is too small to outline", element_id));
+            }
+
+            let mut shape = crate::element::factory::create_shape(
+                editor_model.reserve_id(),
+                outline,
+                stroke.color(),
+            );
+            *shape.metadata_mut() = stroke.metadata().clone();
+            removed.push(ElementType::Stroke(stroke.clone()));
+            added.push(shape);
+        }
+        if removed.is_empty() {
+            return Err("Select at least one stroke to outline".to_string());
+        }
+
+        Ok(Command::new_replace_elements(
+            editor_model,
+            "Outline Strokes",
+            removed,
+            added,
+        ))
+    }
+
+    /// Create a command combining two shapes with a boolean operation
+    ///
+    /// The shape further back is the one cut from when subtracting. Each piece
+    /// of the result becomes a shape with the back shape's fill and name, and
+    /// both operands are removed.
+    pub fn new_shape_boolean(
+        editor_model: &EditorModel,
+        element_ids: &[ElementId],
+        op: crate::geometry::BooleanOp,
+    ) -> Result<Self, String> {
+        // In drawing order, back to front
+        let shapes: Vec<_> = editor_model
+            .elements
+            .iter()
+            .filter(|element| element_ids.contains(&element.id()))
+            .collect();
+        let [ElementType::Shape(back), ElementType::Shape(front)] = shapes.as_slice() else {
+            return Err("Select exactly two shapes".to_string());
+        };
+
+        let outlines = crate::geometry::polygon_boolean(
+            back.flattened().points(),
+            front.flattened().points(),
+            op,
+        );
+        if outlines.is_empty() {
+            return Err("The result would be empty".to_string());
+        }
+        let added = outlines
+            .into_iter()
+            .map(|outline| {
+                let mut shape = crate::element::factory::create_shape(
+                    editor_model.reserve_id(),
+                    outline,
+                    back.fill(),
+                );
+                *shape.metadata_mut() = back.metadata().clone();
+                shape
+            })
+            .collect();
+
+        Ok(Command::new_replace_elements(
+            editor_model,
+            match op {
+                crate::geometry::BooleanOp::Union => "Union Shapes",
+                crate::geometry::BooleanOp::Subtract => "Subtract Shapes",
+                crate::geometry::BooleanOp::Intersect => "Intersect Shapes",
+            },
+            vec![
+                ElementType::Shape(back.clone()),
+                ElementType::Shape(front.clone()),
+            ],
+            added,
+        ))
+    }
+
+    /// Create a command changing the points of each stroke with `modify`
+    ///
+    /// Non-stroke IDs are skipped, as are strokes `modify` leaves unchanged.
+    /// Fails if no stroke would change.
+    pub fn new_set_stroke_points(
+        editor_model: &EditorModel,
+        element_ids: &[ElementId],
+        description: &'static str,
+        modify: impl Fn(&[egui::Pos2]) -> Vec<egui::Pos2>,
+    ) -> Result<Self, String> {
+        let mut element_ids = element_ids.to_vec();
+        element_ids.sort_unstable();
+
+        let mut changed_ids = Vec::new();
+        let mut old_points = Vec::new();
+        let mut new_points = Vec::new();
+        for element_id in element_ids {
+            let Some(ElementType::Stroke(stroke)) = editor_model.find_element_by_id(element_id)
+            else {
+                continue;
+            };
+            let points = modify(stroke.points());
+            if points != stroke.points() {
+                changed_ids.push(element_id);
+                old_points.push(stroke.points().to_vec());
+                new_points.push(points);
+            }
+        }
+        if changed_ids.is_empty() {
+            return Err("The selected strokes would not change".to_string());
+        }
+
+        Ok(Command::SetStrokePoints {
+            description,
+            element_ids: changed_ids,
+            old_points,
+            new_points,
+        })
+    }
+
     /// Handle texture invalidation after command execution
     ///
     /// This method leverages the unified Element trait approach for consistent
     /// texture invalidation across all element types.
     pub fn invalidate_textures(&self, renderer: &mut Renderer) {
+        log::trace!(command = self.label(); "Invalidating textures");
         match self {
             Command::AddElement { element } => {
-                log::info!("🧹 Invalidating texture for new element {}", element.id());
                 // Clear any existing texture for this element ID
                 renderer.clear_element_state(element.id());
 
@@ -63,13 +1188,10 @@ impl Command {
                 element_clone.invalidate_texture();
             }
             Command::RemoveElement { element_id, .. } => {
-                log::info!("🧹 Invalidating texture for removed element {}", element_id);
                 // Clean up all texture state for this element
                 renderer.clear_element_state(*element_id);
             }
             Command::ResizeElement { element_id, .. } => {
-                log::info!("🧹 Invalidating texture for resized element {}", element_id);
-
                 // First clear by ID to remove any stale textures
                 renderer.clear_element_state(*element_id);
 
@@ -78,8 +1200,6 @@ impl Command {
                 renderer.clear_all_element_state();
             }
             Command::MoveElement { element_id, .. } => {
-                log::info!("🧹 Invalidating texture for moved element {}", element_id);
-
                 // Clear element state for this specific element
                 renderer.clear_element_state(*element_id);
 
@@ -88,7 +1208,6 @@ impl Command {
                 if let Some(element) = renderer.find_element(*element_id) {
                     // Check element type and apply specific invalidation if needed
                     if element.element_type() == "stroke" {
-                        log::info!("🧹 Extra invalidation for stroke element {}", element_id);
                         renderer.invalidate_texture(*element_id);
                     }
                 } else {
@@ -96,8 +1215,75 @@ impl Command {
                     renderer.clear_all_element_state();
                 }
             }
-            // Selection commands don't need texture invalidation
-            Command::SelectElement(_)
+            Command::RestoreDocument { .. } => {
+                renderer.clear_all_element_state();
+            }
+            Command::ReplaceElements { removed, added, .. } => {
+                for element in removed.iter().chain(added) {
+                    renderer.clear_element_state(element.id());
+                }
+            }
+            Command::SetStrokePoints { element_ids, .. } => {
+                for &element_id in element_ids {
+                    renderer.clear_element_state(element_id);
+                }
+            }
+            Command::SetPathNode { element_id, .. } => {
+                renderer.clear_element_state(*element_id);
+            }
+            Command::SetPolygon { element_id, .. } => {
+                renderer.clear_element_state(*element_id);
+            }
+            Command::SetStrokeStyle { element_id, .. } => {
+                renderer.clear_element_state(*element_id);
+            }
+            Command::SetStickyNote { element_id, .. } => {
+                renderer.clear_element_state(*element_id);
+            }
+            Command::SetPatternFill { element_id, .. } => {
+                renderer.clear_element_state(*element_id);
+            }
+            Command::PaintPixels { element_id, .. } => {
+                renderer.clear_element_state(*element_id);
+            }
+            Command::SetTransform { element_id, .. } => {
+                renderer.clear_element_state(*element_id);
+            }
+            Command::FlattenTransforms { new_elements, .. } => {
+                for element in new_elements {
+                    renderer.clear_element_state(element.id());
+                }
+            }
+            Command::SetStepNumbers { element_ids, .. } => {
+                for &element_id in element_ids {
+                    renderer.clear_element_state(element_id);
+                }
+            }
+            Command::SetColors { element_ids, .. } => {
+                for &element_id in element_ids {
+                    renderer.clear_element_state(element_id);
+                }
+            }
+            Command::Batch { commands, .. } => {
+                for command in commands {
+                    command.invalidate_textures(renderer);
+                }
+            }
+            Command::MoveArtboard { element_ids, .. } => {
+                for &element_id in element_ids {
+                    renderer.clear_element_state(element_id);
+                }
+            }
+            // Metadata and selection commands don't need texture invalidation
+            Command::RenameElement { .. }
+            | Command::SetDocumentMetadata { .. }
+            | Command::SetDocumentUnits { .. }
+            | Command::SetDocumentProperties { .. }
+            | Command::SetReferenceImage { .. }
+            | Command::SetArtboards { .. }
+            | Command::SetExportSlices { .. }
+            | Command::SetRulerGuides { .. }
+            | Command::SelectElement(_)
             | Command::DeselectElement(_)
             | Command::ClearSelection { .. }
             | Command::ToggleSelection(_) => {
@@ -110,38 +1296,146 @@ impl Command {
         renderer.get_ctx().request_repaint();
     }
 
+    /// Where in the document the command made its changes, as the document
+    /// now stands: around the elements it touched, including any it removed
+    ///
+    /// None for changes to the document as a whole and to the selection.
+    pub fn changed_rect(&self, editor_model: &EditorModel) -> Option<egui::Rect> {
+        let current = |element_id| {
+            editor_model
+                .find_element_by_id(element_id)
+                .map(|element| element.obb().bounding_rect())
+        };
+        let stored =
+            |element: &ElementType| current(element.id()).or(Some(element.obb().bounding_rect()));
+        let union = |rects: &mut dyn Iterator<Item = Option<egui::Rect>>| {
+            rects.flatten().reduce(|acc, rect| acc.union(rect))
+        };
+
+        match self {
+            Command::AddElement { element }
+            | Command::RemoveElement {
+                old_element: element,
+                ..
+            } => stored(element),
+            Command::MoveElement { element_id, .. }
+            | Command::ResizeElement { element_id, .. }
+            | Command::RenameElement { element_id, .. }
+            | Command::SetPathNode { element_id, .. }
+            | Command::SetPolygon { element_id, .. }
+            | Command::SetStrokeStyle { element_id, .. }
+            | Command::SetStickyNote { element_id, .. }
+            | Command::SetPatternFill { element_id, .. }
+            | Command::PaintPixels { element_id, .. }
+            | Command::SetTransform { element_id, .. } => current(*element_id),
+            Command::FlattenTransforms {
+                old_elements,
+                new_elements,
+            } => union(&mut old_elements.iter().chain(new_elements).map(stored)),
+            Command::ReplaceElements { removed, added, .. } => {
+                union(&mut removed.iter().chain(added).map(stored))
+            }
+            Command::SetStrokePoints { element_ids, .. }
+            | Command::SetStepNumbers { element_ids, .. }
+            | Command::SetColors { element_ids, .. } => {
+                union(&mut element_ids.iter().map(|&element_id| current(element_id)))
+            }
+            Command::MoveArtboard { artboard_id, .. } => editor_model
+                .find_artboard(*artboard_id)
+                .map(|artboard| artboard.rect),
+            Command::SetExportSlices {
+                old_slices,
+                new_slices,
+                ..
+            } => {
+                // The slices added, removed or changed, where they were and now are
+                let removed = old_slices.iter().filter(|slice| !new_slices.contains(slice));
+                let added = new_slices.iter().filter(|slice| !old_slices.contains(slice));
+                union(&mut removed.chain(added).map(|slice| Some(slice.rect)))
+            }
+            Command::Batch { commands, .. } => union(
+                &mut commands
+                    .iter()
+                    .map(|command| command.changed_rect(editor_model)),
+            ),
+            Command::SetDocumentMetadata { .. }
+            | Command::SetDocumentUnits { .. }
+            | Command::SetDocumentProperties { .. }
+            | Command::SetReferenceImage { .. }
+            | Command::SetArtboards { .. }
+            | Command::SetRulerGuides { .. }
+            | Command::RestoreDocument { .. }
+            | Command::SelectElement(_)
+            | Command::DeselectElement(_)
+            | Command::ClearSelection { .. }
+            | Command::ToggleSelection(_) => None,
+        }
+    }
+
+    /// Short name of what the command does, for the history list and announcements
+    pub fn label(&self) -> &'static str {
+        match self {
+            Command::AddElement { .. } => "Add Element",
+            Command::RemoveElement { .. } => "Remove Element",
+            Command::ResizeElement { .. } => "Resize Element",
+            Command::MoveElement { .. } => "Move Element",
+            Command::RenameElement { .. } => "Rename Element",
+            Command::SetDocumentMetadata { .. } => "Edit Document Info",
+            Command::SetDocumentUnits { .. } => "Change Document Units",
+            Command::SetDocumentProperties { .. } => "Change Document Properties",
+            Command::SetReferenceImage {
+                new_reference: None,
+                ..
+            } => "Remove Reference Image",
+            Command::SetReferenceImage {
+                old_reference: None,
+                ..
+            } => "Set Reference Image",
+            Command::SetReferenceImage { .. } => "Change Reference Image",
+            Command::MoveArtboard { .. } => "Move Artboard",
+            Command::SetPathNode { .. } => "Edit Path Node",
+            Command::SetPolygon { .. } => "Edit Polygon",
+            Command::SetStrokeStyle { .. } => "Stroke Style",
+            Command::SetStickyNote { .. } => "Edit Sticky Note",
+            Command::SetPatternFill { .. } => "Change Fill Pattern",
+            Command::PaintPixels { .. } => "Paint Pixels",
+            Command::SetTransform { .. } => "Transform Element",
+            Command::FlattenTransforms { .. } => "Flatten Transform",
+            Command::ReplaceElements { description, .. }
+            | Command::SetStrokePoints { description, .. }
+            | Command::SetArtboards { description, .. }
+            | Command::SetExportSlices { description, .. }
+            | Command::SetRulerGuides { description, .. }
+            | Command::SetStepNumbers { description, .. }
+            | Command::SetColors { description, .. }
+            | Command::Batch { description, .. }
+            | Command::RestoreDocument { description, .. } => description,
+            Command::SelectElement(_) => "Select Element",
+            Command::DeselectElement(_) => "Deselect Element",
+            Command::ClearSelection { .. } => "Clear Selection",
+            Command::ToggleSelection(_) => "Toggle Selection",
+        }
+    }
+
     /// Execute a command on the editor model
     ///
     /// This method applies the command to the editor model and returns a Result
     /// to indicate success or failure. The result contains an error message if
     /// the command execution failed.
     pub fn execute(&self, editor_model: &mut EditorModel) -> Result<(), String> {
+        log::debug!(command = self.label(); "Executing command");
         match self {
             Command::AddElement { element } => {
-                log::info!(
-                    "💻 Executing AddElement command for element {} (type: {})",
-                    element.id(),
-                    element.element_type()
-                );
-
                 // Clone the element since we need to add it to the editor model
                 let new_element = element.clone();
 
                 // Add the element to the editor model
-                editor_model.add_element(new_element);
+                editor_model.add_element(new_element)?;
                 editor_model.mark_modified();
 
                 Ok(())
             }
-            Command::RemoveElement {
-                element_id,
-                old_element: _,
-            } => {
-                log::info!(
-                    "💻 Executing RemoveElement command for element {}",
-                    element_id
-                );
-
+            Command::RemoveElement { element_id, .. } => {
                 // Remove the element from the editor model
                 if editor_model.remove_element_by_id(*element_id).is_none() {
                     return Err(format!("Element with id {} not found", element_id));
@@ -153,18 +1447,12 @@ impl Command {
             Command::MoveElement {
                 element_id,
                 _element_type,
-                _old_position,
+                old_position: _,
                 new_position,
             } => {
-                log::info!(
-                    "💻 Executing MoveElement command: element={}, new_position={:?}",
-                    element_id,
-                    new_position
-                );
-
-                // Take ownership of the element
-                let mut element = editor_model
-                    .take_element_by_id(*element_id)
+                // Edit the element in place so it keeps its place in the z-order
+                let element = editor_model
+                    .get_element_mut(*element_id)
                     .ok_or_else(|| format!("Element with id {} not found", element_id))?;
 
                 // Translate the element using the Element trait method
@@ -173,8 +1461,6 @@ impl Command {
                 // Invalidate the texture
                 element.invalidate_texture();
 
-                // Return ownership to the model
-                editor_model.add_element(element);
                 editor_model.mark_modified();
 
                 Ok(())
@@ -182,73 +1468,171 @@ impl Command {
             Command::ResizeElement {
                 element_id,
                 _element_type,
-                _old_rect,
+                old_rect: _,
                 new_rect,
-                _scaling_corner,
+                _scaling_handle,
                 _original_image,
             } => {
-                log::info!(
-                    "💻 Executing ResizeElement command for element {}",
-                    element_id
-                );
-
-                // Find the element and get its current rect
-                let current_rect = editor_model
-                    .find_element_by_id(*element_id)
-                    .ok_or_else(|| format!("Element with id {} not found", element_id))?
-                    .rect();
-
-                log::info!(
-                    "📐 Resizing element {} from {:?} to {:?}",
-                    element_id,
-                    current_rect,
-                    new_rect
-                );
-
-                // Take ownership of the element
-                let mut element = editor_model
-                    .take_element_by_id(*element_id)
+                // Edit the element in place so it keeps its place in the z-order
+                let element = editor_model
+                    .get_element_mut(*element_id)
                     .ok_or_else(|| format!("Element with id {} not found", element_id))?;
 
+                // Refuse sizes the element's type doesn't allow; undo skips this
+                // so it can always restore the old size
+                element
+                    .resize_constraints()
+                    .check(element.element_type(), new_rect)?;
+
                 // Resize the element using the Element trait method
                 element.resize(*new_rect)?;
 
                 // Invalidate the texture
                 element.invalidate_texture();
 
-                // Return ownership to the model
-                editor_model.add_element(element);
                 editor_model.mark_modified();
 
                 Ok(())
             }
+            Command::RenameElement {
+                element_id,
+                new_name,
+                ..
+            } => {
+                let element = editor_model
+                    .get_element_mut(*element_id)
+                    .ok_or_else(|| format!("Element with id {} not found", element_id))?;
+                element.metadata_mut().name = new_name.clone();
+
+                editor_model.mark_modified();
+                Ok(())
+            }
+            Command::SetDocumentMetadata { new_metadata, .. } => {
+                editor_model.metadata = new_metadata.clone();
+                editor_model.mark_modified();
+                Ok(())
+            }
+            Command::SetDocumentUnits { new_units, .. } => {
+                editor_model.units = *new_units;
+                editor_model.mark_modified();
+                Ok(())
+            }
+            Command::SetDocumentProperties { new_properties, .. } => {
+                editor_model.set_properties(new_properties.clone());
+                editor_model.mark_modified();
+                Ok(())
+            }
+            Command::RestoreDocument { new_document, .. } => {
+                restore_document(editor_model, new_document);
+                Ok(())
+            }
+            Command::SetReferenceImage { new_reference, .. } => {
+                editor_model.reference_image = new_reference.clone();
+                editor_model.mark_modified();
+                Ok(())
+            }
+            Command::SetArtboards { new_artboards, .. } => {
+                editor_model.artboards = new_artboards.clone();
+                editor_model.mark_modified();
+                Ok(())
+            }
+            Command::SetExportSlices { new_slices, .. } => {
+                editor_model.slices = new_slices.clone();
+                editor_model.mark_modified();
+                Ok(())
+            }
+            Command::SetRulerGuides { new_guides, .. } => {
+                editor_model.ruler_guides = new_guides.clone();
+                editor_model.mark_modified();
+                Ok(())
+            }
+            Command::MoveArtboard {
+                artboard_id,
+                element_ids,
+                delta,
+            } => move_artboard(editor_model, *artboard_id, element_ids, *delta),
+            Command::ReplaceElements { removed, added, .. } => {
+                replace_elements(editor_model, removed, added, &[], &[])
+            }
+            Command::SetStrokePoints {
+                element_ids,
+                new_points,
+                ..
+            } => set_stroke_points(editor_model, element_ids, new_points),
+            Command::SetPathNode {
+                element_id,
+                index,
+                new_node,
+                ..
+            } => set_path_node(editor_model, *element_id, *index, *new_node),
+            Command::SetPolygon {
+                element_id,
+                new_params,
+                ..
+            } => set_polygon(editor_model, *element_id, *new_params),
+            Command::SetStrokeStyle {
+                element_id,
+                new_style,
+                ..
+            } => set_stroke_style(editor_model, *element_id, *new_style),
+            Command::SetStickyNote {
+                element_id,
+                new_text,
+                new_color,
+                ..
+            } => set_sticky_note(editor_model, *element_id, new_text, *new_color),
+            Command::SetPatternFill {
+                element_id,
+                new_pattern,
+                ..
+            } => set_pattern_fill(editor_model, *element_id, new_pattern.clone()),
+            Command::PaintPixels {
+                element_id, after, ..
+            } => write_pixels(editor_model, *element_id, after),
+            Command::SetTransform {
+                element_id,
+                new_transform,
+                ..
+            } => set_transform(editor_model, *element_id, *new_transform),
+            Command::FlattenTransforms { new_elements, .. } => {
+                swap_elements(editor_model, new_elements)
+            }
+            Command::SetStepNumbers {
+                element_ids,
+                new_numbers,
+                ..
+            } => set_step_numbers(editor_model, element_ids, new_numbers),
+            Command::SetColors {
+                element_ids,
+                new_colors,
+                ..
+            } => set_colors(editor_model, element_ids, new_colors),
+            Command::Batch { commands, .. } => {
+                for (index, command) in commands.iter().enumerate() {
+                    if let Err(error) = command.execute(editor_model) {
+                        // Roll back what already ran so a failed batch changes nothing
+                        for done in commands[..index].iter().rev() {
+                            let _ = done.undo(editor_model);
+                        }
+                        return Err(error);
+                    }
+                }
+                Ok(())
+            }
             Command::SelectElement(element_id) => {
-                log::info!(
-                    "💻 Executing SelectElement command for element {}",
-                    element_id
-                );
                 editor_model.select_element(*element_id);
                 Ok(())
             }
             Command::DeselectElement(element_id) => {
-                log::info!(
-                    "💻 Executing DeselectElement command for element {}",
-                    element_id
-                );
                 editor_model.deselect_element(*element_id);
                 Ok(())
             }
             Command::ClearSelection { .. } => {
-                log::info!("💻 Executing ClearSelection command");
                 // The previous selection is already stored in the command
                 editor_model.clear_selection();
                 Ok(())
             }
             Command::ToggleSelection(element_id) => {
-                log::info!(
-                    "💻 Executing ToggleSelection command for element {}",
-                    element_id
-                );
                 editor_model.toggle_selection(*element_id);
                 Ok(())
             }
@@ -261,10 +1645,9 @@ impl Command {
     /// to indicate success or failure. The result contains an error message if
     /// the undo operation failed.
     pub fn undo(&self, editor_model: &mut EditorModel) -> Result<(), String> {
+        log::debug!(command = self.label(); "Undoing command");
         match self {
             Command::AddElement { element } => {
-                log::info!("↩️ Undoing AddElement command for element {}", element.id());
-
                 // Remove the added element
                 if editor_model.remove_element_by_id(element.id()).is_none() {
                     return Err(format!(
@@ -277,39 +1660,36 @@ impl Command {
                 Ok(())
             }
             Command::RemoveElement {
-                element_id: _,
+                element_id,
                 old_element,
+                old_index,
+                was_selected,
             } => {
-                log::info!(
-                    "↩️ Undoing RemoveElement command for element {}",
-                    old_element.id()
-                );
-
-                // Re-add the removed element
-                editor_model.add_element(old_element.clone());
+                // Re-add the removed element where it was, or on top if that's not known
+                let index = old_index.unwrap_or(editor_model.elements.len());
+                editor_model.insert_element(index, old_element.clone())?;
+                if *was_selected {
+                    editor_model.select_element(*element_id);
+                }
                 editor_model.mark_modified();
                 Ok(())
             }
             Command::MoveElement {
                 element_id,
                 _element_type,
-                _old_position,
-                new_position,
+                old_position,
+                new_position: _,
             } => {
-                log::info!("↩️ Undoing MoveElement command for element {}", element_id);
-
-                // Take ownership of the element
-                let mut element = editor_model
-                    .take_element_by_id(*element_id)
+                // Edit the element in place so it keeps its place in the z-order
+                let element = editor_model
+                    .get_element_mut(*element_id)
                     .ok_or_else(|| format!("Element with id {} not found", element_id))?;
 
                 // Get the current position
                 let current_pos = element.rect().min;
 
                 // Calculate the delta to move back to the original position
-                let reverse_delta = current_pos - *new_position;
-
-                log::info!("🔙 Moving element back with delta {:?}", reverse_delta);
+                let reverse_delta = *old_position - current_pos;
 
                 // Translate the element back to its original position
                 element.translate(reverse_delta)?;
@@ -317,8 +1697,6 @@ impl Command {
                 // Invalidate the texture
                 element.invalidate_texture();
 
-                // Return ownership to the model
-                editor_model.add_element(element);
                 editor_model.mark_modified();
 
                 Ok(())
@@ -326,79 +1704,511 @@ impl Command {
             Command::ResizeElement {
                 element_id,
                 _element_type,
-                _old_rect,
-                new_rect,
-                _scaling_corner,
-                _original_image,
+                old_rect,
+                ..
             } => {
-                log::info!(
-                    "↩️ Undoing ResizeElement command for element {}",
-                    element_id
-                );
+                // Edit the element in place so it keeps its place in the z-order
+                let element = editor_model
+                    .get_element_mut(*element_id)
+                    .ok_or_else(|| format!("Element with id {} not found", element_id))?;
+
+                // Resize the element back to its original rectangle
+                element.resize(*old_rect)?;
+
+                // Invalidate the texture
+                element.invalidate_texture();
+
+                editor_model.mark_modified();
+
+                Ok(())
+            }
+            Command::RenameElement {
+                element_id,
+                old_name,
+                ..
+            } => {
+                let element = editor_model
+                    .get_element_mut(*element_id)
+                    .ok_or_else(|| format!("Element with id {} not found", element_id))?;
+                element.metadata_mut().name = old_name.clone();
+
+                editor_model.mark_modified();
+                Ok(())
+            }
+            Command::SetDocumentMetadata { old_metadata, .. } => {
+                editor_model.metadata = old_metadata.clone();
+                editor_model.mark_modified();
+                Ok(())
+            }
+            Command::SetDocumentUnits { old_units, .. } => {
+                editor_model.units = *old_units;
+                editor_model.mark_modified();
+                Ok(())
+            }
+            Command::SetDocumentProperties { old_properties, .. } => {
+                editor_model.set_properties(old_properties.clone());
+                editor_model.mark_modified();
+                Ok(())
+            }
+            Command::RestoreDocument { old_document, .. } => {
+                restore_document(editor_model, old_document);
+                Ok(())
+            }
+            Command::SetReferenceImage { old_reference, .. } => {
+                editor_model.reference_image = old_reference.clone();
+                editor_model.mark_modified();
+                Ok(())
+            }
+            Command::SetArtboards { old_artboards, .. } => {
+                editor_model.artboards = old_artboards.clone();
+                editor_model.mark_modified();
+                Ok(())
+            }
+            Command::SetExportSlices { old_slices, .. } => {
+                editor_model.slices = old_slices.clone();
+                editor_model.mark_modified();
+                Ok(())
+            }
+            Command::SetRulerGuides { old_guides, .. } => {
+                editor_model.ruler_guides = old_guides.clone();
+                editor_model.mark_modified();
+                Ok(())
+            }
+            Command::MoveArtboard {
+                artboard_id,
+                element_ids,
+                delta,
+            } => move_artboard(editor_model, *artboard_id, element_ids, -*delta),
+            Command::ReplaceElements {
+                removed,
+                added,
+                old_indices,
+                was_selected,
+                ..
+            } => replace_elements(editor_model, added, removed, old_indices, was_selected),
+            Command::SetStrokePoints {
+                element_ids,
+                old_points,
+                ..
+            } => set_stroke_points(editor_model, element_ids, old_points),
+            Command::SetPathNode {
+                element_id,
+                index,
+                old_node,
+                ..
+            } => set_path_node(editor_model, *element_id, *index, *old_node),
+            Command::SetPolygon {
+                element_id,
+                old_params,
+                ..
+            } => set_polygon(editor_model, *element_id, *old_params),
+            Command::SetStrokeStyle {
+                element_id,
+                old_style,
+                ..
+            } => set_stroke_style(editor_model, *element_id, *old_style),
+            Command::SetStickyNote {
+                element_id,
+                old_text,
+                old_color,
+                ..
+            } => set_sticky_note(editor_model, *element_id, old_text, *old_color),
+            Command::SetPatternFill {
+                element_id,
+                old_pattern,
+                ..
+            } => set_pattern_fill(editor_model, *element_id, old_pattern.clone()),
+            Command::PaintPixels {
+                element_id, before, ..
+            } => write_pixels(editor_model, *element_id, before),
+            Command::SetTransform {
+                element_id,
+                old_transform,
+                ..
+            } => set_transform(editor_model, *element_id, *old_transform),
+            Command::FlattenTransforms { old_elements, .. } => {
+                swap_elements(editor_model, old_elements)
+            }
+            Command::SetStepNumbers {
+                element_ids,
+                old_numbers,
+                ..
+            } => set_step_numbers(editor_model, element_ids, old_numbers),
+            Command::SetColors {
+                element_ids,
+                old_colors,
+                ..
+            } => set_colors(editor_model, element_ids, old_colors),
+            Command::Batch { commands, .. } => {
+                for command in commands.iter().rev() {
+                    command.undo(editor_model)?;
+                }
+                Ok(())
+            }
+            Command::SelectElement(element_id) => {
+                // Undo a selection by deselecting the element
+                editor_model.deselect_element(*element_id);
+                Ok(())
+            }
+            Command::DeselectElement(element_id) => {
+                // Undo a deselection by selecting the element
+                editor_model.select_element(*element_id);
+                Ok(())
+            }
+            Command::ClearSelection { previous_selection } => {
+                
+                // Restore the previous selection
+                for &element_id in previous_selection.iter() {
+                    editor_model.select_element(element_id);
+                }
+                
+                Ok(())
+            }
+            Command::ToggleSelection(element_id) => {
+                // Undo a toggle by toggling again
+                editor_model.toggle_selection(*element_id);
+                Ok(())
+            }
+        }
+    }
+}
+
+/// Remove `removed` from the model and add `added`, failing before any change
+/// if one of the elements to remove is missing
+///
+/// `indices` are the places in the stack for `added`, if known, and
+/// `selected` which of them to select.
+fn replace_elements(
+    editor_model: &mut EditorModel,
+    removed: &[ElementType],
+    added: &[ElementType],
+    indices: &[usize],
+    selected: &[bool],
+) -> Result<(), String> {
+    if let Some(missing) = removed
+        .iter()
+        .find(|element| !editor_model.contains_element(element.id()))
+    {
+        return Err(format!("Element with id {} not found", missing.id()));
+    }
+    if let Some(taken) = added.iter().find(|element| {
+        editor_model.contains_element(element.id())
+            && !removed.iter().any(|removed| removed.id() == element.id())
+    }) {
+        return Err(format!("Element id {} is already in use", taken.id()));
+    }
+
+    let first = removed
+        .iter()
+        .filter_map(|element| {
+            editor_model
+                .elements
+                .iter()
+                .position(|other| other.id() == element.id())
+        })
+        .min()
+        .unwrap_or(editor_model.elements.len());
+    for element in removed {
+        editor_model.remove_element_by_id(element.id());
+    }
+
+    // Put the added elements at their own places in the stack if those are
+    // known, or together where the first removed one was. Lower places go
+    // first, so each lands where it was.
+    let mut placed: Vec<(usize, &ElementType)> = if indices.len() == added.len() {
+        indices.iter().copied().zip(added).collect()
+    } else {
+        (first..).zip(added).collect()
+    };
+    placed.sort_by_key(|(index, _)| *index);
+    for (index, element) in placed {
+        let mut element = element.clone();
+        element.invalidate_texture();
+        editor_model.insert_element(index, element)?;
+    }
+    for (element, _) in added
+        .iter()
+        .zip(selected)
+        .filter(|(_, selected)| **selected)
+    {
+        editor_model.select_element(element.id());
+    }
+
+    editor_model.mark_modified();
+    Ok(())
+}
+
+/// Put each of `elements` in place of the element with its id, failing before
+/// any change if one of them is missing
+fn swap_elements(editor_model: &mut EditorModel, elements: &[ElementType]) -> Result<(), String> {
+    if let Some(missing) = elements
+        .iter()
+        .find(|element| !editor_model.contains_element(element.id()))
+    {
+        return Err(format!("Element with id {} not found", missing.id()));
+    }
+
+    for element in elements {
+        if let Some(slot) = editor_model.get_element_mut(element.id()) {
+            *slot = element.clone();
+            slot.invalidate_texture();
+        }
+    }
+
+    editor_model.mark_modified();
+    Ok(())
+}
+
+/// Give each stroke in `element_ids` the matching points, failing before any
+/// change if one of them is missing or not a stroke
+fn set_stroke_points(
+    editor_model: &mut EditorModel,
+    element_ids: &[ElementId],
+    points: &[Vec<egui::Pos2>],
+) -> Result<(), String> {
+    if let Some(&missing) = element_ids.iter().find(|&&element_id| {
+        !matches!(
+            editor_model.find_element_by_id(element_id),
+            Some(ElementType::Stroke(_))
+        )
+    }) {
+        return Err(format!("Stroke with id {} not found", missing));
+    }
+
+    for (&element_id, points) in element_ids.iter().zip(points) {
+        if let Some(ElementType::Stroke(stroke)) = editor_model.get_element_mut(element_id) {
+            stroke.set_points(points.clone());
+        }
+    }
+
+    editor_model.mark_modified();
+    Ok(())
+}
+
+fn set_path_node(
+    editor_model: &mut EditorModel,
+    element_id: ElementId,
+    index: usize,
+    node: PathNode,
+) -> Result<(), String> {
+    let Some(ElementType::Path(path)) = editor_model.get_element_mut(element_id) else {
+        return Err(format!("Path with id {} not found", element_id));
+    };
+    path.set_node(index, node)?;
+
+    editor_model.mark_modified();
+    Ok(())
+}
+
+fn set_polygon(
+    editor_model: &mut EditorModel,
+    element_id: ElementId,
+    params: PolygonParams,
+) -> Result<(), String> {
+    let Some(ElementType::Shape(shape)) = editor_model.get_element_mut(element_id) else {
+        return Err(format!("Shape with id {} not found", element_id));
+    };
+    shape.set_polygon(params)?;
+
+    editor_model.mark_modified();
+    Ok(())
+}
+
+fn set_transform(
+    editor_model: &mut EditorModel,
+    element_id: ElementId,
+    transform: Transform,
+) -> Result<(), String> {
+    let element = editor_model
+        .get_element_mut(element_id)
+        .ok_or_else(|| format!("Element with id {} not found", element_id))?;
+    element.set_transform(transform);
+    element.invalidate_texture();
+
+    editor_model.mark_modified();
+    Ok(())
+}
+
+fn set_stroke_style(
+    editor_model: &mut EditorModel,
+    element_id: ElementId,
+    style: StrokeStyle,
+) -> Result<(), String> {
+    match editor_model.get_element_mut(element_id) {
+        Some(ElementType::Stroke(stroke)) => stroke.set_style(style),
+        Some(ElementType::Path(path)) => path.set_style(style),
+        _ => return Err(format!("Stroke or path with id {} not found", element_id)),
+    }
+
+    editor_model.mark_modified();
+    Ok(())
+}
+
+fn set_sticky_note(
+    editor_model: &mut EditorModel,
+    element_id: ElementId,
+    text: &str,
+    color: egui::Color32,
+) -> Result<(), String> {
+    let Some(ElementType::StickyNote(note)) = editor_model.get_element_mut(element_id) else {
+        return Err(format!("Sticky note with id {} not found", element_id));
+    };
+    note.set_text(text.to_string());
+    note.set_color(color);
+
+    editor_model.mark_modified();
+    Ok(())
+}
+
+fn set_pattern_fill(
+    editor_model: &mut EditorModel,
+    element_id: ElementId,
+    pattern: Option<PatternFill>,
+) -> Result<(), String> {
+    let Some(ElementType::Shape(shape)) = editor_model.get_element_mut(element_id) else {
+        return Err(format!("Shape with id {} not found", element_id));
+    };
+    shape.set_pattern(pattern);
+
+    editor_model.mark_modified();
+    Ok(())
+}
+
+fn write_pixels(
+    editor_model: &mut EditorModel,
+    element_id: ElementId,
+    region: &PixelRegion,
+) -> Result<(), String> {
+    let Some(ElementType::Image(image)) = editor_model.get_element_mut(element_id) else {
+        return Err(format!("Image with id {} not found", element_id));
+    };
+    image.write_pixels(region)?;
+
+    editor_model.mark_modified();
+    Ok(())
+}
+
+/// Set the numbers of step badges, failing before any change if one is missing
+fn set_step_numbers(
+    editor_model: &mut EditorModel,
+    element_ids: &[ElementId],
+    numbers: &[u32],
+) -> Result<(), String> {
+    if let Some(&missing) = element_ids.iter().find(|&&element_id| {
+        !matches!(
+            editor_model.find_element_by_id(element_id),
+            Some(ElementType::StepBadge(_))
+        )
+    }) {
+        return Err(format!("Step badge with id {} not found", missing));
+    }
+
+    for (&element_id, &number) in element_ids.iter().zip(numbers) {
+        if let Some(ElementType::StepBadge(badge)) = editor_model.get_element_mut(element_id) {
+            badge.set_number(number);
+        }
+    }
 
-                // Take ownership of the element
-                let mut element = editor_model
-                    .take_element_by_id(*element_id)
-                    .ok_or_else(|| format!("Element with id {} not found", element_id))?;
+    editor_model.mark_modified();
+    Ok(())
+}
 
-                log::info!("🔙 Resizing element back to original rect {:?}", new_rect);
+fn set_colors(
+    editor_model: &mut EditorModel,
+    element_ids: &[ElementId],
+    colors: &[egui::Color32],
+) -> Result<(), String> {
+    if let Some(&missing) = element_ids
+        .iter()
+        .find(|&&element_id| !editor_model.contains_element(element_id))
+    {
+        return Err(format!("Element with id {} not found", missing));
+    }
 
-                // Resize the element back to its original rectangle
-                element.resize(*new_rect)?;
+    for (&element_id, &color) in element_ids.iter().zip(colors) {
+        if let Some(element) = editor_model.get_element_mut(element_id) {
+            element.set_color(color);
+        }
+    }
 
-                // Invalidate the texture
-                element.invalidate_texture();
+    editor_model.mark_modified();
+    Ok(())
+}
 
-                // Return ownership to the model
-                editor_model.add_element(element);
-                editor_model.mark_modified();
+/// Load `document` in place of the open one
+///
+/// Ids keep counting up from where they were, so ids handed out since never
+/// come round again for steps still in the history.
+fn restore_document(editor_model: &mut EditorModel, document: &DocumentFile) {
+    let next_id = editor_model.next_id();
+    editor_model.load_document(document.clone());
+    editor_model.reserve_ids_before(next_id);
+}
 
-                Ok(())
-            }
-            Command::SelectElement(element_id) => {
-                log::info!(
-                    "↩️ Undoing SelectElement command for element {}",
-                    element_id
-                );
-                // Undo a selection by deselecting the element
-                editor_model.deselect_element(*element_id);
-                Ok(())
-            }
-            Command::DeselectElement(element_id) => {
-                log::info!(
-                    "↩️ Undoing DeselectElement command for element {}",
-                    element_id
-                );
-                // Undo a deselection by selecting the element
-                editor_model.select_element(*element_id);
-                Ok(())
-            }
-            Command::ClearSelection { previous_selection } => {
-                log::info!("↩️ Undoing ClearSelection command");
-                
-                // Restore the previous selection
-                for &element_id in previous_selection.iter() {
-                    editor_model.select_element(element_id);
-                }
-                
-                Ok(())
-            }
-            Command::ToggleSelection(element_id) => {
-                log::info!(
-                    "↩️ Undoing ToggleSelection command for element {}",
-                    element_id
-                );
-                // Undo a toggle by toggling again
-                editor_model.toggle_selection(*element_id);
-                Ok(())
-            }
+/// Translate an artboard and the given elements, failing before any change if
+/// the artboard or one of the elements is missing
+fn move_artboard(
+    editor_model: &mut EditorModel,
+    artboard_id: usize,
+    element_ids: &[ElementId],
+    delta: egui::Vec2,
+) -> Result<(), String> {
+    if let Some(&missing) = element_ids
+        .iter()
+        .find(|&&element_id| !editor_model.contains_element(element_id))
+    {
+        return Err(format!("Element with id {} not found", missing));
+    }
+    let artboard = editor_model
+        .artboards
+        .iter_mut()
+        .find(|artboard| artboard.id == artboard_id)
+        .ok_or_else(|| format!("Artboard with id {} not found", artboard_id))?;
+    artboard.rect = artboard.rect.translate(delta);
+
+    for &element_id in element_ids {
+        if let Some(element) = editor_model.get_element_mut(element_id) {
+            element.translate(delta)?;
         }
     }
+
+    editor_model.mark_modified();
+    Ok(())
+}
+
+/// A named snapshot of the whole document, to revert to in one step
+///
+/// Reverting swaps the snapshot in wholesale, rather than undoing every step
+/// since, so it stays quick however long the history has grown.
+#[derive(Clone, Debug, Serialize, Deserialize)]
+pub struct Checkpoint {
+    pub name: String,
+    pub document: DocumentFile,
 }
 
 pub struct CommandHistory {
     undo_stack: Vec<Command>,
     redo_stack: Vec<Command>,
+    // Maximum number of undo steps kept, 0 for unlimited
+    undo_limit: usize,
+    // Told about every command executed, undone or redone
+    events: EventBus,
+    // How many commands go into each undo step, and the longest pause within
+    // a step under UndoGranularity::TimeWindow
+    granularity: UndoGranularity,
+    undo_window: Duration,
+    // Whether the pointer is held, and whether the top step was pushed since it was
+    in_gesture: bool,
+    gesture_step: bool,
+    // When the top step last took a command; None once it may take no more
+    last_command: Option<Instant>,
+    // Whether the top step is a batch made here by grouping, so it can be extended
+    grouped_step: bool,
+    // Whether undone steps are kept as branches rather than dropped by new work
+    keep_branches: bool,
+    branches: Vec<Branch>,
+    // Snapshots of the document to revert to, oldest first
+    checkpoints: Vec<Checkpoint>,
 }
 
 impl CommandHistory {
@@ -406,31 +2216,273 @@ impl CommandHistory {
         Self {
             undo_stack: Vec::new(),
             redo_stack: Vec::new(),
+            undo_limit: 0,
+            events: EventBus::default(),
+            granularity: UndoGranularity::PerStroke,
+            undo_window: Duration::ZERO,
+            in_gesture: false,
+            gesture_step: false,
+            last_command: None,
+            grouped_step: false,
+            keep_branches: false,
+            branches: Vec::new(),
+            checkpoints: Vec::new(),
+        }
+    }
+
+    /// Listen for every command executed, undone or redone from now on
+    pub fn subscribe(&mut self) -> std::sync::mpsc::Receiver<EditorEvent> {
+        self.events.subscribe()
+    }
+
+    /// Limit how many undo steps are kept, dropping the oldest ones beyond it
+    ///
+    /// A limit of 0 keeps every step.
+    pub fn set_undo_limit(&mut self, undo_limit: usize) {
+        self.undo_limit = undo_limit;
+        self.enforce_undo_limit();
+    }
+
+    /// Choose how many commands go into each undo step
+    ///
+    /// `undo_window` is the longest pause between commands grouped under
+    /// [`UndoGranularity::TimeWindow`].
+    pub fn set_undo_granularity(&mut self, granularity: UndoGranularity, undo_window: Duration) {
+        self.granularity = granularity;
+        self.undo_window = undo_window;
+    }
+
+    /// Note that the pointer was pressed on the canvas, starting a new step
+    /// under [`UndoGranularity::PerGesture`]
+    pub fn begin_gesture(&mut self) {
+        self.in_gesture = true;
+        self.gesture_step = false;
+    }
+
+    /// Note that the pointer was let go, closing the gesture's step
+    pub fn end_gesture(&mut self) {
+        self.in_gesture = false;
+        self.gesture_step = false;
+    }
+
+    /// Stop the top undo step taking further commands
+    fn close_step(&mut self) {
+        self.gesture_step = false;
+        self.last_command = None;
+        self.grouped_step = false;
+    }
+
+    /// Whether a command executed at `now` joins the top undo step
+    fn joins_top_step(&self, now: Instant) -> bool {
+        if self.undo_stack.is_empty() {
+            return false;
         }
+        match self.granularity {
+            UndoGranularity::PerStroke => false,
+            UndoGranularity::PerGesture => self.in_gesture && self.gesture_step,
+            UndoGranularity::TimeWindow => self
+                .last_command
+                .is_some_and(|last| now.duration_since(last) <= self.undo_window),
+        }
+    }
+
+    /// Add `command` to the top undo step, which becomes a batch named after
+    /// its first command
+    fn join_top_step(&mut self, command: Command) {
+        if self.grouped_step {
+            if let Some(Command::Batch { commands, .. }) = self.undo_stack.last_mut() {
+                commands.push(command);
+                return;
+            }
+        }
+        let Some(top) = self.undo_stack.pop() else {
+            return;
+        };
+        self.undo_stack.push(Command::Batch {
+            description: top.label(),
+            commands: vec![top, command],
+        });
+        self.grouped_step = true;
+    }
+
+    fn enforce_undo_limit(&mut self) {
+        if self.undo_limit > 0 && self.undo_stack.len() > self.undo_limit {
+            let excess = self.undo_stack.len() - self.undo_limit;
+            self.undo_stack.drain(..excess);
+            undo_tree::drop_oldest(&mut self.branches, excess);
+        }
+    }
+
+    /// Keep undone steps as branches when new work would otherwise drop them
+    ///
+    /// Turning it off drops the branches kept so far.
+    pub fn set_keep_branches(&mut self, keep_branches: bool) {
+        self.keep_branches = keep_branches;
+        if !keep_branches {
+            self.branches.clear();
+        }
+    }
+
+    /// Branches forking from the path to the current state
+    pub fn branches(&self) -> &[Branch] {
+        &self.branches
+    }
+
+    /// Make way for a new step after the current state: the redo steps are
+    /// dropped, or kept as a branch along with the branches forking from them
+    fn leave_redo_steps(&mut self) {
+        let fork = self.undo_stack.len();
+        let forking_later = undo_tree::split_off(&mut self.branches, fork);
+        if !self.keep_branches || self.redo_stack.is_empty() {
+            self.redo_stack.clear();
+            return;
+        }
+        let steps: Vec<Command> = self.redo_stack.drain(..).rev().collect();
+        match Branch::new(fork, &steps, forking_later) {
+            Ok(branch) => self.branches.push(branch),
+            Err(e) => log::error!(error:% = e; "Couldn't keep the undone steps as a branch"),
+        }
+    }
+
+    /// Go to the end of branch `index`: undo back to where it forks, then redo
+    /// its steps
+    ///
+    /// The steps undone on the way are kept as a branch in turn.
+    pub fn switch_branch(
+        &mut self,
+        index: usize,
+        editor_model: &mut EditorModel,
+    ) -> Result<(), String> {
+        let fork = self.branches.get(index).ok_or("No such branch")?.fork;
+        while self.undo_stack.len() > fork {
+            self.undo(editor_model)?;
+        }
+        while self.undo_stack.len() < fork {
+            self.redo(editor_model)?;
+        }
+
+        let branch = self.branches.remove(index);
+        let steps = match branch.unpack() {
+            Ok(steps) => steps,
+            Err(e) => {
+                self.branches.insert(index, branch);
+                return Err(e);
+            }
+        };
+        self.leave_redo_steps();
+        self.redo_stack = steps.into_iter().rev().collect();
+        self.branches
+            .extend(branch.branches.into_iter().map(|mut nested| {
+                nested.fork += fork;
+                nested
+            }));
+        while self.can_redo() {
+            self.redo(editor_model)?;
+        }
+        Ok(())
+    }
+
+    /// Snapshot the document as it stands, under `name`
+    pub fn add_checkpoint(&mut self, name: impl Into<String>, editor_model: &EditorModel) {
+        self.checkpoints.push(Checkpoint {
+            name: name.into(),
+            document: DocumentFile::from_model(editor_model),
+        });
+    }
+
+    /// Checkpoints kept, oldest first
+    pub fn checkpoints(&self) -> &[Checkpoint] {
+        &self.checkpoints
+    }
+
+    pub fn remove_checkpoint(&mut self, index: usize) {
+        if index < self.checkpoints.len() {
+            self.checkpoints.remove(index);
+        }
+    }
+
+    /// The command reverting the document to checkpoint `index`, as one undo step
+    pub fn revert_command(&self, index: usize, editor_model: &EditorModel) -> Option<Command> {
+        let checkpoint = self.checkpoints.get(index)?;
+        Some(Command::new_restore_document(
+            editor_model,
+            "Revert to Checkpoint",
+            checkpoint.document.clone(),
+        ))
+    }
+
+    /// The history, branches included, packed to be saved with the session
+    pub fn to_session(&self, editor_model: &EditorModel) -> Result<String, String> {
+        SessionHistory::new(
+            &self.undo_stack,
+            &self.redo_stack,
+            &self.branches,
+            &self.checkpoints,
+            editor_model.next_id(),
+            crate::id_generator::next_id(),
+        )?
+        .encode()
+    }
+
+    /// Take up a history saved with the session in place of this one
+    ///
+    /// `editor_model` must hold the document as it was when the history was saved.
+    pub fn restore_session(
+        &mut self,
+        text: &str,
+        editor_model: &EditorModel,
+    ) -> Result<(), String> {
+        let mut session = SessionHistory::decode(text)?;
+        let branches = session.branches()?;
+        self.undo_stack = session.undo_stack.into_owned();
+        self.redo_stack = session.redo_stack.into_owned();
+        self.branches = branches;
+        self.checkpoints = session.checkpoints.into_owned();
+        self.close_step();
+        self.enforce_undo_limit();
+
+        editor_model.reserve_ids_before(session.next_element_id);
+        crate::id_generator::reserve_past(session.next_generated_id.saturating_sub(1));
+        Ok(())
     }
 
     /// Execute a command on an EditorModel
     ///
     /// Returns a Result indicating success or failure. If successful, the command
-    /// is added to the undo stack and the redo stack is cleared.
+    /// is added to the undo stack and the redo stack is cleared. Depending on the
+    /// undo granularity, it may join the step on top rather than make its own.
     pub fn execute(
         &mut self,
-        command: Command,
+        mut command: Command,
         editor_model: &mut EditorModel,
     ) -> Result<(), String> {
+        command.stamp_new_elements(editor_model);
+
         // Execute the command and handle any errors
         match command.execute(editor_model) {
             Ok(()) => {
-                // Clear the redo stack when a new command is executed
-                self.redo_stack.clear();
+                // The redo steps no longer follow on; keep them as a branch, if wanted
+                self.leave_redo_steps();
+
+                self.events
+                    .publish(EditorEvent::CommandExecuted(command.clone()));
 
                 // Add the command to the undo stack
-                self.undo_stack.push(command);
+                let now = Instant::now();
+                if self.joins_top_step(now) {
+                    self.join_top_step(command);
+                } else {
+                    self.undo_stack.push(command);
+                    self.grouped_step = false;
+                    self.enforce_undo_limit();
+                }
+                self.gesture_step = self.in_gesture;
+                self.last_command = Some(now);
 
                 Ok(())
             }
             Err(e) => {
-                log::error!("⚠️ Command execution failed: {}", e);
+                log::error!(error:% = e; "Command execution failed");
                 Err(e)
             }
         }
@@ -441,16 +2493,20 @@ impl CommandHistory {
     /// Returns a Result indicating success or failure. If successful, the command
     /// is moved from the undo stack to the redo stack.
     pub fn undo(&mut self, editor_model: &mut EditorModel) -> Result<(), String> {
+        self.close_step();
         if let Some(command) = self.undo_stack.pop() {
             // Try to undo the command
             match command.undo(editor_model) {
                 Ok(()) => {
+                    self.events
+                        .publish(EditorEvent::CommandUndone(command.clone()));
+
                     // Add the command to the redo stack
                     self.redo_stack.push(command);
                     Ok(())
                 }
                 Err(e) => {
-                    log::error!("⚠️ Command undo failed: {}", e);
+                    log::error!(error:% = e; "Command undo failed");
                     // Put the command back on the undo stack if it fails
                     self.undo_stack.push(command);
                     Err(e)
@@ -468,16 +2524,21 @@ impl CommandHistory {
     /// Returns a Result indicating success or failure. If successful, the command
     /// is moved from the redo stack to the undo stack.
     pub fn redo(&mut self, editor_model: &mut EditorModel) -> Result<(), String> {
+        self.close_step();
         if let Some(command) = self.redo_stack.pop() {
             // Try to execute the command
             match command.execute(editor_model) {
                 Ok(()) => {
+                    self.events
+                        .publish(EditorEvent::CommandRedone(command.clone()));
+
                     // Add the command to the undo stack
                     self.undo_stack.push(command);
+                    self.enforce_undo_limit();
                     Ok(())
                 }
                 Err(e) => {
-                    log::error!("⚠️ Command redo failed: {}", e);
+                    log::error!(error:% = e; "Command redo failed");
                     // Put the command back on the redo stack if it fails
                     self.redo_stack.push(command);
                     Err(e)
@@ -490,6 +2551,25 @@ impl CommandHistory {
         }
     }
 
+    /// Forget all undo and redo steps and checkpoints, e.g. when another document is loaded
+    pub fn clear(&mut self) {
+        self.undo_stack.clear();
+        self.redo_stack.clear();
+        self.branches.clear();
+        self.checkpoints.clear();
+        self.close_step();
+    }
+
+    /// Drop the redo steps, the branches and all but the `keep` most recent undo steps,
+    /// handing their memory back
+    pub fn compact(&mut self, keep: usize) {
+        self.redo_stack = Vec::new();
+        self.branches = Vec::new();
+        let excess = self.undo_stack.len().saturating_sub(keep);
+        self.undo_stack.drain(..excess);
+        self.undo_stack.shrink_to_fit();
+    }
+
     pub fn can_undo(&self) -> bool {
         !self.undo_stack.is_empty()
     }
@@ -506,3 +2586,978 @@ impl CommandHistory {
         &self.redo_stack
     }
 }
+
+impl Default for CommandHistory {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::element::factory;
+    use crate::element::MIN_FILLED_SIZE;
+    use crate::widgets::Corner;
+    use egui::{Color32, Pos2};
+
+    fn create_test_model() -> EditorModel {
+        let mut model = EditorModel::new();
+        let points = vec![Pos2::new(10.0, 10.0), Pos2::new(30.0, 30.0)];
+        model
+            .add_element(factory::create_stroke(
+                ElementId::new(1),
+                points,
+                2.0,
+                Color32::RED,
+            ))
+            .unwrap();
+        model
+    }
+
+    #[test]
+    fn test_set_stroke_style_undo_and_svg() {
+        let mut model = create_test_model();
+        let mut history = CommandHistory::new();
+        let dashed = StrokeStyle {
+            dash: crate::element::DashPattern::Dashed,
+            ..Default::default()
+        };
+
+        let command = Command::new_set_stroke_style(&model, ElementId::new(1), dashed).unwrap();
+        history.execute(command, &mut model).unwrap();
+        let style = model
+            .find_element_by_id(ElementId::new(1))
+            .unwrap()
+            .stroke_style();
+        assert_eq!(style, dashed);
+        assert!(Command::new_set_stroke_style(&model, ElementId::new(1), dashed).is_none());
+
+        // Dash lengths scale with the 2px thickness
+        let svg = crate::export::export_svg(
+            &model,
+            &crate::export::ExportSettings::default(),
+            &egui::Context::default(),
+        )
+        .unwrap();
+        assert!(svg.contains(r#"stroke-dasharray="6 4""#));
+
+        history.undo(&mut model).unwrap();
+        let style = model
+            .find_element_by_id(ElementId::new(1))
+            .unwrap()
+            .stroke_style();
+        assert_eq!(style, StrokeStyle::default());
+    }
+
+    #[test]
+    fn test_set_sticky_note_undo_and_svg() {
+        let mut model = EditorModel::new();
+        let mut history = CommandHistory::new();
+        let id = ElementId::new(1);
+        let rect = egui::Rect::from_min_size(egui::Pos2::ZERO, crate::element::DEFAULT_NOTE_SIZE);
+        let yellow = crate::element::NOTE_COLORS[0].1;
+        model
+            .add_element(factory::create_sticky_note(id, rect, String::new(), yellow))
+            .unwrap();
+
+        let pink = crate::element::NOTE_COLORS[1].1;
+        let command =
+            Command::new_set_sticky_note(&model, id, "Fish & chips".to_string(), pink).unwrap();
+        history.execute(command, &mut model).unwrap();
+        let Some(ElementType::StickyNote(note)) = model.find_element_by_id(id) else {
+            panic!("expected a sticky note");
+        };
+        assert_eq!((note.text(), note.color()), ("Fish & chips", pink));
+        assert!(
+            Command::new_set_sticky_note(&model, id, "Fish & chips".to_string(), pink).is_none()
+        );
+
+        let ctx = egui::Context::default();
+        let _ = ctx.run(Default::default(), |_| {});
+        let svg =
+            crate::export::export_svg(&model, &crate::export::ExportSettings::default(), &ctx)
+                .unwrap();
+        assert!(svg.contains(r#"rx="8""#));
+        assert!(svg.contains(">Fish &amp; chips</text>"));
+
+        history.undo(&mut model).unwrap();
+        let Some(ElementType::StickyNote(note)) = model.find_element_by_id(id) else {
+            panic!("expected a sticky note");
+        };
+        assert_eq!((note.text(), note.color()), ("", yellow));
+    }
+
+    #[test]
+    fn test_rename_element_undo_redo() {
+        let mut model = create_test_model();
+        let mut history = CommandHistory::new();
+
+        let command = Command::new_rename_element(&model, ElementId::new(1), "Outline").unwrap();
+        history.execute(command, &mut model).unwrap();
+        assert_eq!(
+            model.find_element_by_id(ElementId::new(1)).unwrap().name(),
+            "Outline"
+        );
+
+        history.undo(&mut model).unwrap();
+        assert_eq!(
+            model.find_element_by_id(ElementId::new(1)).unwrap().name(),
+            ""
+        );
+        assert_eq!(
+            model
+                .find_element_by_id(ElementId::new(1))
+                .unwrap()
+                .display_name(),
+            "stroke 1"
+        );
+
+        history.redo(&mut model).unwrap();
+        assert_eq!(
+            model.find_element_by_id(ElementId::new(1)).unwrap().name(),
+            "Outline"
+        );
+
+        // Renaming to the current name is a no-op
+        assert!(Command::new_rename_element(&model, ElementId::new(1), "Outline").is_none());
+    }
+
+    #[test]
+    fn test_changed_rect_after_undo() {
+        let mut model = create_test_model();
+        let stroke = model.find_element_by_id(ElementId::new(1)).unwrap().clone();
+        let rect = stroke.rect();
+        let remove = Command::new_remove_element(&model, ElementId::new(1)).unwrap();
+        remove.execute(&mut model).unwrap();
+        // Removed elements are found from the copy the command keeps
+        assert_eq!(remove.changed_rect(&model), Some(rect));
+        remove.undo(&mut model).unwrap();
+        assert_eq!(remove.changed_rect(&model), Some(rect));
+
+        let select = Command::SelectElement(ElementId::new(1));
+        let batch = Command::Batch {
+            description: "Remove",
+            commands: vec![select.clone(), remove],
+        };
+        assert_eq!(batch.changed_rect(&model), Some(rect));
+        assert_eq!(select.changed_rect(&model), None);
+    }
+
+    #[test]
+    fn test_undo_limit_drops_oldest_steps() {
+        let mut model = create_test_model();
+        let mut history = CommandHistory::new();
+        history.set_undo_limit(2);
+
+        for name in ["One", "Two", "Three"] {
+            let command = Command::new_rename_element(&model, ElementId::new(1), name).unwrap();
+            history.execute(command, &mut model).unwrap();
+        }
+        assert_eq!(history.undo_stack().len(), 2);
+
+        history.undo(&mut model).unwrap();
+        history.undo(&mut model).unwrap();
+        assert_eq!(
+            model.find_element_by_id(ElementId::new(1)).unwrap().name(),
+            "One"
+        );
+        assert!(!history.can_undo());
+    }
+
+    #[test]
+    fn test_undo_granularity_groups_commands_into_steps() {
+        let mut model = create_test_model();
+        let mut history = CommandHistory::new();
+        let rename = |model: &EditorModel, name| {
+            Command::new_rename_element(model, ElementId::new(1), name).unwrap()
+        };
+
+        // Everything between pressing and letting go of the pointer is one step
+        history.set_undo_granularity(UndoGranularity::PerGesture, Duration::ZERO);
+        history.begin_gesture();
+        for name in ["One", "Two", "Three"] {
+            history.execute(rename(&model, name), &mut model).unwrap();
+        }
+        history.end_gesture();
+        history.execute(rename(&model, "Four"), &mut model).unwrap();
+        assert_eq!(history.undo_stack().len(), 2);
+        assert_eq!(history.undo_stack()[0].label(), "Rename Element");
+
+        history.undo(&mut model).unwrap();
+        history.undo(&mut model).unwrap();
+        assert!(!history.can_undo());
+        history.redo(&mut model).unwrap();
+        assert_eq!(
+            model.find_element_by_id(ElementId::new(1)).unwrap().name(),
+            "Three"
+        );
+
+        // Commands in quick succession share a step; a long enough pause ends it
+        history.set_undo_granularity(UndoGranularity::TimeWindow, Duration::from_secs(60));
+        history.execute(rename(&model, "Five"), &mut model).unwrap();
+        history.execute(rename(&model, "Six"), &mut model).unwrap();
+        assert_eq!(history.undo_stack().len(), 2);
+        history.set_undo_granularity(UndoGranularity::TimeWindow, Duration::ZERO);
+        std::thread::sleep(Duration::from_millis(5));
+        history
+            .execute(rename(&model, "Seven"), &mut model)
+            .unwrap();
+        assert_eq!(history.undo_stack().len(), 3);
+
+        // Each command is its own step by default
+        history.set_undo_granularity(UndoGranularity::PerStroke, Duration::ZERO);
+        history.begin_gesture();
+        history
+            .execute(rename(&model, "Eight"), &mut model)
+            .unwrap();
+        history.execute(rename(&model, "Nine"), &mut model).unwrap();
+        assert_eq!(history.undo_stack().len(), 5);
+    }
+
+    #[test]
+    fn test_undo_tree_keeps_branches_and_saves_them_with_the_session() {
+        let mut model = create_test_model();
+        let mut history = CommandHistory::new();
+        history.set_keep_branches(true);
+        let rename = |model: &EditorModel, name| {
+            Command::new_rename_element(model, ElementId::new(1), name).unwrap()
+        };
+        let name = |model: &EditorModel| {
+            let element = model.find_element_by_id(ElementId::new(1)).unwrap();
+            element.name().to_string()
+        };
+
+        for step in ["One", "Two", "Three"] {
+            history.execute(rename(&model, step), &mut model).unwrap();
+        }
+        history.undo(&mut model).unwrap();
+        history.undo(&mut model).unwrap();
+        history
+            .execute(rename(&model, "Other"), &mut model)
+            .unwrap();
+        assert!(!history.can_redo());
+        assert_eq!(history.branches().len(), 1);
+        assert_eq!(history.branches()[0].fork, 1);
+        assert_eq!(history.branches()[0].len, 2);
+
+        // A new element on the current path, which a restored history must not reuse the id of
+        let stroke = factory::create_stroke(
+            model.reserve_id(),
+            vec![Pos2::new(0.0, 0.0), Pos2::new(5.0, 5.0)],
+            1.0,
+            Color32::BLUE,
+        );
+        let added = stroke.id();
+        history
+            .execute(Command::AddElement { element: stroke }, &mut model)
+            .unwrap();
+
+        // Switching undoes back to the fork and redoes the branch; the steps
+        // undone on the way become a branch in turn
+        history.switch_branch(0, &mut model).unwrap();
+        assert_eq!(name(&model), "Three");
+        assert!(model.find_element_by_id(added).is_none());
+        assert_eq!(history.undo_stack().len(), 3);
+        assert_eq!(history.branches().len(), 1);
+        assert_eq!(history.branches()[0].len, 2);
+
+        // The whole tree is saved with the session and taken up again
+        let session = history.to_session(&model).unwrap();
+        let mut restored_model = EditorModel::new();
+        restored_model.load_document(crate::document::DocumentFile::from_model(&model));
+        let mut restored = CommandHistory::new();
+        restored.set_keep_branches(true);
+        restored.restore_session(&session, &restored_model).unwrap();
+        assert!(restored_model.reserve_id() > added);
+        assert_eq!(restored.undo_stack().len(), 3);
+        restored.switch_branch(0, &mut restored_model).unwrap();
+        assert_eq!(name(&restored_model), "Other");
+        assert!(restored_model.find_element_by_id(added).is_some());
+        restored.undo(&mut restored_model).unwrap();
+        restored.undo(&mut restored_model).unwrap();
+        assert_eq!(name(&restored_model), "One");
+
+        // Without the tree, undone steps are dropped as before
+        restored.set_keep_branches(false);
+        restored
+            .execute(rename(&restored_model, "Last"), &mut restored_model)
+            .unwrap();
+        assert!(restored.branches().is_empty());
+        assert!(!restored.can_redo());
+    }
+
+    #[test]
+    fn test_reverting_to_a_checkpoint_is_one_undo_step() {
+        let mut model = create_test_model();
+        let mut history = CommandHistory::new();
+        history.add_checkpoint("Before strokes", &model);
+
+        for offset in [0.0, 50.0, 100.0] {
+            let stroke = factory::create_stroke(
+                model.reserve_id(),
+                vec![Pos2::new(offset, 0.0), Pos2::new(offset + 20.0, 20.0)],
+                1.0,
+                Color32::BLUE,
+            );
+            history
+                .execute(Command::AddElement { element: stroke }, &mut model)
+                .unwrap();
+        }
+        assert_eq!(model.elements.len(), 4);
+        let next_id = model.next_id();
+
+        let revert = history.revert_command(0, &model).unwrap();
+        assert_eq!(revert.label(), "Revert to Checkpoint");
+        history.execute(revert, &mut model).unwrap();
+        assert_eq!(model.elements.len(), 1);
+        assert_eq!(history.undo_stack().len(), 4);
+        // Ids given out before the revert aren't handed out again
+        assert_eq!(model.next_id(), next_id);
+
+        history.undo(&mut model).unwrap();
+        assert_eq!(model.elements.len(), 4);
+        history.redo(&mut model).unwrap();
+        assert_eq!(model.elements.len(), 1);
+        assert_eq!(history.checkpoints()[0].name, "Before strokes");
+        assert!(history.revert_command(1, &model).is_none());
+    }
+
+    #[test]
+    fn test_compact_keeps_latest_undo_steps() {
+        let mut model = create_test_model();
+        let mut history = CommandHistory::new();
+        for name in ["One", "Two", "Three", "Four"] {
+            let command = Command::new_rename_element(&model, ElementId::new(1), name).unwrap();
+            history.execute(command, &mut model).unwrap();
+        }
+        history.undo(&mut model).unwrap();
+
+        history.compact(1);
+        assert!(!history.can_redo());
+        assert_eq!(history.undo_stack().len(), 1);
+        history.undo(&mut model).unwrap();
+        assert_eq!(
+            model.find_element_by_id(ElementId::new(1)).unwrap().name(),
+            "Two"
+        );
+    }
+
+    #[test]
+    fn test_document_properties_undo_and_save() {
+        let mut model = create_test_model();
+        let mut history = CommandHistory::new();
+        let old_properties = model.properties();
+
+        let mut properties = model.properties();
+        properties.units.dpi = 300.0;
+        properties.background = Some(Color32::BLACK);
+        properties.artboards = vec![Artboard::new(
+            "Canvas",
+            egui::Rect::from_min_size(Pos2::ZERO, egui::vec2(400.0, 300.0)),
+        )];
+        let command = Command::new_set_document_properties(&model, properties.clone()).unwrap();
+        history.execute(command, &mut model).unwrap();
+        assert_eq!(model.properties(), properties);
+        assert!(Command::new_set_document_properties(&model, properties.clone()).is_none());
+
+        // Saved with the document and restored on opening
+        let text = crate::document::DocumentFile::from_model(&model)
+            .to_ron()
+            .unwrap();
+        let mut reopened = EditorModel::new();
+        reopened.load_document(crate::document::DocumentFile::from_ron(&text).unwrap());
+        assert_eq!(reopened.properties(), properties);
+
+        history.undo(&mut model).unwrap();
+        assert_eq!(model.properties(), old_properties);
+    }
+
+    #[test]
+    fn test_join_strokes_undo_restores_originals() {
+        let mut model = EditorModel::new();
+        let (first_id, second_id) = (model.reserve_id(), model.reserve_id());
+        let first = vec![Pos2::new(10.0, 10.0), Pos2::new(30.0, 30.0)];
+        let second = vec![Pos2::new(31.0, 30.0), Pos2::new(60.0, 30.0)];
+        model
+            .add_element(factory::create_stroke(first_id, first, 2.0, Color32::RED))
+            .unwrap();
+        model
+            .add_element(factory::create_stroke(
+                second_id,
+                second,
+                2.0,
+                Color32::BLUE,
+            ))
+            .unwrap();
+
+        let command = Command::new_join_strokes(&model, &[second_id, first_id], 2.0).unwrap();
+        command.execute(&mut model).unwrap();
+        assert_eq!(model.elements.len(), 1);
+        let ElementType::Stroke(joined) = &model.elements[0] else {
+            panic!("expected a stroke");
+        };
+        assert_eq!(joined.points().len(), 3);
+        assert_eq!(joined.color(), Color32::RED);
+
+        command.undo(&mut model).unwrap();
+        assert!(model.contains_element(first_id) && model.contains_element(second_id));
+
+        assert!(Command::new_join_strokes(&model, &[first_id, second_id], 0.5).is_err());
+    }
+
+    #[test]
+    fn test_outline_strokes_replaces_with_shapes() {
+        let mut model = EditorModel::new();
+        let stroke_id = model.reserve_id();
+        let points = vec![Pos2::new(10.0, 10.0), Pos2::new(50.0, 10.0)];
+        model
+            .add_element(factory::create_stroke(
+                stroke_id,
+                points,
+                6.0,
+                Color32::BLUE,
+            ))
+            .unwrap();
+
+        let command = Command::new_outline_strokes(&model, &[stroke_id]).unwrap();
+        command.execute(&mut model).unwrap();
+        let ElementType::Shape(shape) = &model.elements[0] else {
+            panic!("expected a shape");
+        };
+        assert_eq!(shape.fill(), Color32::BLUE);
+        assert!(model.elements[0].hit_test(Pos2::new(30.0, 12.0)));
+        assert!(!model.elements[0].hit_test(Pos2::new(30.0, 14.0)));
+
+        command.undo(&mut model).unwrap();
+        assert!(matches!(model.elements[0], ElementType::Stroke(_)));
+    }
+
+    #[test]
+    fn test_replacing_elements_keeps_their_place_and_selection() {
+        let mut model = EditorModel::new();
+        let ids: Vec<ElementId> = (0..4)
+            .map(|i| {
+                let id = model.reserve_id();
+                let y = i as f32 * 20.0;
+                let points = vec![Pos2::new(10.0, y), Pos2::new(50.0, y)];
+                model
+                    .add_element(factory::create_stroke(id, points, 4.0, Color32::RED))
+                    .unwrap();
+                id
+            })
+            .collect();
+        model.select_element(ids[1]);
+        model.select_element(ids[3]);
+        let order = |model: &EditorModel| -> Vec<ElementId> {
+            model.elements.iter().map(|element| element.id()).collect()
+        };
+
+        // The shapes go where the first stroke outlined was
+        let command = Command::new_outline_strokes(&model, &[ids[3], ids[1]]).unwrap();
+        command.execute(&mut model).unwrap();
+        let after = order(&model);
+        assert_eq!((after[0], after[3]), (ids[0], ids[2]));
+        assert!(matches!(model.elements[1], ElementType::Shape(_)));
+
+        command.undo(&mut model).unwrap();
+        assert_eq!(order(&model), ids);
+        assert!(model.is_element_selected(ids[1]) && model.is_element_selected(ids[3]));
+        assert!(!model.is_element_selected(ids[0]));
+
+        command.execute(&mut model).unwrap();
+        assert_eq!(order(&model), after);
+    }
+
+    #[test]
+    fn test_set_stroke_points_undo_redo() {
+        let mut model = EditorModel::new();
+        let points = vec![
+            Pos2::new(0.0, 0.0),
+            Pos2::new(5.0, 0.1),
+            Pos2::new(10.0, 0.0),
+        ];
+        model
+            .add_element(factory::create_stroke(
+                ElementId::new(1),
+                points.clone(),
+                2.0,
+                Color32::RED,
+            ))
+            .unwrap();
+        let mut history = CommandHistory::new();
+
+        let command = Command::new_set_stroke_points(
+            &model,
+            &[ElementId::new(1)],
+            "Simplify Strokes",
+            |points| crate::geometry::simplify_polyline(points, 1.0),
+        )
+        .unwrap();
+        history.execute(command, &mut model).unwrap();
+        let ElementType::Stroke(stroke) = model.find_element_by_id(ElementId::new(1)).unwrap()
+        else {
+            panic!("expected a stroke");
+        };
+        assert_eq!(stroke.points().len(), 2);
+
+        history.undo(&mut model).unwrap();
+        let ElementType::Stroke(stroke) = model.find_element_by_id(ElementId::new(1)).unwrap()
+        else {
+            panic!("expected a stroke");
+        };
+        assert_eq!(stroke.points(), points.as_slice());
+
+        // Nothing left to simplify at a tighter tolerance
+        assert!(
+            Command::new_set_stroke_points(
+                &model,
+                &[ElementId::new(1)],
+                "Simplify Strokes",
+                |points| { crate::geometry::simplify_polyline(points, 0.01) }
+            )
+            .is_err()
+        );
+    }
+
+    #[test]
+    fn test_move_artboard_carries_its_elements() {
+        let mut model = create_test_model();
+        let outside_id = model.reserve_id();
+        let outside = vec![Pos2::new(200.0, 200.0), Pos2::new(210.0, 210.0)];
+        model
+            .add_element(factory::create_stroke(
+                outside_id,
+                outside,
+                2.0,
+                Color32::RED,
+            ))
+            .unwrap();
+        let artboard = Artboard::new(
+            "Page",
+            egui::Rect::from_min_size(Pos2::ZERO, egui::vec2(100.0, 100.0)),
+        );
+        let artboard_id = artboard.id;
+        model.artboards.push(artboard);
+        let mut history = CommandHistory::new();
+
+        let command =
+            Command::new_move_artboard(&model, artboard_id, egui::vec2(50.0, 0.0)).unwrap();
+        history.execute(command, &mut model).unwrap();
+        assert_eq!(model.artboards[0].rect.min, Pos2::new(50.0, 0.0));
+        assert_eq!(
+            model
+                .find_element_by_id(ElementId::new(1))
+                .unwrap()
+                .rect()
+                .min
+                .x,
+            59.0
+        );
+        assert_eq!(
+            model.find_element_by_id(outside_id).unwrap().rect().min.x,
+            199.0
+        );
+
+        history.undo(&mut model).unwrap();
+        assert_eq!(model.artboards[0].rect.min, Pos2::ZERO);
+        assert_eq!(
+            model
+                .find_element_by_id(ElementId::new(1))
+                .unwrap()
+                .rect()
+                .min
+                .x,
+            9.0
+        );
+    }
+
+    #[test]
+    fn test_set_path_node_moves_anchor_and_undoes() {
+        let mut model = create_test_model();
+        let path_id = model.reserve_id();
+        let nodes = vec![
+            PathNode::corner(Pos2::new(0.0, 0.0)),
+            PathNode::corner(Pos2::new(100.0, 0.0)),
+        ];
+        model
+            .add_element(factory::create_path(
+                path_id,
+                nodes,
+                false,
+                2.0,
+                Color32::RED,
+            ))
+            .unwrap();
+        let mut history = CommandHistory::new();
+
+        let moved = PathNode::corner(Pos2::new(100.0, 0.0)).translated(egui::vec2(0.0, 40.0));
+        assert!(Command::new_set_path_node(&model, path_id, 1, moved).is_some());
+        assert!(Command::new_set_path_node(&model, path_id, 2, moved).is_none());
+        assert!(Command::new_set_path_node(&model, ElementId::new(1), 1, moved).is_none());
+
+        let command = Command::new_set_path_node(&model, path_id, 1, moved).unwrap();
+        history.execute(command, &mut model).unwrap();
+        let node = |model: &EditorModel| match model.find_element_by_id(path_id) {
+            Some(ElementType::Path(path)) => path.nodes()[1],
+            _ => panic!("path missing"),
+        };
+        assert_eq!(node(&model).anchor, Pos2::new(100.0, 40.0));
+
+        history.undo(&mut model).unwrap();
+        assert_eq!(node(&model).anchor, Pos2::new(100.0, 0.0));
+    }
+
+    #[test]
+    fn test_set_polygon_regenerates_outline_and_undoes() {
+        let mut model = create_test_model();
+        let shape_id = model.reserve_id();
+        let params = PolygonParams {
+            center: Pos2::new(100.0, 100.0),
+            radius: egui::vec2(50.0, 50.0),
+            sides: 5,
+            star_ratio: 1.0,
+            corner_radius: 0.0,
+            rotation: 0.0,
+        };
+        model
+            .add_element(factory::create_polygon(shape_id, params, Color32::RED))
+            .unwrap();
+        let mut history = CommandHistory::new();
+
+        let star = PolygonParams {
+            star_ratio: 0.5,
+            ..params
+        };
+        assert!(Command::new_set_polygon(&model, shape_id, params).is_none());
+        assert!(Command::new_set_polygon(&model, ElementId::new(1), star).is_none());
+
+        let command = Command::new_set_polygon(&model, shape_id, star).unwrap();
+        history.execute(command, &mut model).unwrap();
+        let corners = |model: &EditorModel| match model.find_element_by_id(shape_id) {
+            Some(ElementType::Shape(shape)) => shape.points().len(),
+            _ => panic!("shape missing"),
+        };
+        assert_eq!(corners(&model), 10);
+
+        history.undo(&mut model).unwrap();
+        assert_eq!(corners(&model), 5);
+    }
+
+    #[test]
+    fn test_removing_a_step_badge_renumbers_the_rest_in_one_step() {
+        let mut model = create_test_model();
+        let ids: Vec<ElementId> = (1..=3)
+            .map(|number| {
+                let id = model.reserve_id();
+                model
+                    .add_element(factory::create_step_badge(
+                        id,
+                        Pos2::new(number as f32 * 40.0, 0.0),
+                        number,
+                        Color32::RED,
+                    ))
+                    .unwrap();
+                id
+            })
+            .collect();
+        let numbers = |model: &EditorModel| -> Vec<(ElementId, u32)> {
+            model
+                .step_badges()
+                .iter()
+                .map(|badge| (badge.id(), badge.number()))
+                .collect()
+        };
+        let mut history = CommandHistory::new();
+
+        assert!(Command::new_remove_step_badge(&model, ElementId::new(1)).is_none());
+        let command = Command::new_remove_step_badge(&model, ids[0]).unwrap();
+        history.execute(command, &mut model).unwrap();
+        assert_eq!(numbers(&model), vec![(ids[1], 1), (ids[2], 2)]);
+        assert_eq!(model.next_step_number(), 3);
+
+        history.undo(&mut model).unwrap();
+        assert_eq!(numbers(&model), vec![(ids[0], 1), (ids[1], 2), (ids[2], 3)]);
+
+        // Reordering is numbering in a new order
+        let command = Command::new_number_steps(&model, "Reorder Steps", &[ids[2], ids[0], ids[1]]);
+        history.execute(command.unwrap(), &mut model).unwrap();
+        assert_eq!(numbers(&model), vec![(ids[2], 1), (ids[0], 2), (ids[1], 3)]);
+        assert!(Command::new_number_steps(&model, "Reorder Steps", &[ids[2], ids[0]]).is_none());
+    }
+
+    #[test]
+    fn test_resize_selection_scales_elements_together_and_undoes() {
+        let mut model = EditorModel::new();
+        let rects = [
+            egui::Rect::from_min_size(Pos2::new(0.0, 0.0), egui::vec2(100.0, 100.0)),
+            egui::Rect::from_min_size(Pos2::new(100.0, 0.0), egui::vec2(100.0, 50.0)),
+        ];
+        let ids = [1, 2].map(ElementId::new);
+        for (id, rect) in ids.into_iter().zip(rects) {
+            model
+                .add_element(factory::create_image(id, Vec::new(), rect.size(), rect.min))
+                .unwrap();
+        }
+        let rect_of = |model: &EditorModel, id| model.find_element_by_id(id).unwrap().rect();
+        let mut history = CommandHistory::new();
+
+        // Doubling the box from its top left corner doubles positions and sizes
+        let old_bounds = rects[0].union(rects[1]);
+        let new_bounds = egui::Rect::from_min_size(old_bounds.min, old_bounds.size() * 2.0);
+        let handle = TransformHandle::Corner(Corner::BottomRight);
+        assert!(
+            Command::new_resize_selection(&model, &ids, handle, old_bounds, old_bounds).is_none()
+        );
+        let command = Command::new_resize_selection(&model, &ids, handle, old_bounds, new_bounds);
+        history.execute(command.unwrap(), &mut model).unwrap();
+        assert_eq!(
+            rect_of(&model, ids[1]),
+            egui::Rect::from_min_size(Pos2::new(200.0, 0.0), egui::vec2(200.0, 100.0))
+        );
+        assert_eq!(rect_of(&model, ids[0]).size(), egui::vec2(200.0, 200.0));
+
+        history.undo(&mut model).unwrap();
+        assert_eq!([rect_of(&model, ids[0]), rect_of(&model, ids[1])], rects);
+    }
+
+    #[test]
+    fn test_transform_selection_moves_scales_and_turns_in_one_step() {
+        let mut model = EditorModel::new();
+        let rects = [
+            egui::Rect::from_min_size(Pos2::new(0.0, 0.0), egui::vec2(100.0, 100.0)),
+            egui::Rect::from_min_size(Pos2::new(100.0, 0.0), egui::vec2(100.0, 50.0)),
+        ];
+        let ids = [1, 2].map(ElementId::new);
+        for (id, rect) in ids.into_iter().zip(rects) {
+            model
+                .add_element(factory::create_image(id, Vec::new(), rect.size(), rect.min))
+                .unwrap();
+        }
+        let element = |model: &EditorModel, id| model.find_element_by_id(id).unwrap().clone();
+        let mut history = CommandHistory::new();
+
+        let identity = Command::new_transform_selection(
+            &model,
+            &ids,
+            egui::Vec2::ZERO,
+            egui::Vec2::splat(1.0),
+            0.0,
+        );
+        assert!(identity.is_none());
+
+        // Doubled and turned a quarter about the middle of both, then moved right
+        let quarter = std::f32::consts::FRAC_PI_2;
+        let command = Command::new_transform_selection(
+            &model,
+            &ids,
+            egui::vec2(10.0, 0.0),
+            egui::Vec2::splat(2.0),
+            quarter,
+        );
+        history.execute(command.unwrap(), &mut model).unwrap();
+        let square = element(&model, ids[0]);
+        assert!((square.rect().center() - Pos2::new(110.0, -50.0)).length() < 1e-3);
+        assert_eq!(square.rect().size(), egui::vec2(200.0, 200.0));
+        assert_eq!(square.transform().rotation, quarter);
+        assert_eq!(element(&model, ids[1]).transform().rotation, quarter);
+
+        // One undo puts everything back
+        history.undo(&mut model).unwrap();
+        for (id, rect) in ids.into_iter().zip(rects) {
+            assert_eq!(element(&model, id).rect(), rect);
+            assert_eq!(element(&model, id).transform().rotation, 0.0);
+        }
+    }
+
+    #[test]
+    fn test_replace_colors_changes_close_colors_and_skips_locked() {
+        let mut model = create_test_model();
+        let square = vec![Pos2::ZERO, Pos2::new(10.0, 0.0), Pos2::new(10.0, 10.0)];
+        let near_red = Color32::from_rgb(250, 5, 0);
+        let mut locked = factory::create_stroke(ElementId::new(3), Vec::new(), 1.0, Color32::RED);
+        locked
+            .metadata_mut()
+            .set(crate::element::LOCKED_PROPERTY, "true");
+        for element in [
+            factory::create_shape(ElementId::new(2), square, near_red),
+            locked,
+            factory::create_stroke(ElementId::new(4), Vec::new(), 1.0, Color32::GREEN),
+        ] {
+            model.add_element(element).unwrap();
+        }
+        let color_of = |model: &EditorModel, id| {
+            model
+                .find_element_by_id(ElementId::new(id))
+                .unwrap()
+                .color()
+        };
+        let mut history = CommandHistory::new();
+
+        let replacement = crate::color_replace::ColorReplacement {
+            find: Color32::RED,
+            replace: Color32::BLUE,
+            tolerance: 8,
+        };
+        let command = Command::new_replace_colors(&model, &replacement).unwrap();
+        history.execute(command, &mut model).unwrap();
+        let colors = [1, 2, 3, 4].map(|id| color_of(&model, id));
+        let expected = [Color32::BLUE, Color32::BLUE, Color32::RED, Color32::GREEN];
+        assert_eq!(colors, expected.map(Some));
+
+        history.undo(&mut model).unwrap();
+        assert_eq!(color_of(&model, 1), Some(Color32::RED));
+        assert_eq!(color_of(&model, 2), Some(near_red));
+
+        let nothing = crate::color_replace::ColorReplacement {
+            find: Color32::YELLOW,
+            ..replacement
+        };
+        assert!(Command::new_replace_colors(&model, &nothing).is_none());
+    }
+
+    #[test]
+    fn test_array_adds_every_copy_in_one_step() {
+        let mut model = EditorModel::new();
+        let id = model.reserve_id();
+        let rect = egui::Rect::from_min_size(Pos2::ZERO, egui::vec2(40.0, 20.0));
+        model
+            .add_element(factory::create_image(id, Vec::new(), rect.size(), rect.min))
+            .unwrap();
+        let mut history = CommandHistory::new();
+
+        let params = crate::array::ArrayParams {
+            layout: crate::array::ArrayLayout::Grid,
+            columns: 3,
+            rows: 2,
+            spacing: egui::vec2(50.0, 30.0),
+            ..Default::default()
+        };
+        let command = Command::new_array(&model, &[id], &params).unwrap();
+        history.execute(command, &mut model).unwrap();
+        assert_eq!(model.elements.len(), 6);
+        let last = model.elements.last().unwrap();
+        assert_ne!(last.id(), id);
+        assert_eq!(last.rect(), rect.translate(egui::vec2(100.0, 30.0)));
+
+        history.undo(&mut model).unwrap();
+        assert_eq!(model.elements.len(), 1);
+        assert_eq!(model.elements[0].rect(), rect);
+    }
+
+    #[test]
+    fn test_resize_refuses_sizes_the_element_type_does_not_allow() {
+        let mut model = create_test_model();
+        let image_id = model.reserve_id();
+        model
+            .add_element(factory::create_image(
+                image_id,
+                Vec::new(),
+                egui::vec2(100.0, 50.0),
+                Pos2::ZERO,
+            ))
+            .unwrap();
+        let resize = |element_id, old_rect, new_rect| Command::ResizeElement {
+            element_id,
+            _element_type: String::new(),
+            old_rect,
+            new_rect,
+            _scaling_handle: TransformHandle::Corner(Corner::BottomRight),
+            _original_image: egui::Image::new((egui::TextureId::default(), egui::Vec2::ZERO)),
+        };
+        let image = model.find_element_by_id(image_id).unwrap();
+        assert!(image.resize_constraints().keeps_aspect_ratio(false));
+        let image_rect = image.rect();
+
+        // Images stay at least MIN_FILLED_SIZE across; strokes can be thinner
+        let tiny = egui::Rect::from_min_size(Pos2::ZERO, egui::vec2(40.0, 4.0));
+        let err = resize(image_id, image_rect, tiny)
+            .execute(&mut model)
+            .unwrap_err();
+        assert!(err.contains("can't resize the image below"), "{}", err);
+        assert_eq!(
+            model.find_element_by_id(image_id).unwrap().rect(),
+            image_rect
+        );
+
+        let stroke_id = ElementId::new(1);
+        let stroke = model.find_element_by_id(stroke_id).unwrap();
+        assert!(!stroke.resize_constraints().keeps_aspect_ratio(false));
+        let stroke_rect = stroke.rect();
+        let mut history = CommandHistory::new();
+        history
+            .execute(resize(stroke_id, stroke_rect, tiny), &mut model)
+            .unwrap();
+        let resized = model.find_element_by_id(stroke_id).unwrap().rect();
+        assert!(resized.height() < MIN_FILLED_SIZE);
+        history.undo(&mut model).unwrap();
+    }
+
+    #[test]
+    fn test_transforms_survive_resizes_and_flatten_in_one_step() {
+        let mut model = create_test_model();
+        let stroke_id = ElementId::new(1);
+        let points_of = |model: &EditorModel| match model.find_element_by_id(stroke_id) {
+            Some(ElementType::Stroke(stroke)) => stroke.points().to_vec(),
+            _ => panic!("expected a stroke"),
+        };
+        let original = points_of(&model);
+        let mut history = CommandHistory::new();
+
+        // Resizing back and forth never resamples the stored points
+        let mut rect = model.find_element_by_id(stroke_id).unwrap().rect();
+        for step in 1..=20 {
+            let next = egui::Rect::from_min_size(
+                Pos2::new(step as f32, 5.0),
+                egui::vec2(7.0 + step as f32 * 13.0, 3.0 + step as f32 * 0.7),
+            );
+            history
+                .execute(
+                    Command::ResizeElement {
+                        element_id: stroke_id,
+                        _element_type: String::new(),
+                        old_rect: rect,
+                        new_rect: next,
+                        _scaling_handle: TransformHandle::Corner(Corner::BottomRight),
+                        _original_image: placeholder_image(),
+                    },
+                    &mut model,
+                )
+                .unwrap();
+            rect = next;
+        }
+        let transform = model.find_element_by_id(stroke_id).unwrap().transform();
+        for (pos, start) in points_of(&model).iter().zip(&original) {
+            assert!(transform.invert(*pos).distance(*start) < 0.001);
+        }
+
+        let quarter = std::f32::consts::FRAC_PI_2;
+        let rotate = Command::new_set_rotation(&model, stroke_id, quarter).unwrap();
+        history.execute(rotate, &mut model).unwrap();
+        assert!(Command::new_set_rotation(&model, stroke_id, quarter).is_none());
+        let frame = model.find_element_by_id(stroke_id).unwrap().obb();
+        let drawn: Vec<Pos2> = points_of(&model)
+            .into_iter()
+            .map(|pos| frame.to_world(pos))
+            .collect();
+
+        // Flattening bakes the rotation into the points and keeps the stack order
+        let flatten = Command::new_flatten_transforms(&model, &[stroke_id]).unwrap();
+        history.execute(flatten, &mut model).unwrap();
+        assert!(
+            model
+                .find_element_by_id(stroke_id)
+                .unwrap()
+                .transform()
+                .is_identity()
+        );
+        for (pos, expected) in points_of(&model).iter().zip(&drawn) {
+            assert!(pos.distance(*expected) < 0.001);
+        }
+        assert!(Command::new_flatten_transforms(&model, &[stroke_id]).is_err());
+
+        history.undo(&mut model).unwrap();
+        let restored = model.find_element_by_id(stroke_id).unwrap();
+        assert_eq!(restored.transform().rotation, quarter);
+    }
+}
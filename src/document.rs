@@ -0,0 +1,586 @@
+use crate::artboard::Artboard;
+use crate::element::{Element, ElementType, LOCKED_PROPERTY, compute_element_rect, factory};
+use crate::export_slice::ExportSlice;
+use crate::migrations::{FORMAT_VERSION, migrate};
+use crate::reference_image::ReferenceImage;
+use crate::ruler_guides::RulerGuide;
+use crate::state::EditorModel;
+use crate::units::DocumentUnits;
+use serde::{Deserialize, Serialize};
+
+/// File extension used for saved documents
+pub const DOCUMENT_EXTENSION: &str = "paint";
+/// Farthest from the origin anything in a document may be, in document units
+pub const MAX_COORDINATE: f32 = 1.0e6;
+/// Widest or tallest an element may be; drawing a larger one takes a texture
+/// bigger than GPUs allow, and more memory than there is to make it in
+pub const MAX_ELEMENT_SIZE: f32 = 16_384.0;
+/// Largest id anything in a document may have, which leaves room to number
+/// what's added after it, and is as far as ids go on the web
+pub const MAX_ID: usize = u32::MAX as usize;
+
+/// Errors from saving or opening a document file
+#[derive(Debug, thiserror::Error)]
+pub enum DocumentError {
+    #[error("Failed to encode document: {0}")]
+    Encode(#[from] ron::Error),
+    #[error("Not a valid document file: {0}")]
+    Decode(#[from] ron::error::SpannedError),
+    #[error(
+        "This document was saved by a newer version of the app (format {found}, this version \
+         reads up to {supported}); update the app to open it"
+    )]
+    NewerVersion { found: u32, supported: u32 },
+    #[error("Failed to access file: {0}")]
+    Io(#[from] std::io::Error),
+    #[error("Not a supported image: {0}")]
+    Image(#[from] image::ImageError),
+    #[error("Passphrase-protected documents are not supported on the web")]
+    Unsupported,
+    #[error("This document is encrypted; enter its passphrase to open it")]
+    PassphraseRequired,
+    #[error("Wrong passphrase, or the file is damaged")]
+    WrongPassphrase,
+    #[error("Encryption failed: {0}")]
+    Encryption(String),
+    #[error("The document is damaged: {0}")]
+    Damaged(String),
+}
+
+/// Descriptive information about the document as a whole
+///
+/// Exporters use this to prefill the metadata embedded in output files.
+#[derive(Clone, Debug, Default, PartialEq, Serialize, Deserialize)]
+pub struct DocumentMetadata {
+    pub title: String,
+    pub author: String,
+    pub copyright: String,
+}
+
+/// Canvas size, resolution and background, changed together as one step
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
+pub struct DocumentProperties {
+    pub units: DocumentUnits,
+    /// Fixed canvas rectangles; empty for a canvas that fits the content
+    pub artboards: Vec<Artboard>,
+    /// Canvas color for this document, or None to use the one in the preferences
+    pub background: Option<egui::Color32>,
+}
+
+/// Everything stored in a document file
+#[derive(Clone, Debug, Serialize, Deserialize)]
+pub struct DocumentFile {
+    /// Format the file was written in; 0 for files from before it was recorded
+    #[serde(default)]
+    pub version: u32,
+    #[serde(default)]
+    pub metadata: DocumentMetadata,
+    #[serde(default)]
+    pub units: DocumentUnits,
+    #[serde(default)]
+    pub artboards: Vec<Artboard>,
+    pub elements: Vec<ElementType>,
+    #[serde(default)]
+    pub reference_image: Option<ReferenceImage>,
+    #[serde(default)]
+    pub background: Option<egui::Color32>,
+    #[serde(default)]
+    pub slices: Vec<ExportSlice>,
+    #[serde(default)]
+    pub ruler_guides: Vec<RulerGuide>,
+}
+
+/// Just the format version, read before the rest so files from newer versions
+/// get a clear error rather than whatever part of them fails to parse
+#[derive(Deserialize)]
+struct VersionProbe {
+    #[serde(default)]
+    version: u32,
+}
+
+impl Default for DocumentFile {
+    fn default() -> Self {
+        Self {
+            version: FORMAT_VERSION,
+            metadata: DocumentMetadata::default(),
+            units: DocumentUnits::default(),
+            artboards: Vec::new(),
+            elements: Vec::new(),
+            reference_image: None,
+            background: None,
+            slices: Vec::new(),
+            ruler_guides: Vec::new(),
+        }
+    }
+}
+
+impl DocumentFile {
+    pub fn from_model(editor_model: &EditorModel) -> Self {
+        Self {
+            version: FORMAT_VERSION,
+            metadata: editor_model.metadata.clone(),
+            units: editor_model.units,
+            artboards: editor_model.artboards.clone(),
+            elements: editor_model
+                .elements
+                .iter()
+                .cloned()
+                .map(|mut element| {
+                    // Linked images are read from their files again on opening
+                    if let ElementType::Image(image) = &mut element {
+                        image.strip_linked_data();
+                    }
+                    element
+                })
+                .collect(),
+            reference_image: editor_model.reference_image.clone(),
+            background: editor_model.background,
+            slices: editor_model.slices.clone(),
+            ruler_guides: editor_model.ruler_guides.clone(),
+        }
+    }
+
+    pub fn to_ron(&self) -> Result<String, DocumentError> {
+        Ok(ron::ser::to_string(self)?)
+    }
+
+    /// Read a document, bringing files from older versions up to date
+    pub fn from_ron(text: &str) -> Result<Self, DocumentError> {
+        // A probe that doesn't parse leaves the error to the full read
+        let version = ron::from_str::<VersionProbe>(text).map_or(0, |probe| probe.version);
+        if version > FORMAT_VERSION {
+            return Err(DocumentError::NewerVersion {
+                found: version,
+                supported: FORMAT_VERSION,
+            });
+        }
+
+        let mut file: Self = ron::from_str(text)?;
+        let version = file.version;
+        migrate(&mut file, version);
+        file.check_drawable()?;
+        Ok(file)
+    }
+
+    /// Turn away files with anything in them the app can't draw: positions
+    /// that aren't numbers or are too far out, elements too large, opacities
+    /// past 0 to 1, and ids too large to number anything after
+    ///
+    /// A damaged or hostile file would otherwise open, then run out of memory
+    /// or panic making textures for it.
+    fn check_drawable(&self) -> Result<(), DocumentError> {
+        // Comparisons with NaN are false, so it fails these as well as infinities
+        let in_range = |value: f32| value.abs() <= MAX_COORDINATE;
+        let rect_in_range = |rect: egui::Rect| {
+            [rect.min.x, rect.min.y, rect.max.x, rect.max.y]
+                .into_iter()
+                .all(in_range)
+        };
+        let damaged = |what: String| Err(DocumentError::Damaged(what));
+
+        let ids = self.elements.iter().map(|element| element.id().get());
+        let ids = ids.chain(self.artboards.iter().map(|artboard| artboard.id));
+        let ids = ids.chain(self.slices.iter().map(|slice| slice.id));
+        let ids = ids.chain(self.ruler_guides.iter().map(|guide| guide.id));
+        if let Some(id) = ids.into_iter().find(|&id| id > MAX_ID) {
+            return damaged(format!("the id {} is too large", id));
+        }
+
+        for element in &self.elements {
+            // A stroke without points has no bounds, and draws nothing
+            if element.rect() == egui::Rect::NOTHING {
+                continue;
+            }
+            // Bounds skip NaN points, so the points are checked one by one too
+            let points = match element {
+                ElementType::Shape(shape) => shape.points().to_vec(),
+                _ => element
+                    .polyline()
+                    .map(|(points, ..)| points)
+                    .unwrap_or_default(),
+            };
+            let rect = compute_element_rect(element);
+            let points_in_range = points
+                .iter()
+                .all(|point| in_range(point.x) && in_range(point.y));
+            if !(rect_in_range(rect) && points_in_range && element.rotation().is_finite()) {
+                return damaged(format!("element {} is out of bounds", element.id()));
+            }
+            if rect.width() > MAX_ELEMENT_SIZE || rect.height() > MAX_ELEMENT_SIZE {
+                return damaged(format!(
+                    "element {} is too large ({:.0} × {:.0})",
+                    element.id(),
+                    rect.width(),
+                    rect.height()
+                ));
+            }
+        }
+        let rects = self.artboards.iter().map(|artboard| artboard.rect);
+        let rects = rects.chain(self.slices.iter().map(|slice| slice.rect));
+        let rects = rects.chain(self.reference_image.iter().map(|reference| reference.rect));
+        if !rects.into_iter().all(rect_in_range) {
+            return damaged("an artboard, slice or reference image is out of bounds".to_string());
+        }
+        if let Some(reference) = &self.reference_image {
+            if !(0.0..=1.0).contains(&reference.opacity) {
+                return damaged(format!(
+                    "the reference image opacity of {} isn't usable",
+                    reference.opacity
+                ));
+            }
+        }
+        if !self
+            .ruler_guides
+            .iter()
+            .all(|guide| in_range(guide.position))
+        {
+            return damaged("a ruler guide is out of bounds".to_string());
+        }
+        if !(self.units.dpi > 0.0 && in_range(self.units.dpi)) {
+            return damaged(format!(
+                "the resolution of {} DPI isn't usable",
+                self.units.dpi
+            ));
+        }
+        Ok(())
+    }
+
+    /// A new document to draw over the image in `bytes`
+    ///
+    /// The image is placed at its own size with its top-left corner at `origin`,
+    /// locked so it stays put as the background, and an artboard named `name`
+    /// covers it exactly so exports come out the size of the image.
+    pub fn from_image(
+        bytes: Vec<u8>,
+        origin: egui::Pos2,
+        name: &str,
+    ) -> Result<Self, DocumentError> {
+        let image = image::load_from_memory(&bytes)?;
+        let rect = egui::Rect::from_min_size(
+            origin,
+            egui::vec2(image.width() as f32, image.height() as f32),
+        );
+        let mut background = factory::create_image(
+            EditorModel::new().reserve_id(),
+            bytes,
+            rect.size(),
+            rect.min,
+        );
+        let metadata = background.metadata_mut();
+        metadata.name = "Background".to_string();
+        metadata.set(LOCKED_PROPERTY, "true");
+
+        Ok(Self {
+            metadata: DocumentMetadata {
+                title: name.to_string(),
+                ..DocumentMetadata::default()
+            },
+            artboards: vec![Artboard::new(name, rect)],
+            elements: vec![background],
+            ..Self::default()
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::element::ElementId;
+    use crate::element::path::PathNode;
+    use crate::file_handler::decode_document;
+    use crate::ruler_guides::GuideAxis;
+    use crate::test_support::TestHarness;
+    use egui::{Color32, Pos2, Rect, pos2, vec2};
+
+    #[test]
+    fn test_document_file_roundtrip() {
+        let mut model = EditorModel::new();
+        model.metadata.title = "Sketch".to_string();
+        let points = vec![Pos2::new(1.0, 2.0), Pos2::new(3.0, 4.0)];
+        model
+            .add_element(factory::create_stroke(
+                ElementId::new(7),
+                points,
+                2.0,
+                Color32::RED,
+            ))
+            .unwrap();
+
+        let text = DocumentFile::from_model(&model).to_ron().unwrap();
+        // Ids are saved as plain numbers
+        assert!(text.contains("id:7"));
+        let file = DocumentFile::from_ron(&text).unwrap();
+        assert_eq!(file.metadata.title, "Sketch");
+        assert_eq!(file.elements.len(), 1);
+        assert_eq!(file.elements[0].id(), ElementId::new(7));
+
+        assert!(matches!(
+            DocumentFile::from_ron("not a document"),
+            Err(DocumentError::Decode(_))
+        ));
+    }
+
+    #[test]
+    fn test_document_versions() {
+        let text = DocumentFile::default().to_ron().unwrap();
+        assert!(text.starts_with(&format!("(version:{}", FORMAT_VERSION)));
+
+        // Files from before the version was recorded still load
+        let old = DocumentFile::from_ron("(elements:[])").unwrap();
+        assert_eq!(old.version, FORMAT_VERSION);
+
+        // Newer files are turned away before their unknown contents trip the parser
+        let newer = format!("(version:{}, elements:[Hologram(())])", FORMAT_VERSION + 1);
+        assert!(matches!(
+            DocumentFile::from_ron(&newer),
+            Err(DocumentError::NewerVersion { found, .. }) if found == FORMAT_VERSION + 1
+        ));
+    }
+
+    #[test]
+    fn test_documents_that_cant_be_drawn_are_turned_away() {
+        let text = "(elements:[Stroke((id:7,points:[(x:1.0,y:2.0),(x:3.0,y:4.0)],\
+                    color:((255,0,0,255)),thickness:2.0))])";
+        assert!(DocumentFile::from_ron(text).is_ok());
+        // A stroke without points has no bounds to check, but is harmless
+        let empty = text.replace("(x:1.0,y:2.0),(x:3.0,y:4.0)", "");
+        assert!(DocumentFile::from_ron(&empty).is_ok());
+
+        for (from, to) in [
+            ("x:3.0", "x:NaN"),
+            ("thickness:2.0", "thickness:inf"),
+            ("x:3.0", "x:20000.0"),
+            ("id:7", "id:4294967296"),
+        ] {
+            let damaged = text.replace(from, to);
+            assert!(
+                matches!(
+                    DocumentFile::from_ron(&damaged),
+                    Err(DocumentError::Damaged(_))
+                ),
+                "{} opened",
+                to
+            );
+        }
+
+        let see_through = "(elements:[],reference_image:Some((data:[],\
+                           rect:(min:(x:0.0,y:0.0),max:(x:8.0,y:8.0)),opacity:-1.0,tiled:false)))";
+        assert!(matches!(
+            DocumentFile::from_ron(see_through),
+            Err(DocumentError::Damaged(_))
+        ));
+    }
+
+    #[test]
+    fn test_document_from_image_is_sized_to_it() {
+        let mut png = Vec::new();
+        image::RgbaImage::new(64, 48)
+            .write_to(
+                &mut std::io::Cursor::new(&mut png),
+                image::ImageOutputFormat::Png,
+            )
+            .unwrap();
+
+        let file = DocumentFile::from_image(png, Pos2::new(10.0, 20.0), "photo").unwrap();
+        let rect = egui::Rect::from_min_size(Pos2::new(10.0, 20.0), egui::vec2(64.0, 48.0));
+        assert_eq!(file.artboards.len(), 1);
+        assert_eq!(file.artboards[0].name, "photo");
+        assert_eq!(file.artboards[0].rect, rect);
+        assert_eq!(file.elements.len(), 1);
+        assert_eq!(file.elements[0].rect(), rect);
+        assert!(file.elements[0].metadata().is_locked());
+
+        assert!(matches!(
+            DocumentFile::from_image(b"not an image".to_vec(), Pos2::ZERO, "bad"),
+            Err(DocumentError::Image(_))
+        ));
+    }
+
+    // Opening damaged and hostile document files
+    //
+    // A seeded generator corrupts a document file with every kind of element
+    // in it: bytes changed, cut out or repeated, and numbers swapped for
+    // extremes. Whatever comes of it must either fail to open with a
+    // DocumentError or open and show without panicking. The fuzz targets in
+    // `fuzz/` search the same way with coverage guidance; this runs a fixed
+    // set of cases with the other tests.
+    /// Corrupted files tried
+    const CASES: u64 = 2000;
+    /// Most changes made to one file
+    const MAX_CHANGES: usize = 4;
+    /// Numbers swapped into files, at and past the limits of what they're read as
+    const EXTREMES: [&str; 12] = [
+        "0",
+        "-1",
+        "-0.0",
+        "1e38",
+        "-1e38",
+        "3.4e39",
+        "NaN",
+        "inf",
+        "-inf",
+        "4294967296",
+        "18446744073709551615",
+        "99999999999999999999999",
+    ];
+
+    /// A small PNG, for the image element and the reference image
+    fn png() -> Vec<u8> {
+        let mut png = Vec::new();
+        ::image::RgbaImage::from_pixel(4, 4, ::image::Rgba([40, 80, 220, 255]))
+            .write_to(
+                &mut std::io::Cursor::new(&mut png),
+                ::image::ImageOutputFormat::Png,
+            )
+            .unwrap();
+        png
+    }
+
+    /// A document using every part of the file format
+    fn document() -> String {
+        let mut model = EditorModel::new();
+        let red = Color32::RED;
+        let elements: Vec<ElementType> = vec![
+            factory::create_stroke(
+                ElementId::new(1),
+                vec![pos2(10.0, 10.0), pos2(60.0, 40.0)],
+                3.0,
+                red,
+            ),
+            factory::create_image(ElementId::new(2), png(), vec2(32.0, 16.0), pos2(80.0, 10.0)),
+            factory::create_shape(
+                ElementId::new(3),
+                vec![pos2(10.0, 80.0), pos2(60.0, 80.0), pos2(35.0, 120.0)],
+                Color32::BLUE,
+            ),
+            factory::create_path(
+                ElementId::new(4),
+                vec![
+                    PathNode::corner(pos2(100.0, 80.0)),
+                    PathNode::smooth(pos2(140.0, 100.0), pos2(150.0, 120.0)),
+                ],
+                false,
+                2.0,
+                red,
+            ),
+            factory::create_step_badge(ElementId::new(5), pos2(200.0, 40.0), 3, red),
+            factory::create_sticky_note(
+                ElementId::new(6),
+                Rect::from_min_size(pos2(180.0, 80.0), vec2(80.0, 60.0)),
+                "Note".to_string(),
+                Color32::YELLOW,
+            ),
+        ];
+        // Fixed ids and no timestamps, so a seed corrupts the same file every run
+        for mut element in elements {
+            element.metadata_mut().created_at = None;
+            model.add_element(element).unwrap();
+        }
+        model.metadata.title = "Corrupted".to_string();
+        model.artboards.push(Artboard {
+            id: 1,
+            name: "Page".to_string(),
+            rect: Rect::from_min_size(pos2(0.0, 0.0), vec2(300.0, 200.0)),
+        });
+        model.slices.push(ExportSlice {
+            id: 2,
+            name: "Header".to_string(),
+            rect: Rect::from_min_size(pos2(0.0, 0.0), vec2(300.0, 50.0)),
+        });
+        model.ruler_guides.push(RulerGuide {
+            id: 3,
+            axis: GuideAxis::Vertical,
+            position: 150.0,
+        });
+        model.reference_image = Some(ReferenceImage::new(
+            png(),
+            Rect::from_min_size(pos2(0.0, 0.0), vec2(100.0, 100.0)),
+        ));
+        DocumentFile::from_model(&model).to_ron().unwrap()
+    }
+
+    /// Where the numbers given as fields in `bytes` are, as start and end offsets
+    ///
+    /// Numbers in lists, mostly image bytes, are left out, so that the ids, sizes
+    /// and positions get most of the changes.
+    fn numbers(bytes: &[u8]) -> Vec<(usize, usize)> {
+        let mut found = Vec::new();
+        let mut start = None;
+        for (i, &byte) in bytes.iter().enumerate() {
+            let in_number = byte.is_ascii_digit() || (start.is_some() && byte == b'.');
+            match (start, in_number) {
+                (None, true) if i > 0 && bytes[i - 1] == b':' => start = Some(i),
+                (Some(from), false) => {
+                    found.push((from, i));
+                    start = None;
+                }
+                _ => {}
+            }
+        }
+        found
+    }
+
+    /// `bytes` with one random change made to them
+    fn corrupt(rng: &mut fastrand::Rng, mut bytes: Vec<u8>) -> Vec<u8> {
+        let len = bytes.len().max(1);
+        let at = rng.usize(..len).min(bytes.len());
+        let span = rng.usize(..64).min(bytes.len() - at);
+        match rng.u8(..8) {
+            0 => {
+                if let Some(byte) = bytes.get_mut(at) {
+                    *byte = rng.u8(..);
+                }
+            }
+            1 => {
+                bytes.drain(at..at + span);
+            }
+            2 => {
+                let copy = bytes[at..at + span].to_vec();
+                let to = rng.usize(..=bytes.len());
+                bytes.splice(to..to, copy);
+            }
+            3 => bytes.truncate(at),
+            _ => {
+                let numbers = numbers(&bytes);
+                if !numbers.is_empty() {
+                    let (from, to) = numbers[rng.usize(..numbers.len())];
+                    let extreme = EXTREMES[rng.usize(..EXTREMES.len())];
+                    bytes.splice(from..to, extreme.bytes());
+                }
+            }
+        }
+        bytes
+    }
+
+    #[test]
+    fn test_corrupted_documents_fail_to_open_or_open_cleanly() {
+        let original = document().into_bytes();
+        let mut harness = TestHarness::new();
+        let mut opened = 0;
+        for seed in 0..CASES {
+            let mut rng = fastrand::Rng::with_seed(seed);
+            let mut bytes = original.clone();
+            for _ in 0..rng.usize(1..=MAX_CHANGES) {
+                bytes = corrupt(&mut rng, bytes);
+            }
+
+            let result = std::panic::catch_unwind(std::panic::AssertUnwindSafe(|| {
+                let file = decode_document(bytes.clone(), None)?;
+                harness.editor_model.load_document(file);
+                harness.run_frame();
+                Ok::<_, DocumentError>(())
+            }));
+            match result {
+                Ok(Ok(())) => opened += 1,
+                Ok(Err(_)) => {}
+                Err(_) => panic!(
+                    "seed {}: opening panicked on\n{}",
+                    seed,
+                    String::from_utf8_lossy(&bytes)
+                ),
+            }
+        }
+        // Some changes leave the file readable, so showing damaged documents is tried too
+        assert!(opened > 0);
+    }
+}
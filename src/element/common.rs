@@ -2,9 +2,16 @@ use egui::{Pos2, Rect};
 
 // Common constants for all element types
 pub const MIN_ELEMENT_SIZE: f32 = 2.0;
+/// Smallest width or height images, shapes and badges can be resized to
+pub const MIN_FILLED_SIZE: f32 = 10.0;
 pub const STROKE_BASE_PADDING: f32 = 10.0;
 pub const IMAGE_PADDING: f32 = 10.0;
 
+/// Serde default for texture state: freshly deserialized elements always need a texture
+pub(crate) fn texture_needs_update_default() -> bool {
+    true
+}
+
 /// Validates that a rectangle has minimum dimensions
 pub(crate) fn validate_rect(rect: &Rect) -> Result<(), String> {
     if rect.width() < MIN_ELEMENT_SIZE || rect.height() < MIN_ELEMENT_SIZE {
@@ -19,6 +26,38 @@ pub(crate) fn validate_rect(rect: &Rect) -> Result<(), String> {
     }
 }
 
+/// Limits on resizing an element, which differ by element type
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct ResizeConstraints {
+    /// Smallest width and height the element can be resized to
+    pub min_size: f32,
+    /// Whether resizing keeps the width-to-height ratio unless Shift is held
+    pub keep_aspect_ratio: bool,
+}
+
+impl ResizeConstraints {
+    /// Whether a resize keeps the aspect ratio, given whether Shift is held
+    pub fn keeps_aspect_ratio(&self, shift: bool) -> bool {
+        self.keep_aspect_ratio != shift
+    }
+
+    /// Check that `element_type` can be resized to `rect`
+    pub fn check(&self, element_type: &str, rect: &Rect) -> Result<(), String> {
+        if rect.width() < self.min_size || rect.height() < self.min_size {
+            Err(format!(
+                "Too small: can't resize the {} below {}×{} (tried {:.0}×{:.0})",
+                element_type.replace('_', " "),
+                self.min_size,
+                self.min_size,
+                rect.width(),
+                rect.height()
+            ))
+        } else {
+            Ok(())
+        }
+    }
+}
+
 /// Calculate distance from a point to a line segment (useful for stroke hit testing)
 pub(crate) fn distance_to_line_segment(point: Pos2, line_start: Pos2, line_end: Pos2) -> f32 {
     let line_vec = line_end - line_start;
@@ -1,23 +1,125 @@
 use egui::{Color32, ColorImage, Context, Painter, Pos2, Rect, TextureHandle, Vec2};
-use log::info;
+use log::{debug, warn};
+use serde::{Deserialize, Serialize};
+use std::sync::OnceLock;
 
-use super::Element;
+use super::{Element, ElementId, ElementMetadata, Obb, Transform};
 use crate::element::common;
 use crate::texture_manager::TextureGenerationError;
 
+/// A rectangle of straight (not premultiplied) RGBA pixels, in the image's own resolution
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
+pub struct PixelRegion {
+    pub x: u32,
+    pub y: u32,
+    pub width: u32,
+    pub height: u32,
+    pub rgba: Vec<u8>,
+}
+
+impl PixelRegion {
+    /// Copy a rectangle out of `pixels`, clipped to its bounds
+    pub fn copy_from(pixels: &image::RgbaImage, x: u32, y: u32, width: u32, height: u32) -> Self {
+        let width = width.min(pixels.width().saturating_sub(x));
+        let height = height.min(pixels.height().saturating_sub(y));
+        let rgba = image::imageops::crop_imm(pixels, x, y, width, height)
+            .to_image()
+            .into_raw();
+        Self {
+            x,
+            y,
+            width,
+            height,
+            rgba,
+        }
+    }
+
+    /// Write the region back into `pixels` at its place
+    pub fn paste_into(&self, pixels: &mut image::RgbaImage) -> Result<(), String> {
+        let region = image::RgbaImage::from_raw(self.width, self.height, self.rgba.clone())
+            .ok_or_else(|| "Pixel region has the wrong amount of data".to_string())?;
+        if self.x + self.width > pixels.width() || self.y + self.height > pixels.height() {
+            return Err("Pixel region is outside the image".to_string());
+        }
+        image::imageops::replace(pixels, &region, self.x as i64, self.y as i64);
+        Ok(())
+    }
+}
+
+/// How images are resampled when drawn or exported at a size other than their own
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq, Serialize, Deserialize)]
+pub enum Resampling {
+    /// Keeps hard pixel edges, for pixel art and screenshots
+    Nearest,
+    /// Smooth and quick
+    Bilinear,
+    /// Sharpest when shrinking, and the slowest
+    #[default]
+    Lanczos,
+}
+
+impl Resampling {
+    pub const ALL: [Resampling; 3] = [
+        Resampling::Nearest,
+        Resampling::Bilinear,
+        Resampling::Lanczos,
+    ];
+
+    pub fn label(self) -> &'static str {
+        match self {
+            Resampling::Nearest => "Nearest",
+            Resampling::Bilinear => "Bilinear",
+            Resampling::Lanczos => "Lanczos",
+        }
+    }
+
+    fn filter(self) -> image::imageops::FilterType {
+        match self {
+            Resampling::Nearest => image::imageops::FilterType::Nearest,
+            Resampling::Bilinear => image::imageops::FilterType::Triangle,
+            Resampling::Lanczos => image::imageops::FilterType::Lanczos3,
+        }
+    }
+
+    /// How the GPU samples textures resampled this way, when it stretches them further
+    pub fn texture_options(self) -> egui::TextureOptions {
+        match self {
+            Resampling::Nearest => egui::TextureOptions::NEAREST,
+            Resampling::Bilinear | Resampling::Lanczos => egui::TextureOptions::LINEAR,
+        }
+    }
+}
+
 /// Image element representing a bitmap image
-#[derive(Clone)]
+#[derive(Clone, Serialize, Deserialize)]
 pub struct Image {
     // Core properties
-    id: usize,
+    id: ElementId,
     original_data: Vec<u8>,  // Original image data (JPG, PNG, etc)
-    rgba_data: Vec<u8>,      // Processed RGBA data
+    // File path or URL of a linked image, whose data isn't saved with the document
+    #[serde(default)]
+    link: Option<String>,
+    #[serde(skip)]
+    rgba_data: Vec<u8>,      // Processed RGBA data (premultiplied alpha)
+    // Width and height of the image data in pixels, read on first use
+    #[serde(skip)]
+    pixel_size: OnceLock<Option<[u32; 2]>>,
     size: Vec2,              // Width and height
     position: Pos2,          // Position in the document
+    // Where the rectangle above is drawn; moving and resizing only change this
+    #[serde(default)]
+    transform: Transform,
+
+    // User-facing name and metadata
+    #[serde(default)]
+    metadata: ElementMetadata,
 
     // Texture caching
+    #[serde(skip)]
     texture_handle: Option<TextureHandle>,
+    #[serde(skip, default = "common::texture_needs_update_default")]
     texture_needs_update: bool,
+    #[serde(skip)]
     texture_version: u64,
 }
 
@@ -27,9 +129,12 @@ impl std::fmt::Debug for Image {
         f.debug_struct("Image")
             .field("id", &self.id)
             .field("original_data_len", &self.original_data.len())
+            .field("link", &self.link)
             .field("rgba_data_len", &self.rgba_data.len())
             .field("size", &self.size)
             .field("position", &self.position)
+            .field("transform", &self.transform)
+            .field("metadata", &self.metadata)
             .field("texture_needs_update", &self.texture_needs_update)
             .field("texture_version", &self.texture_version)
             .finish()
@@ -38,65 +143,212 @@ impl std::fmt::Debug for Image {
 
 impl Image {
     /// Create a new image with the given properties
-    pub(crate) fn new(id: usize, data: Vec<u8>, size: Vec2, position: Pos2) -> Self {
+    pub(crate) fn new(id: ElementId, data: Vec<u8>, size: Vec2, position: Pos2) -> Self {
         // Store original data and create empty RGBA data (will be populated in generate_texture)
         Self {
             id,
             original_data: data,
+            link: None,
             rgba_data: Vec::new(),
+            pixel_size: OnceLock::new(),
             size,
             position,
+            transform: Transform::IDENTITY,
+            metadata: ElementMetadata::default(),
             texture_handle: None,
             texture_needs_update: true,
             texture_version: 0,
         }
     }
 
-    /// Get the image size
+    /// A copy of this image under another id
+    pub(crate) fn with_id(&self, id: ElementId) -> Self {
+        Self { id, ..self.clone() }
+    }
+
+    /// Get the size the image is drawn at
     pub(crate) fn size(&self) -> Vec2 {
-        self.size
+        self.rect().size()
+    }
+
+    /// Get the original encoded image data (PNG, JPG, ...)
+    pub(crate) fn original_data(&self) -> &[u8] {
+        &self.original_data
+    }
+
+    /// File path or URL the image is linked to, or None if it's embedded
+    pub(crate) fn link(&self) -> Option<&str> {
+        self.link.as_deref()
+    }
+
+    /// Whether the image has no data, as when its linked file couldn't be read
+    pub(crate) fn is_missing(&self) -> bool {
+        self.original_data.is_empty()
     }
 
-    /// Get the image position
+    /// Link the image to a file path or URL, or embed it with None, showing `data`
+    pub(crate) fn set_source(&mut self, link: Option<String>, data: Vec<u8>) {
+        self.link = link;
+        self.original_data = data;
+        self.drop_decoded();
+        self.invalidate_texture();
+    }
+
+    /// Drop the data of a linked image, which is read from its link on opening
+    pub(crate) fn strip_linked_data(&mut self) {
+        if self.link.is_some() {
+            self.original_data = Vec::new();
+        }
+    }
+
+    /// Get the position the image is drawn at
     pub(crate) fn position(&self) -> Pos2 {
-        self.position
+        self.rect().min
+    }
+
+    /// Bytes of decoded pixels kept from the last texture generated
+    pub(crate) fn decoded_len(&self) -> usize {
+        self.rgba_data.len()
+    }
+
+    /// Free the decoded pixels; the next texture decodes the image again
+    pub(crate) fn drop_decoded(&mut self) {
+        self.rgba_data = Vec::new();
+        self.pixel_size = OnceLock::new();
+    }
+
+    /// Width and height of the image data in pixels, or None if it can't be read
+    pub(crate) fn pixel_size(&self) -> Option<[u32; 2]> {
+        *self.pixel_size.get_or_init(|| {
+            image::io::Reader::new(std::io::Cursor::new(&self.original_data))
+                .with_guessed_format()
+                .ok()?
+                .into_dimensions()
+                .ok()
+                .map(|(width, height)| [width, height])
+        })
+    }
+
+    /// The image resampled to `size` pixels with premultiplied alpha, or None
+    /// if it fails to decode
+    pub(crate) fn resampled(
+        &self,
+        size: [u32; 2],
+        resampling: Resampling,
+    ) -> Option<image::RgbaImage> {
+        // Resize with premultiplied alpha so the color of fully transparent
+        // pixels doesn't bleed into the edges of visible ones
+        let mut rgba = self.decode_pixels()?;
+        for pixel in rgba.pixels_mut() {
+            let alpha = pixel[3] as u16;
+            for channel in &mut pixel.0[..3] {
+                *channel = ((*channel as u16 * alpha + 127) / 255) as u8;
+            }
+        }
+        if [rgba.width(), rgba.height()] == size {
+            return Some(rgba);
+        }
+        let mut resized = image::imageops::resize(&rgba, size[0], size[1], resampling.filter());
+        // Lanczos ringing can push a channel above alpha, which isn't valid premultiplied color
+        for pixel in resized.pixels_mut() {
+            let alpha = pixel[3];
+            for channel in &mut pixel.0[..3] {
+                *channel = (*channel).min(alpha);
+            }
+        }
+        Some(resized)
+    }
+
+    /// Decode the image to straight RGBA at its own resolution
+    pub(crate) fn decode_pixels(&self) -> Option<image::RgbaImage> {
+        image::load_from_memory(&self.original_data)
+            .ok()
+            .map(|img| img.to_rgba8())
+    }
+
+    /// Write a region of pixels into the image, which is stored as PNG from then on
+    pub(crate) fn write_pixels(&mut self, region: &PixelRegion) -> Result<(), String> {
+        let mut pixels = self
+            .decode_pixels()
+            .ok_or_else(|| format!("Failed to decode image {}", self.id))?;
+        region.paste_into(&mut pixels)?;
+        self.store_pixels(&pixels)
+    }
+
+    /// Replace the image data with `pixels`, stored as PNG
+    fn store_pixels(&mut self, pixels: &image::RgbaImage) -> Result<(), String> {
+        let mut png = std::io::Cursor::new(Vec::new());
+        pixels
+            .write_to(&mut png, image::ImageOutputFormat::Png)
+            .map_err(|err| format!("Failed to encode image {}: {}", self.id, err))?;
+        self.original_data = png.into_inner();
+        self.drop_decoded();
+        self.invalidate_texture();
+        Ok(())
+    }
+
+    /// Store the image where it's drawn and drop the transform
+    ///
+    /// Moving and scaling only change where the image sits, since textures are
+    /// always made from the full data. A turned image is resampled upright, at
+    /// one pixel per unit, into the box around it; it no longer matches its
+    /// linked file, if any, so it's embedded.
+    pub(crate) fn flatten_transform(&mut self) -> Result<(), String> {
+        let obb = self.obb();
+        let mut rect = obb.rect;
+        if obb.rotation != 0.0 {
+            let pixels = self
+                .decode_pixels()
+                .ok_or_else(|| format!("Failed to decode image {}", self.id))?;
+            rect = obb.bounding_rect();
+            self.store_pixels(&turned_pixels(&pixels, obb, rect))?;
+            self.link = None;
+        }
+        self.position = rect.min;
+        self.size = rect.size();
+        self.transform = Transform::IDENTITY;
+        self.invalidate_texture();
+        Ok(())
     }
 
     /// Generates a texture representation of the image
     fn generate_texture_internal(&mut self, _ctx: &Context) -> Result<ColorImage, TextureGenerationError> {
-        let target_width = self.size.x as usize;
-        let target_height = self.size.y as usize;
+        let size = self.size();
+        let target_width = size.x as usize;
+        let target_height = size.y as usize;
+
+        // A missing linked image is drawn as a placeholder until it's relinked
+        if self.is_missing() {
+            return Err(TextureGenerationError::GenerationFailed);
+        }
         
         // Try to load as standard image format from original data
-        if let Ok(img) = image::load_from_memory(&self.original_data) {
-            info!("✅ Successfully loaded image format: {:?}", img.color());
-            
-            let resized = img.resize_exact(
-                target_width as u32,
-                target_height as u32,
-                image::imageops::FilterType::Lanczos3
-            );
-            let rgba = resized.to_rgba8();
-            
+        let target = [target_width as u32, target_height as u32];
+        if let Some(resized) = self.resampled(target, Resampling::Lanczos) {
             // Store the RGBA data for future use
-            self.rgba_data = rgba.as_raw().to_vec();
+            self.rgba_data = resized.into_raw();
             self.texture_needs_update = false;
             
-            return Ok(ColorImage::from_rgba_unmultiplied(
+            return Ok(ColorImage::from_rgba_premultiplied(
                 [target_width, target_height],
                 &self.rgba_data
             ));
         }
 
         // If standard format loading fails, log error and fail
-        info!("❌ Failed to process image data: len={}, format=unknown, target={}x{}", 
-              self.original_data.len(), target_width, target_height);
+        warn!(
+            id:% = self.id,
+            bytes = self.original_data.len(),
+            width = target_width,
+            height = target_height;
+            "Failed to decode image data"
+        );
         Err(TextureGenerationError::GenerationFailed)
     }
 }
 
 impl Element for Image {
-    fn id(&self) -> usize {
+    fn id(&self) -> ElementId {
         self.id
     }
 
@@ -104,8 +356,29 @@ impl Element for Image {
         "image"
     }
 
+    fn metadata(&self) -> &ElementMetadata {
+        &self.metadata
+    }
+
+    fn metadata_mut(&mut self) -> &mut ElementMetadata {
+        &mut self.metadata
+    }
+
     fn rect(&self) -> Rect {
-        Rect::from_min_size(self.position, self.size)
+        self.transform
+            .apply_rect(Rect::from_min_size(self.position, self.size))
+    }
+
+    fn transform(&self) -> Transform {
+        self.transform
+    }
+
+    fn set_transform(&mut self, transform: Transform) {
+        let resized = transform.scale != self.transform.scale;
+        self.transform = transform;
+        if resized {
+            self.invalidate_texture();
+        }
     }
 
     fn draw(&self, painter: &Painter) {
@@ -133,7 +406,7 @@ impl Element for Image {
     }
 
     fn translate(&mut self, delta: Vec2) -> Result<(), String> {
-        self.position += delta;
+        self.transform = self.transform.translated(delta);
         // No need to invalidate texture for translation
         Ok(())
     }
@@ -141,17 +414,12 @@ impl Element for Image {
     fn resize(&mut self, new_rect: Rect) -> Result<(), String> {
         common::validate_rect(&new_rect)?;
 
-        // Update position and size
-        self.position = new_rect.min;
-        self.size = new_rect.size();
-
-        // Invalidate texture when resizing since we need to adjust for the new dimensions
+        // Scale the transform; the texture is made again from the full image
+        // data at the new size
+        self.transform = self.transform.resized(self.rect(), new_rect);
         self.invalidate_texture();
 
-        info!(
-            "✅ Image {} resized: pos={:?}, size={:?}",
-            self.id, self.position, self.size
-        );
+        debug!(id:% = self.id, pos:? = self.position(), size:? = self.size(); "Resized image");
         Ok(())
     }
 
@@ -176,3 +444,47 @@ impl Element for Image {
         self.generate_texture_internal(ctx)
     }
 }
+
+/// `pixels` stretched over `obb` and sampled upright, at one pixel per unit,
+/// into an image covering `bounds`
+fn turned_pixels(pixels: &image::RgbaImage, obb: Obb, bounds: Rect) -> image::RgbaImage {
+    let width = bounds.width().round().max(1.0) as u32;
+    let height = bounds.height().round().max(1.0) as u32;
+    let scale = Vec2::new(pixels.width() as f32, pixels.height() as f32) / obb.rect.size();
+    image::RgbaImage::from_fn(width, height, |x, y| {
+        let pos = bounds.min + Vec2::new(x as f32 + 0.5, y as f32 + 0.5);
+        let source = (obb.to_local(pos) - obb.rect.min) * scale - Vec2::splat(0.5);
+        sample_bilinear(pixels, source)
+    })
+}
+
+/// The color of `pixels` at `pos`, in pixels from the center of the top-left
+/// one, blended from the four nearest; outside the image counts as transparent
+fn sample_bilinear(pixels: &image::RgbaImage, pos: Vec2) -> image::Rgba<u8> {
+    let (left, top) = (pos.x.floor(), pos.y.floor());
+    let (fx, fy) = (pos.x - left, pos.y - top);
+    // Blend premultiplied, so transparent neighbours don't darken the edges
+    let mut sum = [0.0_f32; 4];
+    for (dx, dy, weight) in [
+        (0, 0, (1.0 - fx) * (1.0 - fy)),
+        (1, 0, fx * (1.0 - fy)),
+        (0, 1, (1.0 - fx) * fy),
+        (1, 1, fx * fy),
+    ] {
+        let (x, y) = (left as i64 + dx, top as i64 + dy);
+        if x < 0 || y < 0 || x >= pixels.width() as i64 || y >= pixels.height() as i64 {
+            continue;
+        }
+        let [r, g, b, a] = pixels.get_pixel(x as u32, y as u32).0.map(f32::from);
+        let alpha = a * weight;
+        sum[0] += r * alpha;
+        sum[1] += g * alpha;
+        sum[2] += b * alpha;
+        sum[3] += alpha;
+    }
+    if sum[3] <= 0.0 {
+        return image::Rgba([0, 0, 0, 0]);
+    }
+    let [r, g, b] = [sum[0], sum[1], sum[2]].map(|channel| (channel / sum[3]).round() as u8);
+    image::Rgba([r, g, b, sum[3].round() as u8])
+}
@@ -0,0 +1,68 @@
+use serde::{Deserialize, Serialize};
+use std::collections::BTreeMap;
+
+/// Property marking an element as a redaction region
+///
+/// Privacy scrub exports burn these in opaque, over everything beneath and above them.
+pub const REDACTION_PROPERTY: &str = "redaction";
+
+/// Property marking an element as locked in place
+///
+/// Locked elements are drawn and exported as usual but can't be picked on the canvas,
+/// so guides and backdrops stay put while the rest of the document is edited.
+pub const LOCKED_PROPERTY: &str = "locked";
+
+/// User-facing name and free-form key/value data attached to an element
+///
+/// The name is empty until the user renames the element; callers that need a
+/// label should use `Element::display_name`, which falls back to the type and ID.
+#[derive(Clone, Debug, Default, PartialEq, Serialize, Deserialize)]
+pub struct ElementMetadata {
+    pub name: String,
+    pub properties: BTreeMap<String, String>,
+    /// When the element was first added to a document, in seconds since the Unix epoch
+    #[serde(default)]
+    pub created_at: Option<f64>,
+    /// Identifies the element across copies of the document, unlike its `ElementId`
+    ///
+    /// Only assigned when built with the `uuid` feature, but kept in any case.
+    #[serde(default)]
+    pub uuid: Option<String>,
+}
+
+impl ElementMetadata {
+    /// Create metadata with the given name and no properties
+    pub fn with_name(name: impl Into<String>) -> Self {
+        Self {
+            name: name.into(),
+            properties: BTreeMap::new(),
+            created_at: None,
+            uuid: None,
+        }
+    }
+
+    /// Get a metadata property by key
+    pub fn get(&self, key: &str) -> Option<&str> {
+        self.properties.get(key).map(String::as_str)
+    }
+
+    /// Set a metadata property, returning the previous value if any
+    pub fn set(&mut self, key: impl Into<String>, value: impl Into<String>) -> Option<String> {
+        self.properties.insert(key.into(), value.into())
+    }
+
+    /// Whether the element covers content that must not be recoverable from exports
+    pub fn is_redaction(&self) -> bool {
+        self.get(REDACTION_PROPERTY) == Some("true")
+    }
+
+    /// Whether the element is locked against selection and editing on the canvas
+    pub fn is_locked(&self) -> bool {
+        self.get(LOCKED_PROPERTY) == Some("true")
+    }
+
+    /// Remove a metadata property, returning its value if it existed
+    pub fn remove(&mut self, key: &str) -> Option<String> {
+        self.properties.remove(key)
+    }
+}
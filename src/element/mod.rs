@@ -1,30 +1,118 @@
 use egui::{ColorImage, Context, Painter, Pos2, Rect, TextureHandle, Vec2};
+use serde::{Deserialize, Serialize};
 
 // Re-export concrete implementations
 mod common;
 pub(crate) mod image;
+mod metadata;
+mod obb;
+pub(crate) mod path;
+pub(crate) mod shape;
+pub(crate) mod step_badge;
+pub(crate) mod sticky_note;
 pub(crate) mod stroke;
+mod transform;
 // We'll add text later
 // pub(crate) mod text;
 
 use crate::texture_manager::TextureGenerationError;
-pub use common::MIN_ELEMENT_SIZE;
+pub use common::{MIN_ELEMENT_SIZE, MIN_FILLED_SIZE, ResizeConstraints};
+pub use image::{PixelRegion, Resampling};
+pub use metadata::{ElementMetadata, LOCKED_PROPERTY, REDACTION_PROPERTY};
+pub use obb::Obb;
+pub use path::PathNode;
+pub use shape::{PatternFill, PolygonParams};
+pub use sticky_note::{DEFAULT_NOTE_SIZE, NOTE_COLORS, NOTE_TEXT_COLOR};
+pub use stroke::{DashPattern, LineCap, LineJoin, MAX_TAPER, StrokeStyle};
+pub use transform::Transform;
+
+/// Identifies an element within its document
+///
+/// A newtype so an id can't be mixed up with an index into the element list.
+/// Saved documents store it as the plain number.
+#[derive(
+    Clone, Copy, Debug, Default, PartialEq, Eq, Hash, PartialOrd, Ord, Serialize, Deserialize,
+)]
+#[serde(transparent)]
+pub struct ElementId(usize);
+
+impl ElementId {
+    pub const fn new(id: usize) -> Self {
+        Self(id)
+    }
+
+    /// The id as a plain number
+    pub const fn get(self) -> usize {
+        self.0
+    }
+
+    /// The id after this one
+    pub(crate) const fn next(self) -> Self {
+        Self(self.0 + 1)
+    }
+}
+
+impl std::fmt::Display for ElementId {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        self.0.fmt(f)
+    }
+}
 
 /// Common trait that all document elements must implement
 pub trait Element {
     /// Get the unique identifier for this element
-    fn id(&self) -> usize;
+    fn id(&self) -> ElementId;
 
     /// Get the element type as a string
     fn element_type(&self) -> &'static str;
 
+    /// Get the user-assigned name and key/value metadata for this element
+    fn metadata(&self) -> &ElementMetadata;
+
+    /// Get mutable access to the element's name and metadata
+    fn metadata_mut(&mut self) -> &mut ElementMetadata;
+
+    /// Get the element's name
+    fn name(&self) -> &str {
+        &self.metadata().name
+    }
+
+    /// Get a label for outliners and exporters, falling back to type and ID when unnamed
+    fn display_name(&self) -> String {
+        let name = self.name();
+        if name.is_empty() {
+            format!("{} {}", self.element_type(), self.id())
+        } else {
+            name.to_string()
+        }
+    }
+
     /// Get the bounding rectangle for this element
     fn rect(&self) -> Rect;
 
+    /// Get where the element's stored geometry is drawn
+    fn transform(&self) -> Transform;
+
+    /// Draw the element's stored geometry somewhere else, without touching it
+    fn set_transform(&mut self, transform: Transform);
+
+    /// Get the element's rotation about the center of `rect`, in radians
+    fn rotation(&self) -> f32 {
+        self.transform().rotation
+    }
+
+    /// Get the element's rectangle turned by its rotation
+    fn obb(&self) -> Obb {
+        Obb::new(self.rect(), self.rotation())
+    }
+
     /// Draw the element using the provided painter
     fn draw(&self, painter: &Painter);
 
     /// Test if the element contains the given position
+    ///
+    /// Elements test in their own unrotated frame; `ElementType` maps the
+    /// position there first.
     fn hit_test(&self, pos: Pos2) -> bool;
 
     /// Translate the element by the given delta
@@ -53,10 +141,14 @@ pub trait Element {
 }
 
 /// Enumeration of all element types in the document
-#[derive(Clone)]
+#[derive(Clone, Serialize, Deserialize)]
 pub enum ElementType {
     Stroke(stroke::Stroke),
     Image(image::Image),
+    Shape(shape::Shape),
+    Path(path::Path),
+    StepBadge(step_badge::StepBadge),
+    StickyNote(sticky_note::StickyNote),
     // We'll add text later
     // Text(text::Text),
 }
@@ -67,6 +159,10 @@ impl std::fmt::Debug for ElementType {
         match self {
             ElementType::Stroke(s) => f.debug_tuple("Stroke").field(s).finish(),
             ElementType::Image(i) => f.debug_tuple("Image").field(i).finish(),
+            ElementType::Shape(s) => f.debug_tuple("Shape").field(s).finish(),
+            ElementType::Path(p) => f.debug_tuple("Path").field(p).finish(),
+            ElementType::StepBadge(b) => f.debug_tuple("StepBadge").field(b).finish(),
+            ElementType::StickyNote(n) => f.debug_tuple("StickyNote").field(n).finish(),
         }
     }
 }
@@ -76,52 +172,185 @@ pub const RESIZE_HANDLE_RADIUS: f32 = 15.0;
 
 /// Legacy function for computing an element's rectangle with padding
 /// This is kept for backward compatibility with existing code
+///
+/// For a rotated element this is the axis-aligned box around its rotated outline.
 pub fn compute_element_rect(element: &ElementType) -> egui::Rect {
-    // Get the base rectangle from the Element trait
-    let base_rect = element.rect();
+    compute_element_obb(element).bounding_rect()
+}
 
+/// The element's padded outline, turned by its rotation, for selection boxes
+/// and handles
+pub fn compute_element_obb(element: &ElementType) -> Obb {
     // Apply padding based on element type
-    match element {
-        ElementType::Stroke(_) => {
-            // For strokes, add the base padding
-            let padding = common::STROKE_BASE_PADDING;
-
-            egui::Rect::from_min_max(
-                egui::pos2(base_rect.min.x - padding, base_rect.min.y - padding),
-                egui::pos2(base_rect.max.x + padding, base_rect.max.y + padding),
-            )
-        }
-        ElementType::Image(_) => {
-            // For images, add the image padding
-            let padding = common::IMAGE_PADDING;
-            egui::Rect::from_min_max(
-                egui::pos2(base_rect.min.x - padding, base_rect.min.y - padding),
-                egui::pos2(base_rect.max.x + padding, base_rect.max.y + padding),
-            )
-        }
-    }
+    let padding = match element {
+        // For strokes and paths, add the base padding
+        ElementType::Stroke(_) | ElementType::Path(_) => common::STROKE_BASE_PADDING,
+        // For images, shapes, badges and notes, add the image padding
+        ElementType::Image(_)
+        | ElementType::Shape(_)
+        | ElementType::StepBadge(_)
+        | ElementType::StickyNote(_) => common::IMAGE_PADDING,
+    };
+    element.obb().expand(padding)
 }
 
 // Additional methods for ElementType that aren't part of the Element trait
 impl ElementType {
+    /// Get the document-space rectangle that the output of `generate_texture` covers
+    pub fn texture_rect(&self) -> Rect {
+        match self {
+            ElementType::Stroke(s) => s.texture_rect(),
+            ElementType::Image(i) => i.rect(),
+            ElementType::Shape(s) => s.rect(),
+            ElementType::Path(p) => p.texture_rect(),
+            ElementType::StepBadge(b) => b.rect(),
+            ElementType::StickyNote(n) => n.texture_rect(),
+        }
+    }
+
+    /// How the element can be resized
+    ///
+    /// Lines can be as thin as any element; images and badges keep their
+    /// proportions unless Shift is held, while notes rewrap their text.
+    pub fn resize_constraints(&self) -> ResizeConstraints {
+        let (min_size, keep_aspect_ratio) = match self {
+            ElementType::Stroke(_) | ElementType::Path(_) => (MIN_ELEMENT_SIZE, false),
+            ElementType::Shape(_) | ElementType::StickyNote(_) => (MIN_FILLED_SIZE, false),
+            ElementType::Image(_) | ElementType::StepBadge(_) => (MIN_FILLED_SIZE, true),
+        };
+        ResizeConstraints {
+            min_size,
+            keep_aspect_ratio,
+        }
+    }
+
+    /// The element's main color: the line color, or the fill for shapes and notes
+    ///
+    /// Images have none.
+    pub fn color(&self) -> Option<egui::Color32> {
+        match self {
+            ElementType::Stroke(s) => Some(s.color()),
+            ElementType::Image(_) => None,
+            ElementType::Shape(s) => Some(s.fill()),
+            ElementType::Path(p) => Some(p.color()),
+            ElementType::StepBadge(b) => Some(b.color()),
+            ElementType::StickyNote(n) => Some(n.color()),
+        }
+    }
+
+    /// Change the color [`Self::color`] returns; images have none to change
+    pub fn set_color(&mut self, color: egui::Color32) {
+        match self {
+            ElementType::Stroke(s) => s.set_color(color),
+            ElementType::Image(_) => {}
+            ElementType::Shape(s) => s.set_fill(color),
+            ElementType::Path(p) => p.set_color(color),
+            ElementType::StepBadge(b) => b.set_color(color),
+            ElementType::StickyNote(n) => n.set_color(color),
+        }
+    }
+
+    /// The line strokes and paths are drawn along, with its thickness and color
+    ///
+    /// Other elements have no such line.
+    pub fn polyline(&self) -> Option<(Vec<Pos2>, f32, egui::Color32)> {
+        match self {
+            ElementType::Stroke(s) => Some((s.points().to_vec(), s.thickness(), s.color())),
+            ElementType::Path(p) => Some((p.flatten(), p.thickness(), p.color())),
+            ElementType::Image(_)
+            | ElementType::Shape(_)
+            | ElementType::StepBadge(_)
+            | ElementType::StickyNote(_) => None,
+        }
+    }
+
+    /// Bake the element's transform into its stored geometry, as if it had
+    /// been drawn where it's shown
+    ///
+    /// Turned images are resampled. Notes and badges lay out their contents
+    /// for their size, so they keep their rotation.
+    pub fn flatten_transform(&mut self) -> Result<(), String> {
+        match self {
+            ElementType::Stroke(s) => s.flatten_transform(),
+            ElementType::Image(i) => return i.flatten_transform(),
+            ElementType::Shape(s) => s.flatten_transform(),
+            ElementType::Path(p) => p.flatten_transform(),
+            ElementType::StepBadge(_) | ElementType::StickyNote(_) => {}
+        }
+        Ok(())
+    }
+
+    /// Whether [`Self::flatten_transform`] would change anything
+    pub fn can_flatten(&self) -> bool {
+        match self {
+            ElementType::StepBadge(_) | ElementType::StickyNote(_) => false,
+            _ => !self.transform().is_identity(),
+        }
+    }
+
+    /// A copy of this element under another id, stamped as new when added
+    pub fn with_id(&self, id: ElementId) -> ElementType {
+        let mut copy = match self {
+            ElementType::Stroke(s) => ElementType::Stroke(s.with_id(id)),
+            ElementType::Image(i) => ElementType::Image(i.with_id(id)),
+            ElementType::Shape(s) => ElementType::Shape(s.with_id(id)),
+            ElementType::Path(p) => ElementType::Path(p.with_id(id)),
+            ElementType::StepBadge(b) => ElementType::StepBadge(b.with_id(id)),
+            ElementType::StickyNote(n) => ElementType::StickyNote(n.with_id(id)),
+        };
+        copy.metadata_mut().created_at = None;
+        copy
+    }
+
+    /// Taper, caps, joins and dashes of the line from [`Self::polyline`]
+    pub fn stroke_style(&self) -> StrokeStyle {
+        match self {
+            ElementType::Stroke(s) => s.style(),
+            ElementType::Path(p) => p.style(),
+            _ => StrokeStyle::default(),
+        }
+    }
+
     pub fn regenerate_texture(&mut self, ctx: &Context) -> bool {
         match self {
             ElementType::Stroke(s) => {
                 if s.needs_texture_update() {
-                    match s.generate_texture(ctx) {
-                        Ok(_) => true,
-                        Err(_) => false,
-                    }
+                    s.generate_texture(ctx).is_ok()
                 } else {
                     false
                 }
             }
             ElementType::Image(i) => {
                 if i.needs_texture_update() {
-                    match i.generate_texture(ctx) {
-                        Ok(_) => true,
-                        Err(_) => false,
-                    }
+                    i.generate_texture(ctx).is_ok()
+                } else {
+                    false
+                }
+            }
+            ElementType::Shape(s) => {
+                if s.needs_texture_update() {
+                    s.generate_texture(ctx).is_ok()
+                } else {
+                    false
+                }
+            }
+            ElementType::Path(p) => {
+                if p.needs_texture_update() {
+                    p.generate_texture(ctx).is_ok()
+                } else {
+                    false
+                }
+            }
+            ElementType::StepBadge(b) => {
+                if b.needs_texture_update() {
+                    b.generate_texture(ctx).is_ok()
+                } else {
+                    false
+                }
+            }
+            ElementType::StickyNote(n) => {
+                if n.needs_texture_update() {
+                    n.generate_texture(ctx).is_ok()
                 } else {
                     false
                 }
@@ -131,10 +360,14 @@ impl ElementType {
 }
 
 impl Element for ElementType {
-    fn id(&self) -> usize {
+    fn id(&self) -> ElementId {
         match self {
             ElementType::Stroke(s) => s.id(),
             ElementType::Image(i) => i.id(),
+            ElementType::Shape(s) => s.id(),
+            ElementType::Path(p) => p.id(),
+            ElementType::StepBadge(b) => b.id(),
+            ElementType::StickyNote(n) => n.id(),
             // ElementType::Text(t) => t.id(),
         }
     }
@@ -143,30 +376,107 @@ impl Element for ElementType {
         match self {
             ElementType::Stroke(_) => "stroke",
             ElementType::Image(_) => "image",
+            ElementType::Shape(_) => "shape",
+            ElementType::Path(_) => "path",
+            ElementType::StepBadge(_) => "step_badge",
+            ElementType::StickyNote(_) => "sticky_note",
             // ElementType::Text(_) => "text",
         }
     }
 
+    fn metadata(&self) -> &ElementMetadata {
+        match self {
+            ElementType::Stroke(s) => s.metadata(),
+            ElementType::Image(i) => i.metadata(),
+            ElementType::Shape(s) => s.metadata(),
+            ElementType::Path(p) => p.metadata(),
+            ElementType::StepBadge(b) => b.metadata(),
+            ElementType::StickyNote(n) => n.metadata(),
+            // ElementType::Text(t) => t.metadata(),
+        }
+    }
+
+    fn metadata_mut(&mut self) -> &mut ElementMetadata {
+        match self {
+            ElementType::Stroke(s) => s.metadata_mut(),
+            ElementType::Image(i) => i.metadata_mut(),
+            ElementType::Shape(s) => s.metadata_mut(),
+            ElementType::Path(p) => p.metadata_mut(),
+            ElementType::StepBadge(b) => b.metadata_mut(),
+            ElementType::StickyNote(n) => n.metadata_mut(),
+            // ElementType::Text(t) => t.metadata_mut(),
+        }
+    }
+
     fn rect(&self) -> Rect {
         match self {
             ElementType::Stroke(s) => s.rect(),
             ElementType::Image(i) => i.rect(),
+            ElementType::Shape(s) => s.rect(),
+            ElementType::Path(p) => p.rect(),
+            ElementType::StepBadge(b) => b.rect(),
+            ElementType::StickyNote(n) => n.rect(),
             // ElementType::Text(t) => t.rect(),
         }
     }
 
+    fn transform(&self) -> Transform {
+        match self {
+            ElementType::Stroke(s) => s.transform(),
+            ElementType::Image(i) => i.transform(),
+            ElementType::Shape(s) => s.transform(),
+            ElementType::Path(p) => p.transform(),
+            ElementType::StepBadge(b) => b.transform(),
+            ElementType::StickyNote(n) => n.transform(),
+            // ElementType::Text(t) => t.transform(),
+        }
+    }
+
+    fn set_transform(&mut self, transform: Transform) {
+        match self {
+            ElementType::Stroke(s) => s.set_transform(transform),
+            ElementType::Image(i) => i.set_transform(transform),
+            ElementType::Shape(s) => s.set_transform(transform),
+            ElementType::Path(p) => p.set_transform(transform),
+            ElementType::StepBadge(b) => b.set_transform(transform),
+            ElementType::StickyNote(n) => n.set_transform(transform),
+            // ElementType::Text(t) => t.set_transform(transform),
+        }
+    }
+
+    fn rotation(&self) -> f32 {
+        match self {
+            ElementType::Stroke(s) => s.rotation(),
+            ElementType::Image(i) => i.rotation(),
+            ElementType::Shape(s) => s.rotation(),
+            ElementType::Path(p) => p.rotation(),
+            ElementType::StepBadge(b) => b.rotation(),
+            ElementType::StickyNote(n) => n.rotation(),
+            // ElementType::Text(t) => t.rotation(),
+        }
+    }
+
     fn draw(&self, painter: &Painter) {
         match self {
             ElementType::Stroke(s) => s.draw(painter),
             ElementType::Image(i) => i.draw(painter),
+            ElementType::Shape(s) => s.draw(painter),
+            ElementType::Path(p) => p.draw(painter),
+            ElementType::StepBadge(b) => b.draw(painter),
+            ElementType::StickyNote(n) => n.draw(painter),
             // ElementType::Text(t) => t.draw(painter),
         }
     }
 
     fn hit_test(&self, pos: Pos2) -> bool {
+        let pos = self.obb().to_local(pos);
         match self {
             ElementType::Stroke(s) => s.hit_test(pos),
             ElementType::Image(i) => i.hit_test(pos),
+            ElementType::Shape(s) => s.hit_test(pos),
+            ElementType::Path(p) => p.hit_test(pos),
+            ElementType::StepBadge(b) => b.hit_test(pos),
+            ElementType::StickyNote(n) => n.hit_test(pos),
             // ElementType::Text(t) => t.hit_test(pos),
         }
     }
@@ -175,6 +485,10 @@ impl Element for ElementType {
         match self {
             ElementType::Stroke(s) => s.translate(delta),
             ElementType::Image(i) => i.translate(delta),
+            ElementType::Shape(s) => s.translate(delta),
+            ElementType::Path(p) => p.translate(delta),
+            ElementType::StepBadge(b) => b.translate(delta),
+            ElementType::StickyNote(n) => n.translate(delta),
             // ElementType::Text(t) => t.translate(delta),
         }
     }
@@ -183,6 +497,10 @@ impl Element for ElementType {
         match self {
             ElementType::Stroke(s) => s.resize(new_rect),
             ElementType::Image(i) => i.resize(new_rect),
+            ElementType::Shape(s) => s.resize(new_rect),
+            ElementType::Path(p) => p.resize(new_rect),
+            ElementType::StepBadge(b) => b.resize(new_rect),
+            ElementType::StickyNote(n) => n.resize(new_rect),
             // ElementType::Text(t) => t.resize(new_rect),
         }
     }
@@ -191,6 +509,10 @@ impl Element for ElementType {
         match self {
             ElementType::Stroke(s) => s.texture(),
             ElementType::Image(i) => i.texture(),
+            ElementType::Shape(s) => s.texture(),
+            ElementType::Path(p) => p.texture(),
+            ElementType::StepBadge(b) => b.texture(),
+            ElementType::StickyNote(n) => n.texture(),
             // ElementType::Text(t) => t.texture(),
         }
     }
@@ -199,6 +521,10 @@ impl Element for ElementType {
         match self {
             ElementType::Stroke(s) => s.needs_texture_update(),
             ElementType::Image(i) => i.needs_texture_update(),
+            ElementType::Shape(s) => s.needs_texture_update(),
+            ElementType::Path(p) => p.needs_texture_update(),
+            ElementType::StepBadge(b) => b.needs_texture_update(),
+            ElementType::StickyNote(n) => n.needs_texture_update(),
             // ElementType::Text(t) => t.needs_texture_update(),
         }
     }
@@ -207,6 +533,10 @@ impl Element for ElementType {
         match self {
             ElementType::Stroke(s) => s.texture_version(),
             ElementType::Image(i) => i.texture_version(),
+            ElementType::Shape(s) => s.texture_version(),
+            ElementType::Path(p) => p.texture_version(),
+            ElementType::StepBadge(b) => b.texture_version(),
+            ElementType::StickyNote(n) => n.texture_version(),
             // ElementType::Text(t) => t.texture_version(),
         }
     }
@@ -215,6 +545,10 @@ impl Element for ElementType {
         match self {
             ElementType::Stroke(s) => s.invalidate_texture(),
             ElementType::Image(i) => i.invalidate_texture(),
+            ElementType::Shape(s) => s.invalidate_texture(),
+            ElementType::Path(p) => p.invalidate_texture(),
+            ElementType::StepBadge(b) => b.invalidate_texture(),
+            ElementType::StickyNote(n) => n.invalidate_texture(),
             // ElementType::Text(t) => t.invalidate_texture(),
         }
     }
@@ -223,6 +557,10 @@ impl Element for ElementType {
         match self {
             ElementType::Stroke(s) => s.generate_texture(ctx),
             ElementType::Image(i) => i.generate_texture(ctx),
+            ElementType::Shape(s) => s.generate_texture(ctx),
+            ElementType::Path(p) => p.generate_texture(ctx),
+            ElementType::StepBadge(b) => b.generate_texture(ctx),
+            ElementType::StickyNote(n) => n.generate_texture(ctx),
             // ElementType::Text(t) => t.generate_texture(ctx),
         }
     }
@@ -244,7 +582,7 @@ pub mod factory {
     /// # Returns
     /// A new stroke element
     pub fn create_stroke(
-        id: usize,
+        id: ElementId,
         points: Vec<Pos2>,
         thickness: f32,
         color: Color32,
@@ -252,6 +590,19 @@ pub mod factory {
         ElementType::Stroke(stroke::Stroke::new(id, points, thickness, color))
     }
 
+    /// Create a new stroke element with the given taper, caps and joins
+    pub fn create_styled_stroke(
+        id: ElementId,
+        points: Vec<Pos2>,
+        thickness: f32,
+        color: Color32,
+        style: StrokeStyle,
+    ) -> ElementType {
+        let mut stroke = stroke::Stroke::new(id, points, thickness, color);
+        stroke.set_style(style);
+        ElementType::Stroke(stroke)
+    }
+
     /// Create a new image element
     /// 
     /// # Arguments
@@ -262,10 +613,139 @@ pub mod factory {
     ///
     /// # Returns
     /// A new image element
-    pub fn create_image(id: usize, data: Vec<u8>, size: Vec2, position: Pos2) -> ElementType {
+    pub fn create_image(id: ElementId, data: Vec<u8>, size: Vec2, position: Pos2) -> ElementType {
         ElementType::Image(image::Image::new(id, data, size, position))
     }
 
+    /// Create a new image element linked to a file rather than embedding it
+    ///
+    /// Only the link is saved with the document; the data is read from it
+    /// again when the document is opened.
+    ///
+    /// # Arguments
+    /// * `id` - Unique identifier for the element
+    /// * `link` - File path, relative to the document's folder or absolute, or URL
+    /// * `data` - Encoded image data read from the link
+    /// * `size` - Size of the image in pixels
+    /// * `position` - Position of the top-left corner
+    ///
+    /// # Returns
+    /// A new image element
+    pub fn create_linked_image(
+        id: ElementId,
+        link: String,
+        data: Vec<u8>,
+        size: Vec2,
+        position: Pos2,
+    ) -> ElementType {
+        let mut image = image::Image::new(id, Vec::new(), size, position);
+        image.set_source(Some(link), data);
+        ElementType::Image(image)
+    }
+
+    /// Create a new filled shape element
+    ///
+    /// # Arguments
+    /// * `id` - Unique identifier for the element
+    /// * `points` - Closed outline of the shape
+    /// * `fill` - Fill color
+    ///
+    /// # Returns
+    /// A new shape element
+    pub fn create_shape(id: ElementId, points: Vec<Pos2>, fill: Color32) -> ElementType {
+        ElementType::Shape(shape::Shape::new(id, points, fill))
+    }
+
+    /// Create a new polygon or star shape element
+    ///
+    /// # Arguments
+    /// * `id` - Unique identifier for the element
+    /// * `params` - Sides, star ratio, corner radius and placement of the polygon
+    /// * `fill` - Fill color
+    ///
+    /// # Returns
+    /// A new shape element whose parameters stay editable
+    pub fn create_polygon(id: ElementId, params: PolygonParams, fill: Color32) -> ElementType {
+        ElementType::Shape(shape::Shape::new_polygon(id, params, fill))
+    }
+
+    /// Create a new Bézier path element
+    ///
+    /// # Arguments
+    /// * `id` - Unique identifier for the element
+    /// * `nodes` - Anchors and control handles of the path
+    /// * `closed` - Whether the last node connects back to the first
+    /// * `thickness` - Line thickness
+    /// * `color` - Line color
+    ///
+    /// # Returns
+    /// A new path element
+    pub fn create_path(
+        id: ElementId,
+        nodes: Vec<path::PathNode>,
+        closed: bool,
+        thickness: f32,
+        color: Color32,
+    ) -> ElementType {
+        ElementType::Path(path::Path::new(id, nodes, closed, thickness, color))
+    }
+
+    /// Create a new step badge element
+    ///
+    /// # Arguments
+    /// * `id` - Unique identifier for the element
+    /// * `center` - Center of the badge
+    /// * `number` - Step number shown on the badge
+    /// * `color` - Fill color
+    ///
+    /// # Returns
+    /// A new step badge element, named after its step
+    pub fn create_step_badge(
+        id: ElementId,
+        center: Pos2,
+        number: u32,
+        color: Color32,
+    ) -> ElementType {
+        ElementType::StepBadge(step_badge::StepBadge::new(id, center, number, color))
+    }
+
+    /// Create a new sticky note element
+    ///
+    /// # Arguments
+    /// * `id` - Unique identifier for the element
+    /// * `rect` - Rectangle the note covers
+    /// * `text` - Text written on the note, wrapped to its width
+    /// * `color` - Fill color
+    ///
+    /// # Returns
+    /// A new sticky note element
+    pub fn create_sticky_note(
+        id: ElementId,
+        rect: Rect,
+        text: String,
+        color: Color32,
+    ) -> ElementType {
+        let mut note = sticky_note::StickyNote::new(id, rect, color);
+        note.set_text(text);
+        ElementType::StickyNote(note)
+    }
+
+    /// Create a new callout: a sticky note drawn as a speech bubble outline
+    ///
+    /// # Arguments
+    /// * `id` - Unique identifier for the element
+    /// * `body` - Rectangle the bubble's text sits in
+    /// * `tip` - Point the bubble's tail reaches, outside `body`
+    /// * `color` - Outline color
+    ///
+    /// # Returns
+    /// A new callout element, with no text yet
+    pub fn create_callout(id: ElementId, body: Rect, tip: Pos2, color: Color32) -> ElementType {
+        ElementType::StickyNote(sticky_note::StickyNote::new_callout(
+            id, body, tip, color,
+        ))
+    }
+
     // We'll add text factory later
     /*
     /// Create a new text element
@@ -279,7 +759,7 @@ pub mod factory {
     /// # Returns
     /// A new text element
     pub fn create_text(
-        id: usize,
+        id: ElementId,
         content: String,
         font: egui::FontId,
         position: Pos2
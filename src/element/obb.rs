@@ -0,0 +1,107 @@
+use egui::{Pos2, Rect, Vec2};
+
+/// An oriented bounding box: an element's own rectangle, rotated about its center
+///
+/// Elements keep their geometry unrotated; this maps between that element-local
+/// frame and the document, for drawing selection boxes, placing handles and hit
+/// testing. With no rotation it's just the rectangle.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct Obb {
+    /// The rectangle before rotation
+    pub rect: Rect,
+    /// Clockwise rotation about the rectangle's center, in radians
+    pub rotation: f32,
+}
+
+impl Obb {
+    pub fn new(rect: Rect, rotation: f32) -> Self {
+        Self { rect, rotation }
+    }
+
+    pub fn center(&self) -> Pos2 {
+        self.rect.center()
+    }
+
+    /// The same box grown by `amount` on every side
+    pub fn expand(&self, amount: f32) -> Self {
+        Self::new(self.rect.expand(amount), self.rotation)
+    }
+
+    /// Map a document position into the element-local frame
+    pub fn to_local(&self, pos: Pos2) -> Pos2 {
+        self.center() + rotate(pos - self.center(), -self.rotation)
+    }
+
+    /// Map an element-local position into the document
+    pub fn to_world(&self, pos: Pos2) -> Pos2 {
+        self.center() + rotate(pos - self.center(), self.rotation)
+    }
+
+    /// Corners in drawing order: top-left, top-right, bottom-right, bottom-left
+    pub fn corners(&self) -> [Pos2; 4] {
+        [
+            self.rect.left_top(),
+            self.rect.right_top(),
+            self.rect.right_bottom(),
+            self.rect.left_bottom(),
+        ]
+        .map(|corner| self.to_world(corner))
+    }
+
+    /// The smallest axis-aligned rectangle around the rotated box
+    pub fn bounding_rect(&self) -> Rect {
+        if self.rotation == 0.0 {
+            return self.rect;
+        }
+        Rect::from_points(&self.corners())
+    }
+
+    pub fn contains(&self, pos: Pos2) -> bool {
+        self.rect.contains(self.to_local(pos))
+    }
+}
+
+impl From<Rect> for Obb {
+    fn from(rect: Rect) -> Self {
+        Self::new(rect, 0.0)
+    }
+}
+
+/// `vector` turned clockwise (on screen, where y points down) by `angle` radians
+fn rotate(vector: Vec2, angle: f32) -> Vec2 {
+    if angle == 0.0 {
+        return vector;
+    }
+    let (sin, cos) = angle.sin_cos();
+    Vec2::new(
+        vector.x * cos - vector.y * sin,
+        vector.x * sin + vector.y * cos,
+    )
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::f32::consts::FRAC_PI_2;
+
+    #[test]
+    fn test_rotated_box_maps_between_frames() {
+        let rect = Rect::from_min_max(Pos2::new(0.0, 0.0), Pos2::new(100.0, 20.0));
+        let obb = Obb::new(rect, FRAC_PI_2);
+
+        // A quarter turn stands the box on end around its center
+        let bounds = obb.bounding_rect();
+        assert!((bounds.width() - 20.0).abs() < 0.001);
+        assert!((bounds.height() - 100.0).abs() < 0.001);
+        assert!((obb.corners()[0] - Pos2::new(60.0, -40.0)).length() < 0.001);
+
+        assert!(obb.contains(Pos2::new(50.0, -30.0)));
+        assert!(!obb.contains(Pos2::new(90.0, 10.0)));
+        let pos = Pos2::new(12.0, 34.0);
+        assert!((obb.to_world(obb.to_local(pos)) - pos).length() < 0.001);
+
+        // Unrotated, it's just the rectangle
+        assert_eq!(Obb::from(rect).bounding_rect(), rect);
+        assert_eq!(Obb::from(rect).to_local(pos), pos);
+    }
+}
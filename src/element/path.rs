@@ -0,0 +1,460 @@
+use egui::{
+    Color32, ColorImage, Context, Painter, Pos2, Rect, Stroke as EguiStroke, TextureHandle, Vec2,
+};
+use log::trace;
+use serde::{Deserialize, Serialize};
+use std::sync::OnceLock;
+
+use super::stroke::{Stroke, StrokeStyle};
+use super::{Element, ElementId, ElementMetadata, Transform};
+use crate::element::common;
+use crate::texture_manager::TextureGenerationError;
+
+/// Line segments each curve is flattened into for drawing and hit testing
+const CURVE_SEGMENTS: usize = 24;
+
+/// An anchor point of a path with the control handles of the curves on either side
+///
+/// Handles are absolute positions; a handle sitting on its anchor makes that
+/// side of the node a sharp corner.
+#[derive(Clone, Copy, Debug, PartialEq, Serialize, Deserialize)]
+pub struct PathNode {
+    pub anchor: Pos2,
+    /// Controls the curve arriving at the anchor
+    pub handle_in: Pos2,
+    /// Controls the curve leaving the anchor
+    pub handle_out: Pos2,
+}
+
+impl PathNode {
+    /// A corner node with both handles on the anchor
+    pub fn corner(anchor: Pos2) -> Self {
+        Self {
+            anchor,
+            handle_in: anchor,
+            handle_out: anchor,
+        }
+    }
+
+    /// A smooth node whose incoming handle mirrors `handle_out`
+    pub fn smooth(anchor: Pos2, handle_out: Pos2) -> Self {
+        Self {
+            anchor,
+            handle_in: anchor - (handle_out - anchor),
+            handle_out,
+        }
+    }
+
+    /// The node moved by `delta`, handles included
+    pub fn translated(self, delta: Vec2) -> Self {
+        Self {
+            anchor: self.anchor + delta,
+            handle_in: self.handle_in + delta,
+            handle_out: self.handle_out + delta,
+        }
+    }
+
+    /// The node with its anchor and handles mapped by `f`
+    pub fn mapped(self, f: impl Fn(Pos2) -> Pos2) -> Self {
+        Self {
+            anchor: f(self.anchor),
+            handle_in: f(self.handle_in),
+            handle_out: f(self.handle_out),
+        }
+    }
+}
+
+/// Point at `t` along the cubic Bézier curve from `from` to `to`
+fn cubic_point(from: &PathNode, to: &PathNode, t: f32) -> Pos2 {
+    let u = 1.0 - t;
+    let [p0, p1, p2, p3] =
+        [from.anchor, from.handle_out, to.handle_in, to.anchor].map(Pos2::to_vec2);
+    (p0 * (u * u * u) + p1 * (3.0 * u * u * t) + p2 * (3.0 * u * t * t) + p3 * (t * t * t))
+        .to_pos2()
+}
+
+/// Polyline following the curves through `nodes`, closing back to the first if `closed`
+pub(crate) fn flatten_nodes(nodes: &[PathNode], closed: bool) -> Vec<Pos2> {
+    let Some(first) = nodes.first() else {
+        return Vec::new();
+    };
+    let closing = closed.then(|| (nodes.last().unwrap_or(first), first));
+
+    let mut points = vec![first.anchor];
+    for (from, to) in nodes.iter().zip(nodes.iter().skip(1)).chain(closing) {
+        points.extend(
+            (1..=CURVE_SEGMENTS)
+                .map(|step| cubic_point(from, to, step as f32 / CURVE_SEGMENTS as f32)),
+        );
+    }
+    points
+}
+
+/// Path element: cubic Bézier curves through a chain of nodes, drawn as a line
+#[derive(Clone, Serialize, Deserialize)]
+pub struct Path {
+    // Core properties
+    id: ElementId,
+    nodes: Vec<PathNode>,
+    closed: bool,
+    color: Color32,
+    thickness: f32,
+    #[serde(default)]
+    style: StrokeStyle,
+    // Where the nodes are drawn; moving and resizing only change this
+    #[serde(default)]
+    transform: Transform,
+
+    // User-facing name and metadata
+    #[serde(default)]
+    metadata: ElementMetadata,
+
+    // The nodes placed by the transform, found on first use
+    #[serde(skip)]
+    placed: OnceLock<Vec<PathNode>>,
+
+    // Texture caching
+    #[serde(skip)]
+    texture_handle: Option<TextureHandle>,
+    #[serde(skip, default = "common::texture_needs_update_default")]
+    texture_needs_update: bool,
+    #[serde(skip)]
+    texture_version: u64,
+}
+
+// Custom Debug implementation since TextureHandle doesn't implement Debug
+impl std::fmt::Debug for Path {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("Path")
+            .field("id", &self.id)
+            .field("nodes", &self.nodes)
+            .field("closed", &self.closed)
+            .field("color", &self.color)
+            .field("thickness", &self.thickness)
+            .field("style", &self.style)
+            .field("transform", &self.transform)
+            .field("metadata", &self.metadata)
+            .field("texture_needs_update", &self.texture_needs_update)
+            .field("texture_version", &self.texture_version)
+            .finish()
+    }
+}
+
+impl Path {
+    /// Create a new path through the given nodes
+    pub(crate) fn new(
+        id: ElementId,
+        nodes: Vec<PathNode>,
+        closed: bool,
+        thickness: f32,
+        color: Color32,
+    ) -> Self {
+        Self {
+            id,
+            nodes,
+            closed,
+            color,
+            thickness,
+            style: StrokeStyle::default(),
+            transform: Transform::IDENTITY,
+            metadata: ElementMetadata::default(),
+            placed: OnceLock::new(),
+            texture_handle: None,
+            texture_needs_update: true,
+            texture_version: 0,
+        }
+    }
+
+    /// A copy of this path under another id
+    pub(crate) fn with_id(&self, id: ElementId) -> Self {
+        Self { id, ..self.clone() }
+    }
+
+    /// A path of curves fitted to `stroke` within `tolerance`, with the same id,
+    /// look and metadata
+    ///
+    /// Returns None if the path wouldn't store fewer points than the stroke,
+    /// counting each node's handles.
+    pub(crate) fn fitted_to(stroke: &Stroke, tolerance: f32) -> Option<Self> {
+        let curves = crate::geometry::fit_cubic_beziers(stroke.points(), tolerance);
+        let first = curves.first()?;
+        if (curves.len() + 1) * 3 >= stroke.points().len() {
+            return None;
+        }
+
+        // Each curve's handles go on the nodes at either end of it
+        let mut nodes = vec![PathNode::corner(first[0])];
+        for curve in &curves {
+            if let Some(node) = nodes.last_mut() {
+                node.handle_out = curve[1];
+            }
+            nodes.push(PathNode {
+                anchor: curve[3],
+                handle_in: curve[2],
+                handle_out: curve[3],
+            });
+        }
+
+        let mut path = Self::new(
+            stroke.id(),
+            nodes,
+            false,
+            stroke.thickness(),
+            stroke.color(),
+        );
+        path.style = stroke.style();
+        path.transform = Transform::rotated(stroke.rotation());
+        path.metadata = stroke.metadata().clone();
+        Some(path)
+    }
+
+    /// Get the nodes of this path, placed by its transform
+    pub(crate) fn nodes(&self) -> &[PathNode] {
+        self.transform
+            .place_all(&self.nodes, &self.placed, |transform, node| {
+                node.mapped(|point| transform.apply(point))
+            })
+    }
+
+    /// Whether the last node connects back to the first
+    pub(crate) fn closed(&self) -> bool {
+        self.closed
+    }
+
+    /// Get the line color
+    pub(crate) fn color(&self) -> Color32 {
+        self.color
+    }
+
+    /// Change the line color
+    pub(crate) fn set_color(&mut self, color: Color32) {
+        self.color = color;
+        self.invalidate_texture();
+    }
+
+    /// Get the line thickness, scaled with the path
+    pub(crate) fn thickness(&self) -> f32 {
+        self.thickness * self.transform.line_scale()
+    }
+
+    pub(crate) fn style(&self) -> StrokeStyle {
+        self.style
+    }
+
+    pub(crate) fn set_style(&mut self, style: StrokeStyle) {
+        self.style = style;
+        self.invalidate_texture();
+    }
+
+    /// Replace one node with one placed like [`Self::nodes`], keeping everything else
+    pub(crate) fn set_node(&mut self, index: usize, node: PathNode) -> Result<(), String> {
+        let transform = self.transform;
+        let slot = self
+            .nodes
+            .get_mut(index)
+            .ok_or_else(|| format!("Path {} has no node {}", self.id, index))?;
+        *slot = node.mapped(|point| transform.invert(point));
+        self.placed = OnceLock::new();
+        self.invalidate_texture();
+        Ok(())
+    }
+
+    /// Store the nodes where they're drawn, turned and all, and drop the transform
+    pub(crate) fn flatten_transform(&mut self) {
+        let obb = self.obb();
+        self.nodes = self
+            .nodes()
+            .iter()
+            .map(|node| node.mapped(|point| obb.to_world(point)))
+            .collect();
+        self.thickness = self.thickness();
+        self.transform = Transform::IDENTITY;
+        self.placed = OnceLock::new();
+        self.invalidate_texture();
+    }
+
+    /// The path as a polyline close enough to the curves for drawing
+    pub(crate) fn flatten(&self) -> Vec<Pos2> {
+        flatten_nodes(self.nodes(), self.closed)
+    }
+
+    /// Distance from `pos` to the nearest point on the path's line
+    pub(crate) fn distance_to(&self, pos: Pos2) -> f32 {
+        let points = self.flatten();
+        if let [point] = points.as_slice() {
+            return point.distance(pos);
+        }
+        points
+            .windows(2)
+            .map(|window| common::distance_to_line_segment(pos, window[0], window[1]))
+            .fold(f32::INFINITY, f32::min)
+    }
+
+    /// The flattened path as a stroke, which paths are rasterized like
+    fn as_stroke(&self) -> Stroke {
+        let mut stroke = Stroke::new(self.id, self.flatten(), self.thickness(), self.color);
+        stroke.set_style(self.style);
+        stroke
+    }
+
+    /// Get the area covered by the generated texture (the bounds plus thickness padding)
+    pub(crate) fn texture_rect(&self) -> Rect {
+        self.as_stroke().texture_rect()
+    }
+}
+
+impl Element for Path {
+    fn id(&self) -> ElementId {
+        self.id
+    }
+
+    fn element_type(&self) -> &'static str {
+        "path"
+    }
+
+    fn metadata(&self) -> &ElementMetadata {
+        &self.metadata
+    }
+
+    fn metadata_mut(&mut self) -> &mut ElementMetadata {
+        &mut self.metadata
+    }
+
+    fn rect(&self) -> Rect {
+        common::calculate_bounds(&self.flatten(), self.thickness() / 2.0)
+    }
+
+    fn transform(&self) -> Transform {
+        self.transform
+    }
+
+    fn set_transform(&mut self, transform: Transform) {
+        self.transform = transform;
+        self.placed = OnceLock::new();
+        self.invalidate_texture();
+    }
+
+    fn draw(&self, painter: &Painter) {
+        let points = self.flatten();
+        if points.len() < 2 {
+            return;
+        }
+
+        painter.add(egui::Shape::line(
+            points,
+            EguiStroke::new(self.thickness(), self.color),
+        ));
+    }
+
+    fn hit_test(&self, pos: Pos2) -> bool {
+        self.nodes.len() >= 2 && self.distance_to(pos) <= self.thickness() / 2.0
+    }
+
+    fn translate(&mut self, delta: Vec2) -> Result<(), String> {
+        self.transform = self.transform.translated(delta);
+        self.placed = OnceLock::new();
+
+        self.invalidate_texture();
+        Ok(())
+    }
+
+    fn resize(&mut self, new_rect: Rect) -> Result<(), String> {
+        common::validate_rect(&new_rect)?;
+
+        let old_rect = self.rect();
+        if old_rect == Rect::NOTHING {
+            return Err("Cannot resize empty path".to_string());
+        }
+
+        // Stretch every anchor and handle from the old bounds over the new ones,
+        // thickness along with them like strokes, by scaling the transform
+        self.transform = self.transform.resized(old_rect, new_rect);
+        self.placed = OnceLock::new();
+
+        self.invalidate_texture();
+        Ok(())
+    }
+
+    fn texture(&self) -> Option<&TextureHandle> {
+        self.texture_handle.as_ref()
+    }
+
+    fn needs_texture_update(&self) -> bool {
+        self.texture_needs_update
+    }
+
+    fn texture_version(&self) -> u64 {
+        self.texture_version
+    }
+
+    fn invalidate_texture(&mut self) {
+        self.texture_needs_update = true;
+        self.texture_version += 1;
+    }
+
+    fn generate_texture(&mut self, ctx: &Context) -> Result<ColorImage, TextureGenerationError> {
+        if self.nodes.is_empty() {
+            return Err(TextureGenerationError::InvalidDimensions);
+        }
+
+        trace!(id:% = self.id, nodes = self.nodes.len(); "Generating texture");
+        let result = self.as_stroke().generate_texture(ctx);
+
+        // Mark as not needing update if successful
+        if result.is_ok() {
+            self.texture_needs_update = false;
+        }
+
+        result
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_flatten_passes_through_anchors_and_closes() {
+        let nodes = vec![
+            PathNode::corner(Pos2::new(0.0, 0.0)),
+            PathNode::smooth(Pos2::new(100.0, 0.0), Pos2::new(100.0, 50.0)),
+            PathNode::corner(Pos2::new(100.0, 100.0)),
+        ];
+        let mut path = Path::new(ElementId::new(1), nodes, false, 2.0, Color32::BLACK);
+
+        let points = path.flatten();
+        assert_eq!(points.len(), 1 + 2 * CURVE_SEGMENTS);
+        assert_eq!(points[CURVE_SEGMENTS], Pos2::new(100.0, 0.0));
+        assert_eq!(*points.last().unwrap(), Pos2::new(100.0, 100.0));
+        // The smooth node's incoming handle pulls the first curve above the anchors
+        assert!(points.iter().any(|point| point.y < 0.0));
+
+        path.closed = true;
+        assert_eq!(path.flatten().len(), 1 + 3 * CURVE_SEGMENTS);
+        assert_eq!(*path.flatten().last().unwrap(), Pos2::new(0.0, 0.0));
+    }
+
+    #[test]
+    fn test_fitted_path_follows_a_dense_stroke() {
+        // A wavy line sampled every half point, as fast drawing interpolates it
+        let points: Vec<Pos2> = (0..=800)
+            .map(|step| {
+                let x = step as f32 * 0.5;
+                Pos2::new(x, 40.0 * (x / 60.0).sin())
+            })
+            .collect();
+        let stroke = Stroke::new(ElementId::new(7), points.clone(), 3.0, Color32::RED);
+
+        let path = Path::fitted_to(&stroke, 1.0).unwrap();
+        assert_eq!(path.id(), stroke.id());
+        assert!(path.nodes().len() * 3 * 10 < points.len());
+        assert_eq!(path.nodes()[0].anchor, points[0]);
+        assert_eq!(path.nodes().last().unwrap().anchor, points[800]);
+        // Every stroke point stays within the tolerance, allowing for flattening
+        assert!(points.iter().all(|point| path.distance_to(*point) < 1.5));
+
+        // A stroke with few points has nothing to gain
+        let short = Stroke::new(ElementId::new(8), points[..4].to_vec(), 3.0, Color32::RED);
+        assert!(Path::fitted_to(&short, 1.0).is_none());
+    }
+}
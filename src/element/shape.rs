@@ -0,0 +1,501 @@
+use egui::{
+    Color32, ColorImage, Context, Painter, Pos2, Rect, Stroke as EguiStroke, TextureHandle, Vec2,
+};
+use log::trace;
+use serde::{Deserialize, Serialize};
+use std::sync::OnceLock;
+
+use super::{Element, ElementId, ElementMetadata, Transform};
+use crate::element::common;
+use crate::texture_manager::TextureGenerationError;
+
+/// Parameters of a regular polygon or star, kept on shapes made from them so
+/// they stay editable
+#[derive(Clone, Copy, Debug, PartialEq, Serialize, Deserialize)]
+pub struct PolygonParams {
+    pub center: Pos2,
+    /// Horizontal and vertical distance from the center to the outer corners
+    pub radius: Vec2,
+    pub sides: u32,
+    /// Distance of a star's inner corners relative to its outer ones; 1 for a plain polygon
+    pub star_ratio: f32,
+    pub corner_radius: f32,
+    /// Direction of the first outer corner, in radians
+    pub rotation: f32,
+}
+
+impl PolygonParams {
+    /// Fewest sides a polygon can have
+    pub const MIN_SIDES: u32 = 3;
+    /// Most sides offered for editing
+    pub const MAX_SIDES: u32 = 64;
+
+    /// The parameters of the polygon placed by `transform`, leaving its rotation aside
+    pub fn placed(&self, transform: &Transform) -> Self {
+        Self {
+            center: transform.apply(self.center),
+            radius: self.radius * transform.scale,
+            corner_radius: self.corner_radius * transform.scale.min_elem(),
+            ..*self
+        }
+    }
+
+    /// Outline through the corners, rounded by the corner radius
+    pub fn outline(&self) -> Vec<Pos2> {
+        let sides = self.sides.max(Self::MIN_SIDES);
+        let star = self.star_ratio < 1.0;
+        let corners = if star { sides * 2 } else { sides };
+        let step = std::f32::consts::TAU / corners as f32;
+
+        let points: Vec<Pos2> = (0..corners)
+            .map(|index| {
+                let scale = if star && index % 2 == 1 {
+                    self.star_ratio.max(0.0)
+                } else {
+                    1.0
+                };
+                let direction = Vec2::angled(self.rotation + step * index as f32);
+                self.center + direction * self.radius * scale
+            })
+            .collect();
+        crate::geometry::round_corners(&points, self.corner_radius)
+    }
+}
+
+/// A repeating image used to fill a shape instead of its solid color
+///
+/// The image's encoded bytes are copied in when the pattern is chosen, so the
+/// fill stays intact if the source image is later moved, resized or deleted.
+#[derive(Clone, PartialEq, Serialize, Deserialize)]
+pub struct PatternFill {
+    /// Image element the pattern was taken from
+    pub source_id: ElementId,
+    /// Encoded image data (PNG, JPEG, ...)
+    pub image_data: Vec<u8>,
+    /// Size of one tile at scale 1: the source image's size in the document
+    pub tile_size: Vec2,
+    /// Tile size multiplier
+    pub scale: f32,
+    /// Shift of the tiles from the shape's top-left corner, in document units
+    pub offset: Vec2,
+}
+
+impl PatternFill {
+    /// Smallest tile scale allowed
+    pub const MIN_SCALE: f32 = 0.05;
+
+    /// A pattern repeating `image_data` at its size in the document
+    pub fn new(source_id: ElementId, image_data: Vec<u8>, tile_size: Vec2) -> Self {
+        Self {
+            source_id,
+            image_data,
+            tile_size,
+            scale: 1.0,
+            offset: Vec2::ZERO,
+        }
+    }
+
+    /// Size of one tile in document units
+    pub fn scaled_tile_size(&self) -> Vec2 {
+        self.tile_size * self.scale.max(Self::MIN_SCALE)
+    }
+
+    /// Decode the image as premultiplied pixels, or None if it can't be read
+    fn decode(&self) -> Option<ColorImage> {
+        let rgba = ::image::load_from_memory(&self.image_data).ok()?.to_rgba8();
+        let size = [rgba.width() as usize, rgba.height() as usize];
+        (size[0] > 0 && size[1] > 0)
+            .then(|| ColorImage::from_rgba_unmultiplied(size, rgba.as_raw()))
+    }
+}
+
+// Custom Debug implementation to keep the image bytes out of logs
+impl std::fmt::Debug for PatternFill {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("PatternFill")
+            .field("source_id", &self.source_id)
+            .field("image_bytes", &self.image_data.len())
+            .field("tile_size", &self.tile_size)
+            .field("scale", &self.scale)
+            .field("offset", &self.offset)
+            .finish()
+    }
+}
+
+/// Shape element: a closed polygon filled with a solid color or an image pattern
+///
+/// The outline may overlap itself; it is filled with the nonzero winding rule.
+#[derive(Clone, Serialize, Deserialize)]
+pub struct Shape {
+    // Core properties
+    id: ElementId,
+    points: Vec<Pos2>,
+    fill: Color32,
+    // Set for shapes drawn with the polygon tool, None for free-form shapes
+    #[serde(default)]
+    polygon: Option<PolygonParams>,
+    // Tiled image drawn instead of the fill color when set
+    #[serde(default)]
+    pattern: Option<PatternFill>,
+    // Where the outline is drawn; moving and resizing only change this
+    #[serde(default)]
+    transform: Transform,
+
+    // User-facing name and metadata
+    #[serde(default)]
+    metadata: ElementMetadata,
+
+    // The outline placed by the transform, found on first use
+    #[serde(skip)]
+    placed: OnceLock<Vec<Pos2>>,
+
+    // Texture caching
+    #[serde(skip)]
+    texture_handle: Option<TextureHandle>,
+    #[serde(skip, default = "common::texture_needs_update_default")]
+    texture_needs_update: bool,
+    #[serde(skip)]
+    texture_version: u64,
+}
+
+// Custom Debug implementation since TextureHandle doesn't implement Debug
+impl std::fmt::Debug for Shape {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("Shape")
+            .field("id", &self.id)
+            .field("points", &self.points)
+            .field("fill", &self.fill)
+            .field("polygon", &self.polygon)
+            .field("pattern", &self.pattern)
+            .field("transform", &self.transform)
+            .field("metadata", &self.metadata)
+            .field("texture_needs_update", &self.texture_needs_update)
+            .field("texture_version", &self.texture_version)
+            .finish()
+    }
+}
+
+impl Shape {
+    /// Create a new shape with the given outline and fill
+    pub(crate) fn new(id: ElementId, points: Vec<Pos2>, fill: Color32) -> Self {
+        Self {
+            id,
+            points,
+            fill,
+            polygon: None,
+            pattern: None,
+            transform: Transform::IDENTITY,
+            metadata: ElementMetadata::default(),
+            placed: OnceLock::new(),
+            texture_handle: None,
+            texture_needs_update: true,
+            texture_version: 0,
+        }
+    }
+
+    /// A copy of this shape under another id
+    pub(crate) fn with_id(&self, id: ElementId) -> Self {
+        Self { id, ..self.clone() }
+    }
+
+    /// Create a new polygon or star shape from its parameters
+    pub(crate) fn new_polygon(id: ElementId, params: PolygonParams, fill: Color32) -> Self {
+        let mut shape = Self::new(id, params.outline(), fill);
+        shape.polygon = Some(params);
+        shape
+    }
+
+    /// Get the parameters this shape was generated from, placed like its
+    /// outline, if it is a polygon
+    pub(crate) fn polygon(&self) -> Option<PolygonParams> {
+        self.polygon.map(|params| params.placed(&self.transform))
+    }
+
+    /// Regenerate a polygon shape from new parameters, placed where the shape
+    /// is drawn
+    ///
+    /// Only the rotation is kept of the transform.
+    pub(crate) fn set_polygon(&mut self, params: PolygonParams) -> Result<(), String> {
+        if self.polygon.is_none() {
+            return Err(format!("Shape {} is not a polygon", self.id));
+        }
+        self.points = params.outline();
+        self.polygon = Some(params);
+        self.transform = Transform::rotated(self.transform.rotation);
+        self.placed = OnceLock::new();
+        self.invalidate_texture();
+        Ok(())
+    }
+
+    /// Get the outline of this shape, placed by its transform
+    pub(crate) fn points(&self) -> &[Pos2] {
+        self.transform
+            .place_all(&self.points, &self.placed, |transform, point| {
+                transform.apply(*point)
+            })
+    }
+
+    /// Store the outline where it's drawn, turned and all, and drop the transform
+    ///
+    /// A turned polygon is left a plain shape, as its parameters can't be
+    /// turned with a stretched radius.
+    pub(crate) fn flatten_transform(&mut self) {
+        let obb = self.obb();
+        self.points = self
+            .points()
+            .iter()
+            .map(|&point| obb.to_world(point))
+            .collect();
+        self.polygon = self.polygon().filter(|_| self.transform.rotation == 0.0);
+        self.transform = Transform::IDENTITY;
+        self.placed = OnceLock::new();
+        self.invalidate_texture();
+    }
+
+    /// A copy of the shape with its transform flattened, for edits that work
+    /// on the outline as it's drawn
+    pub(crate) fn flattened(&self) -> Self {
+        let mut shape = self.clone();
+        shape.flatten_transform();
+        shape
+    }
+
+    /// Get the fill color
+    pub(crate) fn fill(&self) -> Color32 {
+        self.fill
+    }
+
+    /// Change the fill color
+    pub(crate) fn set_fill(&mut self, fill: Color32) {
+        self.fill = fill;
+        self.invalidate_texture();
+    }
+
+    /// Get the image pattern filling this shape, if any
+    pub(crate) fn pattern(&self) -> Option<&PatternFill> {
+        self.pattern.as_ref()
+    }
+
+    /// Fill with an image pattern, or with the solid fill color again for None
+    pub(crate) fn set_pattern(&mut self, pattern: Option<PatternFill>) {
+        self.pattern = pattern;
+        self.invalidate_texture();
+    }
+
+    /// Internal helper for generating a texture representation (used by the trait implementation)
+    fn internal_generate_texture(&mut self) -> Result<ColorImage, TextureGenerationError> {
+        if self.points.len() < 3 {
+            return Err(TextureGenerationError::InvalidDimensions);
+        }
+
+        trace!(id:% = self.id, points = self.points.len(); "Generating texture");
+        let points = self.points();
+
+        let bounds = self.rect();
+        let width = bounds.width().ceil().max(1.0) as usize;
+        let height = bounds.height().ceil().max(1.0) as usize;
+        let mut image = ColorImage::new([width, height], Color32::TRANSPARENT);
+
+        // Pattern tiles are anchored to the shape's top-left corner, so they move with it
+        let pattern = self.pattern.as_ref().and_then(|pattern| {
+            let tile = pattern.decode()?;
+            Some((tile, pattern.scaled_tile_size(), pattern.offset))
+        });
+        let fill_at = |column: usize, row: usize| match &pattern {
+            Some((tile, tile_size, offset)) => {
+                let local = Vec2::new(column as f32 + 0.5, row as f32 + 0.5) - *offset;
+                let u = local.x.rem_euclid(tile_size.x) / tile_size.x;
+                let v = local.y.rem_euclid(tile_size.y) / tile_size.y;
+                let x = ((u * tile.size[0] as f32) as usize).min(tile.size[0] - 1);
+                let y = ((v * tile.size[1] as f32) as usize).min(tile.size[1] - 1);
+                tile.pixels[y * tile.size[0] + x]
+            }
+            None => self.fill,
+        };
+
+        // Scanline fill through pixel centers, keeping track of the winding number
+        let edges: Vec<(Pos2, Pos2)> = points
+            .iter()
+            .zip(points.iter().cycle().skip(1))
+            .map(|(&start, &end)| (start - bounds.min.to_vec2(), end - bounds.min.to_vec2()))
+            .collect();
+        let mut crossings: Vec<(f32, i32)> = Vec::new();
+        for row in 0..height {
+            let y = row as f32 + 0.5;
+            crossings.clear();
+            for &(start, end) in &edges {
+                let direction = if start.y <= y && end.y > y {
+                    1
+                } else if end.y <= y && start.y > y {
+                    -1
+                } else {
+                    continue;
+                };
+                let t = (y - start.y) / (end.y - start.y);
+                crossings.push((start.x + (end.x - start.x) * t, direction));
+            }
+            crossings.sort_by(|a, b| a.0.total_cmp(&b.0));
+
+            let mut winding = 0;
+            for pair in crossings.windows(2) {
+                winding += pair[0].1;
+                if winding == 0 {
+                    continue;
+                }
+                let first = (pair[0].0 - 0.5).ceil().max(0.0) as usize;
+                let last = ((pair[1].0 - 0.5).ceil().max(0.0) as usize).min(width);
+                for column in first..last {
+                    image.pixels[row * width + column] = fill_at(column, row);
+                }
+            }
+        }
+
+        // Mark as not needing update
+        self.texture_needs_update = false;
+
+        Ok(image)
+    }
+}
+
+impl Element for Shape {
+    fn id(&self) -> ElementId {
+        self.id
+    }
+
+    fn element_type(&self) -> &'static str {
+        "shape"
+    }
+
+    fn metadata(&self) -> &ElementMetadata {
+        &self.metadata
+    }
+
+    fn metadata_mut(&mut self) -> &mut ElementMetadata {
+        &mut self.metadata
+    }
+
+    fn rect(&self) -> Rect {
+        common::calculate_bounds(self.points(), 0.0)
+    }
+
+    fn transform(&self) -> Transform {
+        self.transform
+    }
+
+    fn set_transform(&mut self, transform: Transform) {
+        self.transform = transform;
+        self.placed = OnceLock::new();
+        self.invalidate_texture();
+    }
+
+    fn draw(&self, painter: &Painter) {
+        // Fallback when there's no texture: egui only fills convex polygons,
+        // so draw the outline instead
+        if self.points.len() < 3 {
+            return;
+        }
+
+        painter.add(egui::Shape::closed_line(
+            self.points().to_vec(),
+            EguiStroke::new(1.0, self.fill),
+        ));
+    }
+
+    fn hit_test(&self, pos: Pos2) -> bool {
+        self.points.len() >= 3 && crate::geometry::polygon_contains(self.points(), pos)
+    }
+
+    fn translate(&mut self, delta: Vec2) -> Result<(), String> {
+        self.transform = self.transform.translated(delta);
+        self.placed = OnceLock::new();
+
+        self.invalidate_texture();
+        Ok(())
+    }
+
+    fn resize(&mut self, new_rect: Rect) -> Result<(), String> {
+        common::validate_rect(&new_rect)?;
+
+        let old_rect = self.rect();
+        if old_rect == Rect::NOTHING {
+            return Err("Cannot resize empty shape".to_string());
+        }
+
+        // Stretch the outline from the old bounds over the new ones by scaling
+        // the transform; polygon parameters are placed by it too, so later
+        // edits keep the new size
+        self.transform = self.transform.resized(old_rect, new_rect);
+        self.placed = OnceLock::new();
+
+        self.invalidate_texture();
+        Ok(())
+    }
+
+    fn texture(&self) -> Option<&TextureHandle> {
+        self.texture_handle.as_ref()
+    }
+
+    fn needs_texture_update(&self) -> bool {
+        self.texture_needs_update
+    }
+
+    fn texture_version(&self) -> u64 {
+        self.texture_version
+    }
+
+    fn invalidate_texture(&mut self) {
+        self.texture_needs_update = true;
+        self.texture_version += 1;
+    }
+
+    fn generate_texture(&mut self, _ctx: &Context) -> Result<ColorImage, TextureGenerationError> {
+        let result = self.internal_generate_texture();
+
+        // Mark as not needing update if successful
+        if result.is_ok() {
+            self.texture_needs_update = false;
+        }
+
+        result
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_pattern_fill_tiles_from_the_shape_corner() {
+        let tile = ColorImage::from_rgba_unmultiplied([2, 1], &[255, 0, 0, 255, 0, 0, 255, 255]);
+        let png =
+            crate::export::encode_png(&tile, &crate::export::ExportMetadata::default()).unwrap();
+        let corners = vec![
+            Pos2::new(10.0, 10.0),
+            Pos2::new(14.0, 10.0),
+            Pos2::new(14.0, 12.0),
+            Pos2::new(10.0, 12.0),
+        ];
+        let mut shape = Shape::new(ElementId::new(1), corners, Color32::BLACK);
+        shape.set_pattern(Some(PatternFill::new(
+            ElementId::new(2),
+            png,
+            Vec2::new(2.0, 1.0),
+        )));
+
+        let image = shape.internal_generate_texture().unwrap();
+        let row: Vec<Color32> = image.pixels[..4].to_vec();
+        assert_eq!(
+            row,
+            [Color32::RED, Color32::BLUE, Color32::RED, Color32::BLUE]
+        );
+
+        let mut pattern = shape.pattern().unwrap().clone();
+        pattern.offset = Vec2::new(1.0, 0.0);
+        pattern.scale = 2.0;
+        shape.set_pattern(Some(pattern));
+        let image = shape.internal_generate_texture().unwrap();
+        assert_eq!(image.pixels[0], Color32::BLUE);
+        assert_eq!(image.pixels[1], Color32::RED);
+        assert_eq!(image.pixels[3], Color32::BLUE);
+    }
+}
@@ -0,0 +1,359 @@
+use egui::{
+    Color32, ColorImage, Context, Painter, Pos2, Rect, Stroke as EguiStroke, TextureHandle, Vec2,
+};
+use log::trace;
+use serde::{Deserialize, Serialize};
+
+use super::{Element, ElementId, ElementMetadata, Transform};
+use crate::element::common;
+use crate::texture_manager::TextureGenerationError;
+
+/// Radius new badges get
+const DEFAULT_RADIUS: f32 = 14.0;
+
+// Digit proportions relative to the badge radius
+const DIGIT_HEIGHT: f32 = 0.8;
+const DIGIT_WIDTH: f32 = 0.5;
+const DIGIT_GAP: f32 = 0.25;
+const DIGIT_THICKNESS: f32 = 0.2;
+// Widest the number may get before its digits are shrunk to fit
+const MAX_TEXT_WIDTH: f32 = 1.4;
+
+/// Step badge element: a filled circle with its step number on top in white
+#[derive(Clone, Serialize, Deserialize)]
+pub struct StepBadge {
+    // Core properties
+    id: ElementId,
+    center: Pos2,
+    radius: f32,
+    number: u32,
+    color: Color32,
+    // Turn about the center; the badge lays out its digits for its own size,
+    // so moving and resizing change that rather than a transform
+    #[serde(default)]
+    rotation: f32,
+
+    // User-facing name and metadata
+    #[serde(default)]
+    metadata: ElementMetadata,
+
+    // Texture caching
+    #[serde(skip)]
+    texture_handle: Option<TextureHandle>,
+    #[serde(skip, default = "common::texture_needs_update_default")]
+    texture_needs_update: bool,
+    #[serde(skip)]
+    texture_version: u64,
+}
+
+// Custom Debug implementation since TextureHandle doesn't implement Debug
+impl std::fmt::Debug for StepBadge {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("StepBadge")
+            .field("id", &self.id)
+            .field("center", &self.center)
+            .field("radius", &self.radius)
+            .field("number", &self.number)
+            .field("color", &self.color)
+            .field("rotation", &self.rotation)
+            .field("metadata", &self.metadata)
+            .field("texture_needs_update", &self.texture_needs_update)
+            .field("texture_version", &self.texture_version)
+            .finish()
+    }
+}
+
+/// Default name of the badge for a step
+fn step_name(number: u32) -> String {
+    format!("Step {}", number)
+}
+
+impl StepBadge {
+    /// Create a new badge of the default size, named after its step
+    pub(crate) fn new(id: ElementId, center: Pos2, number: u32, color: Color32) -> Self {
+        Self {
+            id,
+            center,
+            radius: DEFAULT_RADIUS,
+            number,
+            color,
+            rotation: 0.0,
+            metadata: ElementMetadata::with_name(step_name(number)),
+            texture_handle: None,
+            texture_needs_update: true,
+            texture_version: 0,
+        }
+    }
+
+    /// A copy of this step badge under another id
+    pub(crate) fn with_id(&self, id: ElementId) -> Self {
+        Self { id, ..self.clone() }
+    }
+
+    /// Get the step number shown on the badge
+    pub(crate) fn number(&self) -> u32 {
+        self.number
+    }
+
+    /// Get the center of the badge
+    pub(crate) fn center(&self) -> Pos2 {
+        self.center
+    }
+
+    /// Get the radius of the badge
+    pub(crate) fn radius(&self) -> f32 {
+        self.radius
+    }
+
+    /// Get the fill color
+    pub(crate) fn color(&self) -> Color32 {
+        self.color
+    }
+
+    /// Change the fill color
+    pub(crate) fn set_color(&mut self, color: Color32) {
+        self.color = color;
+        self.invalidate_texture();
+    }
+
+    /// Change the step number; a badge still carrying its default name is renamed along
+    pub(crate) fn set_number(&mut self, number: u32) {
+        if self.metadata.name == step_name(self.number) {
+            self.metadata.name = step_name(number);
+        }
+        self.number = number;
+        self.invalidate_texture();
+    }
+
+    /// Width of a digit's lines
+    pub(crate) fn digit_thickness(&self) -> f32 {
+        self.digit_scale() * DIGIT_THICKNESS
+    }
+
+    /// Digit size in radii, shrunk for numbers too long to fit the circle
+    fn digit_scale(&self) -> f32 {
+        let count = self.number.to_string().len() as f32;
+        let text_width = count * (DIGIT_WIDTH + DIGIT_GAP) - DIGIT_GAP;
+        self.radius * (MAX_TEXT_WIDTH / text_width).min(1.0)
+    }
+
+    /// Polylines drawing the number, centered on the badge
+    pub(crate) fn digit_lines(&self) -> Vec<Vec<Pos2>> {
+        let scale = self.digit_scale();
+        let digits: Vec<u32> = self
+            .number
+            .to_string()
+            .chars()
+            .filter_map(|c| c.to_digit(10))
+            .collect();
+        let size = Vec2::new(DIGIT_WIDTH, DIGIT_HEIGHT) * scale;
+        let advance = (DIGIT_WIDTH + DIGIT_GAP) * scale;
+        let text_width = digits.len() as f32 * advance - DIGIT_GAP * scale;
+
+        let mut lines = Vec::new();
+        let mut cell_min = self.center - Vec2::new(text_width, size.y) / 2.0;
+        for digit in digits {
+            let cell = Rect::from_min_size(cell_min, size);
+            lines.extend(digit_paths(digit).iter().map(|path| {
+                path.iter()
+                    .map(|&(x, y)| cell.lerp_inside(Vec2::new(x, y)))
+                    .collect()
+            }));
+            cell_min.x += advance;
+        }
+        lines
+    }
+}
+
+/// Seven-segment style paths for a digit, in a unit cell with y pointing down
+fn digit_paths(digit: u32) -> &'static [&'static [(f32, f32)]] {
+    match digit {
+        0 => &[&[(0.0, 0.0), (1.0, 0.0), (1.0, 1.0), (0.0, 1.0), (0.0, 0.0)]],
+        1 => &[&[(0.2, 0.2), (0.6, 0.0), (0.6, 1.0)]],
+        2 => &[&[
+            (0.0, 0.0),
+            (1.0, 0.0),
+            (1.0, 0.5),
+            (0.0, 0.5),
+            (0.0, 1.0),
+            (1.0, 1.0),
+        ]],
+        3 => &[
+            &[(0.0, 0.0), (1.0, 0.0), (1.0, 1.0), (0.0, 1.0)],
+            &[(0.0, 0.5), (1.0, 0.5)],
+        ],
+        4 => &[
+            &[(0.0, 0.0), (0.0, 0.5), (1.0, 0.5)],
+            &[(1.0, 0.0), (1.0, 1.0)],
+        ],
+        5 => &[&[
+            (1.0, 0.0),
+            (0.0, 0.0),
+            (0.0, 0.5),
+            (1.0, 0.5),
+            (1.0, 1.0),
+            (0.0, 1.0),
+        ]],
+        6 => &[&[
+            (1.0, 0.0),
+            (0.0, 0.0),
+            (0.0, 1.0),
+            (1.0, 1.0),
+            (1.0, 0.5),
+            (0.0, 0.5),
+        ]],
+        7 => &[&[(0.0, 0.0), (1.0, 0.0), (1.0, 1.0)]],
+        8 => &[
+            &[(0.0, 0.0), (1.0, 0.0), (1.0, 1.0), (0.0, 1.0), (0.0, 0.0)],
+            &[(0.0, 0.5), (1.0, 0.5)],
+        ],
+        _ => &[&[
+            (1.0, 0.5),
+            (0.0, 0.5),
+            (0.0, 0.0),
+            (1.0, 0.0),
+            (1.0, 1.0),
+            (0.0, 1.0),
+        ]],
+    }
+}
+
+impl Element for StepBadge {
+    fn id(&self) -> ElementId {
+        self.id
+    }
+
+    fn element_type(&self) -> &'static str {
+        "step_badge"
+    }
+
+    fn metadata(&self) -> &ElementMetadata {
+        &self.metadata
+    }
+
+    fn metadata_mut(&mut self) -> &mut ElementMetadata {
+        &mut self.metadata
+    }
+
+    fn rect(&self) -> Rect {
+        Rect::from_center_size(self.center, Vec2::splat(self.radius * 2.0))
+    }
+
+    fn transform(&self) -> Transform {
+        Transform::rotated(self.rotation)
+    }
+
+    fn set_transform(&mut self, transform: Transform) {
+        self.rotation = transform.rotation;
+    }
+
+    fn draw(&self, painter: &Painter) {
+        painter.circle_filled(self.center, self.radius, self.color);
+        let stroke = EguiStroke::new(self.digit_thickness(), Color32::WHITE);
+        for line in self.digit_lines() {
+            painter.add(egui::Shape::line(line, stroke));
+        }
+    }
+
+    fn hit_test(&self, pos: Pos2) -> bool {
+        self.center.distance(pos) <= self.radius
+    }
+
+    fn translate(&mut self, delta: Vec2) -> Result<(), String> {
+        self.center += delta;
+
+        self.invalidate_texture();
+        Ok(())
+    }
+
+    fn resize(&mut self, new_rect: Rect) -> Result<(), String> {
+        common::validate_rect(&new_rect)?;
+
+        // Badges stay round: fit the circle inside the new bounds
+        self.center = new_rect.center();
+        self.radius = new_rect.width().min(new_rect.height()) / 2.0;
+
+        self.invalidate_texture();
+        Ok(())
+    }
+
+    fn texture(&self) -> Option<&TextureHandle> {
+        self.texture_handle.as_ref()
+    }
+
+    fn needs_texture_update(&self) -> bool {
+        self.texture_needs_update
+    }
+
+    fn texture_version(&self) -> u64 {
+        self.texture_version
+    }
+
+    fn invalidate_texture(&mut self) {
+        self.texture_needs_update = true;
+        self.texture_version += 1;
+    }
+
+    fn generate_texture(&mut self, _ctx: &Context) -> Result<ColorImage, TextureGenerationError> {
+        if self.radius <= 0.0 {
+            return Err(TextureGenerationError::InvalidDimensions);
+        }
+
+        trace!(id:% = self.id, step = self.number; "Generating texture");
+
+        let bounds = self.rect();
+        let width = bounds.width().ceil().max(1.0) as usize;
+        let height = bounds.height().ceil().max(1.0) as usize;
+        let mut image = ColorImage::new([width, height], Color32::TRANSPARENT);
+
+        // Fill the circle, then paint pixels close enough to a digit line white
+        let lines = self.digit_lines();
+        let half_thickness = self.digit_thickness() / 2.0;
+        for row in 0..height {
+            for column in 0..width {
+                let pos = bounds.min + Vec2::new(column as f32 + 0.5, row as f32 + 0.5);
+                if pos.distance(self.center) > self.radius {
+                    continue;
+                }
+                let on_digit = lines.iter().any(|line| {
+                    line.windows(2).any(|segment| {
+                        common::distance_to_line_segment(pos, segment[0], segment[1])
+                            <= half_thickness
+                    })
+                });
+                image.pixels[row * width + column] =
+                    if on_digit { Color32::WHITE } else { self.color };
+            }
+        }
+
+        self.texture_needs_update = false;
+        Ok(image)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_long_numbers_shrink_to_fit_and_keep_names_in_step() {
+        let mut badge = StepBadge::new(ElementId::new(1), Pos2::new(50.0, 50.0), 7, Color32::RED);
+        assert_eq!(badge.metadata().name, "Step 7");
+
+        badge.set_number(128);
+        assert_eq!(badge.metadata().name, "Step 128");
+        let rect = badge.rect();
+        assert!(
+            badge
+                .digit_lines()
+                .iter()
+                .flatten()
+                .all(|point| rect.contains(*point))
+        );
+
+        // Names the user picked are left alone
+        badge.metadata_mut().name = "Open the menu".to_string();
+        badge.set_number(2);
+        assert_eq!(badge.metadata().name, "Open the menu");
+    }
+}
@@ -0,0 +1,466 @@
+use std::sync::Arc;
+
+use egui::{
+    Color32, ColorImage, Context, FontId, Galley, Painter, Pos2, Rect, Shape, Stroke,
+    TextureHandle, Vec2,
+};
+use log::trace;
+use serde::{Deserialize, Serialize};
+
+use super::{Element, ElementId, ElementMetadata, Transform};
+use crate::element::common;
+use crate::geometry::speech_bubble_outline;
+use crate::tessellation;
+use crate::text::render_text;
+use crate::texture_manager::TextureGenerationError;
+
+/// Size new notes get when placed with a click
+pub const DEFAULT_NOTE_SIZE: Vec2 = Vec2::splat(160.0);
+
+/// Colors offered for notes, by name; the first is the default
+pub const NOTE_COLORS: [(&str, Color32); 5] = [
+    ("Yellow", Color32::from_rgb(255, 236, 140)),
+    ("Pink", Color32::from_rgb(255, 190, 210)),
+    ("Green", Color32::from_rgb(195, 235, 170)),
+    ("Blue", Color32::from_rgb(175, 215, 250)),
+    ("Orange", Color32::from_rgb(255, 205, 150)),
+];
+
+/// Color of the text on every note, dark enough to read on all of [`NOTE_COLORS`]
+pub const NOTE_TEXT_COLOR: Color32 = Color32::from_gray(40);
+
+const CORNER_RADIUS: f32 = 8.0;
+// Space between the edge of the note and its text
+const PADDING: f32 = 12.0;
+// Text starts at this size and shrinks, down to the minimum, until it fits
+const FONT_SIZE: f32 = 18.0;
+const MIN_FONT_SIZE: f32 = 8.0;
+// Callouts are a speech bubble outline around the text, with no fill
+pub(crate) const CALLOUT_CORNER_RADIUS: f32 = 14.0;
+pub(crate) const CALLOUT_TAIL_WIDTH: f32 = 24.0;
+pub(crate) const CALLOUT_OUTLINE_WIDTH: f32 = 3.0;
+
+/// Sticky note element: a colored rounded rectangle with text wrapped inside
+///
+/// The text shrinks to fit the note; past the smallest size it runs off the
+/// bottom and is clipped. A callout is a note drawn as a speech bubble
+/// outline instead, with a tail pointing at what it calls out.
+#[derive(Clone, Serialize, Deserialize)]
+pub struct StickyNote {
+    // Core properties
+    id: ElementId,
+    rect: Rect,
+    text: String,
+    color: Color32,
+    // Point the tail of a callout reaches; `rect` is then the bubble's body
+    #[serde(default)]
+    tail: Option<Pos2>,
+    // Turn about the center; the note lays out its text for its own size,
+    // so moving and resizing change that rather than a transform
+    #[serde(default)]
+    rotation: f32,
+
+    // User-facing name and metadata
+    #[serde(default)]
+    metadata: ElementMetadata,
+
+    // Texture caching
+    #[serde(skip)]
+    texture_handle: Option<TextureHandle>,
+    #[serde(skip, default = "common::texture_needs_update_default")]
+    texture_needs_update: bool,
+    #[serde(skip)]
+    texture_version: u64,
+}
+
+// Custom Debug implementation since TextureHandle doesn't implement Debug
+impl std::fmt::Debug for StickyNote {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("StickyNote")
+            .field("id", &self.id)
+            .field("rect", &self.rect)
+            .field("text", &self.text)
+            .field("color", &self.color)
+            .field("tail", &self.tail)
+            .field("rotation", &self.rotation)
+            .field("metadata", &self.metadata)
+            .field("texture_needs_update", &self.texture_needs_update)
+            .field("texture_version", &self.texture_version)
+            .finish()
+    }
+}
+
+impl StickyNote {
+    /// Create a new note with no text
+    pub(crate) fn new(id: ElementId, rect: Rect, color: Color32) -> Self {
+        Self {
+            id,
+            rect,
+            text: String::new(),
+            color,
+            tail: None,
+            rotation: 0.0,
+            metadata: ElementMetadata::default(),
+            texture_handle: None,
+            texture_needs_update: true,
+            texture_version: 0,
+        }
+    }
+
+    /// Create a callout with no text: a bubble over `body` outlined in `color`,
+    /// with its tail pointing at `tip`
+    pub(crate) fn new_callout(id: ElementId, body: Rect, tip: Pos2, color: Color32) -> Self {
+        Self {
+            tail: Some(tip),
+            ..Self::new(id, body, color)
+        }
+    }
+
+    /// A copy of this sticky note under another id
+    pub(crate) fn with_id(&self, id: ElementId) -> Self {
+        Self { id, ..self.clone() }
+    }
+
+    /// Get the text written on the note
+    pub(crate) fn text(&self) -> &str {
+        &self.text
+    }
+
+    /// Get the fill color, or the outline color of a callout
+    pub(crate) fn color(&self) -> Color32 {
+        self.color
+    }
+
+    /// Whether this is a callout, drawn as an outline with a tail
+    pub(crate) fn is_callout(&self) -> bool {
+        self.tail.is_some()
+    }
+
+    /// The part of the note holding the text, which is all of it unless it's
+    /// a callout
+    pub(crate) fn body(&self) -> Rect {
+        self.rect
+    }
+
+    /// The area the texture covers, leaving room for a callout's outline
+    pub(crate) fn texture_rect(&self) -> Rect {
+        if self.is_callout() {
+            self.rect().expand(CALLOUT_OUTLINE_WIDTH)
+        } else {
+            self.rect
+        }
+    }
+
+    /// Outline of a callout whose bounds are moved to `rect`, None for plain notes
+    pub(crate) fn outline(&self, rect: Rect) -> Option<Vec<Pos2>> {
+        let (body, tip) = self.fit(rect);
+        speech_bubble_outline(
+            body,
+            tip?,
+            Self::callout_corner_radius(body),
+            CALLOUT_TAIL_WIDTH,
+        )
+    }
+
+    /// Body and tail tip once the note's bounds are moved to `rect`
+    fn fit(&self, rect: Rect) -> (Rect, Option<Pos2>) {
+        let bounds = self.rect();
+        let scale = rect.size() / bounds.size();
+        let map = |pos: Pos2| rect.min + (pos - bounds.min) * scale;
+        (
+            Rect::from_min_max(map(self.rect.min), map(self.rect.max)),
+            self.tail.map(map),
+        )
+    }
+
+    /// Replace the text written on the note
+    pub(crate) fn set_text(&mut self, text: String) {
+        self.text = text;
+        self.invalidate_texture();
+    }
+
+    /// Change the fill color
+    pub(crate) fn set_color(&mut self, color: Color32) {
+        self.color = color;
+        self.invalidate_texture();
+    }
+
+    /// Radius of the corners of a note covering `rect`, smaller for tiny notes
+    pub(crate) fn corner_radius(rect: Rect) -> f32 {
+        CORNER_RADIUS
+            .min(rect.width() / 2.0)
+            .min(rect.height() / 2.0)
+    }
+
+    /// Radius of the corners of a callout's body, smaller for tiny bubbles
+    fn callout_corner_radius(body: Rect) -> f32 {
+        CALLOUT_CORNER_RADIUS
+            .min(body.width() / 2.0)
+            .min(body.height() / 2.0)
+    }
+
+    /// Where the text goes on a note covering `rect`
+    pub(crate) fn text_rect(rect: Rect) -> Rect {
+        let padding = PADDING.min(rect.width() / 4.0).min(rect.height() / 4.0);
+        rect.shrink(padding)
+    }
+
+    /// Lay out the note's text over `rect`, at the largest font size that fits
+    ///
+    /// Returns the font size and the galley, positioned at the top left of
+    /// [`Self::text_rect`].
+    pub(crate) fn layout(&self, ctx: &Context, rect: Rect) -> (f32, Arc<Galley>) {
+        Self::layout_text(ctx, &self.text, rect)
+    }
+
+    /// Lay out `text` for a note covering `rect`, as [`Self::layout`] does
+    pub(crate) fn layout_text(ctx: &Context, text: &str, rect: Rect) -> (f32, Arc<Galley>) {
+        let text_rect = Self::text_rect(rect);
+        let layout = |size: f32| {
+            ctx.fonts(|fonts| {
+                fonts.layout(
+                    text.to_string(),
+                    FontId::proportional(size),
+                    NOTE_TEXT_COLOR,
+                    text_rect.width(),
+                )
+            })
+        };
+
+        let mut size = FONT_SIZE;
+        let mut galley = layout(size);
+        while galley.size().y > text_rect.height() && size > MIN_FONT_SIZE {
+            size = (size - 1.0).max(MIN_FONT_SIZE);
+            galley = layout(size);
+        }
+        (size, galley)
+    }
+
+    /// The font size and the rows of text that fit on the note, each with the
+    /// start of its baseline in document coordinates
+    pub(crate) fn text_rows(&self, ctx: &Context) -> (f32, Vec<(Pos2, String)>) {
+        let (size, galley) = self.layout(ctx, self.rect);
+        let text_rect = Self::text_rect(self.rect);
+        let rows = clipped_galley(&galley, text_rect.height())
+            .rows
+            .iter()
+            .filter_map(|row| {
+                let baseline = row.glyphs.first()?.pos.y;
+                let text: String = row.glyphs.iter().map(|glyph| glyph.chr).collect();
+                Some((text_rect.min + Vec2::new(row.rect.min.x, baseline), text))
+            })
+            .collect();
+        (size, rows)
+    }
+
+    /// Shapes drawing the note over `rect`, which is its own unless it's being
+    /// dragged or resized
+    pub(crate) fn shapes(&self, ctx: &Context, rect: Rect) -> Vec<Shape> {
+        let (body, _) = self.fit(rect);
+        let mut shapes = match self.outline(rect) {
+            Some(outline) => vec![Shape::closed_line(
+                outline,
+                Stroke::new(CALLOUT_OUTLINE_WIDTH, self.color),
+            )],
+            None => vec![Shape::rect_filled(
+                body,
+                Self::corner_radius(body),
+                self.color,
+            )],
+        };
+        if !self.text.is_empty() {
+            let (_, galley) = self.layout(ctx, body);
+            // Text that didn't fit even at the smallest size is cut off at the padding
+            let text_rect = Self::text_rect(body);
+            let galley = clipped_galley(&galley, text_rect.height());
+            shapes.push(Shape::galley(text_rect.min, galley, NOTE_TEXT_COLOR));
+        }
+        shapes
+    }
+}
+
+/// `galley` with only the rows that end within `height`
+fn clipped_galley(galley: &Arc<Galley>, height: f32) -> Arc<Galley> {
+    if galley.size().y <= height {
+        return galley.clone();
+    }
+    let mut clipped = (**galley).clone();
+    clipped.rows.retain(|row| row.rect.max.y <= height);
+    clipped.rect.max.y = clipped.rect.min.y + height;
+    clipped.mesh_bounds = clipped.mesh_bounds.intersect(clipped.rect);
+    Arc::new(clipped)
+}
+
+impl Element for StickyNote {
+    fn id(&self) -> ElementId {
+        self.id
+    }
+
+    fn element_type(&self) -> &'static str {
+        "sticky_note"
+    }
+
+    fn metadata(&self) -> &ElementMetadata {
+        &self.metadata
+    }
+
+    fn metadata_mut(&mut self) -> &mut ElementMetadata {
+        &mut self.metadata
+    }
+
+    fn rect(&self) -> Rect {
+        match self.tail {
+            Some(tip) => self.rect.union(Rect::from_min_max(tip, tip)),
+            None => self.rect,
+        }
+    }
+
+    fn transform(&self) -> Transform {
+        Transform::rotated(self.rotation)
+    }
+
+    fn set_transform(&mut self, transform: Transform) {
+        self.rotation = transform.rotation;
+    }
+
+    fn draw(&self, painter: &Painter) {
+        painter.extend(self.shapes(painter.ctx(), self.rect));
+    }
+
+    fn hit_test(&self, pos: Pos2) -> bool {
+        self.rect().contains(pos)
+    }
+
+    fn translate(&mut self, delta: Vec2) -> Result<(), String> {
+        self.rect = self.rect.translate(delta);
+        self.tail = self.tail.map(|tip| tip + delta);
+
+        self.invalidate_texture();
+        Ok(())
+    }
+
+    fn resize(&mut self, new_rect: Rect) -> Result<(), String> {
+        common::validate_rect(&new_rect)?;
+
+        // The text rewraps to the new width
+        (self.rect, self.tail) = self.fit(new_rect);
+
+        self.invalidate_texture();
+        Ok(())
+    }
+
+    fn texture(&self) -> Option<&TextureHandle> {
+        self.texture_handle.as_ref()
+    }
+
+    fn needs_texture_update(&self) -> bool {
+        self.texture_needs_update
+    }
+
+    fn texture_version(&self) -> u64 {
+        self.texture_version
+    }
+
+    fn invalidate_texture(&mut self) {
+        self.texture_needs_update = true;
+        self.texture_version += 1;
+    }
+
+    fn generate_texture(&mut self, ctx: &Context) -> Result<ColorImage, TextureGenerationError> {
+        if self.rect.width() <= 0.0 || self.rect.height() <= 0.0 {
+            return Err(TextureGenerationError::InvalidDimensions);
+        }
+
+        trace!(id:% = self.id, characters = self.text.chars().count(); "Generating texture");
+
+        let texture_rect = self.texture_rect();
+        let width = texture_rect.width().ceil().max(1.0) as usize;
+        let height = texture_rect.height().ceil().max(1.0) as usize;
+        let mut image = ColorImage::new([width, height], Color32::TRANSPARENT);
+
+        if let Some(outline) = self.outline(self.rect()) {
+            // Callouts are only their outline, closed back at the first point
+            let mut outline: Vec<Pos2> = outline
+                .into_iter()
+                .map(|pos| pos - texture_rect.min.to_vec2())
+                .collect();
+            outline.push(outline[0]);
+            let mesh = tessellation::stroke_mesh(&outline, CALLOUT_OUTLINE_WIDTH, self.color);
+            tessellation::rasterize_mesh(&mesh, &mut image);
+        } else {
+            // Fill the rounded rectangle, antialiasing the corners
+            let radius = Self::corner_radius(self.rect);
+            let inner = Rect::from_min_size(Pos2::ZERO, self.rect.size()).shrink(radius);
+            for row in 0..height {
+                for column in 0..width {
+                    let pos = Pos2::new(column as f32 + 0.5, row as f32 + 0.5);
+                    let distance = (pos - inner.clamp(pos)).length();
+                    let coverage = (radius - distance + 0.5).clamp(0.0, 1.0);
+                    if coverage > 0.0 {
+                        image.pixels[row * width + column] = self.color.gamma_multiply(coverage);
+                    }
+                }
+            }
+        }
+
+        // Then the text, at one pixel per unit like the rest of the texture
+        if !self.text.is_empty() {
+            let (size, _) = self.layout(ctx, self.rect);
+            let text_rect = Self::text_rect(self.rect);
+            let text = render_text(ctx, &self.text, size, NOTE_TEXT_COLOR, text_rect.width());
+            let offset = text_rect.min - texture_rect.min;
+            let (left, top) = (offset.x.round() as usize, offset.y.round() as usize);
+            let bottom = (text_rect.max.y - texture_rect.min.y).round() as usize;
+            for row in 0..text.size[1] {
+                for column in 0..text.size[0] {
+                    let (x, y) = (left + column, top + row);
+                    if x >= width || y >= bottom.min(height) {
+                        continue;
+                    }
+                    let coverage = text.pixels[row * text.size[0] + column].a() as f32 / 255.0;
+                    let pixel = &mut image.pixels[y * width + x];
+                    *pixel = pixel.lerp_to_gamma(NOTE_TEXT_COLOR, coverage);
+                }
+            }
+        }
+
+        self.texture_needs_update = false;
+        Ok(image)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_long_text_shrinks_to_fit_the_note() {
+        let ctx = Context::default();
+        // Fonts are only loaded once a frame has run
+        let _ = ctx.run(Default::default(), |_| {});
+
+        let rect = Rect::from_min_size(Pos2::new(10.0, 10.0), DEFAULT_NOTE_SIZE);
+        let mut note = StickyNote::new(ElementId::new(1), rect, NOTE_COLORS[0].1);
+        note.set_text("Ship it".to_string());
+        let (size, galley) = note.layout(&ctx, rect);
+        assert_eq!(size, FONT_SIZE);
+        assert_eq!(galley.rows.len(), 1);
+
+        note.set_text("Talk to the design team about the onboarding flow ".repeat(4));
+        let (size, galley) = note.layout(&ctx, rect);
+        assert!(size < FONT_SIZE);
+        assert!(galley.rows.len() > 1);
+        assert!(galley.size().y <= StickyNote::text_rect(rect).height());
+
+        let image = note.generate_texture(&ctx).unwrap();
+        assert_eq!(image.size, [160, 160]);
+        // Rounded corners stay clear, and some text was drawn over the fill
+        assert_eq!(image.pixels[0], Color32::TRANSPARENT);
+        assert!(
+            image
+                .pixels
+                .iter()
+                .any(|&pixel| pixel != note.color() && pixel.a() == 255)
+        );
+    }
+}
@@ -1,24 +1,153 @@
 use egui::{
     Color32, ColorImage, Context, Painter, Pos2, Rect, Stroke as EguiStroke, TextureHandle, Vec2,
 };
-use log::info;
+use log::trace;
+use serde::{Deserialize, Serialize};
+use std::sync::OnceLock;
 
-use super::Element;
+use super::{Element, ElementId, ElementMetadata, Transform};
+use crate::arrowhead::Arrowhead;
 use crate::element::common;
+use crate::tessellation;
 use crate::texture_manager::TextureGenerationError;
 
+/// Most of a stroke's length each end can taper over
+pub const MAX_TAPER: f32 = 0.5;
+
+/// How the ends of a stroke are finished
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq, Serialize, Deserialize)]
+pub enum LineCap {
+    /// Rounded off, half the thickness past the end point
+    #[default]
+    Round,
+    /// Cut square at the end point
+    Flat,
+}
+
+impl LineCap {
+    pub const ALL: [LineCap; 2] = [LineCap::Round, LineCap::Flat];
+
+    pub fn label(self) -> &'static str {
+        match self {
+            LineCap::Round => "Round",
+            LineCap::Flat => "Flat",
+        }
+    }
+}
+
+/// How a stroke's corners are filled where it turns
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq, Serialize, Deserialize)]
+pub enum LineJoin {
+    #[default]
+    Round,
+    /// Sharp corners, cut off where they would stick out too far
+    Miter,
+    /// Corners cut straight across
+    Bevel,
+}
+
+impl LineJoin {
+    pub const ALL: [LineJoin; 3] = [LineJoin::Round, LineJoin::Miter, LineJoin::Bevel];
+
+    pub fn label(self) -> &'static str {
+        match self {
+            LineJoin::Round => "Round",
+            LineJoin::Miter => "Miter",
+            LineJoin::Bevel => "Bevel",
+        }
+    }
+}
+
+/// Whether a line is drawn solid or broken into dashes or dots
+///
+/// Patterns alternate dash and gap lengths in multiples of the line's
+/// thickness, so they scale with it. Caps are added to both ends of each dash,
+/// like in SVG, which makes zero-length dashes dots.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Serialize, Deserialize)]
+pub enum DashPattern {
+    #[default]
+    Solid,
+    Dashed,
+    Dotted,
+    /// Two dash and gap pairs, for patterns like dash-dot
+    Custom([f32; 4]),
+}
+
+impl DashPattern {
+    pub const PRESETS: [DashPattern; 3] =
+        [DashPattern::Solid, DashPattern::Dashed, DashPattern::Dotted];
+
+    pub fn label(&self) -> &'static str {
+        match self {
+            DashPattern::Solid => "Solid",
+            DashPattern::Dashed => "Dashed",
+            DashPattern::Dotted => "Dotted",
+            DashPattern::Custom(_) => "Custom",
+        }
+    }
+
+    /// Dash and gap lengths in turn, in multiples of the thickness; empty for a
+    /// solid line or a pattern with nothing in it
+    pub fn intervals(&self) -> &[f32] {
+        match self {
+            DashPattern::Solid => &[],
+            DashPattern::Dashed => &[3.0, 2.0],
+            DashPattern::Dotted => &[0.0, 2.0],
+            DashPattern::Custom(lengths) if lengths.iter().sum::<f32>() > 0.0 => lengths,
+            DashPattern::Custom(_) => &[],
+        }
+    }
+}
+
+/// Taper, caps, joins, dashes and arrowheads of a stroke
+///
+/// Tapers are fractions of the stroke's length, up to [`MAX_TAPER`], over
+/// which it narrows to a point at that end.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Serialize, Deserialize)]
+#[serde(default)]
+pub struct StrokeStyle {
+    pub taper_start: f32,
+    pub taper_end: f32,
+    pub cap: LineCap,
+    pub join: LineJoin,
+    pub dash: DashPattern,
+    pub start_arrow: Arrowhead,
+    pub end_arrow: Arrowhead,
+}
+
 /// Stroke element representing a series of connected points
-#[derive(Clone)]
+#[derive(Clone, Serialize, Deserialize)]
 pub struct Stroke {
     // Core properties
-    id: usize,
+    id: ElementId,
     points: Vec<Pos2>,
     color: Color32,
     thickness: f32,
+    #[serde(default)]
+    style: StrokeStyle,
+    // Where the points are drawn; moving and resizing only change this
+    #[serde(default)]
+    transform: Transform,
+
+    // User-facing name and metadata
+    #[serde(default)]
+    metadata: ElementMetadata,
+
+    // The points placed by the transform, found on first use
+    #[serde(skip)]
+    placed: OnceLock<Vec<Pos2>>,
+    // Bounds of the placed points, without the thickness; found on first use
+    // and kept up to date by edits that move the points, so finding the rect
+    // doesn't scan them all
+    #[serde(skip)]
+    bounds: OnceLock<Rect>,
 
     // Texture caching
+    #[serde(skip)]
     texture_handle: Option<TextureHandle>,
+    #[serde(skip, default = "common::texture_needs_update_default")]
     texture_needs_update: bool,
+    #[serde(skip)]
     texture_version: u64,
 }
 
@@ -30,6 +159,9 @@ impl std::fmt::Debug for Stroke {
             .field("points", &self.points)
             .field("color", &self.color)
             .field("thickness", &self.thickness)
+            .field("style", &self.style)
+            .field("transform", &self.transform)
+            .field("metadata", &self.metadata)
             .field("texture_needs_update", &self.texture_needs_update)
             .field("texture_version", &self.texture_version)
             .finish()
@@ -38,21 +170,34 @@ impl std::fmt::Debug for Stroke {
 
 impl Stroke {
     /// Create a new stroke with the given properties
-    pub(crate) fn new(id: usize, points: Vec<Pos2>, thickness: f32, color: Color32) -> Self {
+    pub(crate) fn new(id: ElementId, points: Vec<Pos2>, thickness: f32, color: Color32) -> Self {
         Self {
             id,
             points,
             color,
             thickness,
+            style: StrokeStyle::default(),
+            transform: Transform::IDENTITY,
+            metadata: ElementMetadata::default(),
+            placed: OnceLock::new(),
+            bounds: OnceLock::new(),
             texture_handle: None,
             texture_needs_update: true,
             texture_version: 0,
         }
     }
 
-    /// Get the points that make up this stroke
+    /// A copy of this stroke under another id
+    pub(crate) fn with_id(&self, id: ElementId) -> Self {
+        Self { id, ..self.clone() }
+    }
+
+    /// Get the points that make up this stroke, placed by its transform
     pub(crate) fn points(&self) -> &[Pos2] {
-        &self.points
+        self.transform
+            .place_all(&self.points, &self.placed, |transform, point| {
+                transform.apply(*point)
+            })
     }
 
     /// Get the stroke color
@@ -60,9 +205,90 @@ impl Stroke {
         self.color
     }
 
-    /// Get the stroke thickness
+    /// Change the stroke color
+    pub(crate) fn set_color(&mut self, color: Color32) {
+        self.color = color;
+        self.invalidate_texture();
+    }
+
+    /// Get the stroke thickness, scaled with the stroke
     pub(crate) fn thickness(&self) -> f32 {
-        self.thickness
+        self.thickness * self.transform.line_scale()
+    }
+
+    pub(crate) fn style(&self) -> StrokeStyle {
+        self.style
+    }
+
+    pub(crate) fn set_style(&mut self, style: StrokeStyle) {
+        self.style = style;
+        self.invalidate_texture();
+    }
+
+    /// Create a stroke with this stroke's color, thickness, style and metadata but new points
+    pub(crate) fn with_points(&self, id: ElementId, points: Vec<Pos2>) -> Self {
+        let mut stroke = Self::new(id, points, self.thickness(), self.color);
+        stroke.style = self.style;
+        stroke.metadata = self.metadata.clone();
+        stroke
+    }
+
+    /// Replace the points of this stroke with ones placed like [`Self::points`],
+    /// keeping everything else
+    pub(crate) fn set_points(&mut self, points: Vec<Pos2>) {
+        self.points = points
+            .into_iter()
+            .map(|point| self.transform.invert(point))
+            .collect();
+        self.clear_cached_geometry();
+        self.invalidate_texture();
+    }
+
+    /// Store the points where they're drawn, turned and all, and drop the transform
+    pub(crate) fn flatten_transform(&mut self) {
+        let obb = self.obb();
+        self.points = self
+            .points()
+            .iter()
+            .map(|&point| obb.to_world(point))
+            .collect();
+        self.thickness = self.thickness();
+        self.transform = Transform::IDENTITY;
+        self.clear_cached_geometry();
+        self.invalidate_texture();
+    }
+
+    /// A copy of the stroke with its transform flattened, for edits that work
+    /// on the points as they're drawn
+    pub(crate) fn flattened(&self) -> Self {
+        let mut stroke = self.clone();
+        stroke.flatten_transform();
+        stroke
+    }
+
+    fn clear_cached_geometry(&mut self) {
+        self.placed = OnceLock::new();
+        self.bounds = OnceLock::new();
+    }
+
+    /// Get the bounds of the points, scanning them only when not cached
+    fn point_bounds(&self) -> Rect {
+        *self
+            .bounds
+            .get_or_init(|| common::calculate_bounds(self.points(), 0.0))
+    }
+
+    /// Get the area covered by the generated texture (the bounds plus thickness padding)
+    pub(crate) fn texture_rect(&self) -> Rect {
+        let bounds = self.rect();
+        let padding = self.thickness() * 1.5;
+        let width = (bounds.width() + padding * 2.0).max(1.0) as usize;
+        let height = (bounds.height() + padding * 2.0).max(1.0) as usize;
+
+        Rect::from_min_size(
+            Pos2::new(bounds.min.x - padding, bounds.min.y - padding),
+            Vec2::new(width as f32, height as f32),
+        )
     }
 
     /// Internal helper for generating a texture representation (used by the trait implementation)
@@ -72,17 +298,14 @@ impl Stroke {
             return Err(TextureGenerationError::InvalidDimensions);
         }
 
-        info!(
-            "🖌️ Generating texture for stroke {}: {} points",
-            self.id,
-            self.points.len()
-        );
+        trace!(id:% = self.id, points = self.points.len(); "Generating texture");
 
         // Calculate bounds
         let bounds = self.rect();
+        let thickness = self.thickness();
 
         // Safety margins for stroke thickness
-        let padding = self.thickness * 1.5;
+        let padding = thickness * 1.5;
         let width = (bounds.width() + padding * 2.0).max(1.0) as usize;
         let height = (bounds.height() + padding * 2.0).max(1.0) as usize;
 
@@ -92,49 +315,20 @@ impl Stroke {
         // Offset points to the image coordinate space
         let offset = Vec2::new(bounds.min.x - padding, bounds.min.y - padding);
         let transformed_points: Vec<Pos2> = self
-            .points
+            .points()
             .iter()
             .map(|p| Pos2::new(p.x - offset.x, p.y - offset.y))
             .collect();
 
-        // Draw the stroke to the image
-        // This is a simplified approach that draws color blocks along the stroke path
-        if transformed_points.len() >= 2 {
-            for window in transformed_points.windows(2) {
-                let (p1, p2) = (window[0], window[1]);
-
-                // Draw line from p1 to p2
-                // Simple Bresenham-like algorithm
-                let dist = p1.distance(p2);
-                let steps = (dist * 2.0).ceil() as usize;
-
-                for step in 0..=steps {
-                    let t = step as f32 / steps as f32;
-                    let point = p1.lerp(p2, t);
-
-                    // Draw a circle at this point
-                    let radius = (self.thickness / 2.0).ceil() as i32;
-
-                    for dy in -radius..=radius {
-                        for dx in -radius..=radius {
-                            let d = (dx * dx + dy * dy) as f32;
-                            if d <= (radius as f32 * radius as f32) {
-                                let x = (point.x + dx as f32) as i32;
-                                let y = (point.y + dy as f32) as i32;
-
-                                // Check bounds
-                                if x >= 0 && y >= 0 && x < width as i32 && y < height as i32 {
-                                    let idx = y as usize * width + x as usize;
-                                    if idx < image.pixels.len() {
-                                        image.pixels[idx] = self.color;
-                                    }
-                                }
-                            }
-                        }
-                    }
-                }
-            }
-        }
+        // Fill the same triangles the stroke is drawn with as a mesh, so the
+        // texture has its taper, caps and joins too
+        let mesh = tessellation::styled_stroke_mesh(
+            &transformed_points,
+            thickness,
+            self.color,
+            &self.style,
+        );
+        tessellation::rasterize_mesh(&mesh, &mut image);
 
         // Mark as not needing update
         self.texture_needs_update = false;
@@ -144,7 +338,7 @@ impl Stroke {
 }
 
 impl Element for Stroke {
-    fn id(&self) -> usize {
+    fn id(&self) -> ElementId {
         self.id
     }
 
@@ -152,13 +346,31 @@ impl Element for Stroke {
         "stroke"
     }
 
+    fn metadata(&self) -> &ElementMetadata {
+        &self.metadata
+    }
+
+    fn metadata_mut(&mut self) -> &mut ElementMetadata {
+        &mut self.metadata
+    }
+
     fn rect(&self) -> Rect {
         // Calculate bounding box from points with padding for stroke thickness
         if self.points.is_empty() {
             return Rect::NOTHING;
         }
 
-        common::calculate_bounds(&self.points, self.thickness / 2.0)
+        self.point_bounds().expand(self.thickness() / 2.0)
+    }
+
+    fn transform(&self) -> Transform {
+        self.transform
+    }
+
+    fn set_transform(&mut self, transform: Transform) {
+        self.transform = transform;
+        self.clear_cached_geometry();
+        self.invalidate_texture();
     }
 
     fn draw(&self, painter: &Painter) {
@@ -169,8 +381,8 @@ impl Element for Stroke {
         }
 
         painter.add(egui::Shape::line(
-            self.points.clone(),
-            EguiStroke::new(self.thickness, self.color),
+            self.points().to_vec(),
+            EguiStroke::new(self.thickness(), self.color),
         ));
     }
 
@@ -180,9 +392,10 @@ impl Element for Stroke {
             return false;
         }
 
-        for window in self.points.windows(2) {
+        let radius = self.thickness() / 2.0;
+        for window in self.points().windows(2) {
             let distance = common::distance_to_line_segment(pos, window[0], window[1]);
-            if distance <= self.thickness / 2.0 {
+            if distance <= radius {
                 return true;
             }
         }
@@ -191,8 +404,10 @@ impl Element for Stroke {
     }
 
     fn translate(&mut self, delta: Vec2) -> Result<(), String> {
-        for point in &mut self.points {
-            *point += delta;
+        self.transform = self.transform.translated(delta);
+        self.placed = OnceLock::new();
+        if let Some(bounds) = self.bounds.get_mut() {
+            *bounds = bounds.translate(delta);
         }
 
         self.invalidate_texture();
@@ -207,23 +422,16 @@ impl Element for Stroke {
             return Err("Cannot resize empty stroke".to_string());
         }
 
-        // Calculate scale factors
-        let scale_x = new_rect.width() / old_rect.width();
-        let scale_y = new_rect.height() / old_rect.height();
-
-        // Transform each point
-        for point in &mut self.points {
-            // Convert to relative coordinates in the original rect
-            let relative_x = (point.x - old_rect.min.x) / old_rect.width();
-            let relative_y = (point.y - old_rect.min.y) / old_rect.height();
-
-            // Apply to new rect
-            point.x = new_rect.min.x + (relative_x * new_rect.width());
-            point.y = new_rect.min.y + (relative_y * new_rect.height());
+        // Stretch the points from the old rect over the new one, thickness
+        // along with them, by scaling the transform rather than the points
+        let stretched = self.transform.resized(old_rect, new_rect);
+        // The scaling keeps order along each axis, so the bounds move with the points
+        if let Some(bounds) = self.bounds.get_mut() {
+            let stretch = Transform::IDENTITY.resized(old_rect, new_rect);
+            *bounds = stretch.apply_rect(*bounds);
         }
-
-        // Scale thickness proportionally
-        self.thickness *= (scale_x + scale_y) / 2.0;
+        self.transform = stretched;
+        self.placed = OnceLock::new();
 
         self.invalidate_texture();
         Ok(())
@@ -262,3 +470,37 @@ impl Element for Stroke {
         result
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_cached_bounds_follow_edits() {
+        let points = vec![
+            Pos2::new(10.0, 20.0),
+            Pos2::new(50.0, 5.0),
+            Pos2::new(30.0, 40.0),
+        ];
+        let mut stroke = Stroke::new(ElementId::default(), points, 4.0, Color32::BLACK);
+        let scanned =
+            |stroke: &Stroke| common::calculate_bounds(stroke.points(), stroke.thickness() / 2.0);
+        assert_eq!(stroke.rect(), scanned(&stroke));
+
+        stroke.translate(Vec2::new(-5.0, 7.5)).unwrap();
+        assert_eq!(stroke.rect(), scanned(&stroke));
+
+        stroke
+            .resize(Rect::from_min_size(
+                Pos2::new(100.0, 100.0),
+                Vec2::new(84.0, 21.0),
+            ))
+            .unwrap();
+        let (cached, rescanned) = (stroke.rect(), scanned(&stroke));
+        assert!((cached.min - rescanned.min).length() < 1e-3);
+        assert!((cached.max - rescanned.max).length() < 1e-3);
+
+        stroke.set_points(vec![Pos2::ZERO, Pos2::new(1.0, 1.0)]);
+        assert_eq!(stroke.rect(), scanned(&stroke));
+    }
+}
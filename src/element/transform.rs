@@ -0,0 +1,135 @@
+use std::sync::OnceLock;
+
+use egui::{Pos2, Rect, Vec2};
+use serde::{Deserialize, Serialize};
+
+/// Where an element's stored geometry is drawn: scaled about the origin, then
+/// moved, then turned about the center of the element's rectangle
+///
+/// Moving and resizing only change the transform, so the stored points and
+/// pixels are never resampled and any number of resizes loses nothing. The
+/// rotation is the one [`super::Obb`] maps through; geometry read from an
+/// element is placed by the scale and translation but stays unrotated.
+#[derive(Clone, Copy, Debug, PartialEq, Serialize, Deserialize)]
+#[serde(default)]
+pub struct Transform {
+    pub translation: Vec2,
+    pub scale: Vec2,
+    /// Clockwise rotation about the center of the element's rectangle, in radians
+    pub rotation: f32,
+}
+
+impl Default for Transform {
+    fn default() -> Self {
+        Self::IDENTITY
+    }
+}
+
+impl Transform {
+    pub const IDENTITY: Self = Self {
+        translation: Vec2::ZERO,
+        scale: Vec2::splat(1.0),
+        rotation: 0.0,
+    };
+
+    /// A transform that only turns the element
+    pub fn rotated(rotation: f32) -> Self {
+        Self {
+            rotation,
+            ..Self::IDENTITY
+        }
+    }
+
+    pub fn is_identity(&self) -> bool {
+        *self == Self::IDENTITY
+    }
+
+    /// Whether stored geometry is drawn somewhere other than where it's stored,
+    /// leaving the rotation aside
+    pub fn places(&self) -> bool {
+        self.translation != Vec2::ZERO || self.scale != Vec2::splat(1.0)
+    }
+
+    /// Place a stored position, without the rotation
+    pub fn apply(&self, pos: Pos2) -> Pos2 {
+        (pos.to_vec2() * self.scale + self.translation).to_pos2()
+    }
+
+    /// Place a stored rectangle, without the rotation
+    pub fn apply_rect(&self, rect: Rect) -> Rect {
+        Rect::from_two_pos(self.apply(rect.min), self.apply(rect.max))
+    }
+
+    /// Map a placed position back to where it's stored
+    pub fn invert(&self, pos: Pos2) -> Pos2 {
+        ((pos.to_vec2() - self.translation) / self.scale).to_pos2()
+    }
+
+    /// How much line widths grow with the scale: the average of both axes,
+    /// as resizing a line has always scaled its thickness
+    pub fn line_scale(&self) -> f32 {
+        (self.scale.x + self.scale.y) / 2.0
+    }
+
+    /// The transform moved by `delta`
+    pub fn translated(self, delta: Vec2) -> Self {
+        Self {
+            translation: self.translation + delta,
+            ..self
+        }
+    }
+
+    /// The transform stretching what it placed over `from` onto `to`
+    pub fn resized(self, from: Rect, to: Rect) -> Self {
+        let stretch = to.size() / from.size();
+        Self {
+            translation: to.min.to_vec2() + (self.translation - from.min.to_vec2()) * stretch,
+            scale: self.scale * stretch,
+            rotation: self.rotation,
+        }
+    }
+
+    /// `stored` placed by this transform with `place`, cached in `placed`
+    ///
+    /// Geometry the transform doesn't place is handed back as stored.
+    pub(crate) fn place_all<'a, T>(
+        &self,
+        stored: &'a [T],
+        placed: &'a OnceLock<Vec<T>>,
+        place: impl Fn(&Self, &T) -> T,
+    ) -> &'a [T] {
+        if !self.places() {
+            return stored;
+        }
+        placed.get_or_init(|| stored.iter().map(|item| place(self, item)).collect())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_repeated_resizes_come_back_to_the_start() {
+        let stored = Rect::from_min_max(Pos2::new(10.0, 20.0), Pos2::new(110.0, 70.0));
+        let mut transform = Transform::IDENTITY;
+        let mut rect = stored;
+        for step in 1..=50 {
+            let next = Rect::from_min_size(
+                Pos2::new(step as f32 * 3.0, 7.0),
+                Vec2::new(37.0 + step as f32 * 11.0, 13.0 + step as f32 * 0.3),
+            );
+            transform = transform.resized(rect, next);
+            rect = next;
+            assert!((transform.apply_rect(stored).max - next.max).length() < 0.01);
+        }
+        transform = transform.resized(rect, stored);
+        assert!((transform.scale - Vec2::splat(1.0)).length() < 0.0001);
+        assert!(transform.translation.length() < 0.01);
+
+        let moved = Transform::IDENTITY.translated(Vec2::new(5.0, -5.0));
+        let pos = Pos2::new(3.0, 4.0);
+        assert_eq!(moved.invert(moved.apply(pos)), pos);
+        assert!(!Transform::rotated(1.0).places());
+    }
+}
@@ -0,0 +1,94 @@
+//! Passphrase protection for document files
+//!
+//! Encrypted files start with a short marker, followed by the salt the key was
+//! derived with (Argon2id), the AES-256-GCM nonce and the sealed document.
+//! GCM authenticates the contents, so a wrong passphrase is reported as such
+//! instead of yielding garbage.
+
+use crate::document::DocumentError;
+use aes_gcm::aead::{Aead, AeadCore, KeyInit, OsRng, rand_core::RngCore};
+use aes_gcm::{Aes256Gcm, Key, Nonce};
+
+/// Marks the start of an encrypted document file
+const MAGIC: &[u8] = b"PAINTENC1\n";
+const SALT_LEN: usize = 16;
+const NONCE_LEN: usize = 12;
+
+/// Whether `bytes` look like an encrypted document
+pub fn is_encrypted(bytes: &[u8]) -> bool {
+    bytes.starts_with(MAGIC)
+}
+
+/// Derive the AES key for `passphrase` and `salt`
+fn derive_key(passphrase: &str, salt: &[u8]) -> Result<Key<Aes256Gcm>, DocumentError> {
+    let mut key = Key::<Aes256Gcm>::default();
+    argon2::Argon2::default()
+        .hash_password_into(passphrase.as_bytes(), salt, &mut key)
+        .map_err(|err| DocumentError::Encryption(err.to_string()))?;
+    Ok(key)
+}
+
+/// Seal `plaintext` with a key derived from `passphrase`, using a fresh salt and nonce
+pub fn encrypt(plaintext: &[u8], passphrase: &str) -> Result<Vec<u8>, DocumentError> {
+    let mut salt = [0u8; SALT_LEN];
+    OsRng.fill_bytes(&mut salt);
+    let nonce = Aes256Gcm::generate_nonce(&mut OsRng);
+
+    let cipher = Aes256Gcm::new(&derive_key(passphrase, &salt)?);
+    let ciphertext = cipher
+        .encrypt(&nonce, plaintext)
+        .map_err(|err| DocumentError::Encryption(err.to_string()))?;
+
+    let mut bytes = Vec::with_capacity(MAGIC.len() + SALT_LEN + NONCE_LEN + ciphertext.len());
+    bytes.extend_from_slice(MAGIC);
+    bytes.extend_from_slice(&salt);
+    bytes.extend_from_slice(&nonce);
+    bytes.extend_from_slice(&ciphertext);
+    Ok(bytes)
+}
+
+/// Open bytes written by `encrypt`
+///
+/// Fails with `WrongPassphrase` if the passphrase doesn't match, which is also
+/// what a tampered or truncated file looks like.
+pub fn decrypt(bytes: &[u8], passphrase: &str) -> Result<Vec<u8>, DocumentError> {
+    let sealed = bytes
+        .strip_prefix(MAGIC)
+        .filter(|sealed| sealed.len() >= SALT_LEN + NONCE_LEN)
+        .ok_or_else(|| DocumentError::Encryption("Not an encrypted document".to_string()))?;
+    let (salt, sealed) = sealed.split_at(SALT_LEN);
+    let (nonce, ciphertext) = sealed.split_at(NONCE_LEN);
+
+    let cipher = Aes256Gcm::new(&derive_key(passphrase, salt)?);
+    cipher
+        .decrypt(Nonce::from_slice(nonce), ciphertext)
+        .map_err(|_| DocumentError::WrongPassphrase)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_roundtrip_and_wrong_passphrase() {
+        let sealed = encrypt(b"(elements: [])", "correct horse").unwrap();
+        assert!(is_encrypted(&sealed));
+        assert!(!is_encrypted(b"(elements: [])"));
+
+        assert_eq!(
+            decrypt(&sealed, "correct horse").unwrap(),
+            b"(elements: [])"
+        );
+        assert!(matches!(
+            decrypt(&sealed, "battery staple"),
+            Err(DocumentError::WrongPassphrase)
+        ));
+        assert!(matches!(
+            decrypt(&sealed[..MAGIC.len() + 4], "correct horse"),
+            Err(DocumentError::Encryption(_))
+        ));
+
+        // Every save gets its own salt and nonce
+        assert_ne!(sealed, encrypt(b"(elements: [])", "correct horse").unwrap());
+    }
+}
@@ -0,0 +1,43 @@
+use crate::command::Command;
+use std::sync::mpsc::{Receiver, Sender, channel};
+
+/// Something that happened to the document
+#[derive(Clone, Debug)]
+pub enum EditorEvent {
+    CommandExecuted(Command),
+    CommandUndone(Command),
+    CommandRedone(Command),
+}
+
+impl EditorEvent {
+    /// The command the event is about
+    pub fn command(&self) -> &Command {
+        match self {
+            EditorEvent::CommandExecuted(command)
+            | EditorEvent::CommandUndone(command)
+            | EditorEvent::CommandRedone(command) => command,
+        }
+    }
+}
+
+/// Hands every published event to each subscriber
+///
+/// Subscribers read events from their receiver whenever suits them, e.g. once a
+/// frame; dropping the receiver unsubscribes.
+#[derive(Debug, Default)]
+pub struct EventBus {
+    subscribers: Vec<Sender<EditorEvent>>,
+}
+
+impl EventBus {
+    pub fn subscribe(&mut self) -> Receiver<EditorEvent> {
+        let (sender, receiver) = channel();
+        self.subscribers.push(sender);
+        receiver
+    }
+
+    pub fn publish(&mut self, event: EditorEvent) {
+        self.subscribers
+            .retain(|subscriber| subscriber.send(event.clone()).is_ok());
+    }
+}
@@ -0,0 +1,91 @@
+use super::raster::{
+    blank_canvas, burn_in_redactions, draw_element, export_bounds, exported_elements,
+};
+use super::{ExportError, ExportSettings, watermark};
+use crate::element::Element;
+use crate::replay::drawing_order;
+use crate::state::{EditorModel, ElementId};
+use egui::{ColorImage, Context};
+
+/// How long the finished drawing stays on screen before the animation loops
+const FINAL_FRAME_HOLD_MS: u32 = 2000;
+
+/// Export the drawing replay as a looping animated GIF
+///
+/// Each frame adds one element in drawing order, so the animation plays back
+/// the same sequence as the in-app replay at the given speed.
+pub fn export_replay_gif(
+    editor_model: &EditorModel,
+    settings: &ExportSettings,
+    speed: f32,
+    ctx: &Context,
+) -> Result<Vec<u8>, ExportError> {
+    let bounds = export_bounds(editor_model, settings)?;
+    let mut canvas = blank_canvas(bounds, settings)?;
+    let elements = exported_elements(editor_model, settings);
+    let element_ids: Vec<ElementId> = elements.iter().map(|element| element.id()).collect();
+    let steps: Vec<_> = drawing_order(editor_model)
+        .into_iter()
+        .filter(|step| element_ids.contains(&step.element_id))
+        .collect();
+
+    let mut frames = Vec::with_capacity(steps.len() + 1);
+    // Start from the empty canvas; each frame is shown for the delay before the next step
+    frames.push(canvas.clone());
+    let mut delays = Vec::with_capacity(steps.len() + 1);
+
+    for step in &steps {
+        delays.push((step.delay / speed.max(0.01) * 1000.0) as u32);
+        if let Some(element) = editor_model.find_element_by_id(step.element_id) {
+            // Redactions are burned into every frame below instead
+            if !(settings.privacy_scrub && element.metadata().is_redaction()) {
+                draw_element(&mut canvas, element, bounds, settings, ctx)?;
+            }
+        }
+        frames.push(canvas.clone());
+    }
+    delays.push(FINAL_FRAME_HOLD_MS);
+
+    // Content is hidden from the first frame on, not only once the redaction was drawn
+    if settings.privacy_scrub {
+        for frame in &mut frames {
+            burn_in_redactions(frame, &elements, bounds, settings.scale, ctx)?;
+        }
+    }
+
+    if let Some(watermark) = &settings.watermark {
+        for frame in &mut frames {
+            watermark::apply_watermark(frame, watermark, settings.scale, ctx)?;
+        }
+    }
+
+    encode_gif(&frames, &delays)
+}
+
+fn encode_gif(frames: &[ColorImage], delays_ms: &[u32]) -> Result<Vec<u8>, ExportError> {
+    use ::image::codecs::gif::{GifEncoder, Repeat};
+    use ::image::{Delay, Frame, RgbaImage};
+
+    let encode_error = |err: ::image::ImageError| ExportError::Encode(err.to_string());
+
+    let mut output = Vec::new();
+    {
+        let mut encoder = GifEncoder::new(&mut output);
+        encoder.set_repeat(Repeat::Infinite).map_err(encode_error)?;
+
+        for (image, &delay_ms) in frames.iter().zip(delays_ms) {
+            let [width, height] = image.size;
+            let bytes = image
+                .pixels
+                .iter()
+                .flat_map(|pixel| pixel.to_srgba_unmultiplied())
+                .collect();
+            let buffer = RgbaImage::from_raw(width as u32, height as u32, bytes)
+                .ok_or_else(|| ExportError::Encode("pixel buffer size mismatch".to_string()))?;
+            let frame = Frame::from_parts(buffer, 0, 0, Delay::from_numer_denom_ms(delay_ms, 1));
+            encoder.encode_frame(frame).map_err(encode_error)?;
+        }
+    }
+
+    Ok(output)
+}
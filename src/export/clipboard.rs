@@ -0,0 +1,51 @@
+//! Copying exported images to the system clipboard (native only)
+
+use super::{ExportError, ExportSettings, rasterize_document, rasterize_selection};
+use crate::state::EditorModel;
+use egui::{ColorImage, Context};
+
+pub use arboard::Clipboard;
+
+/// Rasterize the document and put the image on the clipboard
+///
+/// On Linux the clipboard owner has to stay alive for the image to remain
+/// pasteable, so the caller keeps `clipboard` around between copies.
+pub fn copy_image_to_clipboard(
+    clipboard: &mut Clipboard,
+    editor_model: &EditorModel,
+    settings: &ExportSettings,
+    ctx: &Context,
+) -> Result<(), ExportError> {
+    let image = rasterize_document(editor_model, settings, ctx)?;
+    set_clipboard_image(clipboard, &image)
+}
+
+/// Rasterize the selected elements, cropped to them, and put the image on the
+/// clipboard, ready to paste into a chat or email
+pub fn copy_selection_to_clipboard(
+    clipboard: &mut Clipboard,
+    editor_model: &EditorModel,
+    settings: &ExportSettings,
+    ctx: &Context,
+) -> Result<(), ExportError> {
+    let image = rasterize_selection(editor_model, settings, ctx)?;
+    set_clipboard_image(clipboard, &image)
+}
+
+fn set_clipboard_image(clipboard: &mut Clipboard, image: &ColorImage) -> Result<(), ExportError> {
+    let [width, height] = image.size;
+    let mut bytes = Vec::with_capacity(width * height * 4);
+    for pixel in &image.pixels {
+        bytes.extend_from_slice(&pixel.to_srgba_unmultiplied());
+    }
+
+    clipboard
+        .set_image(arboard::ImageData {
+            width,
+            height,
+            bytes: bytes.into(),
+        })
+        .map_err(|err| ExportError::Clipboard(err.to_string()))?;
+    log::info!("Copied {}x{} image to the clipboard", width, height);
+    Ok(())
+}
@@ -0,0 +1,113 @@
+//! Post-export hooks: telling other tools about a freshly exported file
+//!
+//! Native builds run a user-configured shell command. Web embeds can define a
+//! global JavaScript function named [`WEB_EXPORT_CALLBACK`] instead.
+
+use super::ExportError;
+use std::path::Path;
+
+/// Placeholder replaced by the quoted export path in hook commands
+pub const PATH_PLACEHOLDER: &str = "{path}";
+/// Environment variable holding the export path while the hook runs
+pub const PATH_ENV_VAR: &str = "PAINT_EXPORT_PATH";
+/// Global JavaScript function called with the export path on the web
+pub const WEB_EXPORT_CALLBACK: &str = "onPaintExport";
+
+/// Build the shell command line for a hook
+///
+/// Every `{path}` is replaced by the quoted path; without a placeholder the
+/// path is appended as the last argument.
+pub fn hook_command_line(command: &str, path: &Path) -> String {
+    let quoted = quote_for_shell(&path.to_string_lossy());
+    if command.contains(PATH_PLACEHOLDER) {
+        command.replace(PATH_PLACEHOLDER, &quoted)
+    } else {
+        format!("{} {}", command.trim_end(), quoted)
+    }
+}
+
+#[cfg(not(windows))]
+fn quote_for_shell(text: &str) -> String {
+    format!("'{}'", text.replace('\'', r"'\''"))
+}
+
+#[cfg(windows)]
+fn quote_for_shell(text: &str) -> String {
+    format!("\"{}\"", text.replace('"', "\"\""))
+}
+
+/// Start the post-export hook for `path`, if one is configured
+///
+/// The command runs in the background; its exit status is only logged.
+#[cfg(not(target_arch = "wasm32"))]
+pub fn run_post_export_hook(command: &str, path: &Path) -> Result<bool, ExportError> {
+    if command.trim().is_empty() {
+        return Ok(false);
+    }
+
+    let command_line = hook_command_line(command, path);
+    log::info!("Running post-export hook: {}", command_line);
+
+    let mut child = shell_command(&command_line)
+        .env(PATH_ENV_VAR, path)
+        .spawn()
+        .map_err(|err| ExportError::Hook(err.to_string()))?;
+
+    std::thread::spawn(move || match child.wait() {
+        Ok(status) if status.success() => log::info!("Post-export hook finished"),
+        Ok(status) => log::warn!("Post-export hook exited with {}", status),
+        Err(err) => log::warn!("Failed to wait for post-export hook: {}", err),
+    });
+    Ok(true)
+}
+
+/// A process running `command_line` through the platform shell
+#[cfg(not(target_arch = "wasm32"))]
+pub fn shell_command(command_line: &str) -> std::process::Command {
+    #[cfg(windows)]
+    let shell = ("cmd", "/C");
+    #[cfg(not(windows))]
+    let shell = ("sh", "-c");
+
+    let mut command = std::process::Command::new(shell.0);
+    command.arg(shell.1).arg(command_line);
+    command
+}
+
+/// Call the page's export callback for `path`, if the page defines one
+///
+/// Shell commands can't run in the browser, so `command` is ignored here.
+#[cfg(target_arch = "wasm32")]
+pub fn run_post_export_hook(_command: &str, path: &Path) -> Result<bool, ExportError> {
+    use eframe::wasm_bindgen::{JsCast as _, JsValue};
+
+    let callback = js_sys::Reflect::get(&js_sys::global(), &JsValue::from_str(WEB_EXPORT_CALLBACK))
+        .map_err(|err| ExportError::Hook(format!("{:?}", err)))?;
+    let Some(callback) = callback.dyn_ref::<js_sys::Function>() else {
+        return Ok(false);
+    };
+
+    log::info!("Calling {} for {}", WEB_EXPORT_CALLBACK, path.display());
+    callback
+        .call1(&JsValue::NULL, &JsValue::from_str(&path.to_string_lossy()))
+        .map_err(|err| ExportError::Hook(format!("{:?}", err)))?;
+    Ok(true)
+}
+
+#[cfg(all(test, not(windows)))]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_hook_command_line_quotes_path() {
+        let path = Path::new("/tmp/it's here.png");
+        assert_eq!(
+            hook_command_line("upload --file {path} --public", path),
+            r"upload --file '/tmp/it'\''s here.png' --public"
+        );
+        assert_eq!(
+            hook_command_line("upload ", path),
+            r"upload '/tmp/it'\''s here.png'"
+        );
+    }
+}
@@ -0,0 +1,64 @@
+use crate::document::DocumentMetadata;
+use serde::{Deserialize, Serialize};
+
+/// Name and version recorded as the creating software in exported files
+pub const CREATION_TOOL: &str = concat!(env!("CARGO_PKG_NAME"), " ", env!("CARGO_PKG_VERSION"));
+
+/// Descriptive metadata embedded into exported files
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
+pub struct ExportMetadata {
+    /// Whether to embed anything at all
+    pub embed: bool,
+    pub title: String,
+    pub author: String,
+    /// Optional; omitted from the file when empty
+    pub copyright: String,
+}
+
+impl Default for ExportMetadata {
+    fn default() -> Self {
+        Self {
+            embed: true,
+            title: String::new(),
+            author: String::new(),
+            copyright: String::new(),
+        }
+    }
+}
+
+impl ExportMetadata {
+    /// Fill any empty fields from the document's own metadata
+    pub fn prefill_from(&mut self, document: &DocumentMetadata) {
+        for (field, value) in [
+            (&mut self.title, &document.title),
+            (&mut self.author, &document.author),
+            (&mut self.copyright, &document.copyright),
+        ] {
+            if field.trim().is_empty() {
+                *field = value.clone();
+            }
+        }
+    }
+
+    /// Key/value pairs to embed, using the standard PNG keywords
+    ///
+    /// Empty fields are skipped; the creation tool is always included when embedding.
+    pub fn entries(&self) -> Vec<(&'static str, String)> {
+        if !self.embed {
+            return Vec::new();
+        }
+
+        let mut entries: Vec<(&'static str, String)> = [
+            ("Title", &self.title),
+            ("Author", &self.author),
+            ("Copyright", &self.copyright),
+        ]
+        .into_iter()
+        .filter(|(_, value)| !value.trim().is_empty())
+        .map(|(key, value)| (key, value.trim().to_string()))
+        .collect();
+
+        entries.push(("Software", CREATION_TOOL.to_string()));
+        entries
+    }
+}
@@ -0,0 +1,901 @@
+//! Exporting the document to image files, SVG, HTML slideshows and replay animations
+//!
+//! Export rasterizes the document on the CPU (independently of the GPU renderer)
+//! so that export-only effects such as watermarks can be composited on top
+//! without ever becoming part of the document.
+
+mod animation;
+#[cfg(not(target_arch = "wasm32"))]
+mod clipboard;
+mod hooks;
+mod metadata;
+mod print;
+mod raster;
+mod slices;
+mod slideshow;
+mod svg;
+mod watermark;
+
+pub use animation::export_replay_gif;
+#[cfg(not(target_arch = "wasm32"))]
+pub use clipboard::{Clipboard, copy_image_to_clipboard, copy_selection_to_clipboard};
+pub use hooks::{PATH_ENV_VAR, PATH_PLACEHOLDER, WEB_EXPORT_CALLBACK, run_post_export_hook};
+#[cfg(not(target_arch = "wasm32"))]
+pub(crate) use hooks::{hook_command_line, shell_command};
+pub use metadata::{CREATION_TOOL, ExportMetadata};
+#[cfg(not(target_arch = "wasm32"))]
+pub use print::send_to_printer;
+pub use print::{
+    PaperSize, PrintLayout, PrintScaling, PrintSettings, encode_print_pdf, print_layout,
+    render_print_page,
+};
+pub use raster::{
+    document_bounds, export_artboard, export_bounds, rasterize_document, rasterize_selection,
+};
+#[cfg(not(target_arch = "wasm32"))]
+pub use slices::export_slices_to_folder;
+pub use slices::{export_slice_png, rasterize_region};
+pub use slideshow::{export_slideshow, slideshow_html};
+pub use svg::export_svg;
+pub use watermark::{Watermark, WatermarkAnchor, WatermarkContent};
+
+use crate::element::Resampling;
+use crate::state::EditorModel;
+use crate::texture_manager::TextureGenerationError;
+use egui::{Color32, ColorImage, Context};
+use serde::{Deserialize, Serialize};
+use thiserror::Error;
+
+/// Largest width or height (in pixels) an export may produce
+pub const MAX_EXPORT_SIDE: usize = 16384;
+
+/// Errors that can occur while exporting
+#[derive(Error, Debug)]
+pub enum ExportError {
+    #[error("Nothing to export: the document is empty")]
+    EmptyDocument,
+    #[error("Nothing to copy: no elements are selected")]
+    EmptySelection,
+    #[error("Export is too large ({width}x{height} px, max {MAX_EXPORT_SIDE} px per side)")]
+    TooLarge { width: usize, height: usize },
+    #[error("Failed to rasterize element: {0}")]
+    Rasterize(#[from] TextureGenerationError),
+    #[error("Failed to decode the reference image: {0}")]
+    ReferenceImage(image::ImageError),
+    #[error("Invalid watermark: {0}")]
+    Watermark(String),
+    #[error("Failed to encode image: {0}")]
+    Encode(String),
+    #[error("Failed to write file: {0}")]
+    Io(#[from] std::io::Error),
+    #[error("Post-export hook failed: {0}")]
+    Hook(String),
+    #[error("Failed to copy to the clipboard: {0}")]
+    Clipboard(String),
+    #[error("Printing failed: {0}")]
+    Print(String),
+}
+
+/// Settings that control how the document is rasterized for export
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
+pub struct ExportSettings {
+    /// Output pixels per document unit
+    pub scale: f32,
+    /// Empty space added around the document bounds, in document units
+    pub margin: f32,
+    /// Optional overlay composited only into the exported image
+    pub watermark: Option<Watermark>,
+    /// Descriptive metadata embedded into the exported file
+    #[serde(default)]
+    pub metadata: ExportMetadata,
+    /// Leave the background transparent instead of filling it
+    #[serde(default)]
+    pub transparent_background: bool,
+    /// Burn redactions in, rasterize vector output and embed no metadata, for sharing
+    #[serde(default)]
+    pub privacy_scrub: bool,
+    /// Draw the document's reference image under the elements of raster exports
+    #[serde(default)]
+    pub include_reference_image: bool,
+    /// How images are resampled to the size they're exported at
+    #[serde(default)]
+    pub resampling: Resampling,
+    /// Fill behind the elements; taken from the canvas, so not saved in presets
+    #[serde(skip, default = "default_background_color")]
+    pub background_color: Color32,
+    /// Command run with the path of each exported file; an app preference, so not saved in presets
+    #[serde(skip)]
+    pub post_export_hook: String,
+    /// Artboard to export instead of the active one; per document, so not saved in presets
+    #[serde(skip)]
+    pub artboard_id: Option<usize>,
+}
+
+impl ExportSettings {
+    /// The color exported images start from
+    pub fn background(&self) -> Color32 {
+        if self.transparent_background {
+            Color32::TRANSPARENT
+        } else {
+            self.background_color
+        }
+    }
+
+    /// Metadata to embed in the exported file: none at all for privacy scrub exports
+    pub fn embedded_metadata(&self) -> ExportMetadata {
+        if self.privacy_scrub {
+            ExportMetadata {
+                embed: false,
+                ..ExportMetadata::default()
+            }
+        } else {
+            self.metadata.clone()
+        }
+    }
+}
+
+impl Default for ExportSettings {
+    fn default() -> Self {
+        Self {
+            scale: 1.0,
+            margin: 10.0,
+            watermark: None,
+            metadata: ExportMetadata::default(),
+            transparent_background: false,
+            privacy_scrub: false,
+            include_reference_image: false,
+            resampling: Resampling::Lanczos,
+            background_color: default_background_color(),
+            post_export_hook: String::new(),
+            artboard_id: None,
+        }
+    }
+}
+
+fn default_background_color() -> Color32 {
+    Color32::WHITE
+}
+
+/// A named, reusable set of export settings
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
+pub struct ExportPreset {
+    pub name: String,
+    pub settings: ExportSettings,
+}
+
+/// The presets offered before the user has saved any of their own
+pub fn default_presets() -> Vec<ExportPreset> {
+    vec![
+        ExportPreset {
+            name: "Standard".to_string(),
+            settings: ExportSettings::default(),
+        },
+        ExportPreset {
+            name: "Work in progress".to_string(),
+            settings: ExportSettings {
+                watermark: Some(Watermark::text("Work in progress")),
+                ..ExportSettings::default()
+            },
+        },
+        ExportPreset {
+            name: "Privacy scrub".to_string(),
+            settings: ExportSettings {
+                privacy_scrub: true,
+                ..ExportSettings::default()
+            },
+        },
+    ]
+}
+
+/// Encode a rasterized image as PNG bytes, embedding metadata as text chunks
+///
+/// Latin-1 values are written as `tEXt` chunks; anything else as UTF-8 `iTXt`.
+pub fn encode_png(image: &ColorImage, metadata: &ExportMetadata) -> Result<Vec<u8>, ExportError> {
+    let [width, height] = image.size;
+    let mut bytes = Vec::with_capacity(width * height * 4);
+    for pixel in &image.pixels {
+        bytes.extend_from_slice(&pixel.to_srgba_unmultiplied());
+    }
+
+    let encode_error = |err: png::EncodingError| ExportError::Encode(err.to_string());
+
+    let mut output = Vec::new();
+    let mut encoder = png::Encoder::new(&mut output, width as u32, height as u32);
+    encoder.set_color(png::ColorType::Rgba);
+    encoder.set_depth(png::BitDepth::Eight);
+
+    for (key, value) in metadata.entries() {
+        if value.chars().all(|c| (c as u32) < 256) {
+            encoder
+                .add_text_chunk(key.to_string(), value)
+                .map_err(encode_error)?;
+        } else {
+            encoder
+                .add_itxt_chunk(key.to_string(), value)
+                .map_err(encode_error)?;
+        }
+    }
+
+    let mut writer = encoder.write_header().map_err(encode_error)?;
+    writer.write_image_data(&bytes).map_err(encode_error)?;
+    writer.finish().map_err(encode_error)?;
+
+    Ok(output)
+}
+
+/// Rasterize the document and encode it as PNG bytes
+pub fn export_png(
+    editor_model: &EditorModel,
+    settings: &ExportSettings,
+    ctx: &Context,
+) -> Result<Vec<u8>, ExportError> {
+    let image = rasterize_document(editor_model, settings, ctx)?;
+    encode_png(&image, &settings.embedded_metadata())
+}
+
+/// Rasterize the document and write it to a PNG file
+#[cfg(not(target_arch = "wasm32"))]
+pub fn export_png_to_file(
+    path: &std::path::Path,
+    editor_model: &EditorModel,
+    settings: &ExportSettings,
+    ctx: &Context,
+) -> Result<(), ExportError> {
+    let bytes = export_png(editor_model, settings, ctx)?;
+    std::fs::write(path, bytes)?;
+    log::info!("Exported document to {}", path.display());
+    Ok(())
+}
+
+/// Serialize the document and write it to an SVG file
+#[cfg(not(target_arch = "wasm32"))]
+pub fn export_svg_to_file(
+    path: &std::path::Path,
+    editor_model: &EditorModel,
+    settings: &ExportSettings,
+    ctx: &Context,
+) -> Result<(), ExportError> {
+    let svg = export_svg(editor_model, settings, ctx)?;
+    std::fs::write(path, svg)?;
+    log::info!("Exported document to {}", path.display());
+    Ok(())
+}
+
+/// Render the drawing replay as an animated GIF and write it to a file
+#[cfg(not(target_arch = "wasm32"))]
+pub fn export_replay_gif_to_file(
+    path: &std::path::Path,
+    editor_model: &EditorModel,
+    settings: &ExportSettings,
+    speed: f32,
+    ctx: &Context,
+) -> Result<(), ExportError> {
+    let bytes = export_replay_gif(editor_model, settings, speed, ctx)?;
+    std::fs::write(path, bytes)?;
+    log::info!("Exported replay to {}", path.display());
+    Ok(())
+}
+
+/// Render the document as an HTML slideshow and write it to a file
+#[cfg(not(target_arch = "wasm32"))]
+pub fn export_slideshow_to_file(
+    path: &std::path::Path,
+    editor_model: &EditorModel,
+    settings: &ExportSettings,
+    ctx: &Context,
+) -> Result<(), ExportError> {
+    let html = export_slideshow(editor_model, settings, ctx)?;
+    std::fs::write(path, html)?;
+    log::info!("Exported slideshow to {}", path.display());
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::artboard::Artboard;
+    use crate::element::{Element, ElementId, ElementType, REDACTION_PROPERTY, factory};
+    use crate::reference_image::ReferenceImage;
+    use crate::test_support::TestHarness;
+    use egui::{Color32, Pos2, Rect, pos2, vec2};
+    use std::path::PathBuf;
+
+    fn create_test_model() -> EditorModel {
+        let mut model = EditorModel::new();
+        let points = vec![Pos2::new(10.0, 10.0), Pos2::new(60.0, 10.0)];
+        model
+            .add_element(factory::create_stroke(ElementId::new(1), points, 4.0, Color32::RED))
+            .unwrap();
+        model
+    }
+
+    fn fonts_ready_context() -> Context {
+        let ctx = Context::default();
+        // Fonts become available after the first frame
+        let _ = ctx.run(Default::default(), |_| {});
+        ctx
+    }
+
+    #[test]
+    fn test_empty_document_fails() {
+        let ctx = Context::default();
+        let result = rasterize_document(&EditorModel::new(), &ExportSettings::default(), &ctx);
+        assert!(matches!(result, Err(ExportError::EmptyDocument)));
+    }
+
+    #[test]
+    fn test_rasterize_stroke_and_scale() {
+        let ctx = Context::default();
+        let model = create_test_model();
+
+        let image = rasterize_document(&model, &ExportSettings::default(), &ctx).unwrap();
+        assert!(image.pixels.contains(&Color32::RED));
+
+        let settings = ExportSettings {
+            scale: 2.0,
+            ..ExportSettings::default()
+        };
+        let scaled = rasterize_document(&model, &settings, &ctx).unwrap();
+        assert_eq!(scaled.size[0], image.size[0] * 2);
+    }
+
+    #[test]
+    fn test_selection_is_cropped_to_selected_elements() {
+        let ctx = Context::default();
+        let mut model = create_test_model();
+        let points = vec![Pos2::new(200.0, 100.0), Pos2::new(220.0, 100.0)];
+        model
+            .add_element(factory::create_stroke(ElementId::new(2), points, 4.0, Color32::BLUE))
+            .unwrap();
+
+        let settings = ExportSettings::default();
+        let result = rasterize_selection(&model, &settings, &ctx);
+        assert!(matches!(result, Err(ExportError::EmptySelection)));
+
+        model.select_element(ElementId::new(2));
+        let image = rasterize_selection(&model, &settings, &ctx).unwrap();
+        let rect = model.elements[1].rect().expand(settings.margin);
+        assert_eq!(image.size[0], rect.width().ceil() as usize);
+        assert!(image.pixels.contains(&Color32::BLUE));
+        assert!(!image.pixels.contains(&Color32::RED));
+    }
+
+    #[test]
+    fn test_slices_export_their_region_at_each_scale() {
+        let ctx = Context::default();
+        let model = create_test_model();
+        let rect = egui::Rect::from_min_size(Pos2::new(0.0, 0.0), egui::vec2(30.0, 20.0));
+        let settings = ExportSettings::default();
+
+        let image = rasterize_region(&model, rect, &settings, &ctx).unwrap();
+        assert_eq!(image.size, [30, 20]);
+        assert!(image.pixels.contains(&Color32::RED));
+
+        let doubled = ExportSettings {
+            scale: 2.0,
+            ..settings.clone()
+        };
+        let image = rasterize_region(&model, rect, &doubled, &ctx).unwrap();
+        assert_eq!(image.size, [60, 40]);
+
+        // Nothing under the slice still exports, as blank
+        let empty = rect.translate(egui::vec2(200.0, 200.0));
+        let image = rasterize_region(&model, empty, &settings, &ctx).unwrap();
+        assert!(!image.pixels.contains(&Color32::RED));
+    }
+
+    #[test]
+    fn test_watermark_only_changes_export() {
+        let ctx = fonts_ready_context();
+        let model = create_test_model();
+        let plain = rasterize_document(&model, &ExportSettings::default(), &ctx).unwrap();
+
+        let mut watermark = Watermark::text("WIP");
+        watermark.size = 8.0;
+        watermark.margin = 0.0;
+        watermark.anchor = WatermarkAnchor::TopLeft;
+        let settings = ExportSettings {
+            watermark: Some(watermark),
+            ..ExportSettings::default()
+        };
+        let marked = rasterize_document(&model, &settings, &ctx).unwrap();
+
+        assert_eq!(plain.size, marked.size);
+        assert_ne!(plain.pixels, marked.pixels);
+        assert_eq!(model.elements.len(), 1);
+    }
+
+    #[test]
+    fn test_reference_image_only_exported_when_asked() {
+        let ctx = Context::default();
+        let mut model = create_test_model();
+        let mut png = Vec::new();
+        image::RgbaImage::from_pixel(2, 2, image::Rgba([0, 0, 255, 255]))
+            .write_to(
+                &mut std::io::Cursor::new(&mut png),
+                image::ImageOutputFormat::Png,
+            )
+            .unwrap();
+        let mut reference =
+            ReferenceImage::new(png, egui::Rect::from_min_size(Pos2::ZERO, egui::vec2(4.0, 4.0)));
+        reference.opacity = 1.0;
+        reference.tiled = true;
+        model.reference_image = Some(reference);
+
+        let plain = rasterize_document(&model, &ExportSettings::default(), &ctx).unwrap();
+        assert!(!plain.pixels.contains(&Color32::BLUE));
+
+        let settings = ExportSettings {
+            include_reference_image: true,
+            ..ExportSettings::default()
+        };
+        let traced = rasterize_document(&model, &settings, &ctx).unwrap();
+        // Tiled, the reference shows in every corner around the stroke
+        assert_eq!(traced.pixels[0], Color32::BLUE);
+        assert_eq!(traced.pixels[traced.pixels.len() - 1], Color32::BLUE);
+        assert!(traced.pixels.contains(&Color32::RED));
+    }
+
+    #[test]
+    fn test_png_embeds_metadata() {
+        let ctx = Context::default();
+        let settings = ExportSettings {
+            metadata: ExportMetadata {
+                title: "Sketch".to_string(),
+                author: "Zoë".to_string(),
+                ..ExportMetadata::default()
+            },
+            ..ExportSettings::default()
+        };
+        let bytes = export_png(&create_test_model(), &settings, &ctx).unwrap();
+
+        let reader = png::Decoder::new(bytes.as_slice()).read_info().unwrap();
+        let info = reader.info();
+        let text = |key: &str| {
+            info.uncompressed_latin1_text
+                .iter()
+                .find(|chunk| chunk.keyword == key)
+                .map(|chunk| chunk.text.clone())
+        };
+        assert_eq!(text("Title").as_deref(), Some("Sketch"));
+        assert_eq!(text("Author").as_deref(), Some("Zoë"));
+        assert_eq!(text("Software").as_deref(), Some(CREATION_TOOL));
+        assert!(text("Copyright").is_none());
+    }
+
+    #[test]
+    fn test_svg_contains_strokes_and_metadata() {
+        let ctx = Context::default();
+        let mut settings = ExportSettings::default();
+        settings.metadata.copyright = "CC-BY <4.0>".to_string();
+        let svg = export_svg(&create_test_model(), &settings, &ctx).unwrap();
+
+        assert!(svg.contains("<polyline id=\"element-1\""));
+        assert!(svg.contains("<dc:rights>CC-BY &lt;4.0&gt;</dc:rights>"));
+    }
+
+    #[test]
+    fn test_privacy_scrub_burns_in_redactions_and_strips_metadata() {
+        let ctx = Context::default();
+        let mut model = create_test_model();
+        // A translucent redaction over the red stroke, and a blue stroke drawn on top of it
+        let corners = vec![
+            Pos2::new(0.0, 0.0),
+            Pos2::new(70.0, 0.0),
+            Pos2::new(70.0, 20.0),
+            Pos2::new(0.0, 20.0),
+        ];
+        let translucent = Color32::from_rgba_unmultiplied(0, 0, 0, 100);
+        let mut redaction = factory::create_shape(ElementId::new(2), corners, translucent);
+        redaction.metadata_mut().set(REDACTION_PROPERTY, "true");
+        model.add_element(redaction).unwrap();
+        let points = vec![Pos2::new(20.0, 12.0), Pos2::new(40.0, 12.0)];
+        model
+            .add_element(factory::create_stroke(ElementId::new(3), points, 4.0, Color32::BLUE))
+            .unwrap();
+
+        let mut settings = ExportSettings::default();
+        settings.metadata.title = "Secret".to_string();
+        let plain = rasterize_document(&model, &settings, &ctx).unwrap();
+        assert!(plain.pixels.contains(&Color32::BLUE));
+
+        settings.privacy_scrub = true;
+        let scrubbed = rasterize_document(&model, &settings, &ctx).unwrap();
+        assert!(scrubbed.pixels.contains(&Color32::BLACK));
+        // Nothing but the background and the solid redaction is left
+        assert!(
+            scrubbed
+                .pixels
+                .iter()
+                .all(|pixel| *pixel == Color32::WHITE || *pixel == Color32::BLACK)
+        );
+
+        let bytes = export_png(&model, &settings, &ctx).unwrap();
+        let reader = png::Decoder::new(bytes.as_slice()).read_info().unwrap();
+        assert!(reader.info().uncompressed_latin1_text.is_empty());
+        assert!(reader.info().utf8_text.is_empty());
+
+        let svg = export_svg(&model, &settings, &ctx).unwrap();
+        assert!(svg.contains("data:image/png;base64,"));
+        assert!(!svg.contains("element-"));
+        assert!(!svg.contains("Secret"));
+        assert!(!svg.contains("<!--"));
+    }
+
+    #[test]
+    fn test_slideshow_has_one_section_per_slide() {
+        let ctx = Context::default();
+        let settings = ExportSettings::default();
+        let page = rasterize_document(&create_test_model(), &settings, &ctx).unwrap();
+
+        let html = slideshow_html("Story <board>", &[page.clone(), page], &settings).unwrap();
+        assert_eq!(html.matches("<section class=\"slide\"").count(), 2);
+        assert!(html.contains("<title>Story &lt;board&gt;</title>"));
+        assert!(html.contains("1 / 2"));
+
+        assert!(matches!(
+            slideshow_html("Empty", &[], &settings),
+            Err(ExportError::EmptyDocument)
+        ));
+    }
+
+    #[test]
+    fn test_replay_gif_has_frame_per_element() {
+        use ::image::AnimationDecoder as _;
+
+        let ctx = Context::default();
+        let mut model = create_test_model();
+        let points = vec![Pos2::new(10.0, 30.0), Pos2::new(60.0, 30.0)];
+        model
+            .add_element(factory::create_stroke(ElementId::new(2), points, 4.0, Color32::BLUE))
+            .unwrap();
+
+        let bytes = export_replay_gif(&model, &ExportSettings::default(), 1.0, &ctx).unwrap();
+        let decoder = ::image::codecs::gif::GifDecoder::new(bytes.as_slice()).unwrap();
+        let frames = decoder.into_frames().collect_frames().unwrap();
+        // Empty canvas, one frame per element
+        assert_eq!(frames.len(), 3);
+    }
+
+    #[test]
+    fn test_background_color_and_transparency() {
+        let ctx = Context::default();
+        let model = create_test_model();
+
+        let mut settings = ExportSettings {
+            background_color: Color32::from_rgb(30, 30, 30),
+            ..ExportSettings::default()
+        };
+        let image = rasterize_document(&model, &settings, &ctx).unwrap();
+        assert_eq!(image.pixels[0], Color32::from_rgb(30, 30, 30));
+
+        settings.transparent_background = true;
+        let image = rasterize_document(&model, &settings, &ctx).unwrap();
+        assert_eq!(image.pixels[0], Color32::TRANSPARENT);
+        assert!(image.pixels.contains(&Color32::RED));
+    }
+
+    #[test]
+    fn test_export_covers_artboard() {
+        let ctx = Context::default();
+        let mut model = create_test_model();
+        // The stroke runs past the right edge of the artboard
+        let artboard = Artboard::new(
+            "Left",
+            egui::Rect::from_min_size(Pos2::ZERO, egui::vec2(40.0, 30.0)),
+        );
+        model.artboards.push(artboard);
+
+        let image = rasterize_document(&model, &ExportSettings::default(), &ctx).unwrap();
+        assert_eq!(image.size, [40, 30]);
+        assert!(image.pixels.contains(&Color32::RED));
+
+        let svg = export_svg(&model, &ExportSettings::default(), &ctx).unwrap();
+        assert!(svg.contains(r#"viewBox="0 0 40 30""#));
+
+        // An empty document with an artboard still exports a blank canvas
+        let mut empty = EditorModel::new();
+        empty.artboards = model.artboards.clone();
+        assert!(rasterize_document(&empty, &ExportSettings::default(), &ctx).is_ok());
+    }
+
+    #[test]
+    fn test_export_single_artboard() {
+        let ctx = Context::default();
+        let mut model = create_test_model();
+        model.artboards.push(Artboard::new(
+            "Left",
+            egui::Rect::from_min_size(Pos2::ZERO, egui::vec2(40.0, 30.0)),
+        ));
+        let right = Artboard::new(
+            "Right",
+            egui::Rect::from_min_size(Pos2::new(50.0, 0.0), egui::vec2(40.0, 30.0)),
+        );
+        let settings = ExportSettings {
+            artboard_id: Some(right.id),
+            ..Default::default()
+        };
+        model.artboards.push(right);
+
+        // The stroke reaches into the right artboard but belongs to the left one
+        let image = rasterize_document(&model, &settings, &ctx).unwrap();
+        assert_eq!(image.size, [40, 30]);
+        assert!(!image.pixels.contains(&Color32::RED));
+
+        let html = export_slideshow(&model, &ExportSettings::default(), &ctx).unwrap();
+        assert_eq!(html.matches("<section").count(), 2);
+    }
+
+    #[test]
+    fn test_png_keeps_partial_alpha() {
+        let ctx = Context::default();
+        let mut model = EditorModel::new();
+        let points = vec![Pos2::new(10.0, 10.0), Pos2::new(60.0, 10.0)];
+        let translucent = Color32::from_rgba_unmultiplied(255, 0, 0, 128);
+        model
+            .add_element(factory::create_stroke(ElementId::new(1), points, 4.0, translucent))
+            .unwrap();
+
+        let settings = ExportSettings {
+            transparent_background: true,
+            ..ExportSettings::default()
+        };
+        let bytes = export_png(&model, &settings, &ctx).unwrap();
+        let decoded = ::image::load_from_memory(&bytes).unwrap().to_rgba8();
+
+        assert_eq!(decoded.get_pixel(0, 0).0, [0, 0, 0, 0]);
+        let stroke_pixels: Vec<_> = decoded.pixels().filter(|pixel| pixel[3] > 0).collect();
+        assert!(!stroke_pixels.is_empty());
+        for pixel in stroke_pixels {
+            // Unmultiplied on the way out: full red at half opacity
+            assert_eq!(pixel.0, [255, 0, 0, 128]);
+        }
+    }
+
+    #[test]
+    fn test_encode_png_roundtrip() {
+        let ctx = Context::default();
+        let bytes = export_png(&create_test_model(), &ExportSettings::default(), &ctx).unwrap();
+        let decoded = ::image::load_from_memory(&bytes).unwrap();
+        assert!(decoded.width() > 50);
+    }
+
+    #[test]
+    fn test_images_export_with_the_chosen_resampling() {
+        let ctx = Context::default();
+        let pixels = ::image::RgbaImage::from_fn(2, 1, |x, _| {
+            ::image::Rgba(if x == 0 { [0, 0, 0, 255] } else { [255; 4] })
+        });
+        let mut png = std::io::Cursor::new(Vec::new());
+        pixels
+            .write_to(&mut png, ::image::ImageOutputFormat::Png)
+            .unwrap();
+        let mut model = EditorModel::new();
+        model
+            .add_element(factory::create_image(
+                ElementId::new(1),
+                png.into_inner(),
+                egui::vec2(40.0, 20.0),
+                Pos2::ZERO,
+            ))
+            .unwrap();
+
+        let export = |resampling| {
+            let settings = ExportSettings {
+                margin: 0.0,
+                resampling,
+                ..ExportSettings::default()
+            };
+            rasterize_document(&model, &settings, &ctx).unwrap()
+        };
+        let is_gray = |color: &Color32| color.r() > 0 && color.r() < 255;
+        assert!(!export(Resampling::Nearest).pixels.iter().any(is_gray));
+        assert!(export(Resampling::Bilinear).pixels.iter().any(is_gray));
+    }
+
+    // Snapshot tests comparing rendered documents against stored images
+    //
+    // Each renders a small document, through export and through the canvas as
+    // TestHarness::screenshot paints it, and compares the result with a PNG in
+    // `tests/golden`. Pixels may differ a little, since float rounding isn't
+    // the same everywhere, but a change anyone would notice fails the test.
+    //
+    // Run with `UPDATE_GOLDEN=1` to write the images afresh after a deliberate
+    // change to rendering, and look them over before committing them. A missing
+    // image is written too, failing the test until it's committed. On a
+    // mismatch the image rendered is left beside the stored one as
+    // `<name>.new.png`.
+    /// How far a channel may be off and the pixel still count as the same
+    const CHANNEL_TOLERANCE: u8 = 12;
+    /// Share of pixels that may differ by more than [`CHANNEL_TOLERANCE`]
+    const MAX_DIFFERING_SHARE: f32 = 0.002;
+    /// Space kept around the elements in canvas snapshots, for selection handles
+    const CANVAS_MARGIN: f32 = 16.0;
+
+    fn golden_path(name: &str, suffix: &str) -> PathBuf {
+        PathBuf::from(env!("CARGO_MANIFEST_DIR"))
+            .join("tests/golden")
+            .join(format!("{}{}.png", name, suffix))
+    }
+
+    fn write_png(path: &PathBuf, image: &ColorImage) {
+        let bytes = encode_png(image, &ExportMetadata::default()).unwrap();
+        std::fs::create_dir_all(path.parent().unwrap()).unwrap();
+        std::fs::write(path, bytes).unwrap();
+    }
+
+    /// Compare `image` against the stored image named `name`, within the tolerances
+    fn assert_matches_golden(name: &str, image: &ColorImage) {
+        let path = golden_path(name, "");
+        let update = std::env::var_os("UPDATE_GOLDEN").is_some();
+        if update || !path.exists() {
+            write_png(&path, image);
+            assert!(
+                update,
+                "wrote missing {}; check it and commit it",
+                path.display()
+            );
+            return;
+        }
+
+        let decoded = ::image::open(&path).unwrap().to_rgba8();
+        let size = [decoded.width() as usize, decoded.height() as usize];
+        let golden = ColorImage::from_rgba_unmultiplied(size, decoded.as_raw());
+        let differing = if golden.size == image.size {
+            golden
+                .pixels
+                .iter()
+                .zip(&image.pixels)
+                .filter(|(expected, actual)| {
+                    let expected = expected.to_srgba_unmultiplied();
+                    let actual = actual.to_srgba_unmultiplied();
+                    (0..4).any(|i| expected[i].abs_diff(actual[i]) > CHANNEL_TOLERANCE)
+                })
+                .count()
+        } else {
+            image.pixels.len()
+        };
+        let allowed = (image.pixels.len() as f32 * MAX_DIFFERING_SHARE) as usize;
+        if differing > allowed {
+            let new_path = golden_path(name, ".new");
+            write_png(&new_path, image);
+            panic!(
+                "{} differs from {} in {} pixels (sizes {:?} and {:?}); rendered image written to {}",
+                name,
+                path.display(),
+                differing,
+                image.size,
+                golden.size,
+                new_path.display()
+            );
+        }
+    }
+
+    /// Export `editor_model` with the default settings
+    fn export(editor_model: &EditorModel) -> ColorImage {
+        let ctx = egui::Context::default();
+        rasterize_document(editor_model, &ExportSettings::default(), &ctx).unwrap()
+    }
+
+    /// Show `elements` on the canvas, select `selected` and snapshot the canvas
+    /// around them
+    fn canvas(elements: Vec<ElementType>, selected: &[ElementId]) -> ColorImage {
+        let mut harness = TestHarness::new();
+        for element in elements {
+            harness.editor_model.add_element(element).unwrap();
+        }
+        harness.set_tool("Selection");
+        for &id in selected {
+            harness.editor_model.select_element(id);
+        }
+        // A second frame, so textures made on the first are painted
+        harness.run_frame();
+        harness.run_frame();
+
+        let bounds = harness
+            .editor_model
+            .elements
+            .iter()
+            .map(crate::element::compute_element_rect)
+            .reduce(Rect::union)
+            .unwrap()
+            .expand(CANVAS_MARGIN);
+        let pixels = Rect::from_min_max(bounds.min.floor(), bounds.max.ceil());
+        harness.screenshot().region(&pixels, None)
+    }
+
+    /// Strokes in thicknesses from a hairline up, each solid, half and barely there
+    fn strokes() -> Vec<ElementType> {
+        let mut strokes = Vec::new();
+        for (row, thickness) in [1.0, 3.0, 8.0, 16.0].into_iter().enumerate() {
+            for (column, alpha) in [255, 128, 48].into_iter().enumerate() {
+                let start = pos2(40.0 + column as f32 * 90.0, 40.0 + row as f32 * 40.0);
+                let points = vec![
+                    start,
+                    start + vec2(30.0, -12.0),
+                    start + vec2(50.0, 10.0),
+                    start + vec2(70.0, 0.0),
+                ];
+                let color = Color32::from_rgba_unmultiplied(200, 40, 120, alpha);
+                let id = ElementId::new(strokes.len() + 1);
+                strokes.push(factory::create_stroke(id, points, thickness, color));
+            }
+        }
+        strokes
+    }
+
+    /// A small PNG of colored quarters with a gradient across the bottom ones
+    fn pattern_png() -> Vec<u8> {
+        let pattern = ::image::RgbaImage::from_fn(8, 8, |x, y| match (x < 4, y < 4) {
+            (true, true) => ::image::Rgba([220, 40, 40, 255]),
+            (false, true) => ::image::Rgba([40, 80, 220, 255]),
+            (_, false) => ::image::Rgba([(x * 32) as u8, 160, 60, 255]),
+        });
+        let mut png = Vec::new();
+        pattern
+            .write_to(
+                &mut std::io::Cursor::new(&mut png),
+                ::image::ImageOutputFormat::Png,
+            )
+            .unwrap();
+        png
+    }
+
+    /// The pattern stretched to a different size each way
+    fn resized_image() -> ElementType {
+        factory::create_image(
+            ElementId::new(1),
+            pattern_png(),
+            vec2(96.0, 48.0),
+            Pos2::new(40.0, 40.0),
+        )
+    }
+
+    fn model_of(elements: Vec<ElementType>) -> EditorModel {
+        let mut model = EditorModel::new();
+        for element in elements {
+            model.add_element(element).unwrap();
+        }
+        model
+    }
+
+    #[test]
+    fn test_strokes_of_each_thickness_and_alpha_render_as_stored() {
+        assert_matches_golden("strokes_export", &export(&model_of(strokes())));
+        assert_matches_golden("strokes_canvas", &canvas(strokes(), &[]));
+    }
+
+    #[test]
+    fn test_resized_image_renders_as_stored() {
+        assert_matches_golden("image_export", &export(&model_of(vec![resized_image()])));
+        assert_matches_golden("image_canvas", &canvas(vec![resized_image()], &[]));
+    }
+
+    #[test]
+    fn test_selections_render_as_stored() {
+        let elements = || {
+            let mut elements = strokes();
+            elements.truncate(3);
+            elements.push(factory::create_shape(
+                ElementId::new(4),
+                vec![
+                    pos2(60.0, 100.0),
+                    pos2(160.0, 100.0),
+                    pos2(140.0, 160.0),
+                    pos2(40.0, 160.0),
+                ],
+                Color32::from_rgb(40, 160, 200),
+            ));
+            elements
+        };
+        // One element has its own handles; several share a box around them all
+        assert_matches_golden(
+            "selection_single",
+            &canvas(elements(), &[ElementId::new(4)]),
+        );
+        assert_matches_golden(
+            "selection_multiple",
+            &canvas(elements(), &[ElementId::new(1), ElementId::new(4)]),
+        );
+    }
+}
@@ -0,0 +1,350 @@
+//! Printing the exported area on paper
+//!
+//! The page is rasterized at the printer's resolution with the export
+//! rasterizer, wrapped in a one-page PDF of the paper's exact size, and handed
+//! to the system's print command. Previews are the same page at a low resolution.
+
+use super::raster::composite;
+use super::{ExportError, ExportSettings, MAX_EXPORT_SIDE, export_bounds, rasterize_document};
+use crate::state::EditorModel;
+use crate::units::MM_PER_INCH;
+use egui::{Color32, ColorImage, Context, Rect, Vec2};
+
+/// PDF user space units per inch
+const POINTS_PER_INCH: f32 = 72.0;
+
+/// Paper sizes the print dialog offers
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum PaperSize {
+    A4,
+    A3,
+    Letter,
+    Legal,
+}
+
+impl PaperSize {
+    pub const ALL: [PaperSize; 4] = [
+        PaperSize::A4,
+        PaperSize::A3,
+        PaperSize::Letter,
+        PaperSize::Legal,
+    ];
+
+    pub fn label(self) -> &'static str {
+        match self {
+            PaperSize::A4 => "A4",
+            PaperSize::A3 => "A3",
+            PaperSize::Letter => "Letter",
+            PaperSize::Legal => "Legal",
+        }
+    }
+
+    /// Portrait width and height, in inches
+    pub fn size_in_inches(self) -> Vec2 {
+        match self {
+            PaperSize::A4 => Vec2::new(210.0, 297.0) / MM_PER_INCH,
+            PaperSize::A3 => Vec2::new(297.0, 420.0) / MM_PER_INCH,
+            PaperSize::Letter => Vec2::new(8.5, 11.0),
+            PaperSize::Legal => Vec2::new(8.5, 14.0),
+        }
+    }
+}
+
+/// How the exported area is sized on the page
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum PrintScaling {
+    /// As large as fits inside the page margins
+    FitToPage,
+    /// At the document's physical size (from its DPI); may not fit the page
+    ActualSize,
+}
+
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct PrintSettings {
+    pub paper: PaperSize,
+    pub scaling: PrintScaling,
+    /// Dots per inch the page is rasterized at
+    pub dpi: f32,
+    /// Blank border kept around the page, in millimeters
+    pub margin_mm: f32,
+}
+
+impl Default for PrintSettings {
+    fn default() -> Self {
+        Self {
+            paper: PaperSize::A4,
+            scaling: PrintScaling::FitToPage,
+            dpi: 300.0,
+            margin_mm: 10.0,
+        }
+    }
+}
+
+/// Where the exported area lands on the paper
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct PrintLayout {
+    /// Paper size in inches, turned to landscape for wide content
+    pub paper: Vec2,
+    /// The exported area on the paper, in inches from the top left corner
+    pub content: Rect,
+    /// Printed inches per document pixel
+    pub inches_per_px: f32,
+    /// Whether all of the content is inside the margins
+    pub fits: bool,
+}
+
+/// Lay out content `content_size` document pixels large, in a document with
+/// `document_dpi` pixels per inch, on the paper
+///
+/// The content is centered inside the margins.
+pub fn print_layout(
+    content_size: Vec2,
+    document_dpi: f32,
+    settings: &PrintSettings,
+) -> PrintLayout {
+    let portrait = settings.paper.size_in_inches();
+    let paper = if content_size.x > content_size.y {
+        Vec2::new(portrait.y, portrait.x)
+    } else {
+        portrait
+    };
+    let margin = settings.margin_mm / MM_PER_INCH;
+    let printable = (paper - Vec2::splat(2.0 * margin)).max(Vec2::ZERO);
+
+    let inches_per_px = match settings.scaling {
+        PrintScaling::FitToPage => (printable.x / content_size.x).min(printable.y / content_size.y),
+        PrintScaling::ActualSize => 1.0 / document_dpi,
+    };
+    let size = content_size * inches_per_px;
+    let content = Rect::from_center_size((paper / 2.0).to_pos2(), size);
+    PrintLayout {
+        paper,
+        content,
+        inches_per_px,
+        fits: size.x <= printable.x + 1e-3 && size.y <= printable.y + 1e-3,
+    }
+}
+
+/// Rasterize the page at `dpi`, on white paper
+///
+/// Content beyond the paper's edges is cut off.
+pub fn render_print_page(
+    editor_model: &EditorModel,
+    export_settings: &ExportSettings,
+    print_settings: &PrintSettings,
+    dpi: f32,
+    ctx: &Context,
+) -> Result<ColorImage, ExportError> {
+    let bounds = export_bounds(editor_model, export_settings)?;
+    let layout = print_layout(bounds.size(), editor_model.units.dpi, print_settings);
+
+    let width = (layout.paper.x * dpi).round() as usize;
+    let height = (layout.paper.y * dpi).round() as usize;
+    if width > MAX_EXPORT_SIDE || height > MAX_EXPORT_SIDE {
+        return Err(ExportError::TooLarge { width, height });
+    }
+    let mut page = ColorImage::new([width, height], Color32::WHITE);
+
+    let content = rasterize_document(
+        editor_model,
+        &ExportSettings {
+            scale: layout.inches_per_px * dpi,
+            ..export_settings.clone()
+        },
+        ctx,
+    )?;
+    let target = Rect::from_min_max(
+        (layout.content.min.to_vec2() * dpi).to_pos2(),
+        (layout.content.max.to_vec2() * dpi).to_pos2(),
+    );
+    composite(&mut page, &content, target, 1.0);
+    Ok(page)
+}
+
+/// A one-page PDF showing `page`, sized for it to print at `dpi`
+pub fn encode_print_pdf(page: &ColorImage, dpi: f32) -> Vec<u8> {
+    let [width, height] = page.size;
+    let mut rgb = Vec::with_capacity(width * height * 3);
+    for pixel in &page.pixels {
+        let [r, g, b, _] = pixel.to_srgba_unmultiplied();
+        rgb.extend_from_slice(&[r, g, b]);
+    }
+    let image_data = miniz_oxide::deflate::compress_to_vec_zlib(&rgb, 6);
+
+    let page_width = width as f32 / dpi * POINTS_PER_INCH;
+    let page_height = height as f32 / dpi * POINTS_PER_INCH;
+    let contents = format!(
+        "q {:.2} 0 0 {:.2} 0 0 cm /Page Do Q",
+        page_width, page_height
+    );
+
+    let mut pdf = b"%PDF-1.4\n".to_vec();
+    let mut offsets = Vec::new();
+    let mut object = |pdf: &mut Vec<u8>, header: String, stream: Option<&[u8]>| {
+        offsets.push(pdf.len());
+        pdf.extend_from_slice(format!("{} 0 obj\n{}\n", offsets.len(), header).as_bytes());
+        if let Some(stream) = stream {
+            pdf.extend_from_slice(b"stream\n");
+            pdf.extend_from_slice(stream);
+            pdf.extend_from_slice(b"\nendstream\n");
+        }
+        pdf.extend_from_slice(b"endobj\n");
+    };
+    object(&mut pdf, "<< /Type /Catalog /Pages 2 0 R >>".into(), None);
+    object(
+        &mut pdf,
+        "<< /Type /Pages /Kids [3 0 R] /Count 1 >>".into(),
+        None,
+    );
+    object(
+        &mut pdf,
+        format!(
+            "<< /Type /Page /Parent 2 0 R /MediaBox [0 0 {:.2} {:.2}] \
+             /Resources << /XObject << /Page 4 0 R >> >> /Contents 5 0 R >>",
+            page_width, page_height
+        ),
+        None,
+    );
+    object(
+        &mut pdf,
+        format!(
+            "<< /Type /XObject /Subtype /Image /Width {} /Height {} /ColorSpace /DeviceRGB \
+             /BitsPerComponent 8 /Filter /FlateDecode /Length {} >>",
+            width,
+            height,
+            image_data.len()
+        ),
+        Some(&image_data),
+    );
+    object(
+        &mut pdf,
+        format!("<< /Length {} >>", contents.len()),
+        Some(contents.as_bytes()),
+    );
+
+    let xref_offset = pdf.len();
+    pdf.extend_from_slice(
+        format!("xref\n0 {}\n0000000000 65535 f \n", offsets.len() + 1).as_bytes(),
+    );
+    for offset in &offsets {
+        pdf.extend_from_slice(format!("{:010} 00000 n \n", offset).as_bytes());
+    }
+    pdf.extend_from_slice(
+        format!(
+            "trailer\n<< /Size {} /Root 1 0 R >>\nstartxref\n{}\n%%EOF\n",
+            offsets.len() + 1,
+            xref_offset
+        )
+        .as_bytes(),
+    );
+    pdf
+}
+
+/// Print the page on the default printer
+///
+/// The page goes to `lp` (CUPS) on Linux and macOS, and to the PDF viewer's
+/// print verb on Windows.
+#[cfg(not(target_arch = "wasm32"))]
+pub fn send_to_printer(page: &ColorImage, settings: &PrintSettings) -> Result<(), ExportError> {
+    let path = std::env::temp_dir().join("eframe_paint_print.pdf");
+    std::fs::write(&path, encode_print_pdf(page, settings.dpi))?;
+    log::info!("Printing {}", path.display());
+
+    #[cfg(windows)]
+    let output = std::process::Command::new("powershell")
+        .arg("-NoProfile")
+        .arg("-Command")
+        .arg(format!(
+            "Start-Process -Verb Print -FilePath '{}'",
+            path.display()
+        ))
+        .output()?;
+    #[cfg(not(windows))]
+    let output = std::process::Command::new("lp")
+        .arg("-o")
+        .arg(format!("media={}", settings.paper.label()))
+        .arg(&path)
+        .output()?;
+
+    if output.status.success() {
+        Ok(())
+    } else {
+        Err(ExportError::Print(
+            String::from_utf8_lossy(&output.stderr).trim().to_string(),
+        ))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::artboard::Artboard;
+    use crate::element::{ElementId, factory};
+    use egui::Pos2;
+
+    #[test]
+    fn test_print_layout_fits_or_keeps_actual_size() {
+        let settings = PrintSettings {
+            paper: PaperSize::Letter,
+            margin_mm: MM_PER_INCH / 2.0,
+            ..PrintSettings::default()
+        };
+
+        // 96 px per inch: a 960 × 480 px artboard is 10 × 5 inches, so landscape
+        let layout = print_layout(Vec2::new(960.0, 480.0), 96.0, &settings);
+        assert_eq!(layout.paper, Vec2::new(11.0, 8.5));
+        assert_eq!(layout.content.size(), Vec2::new(10.0, 5.0));
+        assert_eq!(layout.content.center(), Pos2::new(5.5, 4.25));
+        assert!(layout.fits);
+
+        let actual = PrintSettings {
+            scaling: PrintScaling::ActualSize,
+            ..settings
+        };
+        let layout = print_layout(Vec2::new(96.0, 192.0), 96.0, &actual);
+        assert_eq!(layout.content.size(), Vec2::new(1.0, 2.0));
+        assert!(layout.fits);
+        assert!(!print_layout(Vec2::new(96.0, 1920.0), 96.0, &actual).fits);
+    }
+
+    #[test]
+    fn test_print_page_is_rasterized_onto_paper_and_wrapped_in_a_pdf() {
+        let mut model = EditorModel::new();
+        model
+            .add_element(factory::create_stroke(
+                ElementId::new(1),
+                vec![Pos2::new(0.0, 50.0), Pos2::new(100.0, 50.0)],
+                10.0,
+                Color32::BLACK,
+            ))
+            .unwrap();
+        model.artboards = vec![Artboard::new(
+            "Page",
+            Rect::from_min_size(Pos2::ZERO, Vec2::splat(100.0)),
+        )];
+        let settings = PrintSettings::default();
+        let ctx = Context::default();
+
+        let page =
+            render_print_page(&model, &ExportSettings::default(), &settings, 20.0, &ctx).unwrap();
+        let a4 = PaperSize::A4.size_in_inches() * 20.0;
+        assert_eq!(page.size, [a4.x.round() as usize, a4.y.round() as usize]);
+        assert_eq!(page.pixels[0], Color32::WHITE);
+        let [width, height] = page.size;
+        assert_eq!(page.pixels[height / 2 * width + width / 2], Color32::BLACK);
+
+        let pdf = encode_print_pdf(&page, 20.0);
+        assert!(pdf.starts_with(b"%PDF-1.4"));
+        assert!(String::from_utf8_lossy(&pdf).contains("/MediaBox [0 0 594.00 842.40]"));
+        // The cross-reference table points at each object
+        let xref = pdf
+            .windows(5)
+            .rposition(|bytes| bytes == b"xref\n")
+            .unwrap();
+        let table = std::str::from_utf8(&pdf[xref..]).unwrap();
+        for (index, line) in table.lines().skip(3).take(5).enumerate() {
+            let offset: usize = line[..10].parse().unwrap();
+            assert!(pdf[offset..].starts_with(format!("{} 0 obj", index + 1).as_bytes()));
+        }
+    }
+}
@@ -0,0 +1,343 @@
+use super::{ExportError, ExportSettings, MAX_EXPORT_SIDE, watermark};
+use crate::artboard::Artboard;
+use crate::element::{Element, ElementType, Obb};
+use crate::reference_image::ReferenceImage;
+use crate::state::EditorModel;
+use crate::texture_manager::TextureGenerationError;
+use egui::{Color32, ColorImage, Context, Rect};
+
+/// Get the union of all element rectangles, turned ones by the box around
+/// them, or None for an empty document
+pub fn document_bounds(editor_model: &EditorModel) -> Option<Rect> {
+    editor_model
+        .elements
+        .iter()
+        .map(|element| element.obb().bounding_rect())
+        .reduce(|acc, rect| acc.union(rect))
+}
+
+/// Rasterize all elements (in document order) into a single image
+pub fn rasterize_document(
+    editor_model: &EditorModel,
+    settings: &ExportSettings,
+    ctx: &Context,
+) -> Result<ColorImage, ExportError> {
+    let bounds = export_bounds(editor_model, settings)?;
+    let elements = exported_elements(editor_model, settings);
+    render_elements(editor_model, &elements, bounds, settings, ctx)
+}
+
+/// Rasterize the selected elements (in document order) into an image cropped
+/// to them, plus the export margin
+pub fn rasterize_selection(
+    editor_model: &EditorModel,
+    settings: &ExportSettings,
+    ctx: &Context,
+) -> Result<ColorImage, ExportError> {
+    let selected_ids = editor_model.selected_ids();
+    let elements: Vec<&ElementType> = editor_model
+        .elements
+        .iter()
+        .filter(|element| selected_ids.contains(&element.id()))
+        .collect();
+    let bounds = elements
+        .iter()
+        .map(|element| element.obb().bounding_rect())
+        .reduce(|acc, rect| acc.union(rect))
+        .ok_or(ExportError::EmptySelection)?
+        .expand(settings.margin);
+    render_elements(editor_model, &elements, bounds, settings, ctx)
+}
+
+/// Draw the reference image if the settings ask for it, then `elements`, then
+/// the watermark, into an image covering `bounds`
+pub(crate) fn render_elements(
+    editor_model: &EditorModel,
+    elements: &[&ElementType],
+    bounds: Rect,
+    settings: &ExportSettings,
+    ctx: &Context,
+) -> Result<ColorImage, ExportError> {
+    let mut image = blank_canvas(bounds, settings)?;
+    if let Some(reference) = editor_model
+        .reference_image
+        .as_ref()
+        .filter(|_| settings.include_reference_image)
+    {
+        draw_reference_image(&mut image, reference, bounds, settings.scale)?;
+    }
+
+    for element in elements {
+        if settings.privacy_scrub && element.metadata().is_redaction() {
+            continue;
+        }
+        draw_element(&mut image, element, bounds, settings, ctx)?;
+    }
+    if settings.privacy_scrub {
+        burn_in_redactions(&mut image, elements, bounds, settings.scale, ctx)?;
+    }
+
+    if let Some(watermark) = &settings.watermark {
+        watermark::apply_watermark(&mut image, watermark, settings.scale, ctx)?;
+    }
+
+    Ok(image)
+}
+
+/// The artboard an export covers: the one chosen in the settings, else the active one
+///
+/// None when the document has no artboards.
+pub fn export_artboard<'a>(
+    editor_model: &'a EditorModel,
+    settings: &ExportSettings,
+) -> Option<&'a Artboard> {
+    settings
+        .artboard_id
+        .and_then(|artboard_id| editor_model.find_artboard(artboard_id))
+        .or_else(|| editor_model.active_artboard())
+}
+
+/// Elements included in an export, in document order
+///
+/// With artboards, only the elements on the exported artboard are included.
+pub(crate) fn exported_elements<'a>(
+    editor_model: &'a EditorModel,
+    settings: &ExportSettings,
+) -> Vec<&'a ElementType> {
+    match export_artboard(editor_model, settings) {
+        Some(artboard) => {
+            let element_ids = editor_model.artboard_element_ids(artboard.id);
+            editor_model
+                .elements
+                .iter()
+                .filter(|element| element_ids.contains(&element.id()))
+                .collect()
+        }
+        None => editor_model.elements.iter().collect(),
+    }
+}
+
+/// Area to export, in document coordinates
+///
+/// This is the exported artboard when the document has any, otherwise the
+/// content bounds plus the export margin.
+pub fn export_bounds(
+    editor_model: &EditorModel,
+    settings: &ExportSettings,
+) -> Result<Rect, ExportError> {
+    if let Some(artboard) = export_artboard(editor_model, settings) {
+        return Ok(artboard.rect);
+    }
+
+    Ok(document_bounds(editor_model)
+        .ok_or(ExportError::EmptyDocument)?
+        .expand(settings.margin))
+}
+
+/// Create the background image for exporting `bounds` at the settings' scale
+pub(crate) fn blank_canvas(
+    bounds: Rect,
+    settings: &ExportSettings,
+) -> Result<ColorImage, ExportError> {
+    let scale = settings.scale;
+    let width = (bounds.width() * scale).ceil().max(1.0) as usize;
+    let height = (bounds.height() * scale).ceil().max(1.0) as usize;
+    if width > MAX_EXPORT_SIDE || height > MAX_EXPORT_SIDE {
+        return Err(ExportError::TooLarge { width, height });
+    }
+
+    Ok(ColorImage::new([width, height], settings.background()))
+}
+
+/// Composite one element into an image covering `bounds`
+///
+/// Images are resampled straight to the size they're exported at, with the
+/// settings' resampling.
+pub(crate) fn draw_element(
+    image: &mut ColorImage,
+    element: &ElementType,
+    bounds: Rect,
+    settings: &ExportSettings,
+    ctx: &Context,
+) -> Result<(), ExportError> {
+    let target = texture_target(element, bounds, settings.scale);
+    let texture = match element {
+        ElementType::Image(picture) => {
+            let size = target.rect.size().round().max(egui::Vec2::splat(1.0));
+            let size = [size.x as u32, size.y as u32];
+            let pixels = picture
+                .resampled(size, settings.resampling)
+                .ok_or(TextureGenerationError::GenerationFailed)?;
+            ColorImage::from_rgba_premultiplied(
+                [size[0] as usize, size[1] as usize],
+                pixels.as_raw(),
+            )
+        }
+        // Generate the texture on a copy so export never touches the document's texture state
+        _ => element.clone().generate_texture(ctx)?,
+    };
+    composite_turned(image, &texture, target, 1.0);
+    Ok(())
+}
+
+/// Where the element's texture goes in an image covering `bounds`, turned
+/// with the element
+fn texture_target(element: &ElementType, bounds: Rect, scale: f32) -> Obb {
+    // The texture turns about the element's center, which needn't be its own
+    let obb = element.obb();
+    let rect = element.texture_rect();
+    let rect = Rect::from_center_size(obb.to_world(rect.center()), rect.size());
+    let target = Rect::from_min_max(
+        ((rect.min - bounds.min) * scale).to_pos2(),
+        ((rect.max - bounds.min) * scale).to_pos2(),
+    );
+    Obb::new(target, obb.rotation)
+}
+
+/// Composite the reference image, tiled if it's set to, into an image covering `bounds`
+fn draw_reference_image(
+    image: &mut ColorImage,
+    reference: &ReferenceImage,
+    bounds: Rect,
+    scale: f32,
+) -> Result<(), ExportError> {
+    let decoded = reference.decode().map_err(ExportError::ReferenceImage)?;
+    for tile in reference.tiles(bounds) {
+        let target = Rect::from_min_max(
+            ((tile.min - bounds.min) * scale).to_pos2(),
+            ((tile.max - bounds.min) * scale).to_pos2(),
+        );
+        composite(image, &decoded, target, reference.opacity);
+    }
+    Ok(())
+}
+
+/// Paint every redaction among `elements` fully opaque on top of `image`
+///
+/// Used by privacy scrub exports: translucent redactions become solid, and
+/// whatever was stacked above a redaction is covered too, so nothing under it
+/// survives in the exported pixels.
+pub(crate) fn burn_in_redactions(
+    image: &mut ColorImage,
+    elements: &[&ElementType],
+    bounds: Rect,
+    scale: f32,
+    ctx: &Context,
+) -> Result<(), ExportError> {
+    for element in elements {
+        if !element.metadata().is_redaction() {
+            continue;
+        }
+        let mut element = (*element).clone();
+        let mut texture = element.generate_texture(ctx)?;
+        for pixel in &mut texture.pixels {
+            if pixel.a() > 0 {
+                let [r, g, b, _] = pixel.to_srgba_unmultiplied();
+                *pixel = Color32::from_rgb(r, g, b);
+            }
+        }
+
+        composite_turned(
+            image,
+            &texture,
+            texture_target(&element, bounds, scale),
+            1.0,
+        );
+    }
+    Ok(())
+}
+
+/// Draw `src` over `dst`, stretched into `target` (in destination pixels)
+///
+/// Both images hold premultiplied colors. Sampling is nearest-neighbour.
+pub(crate) fn composite(dst: &mut ColorImage, src: &ColorImage, target: Rect, opacity: f32) {
+    let [src_width, src_height] = src.size;
+    let [dst_width, dst_height] = dst.size;
+    if src_width == 0 || src_height == 0 || target.width() <= 0.0 || target.height() <= 0.0 {
+        return;
+    }
+
+    let x_start = target.min.x.floor().max(0.0) as usize;
+    let y_start = target.min.y.floor().max(0.0) as usize;
+    let x_end = (target.max.x.ceil().max(0.0) as usize).min(dst_width);
+    let y_end = (target.max.y.ceil().max(0.0) as usize).min(dst_height);
+
+    for y in y_start..y_end {
+        let v = (y as f32 + 0.5 - target.min.y) / target.height();
+        if !(0.0..1.0).contains(&v) {
+            continue;
+        }
+        let src_y = (v * src_height as f32) as usize;
+
+        for x in x_start..x_end {
+            let u = (x as f32 + 0.5 - target.min.x) / target.width();
+            if !(0.0..1.0).contains(&u) {
+                continue;
+            }
+            let src_x = (u * src_width as f32) as usize;
+
+            let mut color = src.pixels[src_y * src_width + src_x];
+            if opacity < 1.0 {
+                color = color.gamma_multiply(opacity);
+            }
+
+            let index = y * dst_width + x;
+            dst.pixels[index] = blend_over(dst.pixels[index], color);
+        }
+    }
+}
+
+/// Draw `src` over `dst`, stretched into `target` and turned with it, as
+/// [`composite`] does
+pub(crate) fn composite_turned(dst: &mut ColorImage, src: &ColorImage, target: Obb, opacity: f32) {
+    if target.rotation == 0.0 {
+        composite(dst, src, target.rect, opacity);
+        return;
+    }
+    let [src_width, src_height] = src.size;
+    let [dst_width, dst_height] = dst.size;
+    let rect = target.rect;
+    if src_width == 0 || src_height == 0 || rect.width() <= 0.0 || rect.height() <= 0.0 {
+        return;
+    }
+
+    let bounds = target.bounding_rect();
+    let x_start = bounds.min.x.floor().max(0.0) as usize;
+    let y_start = bounds.min.y.floor().max(0.0) as usize;
+    let x_end = (bounds.max.x.ceil().max(0.0) as usize).min(dst_width);
+    let y_end = (bounds.max.y.ceil().max(0.0) as usize).min(dst_height);
+
+    for y in y_start..y_end {
+        for x in x_start..x_end {
+            let local = target.to_local(egui::pos2(x as f32 + 0.5, y as f32 + 0.5));
+            let u = (local.x - rect.min.x) / rect.width();
+            let v = (local.y - rect.min.y) / rect.height();
+            if !(0.0..1.0).contains(&u) || !(0.0..1.0).contains(&v) {
+                continue;
+            }
+            let src_x = (u * src_width as f32) as usize;
+            let src_y = (v * src_height as f32) as usize;
+
+            let mut color = src.pixels[src_y * src_width + src_x];
+            if opacity < 1.0 {
+                color = color.gamma_multiply(opacity);
+            }
+
+            let index = y * dst_width + x;
+            dst.pixels[index] = blend_over(dst.pixels[index], color);
+        }
+    }
+}
+
+/// Porter-Duff "over" for premultiplied colors
+pub(crate) fn blend_over(dst: Color32, src: Color32) -> Color32 {
+    let inverse_alpha = 255 - src.a() as u32;
+    let channel = |s: u8, d: u8| (s as u32 + (d as u32 * inverse_alpha + 127) / 255).min(255) as u8;
+
+    Color32::from_rgba_premultiplied(
+        channel(src.r(), dst.r()),
+        channel(src.g(), dst.g()),
+        channel(src.b(), dst.b()),
+        channel(src.a(), dst.a()),
+    )
+}
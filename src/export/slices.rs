@@ -0,0 +1,66 @@
+//! Batch export of the document's slices, each at one or more scales
+
+use super::{ExportError, ExportSettings, encode_png, raster};
+use crate::element::{Element, ElementType};
+use crate::export_slice::ExportSlice;
+use crate::state::EditorModel;
+use egui::{ColorImage, Context, Rect};
+
+/// Rasterize exactly the part of the document under `rect`
+///
+/// Artboards are ignored: everything overlapping the rectangle is drawn.
+pub fn rasterize_region(
+    editor_model: &EditorModel,
+    rect: Rect,
+    settings: &ExportSettings,
+    ctx: &Context,
+) -> Result<ColorImage, ExportError> {
+    let elements: Vec<&ElementType> = editor_model
+        .elements
+        .iter()
+        .filter(|element| element.rect().intersects(rect))
+        .collect();
+    raster::render_elements(editor_model, &elements, rect, settings, ctx)
+}
+
+/// Rasterize a slice at `scale` and encode it as PNG bytes
+pub fn export_slice_png(
+    editor_model: &EditorModel,
+    slice: &ExportSlice,
+    scale: f32,
+    settings: &ExportSettings,
+    ctx: &Context,
+) -> Result<Vec<u8>, ExportError> {
+    let settings = ExportSettings {
+        scale,
+        ..settings.clone()
+    };
+    let image = rasterize_region(editor_model, slice.rect, &settings, ctx)?;
+    encode_png(&image, &settings.embedded_metadata())
+}
+
+/// Write every slice of the document at each of `scales` into `folder`,
+/// returning the files written
+///
+/// Each scale comes with the suffix its files are named with.
+#[cfg(not(target_arch = "wasm32"))]
+pub fn export_slices_to_folder(
+    folder: &std::path::Path,
+    editor_model: &EditorModel,
+    scales: &[(f32, &str)],
+    settings: &ExportSettings,
+    ctx: &Context,
+) -> Result<Vec<std::path::PathBuf>, ExportError> {
+    std::fs::create_dir_all(folder)?;
+    let mut written = Vec::new();
+    for slice in &editor_model.slices {
+        for &(scale, suffix) in scales {
+            let bytes = export_slice_png(editor_model, slice, scale, settings, ctx)?;
+            let path = folder.join(slice.file_name(suffix));
+            std::fs::write(&path, bytes)?;
+            written.push(path);
+        }
+    }
+    log::info!("Exported {} slice files to {}", written.len(), folder.display());
+    Ok(written)
+}
@@ -0,0 +1,102 @@
+use super::svg::escape_xml;
+use super::{ExportError, ExportSettings, encode_png, rasterize_document};
+use crate::state::EditorModel;
+use base64::Engine as _;
+use egui::{ColorImage, Context};
+use std::fmt::Write as _;
+
+/// Build a self-contained HTML slideshow, one slide per image
+///
+/// Slides are embedded as PNG data URIs; arrow keys, space and the on-screen
+/// buttons step through them.
+pub fn slideshow_html(
+    title: &str,
+    slides: &[ColorImage],
+    settings: &ExportSettings,
+) -> Result<String, ExportError> {
+    if slides.is_empty() {
+        return Err(ExportError::EmptyDocument);
+    }
+
+    let title = escape_xml(title);
+    let mut html = String::new();
+    // Writing to a String cannot fail, so the fmt results are ignored throughout
+    let _ = writeln!(html, "<!DOCTYPE html>");
+    let _ = writeln!(html, "<html>\n<head>\n<meta charset=\"utf-8\">");
+    let _ = writeln!(html, "<title>{title}</title>");
+    let _ = writeln!(html, "<style>{STYLE}</style>\n</head>\n<body>");
+
+    for (index, slide) in slides.iter().enumerate() {
+        let png = encode_png(slide, &settings.embedded_metadata())?;
+        let _ = writeln!(
+            html,
+            r#"<section class="slide"{hidden}><img alt="{title} {number}" src="data:image/png;base64,{data}"></section>"#,
+            hidden = if index == 0 { "" } else { " hidden" },
+            number = index + 1,
+            data = base64::engine::general_purpose::STANDARD.encode(png),
+        );
+    }
+
+    let _ = writeln!(
+        html,
+        r#"<nav><button id="prev">&larr;</button><span id="counter">1 / {count}</span><button id="next">&rarr;</button></nav>"#,
+        count = slides.len(),
+    );
+    let _ = writeln!(html, "<script>{SCRIPT}</script>\n</body>\n</html>");
+
+    Ok(html)
+}
+
+/// Export the document as an HTML slideshow
+///
+/// Each artboard becomes a slide; a document without artboards is a single slide.
+pub fn export_slideshow(
+    editor_model: &EditorModel,
+    settings: &ExportSettings,
+    ctx: &Context,
+) -> Result<String, ExportError> {
+    // One slide per artboard, or a single slide of the whole drawing
+    let pages = if editor_model.artboards.is_empty() {
+        vec![rasterize_document(editor_model, settings, ctx)?]
+    } else {
+        editor_model
+            .artboards
+            .iter()
+            .map(|artboard| {
+                let settings = ExportSettings {
+                    artboard_id: Some(artboard.id),
+                    ..settings.clone()
+                };
+                rasterize_document(editor_model, &settings, ctx)
+            })
+            .collect::<Result<Vec<_>, _>>()?
+    };
+    let metadata = settings.embedded_metadata();
+    let title = if metadata.title.is_empty() {
+        "Slideshow"
+    } else {
+        metadata.title.as_str()
+    };
+    slideshow_html(title, &pages, settings)
+}
+
+const STYLE: &str = "\
+body { margin: 0; background: #222; color: #eee; font-family: sans-serif; }
+.slide { display: flex; align-items: center; justify-content: center; height: calc(100vh - 3em); }
+.slide img { max-width: 100%; max-height: 100%; background: white; }
+nav { display: flex; gap: 1em; align-items: center; justify-content: center; height: 3em; }";
+
+const SCRIPT: &str = "\
+const slides = document.querySelectorAll('.slide');
+let current = 0;
+function show(index) {
+  current = Math.max(0, Math.min(slides.length - 1, index));
+  slides.forEach((slide, i) => slide.hidden = i !== current);
+  document.getElementById('counter').textContent = (current + 1) + ' / ' + slides.length;
+}
+document.getElementById('prev').onclick = () => show(current - 1);
+document.getElementById('next').onclick = () => show(current + 1);
+document.addEventListener('keydown', (e) => {
+  if (e.key === 'ArrowRight' || e.key === ' ') show(current + 1);
+  if (e.key === 'ArrowLeft') show(current - 1);
+});";
@@ -0,0 +1,386 @@
+use super::raster::exported_elements;
+use super::{
+    ExportError, ExportMetadata, ExportSettings, encode_png, export_bounds, rasterize_document,
+};
+use crate::arrowhead::{Arrowhead, ArrowheadShape, with_arrowheads};
+use crate::element::sticky_note::{CALLOUT_OUTLINE_WIDTH, StickyNote};
+use crate::element::{Element, ElementType, LineCap, LineJoin, NOTE_TEXT_COLOR, StrokeStyle};
+use crate::state::{EditorModel, ElementId};
+use base64::Engine as _;
+use egui::{Color32, Context, Pos2};
+use std::fmt::Write as _;
+
+/// Serialize the document as an SVG string
+///
+/// Strokes become polylines, paths keep their curves, step badges become a
+/// circle with polyline digits, sticky notes a rounded rectangle with a text
+/// element per wrapped row, and images are embedded as data URIs. Watermarks
+/// and stroke tapers are raster-only and are not included.
+///
+/// Privacy scrub exports contain only the scrubbed raster image, since vector
+/// output would keep whatever lies under a redaction.
+pub fn export_svg(
+    editor_model: &EditorModel,
+    settings: &ExportSettings,
+    ctx: &Context,
+) -> Result<String, ExportError> {
+    let bounds = export_bounds(editor_model, settings)?;
+
+    let mut svg = String::new();
+    // Writing to a String cannot fail, so the fmt results are ignored throughout
+    let _ = writeln!(svg, r#"<?xml version="1.0" encoding="UTF-8"?>"#);
+    let _ = writeln!(
+        svg,
+        r#"<svg xmlns="http://www.w3.org/2000/svg" xmlns:rdf="http://www.w3.org/1999/02/22-rdf-syntax-ns#" xmlns:dc="http://purl.org/dc/elements/1.1/" width="{w}" height="{h}" viewBox="{x} {y} {vw} {vh}">"#,
+        w = bounds.width() * settings.scale,
+        h = bounds.height() * settings.scale,
+        x = bounds.min.x,
+        y = bounds.min.y,
+        vw = bounds.width(),
+        vh = bounds.height(),
+    );
+
+    if settings.privacy_scrub {
+        let image = rasterize_document(editor_model, settings, ctx)?;
+        let png = encode_png(&image, &settings.embedded_metadata())?;
+        let _ = writeln!(
+            svg,
+            r#"  <image x="{x}" y="{y}" width="{w}" height="{h}" preserveAspectRatio="none" href="data:image/png;base64,{data}"/>"#,
+            x = bounds.min.x,
+            y = bounds.min.y,
+            w = bounds.width(),
+            h = bounds.height(),
+            data = base64::engine::general_purpose::STANDARD.encode(png),
+        );
+        let _ = writeln!(svg, "</svg>");
+        return Ok(svg);
+    }
+
+    write_metadata(&mut svg, &settings.metadata);
+
+    let background = settings.background();
+    if background.a() > 0 {
+        let _ = writeln!(
+            svg,
+            r#"  <rect x="{x}" y="{y}" width="{w}" height="{h}" fill="{color}" fill-opacity="{opacity}"/>"#,
+            x = bounds.min.x,
+            y = bounds.min.y,
+            w = bounds.width(),
+            h = bounds.height(),
+            color = hex_color(background),
+            opacity = background.a() as f32 / 255.0,
+        );
+    }
+
+    for element in exported_elements(editor_model, settings) {
+        let rotation = element.rotation();
+        if rotation == 0.0 {
+            write_element(&mut svg, element, ctx);
+            continue;
+        }
+        // SVG turns clockwise for positive angles too, as its y axis also points down
+        let center = element.rect().center();
+        let _ = writeln!(
+            svg,
+            r#"  <g transform="rotate({angle} {x} {y})">"#,
+            angle = rotation.to_degrees(),
+            x = center.x,
+            y = center.y,
+        );
+        write_element(&mut svg, element, ctx);
+        let _ = writeln!(svg, "  </g>");
+    }
+
+    let _ = writeln!(svg, "</svg>");
+    Ok(svg)
+}
+
+/// Write the SVG for one element, as placed but unrotated
+fn write_element(svg: &mut String, element: &ElementType, ctx: &Context) {
+    match element {
+        ElementType::Stroke(stroke) => {
+            if stroke.points().len() < 2 {
+                return;
+            }
+            write_polyline(
+                svg,
+                stroke.id(),
+                stroke.points(),
+                stroke.thickness(),
+                stroke.color(),
+                &stroke.style(),
+            );
+        }
+        ElementType::Shape(shape) => {
+            if shape.points().len() < 3 {
+                return;
+            }
+            let points: Vec<String> = shape
+                .points()
+                .iter()
+                .map(|p| format!("{},{}", p.x, p.y))
+                .collect();
+            let (fill, opacity) = match shape.pattern() {
+                Some(pattern) => {
+                    // Tiles start at the shape's top-left corner, like in the app
+                    let origin = shape.rect().min + pattern.offset;
+                    let tile = pattern.scaled_tile_size();
+                    let mime = ::image::guess_format(&pattern.image_data)
+                        .map(|format| format.to_mime_type())
+                        .unwrap_or("application/octet-stream");
+                    let _ = writeln!(
+                        svg,
+                        r#"  <defs><pattern id="pattern-{id}" patternUnits="userSpaceOnUse" x="{x}" y="{y}" width="{w}" height="{h}"><image width="{w}" height="{h}" preserveAspectRatio="none" href="data:{mime};base64,{data}"/></pattern></defs>"#,
+                        id = shape.id(),
+                        x = origin.x,
+                        y = origin.y,
+                        w = tile.x,
+                        h = tile.y,
+                        data =
+                            base64::engine::general_purpose::STANDARD.encode(&pattern.image_data),
+                    );
+                    (format!("url(#pattern-{})", shape.id()), 1.0)
+                }
+                None => (hex_color(shape.fill()), shape.fill().a() as f32 / 255.0),
+            };
+            let _ = writeln!(
+                svg,
+                r#"  <polygon id="element-{id}" points="{points}" fill="{fill}" fill-opacity="{opacity}" fill-rule="nonzero"/>"#,
+                id = shape.id(),
+                points = points.join(" "),
+            );
+        }
+        ElementType::Path(path) => {
+            let Some(first) = path.nodes().first() else {
+                return;
+            };
+            // Heads need the line cut short, which is done on its flattened points
+            let style = path.style();
+            if (style.start_arrow, style.end_arrow) != (Arrowhead::None, Arrowhead::None) {
+                let points = path.flatten();
+                write_polyline(
+                    svg,
+                    path.id(),
+                    &points,
+                    path.thickness(),
+                    path.color(),
+                    &style,
+                );
+                return;
+            }
+            let mut data = format!("M {},{}", first.anchor.x, first.anchor.y);
+            let nodes = path.nodes();
+            let closing = path.closed().then(|| (nodes.len() - 1, 0));
+            for (from, to) in (1..nodes.len()).map(|i| (i - 1, i)).chain(closing) {
+                let (from, to) = (&nodes[from], &nodes[to]);
+                let _ = write!(
+                    data,
+                    " C {},{} {},{} {},{}",
+                    from.handle_out.x,
+                    from.handle_out.y,
+                    to.handle_in.x,
+                    to.handle_in.y,
+                    to.anchor.x,
+                    to.anchor.y
+                );
+            }
+            if path.closed() {
+                data.push_str(" Z");
+            }
+            let _ = writeln!(
+                svg,
+                r#"  <path id="element-{id}" d="{data}" fill="none" stroke="{color}" stroke-opacity="{opacity}" stroke-width="{width}"{line}/>"#,
+                id = path.id(),
+                color = hex_color(path.color()),
+                opacity = path.color().a() as f32 / 255.0,
+                width = path.thickness(),
+                line = line_attributes(&path.style(), path.thickness()),
+            );
+        }
+        ElementType::StepBadge(badge) => {
+            let _ = writeln!(svg, r#"  <g id="element-{}">"#, badge.id());
+            let _ = writeln!(
+                svg,
+                r#"    <circle cx="{x}" cy="{y}" r="{r}" fill="{color}" fill-opacity="{opacity}"/>"#,
+                x = badge.center().x,
+                y = badge.center().y,
+                r = badge.radius(),
+                color = hex_color(badge.color()),
+                opacity = badge.color().a() as f32 / 255.0,
+            );
+            for line in badge.digit_lines() {
+                let points: Vec<String> = line.iter().map(|p| format!("{},{}", p.x, p.y)).collect();
+                let _ = writeln!(
+                    svg,
+                    r##"    <polyline points="{points}" fill="none" stroke="#ffffff" stroke-width="{width}" stroke-linecap="round" stroke-linejoin="round"/>"##,
+                    points = points.join(" "),
+                    width = badge.digit_thickness(),
+                );
+            }
+            let _ = writeln!(svg, "  </g>");
+        }
+        ElementType::StickyNote(note) => {
+            let rect = note.body();
+            let _ = writeln!(svg, r#"  <g id="element-{}">"#, note.id());
+            if let Some(outline) = note.outline(note.rect()) {
+                let points: Vec<String> =
+                    outline.iter().map(|p| format!("{},{}", p.x, p.y)).collect();
+                let _ = writeln!(
+                    svg,
+                    r#"    <polygon points="{points}" fill="none" stroke="{color}" stroke-opacity="{opacity}" stroke-width="{width}" stroke-linejoin="round"/>"#,
+                    points = points.join(" "),
+                    color = hex_color(note.color()),
+                    opacity = note.color().a() as f32 / 255.0,
+                    width = CALLOUT_OUTLINE_WIDTH,
+                );
+            } else {
+                let _ = writeln!(
+                    svg,
+                    r#"    <rect x="{x}" y="{y}" width="{w}" height="{h}" rx="{r}" fill="{color}" fill-opacity="{opacity}"/>"#,
+                    x = rect.min.x,
+                    y = rect.min.y,
+                    w = rect.width(),
+                    h = rect.height(),
+                    r = StickyNote::corner_radius(rect),
+                    color = hex_color(note.color()),
+                    opacity = note.color().a() as f32 / 255.0,
+                );
+            }
+            // One text element per wrapped row, as SVG text doesn't wrap
+            let (size, rows) = note.text_rows(ctx);
+            for (start, text) in rows {
+                let _ = writeln!(
+                    svg,
+                    r#"    <text x="{x}" y="{y}" font-family="sans-serif" font-size="{size}" fill="{color}" xml:space="preserve">{text}</text>"#,
+                    x = start.x,
+                    y = start.y,
+                    color = hex_color(NOTE_TEXT_COLOR),
+                    text = escape_xml(&text),
+                );
+            }
+            let _ = writeln!(svg, "  </g>");
+        }
+        ElementType::Image(image) => {
+            let mime = ::image::guess_format(image.original_data())
+                .map(|format| format.to_mime_type())
+                .unwrap_or("application/octet-stream");
+            let rect = image.rect();
+            let _ = writeln!(
+                svg,
+                r#"  <image id="element-{id}" x="{x}" y="{y}" width="{w}" height="{h}" preserveAspectRatio="none" href="data:{mime};base64,{data}"/>"#,
+                id = image.id(),
+                x = rect.min.x,
+                y = rect.min.y,
+                w = rect.width(),
+                h = rect.height(),
+                data = base64::engine::general_purpose::STANDARD.encode(image.original_data()),
+            );
+        }
+    }
+}
+
+/// Write `<title>` and a Dublin Core `<metadata>` block
+fn write_metadata(svg: &mut String, metadata: &ExportMetadata) {
+    let entries = metadata.entries();
+    if entries.is_empty() {
+        return;
+    }
+
+    if let Some((_, title)) = entries.iter().find(|(key, _)| *key == "Title") {
+        let _ = writeln!(svg, "  <title>{}</title>", escape_xml(title));
+    }
+
+    if let Some((_, software)) = entries.iter().find(|(key, _)| *key == "Software") {
+        let _ = writeln!(svg, "  <!-- Generator: {} -->", escape_xml(software));
+    }
+
+    let _ = writeln!(svg, "  <metadata>");
+    let _ = writeln!(svg, "    <rdf:RDF>");
+    let _ = writeln!(svg, r#"      <rdf:Description rdf:about="">"#);
+    for (key, value) in &entries {
+        let tag = match *key {
+            "Title" => "dc:title",
+            "Author" => "dc:creator",
+            "Copyright" => "dc:rights",
+            _ => continue,
+        };
+        let _ = writeln!(svg, "        <{tag}>{}</{tag}>", escape_xml(value));
+    }
+    let _ = writeln!(svg, "      </rdf:Description>");
+    let _ = writeln!(svg, "    </rdf:RDF>");
+    let _ = writeln!(svg, "  </metadata>");
+}
+
+/// A line as a polyline, followed by its arrowheads
+fn write_polyline(
+    svg: &mut String,
+    id: ElementId,
+    points: &[Pos2],
+    thickness: f32,
+    color: Color32,
+    style: &StrokeStyle,
+) {
+    let (line, heads) = with_arrowheads(points, thickness, style.start_arrow, style.end_arrow);
+    let opacity = color.a() as f32 / 255.0;
+    let color = hex_color(color);
+    let _ = writeln!(
+        svg,
+        r#"  <polyline id="element-{id}" points="{points}" fill="none" stroke="{color}" stroke-opacity="{opacity}" stroke-width="{thickness}"{line}/>"#,
+        points = svg_points(&line),
+        line = line_attributes(style, thickness),
+    );
+    for head in heads {
+        let _ = match head {
+            ArrowheadShape::Polygon(corners) => writeln!(
+                svg,
+                r#"  <polygon points="{}" fill="{color}" fill-opacity="{opacity}"/>"#,
+                svg_points(&corners),
+            ),
+            ArrowheadShape::Circle { center, radius } => writeln!(
+                svg,
+                r#"  <circle cx="{}" cy="{}" r="{radius}" fill="{color}" fill-opacity="{opacity}"/>"#,
+                center.x, center.y,
+            ),
+        };
+    }
+}
+
+fn svg_points(points: &[Pos2]) -> String {
+    let points: Vec<String> = points.iter().map(|p| format!("{},{}", p.x, p.y)).collect();
+    points.join(" ")
+}
+
+/// Cap, join and dash attributes drawing a line in `style`
+fn line_attributes(style: &StrokeStyle, thickness: f32) -> String {
+    let cap = match style.cap {
+        LineCap::Round => "round",
+        LineCap::Flat => "butt",
+    };
+    let join = match style.join {
+        LineJoin::Round => "round",
+        LineJoin::Miter => "miter",
+        LineJoin::Bevel => "bevel",
+    };
+    let mut attributes = format!(r#" stroke-linecap="{cap}" stroke-linejoin="{join}""#);
+    let intervals = style.dash.intervals();
+    if !intervals.is_empty() {
+        let lengths: Vec<String> = intervals
+            .iter()
+            .map(|length| (length * thickness).to_string())
+            .collect();
+        let _ = write!(attributes, r#" stroke-dasharray="{}""#, lengths.join(" "));
+    }
+    attributes
+}
+
+fn hex_color(color: Color32) -> String {
+    let [r, g, b, _] = color.to_srgba_unmultiplied();
+    format!("#{:02x}{:02x}{:02x}", r, g, b)
+}
+
+pub(super) fn escape_xml(text: &str) -> String {
+    text.replace('&', "&amp;")
+        .replace('<', "&lt;")
+        .replace('>', "&gt;")
+        .replace('"', "&quot;")
+}
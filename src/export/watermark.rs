@@ -0,0 +1,158 @@
+use super::ExportError;
+use super::raster::composite;
+use crate::text::render_text;
+use egui::{Color32, ColorImage, Context, Pos2, Rect, Vec2};
+use serde::{Deserialize, Serialize};
+
+/// What the watermark shows
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
+pub enum WatermarkContent {
+    Text(String),
+    /// Encoded image file bytes (PNG, JPG, ...)
+    Image(Vec<u8>),
+}
+
+/// Where the watermark is placed on the exported image
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Serialize, Deserialize)]
+pub enum WatermarkAnchor {
+    TopLeft,
+    TopRight,
+    BottomLeft,
+    BottomRight,
+    Center,
+}
+
+impl WatermarkAnchor {
+    pub const ALL: [WatermarkAnchor; 5] = [
+        WatermarkAnchor::TopLeft,
+        WatermarkAnchor::TopRight,
+        WatermarkAnchor::BottomLeft,
+        WatermarkAnchor::BottomRight,
+        WatermarkAnchor::Center,
+    ];
+
+    pub fn label(&self) -> &'static str {
+        match self {
+            WatermarkAnchor::TopLeft => "Top left",
+            WatermarkAnchor::TopRight => "Top right",
+            WatermarkAnchor::BottomLeft => "Bottom left",
+            WatermarkAnchor::BottomRight => "Bottom right",
+            WatermarkAnchor::Center => "Center",
+        }
+    }
+
+    /// Position of the watermark's top-left corner inside `bounds`
+    fn place(&self, bounds: Rect, size: Vec2, margin: f32) -> Pos2 {
+        let left = bounds.min.x + margin;
+        let right = bounds.max.x - margin - size.x;
+        let top = bounds.min.y + margin;
+        let bottom = bounds.max.y - margin - size.y;
+
+        match self {
+            WatermarkAnchor::TopLeft => Pos2::new(left, top),
+            WatermarkAnchor::TopRight => Pos2::new(right, top),
+            WatermarkAnchor::BottomLeft => Pos2::new(left, bottom),
+            WatermarkAnchor::BottomRight => Pos2::new(right, bottom),
+            WatermarkAnchor::Center => bounds.center() - size / 2.0,
+        }
+    }
+}
+
+/// An attribution overlay composited only at export time
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
+pub struct Watermark {
+    pub content: WatermarkContent,
+    pub anchor: WatermarkAnchor,
+    /// 0.0 (invisible) to 1.0 (opaque)
+    pub opacity: f32,
+    /// Text height, or image width, in document units
+    pub size: f32,
+    /// Distance from the image edges, in document units
+    pub margin: f32,
+    /// Text color (ignored for image watermarks)
+    pub color: Color32,
+}
+
+impl Watermark {
+    /// Create a text watermark with default styling
+    pub fn text(text: impl Into<String>) -> Self {
+        Self {
+            content: WatermarkContent::Text(text.into()),
+            anchor: WatermarkAnchor::BottomRight,
+            opacity: 0.5,
+            size: 16.0,
+            margin: 8.0,
+            color: Color32::BLACK,
+        }
+    }
+
+    /// Create an image watermark from encoded image bytes
+    pub fn image(bytes: Vec<u8>) -> Self {
+        Self {
+            content: WatermarkContent::Image(bytes),
+            size: 64.0,
+            ..Self::text("")
+        }
+    }
+}
+
+/// Composite the watermark onto an exported image rasterized at `scale`
+pub(crate) fn apply_watermark(
+    image: &mut ColorImage,
+    watermark: &Watermark,
+    scale: f32,
+    ctx: &Context,
+) -> Result<(), ExportError> {
+    let overlay = match &watermark.content {
+        WatermarkContent::Text(text) if text.trim().is_empty() => return Ok(()),
+        WatermarkContent::Text(text) => render_text(
+            ctx,
+            text,
+            watermark.size * scale,
+            watermark.color,
+            f32::INFINITY,
+        ),
+        WatermarkContent::Image(bytes) => decode_image(bytes)?,
+    };
+
+    let [overlay_width, overlay_height] = overlay.size;
+    if overlay_width == 0 || overlay_height == 0 {
+        return Ok(());
+    }
+
+    // Text is rendered at its final pixel size; images are scaled to the requested width
+    let size = match &watermark.content {
+        WatermarkContent::Text(_) => Vec2::new(overlay_width as f32, overlay_height as f32),
+        WatermarkContent::Image(_) => {
+            let width = watermark.size * scale;
+            Vec2::new(width, width * overlay_height as f32 / overlay_width as f32)
+        }
+    };
+
+    let bounds = Rect::from_min_size(
+        Pos2::ZERO,
+        Vec2::new(image.size[0] as f32, image.size[1] as f32),
+    );
+    let min = watermark
+        .anchor
+        .place(bounds, size, watermark.margin * scale);
+    composite(
+        image,
+        &overlay,
+        Rect::from_min_size(min, size),
+        watermark.opacity.clamp(0.0, 1.0),
+    );
+
+    Ok(())
+}
+
+/// Decode encoded image bytes into a premultiplied color image
+fn decode_image(bytes: &[u8]) -> Result<ColorImage, ExportError> {
+    let decoded =
+        ::image::load_from_memory(bytes).map_err(|err| ExportError::Watermark(err.to_string()))?;
+    let rgba = decoded.to_rgba8();
+    Ok(ColorImage::from_rgba_unmultiplied(
+        [rgba.width() as usize, rgba.height() as usize],
+        rgba.as_raw(),
+    ))
+}
@@ -0,0 +1,77 @@
+//! Named export slices: rectangles of the canvas exported as images of their own
+//!
+//! Slices are saved with the document but never drawn into exports; they only
+//! mark out what a batch export writes, one PNG per slice and scale, named
+//! after the slice (`icon.png`, `icon@2x.png`, ...).
+
+use egui::Rect;
+use serde::{Deserialize, Serialize};
+
+/// Scales a batch export can write each slice at, with their file name suffixes
+pub const SLICE_SCALES: [(f32, &str); 2] = [(1.0, ""), (2.0, "@2x")];
+
+/// A named rectangle in document coordinates, exported on its own
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
+pub struct ExportSlice {
+    pub id: usize,
+    pub name: String,
+    pub rect: Rect,
+}
+
+impl ExportSlice {
+    pub fn new(name: impl Into<String>, rect: Rect) -> Self {
+        Self {
+            id: crate::id_generator::generate_id(),
+            name: name.into(),
+            rect,
+        }
+    }
+
+    /// File the slice is exported to at the scale with `suffix`, e.g. `hero-image@2x.png`
+    pub fn file_name(&self, suffix: &str) -> String {
+        let stem: String = self
+            .name
+            .trim()
+            .chars()
+            .map(|c| if c.is_alphanumeric() { c } else { '-' })
+            .collect();
+        let stem = if stem.is_empty() { "slice" } else { &stem };
+        format!("{}{}.png", stem.to_lowercase(), suffix)
+    }
+}
+
+/// Default name for a new slice: "Slice N" with the first unused N
+pub fn next_slice_name(slices: &[ExportSlice]) -> String {
+    (1..)
+        .map(|number| format!("Slice {}", number))
+        .find(|name| slices.iter().all(|slice| slice.name != *name))
+        .unwrap_or_default()
+}
+
+/// Which scales a batch export writes, and where
+#[derive(Clone, Debug, PartialEq)]
+pub struct SliceExportOptions {
+    pub folder: String,
+    /// Whether each of [`SLICE_SCALES`] is written
+    pub scales: [bool; SLICE_SCALES.len()],
+}
+
+impl SliceExportOptions {
+    /// The chosen scales, with their file name suffixes
+    pub fn chosen_scales(&self) -> Vec<(f32, &'static str)> {
+        SLICE_SCALES
+            .into_iter()
+            .zip(self.scales)
+            .filter_map(|(scale, chosen)| chosen.then_some(scale))
+            .collect()
+    }
+}
+
+impl Default for SliceExportOptions {
+    fn default() -> Self {
+        Self {
+            folder: "slices".to_string(),
+            scales: [true, true],
+        }
+    }
+}
@@ -1,4 +1,10 @@
 use crate::command::Command;
+#[cfg(target_arch = "wasm32")]
+use crate::document::DOCUMENT_EXTENSION;
+use crate::document::{DocumentError, DocumentFile};
+#[cfg(not(target_arch = "wasm32"))]
+use crate::encryption;
+use crate::state::EditorModel;
 use eframe::egui;
 // Element imports are handled in the rest of the code
 use image;
@@ -7,6 +13,14 @@ use log;
 pub struct FileHandler {
     dropped_files: Vec<egui::DroppedFile>,
     processed_files: Vec<String>,
+    // Link images dropped from disk to their files rather than embedding them
+    link_dropped_images: bool,
+    // The file being read after the user picked it in the browser
+    #[cfg(target_arch = "wasm32")]
+    picking: web::PickedSlot,
+    // The last document picked in the browser, kept to open again with a passphrase
+    #[cfg(target_arch = "wasm32")]
+    picked_document: Option<web::PickedFile>,
 }
 
 impl FileHandler {
@@ -14,9 +28,19 @@ impl FileHandler {
         Self {
             dropped_files: Vec::new(),
             processed_files: Vec::new(),
+            link_dropped_images: false,
+            #[cfg(target_arch = "wasm32")]
+            picking: Default::default(),
+            #[cfg(target_arch = "wasm32")]
+            picked_document: None,
         }
     }
 
+    /// Whether images dropped from disk are linked to their files rather than embedded
+    pub fn set_link_dropped_images(&mut self, link: bool) {
+        self.link_dropped_images = link;
+    }
+
     /// Process any newly dropped files from the UI context
     /// Returns true if any new files were processed
     pub fn check_for_dropped_files(&mut self, ctx: &egui::Context) -> bool {
@@ -38,6 +62,7 @@ impl FileHandler {
     pub fn process_dropped_files(
         &mut self,
         ctx: &egui::Context,
+        editor_model: &EditorModel,
         central_panel_rect: egui::Rect,
     ) -> Vec<Command> {
         let mut commands = Vec::new();
@@ -62,18 +87,29 @@ impl FileHandler {
                 continue;
             }
 
-            // Check if it's an image file
-            if self.is_image_file(file) {
-                // Process the image file
+            // SVGs become editable elements; other images are added as bitmaps
+            if crate::svg_import::is_svg(&file.mime, &file_name) {
                 if let Some(cmd) =
-                    self.process_image_file(file, file_name.clone(), central_panel_rect, ctx)
+                    self.process_svg_file(file, &file_name, editor_model, central_panel_rect, ctx)
                 {
+                    commands.push(cmd);
+                    self.processed_files.push(file_name);
+                }
+            } else if self.is_image_file(file) {
+                // Process the image file
+                if let Some(cmd) = self.process_image_file(
+                    file,
+                    file_name.clone(),
+                    editor_model,
+                    central_panel_rect,
+                    ctx,
+                ) {
                     commands.push(cmd);
                     // Add to processed files list
                     self.processed_files.push(file_name);
                 }
             } else {
-                log::warn!("Dropped file is not a supported type: {}", file_name);
+                log::warn!(file:% = file_name; "Dropped file is not a supported type");
             }
         }
 
@@ -85,15 +121,7 @@ impl FileHandler {
         if !file.mime.is_empty() {
             file.mime.starts_with("image/")
         } else if let Some(path) = &file.path {
-            if let Some(ext) = path.extension() {
-                let ext = ext.to_string_lossy().to_lowercase();
-                matches!(
-                    ext.as_str(),
-                    "png" | "jpg" | "jpeg" | "gif" | "webp" | "bmp"
-                )
-            } else {
-                false
-            }
+            is_image_path(path)
         } else {
             false
         }
@@ -104,26 +132,68 @@ impl FileHandler {
         &self,
         file: &egui::DroppedFile,
         file_name: String,
+        editor_model: &EditorModel,
         panel_rect: egui::Rect,
         ctx: &egui::Context,
     ) -> Option<Command> {
-        // Try to get image data
+        let bytes = self.dropped_file_bytes(file, &file_name)?;
+        let link = file
+            .path
+            .as_ref()
+            .filter(|_| self.link_dropped_images)
+            .map(|path| path.to_string_lossy().into_owned());
+        self.create_image_from_bytes(&bytes, link, editor_model, panel_rect, ctx)
+    }
+
+    /// Process an SVG file and return a command adding its paths, shapes and
+    /// images as elements, centered in the panel
+    fn process_svg_file(
+        &self,
+        file: &egui::DroppedFile,
+        file_name: &str,
+        editor_model: &EditorModel,
+        panel_rect: egui::Rect,
+        ctx: &egui::Context,
+    ) -> Option<Command> {
+        let bytes = self.dropped_file_bytes(file, file_name)?;
+        match crate::svg_import::import_svg(&bytes, editor_model, panel_rect.center()) {
+            Ok(command) => {
+                ctx.request_repaint();
+                Some(command)
+            }
+            Err(err) => {
+                log::error!(file:% = file_name, error:% = err; "Failed to import SVG");
+                None
+            }
+        }
+    }
+
+    /// The contents of a dropped file, from memory or, natively, from its path
+    fn dropped_file_bytes<'a>(
+        &self,
+        file: &'a egui::DroppedFile,
+        file_name: &str,
+    ) -> Option<std::borrow::Cow<'a, [u8]>> {
         if let Some(bytes) = &file.bytes {
             log::info!(
-                "Processing image from memory: {} ({} bytes)",
+                "Processing file from memory: {} ({} bytes)",
                 file_name,
                 bytes.len()
             );
-            self.create_image_from_bytes(bytes, panel_rect, ctx)
+            Some(std::borrow::Cow::Borrowed(&bytes[..]))
         } else if let Some(path) = &file.path {
             // For native platforms, we can load the file from the path
             #[cfg(not(target_arch = "wasm32"))]
             {
-                log::info!("Processing image from path: {}", path.display());
+                log::debug!(path:% = path.display(); "Processing file from path");
                 match std::fs::read(path) {
-                    Ok(bytes) => self.create_image_from_bytes(&bytes, panel_rect, ctx),
+                    Ok(bytes) => Some(std::borrow::Cow::Owned(bytes)),
                     Err(err) => {
-                        log::error!("Failed to read image file: {}: {}", path.display(), err);
+                        log::error!(
+                            path:% = path.display(),
+                            error:% = err;
+                            "Failed to read dropped file"
+                        );
                         None
                     }
                 }
@@ -133,19 +203,22 @@ impl FileHandler {
             #[cfg(target_arch = "wasm32")]
             {
                 let _path = path; // Rename with underscore to indicate intentionally unused
-                log::warn!("File path access not supported on WASM: {}", file_name);
+                log::warn!(file:% = file_name; "File path access not supported on WASM");
                 None
             }
         } else {
-            log::warn!("Dropped file has no accessible data: {}", file_name);
+            log::warn!(file:% = file_name; "Dropped file has no accessible data");
             None
         }
     }
 
-    /// Create an image from bytes and return a command to add it to the document
+    /// Create an image from bytes and return a command to add it to the document,
+    /// linked to `link` if given rather than embedded
     fn create_image_from_bytes(
         &self,
         bytes: &[u8],
+        link: Option<String>,
+        editor_model: &EditorModel,
         panel_rect: egui::Rect,
         ctx: &egui::Context,
     ) -> Option<Command> {
@@ -158,7 +231,7 @@ impl FileHandler {
 
                 // Validate panel rect
                 if panel_rect.width() <= 0.0 || panel_rect.height() <= 0.0 {
-                    log::error!("Invalid panel rect: {:?}", panel_rect);
+                    log::error!(rect:? = panel_rect; "Invalid panel rect");
                     return None;
                 }
 
@@ -171,12 +244,20 @@ impl FileHandler {
 
                 // Create an image element using the element factory
                 // Pass the original bytes - conversion to RGBA happens in generate_texture
-                let element = crate::element::factory::create_image(
-                    crate::id_generator::generate_id(),
-                    bytes.to_vec(),
-                    egui::vec2(width, height),
-                    position,
-                );
+                let id = editor_model.reserve_id();
+                let size = egui::vec2(width, height);
+                let element = match link {
+                    Some(link) => crate::element::factory::create_linked_image(
+                        id,
+                        link,
+                        bytes.to_vec(),
+                        size,
+                        position,
+                    ),
+                    None => {
+                        crate::element::factory::create_image(id, bytes.to_vec(), size, position)
+                    }
+                };
 
                 // Create a command to add the element
                 let command = Command::AddElement { element };
@@ -187,12 +268,168 @@ impl FileHandler {
                 Some(command)
             }
             Err(err) => {
-                log::error!("Failed to decode image: {}", err);
+                log::error!(error:% = err; "Failed to decode image");
                 None
             }
         }
     }
 
+    /// Write the document to a file, encrypted if a passphrase is given
+    #[cfg(not(target_arch = "wasm32"))]
+    pub fn save_document(
+        &self,
+        path: &std::path::Path,
+        editor_model: &EditorModel,
+        passphrase: Option<&str>,
+    ) -> Result<(), DocumentError> {
+        log::info!(path:% = path.display(); "Saving document");
+        let bytes = encode_document(editor_model, passphrase)?;
+        std::fs::write(path, bytes)?;
+        Ok(())
+    }
+
+    /// Download the document, named after the file name of `path`
+    ///
+    /// Browsers save downloads where the user tells them to, so the rest of the
+    /// path is ignored.
+    #[cfg(target_arch = "wasm32")]
+    pub fn save_document(
+        &self,
+        path: &std::path::Path,
+        editor_model: &EditorModel,
+        passphrase: Option<&str>,
+    ) -> Result<(), DocumentError> {
+        let name = path
+            .file_name()
+            .map(|name| name.to_string_lossy().into_owned())
+            .unwrap_or_else(|| format!("untitled.{}", DOCUMENT_EXTENSION));
+        log::info!(name:% = name; "Downloading document");
+        let bytes = encode_document(editor_model, passphrase)?;
+        web::download(&name, &bytes).map_err(web::io_error)?;
+        Ok(())
+    }
+
+    /// Read a document from a file, along with the files its linked images
+    /// point at
+    ///
+    /// Encrypted files need their passphrase; without one this fails with
+    /// `PassphraseRequired`, so the caller can ask for it and try again.
+    /// Linked images that can't be read are left missing.
+    pub fn open_document(
+        &self,
+        path: &std::path::Path,
+        passphrase: Option<&str>,
+    ) -> Result<DocumentFile, DocumentError> {
+        log::info!(path:% = path.display(); "Opening document");
+        let mut file = decode_document(self.read_file(path)?, passphrase)?;
+        let document_dir = path.parent().unwrap_or(std::path::Path::new(""));
+        crate::assets::load_linked_images(&mut file.elements, document_dir);
+        Ok(file)
+    }
+
+    /// Start a new document from the image file at `path`, with the image at
+    /// its own size and its top-left corner at `origin`
+    pub fn open_image_document(
+        &self,
+        path: &std::path::Path,
+        origin: egui::Pos2,
+    ) -> Result<DocumentFile, DocumentError> {
+        log::info!(path:% = path.display(); "Starting a document from an image");
+        let name = path
+            .file_stem()
+            .map(|stem| stem.to_string_lossy().into_owned())
+            .unwrap_or_default();
+        DocumentFile::from_image(self.read_file(path)?, origin, &name)
+    }
+
+    #[cfg(not(target_arch = "wasm32"))]
+    fn read_file(&self, path: &std::path::Path) -> Result<Vec<u8>, DocumentError> {
+        Ok(std::fs::read(path)?)
+    }
+
+    /// The contents of the file last picked with [`Self::pick_document`], if
+    /// `path` names it
+    ///
+    /// Web pages can't read files by path, so this is how the picked file is
+    /// opened once its contents have arrived.
+    #[cfg(target_arch = "wasm32")]
+    fn read_file(&self, path: &std::path::Path) -> Result<Vec<u8>, DocumentError> {
+        let name = path.to_string_lossy();
+        match &self.picked_document {
+            Some(picked) if picked.name == name => Ok(picked.bytes.clone()),
+            _ => Err(std::io::Error::new(
+                std::io::ErrorKind::NotFound,
+                format!("{} hasn't been picked; use Open to choose it", name),
+            )
+            .into()),
+        }
+    }
+
+    /// Ask the browser for a document or image file to open
+    ///
+    /// The file is read in the background; [`Self::poll_picked_document`]
+    /// hands over its name once it has been read.
+    #[cfg(target_arch = "wasm32")]
+    pub fn pick_document(&mut self, ctx: &egui::Context) {
+        let accept = format!(".{},image/*", DOCUMENT_EXTENSION);
+        if let Err(err) = web::pick_file(&accept, self.picking.clone(), ctx.clone()) {
+            *self.picking.borrow_mut() = Some(Err(format!("{:?}", err)));
+        }
+    }
+
+    /// The name of the document picked with [`Self::pick_document`], once it
+    /// has been read and can be opened with [`Self::open_document`]
+    #[cfg(target_arch = "wasm32")]
+    pub fn poll_picked_document(&mut self) -> Option<Result<String, DocumentError>> {
+        let picked = self.picking.borrow_mut().take()?;
+        Some(match picked {
+            Ok(picked) => {
+                let name = picked.name.clone();
+                self.picked_document = Some(picked);
+                Ok(name)
+            }
+            Err(err) => Err(std::io::Error::other(err).into()),
+        })
+    }
+
+    /// A new document holding just an image, fitted into `fit_rect`
+    ///
+    /// The image keeps its aspect ratio and is never scaled up. An artboard
+    /// of the same size makes exports come out as the (annotated) image.
+    pub fn image_document(
+        &self,
+        bytes: Vec<u8>,
+        fit_rect: egui::Rect,
+    ) -> Result<DocumentFile, String> {
+        let (width, height) = image::load_from_memory(&bytes)
+            .map(|img| (img.width() as f32, img.height() as f32))
+            .map_err(|err| format!("Failed to decode image: {}", err))?;
+        if fit_rect.width() <= 0.0 || fit_rect.height() <= 0.0 {
+            return Err(format!("Invalid panel rect: {:?}", fit_rect));
+        }
+
+        let scale = (fit_rect.width() / width)
+            .min(fit_rect.height() / height)
+            .min(1.0);
+        let rect = egui::Rect::from_center_size(
+            fit_rect.center(),
+            egui::vec2(width * scale, height * scale),
+        );
+        // The only element of a new document
+        let element = crate::element::factory::create_image(
+            EditorModel::new().reserve_id(),
+            bytes,
+            rect.size(),
+            rect.min,
+        );
+
+        Ok(DocumentFile {
+            artboards: vec![crate::artboard::Artboard::new("Screenshot", rect)],
+            elements: vec![element],
+            ..DocumentFile::default()
+        })
+    }
+
     /// Preview files being dragged over the application
     pub fn preview_files_being_dropped(&self, ctx: &egui::Context) {
         use egui::{Align2, Color32, Id, LayerId, Order};
@@ -232,3 +469,151 @@ impl FileHandler {
         }
     }
 }
+
+/// Whether `path` names an image file that can be opened, from its extension
+pub fn is_image_path(path: &std::path::Path) -> bool {
+    path.extension().is_some_and(|ext| {
+        matches!(
+            ext.to_string_lossy().to_lowercase().as_str(),
+            "png" | "jpg" | "jpeg" | "gif" | "webp" | "bmp"
+        )
+    })
+}
+
+impl Default for FileHandler {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+/// The bytes of a document file, encrypted if a passphrase is given
+fn encode_document(
+    editor_model: &EditorModel,
+    passphrase: Option<&str>,
+) -> Result<Vec<u8>, DocumentError> {
+    let text = DocumentFile::from_model(editor_model).to_ron()?;
+    match passphrase {
+        #[cfg(not(target_arch = "wasm32"))]
+        Some(passphrase) => encryption::encrypt(text.as_bytes(), passphrase),
+        #[cfg(target_arch = "wasm32")]
+        Some(_) => Err(DocumentError::Unsupported),
+        None => Ok(text.into_bytes()),
+    }
+}
+
+/// Read the bytes of a document file, decrypting them if they're encrypted
+pub fn decode_document(
+    bytes: Vec<u8>,
+    passphrase: Option<&str>,
+) -> Result<DocumentFile, DocumentError> {
+    #[cfg(not(target_arch = "wasm32"))]
+    let bytes = if encryption::is_encrypted(&bytes) {
+        let passphrase = passphrase.ok_or(DocumentError::PassphraseRequired)?;
+        encryption::decrypt(&bytes, passphrase)?
+    } else {
+        bytes
+    };
+    // Encrypted documents can't be read on the web; they fail to decode below
+    #[cfg(target_arch = "wasm32")]
+    let _ = passphrase;
+    let text = String::from_utf8(bytes)
+        .map_err(|err| std::io::Error::new(std::io::ErrorKind::InvalidData, err.utf8_error()))?;
+    DocumentFile::from_ron(&text)
+}
+
+/// Reading and downloading files through the browser
+#[cfg(target_arch = "wasm32")]
+mod web {
+    use crate::document::DocumentError;
+    use eframe::wasm_bindgen::closure::Closure;
+    use eframe::wasm_bindgen::{JsCast as _, JsValue};
+    use std::cell::RefCell;
+    use std::rc::Rc;
+
+    /// How long a download's object URL is kept before it's revoked
+    const DOWNLOAD_URL_LIFETIME_MS: i32 = 40_000;
+
+    /// A file the user picked, read into memory
+    #[derive(Debug)]
+    pub struct PickedFile {
+        pub name: String,
+        pub bytes: Vec<u8>,
+    }
+
+    /// Where a picked file lands once it has been read
+    pub type PickedSlot = Rc<RefCell<Option<Result<PickedFile, String>>>>;
+
+    pub fn io_error(err: JsValue) -> DocumentError {
+        std::io::Error::other(format!("{:?}", err)).into()
+    }
+
+    fn document() -> Result<web_sys::Document, JsValue> {
+        web_sys::window()
+            .and_then(|window| window.document())
+            .ok_or_else(|| JsValue::from_str("No document to show a file dialog in"))
+    }
+
+    /// Show the browser's file dialog through a hidden `<input type=file>`
+    ///
+    /// The picked file is read asynchronously into `slot`, waking up the UI
+    /// when it's there. Nothing happens if the dialog is cancelled.
+    pub fn pick_file(accept: &str, slot: PickedSlot, ctx: egui::Context) -> Result<(), JsValue> {
+        let input = document()?
+            .create_element("input")?
+            .dyn_into::<web_sys::HtmlInputElement>()?;
+        input.set_type("file");
+        input.set_accept(accept);
+
+        let on_change = {
+            let input = input.clone();
+            Closure::once_into_js(move || {
+                let Some(file) = input.files().and_then(|files| files.get(0)) else {
+                    return;
+                };
+                wasm_bindgen_futures::spawn_local(async move {
+                    let read = wasm_bindgen_futures::JsFuture::from(file.array_buffer()).await;
+                    *slot.borrow_mut() = Some(match read {
+                        Ok(buffer) => Ok(PickedFile {
+                            name: file.name(),
+                            bytes: js_sys::Uint8Array::new(&buffer).to_vec(),
+                        }),
+                        Err(err) => Err(format!("Failed to read {}: {:?}", file.name(), err)),
+                    });
+                    ctx.request_repaint();
+                });
+            })
+        };
+        input.set_onchange(Some(on_change.unchecked_ref()));
+        input.click();
+        Ok(())
+    }
+
+    /// Have the browser download `bytes` as a file called `name`
+    pub fn download(name: &str, bytes: &[u8]) -> Result<(), JsValue> {
+        let parts = js_sys::Array::of1(&js_sys::Uint8Array::from(bytes));
+        let options = web_sys::BlobPropertyBag::new();
+        options.set_type("application/octet-stream");
+        let blob = web_sys::Blob::new_with_u8_array_sequence_and_options(&parts, &options)?;
+        let url = web_sys::Url::create_object_url_with_blob(&blob)?;
+
+        let anchor = document()?
+            .create_element("a")?
+            .dyn_into::<web_sys::HtmlAnchorElement>()?;
+        anchor.set_href(&url);
+        anchor.set_download(name);
+        anchor.click();
+
+        // Some browsers start the download after the click returns, so the
+        // URL has to stay valid for a while
+        let revoke = Closure::once_into_js(move || {
+            let _ = web_sys::Url::revoke_object_url(&url);
+        });
+        web_sys::window()
+            .ok_or_else(|| JsValue::from_str("No window"))?
+            .set_timeout_with_callback_and_timeout_and_arguments_0(
+                revoke.unchecked_ref(),
+                DOWNLOAD_URL_LIFETIME_MS,
+            )?;
+        Ok(())
+    }
+}
@@ -0,0 +1,1045 @@
+//! Polyline geometry used by the stroke and shape editing commands
+
+use egui::{Pos2, Rect, Vec2};
+use std::f32::consts::{PI, TAU};
+
+/// Intersect segment `a` with segment `b`
+///
+/// Returns the parameter along `a` (0 at `a_start`, 1 at `a_end`) and the
+/// intersection point. Parallel and collinear segments don't intersect.
+pub fn segment_intersection(
+    a_start: Pos2,
+    a_end: Pos2,
+    b_start: Pos2,
+    b_end: Pos2,
+) -> Option<(f32, Pos2)> {
+    let a = a_end - a_start;
+    let b = b_end - b_start;
+    let denominator = a.x * b.y - a.y * b.x;
+    if denominator.abs() < f32::EPSILON {
+        return None;
+    }
+
+    let offset = b_start - a_start;
+    let t = (offset.x * b.y - offset.y * b.x) / denominator;
+    let u = (offset.x * a.y - offset.y * a.x) / denominator;
+    if (0.0..=1.0).contains(&t) && (0.0..=1.0).contains(&u) {
+        Some((t, a_start + a * t))
+    } else {
+        None
+    }
+}
+
+/// Split a polyline everywhere it crosses the segment from `cut_start` to `cut_end`
+///
+/// Returns the pieces in order along the polyline; a polyline that isn't crossed
+/// comes back as a single piece. Degenerate pieces (fewer than two points) are dropped.
+pub fn split_polyline(points: &[Pos2], cut_start: Pos2, cut_end: Pos2) -> Vec<Vec<Pos2>> {
+    let mut pieces = Vec::new();
+    let mut current = Vec::new();
+
+    for window in points.windows(2) {
+        let (start, end) = (window[0], window[1]);
+        if current.is_empty() {
+            current.push(start);
+        }
+
+        // A cut through a shared vertex is found on the segment that starts there
+        if let Some((t, point)) = segment_intersection(start, end, cut_start, cut_end) {
+            if t < 1.0 {
+                current.push(point);
+                pieces.push(std::mem::take(&mut current));
+                current.push(point);
+            }
+        }
+        current.push(end);
+    }
+
+    pieces.push(current);
+    pieces.retain(|piece| piece.len() >= 2 && piece.first() != piece.last());
+    pieces
+}
+
+/// Split a polyline in two at the point on it nearest to `pos`
+///
+/// Both pieces share the split point. Returns None if `pos` is further than
+/// `tolerance` from the polyline, or so close to an end that one piece would
+/// have no length.
+pub fn split_polyline_at(
+    points: &[Pos2],
+    pos: Pos2,
+    tolerance: f32,
+) -> Option<(Vec<Pos2>, Vec<Pos2>)> {
+    let (index, distance) = points
+        .windows(2)
+        .map(|segment| distance_to_segment(pos, segment[0], segment[1]))
+        .enumerate()
+        .min_by(|a, b| a.1.total_cmp(&b.1))?;
+    if distance > tolerance {
+        return None;
+    }
+
+    let (start, end) = (points[index], points[index + 1]);
+    let segment = end - start;
+    let t = if segment.length_sq() > 0.0 {
+        ((pos - start).dot(segment) / segment.length_sq()).clamp(0.0, 1.0)
+    } else {
+        0.0
+    };
+    let split = start + segment * t;
+
+    let mut first = points[..=index].to_vec();
+    let mut second = points[index + 1..].to_vec();
+    if first.last() != Some(&split) {
+        first.push(split);
+    }
+    if second.first() != Some(&split) {
+        second.insert(0, split);
+    }
+    let has_length = |piece: &[Pos2]| piece.windows(2).any(|pair| pair[0] != pair[1]);
+    (has_length(&first) && has_length(&second)).then_some((first, second))
+}
+
+/// Chain polylines into one continuous polyline, reversing pieces as needed
+///
+/// Starting from the first polyline, each step attaches whichever remaining
+/// polyline has an endpoint closest to either end of the chain. Returns None if
+/// some polyline can't be reached within `tolerance`. Endpoints that are joined
+/// are merged into a single point at their midpoint.
+pub fn join_polylines(polylines: &[Vec<Pos2>], tolerance: f32) -> Option<Vec<Pos2>> {
+    let (first, rest) = polylines.split_first()?;
+    let mut chain = first.clone();
+    let mut remaining: Vec<&Vec<Pos2>> = rest.iter().collect();
+
+    while !remaining.is_empty() {
+        let chain_start = *chain.first()?;
+        let chain_end = *chain.last()?;
+
+        // (index, attach at chain end?, reverse the piece?, distance)
+        let mut best: Option<(usize, bool, bool, f32)> = None;
+        for (index, piece) in remaining.iter().enumerate() {
+            let (Some(&start), Some(&end)) = (piece.first(), piece.last()) else {
+                continue;
+            };
+            for (at_end, reverse, distance) in [
+                (true, false, chain_end.distance(start)),
+                (true, true, chain_end.distance(end)),
+                (false, false, chain_start.distance(end)),
+                (false, true, chain_start.distance(start)),
+            ] {
+                if distance <= tolerance && best.is_none_or(|(.., best)| distance < best) {
+                    best = Some((index, at_end, reverse, distance));
+                }
+            }
+        }
+
+        let (index, at_end, reverse, _) = best?;
+        let mut piece = remaining.swap_remove(index).clone();
+        if reverse {
+            piece.reverse();
+        }
+
+        if at_end {
+            let joint = chain.pop()?.lerp(piece[0], 0.5);
+            chain.push(joint);
+            chain.extend_from_slice(&piece[1..]);
+        } else {
+            let joint = piece.pop()?.lerp(chain[0], 0.5);
+            chain[0] = joint;
+            piece.extend_from_slice(&chain);
+            chain = piece;
+        }
+    }
+
+    Some(chain)
+}
+
+/// Drop points that deviate less than `tolerance` from the simplified line
+/// (Ramer–Douglas–Peucker). Endpoints are always kept.
+pub fn simplify_polyline(points: &[Pos2], tolerance: f32) -> Vec<Pos2> {
+    if points.len() < 3 {
+        return points.to_vec();
+    }
+
+    let mut keep = vec![false; points.len()];
+    keep[0] = true;
+    keep[points.len() - 1] = true;
+
+    // Explicit stack instead of recursion, so long strokes can't overflow
+    let mut ranges = vec![(0, points.len() - 1)];
+    while let Some((first, last)) = ranges.pop() {
+        let (start, end) = (points[first], points[last]);
+        let farthest = (first + 1..last)
+            .map(|index| (index, distance_to_segment(points[index], start, end)))
+            .max_by(|a, b| a.1.total_cmp(&b.1));
+
+        if let Some((index, distance)) = farthest {
+            if distance > tolerance {
+                keep[index] = true;
+                ranges.push((first, index));
+                ranges.push((index, last));
+            }
+        }
+    }
+
+    points
+        .iter()
+        .zip(keep)
+        .filter_map(|(point, keep)| keep.then_some(*point))
+        .collect()
+}
+
+/// Smooth a polyline by repeatedly pulling each interior point toward the
+/// midpoint of its neighbours. `strength` ranges from 0 (unchanged) to 1.
+/// Endpoints stay fixed so smoothed strokes still meet whatever they touched.
+pub fn smooth_polyline(points: &[Pos2], strength: f32) -> Vec<Pos2> {
+    const PASSES: usize = 3;
+
+    let strength = strength.clamp(0.0, 1.0);
+    let mut smoothed = points.to_vec();
+    if smoothed.len() < 3 || strength == 0.0 {
+        return smoothed;
+    }
+
+    for _ in 0..PASSES {
+        let previous = smoothed.clone();
+        for index in 1..previous.len() - 1 {
+            let midpoint = previous[index - 1].lerp(previous[index + 1], 0.5);
+            smoothed[index] = previous[index].lerp(midpoint, strength);
+        }
+    }
+    smoothed
+}
+
+/// Fit cubic Bézier curves through a polyline, each staying within `tolerance`
+/// of the points it replaces (Schneider's algorithm)
+///
+/// Curves are returned in order as `[start, control, control, end]`, each
+/// starting where the last ended, and meet smoothly except where a curve had to
+/// be split to stay within the tolerance. Repeated points are ignored; fewer
+/// than two distinct points give no curves.
+pub fn fit_cubic_beziers(points: &[Pos2], tolerance: f32) -> Vec<[Pos2; 4]> {
+    /// Newton steps tried before splitting a curve that misses by a little
+    const REPARAMETERIZE_STEPS: usize = 4;
+
+    let mut points = points.to_vec();
+    points.dedup();
+    let last = points.len().saturating_sub(1);
+    if last == 0 {
+        return Vec::new();
+    }
+
+    let tolerance_sq = tolerance * tolerance;
+    let start_tangent = (points[1] - points[0]).normalized();
+    let end_tangent = (points[last - 1] - points[last]).normalized();
+
+    // Explicit stack instead of recursion, like simplify_polyline; the right
+    // half of a split is pushed first so curves come out in order
+    let mut curves = Vec::new();
+    let mut ranges = vec![(0, last, start_tangent, end_tangent)];
+    while let Some((first, last, start_tangent, end_tangent)) = ranges.pop() {
+        let span = &points[first..=last];
+        if span.len() == 2 {
+            let third = span[0].distance(span[1]) / 3.0;
+            curves.push([
+                span[0],
+                span[0] + start_tangent * third,
+                span[1] + end_tangent * third,
+                span[1],
+            ]);
+            continue;
+        }
+
+        let mut params = chord_length_params(span);
+        let mut curve = fit_one_cubic(span, &params, start_tangent, end_tangent);
+        let (mut error, mut split) = max_fit_error(span, &params, &curve);
+        if error > tolerance_sq && error < tolerance_sq * 4.0 {
+            for _ in 0..REPARAMETERIZE_STEPS {
+                reparameterize(span, &mut params, &curve);
+                curve = fit_one_cubic(span, &params, start_tangent, end_tangent);
+                (error, split) = max_fit_error(span, &params, &curve);
+                if error <= tolerance_sq {
+                    break;
+                }
+            }
+        }
+        if error <= tolerance_sq {
+            curves.push(curve);
+            continue;
+        }
+
+        let split = first + split;
+        let mut center = points[split - 1] - points[split + 1];
+        if center.length_sq() == 0.0 {
+            center = points[split - 1] - points[split];
+        }
+        let center = center.normalized();
+        ranges.push((split, last, -center, end_tangent));
+        ranges.push((first, split, start_tangent, center));
+    }
+    curves
+}
+
+/// Point at `t` along the cubic Bézier curve `curve`
+fn cubic_at(curve: &[Pos2; 4], t: f32) -> Pos2 {
+    let u = 1.0 - t;
+    let [p0, p1, p2, p3] = curve.map(Pos2::to_vec2);
+    (p0 * (u * u * u) + p1 * (3.0 * u * u * t) + p2 * (3.0 * u * t * t) + p3 * (t * t * t))
+        .to_pos2()
+}
+
+/// Position of each point along the polyline, from 0 at the start to 1 at the end
+fn chord_length_params(points: &[Pos2]) -> Vec<f32> {
+    let mut params = Vec::with_capacity(points.len());
+    let mut length = 0.0;
+    params.push(0.0);
+    for window in points.windows(2) {
+        length += window[0].distance(window[1]);
+        params.push(length);
+    }
+    params.iter().map(|param| param / length).collect()
+}
+
+/// The least-squares cubic through `points` at `params`, leaving its ends along
+/// the given unit tangents
+fn fit_one_cubic(
+    points: &[Pos2],
+    params: &[f32],
+    start_tangent: egui::Vec2,
+    end_tangent: egui::Vec2,
+) -> [Pos2; 4] {
+    let (start, end) = (points[0], points[points.len() - 1]);
+    let (mut c00, mut c01, mut c11, mut x0, mut x1) = (0.0, 0.0, 0.0, 0.0, 0.0);
+    for (point, &t) in points.iter().zip(params) {
+        let u = 1.0 - t;
+        let a1 = start_tangent * (3.0 * u * u * t);
+        let a2 = end_tangent * (3.0 * u * t * t);
+        c00 += a1.dot(a1);
+        c01 += a1.dot(a2);
+        c11 += a2.dot(a2);
+        let fixed = start.to_vec2() * (u * u * u + 3.0 * u * u * t)
+            + end.to_vec2() * (3.0 * u * t * t + t * t * t);
+        let offset = point.to_vec2() - fixed;
+        x0 += a1.dot(offset);
+        x1 += a2.dot(offset);
+    }
+
+    // Fall back to handles a third of the way along when the fit degenerates
+    let chord = start.distance(end);
+    let det = c00 * c11 - c01 * c01;
+    let (mut alpha_start, mut alpha_end) = (0.0, 0.0);
+    if det.abs() > f32::EPSILON {
+        alpha_start = (x0 * c11 - x1 * c01) / det;
+        alpha_end = (c00 * x1 - c01 * x0) / det;
+    }
+    let min_alpha = chord * 1.0e-6;
+    if alpha_start < min_alpha || alpha_end < min_alpha {
+        alpha_start = chord / 3.0;
+        alpha_end = chord / 3.0;
+    }
+    [
+        start,
+        start + start_tangent * alpha_start,
+        end + end_tangent * alpha_end,
+        end,
+    ]
+}
+
+/// Largest squared distance between a point and the curve at its param, and
+/// the index of that point
+fn max_fit_error(points: &[Pos2], params: &[f32], curve: &[Pos2; 4]) -> (f32, usize) {
+    let mut worst = (0.0, points.len() / 2);
+    for index in 1..points.len() - 1 {
+        let error = cubic_at(curve, params[index]).distance_sq(points[index]);
+        if error > worst.0 {
+            worst = (error, index);
+        }
+    }
+    worst
+}
+
+/// Move each param to the nearest point of the curve with a Newton step
+fn reparameterize(points: &[Pos2], params: &mut [f32], curve: &[Pos2; 4]) {
+    let [p0, p1, p2, p3] = curve.map(Pos2::to_vec2);
+    let first = [(p1 - p0) * 3.0, (p2 - p1) * 3.0, (p3 - p2) * 3.0];
+    let second = [(first[1] - first[0]) * 2.0, (first[2] - first[1]) * 2.0];
+    for (point, t) in points.iter().zip(params.iter_mut()) {
+        let u = 1.0 - *t;
+        let offset = cubic_at(curve, *t) - *point;
+        let velocity = first[0] * (u * u) + first[1] * (2.0 * u * *t) + first[2] * (*t * *t);
+        let acceleration = second[0] * u + second[1] * *t;
+        let denominator = velocity.dot(velocity) + offset.dot(acceleration);
+        if denominator.abs() > f32::EPSILON {
+            *t = (*t - offset.dot(velocity) / denominator).clamp(0.0, 1.0);
+        }
+    }
+}
+
+/// Outline a polyline drawn `width` wide as a closed polygon
+///
+/// Joins are mitered, falling back to bevels where a miter would reach too far,
+/// and the ends get round caps. The outline can overlap itself at sharp turns,
+/// so it should be filled with the nonzero winding rule.
+pub fn outline_polyline(points: &[Pos2], width: f32) -> Vec<Pos2> {
+    let mut points = points.to_vec();
+    points.dedup();
+    let radius = width / 2.0;
+    if points.is_empty() || radius <= 0.0 {
+        return Vec::new();
+    }
+    if points.len() == 1 {
+        return arc(points[0], radius, 0.0, TAU, ROUND_CAP_SEGMENTS * 2);
+    }
+
+    let mut outline = offset_side(&points, radius);
+    points.reverse();
+    let other_side = offset_side(&points, radius);
+
+    // Cap at the end of the polyline, which is now points[0]
+    outline.extend(cap(points[0], points[1], radius));
+    outline.extend(other_side);
+    let last = points.len() - 1;
+    outline.extend(cap(points[last], points[last - 1], radius));
+    outline
+}
+
+/// `to` swung around `from` onto the nearest direction at a multiple of
+/// `step` radians from the x axis, at the same distance
+pub fn snap_angle(from: Pos2, to: Pos2, step: f32) -> Pos2 {
+    let offset = to - from;
+    if step <= 0.0 || offset == Vec2::ZERO {
+        return to;
+    }
+    let angle = (offset.angle() / step).round() * step;
+    from + Vec2::angled(angle) * offset.length()
+}
+
+/// Whether `pos` is inside the polygon, using the nonzero winding rule
+pub fn polygon_contains(polygon: &[Pos2], pos: Pos2) -> bool {
+    let mut winding = 0;
+    for (index, &start) in polygon.iter().enumerate() {
+        let end = polygon[(index + 1) % polygon.len()];
+        let side = (end - start).x * (pos - start).y - (end - start).y * (pos - start).x;
+        if start.y <= pos.y && end.y > pos.y && side > 0.0 {
+            winding += 1;
+        } else if start.y > pos.y && end.y <= pos.y && side < 0.0 {
+            winding -= 1;
+        }
+    }
+    winding != 0
+}
+
+/// Round each corner of a closed polygon with an arc of `radius`
+///
+/// Corners too tight for the full radius get the largest arc that fits in half
+/// of each adjoining edge. Straight and degenerate corners are kept as they are.
+pub fn round_corners(polygon: &[Pos2], radius: f32) -> Vec<Pos2> {
+    if radius <= 0.0 || polygon.len() < 3 {
+        return polygon.to_vec();
+    }
+
+    let mut rounded = Vec::new();
+    for (index, &corner) in polygon.iter().enumerate() {
+        let previous = polygon[(index + polygon.len() - 1) % polygon.len()];
+        let next = polygon[(index + 1) % polygon.len()];
+        let (to_previous, to_next) = (previous - corner, next - corner);
+        let half_angle = to_previous
+            .normalized()
+            .dot(to_next.normalized())
+            .clamp(-1.0, 1.0)
+            .acos()
+            / 2.0;
+        if !(1e-3..=PI / 2.0 - 1e-3).contains(&half_angle) {
+            rounded.push(corner);
+            continue;
+        }
+
+        // Distance from the corner to where the arc meets each edge
+        let tangent = (radius / half_angle.tan())
+            .min(to_previous.length() / 2.0)
+            .min(to_next.length() / 2.0);
+        let fitted_radius = tangent * half_angle.tan();
+        let bisector = (to_previous.normalized() + to_next.normalized()).normalized();
+        let center = corner + bisector * (fitted_radius / half_angle.sin());
+
+        let start = corner + to_previous.normalized() * tangent;
+        let end = corner + to_next.normalized() * tangent;
+        let start_angle = (start - center).angle();
+        let mut sweep = (end - center).angle() - start_angle;
+        if sweep > PI {
+            sweep -= TAU;
+        } else if sweep < -PI {
+            sweep += TAU;
+        }
+        rounded.extend(arc(
+            center,
+            fitted_radius,
+            start_angle,
+            sweep,
+            ROUND_CORNER_SEGMENTS,
+        ));
+    }
+    rounded
+}
+
+/// Outline of a speech bubble over `body`, with rounded corners and a tail
+/// `tail_width` wide at its base pointing at `tip`
+///
+/// The tail leaves from the side of the bubble facing `tip`. Returns None if
+/// `tip` is inside the bubble, leaving nothing for the tail to point at.
+pub fn speech_bubble_outline(
+    body: Rect,
+    tip: Pos2,
+    corner_radius: f32,
+    tail_width: f32,
+) -> Option<Vec<Pos2>> {
+    if body.contains(tip) {
+        return None;
+    }
+
+    // Corners in order, each followed by the edge running to the next one
+    let corners = [
+        body.left_top(),
+        body.right_top(),
+        body.right_bottom(),
+        body.left_bottom(),
+    ];
+    let offset = tip - body.center();
+    let edge = if offset.x.abs() / body.width() > offset.y.abs() / body.height() {
+        if offset.x > 0.0 { 1 } else { 3 }
+    } else if offset.y > 0.0 {
+        2
+    } else {
+        0
+    };
+
+    // Put the tail's base where the edge is closest to the tip, clear of the
+    // corners, narrowing it on edges too short to fit it
+    let (from, to) = (corners[edge], corners[(edge + 1) % 4]);
+    let length = (to - from).length();
+    let direction = (to - from).normalized();
+    let half_width = (tail_width / 2.0).min(length / 2.0);
+    let inset = (corner_radius + half_width).min(length / 2.0);
+    let along = (tip - from).dot(direction).clamp(inset, length - inset);
+    let base = from + direction * along;
+    let half_width = direction * half_width;
+
+    let mut outline = round_corners(&corners, corner_radius);
+    let per_corner = outline.len() / corners.len();
+    let insert_at = (edge + 1) * per_corner;
+    outline.splice(
+        insert_at..insert_at,
+        [base - half_width, tip, base + half_width],
+    );
+    Some(outline)
+}
+
+/// How `polygon_boolean` combines two polygons
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum BooleanOp {
+    Union,
+    /// The first polygon with the second cut out of it
+    Subtract,
+    Intersect,
+}
+
+/// Combine two filled polygons, returning the outlines of the result
+///
+/// Both polygons should be simple (not crossing themselves); either winding
+/// direction is fine. Each returned outline encloses one separate piece, with
+/// any holes in it bridged in by a zero-width slit so it fills correctly on its
+/// own under the nonzero rule. Returns nothing if the result is empty.
+pub fn polygon_boolean(a: &[Pos2], b: &[Pos2], op: BooleanOp) -> Vec<Vec<Pos2>> {
+    if a.len() < 3 || b.len() < 3 {
+        return Vec::new();
+    }
+    let a = counter_clockwise(a);
+    let b = counter_clockwise(b);
+    let (a_edges, b_edges) = split_at_crossings(&a, &b);
+
+    // Keep the pieces of each boundary that bound the result, pointing so the
+    // result is on their left
+    let mut kept = Vec::new();
+    for (start, end) in a_edges {
+        let keep = match (classify_edge(start, end, &b), op) {
+            (EdgeSide::Outside, BooleanOp::Union | BooleanOp::Subtract) => true,
+            (EdgeSide::Inside, BooleanOp::Intersect) => true,
+            (EdgeSide::Shared { same_direction }, BooleanOp::Subtract) => !same_direction,
+            (EdgeSide::Shared { same_direction }, _) => same_direction,
+            _ => false,
+        };
+        if keep {
+            kept.push((start, end));
+        }
+    }
+    for (start, end) in b_edges {
+        match (classify_edge(start, end, &a), op) {
+            (EdgeSide::Outside, BooleanOp::Union) | (EdgeSide::Inside, BooleanOp::Intersect) => {
+                kept.push((start, end))
+            }
+            (EdgeSide::Inside, BooleanOp::Subtract) => kept.push((end, start)),
+            // Shared edges are taken from `a`
+            _ => {}
+        }
+    }
+
+    let rings = chain_edges(kept);
+    let (outers, holes): (Vec<_>, Vec<_>) = rings
+        .into_iter()
+        .filter(|ring| signed_area(ring).abs() > BOOLEAN_EPSILON)
+        .partition(|ring| signed_area(ring) > 0.0);
+
+    let mut outers = outers;
+    for hole in holes {
+        // The hole belongs to the smallest piece around it
+        let around = outers
+            .iter()
+            .enumerate()
+            .filter(|(_, outer)| polygon_contains(outer, hole[0]))
+            .min_by(|(_, x), (_, y)| signed_area(x).total_cmp(&signed_area(y)))
+            .map(|(index, _)| index);
+        if let Some(index) = around {
+            outers[index] = bridge_hole(&outers[index], &hole);
+        }
+    }
+    outers
+}
+
+// Distance under which points count as the same during boolean operations
+const BOOLEAN_EPSILON: f32 = 1e-3;
+
+/// A directed polygon edge, start to end
+type Edge = (Pos2, Pos2);
+
+/// Where an edge of one polygon lies relative to the other polygon
+enum EdgeSide {
+    Inside,
+    Outside,
+    /// On an edge of the other polygon, running the same or the opposite way
+    Shared {
+        same_direction: bool,
+    },
+}
+
+/// Twice the signed area of a closed polygon, positive when counter-clockwise
+/// on screen (y pointing down)
+fn signed_area(polygon: &[Pos2]) -> f32 {
+    polygon
+        .iter()
+        .zip(polygon.iter().cycle().skip(1))
+        .map(|(p, q)| q.x * p.y - p.x * q.y)
+        .sum()
+}
+
+fn counter_clockwise(polygon: &[Pos2]) -> Vec<Pos2> {
+    let mut polygon = polygon.to_vec();
+    if signed_area(&polygon) < 0.0 {
+        polygon.reverse();
+    }
+    polygon
+}
+
+fn closed_edges(polygon: &[Pos2]) -> impl Iterator<Item = Edge> + '_ {
+    polygon
+        .iter()
+        .copied()
+        .zip(polygon.iter().copied().cycle().skip(1))
+}
+
+/// Split the edges of both polygons wherever they cross or touch each other
+///
+/// Crossing points are computed once and shared by both sides, so the pieces
+/// meet exactly when chained back together.
+fn split_at_crossings(a: &[Pos2], b: &[Pos2]) -> (Vec<Edge>, Vec<Edge>) {
+    let mut a_splits: Vec<Vec<Pos2>> = vec![Vec::new(); a.len()];
+    let mut b_splits: Vec<Vec<Pos2>> = vec![Vec::new(); b.len()];
+    for (i, (a_start, a_end)) in closed_edges(a).enumerate() {
+        for (j, (b_start, b_end)) in closed_edges(b).enumerate() {
+            if let Some((_, point)) = segment_intersection(a_start, a_end, b_start, b_end) {
+                a_splits[i].push(point);
+                b_splits[j].push(point);
+            }
+            // Corners touching the other edge, which also covers overlapping edges
+            if distance_to_segment(b_start, a_start, a_end) < BOOLEAN_EPSILON {
+                a_splits[i].push(b_start);
+            }
+            if distance_to_segment(a_start, b_start, b_end) < BOOLEAN_EPSILON {
+                b_splits[j].push(a_start);
+            }
+        }
+    }
+    (split_edges(a, a_splits), split_edges(b, b_splits))
+}
+
+fn split_edges(polygon: &[Pos2], mut splits: Vec<Vec<Pos2>>) -> Vec<Edge> {
+    let mut edges = Vec::new();
+    for ((start, end), points) in closed_edges(polygon).zip(&mut splits) {
+        points.sort_by(|p, q| p.distance_sq(start).total_cmp(&q.distance_sq(start)));
+        let mut previous = start;
+        for &point in points.iter().chain([&end]) {
+            if point.distance(previous) > BOOLEAN_EPSILON {
+                edges.push((previous, point));
+                previous = point;
+            }
+        }
+    }
+    edges
+}
+
+fn classify_edge(start: Pos2, end: Pos2, other: &[Pos2]) -> EdgeSide {
+    let middle = start.lerp(end, 0.5);
+    let shared = closed_edges(other).find(|&(other_start, other_end)| {
+        distance_to_segment(middle, other_start, other_end) < BOOLEAN_EPSILON
+    });
+    match shared {
+        Some((other_start, other_end)) => EdgeSide::Shared {
+            same_direction: (end - start).dot(other_end - other_start) > 0.0,
+        },
+        None if polygon_contains(other, middle) => EdgeSide::Inside,
+        None => EdgeSide::Outside,
+    }
+}
+
+/// Join directed edges end to start into closed rings
+fn chain_edges(mut edges: Vec<Edge>) -> Vec<Vec<Pos2>> {
+    let mut rings = Vec::new();
+    while let Some((first, mut end)) = edges.pop() {
+        let mut ring = vec![first];
+        while end.distance(first) > BOOLEAN_EPSILON {
+            let Some(next) = edges
+                .iter()
+                .position(|(start, _)| start.distance(end) < BOOLEAN_EPSILON)
+            else {
+                // Open chains only come from degenerate input; drop them
+                ring.clear();
+                break;
+            };
+            ring.push(end);
+            end = edges.swap_remove(next).1;
+        }
+        if ring.len() >= 3 {
+            rings.push(ring);
+        }
+    }
+    rings
+}
+
+/// Splice `hole` into `outer` through a slit from the hole's first point to the
+/// nearest corner of the outline
+fn bridge_hole(outer: &[Pos2], hole: &[Pos2]) -> Vec<Pos2> {
+    let Some(nearest) = (0..outer.len()).min_by(|&i, &j| {
+        outer[i]
+            .distance_sq(hole[0])
+            .total_cmp(&outer[j].distance_sq(hole[0]))
+    }) else {
+        return outer.to_vec();
+    };
+    let mut bridged = outer[..=nearest].to_vec();
+    bridged.extend(hole.iter().copied());
+    bridged.push(hole[0]);
+    bridged.extend(outer[nearest..].iter().copied());
+    bridged
+}
+
+// Segments used for a half circle in round caps
+const ROUND_CAP_SEGMENTS: usize = 8;
+// Segments used for each rounded polygon corner
+const ROUND_CORNER_SEGMENTS: usize = 6;
+// Longest miter, relative to the offset radius, before a join is beveled
+const MITER_LIMIT: f32 = 4.0;
+
+/// Points offset `radius` to the left of a polyline with no repeated points
+fn offset_side(points: &[Pos2], radius: f32) -> Vec<Pos2> {
+    let normals: Vec<egui::Vec2> = points
+        .windows(2)
+        .map(|window| (window[1] - window[0]).normalized().rot90())
+        .collect();
+
+    let mut side = vec![points[0] + normals[0] * radius];
+    for (index, pair) in normals.windows(2).enumerate() {
+        let point = points[index + 1];
+        let (before, after) = (pair[0], pair[1]);
+        let miter = (before + after).normalized();
+        let cos = miter.dot(after);
+        if cos > 1.0 / MITER_LIMIT {
+            side.push(point + miter * (radius / cos));
+        } else {
+            side.push(point + before * radius);
+            side.push(point + after * radius);
+        }
+    }
+    side.push(points[points.len() - 1] + normals[normals.len() - 1] * radius);
+    side
+}
+
+/// Half circle around `end`, bulging away from `previous`, between the two offset sides
+fn cap(end: Pos2, previous: Pos2, radius: f32) -> Vec<Pos2> {
+    let direction = end - previous;
+    let start_angle = direction.rot90().angle();
+    let mut points = arc(end, radius, start_angle, PI, ROUND_CAP_SEGMENTS);
+    // The endpoints already come from the offset sides
+    points.pop();
+    points.remove(0);
+    points
+}
+
+/// Points along an arc, including both ends
+fn arc(center: Pos2, radius: f32, start_angle: f32, sweep: f32, segments: usize) -> Vec<Pos2> {
+    (0..=segments)
+        .map(|step| {
+            let angle = start_angle + sweep * step as f32 / segments as f32;
+            center + egui::Vec2::angled(angle) * radius
+        })
+        .collect()
+}
+
+pub(crate) fn distance_to_segment(point: Pos2, start: Pos2, end: Pos2) -> f32 {
+    let segment = end - start;
+    let length_sq = segment.length_sq();
+    if length_sq == 0.0 {
+        return point.distance(start);
+    }
+    let t = ((point - start).dot(segment) / length_sq).clamp(0.0, 1.0);
+    point.distance(start + segment * t)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_fit_cubic_beziers_splits_at_corners() {
+        // Two straight runs meeting at a right angle, sampled every point
+        let mut points: Vec<Pos2> = (0..=50).map(|x| Pos2::new(x as f32, 0.0)).collect();
+        points.extend((1..=50).map(|y| Pos2::new(50.0, y as f32)));
+        points.insert(10, points[10]);
+
+        let curves = fit_cubic_beziers(&points, 0.5);
+        assert!(curves.len() >= 2 && curves.len() < 10);
+        assert_eq!(curves[0][0], points[0]);
+        assert_eq!(curves[curves.len() - 1][3], points[points.len() - 1]);
+        for pair in curves.windows(2) {
+            assert_eq!(pair[0][3], pair[1][0]);
+        }
+        for point in &points {
+            let nearest = curves
+                .iter()
+                .flat_map(|curve| (0..=32).map(|step| cubic_at(curve, step as f32 / 32.0)))
+                .map(|on_curve| on_curve.distance(*point))
+                .fold(f32::INFINITY, f32::min);
+            assert!(nearest < 1.0, "{point:?} is {nearest} from the curves");
+        }
+
+        assert!(fit_cubic_beziers(&[Pos2::ZERO, Pos2::ZERO], 1.0).is_empty());
+    }
+
+    #[test]
+    fn test_split_polyline_at_each_crossing() {
+        // A zig-zag crossed twice by a horizontal cut
+        let points = [
+            Pos2::new(0.0, 0.0),
+            Pos2::new(10.0, 20.0),
+            Pos2::new(20.0, 0.0),
+        ];
+        let pieces = split_polyline(&points, Pos2::new(-5.0, 10.0), Pos2::new(25.0, 10.0));
+
+        assert_eq!(pieces.len(), 3);
+        assert_eq!(pieces[0], vec![Pos2::new(0.0, 0.0), Pos2::new(5.0, 10.0)]);
+        assert_eq!(
+            pieces[1],
+            vec![
+                Pos2::new(5.0, 10.0),
+                Pos2::new(10.0, 20.0),
+                Pos2::new(15.0, 10.0)
+            ]
+        );
+        assert_eq!(pieces[2], vec![Pos2::new(15.0, 10.0), Pos2::new(20.0, 0.0)]);
+    }
+
+    #[test]
+    fn test_join_polylines_orders_and_reverses() {
+        let first = vec![Pos2::new(10.0, 0.0), Pos2::new(20.0, 0.0)];
+        // Attaches before the first piece
+        let before = vec![Pos2::new(0.0, 0.0), Pos2::new(10.0, 1.0)];
+        // Attaches after the first piece, but drawn backwards
+        let after = vec![Pos2::new(30.0, 0.0), Pos2::new(20.0, 1.0)];
+
+        let joined = join_polylines(&[first, after, before], 2.0).unwrap();
+        assert_eq!(
+            joined,
+            vec![
+                Pos2::new(0.0, 0.0),
+                Pos2::new(10.0, 0.5),
+                Pos2::new(20.0, 0.5),
+                Pos2::new(30.0, 0.0),
+            ]
+        );
+
+        let far = vec![Pos2::new(100.0, 0.0), Pos2::new(110.0, 0.0)];
+        assert!(join_polylines(&[joined, far], 2.0).is_none());
+    }
+
+    #[test]
+    fn test_simplify_drops_nearly_collinear_points() {
+        let points = [
+            Pos2::new(0.0, 0.0),
+            Pos2::new(5.0, 0.2),
+            Pos2::new(10.0, 0.0),
+            Pos2::new(10.0, 10.0),
+        ];
+        assert_eq!(
+            simplify_polyline(&points, 0.5),
+            vec![
+                Pos2::new(0.0, 0.0),
+                Pos2::new(10.0, 0.0),
+                Pos2::new(10.0, 10.0)
+            ]
+        );
+        assert_eq!(simplify_polyline(&points, 0.1), points.to_vec());
+    }
+
+    #[test]
+    fn test_smooth_keeps_endpoints() {
+        let points = [
+            Pos2::new(0.0, 0.0),
+            Pos2::new(5.0, 10.0),
+            Pos2::new(10.0, 0.0),
+        ];
+        let smoothed = smooth_polyline(&points, 1.0);
+        assert_eq!(smoothed[0], points[0]);
+        assert_eq!(smoothed[2], points[2]);
+        assert!(smoothed[1].y < points[1].y);
+        assert_eq!(smooth_polyline(&points, 0.0), points.to_vec());
+    }
+
+    #[test]
+    fn test_outline_covers_the_stroke_width() {
+        let points = [
+            Pos2::new(0.0, 0.0),
+            Pos2::new(20.0, 0.0),
+            Pos2::new(20.0, 20.0),
+        ];
+        let outline = outline_polyline(&points, 4.0);
+
+        assert!(polygon_contains(&outline, Pos2::new(10.0, 1.5)));
+        assert!(polygon_contains(&outline, Pos2::new(10.0, -1.5)));
+        assert!(polygon_contains(&outline, Pos2::new(20.0, 10.0)));
+        // Round caps reach past the endpoints
+        assert!(polygon_contains(&outline, Pos2::new(-1.5, 0.0)));
+        assert!(!polygon_contains(&outline, Pos2::new(10.0, 3.0)));
+        assert!(!polygon_contains(&outline, Pos2::new(10.0, 10.0)));
+    }
+
+    #[test]
+    fn test_round_corners_stays_inside_the_polygon() {
+        let square = [
+            Pos2::new(0.0, 0.0),
+            Pos2::new(10.0, 0.0),
+            Pos2::new(10.0, 10.0),
+            Pos2::new(0.0, 10.0),
+        ];
+        let rounded = round_corners(&square, 2.0);
+
+        assert_eq!(rounded.len(), 4 * (ROUND_CORNER_SEGMENTS + 1));
+        assert!(
+            rounded
+                .iter()
+                .any(|point| point.distance(Pos2::new(2.0, 0.0)) < 1e-3)
+        );
+        assert!(rounded.iter().all(|point| {
+            (-1e-4..=10.0001).contains(&point.x) && (-1e-4..=10.0001).contains(&point.y)
+        }));
+        assert!(!polygon_contains(&rounded, Pos2::new(0.3, 0.3)));
+        assert!(polygon_contains(&rounded, Pos2::new(1.0, 1.0)));
+
+        // A radius larger than the edges allow is shrunk to fit
+        let capped = round_corners(&square, 100.0);
+        assert!(polygon_contains(&capped, Pos2::new(5.0, 5.0)));
+        assert_eq!(round_corners(&square, 0.0), square.to_vec());
+    }
+
+    #[test]
+    fn test_split_polyline_at_nearest_point() {
+        let points = [
+            Pos2::new(0.0, 0.0),
+            Pos2::new(10.0, 0.0),
+            Pos2::new(10.0, 10.0),
+        ];
+        let (first, second) = split_polyline_at(&points, Pos2::new(12.0, 4.0), 3.0).unwrap();
+        assert_eq!(first, vec![points[0], points[1], Pos2::new(10.0, 4.0)]);
+        assert_eq!(second, vec![Pos2::new(10.0, 4.0), points[2]]);
+
+        // Splitting at a corner keeps it in both pieces
+        let (first, second) = split_polyline_at(&points, points[1], 3.0).unwrap();
+        assert_eq!((first.len(), second.len()), (2, 2));
+
+        // Too far away, or at an end
+        assert!(split_polyline_at(&points, Pos2::new(5.0, 8.0), 3.0).is_none());
+        assert!(split_polyline_at(&points, Pos2::new(-1.0, 0.0), 3.0).is_none());
+    }
+
+    #[test]
+    fn test_split_polyline_without_crossing() {
+        let points = [Pos2::new(0.0, 0.0), Pos2::new(10.0, 0.0)];
+        let pieces = split_polyline(&points, Pos2::new(0.0, 5.0), Pos2::new(10.0, 5.0));
+        assert_eq!(pieces, vec![points.to_vec()]);
+    }
+
+    fn square(min: f32, size: f32) -> Vec<Pos2> {
+        vec![
+            Pos2::new(min, min),
+            Pos2::new(min + size, min),
+            Pos2::new(min + size, min + size),
+            Pos2::new(min, min + size),
+        ]
+    }
+
+    fn area(outlines: &[Vec<Pos2>]) -> f32 {
+        outlines
+            .iter()
+            .map(|outline| signed_area(outline) / 2.0)
+            .sum()
+    }
+
+    #[test]
+    fn test_polygon_boolean_of_overlapping_squares() {
+        let a = square(0.0, 10.0);
+        let mut b = square(5.0, 10.0);
+        // Winding direction doesn't matter
+        b.reverse();
+
+        let union = polygon_boolean(&a, &b, BooleanOp::Union);
+        assert_eq!(union.len(), 1);
+        assert!((area(&union) - 175.0).abs() < 0.01);
+        let subtract = polygon_boolean(&a, &b, BooleanOp::Subtract);
+        assert!((area(&subtract) - 75.0).abs() < 0.01);
+        assert!(!polygon_contains(&subtract[0], Pos2::new(7.0, 7.0)));
+        let intersect = polygon_boolean(&a, &b, BooleanOp::Intersect);
+        assert!((area(&intersect) - 25.0).abs() < 0.01);
+
+        // Apart, there is nothing in common and the union keeps both pieces
+        let far = square(20.0, 5.0);
+        assert!(polygon_boolean(&a, &far, BooleanOp::Intersect).is_empty());
+        assert_eq!(polygon_boolean(&a, &far, BooleanOp::Union).len(), 2);
+    }
+
+    #[test]
+    fn test_polygon_boolean_holes_and_shared_edges() {
+        // Cutting out the middle leaves a hole bridged into the outline
+        let frame = polygon_boolean(&square(0.0, 30.0), &square(10.0, 10.0), BooleanOp::Subtract);
+        assert_eq!(frame.len(), 1);
+        assert!((area(&frame) - 800.0).abs() < 0.01);
+        assert!(polygon_contains(&frame[0], Pos2::new(5.0, 5.0)));
+        assert!(!polygon_contains(&frame[0], Pos2::new(15.0, 15.0)));
+
+        // Squares side by side merge into one rectangle
+        let right = square(0.0, 10.0)
+            .into_iter()
+            .map(|point| point + egui::vec2(10.0, 0.0))
+            .collect::<Vec<_>>();
+        let merged = polygon_boolean(&square(0.0, 10.0), &right, BooleanOp::Union);
+        assert_eq!(merged.len(), 1);
+        assert!((area(&merged) - 200.0).abs() < 0.01);
+        assert!(polygon_contains(&merged[0], Pos2::new(10.0, 5.0)));
+    }
+}
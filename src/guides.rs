@@ -0,0 +1,258 @@
+//! Perspective and isometric guides drawn over the canvas
+//!
+//! Guides are lines to draw along, not part of the document: they're drawn
+//! above the elements, never exported, and kept with the preferences. With
+//! snapping on, strokes run straight from where they start along whichever
+//! guide direction there is closest to the way the pointer went.
+
+use crate::tools::ToolConfig;
+use egui::{Color32, Painter, Pos2, Rect, Stroke, Vec2};
+use serde::{Deserialize, Serialize};
+use std::any::Any;
+
+/// Angle between the lines drawn from each vanishing point, in degrees
+const RAY_SPACING_DEGREES: f32 = 10.0;
+/// Isometric grids with lines closer than this on screen, in points, aren't drawn
+const MIN_SCREEN_SPACING: f32 = 4.0;
+const GUIDE_COLOR: Color32 = Color32::from_rgba_premultiplied(0, 80, 160, 90);
+const HORIZON_COLOR: Color32 = Color32::from_rgba_premultiplied(0, 100, 200, 160);
+
+/// Which guides are drawn over the canvas
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq, Serialize, Deserialize)]
+pub enum GuideKind {
+    #[default]
+    Off,
+    OnePoint,
+    TwoPoint,
+    ThreePoint,
+    Isometric,
+}
+
+impl GuideKind {
+    pub const ALL: [GuideKind; 5] = [
+        GuideKind::Off,
+        GuideKind::OnePoint,
+        GuideKind::TwoPoint,
+        GuideKind::ThreePoint,
+        GuideKind::Isometric,
+    ];
+
+    pub fn label(self) -> &'static str {
+        match self {
+            GuideKind::Off => "Off",
+            GuideKind::OnePoint => "1-point perspective",
+            GuideKind::TwoPoint => "2-point perspective",
+            GuideKind::ThreePoint => "3-point perspective",
+            GuideKind::Isometric => "Isometric grid",
+        }
+    }
+
+    /// How many of the vanishing points the guides use
+    pub fn vanishing_points(self) -> usize {
+        match self {
+            GuideKind::Off | GuideKind::Isometric => 0,
+            GuideKind::OnePoint => 1,
+            GuideKind::TwoPoint => 2,
+            GuideKind::ThreePoint => 3,
+        }
+    }
+}
+
+/// The guide system drawn over the canvas and how strokes follow it
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
+#[serde(default)]
+pub struct Guides {
+    pub kind: GuideKind,
+    /// Vanishing points in document coordinates; one-point perspective uses
+    /// the first, two-point the first two. The first two share the horizon.
+    pub vanishing_points: [Pos2; 3],
+    /// Distance between the isometric grid's lines, measured upright
+    pub spacing: f32,
+    /// Snap strokes onto the guide direction nearest the way they're drawn
+    pub snap: bool,
+}
+
+impl Default for Guides {
+    fn default() -> Self {
+        Self {
+            kind: GuideKind::Off,
+            vanishing_points: [
+                Pos2::new(400.0, 300.0),
+                Pos2::new(1400.0, 300.0),
+                Pos2::new(400.0, 1600.0),
+            ],
+            spacing: 40.0,
+            snap: false,
+        }
+    }
+}
+
+impl Guides {
+    /// Whether strokes snap to the guides, which they do only while some are shown
+    pub fn snaps(&self) -> bool {
+        self.snap && self.kind != GuideKind::Off
+    }
+
+    /// The vanishing points in use
+    pub fn used_vanishing_points(&self) -> &[Pos2] {
+        &self.vanishing_points[..self.kind.vanishing_points()]
+    }
+
+    /// Unit directions of the guide lines through `pos`
+    pub fn directions(&self, pos: Pos2) -> Vec<Vec2> {
+        let mut directions: Vec<Vec2> = self
+            .used_vanishing_points()
+            .iter()
+            .map(|&point| point - pos)
+            .filter(|toward| toward.length() > f32::EPSILON)
+            .map(Vec2::normalized)
+            .collect();
+        match self.kind {
+            GuideKind::Off | GuideKind::ThreePoint => {}
+            GuideKind::OnePoint => directions.extend([Vec2::X, Vec2::Y]),
+            GuideKind::TwoPoint => directions.push(Vec2::Y),
+            GuideKind::Isometric => directions.extend(isometric_directions()),
+        }
+        directions
+    }
+
+    /// `to` moved onto the guide line through `from` running closest to the
+    /// way from `from` to `to`
+    pub fn snap(&self, from: Pos2, to: Pos2) -> Pos2 {
+        let offset = to - from;
+        self.directions(from)
+            .into_iter()
+            .map(|direction| direction * offset.dot(direction))
+            .max_by(|a, b| a.length_sq().total_cmp(&b.length_sq()))
+            .map_or(to, |along| from + along)
+    }
+
+    /// Draw the guides across `visible`, with lines one screen point wide at
+    /// `scale` screen points per document unit
+    pub fn paint(&self, painter: &Painter, visible: Rect, scale: f32) {
+        let stroke = Stroke::new(1.0 / scale, GUIDE_COLOR);
+        let reach = visible.size().length();
+        for &point in self.used_vanishing_points() {
+            // Long enough to cross everything shown from wherever the point is
+            let length = point.distance(visible.center()) + reach;
+            for index in 0..(360.0 / RAY_SPACING_DEGREES) as usize {
+                let angle = (index as f32 * RAY_SPACING_DEGREES).to_radians();
+                painter.line_segment([point, point + Vec2::angled(angle) * length], stroke);
+            }
+        }
+        if matches!(self.kind, GuideKind::OnePoint | GuideKind::TwoPoint) {
+            let horizon = self.vanishing_points[0].y;
+            painter.hline(
+                visible.x_range(),
+                horizon,
+                Stroke::new(1.5 / scale, HORIZON_COLOR),
+            );
+        }
+        if self.kind == GuideKind::Isometric && self.spacing * scale >= MIN_SCREEN_SPACING {
+            for direction in isometric_directions() {
+                paint_parallel_lines(painter, visible, direction, self.spacing, stroke);
+            }
+        }
+    }
+}
+
+impl ToolConfig for Guides {
+    fn tool_name(&self) -> &'static str {
+        "Guides"
+    }
+
+    fn as_any(&self) -> &dyn Any {
+        self
+    }
+
+    fn as_any_mut(&mut self) -> &mut dyn Any {
+        self
+    }
+}
+
+/// The isometric axes: rising to the right and to the left at 30°, and upright
+fn isometric_directions() -> [Vec2; 3] {
+    [
+        Vec2::angled(-30f32.to_radians()),
+        Vec2::angled(-150f32.to_radians()),
+        Vec2::Y,
+    ]
+}
+
+/// Lines along `direction` across `visible`, `spacing` apart measured upright
+/// (across, for upright lines), one of them through the document origin
+fn paint_parallel_lines(
+    painter: &Painter,
+    visible: Rect,
+    direction: Vec2,
+    spacing: f32,
+    stroke: Stroke,
+) {
+    if direction.x.abs() < f32::EPSILON {
+        // Upright lines, spaced so they meet the slanted ones at their crossings
+        let step = spacing / (2.0 * 30f32.to_radians().tan());
+        let mut x = (visible.min.x / step).ceil() * step;
+        while x <= visible.max.x {
+            painter.vline(x, visible.y_range(), stroke);
+            x += step;
+        }
+        return;
+    }
+
+    // Each line is y = slope * x + offset, with offsets a multiple of the spacing
+    let slope = direction.y / direction.x;
+    let corners = [
+        visible.left_top(),
+        visible.right_top(),
+        visible.left_bottom(),
+        visible.right_bottom(),
+    ];
+    let offset_of = |pos: Pos2| pos.y - slope * pos.x;
+    let lowest = corners
+        .into_iter()
+        .map(offset_of)
+        .fold(f32::INFINITY, f32::min);
+    let highest = corners
+        .into_iter()
+        .map(offset_of)
+        .fold(f32::NEG_INFINITY, f32::max);
+    let mut offset = (lowest / spacing).ceil() * spacing;
+    while offset <= highest {
+        let start = Pos2::new(visible.min.x, slope * visible.min.x + offset);
+        let end = Pos2::new(visible.max.x, slope * visible.max.x + offset);
+        painter.line_segment([start, end], stroke);
+        offset += spacing;
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_strokes_snap_toward_vanishing_points_and_isometric_axes() {
+        let mut guides = Guides {
+            kind: GuideKind::TwoPoint,
+            snap: true,
+            ..Guides::default()
+        };
+        let start = Pos2::new(400.0, 600.0);
+
+        // Heading up and to the left follows the line to the first point
+        let end = guides.snap(start, Pos2::new(380.0, 400.0));
+        let toward = guides.vanishing_points[0] - start;
+        assert!((end - start).normalized().dot(toward.normalized()) > 0.9999);
+        // Heading straight down stays upright
+        let end = guides.snap(start, Pos2::new(410.0, 700.0));
+        assert!((end - Pos2::new(400.0, 700.0)).length() < 0.001);
+
+        guides.kind = GuideKind::Isometric;
+        let end = guides.snap(start, start + Vec2::new(100.0, -50.0));
+        let along = end - start;
+        assert!((along.angle() + 30f32.to_radians()).abs() < 0.0001);
+
+        guides.kind = GuideKind::Off;
+        assert!(!guides.snaps());
+        assert_eq!(guides.snap(start, Pos2::new(1.0, 2.0)), Pos2::new(1.0, 2.0));
+    }
+}
@@ -1,8 +1,21 @@
 use std::sync::atomic::{AtomicUsize, Ordering};
 
-// Single static counter for all elements
-static NEXT_ELEMENT_ID: AtomicUsize = AtomicUsize::new(1);
+// Single static counter for the ids of artboards, export slices and ruler
+// guides; elements get their ids from their document with
+// `EditorModel::reserve_id`
+static NEXT_OBJECT_ID: AtomicUsize = AtomicUsize::new(1);
 
 pub fn generate_id() -> usize {
-    NEXT_ELEMENT_ID.fetch_add(1, Ordering::SeqCst)
+    NEXT_OBJECT_ID.fetch_add(1, Ordering::SeqCst)
+}
+
+/// The id [`generate_id`] hands out next
+pub fn next_id() -> usize {
+    NEXT_OBJECT_ID.load(Ordering::SeqCst)
+}
+
+/// Make sure `id` and everything before it is never generated again, e.g.
+/// after loading a document that already uses them
+pub fn reserve_past(id: usize) {
+    NEXT_OBJECT_ID.fetch_max(id + 1, Ordering::SeqCst);
 }
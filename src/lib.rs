@@ -27,19 +27,61 @@
 //! visualization is separate from logic, and the application state
 //! is modified only through well-defined commands.
 
+pub mod accessibility;
 pub mod app;
+pub mod array;
+pub mod arrowhead;
+pub mod artboard;
+pub mod assets;
+pub mod autosave;
+pub mod bug_report;
+pub mod color_replace;
 pub mod command;
+pub mod document;
 pub mod element;
+#[cfg(not(target_arch = "wasm32"))]
+pub mod encryption;
+pub mod events;
+pub mod export;
+pub mod export_slice;
 pub mod file_handler;
+pub mod geometry;
+pub mod guides;
 pub mod id_generator;
+pub mod log_console;
+pub mod memory;
+pub mod migrations;
+pub mod new_document;
 pub mod panels;
+pub mod pressure;
+pub mod profiler;
+pub mod reference_image;
 pub mod renderer;
+pub mod repeat_action;
+pub mod replay;
+pub mod ruler_guides;
+#[cfg(not(target_arch = "wasm32"))]
+pub mod screenshot;
+pub mod settings;
+pub mod share_link;
 pub mod state;
+pub mod svg_import;
+pub mod tessellation;
+#[cfg(test)]
+pub(crate) mod test_support;
+pub mod text;
 pub mod texture_manager;
 pub mod tools;
+pub mod trace;
+#[cfg(all(feature = "tray", not(target_arch = "wasm32")))]
+pub mod tray;
+pub mod tutorial;
+pub mod undo_tree;
+pub mod units;
+pub mod view;
 pub mod widgets;
 
-pub use app::PaintApp;
+pub use app::{DocumentAction, PaintApp};
 pub use command::Command;
 pub use command::CommandHistory;
 pub use element::Element;
@@ -53,4 +95,4 @@ pub use tools::UnifiedDrawStrokeTool;
 pub use tools::UnifiedSelectionTool;
 pub use tools::new_draw_stroke_tool;
 pub use tools::new_selection_tool;
-pub use widgets::{Corner, ResizeHandle};
+pub use widgets::{Corner, Edge, TransformHandle, TransformHandleStyle, TransformHandles};
@@ -0,0 +1,219 @@
+//! Log records kept in memory for the Log console and bug reports
+//!
+//! Every record also goes on to the platform logger: stderr natively (see
+//! `RUST_LOG`) and the browser console on the web. Records keep their level,
+//! module and key-value fields apart, so the console can filter on them.
+
+use std::collections::VecDeque;
+use std::sync::{Mutex, OnceLock};
+use web_time::Instant;
+
+/// Number of recent records kept
+pub const MAX_LOG_RECORDS: usize = 2000;
+/// Prefix of the modules of this crate, whose debug records are kept too
+const CRATE_TARGET: &str = env!("CARGO_CRATE_NAME");
+
+static RECORDS: Mutex<VecDeque<LogRecord>> = Mutex::new(VecDeque::new());
+static STARTED: OnceLock<Instant> = OnceLock::new();
+
+/// One logged message, with its key-value fields
+#[derive(Clone, Debug, PartialEq)]
+pub struct LogRecord {
+    /// Seconds since logging started
+    pub time: f64,
+    pub level: log::Level,
+    /// Module the record came from, like `eframe_paint::command`
+    pub target: String,
+    pub message: String,
+    /// Key-value fields, in the order they were given
+    pub fields: Vec<(String, String)>,
+}
+
+impl LogRecord {
+    /// The record as one line of text, like
+    /// `12.345 DEBUG eframe_paint::command Executing command command="Move Element"`
+    pub fn format(&self) -> String {
+        let mut line = format!(
+            "{:.3} {:<5} {} {}",
+            self.time, self.level, self.target, self.message
+        );
+        for (key, value) in &self.fields {
+            line.push_str(&format!(" {}={:?}", key, value));
+        }
+        line
+    }
+
+    /// Whether the record passes the console's filters: at `level` or more
+    /// severe, from a module whose path contains `module`
+    pub fn matches(&self, level: log::LevelFilter, module: &str) -> bool {
+        self.level <= level && self.target.contains(module)
+    }
+}
+
+/// Collects a record's key-value fields as text
+struct FieldCollector<'a>(&'a mut Vec<(String, String)>);
+
+impl<'kvs> log::kv::VisitSource<'kvs> for FieldCollector<'_> {
+    fn visit_pair(
+        &mut self,
+        key: log::kv::Key<'kvs>,
+        value: log::kv::Value<'kvs>,
+    ) -> Result<(), log::kv::Error> {
+        self.0.push((key.to_string(), value.to_string()));
+        Ok(())
+    }
+}
+
+/// Logger keeping recent records and passing everything on
+///
+/// Info and above are always kept, and debug records from this crate,
+/// whatever the wrapped logger's filter lets through.
+struct ConsoleLogger<L> {
+    inner: L,
+}
+
+impl<L> ConsoleLogger<L> {
+    fn keeps(metadata: &log::Metadata<'_>) -> bool {
+        metadata.level() <= log::Level::Info
+            || (metadata.level() <= log::Level::Debug
+                && metadata.target().starts_with(CRATE_TARGET))
+    }
+}
+
+impl<L: log::Log> log::Log for ConsoleLogger<L> {
+    fn enabled(&self, metadata: &log::Metadata<'_>) -> bool {
+        Self::keeps(metadata) || self.inner.enabled(metadata)
+    }
+
+    fn log(&self, record: &log::Record<'_>) {
+        if Self::keeps(record.metadata()) {
+            let mut fields = Vec::new();
+            record
+                .key_values()
+                .visit(&mut FieldCollector(&mut fields))
+                .ok();
+            push_record(LogRecord {
+                time: STARTED.get_or_init(Instant::now).elapsed().as_secs_f64(),
+                level: record.level(),
+                target: record.target().to_string(),
+                message: record.args().to_string(),
+                fields,
+            });
+        }
+        if self.inner.enabled(record.metadata()) {
+            self.inner.log(record);
+        }
+    }
+
+    fn flush(&self) {
+        self.inner.flush();
+    }
+}
+
+/// Log to stderr as `env_logger` does (see `RUST_LOG`), keeping recent records
+/// for the Log console and bug reports
+#[cfg(not(target_arch = "wasm32"))]
+pub fn init_logging() {
+    let inner = env_logger::Builder::from_default_env().build();
+    let max_level = inner.filter().max(log::LevelFilter::Debug);
+    install(ConsoleLogger { inner }, max_level);
+}
+
+/// Log to the browser console, keeping recent records for the Log console and
+/// bug reports
+#[cfg(target_arch = "wasm32")]
+pub fn init_logging() {
+    let inner = eframe::WebLogger::new(log::LevelFilter::Debug);
+    install(ConsoleLogger { inner }, log::LevelFilter::Debug);
+}
+
+fn install<L: log::Log + 'static>(logger: ConsoleLogger<L>, max_level: log::LevelFilter) {
+    STARTED.get_or_init(Instant::now);
+    if log::set_boxed_logger(Box::new(logger)).is_ok() {
+        log::set_max_level(max_level);
+    }
+}
+
+/// Keep `record`, dropping the oldest past [`MAX_LOG_RECORDS`]
+pub(crate) fn push_record(record: LogRecord) {
+    if let Ok(mut records) = RECORDS.lock() {
+        if records.len() == MAX_LOG_RECORDS {
+            records.pop_front();
+        }
+        records.push_back(record);
+    }
+}
+
+/// The kept records, oldest first
+pub fn records() -> Vec<LogRecord> {
+    RECORDS
+        .lock()
+        .map(|records| records.iter().cloned().collect())
+        .unwrap_or_default()
+}
+
+/// Forget the kept records, e.g. to watch what one action logs
+pub fn clear_records() {
+    if let Ok(mut records) = RECORDS.lock() {
+        records.clear();
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use log::Log;
+
+    struct Discard;
+
+    impl log::Log for Discard {
+        fn enabled(&self, _: &log::Metadata<'_>) -> bool {
+            false
+        }
+        fn log(&self, _: &log::Record<'_>) {}
+        fn flush(&self) {}
+    }
+
+    #[test]
+    fn test_records_keep_their_fields_and_filter_by_level_and_module() {
+        let logger = ConsoleLogger { inner: Discard };
+        let fields: &[(&str, log::kv::Value<'_>)] = &[("command", "Move Element".into())];
+        let target = format!("{}::command", CRATE_TARGET);
+        logger.log(
+            &log::Record::builder()
+                .level(log::Level::Debug)
+                .target(&target)
+                .args(format_args!("Executing command"))
+                .key_values(&fields)
+                .build(),
+        );
+        // Debug records from other crates aren't kept
+        logger.log(
+            &log::Record::builder()
+                .level(log::Level::Debug)
+                .target("winit::window")
+                .args(format_args!("Resized"))
+                .build(),
+        );
+
+        let kept: Vec<LogRecord> = records()
+            .into_iter()
+            .filter(|record| record.message == "Executing command" || record.message == "Resized")
+            .collect();
+        let [record] = &kept[..] else {
+            panic!("expected one record, got {:?}", kept);
+        };
+        assert_eq!(
+            record.fields,
+            [("command".to_string(), "Move Element".to_string())]
+        );
+        assert!(
+            record
+                .format()
+                .ends_with(r#"Executing command command="Move Element""#)
+        );
+        assert!(record.matches(log::LevelFilter::Debug, "command"));
+        assert!(!record.matches(log::LevelFilter::Info, "command"));
+        assert!(!record.matches(log::LevelFilter::Trace, "renderer"));
+    }
+}
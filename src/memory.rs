@@ -0,0 +1,313 @@
+//! Estimated memory use of the document, texture cache and undo history, for
+//! the diagnostics window
+//!
+//! Estimates count the bulk data each part holds (points, encoded and decoded
+//! image bytes, texture pixels) plus the fixed size of each item. Allocator
+//! overhead and spare capacity are left out, so real use runs somewhat higher.
+
+use crate::artboard::Artboard;
+use crate::command::{Command, CommandHistory};
+use crate::document::DocumentFile;
+use crate::element::{ElementId, ElementType};
+use crate::export_slice::ExportSlice;
+use crate::ruler_guides::RulerGuide;
+use crate::state::EditorModel;
+use crate::undo_tree::Branch;
+use egui::{Color32, Pos2};
+use std::mem::{size_of, size_of_val};
+
+/// Undo steps kept when the history is compacted
+pub const COMPACT_HISTORY_STEPS: usize = 10;
+
+/// What a set of elements holds
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub struct ElementMemory {
+    pub count: usize,
+    /// Stroke and shape points, and path anchors and handles
+    pub points: usize,
+    /// Encoded image data, decoded pixels kept around, and pattern fills
+    pub image_bytes: usize,
+    /// Everything, points and images included
+    pub bytes: usize,
+}
+
+impl ElementMemory {
+    pub fn of(element: &ElementType) -> Self {
+        let (points, point_bytes, image_bytes) = match element {
+            ElementType::Stroke(stroke) => {
+                let points = stroke.points();
+                (points.len(), size_of_val(points), 0)
+            }
+            ElementType::Path(path) => {
+                let nodes = path.nodes();
+                (nodes.len() * 3, size_of_val(nodes), 0)
+            }
+            ElementType::Shape(shape) => {
+                let points = shape.points();
+                let pattern = shape
+                    .pattern()
+                    .map_or(0, |pattern| pattern.image_data.len());
+                (points.len(), size_of_val(points), pattern)
+            }
+            ElementType::Image(image) => (0, 0, image.original_data().len() + image.decoded_len()),
+            ElementType::StepBadge(_) => (0, 0, 0),
+            // The text counts toward the total like points do
+            ElementType::StickyNote(note) => (0, note.text().len(), 0),
+        };
+        Self {
+            count: 1,
+            points,
+            image_bytes,
+            bytes: size_of::<ElementType>() + point_bytes + image_bytes,
+        }
+    }
+
+    pub fn of_all<'a>(elements: impl IntoIterator<Item = &'a ElementType>) -> Self {
+        elements
+            .into_iter()
+            .map(Self::of)
+            .fold(Self::default(), |total, element| Self {
+                count: total.count + element.count,
+                points: total.points + element.points,
+                image_bytes: total.image_bytes + element.image_bytes,
+                bytes: total.bytes + element.bytes,
+            })
+    }
+}
+
+/// Estimated size of an undo step, including the elements and pixels it keeps
+/// for undoing and redoing
+pub fn command_bytes(command: &Command) -> usize {
+    let ids = |ids: &[ElementId]| size_of_val(ids);
+    let heap = match command {
+        Command::AddElement { element }
+        | Command::RemoveElement {
+            old_element: element,
+            ..
+        } => ElementMemory::of(element).bytes,
+        Command::ReplaceElements { removed, added, .. } => {
+            ElementMemory::of_all(removed.iter().chain(added)).bytes
+        }
+        Command::FlattenTransforms {
+            old_elements,
+            new_elements,
+        } => ElementMemory::of_all(old_elements.iter().chain(new_elements)).bytes,
+        Command::RenameElement {
+            old_name, new_name, ..
+        } => old_name.len() + new_name.len(),
+        Command::SetStickyNote {
+            old_text, new_text, ..
+        } => old_text.len() + new_text.len(),
+        Command::SetDocumentProperties {
+            old_properties,
+            new_properties,
+        } => {
+            (old_properties.artboards.len() + new_properties.artboards.len())
+                * size_of::<Artboard>()
+        }
+        Command::SetReferenceImage {
+            old_reference,
+            new_reference,
+        } => [old_reference, new_reference]
+            .into_iter()
+            .flatten()
+            .map(|reference| reference.data().len())
+            .sum(),
+        Command::SetArtboards {
+            old_artboards,
+            new_artboards,
+            ..
+        } => (old_artboards.len() + new_artboards.len()) * size_of::<Artboard>(),
+        Command::SetExportSlices {
+            old_slices,
+            new_slices,
+            ..
+        } => (old_slices.len() + new_slices.len()) * size_of::<ExportSlice>(),
+        Command::SetRulerGuides {
+            old_guides,
+            new_guides,
+            ..
+        } => (old_guides.len() + new_guides.len()) * size_of::<RulerGuide>(),
+        Command::MoveArtboard { element_ids, .. } => ids(element_ids),
+        Command::SetStrokePoints {
+            element_ids,
+            old_points,
+            new_points,
+            ..
+        } => {
+            let points: usize = old_points.iter().chain(new_points).map(Vec::len).sum();
+            ids(element_ids) + points * size_of::<Pos2>()
+        }
+        Command::SetPatternFill {
+            old_pattern,
+            new_pattern,
+            ..
+        } => [old_pattern, new_pattern]
+            .into_iter()
+            .flatten()
+            .map(|pattern| pattern.image_data.len())
+            .sum(),
+        Command::PaintPixels { before, after, .. } => before.rgba.len() + after.rgba.len(),
+        Command::SetStepNumbers {
+            element_ids,
+            old_numbers,
+            new_numbers,
+            ..
+        } => ids(element_ids) + (old_numbers.len() + new_numbers.len()) * size_of::<u32>(),
+        Command::SetColors {
+            element_ids,
+            old_colors,
+            new_colors,
+            ..
+        } => ids(element_ids) + (old_colors.len() + new_colors.len()) * size_of::<Color32>(),
+        Command::Batch { commands, .. } => commands.iter().map(command_bytes).sum(),
+        Command::RestoreDocument {
+            old_document,
+            new_document,
+            ..
+        } => document_bytes(old_document) + document_bytes(new_document),
+        Command::ClearSelection { previous_selection } => {
+            previous_selection.len() * size_of::<ElementId>()
+        }
+        Command::MoveElement { .. }
+        | Command::ResizeElement { .. }
+        | Command::SetDocumentMetadata { .. }
+        | Command::SetDocumentUnits { .. }
+        | Command::SetPathNode { .. }
+        | Command::SetPolygon { .. }
+        | Command::SetStrokeStyle { .. }
+        | Command::SetTransform { .. }
+        | Command::SelectElement(_)
+        | Command::DeselectElement(_)
+        | Command::ToggleSelection(_) => 0,
+    };
+    size_of::<Command>() + heap
+}
+
+/// Estimated bytes a document held apart from the model takes, e.g. a checkpoint
+pub fn document_bytes(document: &DocumentFile) -> usize {
+    let reference = document
+        .reference_image
+        .as_ref()
+        .map_or(0, |reference| reference.data().len());
+    ElementMemory::of_all(&document.elements).bytes
+        + reference
+        + document.artboards.len() * size_of::<Artboard>()
+        + document.slices.len() * size_of::<ExportSlice>()
+        + document.ruler_guides.len() * size_of::<RulerGuide>()
+}
+
+/// Estimated memory of everything the diagnostics window reports on
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub struct MemoryUsage {
+    pub elements: ElementMemory,
+    pub reference_image_bytes: usize,
+    /// Pixels of the cached element textures
+    pub texture_bytes: usize,
+    /// Undo and redo steps
+    pub history_steps: usize,
+    /// The steps, with branches and checkpoints
+    pub history_bytes: usize,
+}
+
+impl MemoryUsage {
+    /// Measure the document and history; textures are measured by the renderer
+    pub fn measure(
+        editor_model: &EditorModel,
+        history: &CommandHistory,
+        texture_bytes: usize,
+    ) -> Self {
+        let steps = || history.undo_stack().iter().chain(history.redo_stack());
+        Self {
+            elements: ElementMemory::of_all(&editor_model.elements),
+            reference_image_bytes: editor_model
+                .reference_image
+                .as_ref()
+                .map_or(0, |reference| reference.data().len()),
+            texture_bytes,
+            history_steps: steps().count()
+                + history
+                    .branches()
+                    .iter()
+                    .map(Branch::total_len)
+                    .sum::<usize>(),
+            // Branches are measured as stored, packed
+            history_bytes: steps().map(command_bytes).sum::<usize>()
+                + history
+                    .branches()
+                    .iter()
+                    .map(Branch::total_bytes)
+                    .sum::<usize>()
+                + history
+                    .checkpoints()
+                    .iter()
+                    .map(|checkpoint| document_bytes(&checkpoint.document))
+                    .sum::<usize>(),
+        }
+    }
+
+    pub fn total_bytes(&self) -> usize {
+        self.elements.bytes + self.reference_image_bytes + self.texture_bytes + self.history_bytes
+    }
+}
+
+/// A byte count in the largest unit that keeps it at one or more, e.g. "2.5 MB"
+pub fn format_bytes(bytes: usize) -> String {
+    const UNITS: [&str; 4] = ["KB", "MB", "GB", "TB"];
+    if bytes < 1024 {
+        return format!("{} B", bytes);
+    }
+    let mut value = bytes as f64 / 1024.0;
+    let mut unit = 0;
+    while value >= 1024.0 && unit < UNITS.len() - 1 {
+        value /= 1024.0;
+        unit += 1;
+    }
+    format!("{:.1} {}", value, UNITS[unit])
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::element::factory;
+    use egui::{Color32, Vec2};
+
+    #[test]
+    fn test_memory_counts_elements_and_history() {
+        let points = vec![Pos2::ZERO, Pos2::new(10.0, 0.0), Pos2::new(10.0, 10.0)];
+        let stroke = factory::create_stroke(ElementId::new(1), points, 2.0, Color32::BLACK);
+        let image = factory::create_image(
+            ElementId::new(2),
+            vec![0; 1000],
+            Vec2::new(10.0, 10.0),
+            Pos2::ZERO,
+        );
+
+        let mut editor_model = EditorModel::new();
+        let mut history = CommandHistory::new();
+        for element in [stroke, image] {
+            history
+                .execute(Command::AddElement { element }, &mut editor_model)
+                .unwrap();
+        }
+
+        let usage = MemoryUsage::measure(&editor_model, &history, 400);
+        assert_eq!(usage.elements.count, 2);
+        assert_eq!(usage.elements.points, 3);
+        assert_eq!(usage.elements.image_bytes, 1000);
+        assert_eq!(usage.history_steps, 2);
+        // The history keeps its own copy of each element
+        assert_eq!(
+            usage.history_bytes,
+            2 * size_of::<Command>() + usage.elements.bytes
+        );
+        assert_eq!(
+            usage.total_bytes(),
+            2 * usage.elements.bytes + 2 * size_of::<Command>() + 400
+        );
+
+        assert_eq!(format_bytes(512), "512 B");
+        assert_eq!(format_bytes(2560), "2.5 KB");
+        assert_eq!(format_bytes(3 * 1024 * 1024), "3.0 MB");
+    }
+}
@@ -0,0 +1,99 @@
+//! Bringing documents saved by older versions of the app up to date
+//!
+//! Every saved document records the format version it was written in; files
+//! from before the version was recorded read as version 0. Loading runs the
+//! migrations from the file's version up to `FORMAT_VERSION` in order, each
+//! taking the document one version forward.
+//!
+//! New fields with a sensible default only need `#[serde(default)]`. A new
+//! version and migration are for changes in what stored data means, so old
+//! files still load the way they looked when saved.
+
+use crate::document::DocumentFile;
+use crate::element::{Element, ElementType};
+
+/// The format version documents are saved in
+pub const FORMAT_VERSION: u32 = 1;
+
+/// Migrations in order; the one at index `n` takes version `n` to `n + 1`
+const MIGRATIONS: [fn(&mut DocumentFile); FORMAT_VERSION as usize] = [background_shape_to_color];
+
+/// Bring `file`, read as written in `version`, up to `FORMAT_VERSION`
+///
+/// Versions newer than `FORMAT_VERSION` must be turned away before this.
+pub fn migrate(file: &mut DocumentFile, version: u32) {
+    for migration in MIGRATIONS.iter().skip(version as usize) {
+        migration(file);
+    }
+    file.version = FORMAT_VERSION;
+}
+
+/// Version 0 to 1: the New Document window used to paint the canvas color as
+/// a locked shape named "Background" covering the canvas, where documents now
+/// have a background color of their own
+fn background_shape_to_color(file: &mut DocumentFile) {
+    let [canvas] = file.artboards.as_slice() else {
+        return;
+    };
+    let Some(ElementType::Shape(shape)) = file.elements.first() else {
+        return;
+    };
+    let is_background = file.background.is_none()
+        && shape.name() == "Background"
+        && shape.metadata().is_locked()
+        && shape.pattern().is_none()
+        && shape.points().len() == 4
+        && shape.rect() == canvas.rect;
+    if is_background {
+        file.background = Some(shape.fill());
+        file.elements.remove(0);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::artboard::Artboard;
+    use crate::element::{ElementId, LOCKED_PROPERTY, factory};
+    use egui::{Color32, Pos2, Rect, vec2};
+
+    #[test]
+    fn test_background_shape_becomes_document_background() {
+        let canvas = Rect::from_min_size(Pos2::new(10.0, 10.0), vec2(200.0, 100.0));
+        let outline = vec![
+            canvas.left_top(),
+            canvas.right_top(),
+            canvas.right_bottom(),
+            canvas.left_bottom(),
+        ];
+        let mut background = factory::create_shape(ElementId::new(1), outline, Color32::KHAKI);
+        let metadata = background.metadata_mut();
+        metadata.name = "Background".to_string();
+        metadata.set(LOCKED_PROPERTY, "true");
+        let stroke = factory::create_stroke(
+            ElementId::new(2),
+            vec![Pos2::new(20.0, 20.0), Pos2::new(40.0, 40.0)],
+            2.0,
+            Color32::BLACK,
+        );
+        let old = DocumentFile {
+            version: 0,
+            artboards: vec![Artboard::new("Canvas", canvas)],
+            elements: vec![background, stroke],
+            ..DocumentFile::default()
+        };
+
+        let mut file = old.clone();
+        migrate(&mut file, 0);
+        assert_eq!(file.version, FORMAT_VERSION);
+        assert_eq!(file.background, Some(Color32::KHAKI));
+        assert_eq!(file.elements.len(), 1);
+        assert_eq!(file.elements[0].id(), ElementId::new(2));
+
+        // Current files are left as they are, shape and all
+        let mut file = old.clone();
+        migrate(&mut file, FORMAT_VERSION);
+        assert_eq!(file.background, None);
+        assert_eq!(file.elements.len(), 2);
+    }
+}
@@ -0,0 +1,334 @@
+//! Starting documents from a canvas size, background and template
+//!
+//! The New Document dialog fills in [`NewDocumentSettings`] and
+//! [`new_document`] turns them into the document to start from. Everything a
+//! template adds is locked, so it stays put underneath the drawing.
+
+use crate::artboard::Artboard;
+use crate::document::DocumentFile;
+use crate::element::{Element, ElementType, LOCKED_PROPERTY, factory};
+use crate::state::EditorModel;
+use crate::units::{DEFAULT_DPI, DocumentUnits, Unit, in_to_px, mm_to_px};
+use egui::{Color32, Pos2, Rect, Vec2, vec2};
+
+/// Spacing of grid paper lines, in millimeters
+const GRID_SPACING_MM: f32 = 5.0;
+/// Every this many grid lines is drawn darker
+const GRID_MAJOR_EVERY: usize = 5;
+const GRID_COLOR: Color32 = Color32::from_rgb(200, 220, 240);
+const GRID_MAJOR_COLOR: Color32 = Color32::from_rgb(150, 185, 220);
+/// Storyboard frames across and down the page
+const STORYBOARD_COLUMNS: usize = 2;
+const STORYBOARD_ROWS: usize = 3;
+const STORYBOARD_COLOR: Color32 = Color32::from_gray(60);
+
+/// Canvas sizes to start from
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum CanvasPreset {
+    /// No artboard; the canvas grows with the content
+    Unbounded,
+    A4,
+    A5,
+    Letter,
+    /// 1920×1080 pixels
+    Screen,
+    /// 1080×1080 pixels
+    Square,
+    /// 1080×1920 pixels
+    Phone,
+    Custom,
+}
+
+impl CanvasPreset {
+    pub const ALL: [CanvasPreset; 8] = [
+        CanvasPreset::Unbounded,
+        CanvasPreset::A4,
+        CanvasPreset::A5,
+        CanvasPreset::Letter,
+        CanvasPreset::Screen,
+        CanvasPreset::Square,
+        CanvasPreset::Phone,
+        CanvasPreset::Custom,
+    ];
+
+    pub fn label(self) -> &'static str {
+        match self {
+            CanvasPreset::Unbounded => "Unbounded",
+            CanvasPreset::A4 => "A4 (210 × 297 mm)",
+            CanvasPreset::A5 => "A5 (148 × 210 mm)",
+            CanvasPreset::Letter => "Letter (8.5 × 11 in)",
+            CanvasPreset::Screen => "Screen (1920 × 1080 px)",
+            CanvasPreset::Square => "Square (1080 × 1080 px)",
+            CanvasPreset::Phone => "Phone (1080 × 1920 px)",
+            CanvasPreset::Custom => "Custom",
+        }
+    }
+
+    /// Size of the canvas in pixels at `dpi`, or None where the preset doesn't
+    /// fix one
+    ///
+    /// Paper sizes depend on the resolution; screen sizes are in pixels.
+    pub fn size(self, dpi: f32) -> Option<Vec2> {
+        match self {
+            CanvasPreset::Unbounded | CanvasPreset::Custom => None,
+            CanvasPreset::A4 => Some(vec2(mm_to_px(210.0, dpi), mm_to_px(297.0, dpi))),
+            CanvasPreset::A5 => Some(vec2(mm_to_px(148.0, dpi), mm_to_px(210.0, dpi))),
+            CanvasPreset::Letter => Some(vec2(in_to_px(8.5, dpi), in_to_px(11.0, dpi))),
+            CanvasPreset::Screen => Some(vec2(1920.0, 1080.0)),
+            CanvasPreset::Square => Some(vec2(1080.0, 1080.0)),
+            CanvasPreset::Phone => Some(vec2(1080.0, 1920.0)),
+        }
+    }
+
+    /// The unit the preset is measured in, for the new document's rulers
+    pub fn unit(self) -> Unit {
+        match self {
+            CanvasPreset::A4 | CanvasPreset::A5 => Unit::Mm,
+            CanvasPreset::Letter => Unit::In,
+            _ => Unit::Px,
+        }
+    }
+}
+
+/// Locked guides to draw over
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Template {
+    Blank,
+    /// Light lines every 5 mm, darker every 25 mm
+    GridPaper,
+    /// Numbered frames with a caption line under each
+    Storyboard,
+}
+
+impl Template {
+    pub const ALL: [Template; 3] = [Template::Blank, Template::GridPaper, Template::Storyboard];
+
+    pub fn label(self) -> &'static str {
+        match self {
+            Template::Blank => "Blank",
+            Template::GridPaper => "Grid paper",
+            Template::Storyboard => "Storyboard frames",
+        }
+    }
+}
+
+/// What the New Document dialog asks for
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct NewDocumentSettings {
+    pub preset: CanvasPreset,
+    /// Canvas size in pixels; ignored for an unbounded canvas
+    pub size: Vec2,
+    pub dpi: f32,
+    /// Canvas color for the document, or None to use the one in the preferences
+    pub background: Option<Color32>,
+    pub template: Template,
+}
+
+impl Default for NewDocumentSettings {
+    fn default() -> Self {
+        Self {
+            preset: CanvasPreset::Unbounded,
+            size: vec2(800.0, 600.0),
+            dpi: DEFAULT_DPI,
+            background: None,
+            template: Template::Blank,
+        }
+    }
+}
+
+impl NewDocumentSettings {
+    /// Choose `preset`, taking its size at the current resolution
+    pub fn set_preset(&mut self, preset: CanvasPreset) {
+        self.preset = preset;
+        if let Some(size) = preset.size(self.dpi) {
+            self.size = size;
+        }
+    }
+
+    /// Change the resolution, resizing paper presets to match
+    pub fn set_dpi(&mut self, dpi: f32) {
+        self.dpi = dpi;
+        self.set_preset(self.preset);
+    }
+
+    /// Whether the canvas has a fixed size that a template can fill
+    pub fn is_bounded(&self) -> bool {
+        self.preset != CanvasPreset::Unbounded
+    }
+}
+
+/// A new document made from `settings`, with its canvas' top-left corner at `origin`
+pub fn new_document(settings: &NewDocumentSettings, origin: Pos2) -> DocumentFile {
+    let units = DocumentUnits {
+        unit: settings.preset.unit(),
+        dpi: settings.dpi,
+    };
+    if !settings.is_bounded() {
+        return DocumentFile {
+            units,
+            background: settings.background,
+            ..DocumentFile::default()
+        };
+    }
+
+    let canvas = Rect::from_min_size(origin, settings.size.max(Vec2::splat(1.0)));
+    // Number the elements the way a new document would
+    let ids = EditorModel::new();
+    let elements = match settings.template {
+        Template::Blank => Vec::new(),
+        Template::GridPaper => grid_paper(canvas, settings.dpi, &ids),
+        Template::Storyboard => storyboard(canvas, &ids),
+    };
+
+    DocumentFile {
+        units,
+        artboards: vec![Artboard::new("Canvas", canvas)],
+        elements,
+        background: settings.background,
+        ..DocumentFile::default()
+    }
+}
+
+fn locked(mut element: ElementType, name: &str) -> ElementType {
+    let metadata = element.metadata_mut();
+    metadata.name = name.to_string();
+    metadata.set(LOCKED_PROPERTY, "true");
+    element
+}
+
+/// Lines across and down `canvas` every 5 mm at `dpi`
+fn grid_paper(canvas: Rect, dpi: f32, ids: &EditorModel) -> Vec<ElementType> {
+    let spacing = mm_to_px(GRID_SPACING_MM, dpi);
+    let line = |from: Pos2, to: Pos2, index: usize| {
+        let color = if index % GRID_MAJOR_EVERY == 0 {
+            GRID_MAJOR_COLOR
+        } else {
+            GRID_COLOR
+        };
+        locked(
+            factory::create_stroke(ids.reserve_id(), vec![from, to], 1.0, color),
+            "Grid line",
+        )
+    };
+
+    // Lines falling on the far edges, give or take rounding, are left out
+    let end = canvas.max - Vec2::splat(spacing * 0.1);
+    let columns = (canvas.width() / spacing).floor() as usize;
+    let rows = (canvas.height() / spacing).floor() as usize;
+    let vertical = (1..=columns).filter_map(|index| {
+        let x = canvas.min.x + index as f32 * spacing;
+        (x < end.x).then(|| {
+            line(
+                Pos2::new(x, canvas.min.y),
+                Pos2::new(x, canvas.max.y),
+                index,
+            )
+        })
+    });
+    let horizontal = (1..=rows).filter_map(|index| {
+        let y = canvas.min.y + index as f32 * spacing;
+        (y < end.y).then(|| {
+            line(
+                Pos2::new(canvas.min.x, y),
+                Pos2::new(canvas.max.x, y),
+                index,
+            )
+        })
+    });
+    vertical.chain(horizontal).collect()
+}
+
+/// Frames in a grid over `canvas`, each with a caption line below it
+fn storyboard(canvas: Rect, ids: &EditorModel) -> Vec<ElementType> {
+    let margin = canvas.width().min(canvas.height()) * 0.05;
+    let area = canvas.shrink(margin);
+    let cell = vec2(
+        area.width() / STORYBOARD_COLUMNS as f32,
+        area.height() / STORYBOARD_ROWS as f32,
+    );
+    let gap = margin / 2.0;
+    // Frames keep a quarter of their cell free for the caption
+    let frame_size = vec2(cell.x - gap, (cell.y - gap) * 0.75);
+    let thickness = (margin / 20.0).max(1.0);
+
+    let mut elements = Vec::new();
+    for row in 0..STORYBOARD_ROWS {
+        for column in 0..STORYBOARD_COLUMNS {
+            let number = row * STORYBOARD_COLUMNS + column + 1;
+            let min = area.min
+                + vec2(column as f32 * cell.x, row as f32 * cell.y)
+                + Vec2::splat(gap / 2.0);
+            let frame = Rect::from_min_size(min, frame_size);
+            let outline = vec![
+                frame.left_top(),
+                frame.right_top(),
+                frame.right_bottom(),
+                frame.left_bottom(),
+                frame.left_top(),
+            ];
+            elements.push(locked(
+                factory::create_stroke(ids.reserve_id(), outline, thickness, STORYBOARD_COLOR),
+                &format!("Frame {}", number),
+            ));
+
+            let caption_y = frame.max.y + (cell.y - gap - frame_size.y) / 2.0;
+            elements.push(locked(
+                factory::create_stroke(
+                    ids.reserve_id(),
+                    vec![
+                        Pos2::new(frame.min.x, caption_y),
+                        Pos2::new(frame.max.x, caption_y),
+                    ],
+                    (thickness / 2.0).max(1.0),
+                    STORYBOARD_COLOR,
+                ),
+                &format!("Caption {}", number),
+            ));
+        }
+    }
+    elements
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_new_document_from_preset_and_template() {
+        let mut settings = NewDocumentSettings::default();
+        let blank = new_document(&settings, Pos2::ZERO);
+        assert!(blank.artboards.is_empty() && blank.elements.is_empty());
+
+        settings.set_preset(CanvasPreset::A4);
+        settings.set_dpi(300.0);
+        assert_eq!(settings.size.round(), vec2(2480.0, 3508.0));
+        settings.background = Some(Color32::WHITE);
+        settings.template = Template::Storyboard;
+
+        let origin = Pos2::new(20.0, 30.0);
+        let file = new_document(&settings, origin);
+        assert_eq!(file.units.unit, Unit::Mm);
+        assert_eq!(file.units.dpi, 300.0);
+        let canvas = file.artboards[0].rect;
+        assert_eq!(canvas.min, origin);
+        assert_eq!(canvas.size(), settings.size);
+
+        assert_eq!(file.background, Some(Color32::WHITE));
+
+        // A frame and caption per storyboard cell
+        assert_eq!(
+            file.elements.len(),
+            2 * STORYBOARD_COLUMNS * STORYBOARD_ROWS
+        );
+        assert_eq!(file.elements[0].name(), "Frame 1");
+        assert!(file.elements.iter().all(|element| {
+            element.metadata().is_locked() && canvas.contains_rect(element.rect())
+        }));
+
+        settings.template = Template::GridPaper;
+        settings.background = None;
+        let file = new_document(&settings, origin);
+        // 210 × 297 mm at 5 mm spacing, without lines on the edges
+        assert_eq!(file.elements.len(), 41 + 59);
+    }
+}
@@ -0,0 +1,127 @@
+use crate::array::{ArrayLayout, ArrayParams, MAX_ARRAY_ITEMS};
+use crate::state::EditorModel;
+
+/// Gap between the items of a new array, so they start side by side
+const ARRAY_GAP: f32 = 10.0;
+
+/// Array window: lay out copies of the selection along a line, in a grid or
+/// around a circle
+///
+/// The copies are previewed on the canvas while the window is open.
+#[derive(Default)]
+pub struct ArrayDialog {
+    pub open: bool,
+    params: ArrayParams,
+}
+
+impl ArrayDialog {
+    /// Open the window on the current selection, spacing the items by its size
+    ///
+    /// Does nothing without a selection.
+    pub fn open_for(&mut self, editor_model: &EditorModel) {
+        let Some(bounds) = editor_model.selection_rect() else {
+            return;
+        };
+        let step = bounds.size() + egui::Vec2::splat(ARRAY_GAP);
+        self.open = true;
+        self.params.spacing = match self.params.layout {
+            ArrayLayout::Line => egui::vec2(step.x, 0.0),
+            _ => step,
+        };
+        self.params.radius = bounds.size().max_elem().max(ARRAY_GAP) * 2.0;
+    }
+
+    /// The array to preview, while the window is open
+    pub fn preview(&self) -> Option<&ArrayParams> {
+        self.open.then_some(&self.params)
+    }
+
+    /// Show the Array window if it is open
+    ///
+    /// Returns the array to add when the user applies it.
+    pub fn show(&mut self, ctx: &egui::Context) -> Option<ArrayParams> {
+        let mut open = self.open;
+        let mut apply = false;
+        let mut cancel = false;
+        egui::Window::new("Array")
+            .open(&mut open)
+            .resizable(false)
+            .show(ctx, |ui| {
+                egui::Grid::new("array_grid")
+                    .num_columns(2)
+                    .show(ui, |ui| self.params_ui(ui));
+                ui.separator();
+                ui.horizontal(|ui| {
+                    apply = ui.button("Apply").clicked();
+                    cancel = ui.button("Cancel").clicked();
+                });
+            });
+
+        self.open = open && !apply && !cancel;
+        apply.then_some(self.params)
+    }
+
+    fn params_ui(&mut self, ui: &mut egui::Ui) {
+        let params = &mut self.params;
+
+        ui.label("Layout");
+        ui.horizontal(|ui| {
+            for layout in ArrayLayout::ALL {
+                ui.selectable_value(&mut params.layout, layout, layout.label());
+            }
+        });
+        ui.end_row();
+
+        match params.layout {
+            ArrayLayout::Line | ArrayLayout::Circle => {
+                ui.label("Items");
+                ui.add(egui::DragValue::new(&mut params.count).range(2..=MAX_ARRAY_ITEMS))
+                    .on_hover_text("How many in all, the original included");
+                ui.end_row();
+            }
+            ArrayLayout::Grid => {
+                ui.label("Columns × rows");
+                ui.horizontal(|ui| {
+                    ui.add(egui::DragValue::new(&mut params.columns).range(1..=MAX_ARRAY_ITEMS));
+                    ui.label("×");
+                    ui.add(egui::DragValue::new(&mut params.rows).range(1..=MAX_ARRAY_ITEMS));
+                });
+                ui.end_row();
+            }
+        }
+
+        match params.layout {
+            ArrayLayout::Line | ArrayLayout::Grid => {
+                ui.label("Spacing");
+                ui.horizontal(|ui| {
+                    ui.add(
+                        egui::DragValue::new(&mut params.spacing.x)
+                            .prefix("x: ")
+                            .suffix(" px"),
+                    );
+                    ui.add(
+                        egui::DragValue::new(&mut params.spacing.y)
+                            .prefix("y: ")
+                            .suffix(" px"),
+                    );
+                })
+                .response
+                .on_hover_text("From the middle of one item to the next");
+                ui.end_row();
+            }
+            ArrayLayout::Circle => {
+                ui.label("Radius");
+                ui.add(
+                    egui::DragValue::new(&mut params.radius)
+                        .range(0.0..=100_000.0)
+                        .suffix(" px"),
+                );
+                ui.end_row();
+
+                ui.label("");
+                ui.checkbox(&mut params.rotate_items, "Turn items to follow the circle");
+                ui.end_row();
+            }
+        }
+    }
+}
@@ -0,0 +1,276 @@
+use crate::PaintApp;
+use crate::artboard::{ARTBOARD_PRESETS, Artboard, next_artboard_name};
+use crate::command::Command;
+use crate::element::Element;
+use crate::units::DocumentUnits;
+use egui;
+
+/// List of the document's artboards with controls for the active one
+///
+/// Clicking an artboard makes it active: it is the one edited here and exported
+/// by default. Edits go through drafts and commit as single undoable commands.
+pub fn artboards_panel(app: &mut PaintApp, ui: &mut egui::Ui) {
+    egui::CollapsingHeader::new("Artboards").show(ui, |ui| {
+        if app.editor_model().artboards.is_empty() {
+            ui.label("No artboards: the canvas grows with the content.");
+        }
+
+        let active_id = app
+            .editor_model()
+            .active_artboard()
+            .map(|artboard| artboard.id);
+        let mut clicked = None;
+        for artboard in &app.editor_model().artboards {
+            if ui
+                .selectable_label(Some(artboard.id) == active_id, &artboard.name)
+                .clicked()
+            {
+                clicked = Some(artboard.id);
+            }
+        }
+        if clicked.is_some() {
+            app.set_active_artboard(clicked);
+        }
+
+        add_artboard_ui(app, ui);
+
+        if let Some(artboard) = app.editor_model().active_artboard().cloned() {
+            ui.separator();
+            active_artboard_ui(app, ui, &artboard);
+        }
+    });
+}
+
+/// Menu adding an artboard of a preset size, or one around the content
+fn add_artboard_ui(app: &mut PaintApp, ui: &mut egui::Ui) {
+    let dpi = app.editor_model().units.dpi;
+    let content_bounds = crate::export::document_bounds(app.editor_model());
+    let mut new_rect = None;
+
+    egui::ComboBox::from_id_salt("add_artboard")
+        .selected_text("Add artboard…")
+        .show_ui(ui, |ui| {
+            if let Some(bounds) = content_bounds {
+                if ui.selectable_label(false, "Around content").clicked() {
+                    new_rect = Some(bounds);
+                }
+            }
+            for preset in &ARTBOARD_PRESETS {
+                if ui.selectable_label(false, preset.name).clicked() {
+                    new_rect = Some(app.place_artboard(preset.size_px(dpi)));
+                }
+            }
+        });
+
+    if let Some(rect) = new_rect {
+        let mut artboards = app.editor_model().artboards.clone();
+        let artboard = Artboard::new(next_artboard_name(&artboards), rect);
+        let artboard_id = artboard.id;
+        artboards.push(artboard);
+        if let Some(command) =
+            Command::new_set_artboards(app.editor_model(), "Add Artboard", artboards)
+        {
+            app.execute_command(command);
+            app.set_active_artboard(Some(artboard_id));
+        }
+    }
+}
+
+/// Name, size and position of the active artboard
+fn active_artboard_ui(app: &mut PaintApp, ui: &mut egui::Ui, artboard: &Artboard) {
+    let units = app.editor_model().units;
+    let mut new_artboard = None;
+    let mut move_by = None;
+
+    egui::Grid::new("active_artboard_grid").show(ui, |ui| {
+        ui.label("Name");
+        let draft_id = ui.make_persistent_id(("artboard_name_draft", artboard.id));
+        let mut name = ui
+            .data_mut(|data| data.get_temp::<String>(draft_id))
+            .unwrap_or_else(|| artboard.name.clone());
+        let response = ui.text_edit_singleline(&mut name);
+        if response.has_focus() {
+            ui.data_mut(|data| data.insert_temp(draft_id, name));
+        } else {
+            ui.data_mut(|data| data.remove::<String>(draft_id));
+            if response.lost_focus() && !name.trim().is_empty() {
+                new_artboard = Some((
+                    "Rename Artboard",
+                    Artboard {
+                        name: name.trim().to_string(),
+                        ..artboard.clone()
+                    },
+                ));
+            }
+        }
+        ui.end_row();
+
+        ui.label("Size");
+        let current = ARTBOARD_PRESETS
+            .iter()
+            .find(|preset| (preset.size_px(units.dpi) - artboard.rect.size()).length() < 0.5)
+            .map_or("Custom", |preset| preset.name);
+        egui::ComboBox::from_id_salt("artboard_size_preset")
+            .selected_text(current)
+            .show_ui(ui, |ui| {
+                for preset in &ARTBOARD_PRESETS {
+                    if ui
+                        .selectable_label(current == preset.name, preset.name)
+                        .clicked()
+                    {
+                        let rect =
+                            egui::Rect::from_min_size(artboard.rect.min, preset.size_px(units.dpi));
+                        new_artboard = Some((
+                            "Resize Artboard",
+                            Artboard {
+                                rect,
+                                ..artboard.clone()
+                            },
+                        ));
+                    }
+                }
+            });
+        ui.end_row();
+
+        ui.label("");
+        let size = artboard.rect.size();
+        if let Some(size) =
+            length_pair_ui(ui, ("artboard_size_draft", artboard.id), units, size, 1.0)
+        {
+            let rect = egui::Rect::from_min_size(artboard.rect.min, size);
+            new_artboard = Some((
+                "Resize Artboard",
+                Artboard {
+                    rect,
+                    ..artboard.clone()
+                },
+            ));
+        }
+        ui.end_row();
+
+        ui.label("Position");
+        let min = artboard.rect.min.to_vec2();
+        if let Some(min) = length_pair_ui(
+            ui,
+            ("artboard_position_draft", artboard.id),
+            units,
+            min,
+            f32::MIN,
+        ) {
+            move_by = Some(min - artboard.rect.min.to_vec2());
+        }
+        ui.end_row();
+    });
+
+    let element_ids = app.editor_model().artboard_element_ids(artboard.id);
+    let content_bounds = element_ids
+        .iter()
+        .filter_map(|&id| app.editor_model().find_element_by_id(id))
+        .map(|element| element.rect())
+        .reduce(|acc, rect| acc.union(rect));
+
+    ui.horizontal(|ui| {
+        if ui
+            .add_enabled(
+                content_bounds.is_some(),
+                egui::Button::new("Fit to content"),
+            )
+            .clicked()
+        {
+            if let Some(rect) = content_bounds {
+                new_artboard = Some((
+                    "Resize Artboard",
+                    Artboard {
+                        rect,
+                        ..artboard.clone()
+                    },
+                ));
+            }
+        }
+        if ui
+            .button("Remove")
+            .on_hover_text("Removes the artboard; the elements on it stay")
+            .clicked()
+        {
+            let artboards = app
+                .editor_model()
+                .artboards
+                .iter()
+                .filter(|other| other.id != artboard.id)
+                .cloned()
+                .collect();
+            if let Some(command) =
+                Command::new_set_artboards(app.editor_model(), "Remove Artboard", artboards)
+            {
+                app.execute_command(command);
+            }
+        }
+    });
+
+    if let Some((description, new_artboard)) = new_artboard {
+        let artboards = app
+            .editor_model()
+            .artboards
+            .iter()
+            .map(|other| {
+                if other.id == new_artboard.id {
+                    new_artboard.clone()
+                } else {
+                    other.clone()
+                }
+            })
+            .collect();
+        if let Some(command) =
+            Command::new_set_artboards(app.editor_model(), description, artboards)
+        {
+            app.execute_command(command);
+        }
+    }
+    if let Some(delta) = move_by {
+        if let Some(command) = Command::new_move_artboard(app.editor_model(), artboard.id, delta) {
+            app.execute_command(command);
+        }
+    }
+}
+
+/// Two drag values editing a pair of lengths (in pixels) in document units
+///
+/// The values are kept in a draft while being edited; returns the new pair,
+/// in pixels, once editing ends.
+fn length_pair_ui(
+    ui: &mut egui::Ui,
+    draft_key: (&str, usize),
+    units: DocumentUnits,
+    value: egui::Vec2,
+    min: f32,
+) -> Option<egui::Vec2> {
+    let draft_id = ui.make_persistent_id(draft_key);
+    let mut draft = ui
+        .data_mut(|data| data.get_temp::<egui::Vec2>(draft_id))
+        .unwrap_or(egui::vec2(units.from_px(value.x), units.from_px(value.y)));
+
+    let (editing, committed) = ui
+        .horizontal(|ui| {
+            let mut editing = false;
+            let mut committed = false;
+            for value in [&mut draft.x, &mut draft.y] {
+                let response = ui.add(
+                    egui::DragValue::new(value)
+                        .range(min..=f32::MAX)
+                        .max_decimals(units.unit.decimals())
+                        .suffix(format!(" {}", units.unit.suffix())),
+                );
+                editing |= response.dragged() || response.has_focus();
+                committed |= response.drag_stopped() || response.lost_focus();
+            }
+            (editing, committed)
+        })
+        .inner;
+
+    if editing {
+        ui.data_mut(|data| data.insert_temp(draft_id, draft));
+        return None;
+    }
+    ui.data_mut(|data| data.remove::<egui::Vec2>(draft_id));
+    committed.then(|| egui::vec2(units.to_px(draft.x), units.to_px(draft.y)))
+}
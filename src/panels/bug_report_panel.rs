@@ -0,0 +1,85 @@
+use crate::bug_report::{self, ISSUES_URL, ImageRedaction};
+use crate::document::DocumentFile;
+use crate::settings::Settings;
+use crate::state::EditorModel;
+use egui;
+
+/// Report Issue window: bundles diagnostics into a zip to attach to a GitHub issue
+pub struct BugReportDialog {
+    pub open: bool,
+    /// Attach an anonymized copy of the current document
+    pub include_document: bool,
+    pub images: ImageRedaction,
+    pub path: String,
+    // Result of the last save, shown under the button
+    status: Option<Result<String, String>>,
+}
+
+impl Default for BugReportDialog {
+    fn default() -> Self {
+        Self {
+            open: false,
+            include_document: false,
+            images: ImageRedaction::default(),
+            path: "bug-report.zip".to_string(),
+            status: None,
+        }
+    }
+}
+
+impl BugReportDialog {
+    /// Show the window if it is open
+    pub fn show(&mut self, ctx: &egui::Context, settings: &Settings, editor_model: &EditorModel) {
+        let mut open = self.open;
+        egui::Window::new("Report Issue")
+            .open(&mut open)
+            .resizable(false)
+            .show(ctx, |ui| {
+                ui.label(
+                    "Saves the app version, your settings and recent log messages into a zip \
+                     you can attach to a GitHub issue. Paths and commands in your settings \
+                     are left out.",
+                );
+                ui.separator();
+
+                ui.checkbox(&mut self.include_document, "Attach the current document")
+                    .on_hover_text("Names, title and author are removed");
+                ui.add_enabled_ui(self.include_document, |ui| {
+                    ui.horizontal(|ui| {
+                        for images in ImageRedaction::ALL {
+                            ui.radio_value(&mut self.images, images, images.label());
+                        }
+                    });
+                });
+
+                ui.horizontal(|ui| {
+                    ui.label("Save to");
+                    ui.text_edit_singleline(&mut self.path);
+                });
+                if ui.button("Save report").clicked() {
+                    self.status = Some(self.save(settings, editor_model));
+                }
+                match &self.status {
+                    Some(Ok(message)) => {
+                        ui.label(message);
+                        ui.hyperlink_to("Open a new GitHub issue", ISSUES_URL);
+                    }
+                    Some(Err(err)) => {
+                        ui.colored_label(egui::Color32::RED, err);
+                    }
+                    None => {}
+                }
+            });
+        self.open = open;
+    }
+
+    fn save(&self, settings: &Settings, editor_model: &EditorModel) -> Result<String, String> {
+        let document = self.include_document.then(|| {
+            bug_report::anonymize_document(&DocumentFile::from_model(editor_model), self.images)
+        });
+        let path = std::path::Path::new(&self.path);
+        bug_report::save_report(path, settings, document.as_ref())
+            .map(|()| format!("Saved to {}; attach it to the issue", path.display()))
+            .map_err(|err| err.to_string())
+    }
+}
@@ -1,15 +1,41 @@
+use crate::accessibility::describe_canvas;
 use crate::command::Command;
 use crate::command::CommandHistory;
+use crate::element::sticky_note::StickyNote;
+use crate::element::{Element, ElementId, ElementType, NOTE_TEXT_COLOR};
 use crate::state::EditorModel;
+use crate::profiler::Phase;
+use crate::panels::rulers;
 use crate::renderer::Renderer;
-use crate::tools::{Tool};
+use crate::tools::{Tool, ToolType};
+use crate::view::{self, View};
 use egui;
-use log::info;
+use log::{debug, trace};
 
 /// A panel for the main editing area of the application
 pub struct CentralPanel {
     last_pointer_pos: Option<egui::Pos2>,
     request_repaint: bool,
+    // Maps screen positions into document coordinates
+    view: View,
+    // Whether positions handed to tools snap to the pixel under the pointer
+    snap_to_pixels: bool,
+    // Sticky note to open for typing once the pointer is handled
+    pending_note_edit: Option<ElementId>,
+}
+
+/// A sticky note being typed into on the canvas, with the text so far
+#[derive(Clone)]
+struct NoteEdit {
+    element_id: ElementId,
+    text: String,
+    // Whether the text field has had focus, so losing it ends the edit
+    focused: bool,
+}
+
+/// Where the note being edited is kept in egui's memory between frames
+fn note_edit_id() -> egui::Id {
+    egui::Id::new("sticky_note_edit")
 }
 
 impl CentralPanel {
@@ -17,10 +43,25 @@ impl CentralPanel {
         Self {
             last_pointer_pos: None,
             request_repaint: false,
+            view: View::default(),
+            snap_to_pixels: false,
+            pending_note_edit: None,
+        }
+    }
+
+    /// The document position under `screen_pos` to hand to tools
+    fn to_document(&self, screen_pos: egui::Pos2) -> egui::Pos2 {
+        let pos = self.view.screen_to_document(screen_pos);
+        if self.snap_to_pixels {
+            view::snap_to_pixel(pos)
+        } else {
+            pos
         }
     }
     
     /// Handle pointer events (mouse down/move/up) and delegate to the active tool
+    ///
+    /// `pos` is in document coordinates, as are the positions handed to the tool.
     fn handle_pointer_events(
         &mut self,
         ctx: &egui::Context,
@@ -32,11 +73,28 @@ impl CentralPanel {
     ) {
         // Get input state from egui
         let modifiers = ctx.input(|i| i.modifiers);
+
+        // Pens report their pressure as touches; the tool takes the latest
+        let pressure = ctx.input(|i| {
+            i.events.iter().rev().find_map(|event| match event {
+                egui::Event::Touch { force, .. } => *force,
+                _ => None,
+            })
+        });
+        if let Some(pressure) = pressure {
+            let mut tool = editor_model.active_tool().clone();
+            tool.set_pressure(pressure);
+            editor_model.update_tool(|_| tool);
+        }
+        if self.handle_brush_resize_drag(ctx, pos, editor_model, renderer) {
+            return;
+        }
         
         // Handle pointer down events
         for button in [egui::PointerButton::Primary, egui::PointerButton::Secondary] {
             if ctx.input(|i| i.pointer.button_pressed(button)) {
-                info!("Tool: pointer down at {:?} with button {:?}", pos, button);
+                trace!(pos:? = pos, button:? = button; "Pointer down");
+                command_history.begin_gesture();
                 
                 // Get a clone of the active tool to avoid borrow issues
                 let mut tool = editor_model.active_tool().clone();
@@ -52,7 +110,7 @@ impl CentralPanel {
                 editor_model.update_tool(|_| tool);
                 
                 if let Some(cmd) = cmd {
-                    info!("Tool generated command from pointer down: {:?}", cmd);
+                    debug!(command = cmd.label(); "Command from pointer down");
                     
                     // Check command type before executing it
                     let is_select_command = matches!(cmd, Command::SelectElement(_));
@@ -63,7 +121,9 @@ impl CentralPanel {
                     // operations in the same gesture
                     if is_select_command {
                         // Update tool state after selection to continue with drag operation
-                        let pos = ctx.input(|i| i.pointer.hover_pos()).unwrap_or(pos);
+                        let pos = ctx
+                            .input(|i| i.pointer.hover_pos())
+                            .map_or(pos, |pos| self.to_document(pos));
                         let held_buttons: Vec<_> = [
                             egui::PointerButton::Primary,
                             egui::PointerButton::Secondary,
@@ -113,25 +173,40 @@ impl CentralPanel {
             if !held_buttons.is_empty() || self.last_pointer_pos != Some(pos) {
                 // Update last known position
                 self.last_pointer_pos = Some(pos);
-                
-                // Get a clone of the active tool to avoid borrow issues
-                let mut tool = editor_model.active_tool().clone();
-                let cmd = tool.on_pointer_move(
-                    pos,
-                    &held_buttons,
-                    &modifiers,
-                    editor_model,
-                    ui,
-                    renderer,
-                );
-                
-                // Update the tool in the model
-                editor_model.update_tool(|_| tool);
-                
-                if let Some(cmd) = cmd {
-                    info!("Tool generated command from pointer move: {:?}", cmd);
-                    self.execute_command(cmd, command_history, editor_model, renderer);
-                    return; // Stop processing after executing a command
+
+                // While a button is held, replay every position the pointer passed
+                // through this frame, so fast strokes don't depend on the frame rate
+                let mut positions = Vec::new();
+                if !held_buttons.is_empty() {
+                    positions = ctx.input(|i| pointer_positions(&i.events));
+                    for position in &mut positions {
+                        *position = self.to_document(*position);
+                    }
+                }
+                if positions.last() != Some(&pos) {
+                    positions.push(pos);
+                }
+
+                for pos in positions {
+                    // Get a clone of the active tool to avoid borrow issues
+                    let mut tool = editor_model.active_tool().clone();
+                    let cmd = tool.on_pointer_move(
+                        pos,
+                        &held_buttons,
+                        &modifiers,
+                        editor_model,
+                        ui,
+                        renderer,
+                    );
+
+                    // Update the tool in the model
+                    editor_model.update_tool(|_| tool);
+
+                    if let Some(cmd) = cmd {
+                        debug!(command = cmd.label(); "Command from pointer move");
+                        self.execute_command(cmd, command_history, editor_model, renderer);
+                        return; // Stop processing after executing a command
+                    }
                 }
             }
         }
@@ -139,7 +214,7 @@ impl CentralPanel {
         // Handle pointer up events
         for button in [egui::PointerButton::Primary, egui::PointerButton::Secondary] {
             if ctx.input(|i| i.pointer.button_released(button)) {
-                info!("Tool: pointer up at {:?} with button {:?}", pos, button);
+                trace!(pos:? = pos, button:? = button; "Pointer up");
                 
                 // Get a clone of the active tool to avoid borrow issues
                 let mut tool = editor_model.active_tool().clone();
@@ -154,8 +229,17 @@ impl CentralPanel {
                 editor_model.update_tool(|_| tool);
                 
                 if let Some(cmd) = cmd {
-                    info!("Tool generated command from pointer up: {:?}", cmd);
+                    debug!(command = cmd.label(); "Command from pointer up");
                     self.execute_command(cmd, command_history, editor_model, renderer);
+
+                    // Run any follow-up as a separate step, so undo stops in between
+                    let mut tool = editor_model.active_tool().clone();
+                    let follow_up = tool.follow_up_command(editor_model);
+                    editor_model.update_tool(|_| tool);
+                    if let Some(cmd) = follow_up {
+                        debug!(command = cmd.label(); "Follow-up command");
+                        self.execute_command(cmd, command_history, editor_model, renderer);
+                    }
                     return; // Stop processing after executing a command
                 }
             }
@@ -167,6 +251,46 @@ impl CentralPanel {
         editor_model.update_tool(|_| tool);
     }
     
+    /// Size the active tool's brush by dragging sideways with Ctrl+Alt held,
+    /// showing it at the point the drag started
+    ///
+    /// Returns whether the pointer went to sizing the brush rather than to the tool.
+    fn handle_brush_resize_drag(
+        &mut self,
+        ctx: &egui::Context,
+        pos: egui::Pos2,
+        editor_model: &mut EditorModel,
+        renderer: &mut Renderer,
+    ) -> bool {
+        let (modifiers, dragging, delta, origin) = ctx.input(|i| {
+            (
+                i.modifiers,
+                i.pointer.primary_down(),
+                i.pointer.delta(),
+                i.pointer.press_origin(),
+            )
+        });
+        let tool = editor_model.active_tool();
+        if !(modifiers.ctrl && modifiers.alt) || tool.current_state_name() != "Idle" {
+            return false;
+        }
+        let Some((size, range)) = tool.brush_size() else {
+            return false;
+        };
+
+        if dragging && delta.x != 0.0 {
+            let size = (size + delta.x / self.view.transform().scaling)
+                .clamp(*range.start(), *range.end());
+            let mut tool = tool.clone();
+            tool.set_brush_size(size);
+            editor_model.update_tool(|_| tool);
+            let center = origin.map_or(pos, |origin| self.view.screen_to_document(origin));
+            renderer.show_brush_size_hud(center, size);
+            self.request_repaint = true;
+        }
+        true
+    }
+
     /// Handle keyboard events and delegate to the active tool
     fn handle_keyboard_events(
         &mut self,
@@ -216,9 +340,11 @@ impl CentralPanel {
         renderer: &mut Renderer,
     ) {
         // Execute the command
+        renderer.profiler_mut().begin(Phase::CommandExecution);
         let _ = command_history
             .execute(cmd.clone(), editor_model)
-            .map_err(|err| log::warn!("Command execution failed: {}", err));
+            .map_err(|err| log::warn!(error:% = err; "Command execution failed"));
+        renderer.profiler_mut().end();
         
         // Only reset the tool's interaction state for non-selection commands
         // This allows drag operations to continue after a selection command
@@ -231,31 +357,201 @@ impl CentralPanel {
             renderer.clear_all_previews();
         }
         
+        // New notes open for typing straight away
+        if let Command::AddElement {
+            element: ElementType::StickyNote(note),
+        } = &cmd
+        {
+            self.pending_note_edit = Some(note.id());
+        }
+
         // Request a repaint
         self.request_repaint = true;
     }
+
+    /// Open the sticky note double-clicked at `pos` for typing
+    ///
+    /// Only the selection and sticky note tools edit notes; locked ones are left alone.
+    fn handle_note_double_click(
+        &mut self,
+        ctx: &egui::Context,
+        pos: egui::Pos2,
+        editor_model: &EditorModel,
+    ) {
+        if !ctx.input(|i| {
+            i.pointer
+                .button_double_clicked(egui::PointerButton::Primary)
+        }) {
+            return;
+        }
+        if !matches!(
+            editor_model.active_tool(),
+            ToolType::Selection(_) | ToolType::StickyNote(_)
+        ) {
+            return;
+        }
+        if let Some(ElementType::StickyNote(note)) = editor_model.element_at_position(pos) {
+            if !note.metadata().is_locked() {
+                self.pending_note_edit = Some(note.id());
+            }
+        }
+    }
+}
+
+/// Start typing into the sticky note `element_id`, beginning from its text
+fn start_note_edit(ctx: &egui::Context, editor_model: &EditorModel, element_id: ElementId) {
+    if let Some(ElementType::StickyNote(note)) = editor_model.find_element_by_id(element_id) {
+        let edit = NoteEdit {
+            element_id,
+            text: note.text().to_string(),
+            focused: false,
+        };
+        ctx.data_mut(|data| data.insert_temp(note_edit_id(), edit));
+    }
+}
+
+/// Whether a sticky note is being typed into
+fn editing_note(ctx: &egui::Context) -> bool {
+    ctx.data(|data| data.get_temp::<NoteEdit>(note_edit_id()).is_some())
+}
+
+/// Text field over the sticky note being edited, if any
+///
+/// The text is committed as one undoable step when the field loses focus, by
+/// Escape or a click elsewhere.
+fn note_editor(
+    ui: &mut egui::Ui,
+    editor_model: &mut EditorModel,
+    command_history: &mut CommandHistory,
+) {
+    let id = note_edit_id();
+    let Some(mut edit) = ui.data(|data| data.get_temp::<NoteEdit>(id)) else {
+        return;
+    };
+    let Some(ElementType::StickyNote(note)) = editor_model.find_element_by_id(edit.element_id)
+    else {
+        // Removed, say by an undo, while it was being edited
+        ui.data_mut(|data| data.remove::<NoteEdit>(id));
+        return;
+    };
+    let (rect, color) = (note.body(), note.color());
+
+    // Cover the text drawn with the document, and type in its place at the
+    // size the draft fits at; callouts have no fill of their own, so they're
+    // covered with the canvas color
+    let cover = if note.is_callout() {
+        editor_model.background.unwrap_or(egui::Color32::WHITE)
+    } else {
+        color
+    };
+    ui.painter()
+        .rect_filled(rect, StickyNote::corner_radius(rect), cover);
+    let text_rect = StickyNote::text_rect(rect);
+    let (size, _) = StickyNote::layout_text(ui.ctx(), &edit.text, rect);
+    let response = ui.put(
+        text_rect,
+        egui::TextEdit::multiline(&mut edit.text)
+            .id(id.with(edit.element_id))
+            .frame(false)
+            .margin(egui::Margin::ZERO)
+            .font(egui::FontId::proportional(size))
+            .text_color(NOTE_TEXT_COLOR)
+            .desired_width(text_rect.width()),
+    );
+
+    if !edit.focused {
+        response.request_focus();
+        edit.focused = response.has_focus();
+    } else if !response.has_focus() {
+        ui.data_mut(|data| data.remove::<NoteEdit>(id));
+        if let Some(command) =
+            Command::new_set_sticky_note(editor_model, edit.element_id, edit.text, color)
+        {
+            let _ = command_history
+                .execute(command, editor_model)
+                .map_err(|err| log::warn!(error:% = err; "Command execution failed"));
+        }
+        return;
+    }
+    ui.data_mut(|data| data.insert_temp(id, edit));
+}
+
+/// Positions the pointer moved through, in the order egui saw them
+fn pointer_positions(events: &[egui::Event]) -> Vec<egui::Pos2> {
+    events
+        .iter()
+        .filter_map(|event| match event {
+            egui::Event::PointerMoved(pos) => Some(*pos),
+            _ => None,
+        })
+        .collect()
+}
+
+impl Default for CentralPanel {
+    fn default() -> Self {
+        Self::new()
+    }
 }
 
-/// Create and show the central editing panel
+/// Create and show the central editing panel, returning the rect on screen
+/// the canvas takes up beside the rulers
+///
+/// The document is drawn zoomed and scrolled by `view`.
 pub fn central_panel(
     editor_model: &mut EditorModel,
     command_history: &mut CommandHistory,
     renderer: &mut Renderer,
+    view: &View,
     ctx: &egui::Context,
 ) -> egui::Rect {
     let panel_response = egui::CentralPanel::default().show(ctx, |ui| {
-        // Get the panel rect for hit testing
-        let panel_rect = ui.max_rect();
-        
-        // Create or reuse a CentralPanel instance to handle input
-        let mut central_panel = CentralPanel::new();
+        // Get the panel rect for hit testing, leaving room for the rulers
+        let full_rect = ui.max_rect();
+        let panel_rect = rulers::canvas_area(full_rect, renderer.shows_rulers());
+        if renderer.shows_rulers() {
+            rulers::paint_rulers(ui, full_rect, view, &editor_model.units);
+        }
+
+        // Draw on the canvas layer, in document coordinates. While the view is
+        // rotated the pointer reaches the layer turned, so the canvas covers
+        // the panel both ways
+        let visible_rect = view.screen_rect_to_document(panel_rect);
+        let canvas_rect = visible_rect.union(view.transform().inverse() * panel_rect);
+        let mut canvas_ui = ui.new_child(
+            egui::UiBuilder::new()
+                .layer_id(view::canvas_layer())
+                .max_rect(canvas_rect),
+        );
+        canvas_ui.set_clip_rect(canvas_rect);
+        let ui = &mut canvas_ui;
         
         // Render the document with the renderer
-        renderer.render(ui, editor_model, panel_rect);
-        
-        // Get current pointer position if it's in the panel
-        if let Some(pos) = ui.input(|i| i.pointer.hover_pos()) {
-            if panel_rect.contains(pos) {
+        renderer.render(ui, editor_model, visible_rect);
+        let on_guides = rulers::drag_ruler_guides(
+            ui,
+            full_rect,
+            panel_rect,
+            view,
+            editor_model,
+            command_history,
+            renderer,
+        );
+
+        // Create or reuse a CentralPanel instance to handle input
+        let mut central_panel = CentralPanel {
+            view: view.clone(),
+            snap_to_pixels: renderer.snaps_to_pixels(),
+            ..CentralPanel::new()
+        };
+
+        // Get current pointer position if it's in the panel. While a sticky note
+        // is being typed into, the pointer and keys are the text field's, and
+        // while Space is held the pointer pans the view
+        renderer.profiler_mut().begin(Phase::InputRouting);
+        let editing = editing_note(ctx);
+        if let Some(screen_pos) = ui.input(|i| i.pointer.hover_pos()) {
+            if panel_rect.contains(screen_pos) && !editing && !on_guides && !view.is_panning() {
+                let pos = central_panel.to_document(screen_pos);
                 // Handle pointer events
                 central_panel.handle_pointer_events(
                     ctx,
@@ -265,16 +561,38 @@ pub fn central_panel(
                     renderer,
                     ui,
                 );
+                central_panel.handle_note_double_click(ctx, pos, editor_model);
+
+                // Show what the pointer would grab, from a fresh hit test every frame
+                let tool = editor_model.active_tool();
+                if let Some(icon) = tool.cursor_icon(pos, editor_model, renderer) {
+                    ctx.set_cursor_icon(icon);
+                }
+                let brush = tool
+                    .brush_outline()
+                    .filter(|_| renderer.shows_brush_outline());
+                if let Some(diameter) = brush {
+                    ctx.set_cursor_icon(egui::CursorIcon::None);
+                    renderer.draw_brush_outline(ui.painter(), pos, diameter);
+                }
             }
         }
         
+        // Once the pointer is let go, wherever that happened, its gesture's undo step is done
+        if !ui.input(|i| i.pointer.any_down()) {
+            command_history.end_gesture();
+        }
+
         // Handle keyboard events regardless of pointer position
-        central_panel.handle_keyboard_events(
-            ctx,
-            editor_model,
-            command_history,
-            renderer,
-        );
+        if !editing {
+            central_panel.handle_keyboard_events(ctx, editor_model, command_history, renderer);
+        }
+        renderer.profiler_mut().end();
+
+        if let Some(element_id) = central_panel.pending_note_edit.take() {
+            start_note_edit(ctx, editor_model, element_id);
+        }
+        note_editor(ui, editor_model, command_history);
         
         // Request repaint if needed
         if central_panel.request_repaint {
@@ -285,10 +603,19 @@ pub fn central_panel(
         panel_rect
     });
 
+    // Describe the elements for screen readers, which can't see the painted canvas
+    let hover_pos = ctx
+        .input(|i| i.pointer.hover_pos())
+        .map(|pos| view.screen_to_document(pos));
+    panel_response.response.widget_info(|| {
+        let description = describe_canvas(editor_model, hover_pos);
+        egui::WidgetInfo::labeled(egui::WidgetType::Other, true, description)
+    });
+
     // Also request repaint if we're interacting with the panel
     if panel_response.response.hovered() || panel_response.response.dragged() {
         ctx.request_repaint();
     }
 
-    panel_response.response.rect
+    panel_response.inner
 }
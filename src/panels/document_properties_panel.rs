@@ -0,0 +1,136 @@
+use crate::artboard::Artboard;
+use crate::document::DocumentProperties;
+use crate::panels::new_document_panel::background_ui;
+use crate::state::EditorModel;
+use crate::units::Unit;
+use egui;
+
+/// Size given to the canvas when an unbounded document is made fixed-size
+const DEFAULT_CANVAS_SIZE: egui::Vec2 = egui::vec2(800.0, 600.0);
+
+/// Document Properties window: canvas size, resolution and background
+///
+/// Edits a copy of the document's properties, applied as one undoable step.
+#[derive(Default)]
+pub struct DocumentPropertiesDialog {
+    pub open: bool,
+    draft: Option<DocumentProperties>,
+    // Where a canvas is put when an unbounded document is given one
+    canvas_origin: egui::Pos2,
+}
+
+impl DocumentPropertiesDialog {
+    /// Open the window on the document's current properties
+    ///
+    /// A canvas added to an unbounded document gets its top-left corner at `canvas_origin`.
+    pub fn open_for(&mut self, editor_model: &EditorModel, canvas_origin: egui::Pos2) {
+        self.open = true;
+        self.draft = Some(editor_model.properties());
+        self.canvas_origin = canvas_origin;
+    }
+
+    /// Show the Document Properties window if it is open
+    ///
+    /// Returns the edited properties when the user applies them.
+    pub fn show(&mut self, ctx: &egui::Context) -> Option<DocumentProperties> {
+        let canvas_origin = self.canvas_origin;
+        let Some(draft) = &mut self.draft else {
+            self.open = false;
+            return None;
+        };
+
+        let mut open = self.open;
+        let mut apply = false;
+        let mut cancel = false;
+        egui::Window::new("Document Properties")
+            .open(&mut open)
+            .resizable(false)
+            .show(ctx, |ui| {
+                egui::Grid::new("document_properties_grid")
+                    .num_columns(2)
+                    .show(ui, |ui| properties_ui(ui, draft, canvas_origin));
+                ui.separator();
+                ui.horizontal(|ui| {
+                    apply = ui.button("Apply").clicked();
+                    cancel = ui.button("Cancel").clicked();
+                });
+            });
+
+        self.open = open && !apply && !cancel;
+        if self.open {
+            return None;
+        }
+        self.draft.take().filter(|_| apply)
+    }
+}
+
+fn properties_ui(
+    ui: &mut egui::Ui,
+    properties: &mut DocumentProperties,
+    canvas_origin: egui::Pos2,
+) {
+    ui.label("Canvas");
+    let mut fixed = !properties.artboards.is_empty();
+    if ui
+        .checkbox(&mut fixed, "Fixed size")
+        .on_hover_text("Otherwise the canvas grows with the drawing")
+        .changed()
+    {
+        properties.artboards = if fixed {
+            let rect = egui::Rect::from_min_size(canvas_origin, DEFAULT_CANVAS_SIZE);
+            vec![Artboard::new("Canvas", rect)]
+        } else {
+            Vec::new()
+        };
+    }
+    ui.end_row();
+
+    // Artboards keep their top-left corner when resized
+    let units = properties.units;
+    for artboard in &mut properties.artboards {
+        ui.label(&artboard.name);
+        ui.horizontal(|ui| {
+            let mut size = artboard.rect.size();
+            ui.add(
+                egui::DragValue::new(&mut size.x)
+                    .range(1.0..=10_000.0)
+                    .suffix(" px"),
+            );
+            ui.label("×");
+            ui.add(
+                egui::DragValue::new(&mut size.y)
+                    .range(1.0..=10_000.0)
+                    .suffix(" px"),
+            );
+            if units.unit != Unit::Px {
+                ui.weak(units.format_size(size));
+            }
+            artboard.rect.set_width(size.x);
+            artboard.rect.set_height(size.y);
+        });
+        ui.end_row();
+    }
+
+    ui.label("Units");
+    egui::ComboBox::from_id_salt("document_properties_unit")
+        .selected_text(properties.units.unit.label())
+        .show_ui(ui, |ui| {
+            for unit in Unit::ALL {
+                ui.selectable_value(&mut properties.units.unit, unit, unit.label());
+            }
+        });
+    ui.end_row();
+
+    ui.label("Resolution");
+    ui.add(
+        egui::DragValue::new(&mut properties.units.dpi)
+            .range(1.0..=2400.0)
+            .suffix(" dpi"),
+    )
+    .on_hover_text("Pixels per inch, for sizes in millimeters or inches");
+    ui.end_row();
+
+    ui.label("Background");
+    background_ui(ui, &mut properties.background);
+    ui.end_row();
+}
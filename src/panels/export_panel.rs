@@ -0,0 +1,474 @@
+use crate::document::DocumentMetadata;
+use crate::element::Resampling;
+use crate::export::{
+    self, ExportMetadata, ExportPreset, ExportSettings, Watermark, WatermarkAnchor,
+    WatermarkContent,
+};
+use crate::state::EditorModel;
+use crate::units::Unit;
+use egui;
+
+/// State for the export window: current settings, saved presets and output path
+///
+/// Metadata is kept outside `settings` so presets stay independent of the document.
+pub struct ExportDialog {
+    pub open: bool,
+    pub settings: ExportSettings,
+    pub metadata: ExportMetadata,
+    // Canvas background exported when the background isn't transparent
+    background_color: egui::Color32,
+    // Command run after each successful export
+    post_export_hook: String,
+    // Artboard to export, or None for the active one
+    artboard_id: Option<usize>,
+    pub presets: Vec<ExportPreset>,
+    pub path: String,
+    new_preset_name: String,
+    watermark_image_path: String,
+    status: Option<Result<String, String>>,
+}
+
+impl ExportDialog {
+    pub fn new(presets: Vec<ExportPreset>) -> Self {
+        let settings = presets
+            .first()
+            .map(|preset| preset.settings.clone())
+            .unwrap_or_default();
+
+        Self {
+            open: false,
+            settings,
+            metadata: ExportMetadata::default(),
+            background_color: egui::Color32::WHITE,
+            post_export_hook: String::new(),
+            artboard_id: None,
+            presets,
+            path: "export.png".to_string(),
+            new_preset_name: String::new(),
+            watermark_image_path: String::new(),
+            status: None,
+        }
+    }
+
+    /// Open the window, filling blank metadata fields from the document
+    pub fn open_for(&mut self, document: &DocumentMetadata) {
+        self.open = true;
+        self.metadata.prefill_from(document);
+    }
+
+    /// Use the canvas background color for opaque exports
+    pub fn set_background_color(&mut self, background_color: egui::Color32) {
+        self.background_color = background_color;
+    }
+
+    /// Run `command` after each successful export
+    pub fn set_post_export_hook(&mut self, command: &str) {
+        self.post_export_hook = command.to_string();
+    }
+
+    /// Show the export window if it is open
+    pub fn show(&mut self, ctx: &egui::Context, editor_model: &EditorModel) {
+        let mut open = self.open;
+        egui::Window::new("Export")
+            .open(&mut open)
+            .resizable(false)
+            .show(ctx, |ui| {
+                self.presets_ui(ui);
+                ui.separator();
+                self.settings_ui(ui);
+                ui.separator();
+                self.watermark_ui(ui);
+                ui.separator();
+                self.metadata_ui(ui);
+                ui.separator();
+                self.output_ui(ui, ctx, editor_model);
+            });
+        self.open = open;
+    }
+
+    fn presets_ui(&mut self, ui: &mut egui::Ui) {
+        ui.horizontal(|ui| {
+            ui.label("Preset:");
+            let current = self
+                .presets
+                .iter()
+                .find(|preset| preset.settings == self.settings)
+                .map_or("Custom", |preset| preset.name.as_str())
+                .to_string();
+
+            egui::ComboBox::from_id_salt("export_preset")
+                .selected_text(current)
+                .show_ui(ui, |ui| {
+                    for preset in &self.presets {
+                        if ui
+                            .selectable_label(preset.settings == self.settings, &preset.name)
+                            .clicked()
+                        {
+                            self.settings = preset.settings.clone();
+                        }
+                    }
+                });
+        });
+
+        ui.horizontal(|ui| {
+            ui.text_edit_singleline(&mut self.new_preset_name);
+            let name = self.new_preset_name.trim().to_string();
+            if ui
+                .add_enabled(!name.is_empty(), egui::Button::new("Save preset"))
+                .clicked()
+            {
+                // Saving under an existing name replaces that preset
+                self.presets.retain(|preset| preset.name != name);
+                self.presets.push(ExportPreset {
+                    name,
+                    settings: self.settings.clone(),
+                });
+                self.new_preset_name.clear();
+            }
+        });
+    }
+
+    fn settings_ui(&mut self, ui: &mut egui::Ui) {
+        ui.add(egui::Slider::new(&mut self.settings.scale, 0.25..=4.0).text("Scale"));
+        ui.add(egui::Slider::new(&mut self.settings.margin, 0.0..=100.0).text("Margin"))
+            .on_hover_text("Not used when the document has a fixed canvas size");
+        ui.checkbox(
+            &mut self.settings.transparent_background,
+            "Export transparent background",
+        );
+        ui.checkbox(
+            &mut self.settings.include_reference_image,
+            "Include reference image",
+        )
+        .on_hover_text("Draw the image being traced under the elements of exported images");
+        ui.horizontal(|ui| {
+            ui.label("Image resampling:");
+            egui::ComboBox::from_id_salt("export_resampling")
+                .selected_text(self.settings.resampling.label())
+                .show_ui(ui, |ui| {
+                    for resampling in Resampling::ALL {
+                        ui.selectable_value(
+                            &mut self.settings.resampling,
+                            resampling,
+                            resampling.label(),
+                        );
+                    }
+                });
+        });
+        ui.checkbox(&mut self.settings.privacy_scrub, "Privacy scrub")
+            .on_hover_text(
+                "Burn redactions in for good, export SVGs as a flat image and embed no metadata",
+            );
+    }
+
+    fn watermark_ui(&mut self, ui: &mut egui::Ui) {
+        let mut enabled = self.settings.watermark.is_some();
+        if ui.checkbox(&mut enabled, "Watermark").changed() {
+            self.settings.watermark = enabled.then(|| Watermark::text("Work in progress"));
+        }
+
+        let Some(watermark) = &mut self.settings.watermark else {
+            return;
+        };
+
+        ui.horizontal(|ui| {
+            let is_text = matches!(watermark.content, WatermarkContent::Text(_));
+            if ui.radio(is_text, "Text").clicked() && !is_text {
+                watermark.content = WatermarkContent::Text(String::new());
+            }
+            if ui.radio(!is_text, "Image").clicked() && is_text {
+                watermark.content = WatermarkContent::Image(Vec::new());
+            }
+        });
+
+        match &mut watermark.content {
+            WatermarkContent::Text(text) => {
+                ui.text_edit_singleline(text);
+                ui.horizontal(|ui| {
+                    ui.label("Color:");
+                    ui.color_edit_button_srgba(&mut watermark.color);
+                });
+            }
+            WatermarkContent::Image(bytes) => {
+                ui.horizontal(|ui| {
+                    ui.text_edit_singleline(&mut self.watermark_image_path);
+                    if ui.button("Load").clicked() {
+                        match std::fs::read(&self.watermark_image_path) {
+                            Ok(data) => *bytes = data,
+                            Err(err) => {
+                                self.status =
+                                    Some(Err(format!("Failed to load watermark: {}", err)))
+                            }
+                        }
+                    }
+                });
+                if bytes.is_empty() {
+                    ui.label("No image loaded");
+                }
+            }
+        }
+
+        egui::ComboBox::from_label("Position")
+            .selected_text(watermark.anchor.label())
+            .show_ui(ui, |ui| {
+                for anchor in WatermarkAnchor::ALL {
+                    ui.selectable_value(&mut watermark.anchor, anchor, anchor.label());
+                }
+            });
+        ui.add(egui::Slider::new(&mut watermark.opacity, 0.0..=1.0).text("Opacity"));
+        ui.add(egui::Slider::new(&mut watermark.size, 4.0..=256.0).text("Size"));
+        ui.add(egui::Slider::new(&mut watermark.margin, 0.0..=100.0).text("Margin"));
+    }
+
+    fn metadata_ui(&mut self, ui: &mut egui::Ui) {
+        if self.settings.privacy_scrub {
+            ui.label("Privacy scrub: no metadata is embedded");
+            return;
+        }
+        ui.checkbox(&mut self.metadata.embed, "Embed metadata");
+        ui.add_enabled_ui(self.metadata.embed, |ui| {
+            egui::Grid::new("export_metadata_grid").show(ui, |ui| {
+                ui.label("Title");
+                ui.text_edit_singleline(&mut self.metadata.title);
+                ui.end_row();
+                ui.label("Author");
+                ui.text_edit_singleline(&mut self.metadata.author);
+                ui.end_row();
+                ui.label("Copyright");
+                ui.text_edit_singleline(&mut self.metadata.copyright);
+                ui.end_row();
+            });
+        });
+    }
+
+    /// Choice of the artboard to export, for documents that have artboards
+    fn artboard_ui(&mut self, ui: &mut egui::Ui, editor_model: &EditorModel) {
+        if editor_model.artboards.is_empty() {
+            return;
+        }
+        // Forget a choice that no longer exists, e.g. after opening another document
+        if self
+            .artboard_id
+            .is_some_and(|artboard_id| editor_model.find_artboard(artboard_id).is_none())
+        {
+            self.artboard_id = None;
+        }
+
+        let name = |artboard_id: Option<usize>| match artboard_id {
+            Some(artboard_id) => editor_model
+                .find_artboard(artboard_id)
+                .map_or(String::new(), |artboard| artboard.name.clone()),
+            None => format!(
+                "Active ({})",
+                editor_model
+                    .active_artboard()
+                    .map_or("", |artboard| artboard.name.as_str())
+            ),
+        };
+        egui::ComboBox::from_label("Artboard")
+            .selected_text(name(self.artboard_id))
+            .show_ui(ui, |ui| {
+                ui.selectable_value(&mut self.artboard_id, None, name(None));
+                for artboard in &editor_model.artboards {
+                    ui.selectable_value(
+                        &mut self.artboard_id,
+                        Some(artboard.id),
+                        artboard.name.as_str(),
+                    );
+                }
+            });
+    }
+
+    /// Settings for the next export: the chosen options plus this dialog's metadata and background
+    pub fn export_settings(&self) -> ExportSettings {
+        ExportSettings {
+            metadata: self.metadata.clone(),
+            background_color: self.background_color,
+            post_export_hook: self.post_export_hook.clone(),
+            artboard_id: self.artboard_id,
+            ..self.settings.clone()
+        }
+    }
+
+    fn output_ui(&mut self, ui: &mut egui::Ui, ctx: &egui::Context, editor_model: &EditorModel) {
+        self.artboard_ui(ui, editor_model);
+        if let Ok(bounds) = export::export_bounds(editor_model, &self.export_settings()) {
+            let size = bounds.size();
+            let pixels = size * self.settings.scale;
+            let units = editor_model.units;
+            let mut text = format!("Output: {:.0} × {:.0} px", pixels.x, pixels.y);
+            if units.unit != Unit::Px {
+                text += &format!(" ({} at {} dpi)", units.format_size(size), units.dpi);
+            }
+            ui.label(text);
+        }
+
+        ui.horizontal(|ui| {
+            ui.label("File:");
+            ui.text_edit_singleline(&mut self.path);
+        });
+
+        ui.horizontal(|ui| {
+            if ui.button("Export PNG").clicked() {
+                self.status = Some(self.export(ctx, editor_model));
+            }
+            if ui.button("Export SVG").clicked() {
+                self.status = Some(self.export_svg(ctx, editor_model));
+            }
+            if ui.button("Export Slideshow").clicked() {
+                self.status = Some(self.export_slideshow(ctx, editor_model));
+            }
+        });
+        if editor_model.artboards.len() > 1
+            && ui
+                .button("Export all artboards")
+                .on_hover_text("One PNG per artboard, named after the file and the artboard")
+                .clicked()
+        {
+            self.status = Some(self.export_artboards(ctx, editor_model));
+        }
+
+        match &self.status {
+            Some(Ok(message)) => {
+                ui.label(message);
+            }
+            Some(Err(message)) => {
+                ui.colored_label(egui::Color32::RED, message);
+            }
+            None => {}
+        }
+    }
+
+    #[cfg(not(target_arch = "wasm32"))]
+    fn export(&self, ctx: &egui::Context, editor_model: &EditorModel) -> Result<String, String> {
+        let path = std::path::Path::new(&self.path);
+        let settings = self.export_settings();
+        export::export_png_to_file(path, editor_model, &settings, ctx)
+            .map(|()| finish_export(path, &settings))
+            .map_err(|err| err.to_string())
+    }
+
+    #[cfg(not(target_arch = "wasm32"))]
+    fn export_svg(
+        &self,
+        ctx: &egui::Context,
+        editor_model: &EditorModel,
+    ) -> Result<String, String> {
+        let path = std::path::Path::new(&self.path).with_extension("svg");
+        let settings = self.export_settings();
+        export::export_svg_to_file(&path, editor_model, &settings, ctx)
+            .map(|()| finish_export(&path, &settings))
+            .map_err(|err| err.to_string())
+    }
+
+    #[cfg(not(target_arch = "wasm32"))]
+    fn export_slideshow(
+        &self,
+        ctx: &egui::Context,
+        editor_model: &EditorModel,
+    ) -> Result<String, String> {
+        let path = std::path::Path::new(&self.path).with_extension("html");
+        let settings = self.export_settings();
+        export::export_slideshow_to_file(&path, editor_model, &settings, ctx)
+            .map(|()| finish_export(&path, &settings))
+            .map_err(|err| err.to_string())
+    }
+
+    #[cfg(not(target_arch = "wasm32"))]
+    fn export_artboards(
+        &self,
+        ctx: &egui::Context,
+        editor_model: &EditorModel,
+    ) -> Result<String, String> {
+        let path = std::path::Path::new(&self.path);
+        let mut messages = Vec::new();
+        for artboard in &editor_model.artboards {
+            let settings = ExportSettings {
+                artboard_id: Some(artboard.id),
+                ..self.export_settings()
+            };
+            let artboard_path = artboard_export_path(path, &artboard.name);
+            export::export_png_to_file(&artboard_path, editor_model, &settings, ctx)
+                .map_err(|err| format!("{}: {}", artboard.name, err))?;
+            messages.push(finish_export(&artboard_path, &settings));
+        }
+        Ok(messages.join("\n"))
+    }
+
+    #[cfg(target_arch = "wasm32")]
+    fn export(&self, ctx: &egui::Context, editor_model: &EditorModel) -> Result<String, String> {
+        let bytes = export::export_png(editor_model, &self.export_settings(), ctx)
+            .map_err(|err| err.to_string())?;
+        log::warn!(
+            "Saving files is not supported on the web yet ({} bytes)",
+            bytes.len()
+        );
+        Err("Saving files is not supported on the web yet".to_string())
+    }
+
+    #[cfg(target_arch = "wasm32")]
+    fn export_svg(
+        &self,
+        ctx: &egui::Context,
+        editor_model: &EditorModel,
+    ) -> Result<String, String> {
+        let svg = export::export_svg(editor_model, &self.export_settings(), ctx)
+            .map_err(|err| err.to_string())?;
+        log::warn!(
+            "Saving files is not supported on the web yet ({} bytes)",
+            svg.len()
+        );
+        Err("Saving files is not supported on the web yet".to_string())
+    }
+
+    #[cfg(target_arch = "wasm32")]
+    fn export_artboards(
+        &self,
+        _ctx: &egui::Context,
+        _editor_model: &EditorModel,
+    ) -> Result<String, String> {
+        Err("Saving files is not supported on the web yet".to_string())
+    }
+
+    #[cfg(target_arch = "wasm32")]
+    fn export_slideshow(
+        &self,
+        ctx: &egui::Context,
+        editor_model: &EditorModel,
+    ) -> Result<String, String> {
+        let html = export::export_slideshow(editor_model, &self.export_settings(), ctx)
+            .map_err(|err| err.to_string())?;
+        log::warn!(
+            "Saving files is not supported on the web yet ({} bytes)",
+            html.len()
+        );
+        Err("Saving files is not supported on the web yet".to_string())
+    }
+}
+
+/// Path for one artboard of a multi-artboard export: `name.png` becomes `name-artboard.png`
+#[cfg(not(target_arch = "wasm32"))]
+fn artboard_export_path(path: &std::path::Path, artboard_name: &str) -> std::path::PathBuf {
+    let stem = path.file_stem().unwrap_or_default().to_string_lossy();
+    let suffix: String = artboard_name
+        .chars()
+        .map(|c| if c.is_alphanumeric() { c } else { '-' })
+        .collect();
+    path.with_file_name(format!("{}-{}.png", stem, suffix.to_lowercase()))
+}
+
+/// Run the post-export hook for a written file and describe the outcome
+pub(crate) fn finish_export(path: &std::path::Path, settings: &ExportSettings) -> String {
+    let exported = format!("Exported to {}", path.display());
+    match export::run_post_export_hook(&settings.post_export_hook, path) {
+        Ok(true) => format!("{}, post-export hook started", exported),
+        Ok(false) => exported,
+        Err(err) => format!("{} ({})", exported, err),
+    }
+}
+
+impl Default for ExportDialog {
+    fn default() -> Self {
+        Self::new(export::default_presets())
+    }
+}
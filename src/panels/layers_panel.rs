@@ -0,0 +1,59 @@
+use super::{artboards_panel, slices_panel, steps_panel};
+use crate::PaintApp;
+use crate::command::Command;
+use crate::element::{Element, ElementId};
+use egui;
+
+/// The document's elements, topmost first, then its artboards and step badges
+///
+/// Clicking an element selects it alone; Shift-clicking adds it to the
+/// selection or takes it out. Locked elements are listed but can't be picked.
+pub fn layers_panel(app: &mut PaintApp, ui: &mut egui::Ui) {
+    let rows: Vec<(ElementId, String, bool, bool)> = app
+        .editor_model()
+        .elements
+        .iter()
+        .rev()
+        .map(|element| {
+            (
+                element.id(),
+                element.display_name(),
+                element.metadata().is_locked(),
+                app.editor_model().is_element_selected(element.id()),
+            )
+        })
+        .collect();
+
+    if rows.is_empty() {
+        ui.weak("No elements yet");
+    }
+    let mut command = None;
+    for (id, name, locked, selected) in rows {
+        let label = if locked {
+            format!("🔒 {}", name)
+        } else {
+            name
+        };
+        let response = ui.add_enabled(!locked, egui::SelectableLabel::new(selected, label));
+        if response.clicked() {
+            command = if ui.input(|input| input.modifiers.shift) {
+                Some(Command::ToggleSelection(id))
+            } else {
+                Some(Command::Batch {
+                    description: "Select Element",
+                    commands: vec![
+                        Command::new_clear_selection(app.editor_model()),
+                        Command::SelectElement(id),
+                    ],
+                })
+            };
+        }
+    }
+    if let Some(command) = command {
+        app.execute_command(command);
+    }
+
+    artboards_panel(app, ui);
+    slices_panel(app, ui);
+    steps_panel(app, ui);
+}
@@ -0,0 +1,114 @@
+use crate::log_console::{LogRecord, clear_records, records};
+
+/// Levels the console can show down to
+const LEVELS: [log::LevelFilter; 5] = [
+    log::LevelFilter::Error,
+    log::LevelFilter::Warn,
+    log::LevelFilter::Info,
+    log::LevelFilter::Debug,
+    log::LevelFilter::Trace,
+];
+
+/// Log console window: recent log records, filtered by level and module
+///
+/// Handy on the web, where there's no terminal to read stderr from.
+pub struct LogConsole {
+    pub open: bool,
+    // Least severe level shown
+    level: log::LevelFilter,
+    // Only records from modules whose path contains this
+    module: String,
+}
+
+impl Default for LogConsole {
+    fn default() -> Self {
+        Self {
+            open: false,
+            level: log::LevelFilter::Info,
+            module: String::new(),
+        }
+    }
+}
+
+impl LogConsole {
+    /// Show the Log console if it is open
+    pub fn show(&mut self, ctx: &egui::Context) {
+        let mut open = self.open;
+        egui::Window::new("Log")
+            .open(&mut open)
+            .default_size([560.0, 320.0])
+            .show(ctx, |ui| {
+                let shown: Vec<LogRecord> = records()
+                    .into_iter()
+                    .filter(|record| record.matches(self.level, &self.module))
+                    .collect();
+                self.filters_ui(ui, &shown);
+                ui.separator();
+                records_ui(ui, &shown);
+            });
+        self.open = open;
+        if open {
+            // Pick up records logged while nothing else changes
+            ctx.request_repaint_after(std::time::Duration::from_secs(1));
+        }
+    }
+
+    fn filters_ui(&mut self, ui: &mut egui::Ui, shown: &[LogRecord]) {
+        ui.horizontal(|ui| {
+            egui::ComboBox::from_id_salt("log_level")
+                .selected_text(self.level.as_str())
+                .show_ui(ui, |ui| {
+                    for level in LEVELS {
+                        ui.selectable_value(&mut self.level, level, level.as_str());
+                    }
+                });
+            ui.add(
+                egui::TextEdit::singleline(&mut self.module)
+                    .hint_text("Module")
+                    .desired_width(140.0),
+            )
+            .on_hover_text("Only records from modules whose path contains this");
+            if ui
+                .add_enabled(!shown.is_empty(), egui::Button::new("Copy"))
+                .on_hover_text("Copy the records shown to the clipboard")
+                .clicked()
+            {
+                let text: Vec<String> = shown.iter().map(LogRecord::format).collect();
+                ui.ctx().copy_text(text.join("\n"));
+            }
+            if ui.button("Clear").clicked() {
+                clear_records();
+            }
+        });
+    }
+}
+
+/// The records, newest at the bottom, kept scrolled there as more arrive
+fn records_ui(ui: &mut egui::Ui, shown: &[LogRecord]) {
+    if shown.is_empty() {
+        ui.weak("Nothing logged at this level");
+        return;
+    }
+    let row_height = ui.text_style_height(&egui::TextStyle::Monospace);
+    egui::ScrollArea::both()
+        .auto_shrink([false, false])
+        .stick_to_bottom(true)
+        .show_rows(ui, row_height, shown.len(), |ui, rows| {
+            for record in &shown[rows] {
+                let color = match record.level {
+                    log::Level::Error => ui.visuals().error_fg_color,
+                    log::Level::Warn => ui.visuals().warn_fg_color,
+                    log::Level::Info => ui.visuals().text_color(),
+                    log::Level::Debug | log::Level::Trace => ui.visuals().weak_text_color(),
+                };
+                ui.add(
+                    egui::Label::new(
+                        egui::RichText::new(record.format())
+                            .monospace()
+                            .color(color),
+                    )
+                    .extend(),
+                );
+            }
+        });
+}
@@ -0,0 +1,77 @@
+use crate::PaintApp;
+use crate::memory::{COMPACT_HISTORY_STEPS, format_bytes};
+use egui;
+
+/// Estimated memory of the document, texture cache and undo history, with
+/// buttons to free what can be rebuilt or spared
+pub fn memory_panel(app: &mut PaintApp, ui: &mut egui::Ui) {
+    let usage = app.memory_usage();
+    egui::CollapsingHeader::new("Memory")
+        .default_open(true)
+        .show(ui, |ui| {
+            egui::Grid::new("memory_usage")
+                .num_columns(3)
+                .striped(true)
+                .show(ui, |ui| {
+                    let elements = &usage.elements;
+                    let rows = [
+                        (
+                            "Elements",
+                            elements.bytes,
+                            format!(
+                                "{} elements, {} points, {} of images",
+                                elements.count,
+                                elements.points,
+                                format_bytes(elements.image_bytes)
+                            ),
+                        ),
+                        (
+                            "Reference image",
+                            usage.reference_image_bytes,
+                            String::new(),
+                        ),
+                        ("Texture cache", usage.texture_bytes, String::new()),
+                        (
+                            "Undo history",
+                            usage.history_bytes,
+                            format!("{} steps", usage.history_steps),
+                        ),
+                    ];
+                    for (part, bytes, contents) in rows {
+                        ui.label(part);
+                        ui.label(format_bytes(bytes));
+                        ui.weak(contents);
+                        ui.end_row();
+                    }
+                    ui.strong("Total");
+                    ui.strong(format_bytes(usage.total_bytes()));
+                    ui.end_row();
+                });
+
+            ui.horizontal(|ui| {
+                if ui
+                    .button("Trim caches")
+                    .on_hover_text(
+                        "Drop textures of elements not on screen and decoded image pixels; \
+                         they're rebuilt when needed",
+                    )
+                    .clicked()
+                {
+                    app.trim_caches();
+                }
+                if ui
+                    .add_enabled(
+                        usage.history_steps > COMPACT_HISTORY_STEPS,
+                        egui::Button::new("Compact history"),
+                    )
+                    .on_hover_text(format!(
+                        "Keep only the last {} undo steps and drop the redo steps",
+                        COMPACT_HISTORY_STEPS
+                    ))
+                    .clicked()
+                {
+                    app.compact_history();
+                }
+            });
+        });
+}
@@ -1,4 +1,54 @@
+pub mod array_panel;
+pub mod artboards_panel;
+pub mod bug_report_panel;
 pub mod central_panel;
+pub mod document_properties_panel;
+pub mod export_panel;
+pub mod layers_panel;
+pub mod log_panel;
+pub mod memory_panel;
+pub mod new_document_panel;
+pub mod panel_layout;
+pub mod passphrase_panel;
+pub mod preferences_panel;
+#[cfg(not(target_arch = "wasm32"))]
+pub mod print_panel;
+pub mod quick_sketch_panel;
+pub mod relink_panel;
+pub mod replace_colors_panel;
+pub mod replay_panel;
+pub mod rulers;
+pub mod slices_panel;
+pub mod status_bar;
+pub mod steps_panel;
 pub mod tools_panel;
+pub mod transform_panel;
+pub mod tutorial_panel;
+pub mod unsaved_changes_panel;
+pub use array_panel::*;
+pub use artboards_panel::*;
+pub use bug_report_panel::*;
 pub use central_panel::*;
+pub use document_properties_panel::*;
+pub use export_panel::*;
+pub use layers_panel::*;
+pub use log_panel::*;
+pub use memory_panel::*;
+pub use new_document_panel::*;
+pub use panel_layout::*;
+pub use passphrase_panel::*;
+pub use preferences_panel::*;
+#[cfg(not(target_arch = "wasm32"))]
+pub use print_panel::*;
+pub use quick_sketch_panel::*;
+pub use relink_panel::*;
+pub use replace_colors_panel::*;
+pub use replay_panel::*;
+pub use rulers::*;
+pub use slices_panel::*;
+pub use status_bar::*;
+pub use steps_panel::*;
 pub use tools_panel::*;
+pub use transform_panel::*;
+pub use tutorial_panel::*;
+pub use unsaved_changes_panel::*;
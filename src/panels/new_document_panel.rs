@@ -0,0 +1,120 @@
+use crate::new_document::{CanvasPreset, NewDocumentSettings, Template};
+use egui;
+
+/// New Document window: canvas size, resolution, background and template
+#[derive(Default)]
+pub struct NewDocumentDialog {
+    pub open: bool,
+    /// Kept between uses, so the next document starts like the last one
+    pub settings: NewDocumentSettings,
+}
+
+impl NewDocumentDialog {
+    /// Show the New Document window if it is open
+    ///
+    /// Returns true when the user asks for the document to be created.
+    pub fn show(&mut self, ctx: &egui::Context) -> bool {
+        let mut open = self.open;
+        let mut create = false;
+        let mut cancel = false;
+        egui::Window::new("New Document")
+            .open(&mut open)
+            .resizable(false)
+            .show(ctx, |ui| {
+                egui::Grid::new("new_document_grid")
+                    .num_columns(2)
+                    .show(ui, |ui| self.settings_ui(ui));
+                ui.separator();
+                ui.horizontal(|ui| {
+                    create = ui.button("Create").clicked();
+                    cancel = ui.button("Cancel").clicked();
+                });
+            });
+        self.open = open && !create && !cancel;
+        create
+    }
+
+    fn settings_ui(&mut self, ui: &mut egui::Ui) {
+        let settings = &mut self.settings;
+
+        ui.label("Canvas");
+        egui::ComboBox::from_id_salt("new_document_preset")
+            .selected_text(settings.preset.label())
+            .show_ui(ui, |ui| {
+                for preset in CanvasPreset::ALL {
+                    if ui
+                        .selectable_label(settings.preset == preset, preset.label())
+                        .clicked()
+                    {
+                        settings.set_preset(preset);
+                    }
+                }
+            });
+        ui.end_row();
+
+        let bounded = settings.is_bounded();
+        ui.label("Size");
+        ui.add_enabled_ui(bounded, |ui| {
+            ui.horizontal(|ui| {
+                let mut size = settings.size;
+                ui.add(
+                    egui::DragValue::new(&mut size.x)
+                        .range(1.0..=10_000.0)
+                        .suffix(" px"),
+                );
+                ui.label("×");
+                ui.add(
+                    egui::DragValue::new(&mut size.y)
+                        .range(1.0..=10_000.0)
+                        .suffix(" px"),
+                );
+                if size != settings.size {
+                    settings.size = size;
+                    settings.preset = CanvasPreset::Custom;
+                }
+            });
+        });
+        ui.end_row();
+
+        ui.label("Resolution");
+        let mut dpi = settings.dpi;
+        ui.add(
+            egui::DragValue::new(&mut dpi)
+                .range(1.0..=2400.0)
+                .suffix(" dpi"),
+        )
+        .on_hover_text("Paper sizes are converted to pixels at this resolution");
+        if dpi != settings.dpi {
+            settings.set_dpi(dpi);
+        }
+        ui.end_row();
+
+        ui.label("Background");
+        background_ui(ui, &mut settings.background);
+        ui.end_row();
+
+        ui.label("Template");
+        ui.add_enabled_ui(bounded, |ui| {
+            egui::ComboBox::from_id_salt("new_document_template")
+                .selected_text(settings.template.label())
+                .show_ui(ui, |ui| {
+                    for template in Template::ALL {
+                        ui.selectable_value(&mut settings.template, template, template.label());
+                    }
+                });
+        });
+        ui.end_row();
+    }
+}
+
+/// A document's own canvas color, or the one in the preferences when unchecked
+pub(crate) fn background_ui(ui: &mut egui::Ui, background: &mut Option<egui::Color32>) {
+    ui.horizontal(|ui| {
+        let mut own = background.is_some();
+        let mut color = background.unwrap_or(egui::Color32::WHITE);
+        ui.checkbox(&mut own, "Own color")
+            .on_hover_text("Otherwise the canvas background from the preferences is used");
+        ui.add_enabled_ui(own, |ui| ui.color_edit_button_srgba(&mut color));
+        *background = own.then_some(color);
+    });
+}
@@ -0,0 +1,346 @@
+use super::{history_ui, layers_panel, properties_ui, tools_ui};
+use crate::PaintApp;
+use egui;
+use serde::{Deserialize, Serialize};
+
+/// Width of a side of docked panels until the user drags it
+const DEFAULT_DOCK_WIDTH: f32 = 220.0;
+
+/// A panel that can be docked beside the canvas
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash, Serialize, Deserialize)]
+pub enum DockPanel {
+    Tools,
+    Properties,
+    Layers,
+    History,
+}
+
+impl DockPanel {
+    pub const ALL: [DockPanel; 4] = [
+        DockPanel::Tools,
+        DockPanel::Properties,
+        DockPanel::Layers,
+        DockPanel::History,
+    ];
+
+    pub fn title(self) -> &'static str {
+        match self {
+            DockPanel::Tools => "Tools",
+            DockPanel::Properties => "Properties",
+            DockPanel::Layers => "Layers",
+            DockPanel::History => "History",
+        }
+    }
+
+    fn show(self, app: &mut PaintApp, ui: &mut egui::Ui) {
+        match self {
+            DockPanel::Tools => tools_ui(app, ui),
+            DockPanel::Properties => properties_ui(app, ui),
+            DockPanel::Layers => layers_panel(app, ui),
+            DockPanel::History => history_ui(app, ui),
+        }
+    }
+}
+
+/// Which side of the canvas a panel is docked to
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Serialize, Deserialize)]
+pub enum DockSide {
+    Left,
+    Right,
+}
+
+impl DockSide {
+    fn other(self) -> Self {
+        match self {
+            DockSide::Left => DockSide::Right,
+            DockSide::Right => DockSide::Left,
+        }
+    }
+
+    /// Id of the side panel, under which egui keeps its width
+    fn panel_id(self) -> egui::Id {
+        match self {
+            DockSide::Left => egui::Id::new("dock_left"),
+            DockSide::Right => egui::Id::new("dock_right"),
+        }
+    }
+}
+
+/// Where a panel is docked and whether it's collapsed to its title
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Serialize, Deserialize)]
+pub struct PanelPlacement {
+    pub panel: DockPanel,
+    pub side: DockSide,
+    pub collapsed: bool,
+}
+
+/// Arrangement of the docked panels, saved between sessions
+///
+/// Panels are listed top to bottom; each side shows the ones docked to it in
+/// that order. The sides' widths are kept by egui with its other memory.
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
+pub struct PanelLayout {
+    pub placements: Vec<PanelPlacement>,
+}
+
+impl Default for PanelLayout {
+    fn default() -> Self {
+        let placement = |panel, side| PanelPlacement {
+            panel,
+            side,
+            collapsed: false,
+        };
+        Self {
+            placements: vec![
+                placement(DockPanel::Tools, DockSide::Left),
+                placement(DockPanel::Properties, DockSide::Left),
+                placement(DockPanel::Layers, DockSide::Right),
+                placement(DockPanel::History, DockSide::Right),
+            ],
+        }
+    }
+}
+
+impl PanelLayout {
+    /// Fix up a layout saved by another version: drop repeated or unknown
+    /// panels and dock missing ones where the default layout has them
+    pub fn sanitized(mut self) -> Self {
+        let mut seen = Vec::new();
+        self.placements.retain(|placement| {
+            let first = !seen.contains(&placement.panel);
+            seen.push(placement.panel);
+            first
+        });
+        for placement in Self::default().placements {
+            if !seen.contains(&placement.panel) {
+                self.placements.push(placement);
+            }
+        }
+        self
+    }
+
+    /// The panels docked to `side`, top to bottom
+    pub fn panels_on(&self, side: DockSide) -> Vec<PanelPlacement> {
+        self.placements
+            .iter()
+            .filter(|placement| placement.side == side)
+            .copied()
+            .collect()
+    }
+
+    fn placement_mut(&mut self, panel: DockPanel) -> Option<&mut PanelPlacement> {
+        self.placements
+            .iter_mut()
+            .find(|placement| placement.panel == panel)
+    }
+
+    pub fn toggle_collapsed(&mut self, panel: DockPanel) {
+        if let Some(placement) = self.placement_mut(panel) {
+            placement.collapsed = !placement.collapsed;
+        }
+    }
+
+    /// Dock a panel to the bottom of the other side
+    pub fn move_to_other_side(&mut self, panel: DockPanel) {
+        let Some(index) = self.index_of(panel) else {
+            return;
+        };
+        let mut placement = self.placements.remove(index);
+        placement.side = placement.side.other();
+        self.placements.push(placement);
+    }
+
+    /// Swap a panel with the one above it (`up`) or below it on its side
+    ///
+    /// Returns false if it's already at that end.
+    pub fn move_within_side(&mut self, panel: DockPanel, up: bool) -> bool {
+        let Some(index) = self.index_of(panel) else {
+            return false;
+        };
+        let side = self.placements[index].side;
+        let neighbour = if up {
+            self.placements[..index]
+                .iter()
+                .rposition(|placement| placement.side == side)
+        } else {
+            self.placements[index + 1..]
+                .iter()
+                .position(|placement| placement.side == side)
+                .map(|offset| index + 1 + offset)
+        };
+        match neighbour {
+            Some(neighbour) => {
+                self.placements.swap(index, neighbour);
+                true
+            }
+            None => false,
+        }
+    }
+
+    fn index_of(&self, panel: DockPanel) -> Option<usize> {
+        self.placements
+            .iter()
+            .position(|placement| placement.panel == panel)
+    }
+}
+
+/// What a panel's menu asked to change
+enum LayoutAction {
+    ToggleCollapsed(DockPanel),
+    MoveToOtherSide(DockPanel),
+    Move { panel: DockPanel, up: bool },
+    Reset,
+}
+
+/// Show the docked panels on both sides of the canvas
+///
+/// Call before the central panel so the canvas gets the space that's left.
+pub fn docked_panels(app: &mut PaintApp, ctx: &egui::Context) {
+    let layout = app.panel_layout().clone();
+    let mut actions = Vec::new();
+
+    for side in [DockSide::Left, DockSide::Right] {
+        let placements = layout.panels_on(side);
+        if placements.is_empty() {
+            continue;
+        }
+        let panel = match side {
+            DockSide::Left => egui::SidePanel::left(side.panel_id()),
+            DockSide::Right => egui::SidePanel::right(side.panel_id()),
+        };
+        panel
+            .resizable(true)
+            .default_width(DEFAULT_DOCK_WIDTH)
+            .show(ctx, |ui| {
+                egui::ScrollArea::vertical().show(ui, |ui| {
+                    for (index, placement) in placements.iter().enumerate() {
+                        let is_last = index + 1 == placements.len();
+                        panel_header(ui, placement, index == 0, is_last, &mut actions);
+                        if !placement.collapsed {
+                            placement.panel.show(app, ui);
+                        }
+                        ui.separator();
+                    }
+                });
+            });
+    }
+
+    let layout = app.panel_layout_mut();
+    for action in actions {
+        match action {
+            LayoutAction::ToggleCollapsed(panel) => layout.toggle_collapsed(panel),
+            LayoutAction::MoveToOtherSide(panel) => layout.move_to_other_side(panel),
+            LayoutAction::Move { panel, up } => {
+                layout.move_within_side(panel, up);
+            }
+            LayoutAction::Reset => {
+                *layout = PanelLayout::default();
+                // Forget the dragged widths too
+                ctx.data_mut(|data| {
+                    for side in [DockSide::Left, DockSide::Right] {
+                        data.remove::<egui::containers::panel::PanelState>(side.panel_id());
+                    }
+                });
+            }
+        }
+    }
+}
+
+/// Title row of a docked panel: click to collapse, with a menu to move it
+fn panel_header(
+    ui: &mut egui::Ui,
+    placement: &PanelPlacement,
+    is_first: bool,
+    is_last: bool,
+    actions: &mut Vec<LayoutAction>,
+) {
+    let panel = placement.panel;
+    ui.horizontal(|ui| {
+        let icon = if placement.collapsed { "⏵" } else { "⏷" };
+        if ui
+            .selectable_label(
+                false,
+                egui::RichText::new(format!("{} {}", icon, panel.title())).heading(),
+            )
+            .on_hover_text(if placement.collapsed {
+                "Expand"
+            } else {
+                "Collapse"
+            })
+            .clicked()
+        {
+            actions.push(LayoutAction::ToggleCollapsed(panel));
+        }
+        ui.with_layout(egui::Layout::right_to_left(egui::Align::Center), |ui| {
+            ui.menu_button("⋯", |ui| {
+                let other_side = match placement.side {
+                    DockSide::Left => "Dock right",
+                    DockSide::Right => "Dock left",
+                };
+                if ui.button(other_side).clicked() {
+                    actions.push(LayoutAction::MoveToOtherSide(panel));
+                    ui.close_menu();
+                }
+                if ui
+                    .add_enabled(!is_first, egui::Button::new("Move up"))
+                    .clicked()
+                {
+                    actions.push(LayoutAction::Move { panel, up: true });
+                    ui.close_menu();
+                }
+                if ui
+                    .add_enabled(!is_last, egui::Button::new("Move down"))
+                    .clicked()
+                {
+                    actions.push(LayoutAction::Move { panel, up: false });
+                    ui.close_menu();
+                }
+                ui.separator();
+                if ui.button("Reset layout").clicked() {
+                    actions.push(LayoutAction::Reset);
+                    ui.close_menu();
+                }
+            });
+        });
+    });
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_panels_move_between_and_within_sides() {
+        let mut layout = PanelLayout::default();
+        let panels = |layout: &PanelLayout, side| -> Vec<DockPanel> {
+            layout.panels_on(side).iter().map(|p| p.panel).collect()
+        };
+
+        layout.move_to_other_side(DockPanel::Tools);
+        assert_eq!(panels(&layout, DockSide::Left), vec![DockPanel::Properties]);
+        assert_eq!(
+            panels(&layout, DockSide::Right),
+            vec![DockPanel::Layers, DockPanel::History, DockPanel::Tools]
+        );
+
+        // Moving skips panels on the other side and stops at the ends
+        assert!(layout.move_within_side(DockPanel::Tools, true));
+        assert!(!layout.move_within_side(DockPanel::Properties, true));
+        assert_eq!(
+            panels(&layout, DockSide::Right),
+            vec![DockPanel::Layers, DockPanel::Tools, DockPanel::History]
+        );
+
+        layout.toggle_collapsed(DockPanel::History);
+        assert!(layout.panels_on(DockSide::Right)[2].collapsed);
+
+        // Saved layouts missing a panel or repeating one are fixed up
+        layout.placements.remove(0);
+        layout.placements.push(layout.placements[0]);
+        let layout = layout.sanitized();
+        assert_eq!(layout.placements.len(), DockPanel::ALL.len());
+        for panel in DockPanel::ALL {
+            assert!(layout.placements.iter().any(|p| p.panel == panel));
+        }
+    }
+}
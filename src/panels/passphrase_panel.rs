@@ -0,0 +1,132 @@
+use egui;
+
+/// What a passphrase is being asked for
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum PassphrasePurpose {
+    /// Opening an encrypted document
+    Open,
+    /// Encrypting the current document; asked twice to catch typos
+    Protect,
+}
+
+/// Answer to the passphrase prompt
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub enum PassphraseChoice {
+    Submit(String),
+    Cancel,
+}
+
+/// Modal asking for a document passphrase
+pub struct PassphrasePrompt {
+    pub purpose: PassphrasePurpose,
+    passphrase: String,
+    confirmation: String,
+    error: Option<String>,
+}
+
+impl PassphrasePrompt {
+    pub fn new(purpose: PassphrasePurpose) -> Self {
+        Self {
+            purpose,
+            passphrase: String::new(),
+            confirmation: String::new(),
+            error: None,
+        }
+    }
+
+    /// Show why the last passphrase didn't work, clearing the fields for another try
+    pub fn set_error(&mut self, error: impl Into<String>) {
+        self.passphrase.clear();
+        self.confirmation.clear();
+        self.error = Some(error.into());
+    }
+
+    /// Ask for the passphrase
+    ///
+    /// Shown as a modal every frame until it returns the user's choice. Escape or
+    /// clicking outside counts as Cancel.
+    pub fn show(&mut self, ctx: &egui::Context, document_name: &str) -> Option<PassphraseChoice> {
+        let modal = egui::Modal::new(egui::Id::new("passphrase_prompt")).show(ctx, |ui| {
+            let mut submitted = false;
+            match self.purpose {
+                PassphrasePurpose::Open => {
+                    ui.heading("Encrypted document");
+                    ui.label(format!("Enter the passphrase for \"{}\".", document_name));
+                }
+                PassphrasePurpose::Protect => {
+                    ui.heading("Protect document");
+                    ui.label(format!(
+                        "\"{}\" will be saved encrypted with this passphrase.",
+                        document_name
+                    ));
+                    ui.label("There is no way to open it without the passphrase.");
+                }
+            }
+
+            egui::Grid::new("passphrase_grid").show(ui, |ui| {
+                ui.label("Passphrase");
+                let response =
+                    ui.add(egui::TextEdit::singleline(&mut self.passphrase).password(true));
+                if ui.memory(|memory| memory.focused().is_none()) {
+                    response.request_focus();
+                }
+                let entered =
+                    response.lost_focus() && ui.input(|i| i.key_pressed(egui::Key::Enter));
+                // When protecting, only Enter in the repeat field submits
+                submitted |= entered && self.purpose == PassphrasePurpose::Open;
+                ui.end_row();
+
+                if self.purpose == PassphrasePurpose::Protect {
+                    ui.label("Repeat");
+                    let response =
+                        ui.add(egui::TextEdit::singleline(&mut self.confirmation).password(true));
+                    submitted |=
+                        response.lost_focus() && ui.input(|i| i.key_pressed(egui::Key::Enter));
+                    ui.end_row();
+                }
+            });
+            if let Some(error) = &self.error {
+                ui.colored_label(egui::Color32::RED, error);
+            }
+
+            ui.separator();
+            let choice = ui
+                .horizontal(|ui| {
+                    let label = match self.purpose {
+                        PassphrasePurpose::Open => "Open",
+                        PassphrasePurpose::Protect => "Encrypt & save",
+                    };
+                    if ui.button(label).clicked() || submitted {
+                        Some(PassphraseChoice::Submit(self.passphrase.clone()))
+                    } else if ui.button("Cancel").clicked() {
+                        Some(PassphraseChoice::Cancel)
+                    } else {
+                        None
+                    }
+                })
+                .inner;
+
+            // Catch empty and mistyped passphrases before anything is encrypted with them
+            match choice {
+                Some(PassphraseChoice::Submit(passphrase)) if passphrase.is_empty() => {
+                    self.set_error("Enter a passphrase");
+                    None
+                }
+                Some(PassphraseChoice::Submit(passphrase))
+                    if self.purpose == PassphrasePurpose::Protect
+                        && passphrase != self.confirmation =>
+                {
+                    self.set_error("The passphrases don't match");
+                    None
+                }
+                choice => choice,
+            }
+        });
+
+        if modal.should_close() {
+            Some(PassphraseChoice::Cancel)
+        } else {
+            modal.inner
+        }
+    }
+}
@@ -0,0 +1,399 @@
+use crate::element::Resampling;
+use crate::export;
+use crate::guides::GuideKind;
+use crate::pressure::PRESSURE_TOOLS;
+use crate::settings::{CANVAS_BACKGROUNDS, CurveFitting, Settings, Theme, UndoGranularity};
+use crate::widgets::pressure_curve_editor;
+use egui;
+
+/// Preferences window editing the app's `Settings` in place
+#[derive(Default)]
+pub struct PreferencesDialog {
+    pub open: bool,
+    /// Index into `PRESSURE_TOOLS` of the tool whose pressure curves are shown
+    pressure_tool: usize,
+}
+
+impl PreferencesDialog {
+    /// Show the preferences window if it is open
+    ///
+    /// Returns true if any setting changed this frame.
+    pub fn show(&mut self, ctx: &egui::Context, settings: &mut Settings) -> bool {
+        let before = settings.clone();
+
+        let mut open = self.open;
+        egui::Window::new("Preferences")
+            .open(&mut open)
+            .resizable(false)
+            .show(ctx, |ui| {
+                egui::Grid::new("preferences_grid")
+                    .num_columns(2)
+                    .show(ui, |ui| {
+                        ui.label("Theme");
+                        egui::ComboBox::from_id_salt("preferences_theme")
+                            .selected_text(settings.theme.label())
+                            .show_ui(ui, |ui| {
+                                for theme in Theme::ALL {
+                                    ui.selectable_value(&mut settings.theme, theme, theme.label());
+                                }
+                            });
+                        ui.end_row();
+
+                        ui.label("Autosave every");
+                        ui.add(
+                            egui::DragValue::new(&mut settings.autosave_interval_secs)
+                                .range(5..=3600)
+                                .suffix(" s"),
+                        );
+                        ui.end_row();
+
+                        ui.label("Stroke thickness");
+                        ui.add(egui::Slider::new(
+                            &mut settings.stroke_thickness,
+                            1.0..=20.0,
+                        ));
+                        ui.end_row();
+
+                        ui.label("");
+                        ui.checkbox(&mut settings.auto_shape, "Auto-shape strokes")
+                            .on_hover_text(
+                                "Replace strokes that look like lines, arrows, rectangles or \
+                                 circles with clean shapes",
+                            );
+                        ui.end_row();
+
+                        ui.label("Fit strokes to curves");
+                        egui::ComboBox::from_id_salt("preferences_curve_fitting")
+                            .selected_text(settings.curve_fitting.label())
+                            .show_ui(ui, |ui| {
+                                for fitting in CurveFitting::ALL {
+                                    ui.selectable_value(
+                                        &mut settings.curve_fitting,
+                                        fitting,
+                                        fitting.label(),
+                                    );
+                                }
+                            })
+                            .response
+                            .on_hover_text(
+                                "Store finished strokes as smooth curves with far fewer \
+                                 points; Compact saves the most, Precise keeps closest",
+                            );
+                        ui.end_row();
+
+                        ui.label("Grid size");
+                        ui.add(egui::Slider::new(&mut settings.grid_size, 0.0..=100.0))
+                            .on_hover_text("0 hides the grid");
+                        ui.end_row();
+
+                        ui.label("");
+                        ui.checkbox(&mut settings.pixel_grid, "Show pixel grid")
+                            .on_hover_text("Outline each pixel when zoomed in past 800%");
+                        ui.end_row();
+
+                        let snap = &mut settings.snap;
+                        ui.label("Nudge step");
+                        ui.horizontal(|ui| {
+                            ui.add(
+                                egui::DragValue::new(&mut snap.nudge)
+                                    .range(0.1..=100.0)
+                                    .speed(0.1),
+                            )
+                            .on_hover_text("How far arrow keys move the selection");
+                            ui.label("with Shift");
+                            ui.add(
+                                egui::DragValue::new(&mut snap.large_nudge)
+                                    .range(0.1..=1000.0)
+                                    .speed(0.5),
+                            )
+                            .on_hover_text("How far Shift+arrow keys move the selection");
+                        });
+                        ui.end_row();
+
+                        ui.label("Snap grid");
+                        ui.add(
+                            egui::DragValue::new(&mut snap.grid)
+                                .range(0.0..=500.0)
+                                .suffix(" px"),
+                        )
+                        .on_hover_text("Spacing Ctrl+drag snaps moves to; 0 turns it off");
+                        ui.end_row();
+
+                        ui.label("Angle snap");
+                        ui.add(
+                            egui::DragValue::new(&mut snap.angle)
+                                .range(1.0..=180.0)
+                                .suffix("°"),
+                        )
+                        .on_hover_text("Step rotations snap to with the turn buttons or knob");
+                        ui.end_row();
+
+                        let guides = &mut settings.guides;
+                        ui.label("Guides");
+                        egui::ComboBox::from_id_salt("preferences_guides")
+                            .selected_text(guides.kind.label())
+                            .show_ui(ui, |ui| {
+                                for kind in GuideKind::ALL {
+                                    ui.selectable_value(&mut guides.kind, kind, kind.label());
+                                }
+                            })
+                            .response
+                            .on_hover_text("Perspective or isometric lines drawn over the canvas");
+                        ui.end_row();
+
+                        let names = ["Vanishing point", "Second point", "Third point"];
+                        let used = guides.kind.vanishing_points();
+                        for (name, point) in
+                            names.iter().zip(&mut guides.vanishing_points).take(used)
+                        {
+                            ui.label(*name);
+                            ui.horizontal(|ui| {
+                                ui.add(egui::DragValue::new(&mut point.x).prefix("x: "));
+                                ui.add(egui::DragValue::new(&mut point.y).prefix("y: "));
+                            });
+                            ui.end_row();
+                        }
+                        if guides.kind == GuideKind::Isometric {
+                            ui.label("Grid spacing");
+                            ui.add(
+                                egui::DragValue::new(&mut guides.spacing)
+                                    .range(2.0..=500.0)
+                                    .suffix(" px"),
+                            )
+                            .on_hover_text("Upright distance between the grid's slanted lines");
+                            ui.end_row();
+                        }
+                        if guides.kind != GuideKind::Off {
+                            ui.label("");
+                            ui.checkbox(&mut guides.snap, "Snap strokes to guides")
+                                .on_hover_text(
+                                    "Draw straight from where a stroke starts along the guide \
+                                     closest to the way the pointer goes",
+                                );
+                            ui.end_row();
+                        }
+
+                        ui.label("Undo limit");
+                        ui.add(egui::DragValue::new(&mut settings.undo_limit).range(0..=10_000))
+                            .on_hover_text("0 keeps every step");
+                        ui.end_row();
+
+                        ui.label("Undo step");
+                        ui.horizontal(|ui| {
+                            egui::ComboBox::from_id_salt("preferences_undo_granularity")
+                                .selected_text(settings.undo_granularity.label())
+                                .show_ui(ui, |ui| {
+                                    for granularity in UndoGranularity::ALL {
+                                        ui.selectable_value(
+                                            &mut settings.undo_granularity,
+                                            granularity,
+                                            granularity.label(),
+                                        );
+                                    }
+                                })
+                                .response
+                                .on_hover_text(
+                                    "How much one undo takes back: each command, everything \
+                                     between pressing and releasing the pointer, or edits \
+                                     made in quick succession",
+                                );
+                            if settings.undo_granularity == UndoGranularity::TimeWindow {
+                                ui.add(
+                                    egui::Slider::new(&mut settings.undo_window_secs, 0.5..=10.0)
+                                        .suffix(" s"),
+                                )
+                                .on_hover_text("Longest pause between edits in one step");
+                            }
+                        });
+                        ui.end_row();
+
+                        ui.label("");
+                        ui.checkbox(
+                            &mut settings.keep_undo_branches,
+                            "Keep undone steps as branches",
+                        )
+                        .on_hover_text(
+                            "Editing after undoing keeps the undone steps in the History \
+                             panel to switch back to, instead of dropping them",
+                        );
+                        ui.end_row();
+
+                        ui.label("Canvas background");
+                        ui.horizontal(|ui| {
+                            ui.color_edit_button_srgba(&mut settings.background_color);
+                            for (name, color) in CANVAS_BACKGROUNDS {
+                                ui.selectable_value(&mut settings.background_color, color, name);
+                            }
+                        });
+                        ui.end_row();
+
+                        ui.label("");
+                        ui.checkbox(
+                            &mut settings.checkerboard,
+                            "Checkerboard behind transparency",
+                        );
+                        ui.end_row();
+
+                        ui.label("");
+                        ui.checkbox(
+                            &mut settings.clip_to_artboard,
+                            "Hide content outside the canvas",
+                        );
+                        ui.end_row();
+
+                        ui.label("");
+                        ui.checkbox(&mut settings.vector_strokes, "Draw strokes as vectors")
+                            .on_hover_text(
+                                "Sharper when zoomed; translucent strokes still use textures",
+                            );
+                        ui.end_row();
+
+                        ui.label("Image resampling");
+                        egui::ComboBox::from_id_salt("preferences_image_resampling")
+                            .selected_text(settings.image_resampling.label())
+                            .show_ui(ui, |ui| {
+                                for resampling in Resampling::ALL {
+                                    ui.selectable_value(
+                                        &mut settings.image_resampling,
+                                        resampling,
+                                        resampling.label(),
+                                    );
+                                }
+                            })
+                            .response
+                            .on_hover_text(
+                                "How images are scaled to their size on screen; Nearest keeps \
+                                 pixels hard-edged, Lanczos is sharpest when shrinking",
+                            );
+                        ui.end_row();
+
+                        ui.label("");
+                        ui.checkbox(&mut settings.brush_outline, "Show brush outline")
+                            .on_hover_text(
+                                "Replace the pointer with a circle the size of the brush",
+                            );
+                        ui.end_row();
+
+                        ui.label("");
+                        ui.checkbox(&mut settings.animate_view, "Animate zooming")
+                            .on_hover_text(
+                                "Ease the canvas into place when zooming, or when undo \
+                                 scrolls to a change",
+                            );
+                        ui.end_row();
+
+                        #[cfg(not(target_arch = "wasm32"))]
+                        {
+                            ui.label("");
+                            ui.checkbox(&mut settings.link_dropped_images, "Link dropped images")
+                                .on_hover_text(
+                                    "Save dropped image files as links to the files rather \
+                                     than copies inside the document",
+                                );
+                            ui.end_row();
+                        }
+
+                        ui.label("After export, run");
+                        ui.add(
+                            egui::TextEdit::singleline(&mut settings.post_export_command)
+                                .hint_text("e.g. optipng {path}"),
+                        )
+                        .on_hover_text(format!(
+                            "Shell command run after each export. {} is replaced by the \
+                             exported file (appended if missing) and {} is set to it.",
+                            export::PATH_PLACEHOLDER,
+                            export::PATH_ENV_VAR
+                        ));
+                        ui.end_row();
+
+                        ui.label("Scratch folder");
+                        ui.add(
+                            egui::TextEdit::singleline(&mut settings.scratch_dir)
+                                .hint_text("App data folder"),
+                        )
+                        .on_hover_text("Where quick sketches are saved");
+                        ui.end_row();
+
+                        #[cfg(not(target_arch = "wasm32"))]
+                        {
+                            ui.label("Screenshot command");
+                            ui.add(
+                                egui::TextEdit::singleline(&mut settings.screenshot_command)
+                                    .hint_text("Platform default"),
+                            )
+                            .on_hover_text(format!(
+                                "Shell command capturing the screen into {} (also set as {}). \
+                                 Default: {}",
+                                export::PATH_PLACEHOLDER,
+                                crate::screenshot::CAPTURE_PATH_ENV_VAR,
+                                crate::screenshot::DEFAULT_CAPTURE_COMMAND
+                            ));
+                            ui.end_row();
+                        }
+
+                        #[cfg(all(feature = "global-hotkey", not(target_arch = "wasm32")))]
+                        {
+                            ui.label("");
+                            ui.checkbox(
+                                &mut settings.screenshot_hotkey,
+                                format!(
+                                    "{} takes a screenshot to annotate",
+                                    crate::screenshot::HOTKEY_LABEL
+                                ),
+                            )
+                            .on_hover_text(
+                                "Works while the app is in the background. \
+                                 Takes effect after restarting the app",
+                            );
+                            ui.end_row();
+                        }
+
+                        #[cfg(all(feature = "tray", not(target_arch = "wasm32")))]
+                        {
+                            ui.label("");
+                            ui.checkbox(&mut settings.tray_icon, "Tray icon for quick sketches")
+                                .on_hover_text("Takes effect after restarting the app");
+                            ui.end_row();
+                        }
+                    });
+
+                ui.separator();
+                self.pressure_ui(ui, settings);
+
+                ui.separator();
+                if ui.button("Restore defaults").clicked() {
+                    *settings = Settings::default();
+                }
+            });
+        self.open = open;
+
+        *settings != before
+    }
+
+    /// Size and opacity curves of the chosen brush tool's response to pen pressure
+    fn pressure_ui(&mut self, ui: &mut egui::Ui, settings: &mut Settings) {
+        egui::CollapsingHeader::new("Pen pressure").show(ui, |ui| {
+            let tool = PRESSURE_TOOLS[self.pressure_tool.min(PRESSURE_TOOLS.len() - 1)];
+            egui::ComboBox::from_id_salt("preferences_pressure_tool")
+                .selected_text(tool)
+                .show_ui(ui, |ui| {
+                    for (index, name) in PRESSURE_TOOLS.iter().enumerate() {
+                        ui.selectable_value(&mut self.pressure_tool, index, *name);
+                    }
+                });
+
+            let response = settings.pressure.entry(tool.to_string()).or_default();
+            ui.horizontal(|ui| {
+                ui.vertical(|ui| {
+                    ui.label("Size");
+                    pressure_curve_editor(ui, &mut response.size);
+                });
+                ui.vertical(|ui| {
+                    ui.label("Opacity");
+                    pressure_curve_editor(ui, &mut response.opacity);
+                });
+            });
+            ui.weak("Pressure goes across, from light to firm. Mice draw at full size.");
+        });
+    }
+}
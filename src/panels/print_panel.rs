@@ -0,0 +1,175 @@
+use crate::export::{self, ExportSettings, PaperSize, PrintScaling, PrintSettings, print_layout};
+use crate::state::EditorModel;
+use eframe::egui;
+
+/// Resolution of the preview page; low, since it's redrawn as settings change
+const PREVIEW_DPI: f32 = 40.0;
+/// Height the preview page is shown at
+const PREVIEW_HEIGHT: f32 = 360.0;
+
+/// Print window: paper and scaling settings with a preview of the page
+#[derive(Default)]
+pub struct PrintDialog {
+    pub open: bool,
+    pub settings: PrintSettings,
+    preview: Option<egui::TextureHandle>,
+    // Document version and settings the preview was drawn for
+    preview_key: Option<(usize, PrintSettings, ExportSettings)>,
+    status: Option<Result<String, String>>,
+}
+
+impl PrintDialog {
+    pub fn open(&mut self) {
+        self.open = true;
+        self.status = None;
+    }
+
+    /// Show the print window if it is open
+    ///
+    /// `export_settings` choose the artboard, background and watermark, as for export.
+    pub fn show(
+        &mut self,
+        ctx: &egui::Context,
+        editor_model: &EditorModel,
+        export_settings: &ExportSettings,
+    ) {
+        if !self.open {
+            // Let the preview texture go while the window is closed
+            self.preview = None;
+            self.preview_key = None;
+            return;
+        }
+        let mut open = self.open;
+        egui::Window::new("Print")
+            .open(&mut open)
+            .resizable(false)
+            .show(ctx, |ui| {
+                self.settings_ui(ui);
+                ui.separator();
+                self.preview_ui(ui, ctx, editor_model, export_settings);
+                ui.separator();
+                self.print_ui(ui, ctx, editor_model, export_settings);
+            });
+        self.open = open;
+    }
+
+    fn settings_ui(&mut self, ui: &mut egui::Ui) {
+        egui::ComboBox::from_label("Paper")
+            .selected_text(self.settings.paper.label())
+            .show_ui(ui, |ui| {
+                for paper in PaperSize::ALL {
+                    ui.selectable_value(&mut self.settings.paper, paper, paper.label());
+                }
+            });
+        ui.horizontal(|ui| {
+            ui.radio_value(
+                &mut self.settings.scaling,
+                PrintScaling::FitToPage,
+                "Fit to page",
+            );
+            ui.radio_value(
+                &mut self.settings.scaling,
+                PrintScaling::ActualSize,
+                "Actual size",
+            )
+            .on_hover_text("Print at the document's size in inches, from its DPI");
+        });
+        ui.add(egui::Slider::new(&mut self.settings.margin_mm, 0.0..=30.0).text("Margin (mm)"));
+        ui.add(
+            egui::Slider::new(&mut self.settings.dpi, 72.0..=600.0)
+                .text("Printer DPI")
+                .step_by(1.0),
+        );
+    }
+
+    fn preview_ui(
+        &mut self,
+        ui: &mut egui::Ui,
+        ctx: &egui::Context,
+        editor_model: &EditorModel,
+        export_settings: &ExportSettings,
+    ) {
+        let key = (
+            editor_model.version(),
+            self.settings,
+            export_settings.clone(),
+        );
+        if self.preview_key.as_ref() != Some(&key) {
+            self.preview = export::render_print_page(
+                editor_model,
+                export_settings,
+                &self.settings,
+                PREVIEW_DPI,
+                ctx,
+            )
+            .map_err(|err| log::warn!("Failed to draw the print preview: {}", err))
+            .ok()
+            .map(|page| ctx.load_texture("print_preview", page, egui::TextureOptions::LINEAR));
+            self.preview_key = Some(key);
+        }
+
+        let Some(preview) = &self.preview else {
+            ui.label("Nothing to print");
+            return;
+        };
+        let size = preview.size_vec2() * (PREVIEW_HEIGHT / preview.size_vec2().y);
+        let response = ui.add(egui::Image::new((preview.id(), size)));
+        ui.painter().rect_stroke(
+            response.rect,
+            0.0,
+            ui.visuals().widgets.noninteractive.bg_stroke,
+        );
+
+        if let Ok(bounds) = export::export_bounds(editor_model, export_settings) {
+            let layout = print_layout(bounds.size(), editor_model.units.dpi, &self.settings);
+            let size = layout.content.size();
+            ui.label(format!(
+                "Printed at {:.2} × {:.2} in ({:.0}%)",
+                size.x,
+                size.y,
+                layout.inches_per_px * editor_model.units.dpi * 100.0
+            ));
+            if !layout.fits {
+                ui.colored_label(
+                    egui::Color32::YELLOW,
+                    "Doesn't fit inside the margins; the edges will be cut off",
+                );
+            }
+        }
+    }
+
+    fn print_ui(
+        &mut self,
+        ui: &mut egui::Ui,
+        ctx: &egui::Context,
+        editor_model: &EditorModel,
+        export_settings: &ExportSettings,
+    ) {
+        if ui
+            .add_enabled(self.preview.is_some(), egui::Button::new("Print"))
+            .clicked()
+        {
+            self.status = Some(
+                export::render_print_page(
+                    editor_model,
+                    export_settings,
+                    &self.settings,
+                    self.settings.dpi,
+                    ctx,
+                )
+                .and_then(|page| export::send_to_printer(&page, &self.settings))
+                .map(|()| "Sent to the printer".to_string())
+                .map_err(|err| err.to_string()),
+            );
+        }
+        match &self.status {
+            Some(Ok(message)) => {
+                ui.label(message);
+            }
+            Some(Err(message)) => {
+                ui.colored_label(egui::Color32::RED, message);
+            }
+            None => {}
+        }
+    }
+}
@@ -0,0 +1,166 @@
+use crate::element::factory;
+use crate::state::EditorModel;
+use egui;
+
+/// Initial size of the quick sketch window
+const QUICK_SKETCH_SIZE: [f32; 2] = [360.0, 280.0];
+
+/// Small frameless, always-on-top window for scribbling something down quickly
+///
+/// Sketches don't touch the open document; saving hands them back as a
+/// document of their own, which the app writes into the scratch folder.
+#[derive(Default)]
+pub struct QuickSketch {
+    pub open: bool,
+    // Finished strokes, in canvas coordinates
+    strokes: Vec<Vec<egui::Pos2>>,
+    // Stroke being drawn
+    current: Vec<egui::Pos2>,
+    status: Option<Result<String, String>>,
+}
+
+impl QuickSketch {
+    /// Record the outcome of saving the sketch; a saved sketch starts over
+    pub fn set_saved(&mut self, result: Result<String, String>) {
+        if result.is_ok() {
+            self.strokes.clear();
+        }
+        self.status = Some(result);
+    }
+
+    /// Show the sketch window if it is open
+    ///
+    /// Returns the sketch as a document when the user asks to save it.
+    pub fn show(&mut self, ctx: &egui::Context, stroke: egui::Stroke) -> Option<EditorModel> {
+        if !self.open {
+            return None;
+        }
+
+        ctx.show_viewport_immediate(
+            egui::ViewportId::from_hash_of("quick_sketch"),
+            egui::ViewportBuilder::default()
+                .with_title("Quick sketch")
+                .with_inner_size(QUICK_SKETCH_SIZE)
+                .with_decorations(false)
+                .with_window_level(egui::WindowLevel::AlwaysOnTop),
+            |ctx, class| {
+                if ctx.input(|i| i.viewport().close_requested() || i.key_pressed(egui::Key::Escape))
+                {
+                    self.open = false;
+                }
+
+                // Without multiple viewport support (e.g. on the web) this is a normal window
+                if class == egui::ViewportClass::Embedded {
+                    let mut open = self.open;
+                    let saved = egui::Window::new("Quick sketch")
+                        .open(&mut open)
+                        .default_size(QUICK_SKETCH_SIZE)
+                        .show(ctx, |ui| self.contents_ui(ui, stroke))
+                        .and_then(|response| response.inner.flatten());
+                    self.open &= open;
+                    saved
+                } else {
+                    egui::CentralPanel::default()
+                        .show(ctx, |ui| {
+                            self.title_bar_ui(ui);
+                            self.contents_ui(ui, stroke)
+                        })
+                        .inner
+                }
+            },
+        )
+    }
+
+    /// Title and close button, doubling as the handle for moving the frameless window
+    fn title_bar_ui(&mut self, ui: &mut egui::Ui) {
+        let bar = ui
+            .horizontal(|ui| {
+                ui.strong("Quick sketch");
+                ui.with_layout(egui::Layout::right_to_left(egui::Align::Center), |ui| {
+                    if ui.small_button("✖").clicked() {
+                        self.open = false;
+                    }
+                });
+            })
+            .response;
+        let drag = ui.interact(bar.rect, ui.id().with("title_bar"), egui::Sense::drag());
+        if drag.drag_started() {
+            ui.ctx().send_viewport_cmd(egui::ViewportCommand::StartDrag);
+        }
+    }
+
+    fn contents_ui(&mut self, ui: &mut egui::Ui, stroke: egui::Stroke) -> Option<EditorModel> {
+        let mut saved = None;
+        ui.horizontal(|ui| {
+            let has_strokes = !self.strokes.is_empty();
+            if ui
+                .add_enabled(has_strokes, egui::Button::new("Save"))
+                .on_hover_text("Save into the scratch folder")
+                .clicked()
+            {
+                saved = Some(self.to_document(stroke));
+            }
+            if ui
+                .add_enabled(has_strokes, egui::Button::new("Clear"))
+                .clicked()
+            {
+                self.strokes.clear();
+                self.status = None;
+            }
+            match &self.status {
+                Some(Ok(message)) => {
+                    ui.label(message);
+                }
+                Some(Err(message)) => {
+                    ui.colored_label(egui::Color32::RED, message);
+                }
+                None => {}
+            }
+        });
+
+        let (response, painter) = ui.allocate_painter(ui.available_size(), egui::Sense::drag());
+        let origin = response.rect.min.to_vec2();
+        painter.rect_filled(response.rect, 0.0, egui::Color32::WHITE);
+
+        if let Some(pos) = response.interact_pointer_pos() {
+            let pos = pos - origin;
+            if self.current.last() != Some(&pos) {
+                self.current.push(pos);
+            }
+        }
+        if response.drag_stopped() {
+            let points = std::mem::take(&mut self.current);
+            if points.len() >= 2 {
+                self.strokes.push(points);
+                self.status = None;
+            }
+        } else if !response.is_pointer_button_down_on() {
+            // A click without dragging leaves no stroke
+            self.current.clear();
+        }
+
+        for points in self.strokes.iter().chain(std::iter::once(&self.current)) {
+            let points = points.iter().map(|&point| point + origin).collect();
+            painter.add(egui::Shape::line(points, stroke));
+        }
+
+        saved
+    }
+
+    /// The sketch as a document of strokes
+    fn to_document(&self, stroke: egui::Stroke) -> EditorModel {
+        let mut document = EditorModel::new();
+        for points in &self.strokes {
+            let id = document.reserve_id();
+            document
+                .add_element(factory::create_stroke(
+                    id,
+                    points.clone(),
+                    stroke.width,
+                    stroke.color,
+                ))
+                .expect("reserved ids are unused");
+        }
+        document
+    }
+}
@@ -0,0 +1,90 @@
+use crate::assets::LinkedImage;
+use crate::element::ElementId;
+use egui;
+use std::collections::HashMap;
+
+/// What the user asked the relink window to do with a linked image
+#[derive(Clone, Debug, PartialEq)]
+pub enum RelinkChoice {
+    /// Point the image at another file path or URL
+    Relink(ElementId, String),
+    /// Copy the image's data into the document, dropping its link
+    Embed(ElementId),
+}
+
+/// Linked Images window: where each linked image points, with a way to point
+/// missing ones elsewhere or embed them
+#[derive(Default)]
+pub struct RelinkDialog {
+    pub open: bool,
+    /// New links being typed, by image
+    links: HashMap<ElementId, String>,
+}
+
+impl RelinkDialog {
+    /// Show the window if it is open, listing `images`
+    pub fn show(&mut self, ctx: &egui::Context, images: &[LinkedImage]) -> Option<RelinkChoice> {
+        let mut open = self.open;
+        let mut choice = None;
+        egui::Window::new("Linked Images")
+            .open(&mut open)
+            .resizable(false)
+            .show(ctx, |ui| {
+                if images.is_empty() {
+                    ui.label("No images are linked; all of them are saved in the document.");
+                    return;
+                }
+                ui.label("Relative paths are taken from the document's folder.");
+                egui::Grid::new("relink_grid")
+                    .num_columns(3)
+                    .striped(true)
+                    .show(ui, |ui| {
+                        for image in images {
+                            if let Some(row_choice) = self.image_row(ui, image) {
+                                choice = Some(row_choice);
+                            }
+                            ui.end_row();
+                        }
+                    });
+            });
+        self.open = open;
+        if let Some(RelinkChoice::Relink(id, _) | RelinkChoice::Embed(id)) = &choice {
+            self.links.remove(id);
+        }
+        choice
+    }
+
+    fn image_row(&mut self, ui: &mut egui::Ui, image: &LinkedImage) -> Option<RelinkChoice> {
+        if image.missing {
+            ui.colored_label(ui.visuals().error_fg_color, format!("⚠ {}", image.name))
+                .on_hover_text("The linked file couldn't be read");
+        } else {
+            ui.label(&image.name);
+        }
+
+        let link = self
+            .links
+            .entry(image.id)
+            .or_insert_with(|| image.link.clone());
+        ui.add(egui::TextEdit::singleline(link).desired_width(260.0));
+
+        let mut choice = None;
+        ui.horizontal(|ui| {
+            if ui
+                .button("Relink")
+                .on_hover_text("Read the image from this path")
+                .clicked()
+            {
+                choice = Some(RelinkChoice::Relink(image.id, link.trim().to_owned()));
+            }
+            if ui
+                .add_enabled(!image.missing, egui::Button::new("Embed"))
+                .on_hover_text("Save the image inside the document instead")
+                .clicked()
+            {
+                choice = Some(RelinkChoice::Embed(image.id));
+            }
+        });
+        choice
+    }
+}
@@ -0,0 +1,117 @@
+use crate::color_replace::{ColorReplacement, document_colors};
+use crate::element::ElementType;
+
+/// Size of the swatches of colors used in the document
+const USED_COLOR_SIZE: f32 = 16.0;
+/// Most used colors offered to pick from
+const MAX_USED_COLORS: usize = 24;
+
+/// Replace Colors window: change every element using one color to another
+pub struct ReplaceColorsDialog {
+    pub open: bool,
+    replacement: ColorReplacement,
+    // Colors in the document when the window was opened, most used first
+    used_colors: Vec<egui::Color32>,
+}
+
+impl Default for ReplaceColorsDialog {
+    fn default() -> Self {
+        Self {
+            open: false,
+            replacement: ColorReplacement {
+                find: egui::Color32::BLACK,
+                replace: egui::Color32::BLACK,
+                tolerance: 0,
+            },
+            used_colors: Vec::new(),
+        }
+    }
+}
+
+impl ReplaceColorsDialog {
+    /// Open the window, finding the most used color and replacing it with `replace`
+    pub fn open_for(&mut self, elements: &[ElementType], replace: egui::Color32) {
+        self.open = true;
+        self.used_colors = document_colors(elements);
+        self.used_colors.truncate(MAX_USED_COLORS);
+        if let Some(&most_used) = self.used_colors.first() {
+            self.replacement.find = most_used;
+        }
+        self.replacement.replace = replace;
+    }
+
+    /// Show the Replace Colors window if it is open, counting the elements
+    /// that would change in `elements`
+    ///
+    /// Returns the replacement when the user applies it.
+    pub fn show(
+        &mut self,
+        ctx: &egui::Context,
+        elements: &[ElementType],
+    ) -> Option<ColorReplacement> {
+        let mut open = self.open;
+        let mut apply = false;
+        let mut cancel = false;
+        egui::Window::new("Replace Colors")
+            .open(&mut open)
+            .resizable(false)
+            .show(ctx, |ui| {
+                let replacement = &mut self.replacement;
+                egui::Grid::new("replace_colors_grid")
+                    .num_columns(2)
+                    .show(ui, |ui| {
+                        ui.label("Find");
+                        ui.color_edit_button_srgba(&mut replacement.find);
+                        ui.end_row();
+
+                        ui.label("");
+                        used_colors_ui(ui, &self.used_colors, &mut replacement.find);
+                        ui.end_row();
+
+                        ui.label("Tolerance");
+                        ui.add(egui::Slider::new(&mut replacement.tolerance, 0..=255))
+                            .on_hover_text("How far each channel may be off and still match");
+                        ui.end_row();
+
+                        ui.label("Replace with");
+                        ui.color_edit_button_srgba(&mut replacement.replace);
+                        ui.end_row();
+                    });
+
+                let matches = replacement.matching(elements).count();
+                ui.weak(match matches {
+                    1 => "1 element matches".to_string(),
+                    n => format!("{} elements match", n),
+                });
+                ui.separator();
+                ui.horizontal(|ui| {
+                    apply = ui
+                        .add_enabled(matches > 0, egui::Button::new("Replace"))
+                        .clicked();
+                    cancel = ui.button("Cancel").clicked();
+                });
+            });
+
+        self.open = open && !apply && !cancel;
+        apply.then_some(self.replacement)
+    }
+}
+
+/// Swatches of the colors used in the document; clicking one picks it to find
+fn used_colors_ui(ui: &mut egui::Ui, colors: &[egui::Color32], find: &mut egui::Color32) {
+    ui.horizontal_wrapped(|ui| {
+        ui.spacing_mut().item_spacing = egui::Vec2::splat(2.0);
+        for &color in colors {
+            let (rect, response) =
+                ui.allocate_exact_size(egui::Vec2::splat(USED_COLOR_SIZE), egui::Sense::click());
+            egui::color_picker::show_color_at(ui.painter(), color, rect);
+            if color == *find {
+                ui.painter()
+                    .rect_stroke(rect, 0.0, ui.visuals().selection.stroke);
+            }
+            if response.on_hover_text("Find this color").clicked() {
+                *find = color;
+            }
+        }
+    });
+}
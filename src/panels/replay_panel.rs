@@ -0,0 +1,183 @@
+use crate::export::{self, ExportSettings};
+use crate::replay::Replay;
+use crate::state::{EditorModel, ElementId};
+use egui;
+use std::collections::HashSet;
+
+/// Controls for replaying the drawing order on the canvas
+///
+/// The replay ends by itself as soon as the document is edited, since it only
+/// knows about the elements that existed when it started.
+pub struct ReplayControls {
+    replay: Option<Replay>,
+    // Document version the replay was built from
+    document_version: usize,
+    path: String,
+    status: Option<Result<String, String>>,
+}
+
+impl ReplayControls {
+    pub fn new() -> Self {
+        Self {
+            replay: None,
+            document_version: 0,
+            path: "replay.gif".to_string(),
+            status: None,
+        }
+    }
+
+    pub fn is_active(&self) -> bool {
+        self.replay.is_some()
+    }
+
+    /// Start replaying the document from the beginning
+    pub fn start(&mut self, editor_model: &EditorModel) {
+        self.replay = Some(Replay::new(editor_model));
+        self.document_version = editor_model.version;
+        self.status = None;
+    }
+
+    pub fn stop(&mut self) {
+        self.replay = None;
+    }
+
+    /// Advance playback for this frame and return the elements to draw
+    ///
+    /// Returns None when no replay is running, meaning everything is visible.
+    pub fn update(
+        &mut self,
+        ctx: &egui::Context,
+        editor_model: &EditorModel,
+    ) -> Option<HashSet<ElementId>> {
+        if editor_model.version != self.document_version {
+            self.stop();
+        }
+
+        let replay = self.replay.as_mut()?;
+        if replay.playing {
+            replay.advance(ctx.input(|i| i.stable_dt));
+            ctx.request_repaint();
+        }
+        Some(replay.visible_ids())
+    }
+
+    /// Show the replay window while a replay is running
+    pub fn show(
+        &mut self,
+        ctx: &egui::Context,
+        editor_model: &EditorModel,
+        export_settings: &ExportSettings,
+    ) {
+        let Some(replay) = &mut self.replay else {
+            return;
+        };
+
+        let mut open = true;
+        egui::Window::new("Replay")
+            .open(&mut open)
+            .resizable(false)
+            .show(ctx, |ui| {
+                ui.horizontal(|ui| {
+                    if replay.is_finished() {
+                        if ui.button("⟲ Restart").clicked() {
+                            replay.restart();
+                        }
+                    } else if replay.playing {
+                        if ui.button("⏸ Pause").clicked() {
+                            replay.playing = false;
+                        }
+                    } else if ui.button("▶ Play").clicked() {
+                        replay.playing = true;
+                    }
+                    ui.label(format!(
+                        "{} / {}",
+                        replay.visible_count(),
+                        replay.steps().len()
+                    ));
+                });
+
+                let mut elapsed = replay.elapsed();
+                if ui
+                    .add(
+                        egui::Slider::new(&mut elapsed, 0.0..=replay.duration())
+                            .suffix(" s")
+                            .show_value(false),
+                    )
+                    .changed()
+                {
+                    replay.seek(elapsed);
+                }
+                ui.add(
+                    egui::Slider::new(&mut replay.speed, 0.25..=8.0)
+                        .logarithmic(true)
+                        .suffix("×")
+                        .text("Speed"),
+                );
+
+                ui.separator();
+                ui.horizontal(|ui| {
+                    ui.label("File:");
+                    ui.text_edit_singleline(&mut self.path);
+                });
+                if ui.button("Export GIF").clicked() {
+                    self.status = Some(export_gif(
+                        &self.path,
+                        editor_model,
+                        export_settings,
+                        replay.speed,
+                        ctx,
+                    ));
+                }
+                match &self.status {
+                    Some(Ok(message)) => {
+                        ui.label(message);
+                    }
+                    Some(Err(message)) => {
+                        ui.colored_label(egui::Color32::RED, message);
+                    }
+                    None => {}
+                }
+            });
+
+        if !open {
+            self.stop();
+        }
+    }
+}
+
+impl Default for ReplayControls {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+#[cfg(not(target_arch = "wasm32"))]
+fn export_gif(
+    path: &str,
+    editor_model: &EditorModel,
+    settings: &ExportSettings,
+    speed: f32,
+    ctx: &egui::Context,
+) -> Result<String, String> {
+    let path = std::path::Path::new(path);
+    export::export_replay_gif_to_file(path, editor_model, settings, speed, ctx)
+        .map(|()| super::finish_export(path, settings))
+        .map_err(|err| err.to_string())
+}
+
+#[cfg(target_arch = "wasm32")]
+fn export_gif(
+    _path: &str,
+    editor_model: &EditorModel,
+    settings: &ExportSettings,
+    speed: f32,
+    ctx: &egui::Context,
+) -> Result<String, String> {
+    let bytes = export::export_replay_gif(editor_model, settings, speed, ctx)
+        .map_err(|err| err.to_string())?;
+    log::warn!(
+        "Saving files is not supported on the web yet ({} bytes)",
+        bytes.len()
+    );
+    Err("Saving files is not supported on the web yet".to_string())
+}
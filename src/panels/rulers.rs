@@ -0,0 +1,335 @@
+use crate::command::{Command, CommandHistory};
+use crate::renderer::Renderer;
+use crate::ruler_guides::{GUIDE_SNAP_DISTANCE, GuideAxis, RulerGuide, guide_at};
+use crate::state::EditorModel;
+use crate::tools::ToolType;
+use crate::units::DocumentUnits;
+use crate::view::View;
+use egui;
+
+/// Thickness of the rulers along the top and left of the canvas, in points
+pub const RULER_SIZE: f32 = 18.0;
+/// Closest labelled ticks come on screen, in points
+const MIN_LABEL_SPACING: f32 = 60.0;
+const GUIDE_COLOR: egui::Color32 = egui::Color32::from_rgb(0, 190, 240);
+
+/// A guide being dragged out of a ruler or across the canvas
+#[derive(Clone, Copy)]
+struct GuideDrag {
+    axis: GuideAxis,
+    /// The guide being moved, or None for a new one
+    guide_id: Option<usize>,
+}
+
+/// Where the guide being dragged is kept in egui's memory between frames
+fn guide_drag_id() -> egui::Id {
+    egui::Id::new("ruler_guide_drag")
+}
+
+/// The part of the central panel left for the canvas beside the rulers
+pub fn canvas_area(panel_rect: egui::Rect, rulers: bool) -> egui::Rect {
+    if rulers {
+        egui::Rect::from_min_max(
+            panel_rect.min + egui::Vec2::splat(RULER_SIZE),
+            panel_rect.max,
+        )
+    } else {
+        panel_rect
+    }
+}
+
+/// Rulers along the top and left of `panel_rect`, measuring the document in
+/// its units
+///
+/// While the view is turned the document's axes no longer run along the
+/// rulers, so they're left blank.
+pub fn paint_rulers(ui: &egui::Ui, panel_rect: egui::Rect, view: &View, units: &DocumentUnits) {
+    let visuals = ui.visuals();
+    let painter = ui.painter_at(panel_rect);
+    let top = egui::Rect::from_min_max(
+        panel_rect.min,
+        egui::pos2(panel_rect.max.x, panel_rect.min.y + RULER_SIZE),
+    );
+    let left = egui::Rect::from_min_max(
+        panel_rect.min,
+        egui::pos2(panel_rect.min.x + RULER_SIZE, panel_rect.max.y),
+    );
+    let border = visuals.widgets.noninteractive.bg_stroke;
+    for rect in [top, left] {
+        painter.rect_filled(rect, 0.0, visuals.extreme_bg_color);
+    }
+    painter.hline(top.x_range(), top.max.y, border);
+    painter.vline(left.max.x, left.y_range(), border);
+    if view.rotation() != 0.0 {
+        return;
+    }
+
+    let scale = view.transform().scaling;
+    let step = label_step(units, scale);
+    let minor = step / subdivisions(step) as f32;
+    let color = visuals.weak_text_color();
+    let font = egui::FontId::proportional(9.0);
+    let hover = ui.input(|i| i.pointer.hover_pos());
+
+    // The top ruler measures across, where vertical guides stand, and the
+    // left one down
+    for axis in [GuideAxis::Vertical, GuideAxis::Horizontal] {
+        let rect = match axis {
+            GuideAxis::Vertical => top,
+            GuideAxis::Horizontal => left,
+        };
+        let along = |pos| axis.coordinate(pos);
+        let painter = painter.with_clip_rect(rect.intersect(panel_rect));
+        let start = units.from_px(along(view.screen_to_document(rect.min)));
+        let end = units.from_px(along(view.screen_to_document(rect.max)));
+        let mut index = (start / minor).floor() as i64;
+        while index as f32 * minor <= end {
+            let value = index as f32 * minor;
+            let at = along(view.document_to_screen(doc_pos(axis, units.to_px(value))));
+            let labelled = index % subdivisions(step) == 0;
+            let length = if labelled {
+                RULER_SIZE
+            } else {
+                RULER_SIZE * 0.3
+            };
+            let stroke = egui::Stroke::new(1.0, color);
+            match axis {
+                GuideAxis::Vertical => {
+                    painter.vline(at, (rect.max.y - length)..=rect.max.y, stroke);
+                }
+                GuideAxis::Horizontal => {
+                    painter.hline((rect.max.x - length)..=rect.max.x, at, stroke);
+                }
+            }
+            if labelled {
+                let text = format!("{:.*}", label_decimals(step), value);
+                let galley = painter.layout_no_wrap(text, font.clone(), color);
+                let shape = match axis {
+                    GuideAxis::Vertical => egui::epaint::TextShape::new(
+                        egui::pos2(at + 2.0, rect.min.y),
+                        galley,
+                        color,
+                    ),
+                    // Read bottom to top, like a ruler along the left of a page
+                    GuideAxis::Horizontal => egui::epaint::TextShape::new(
+                        egui::pos2(rect.min.x, at - 2.0),
+                        galley,
+                        color,
+                    )
+                    .with_angle(-std::f32::consts::FRAC_PI_2),
+                };
+                painter.add(shape);
+            }
+            index += 1;
+        }
+
+        // Mark where the pointer is
+        if let Some(pos) = hover.filter(|pos| panel_rect.contains(*pos)) {
+            let stroke = egui::Stroke::new(1.0, visuals.strong_text_color());
+            match axis {
+                GuideAxis::Vertical => painter.vline(pos.x, rect.y_range(), stroke),
+                GuideAxis::Horizontal => painter.hline(rect.x_range(), pos.y, stroke),
+            };
+        }
+    }
+}
+
+/// Drag guides out of the rulers, and with the selection tool move the ones
+/// on the canvas or drop them back on a ruler to remove them; then draw them
+///
+/// `ui` draws on the canvas, in document coordinates. Returns whether the
+/// pointer went to the guides rather than to the active tool.
+pub fn drag_ruler_guides(
+    ui: &mut egui::Ui,
+    panel_rect: egui::Rect,
+    canvas_area: egui::Rect,
+    view: &View,
+    editor_model: &mut EditorModel,
+    command_history: &mut CommandHistory,
+    renderer: &Renderer,
+) -> bool {
+    let ctx = ui.ctx().clone();
+    let (hover, pressed, down) = ctx.input(|i| {
+        (
+            i.pointer.hover_pos(),
+            i.pointer.primary_pressed(),
+            i.pointer.primary_down(),
+        )
+    });
+    let mut drag = ctx.data(|data| data.get_temp::<GuideDrag>(guide_drag_id()));
+    let visible = renderer.shows_ruler_guides();
+    let scale = view.transform().scaling;
+
+    // Pick a guide up: a new one from a ruler, or one on the canvas
+    let mut hovered = None;
+    if let Some(pos) = hover.filter(|_| drag.is_none() && visible) {
+        let rulers = renderer.shows_rulers() && view.rotation() == 0.0;
+        if rulers && panel_rect.contains(pos) && !canvas_area.contains(pos) {
+            let axis = if pos.y < canvas_area.min.y {
+                GuideAxis::Horizontal
+            } else {
+                GuideAxis::Vertical
+            };
+            hovered = Some(GuideDrag {
+                axis,
+                guide_id: None,
+            });
+        } else if canvas_area.contains(pos)
+            && !renderer.ruler_guides_locked()
+            && matches!(editor_model.active_tool(), ToolType::Selection(_))
+            && editor_model.active_tool().current_state_name() == "Idle"
+        {
+            let tolerance = GUIDE_SNAP_DISTANCE / scale;
+            hovered = guide_at(
+                &editor_model.ruler_guides,
+                view.screen_to_document(pos),
+                tolerance,
+            )
+            .map(|guide| GuideDrag {
+                axis: guide.axis,
+                guide_id: Some(guide.id),
+            });
+        }
+        if pressed {
+            drag = hovered;
+        }
+    }
+
+    let document_pos = hover.map(|pos| view.screen_to_document(pos));
+    if let Some(GuideDrag { axis, guide_id }) = drag {
+        let position = document_pos.map(|pos| axis.coordinate(pos).round());
+        let on_canvas = hover.is_some_and(|pos| canvas_area.contains(pos));
+        if down {
+            ctx.data_mut(|data| data.insert_temp(guide_drag_id(), GuideDrag { axis, guide_id }));
+            ctx.request_repaint();
+        } else {
+            // Let go: on the canvas the guide stays there, anywhere else it goes
+            ctx.data_mut(|data| data.remove::<GuideDrag>(guide_drag_id()));
+            let mut guides = editor_model.ruler_guides.clone();
+            let (guides, description) = match (guide_id, position.filter(|_| on_canvas)) {
+                (None, Some(position)) => {
+                    guides.push(RulerGuide::new(axis, position));
+                    (guides, "Add Guide")
+                }
+                (Some(id), Some(position)) => {
+                    for guide in guides.iter_mut().filter(|guide| guide.id == id) {
+                        guide.position = position;
+                    }
+                    (guides, "Move Guide")
+                }
+                (Some(id), None) => {
+                    guides.retain(|guide| guide.id != id);
+                    (guides, "Remove Guide")
+                }
+                (None, None) => (guides, ""),
+            };
+            if let Some(command) = Command::new_set_ruler_guides(editor_model, description, guides)
+            {
+                let _ = command_history
+                    .execute(command, editor_model)
+                    .map_err(|err| log::warn!("Command execution failed: {}", err));
+            }
+            drag = None;
+        }
+    }
+
+    if visible {
+        let dragged = drag.map(|drag| (drag, document_pos));
+        paint_guides(ui, &editor_model.ruler_guides, dragged, scale);
+    }
+    if let Some(GuideDrag { axis, .. }) = drag.or(hovered) {
+        ctx.set_cursor_icon(match axis {
+            GuideAxis::Horizontal => egui::CursorIcon::ResizeVertical,
+            GuideAxis::Vertical => egui::CursorIcon::ResizeHorizontal,
+        });
+    }
+    drag.is_some() || hovered.is_some()
+}
+
+/// Draw the guides across the canvas, one screen point wide, with the one
+/// being dragged where the pointer has it
+fn paint_guides(
+    ui: &egui::Ui,
+    guides: &[RulerGuide],
+    dragged: Option<(GuideDrag, Option<egui::Pos2>)>,
+    scale: f32,
+) {
+    let visible = ui.clip_rect();
+    let stroke = egui::Stroke::new(1.0 / scale, GUIDE_COLOR);
+    let moving = dragged.and_then(|(drag, _)| drag.guide_id);
+    let lines = guides
+        .iter()
+        .filter(|guide| Some(guide.id) != moving)
+        .map(|guide| (guide.axis, guide.position));
+    let preview = dragged.and_then(|(drag, pos)| {
+        let pos = pos?;
+        Some((drag.axis, drag.axis.coordinate(pos).round()))
+    });
+    for (axis, position) in lines.chain(preview) {
+        match axis {
+            GuideAxis::Horizontal => ui.painter().hline(visible.x_range(), position, stroke),
+            GuideAxis::Vertical => ui.painter().vline(position, visible.y_range(), stroke),
+        };
+    }
+}
+
+/// A document position `position` along the ruler for guides on `axis`
+fn doc_pos(axis: GuideAxis, position: f32) -> egui::Pos2 {
+    match axis {
+        GuideAxis::Vertical => egui::pos2(position, 0.0),
+        GuideAxis::Horizontal => egui::pos2(0.0, position),
+    }
+}
+
+/// Document units between labelled ticks: the smallest 1, 2 or 5 times a
+/// power of ten that keeps the labels apart at `scale` screen points per pixel
+fn label_step(units: &DocumentUnits, scale: f32) -> f32 {
+    let least = units.from_px(MIN_LABEL_SPACING / scale);
+    let magnitude = 10f32.powf(least.log10().floor());
+    [1.0, 2.0, 5.0, 10.0]
+        .into_iter()
+        .map(|mantissa| mantissa * magnitude)
+        .find(|step| *step >= least * 0.999)
+        .unwrap_or(10.0 * magnitude)
+}
+
+/// Unlabelled ticks each labelled step is split into
+fn subdivisions(step: f32) -> i64 {
+    let mantissa = step / 10f32.powf(step.log10().floor());
+    match mantissa.round() as i64 {
+        2 => 4,
+        5 => 5,
+        _ => 10,
+    }
+}
+
+/// Decimal places labels need to tell steps of `step` apart
+fn label_decimals(step: f32) -> usize {
+    (-step.log10().floor()).max(0.0) as usize
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::units::Unit;
+
+    #[test]
+    fn test_ruler_labels_keep_apart_on_round_steps() {
+        let pixels = DocumentUnits::default();
+        assert_eq!(label_step(&pixels, 1.0), 100.0);
+        assert_eq!(label_step(&pixels, 4.0), 20.0);
+        assert_eq!(label_step(&pixels, 0.1), 1000.0);
+        assert_eq!(subdivisions(20.0), 4);
+        assert_eq!(label_decimals(20.0), 0);
+
+        let inches = DocumentUnits {
+            unit: Unit::In,
+            dpi: 96.0,
+        };
+        // 60 points at 2x is 0.31 inches
+        let step = label_step(&inches, 2.0);
+        assert!((step - 0.5).abs() < 1e-6);
+        assert_eq!(subdivisions(step), 5);
+        assert_eq!(label_decimals(step), 1);
+    }
+}
@@ -0,0 +1,114 @@
+use crate::PaintApp;
+use crate::command::Command;
+use crate::export_slice::{ExportSlice, SLICE_SCALES};
+use egui;
+
+/// List of the document's export slices, with batch export of all of them
+///
+/// Slices are drawn with the Export Slice tool; here they are renamed, removed
+/// and exported, each at the chosen scales, into one folder.
+pub fn slices_panel(app: &mut PaintApp, ui: &mut egui::Ui) {
+    egui::CollapsingHeader::new("Slices").show(ui, |ui| {
+        let slices = app.editor_model().slices.clone();
+        if slices.is_empty() {
+            ui.label("No slices: drag one out with the Export Slice tool.");
+        }
+
+        let mut new_slices = None;
+        for slice in &slices {
+            if let Some(change) = slice_row(ui, &slices, slice) {
+                new_slices = Some(change);
+            }
+        }
+        if let Some((description, slices)) = new_slices {
+            if let Some(command) =
+                Command::new_set_export_slices(app.editor_model(), description, slices)
+            {
+                app.execute_command(command);
+            }
+        }
+
+        ui.separator();
+        export_ui(app, ui);
+    });
+}
+
+/// A slice's name, edited through a draft, and a button removing it
+fn slice_row(
+    ui: &mut egui::Ui,
+    slices: &[ExportSlice],
+    slice: &ExportSlice,
+) -> Option<(&'static str, Vec<ExportSlice>)> {
+    let mut change = None;
+    ui.horizontal(|ui| {
+        let draft_id = ui.make_persistent_id(("slice_name_draft", slice.id));
+        let mut name = ui
+            .data_mut(|data| data.get_temp::<String>(draft_id))
+            .unwrap_or_else(|| slice.name.clone());
+        let response = ui.add(egui::TextEdit::singleline(&mut name).desired_width(120.0));
+        if response.has_focus() {
+            ui.data_mut(|data| data.insert_temp(draft_id, name));
+        } else {
+            ui.data_mut(|data| data.remove::<String>(draft_id));
+            if response.lost_focus() && !name.trim().is_empty() && name.trim() != slice.name {
+                let renamed = slices
+                    .iter()
+                    .map(|other| ExportSlice {
+                        name: if other.id == slice.id {
+                            name.trim().to_string()
+                        } else {
+                            other.name.clone()
+                        },
+                        ..other.clone()
+                    })
+                    .collect();
+                change = Some(("Rename Slice", renamed));
+            }
+        }
+        ui.weak(format!(
+            "{:.0}×{:.0}",
+            slice.rect.width(),
+            slice.rect.height()
+        ));
+        if ui.button("Remove").clicked() {
+            let remaining = slices
+                .iter()
+                .filter(|other| other.id != slice.id)
+                .cloned()
+                .collect();
+            change = Some(("Remove Slice", remaining));
+        }
+    });
+    change
+}
+
+/// Scales, folder and the button exporting every slice
+fn export_ui(app: &mut PaintApp, ui: &mut egui::Ui) {
+    let has_slices = !app.editor_model().slices.is_empty();
+    let options = app.slice_export_mut();
+    ui.horizontal(|ui| {
+        ui.label("Scales");
+        for ((scale, _), chosen) in SLICE_SCALES.iter().zip(&mut options.scales) {
+            ui.checkbox(chosen, format!("{}x", scale));
+        }
+    });
+    ui.horizontal(|ui| {
+        ui.label("Folder");
+        ui.text_edit_singleline(&mut options.folder)
+            .on_hover_text("Relative folders are taken from the document's folder");
+    });
+    let can_export = has_slices && options.scales.contains(&true);
+
+    if cfg!(target_arch = "wasm32") {
+        ui.add_enabled(false, egui::Button::new("Export all slices"))
+            .on_disabled_hover_text("Slices can only be exported to a folder on desktop");
+        return;
+    }
+    if ui
+        .add_enabled(can_export, egui::Button::new("Export all slices"))
+        .clicked()
+    {
+        #[cfg(not(target_arch = "wasm32"))]
+        app.export_slices(ui.ctx());
+    }
+}
@@ -0,0 +1,40 @@
+use crate::PaintApp;
+use crate::view::PIXEL_GRID_ZOOM;
+use egui;
+
+/// Bar along the bottom of the window with the canvas zoom and rotation, a
+/// button to turn the canvas back upright, and pixel snapping once zoomed in
+/// far enough to see the pixels
+///
+/// Call before the docked panels so the bar spans the whole window.
+pub fn status_bar(app: &mut PaintApp, ctx: &egui::Context) {
+    egui::TopBottomPanel::bottom("status_bar").show(ctx, |ui| {
+        ui.horizontal(|ui| {
+            let zoom = app.zoom();
+            ui.label(format!("Zoom: {:.0}%", zoom * 100.0));
+            let mut snap = app.settings().snap_to_pixels;
+            let zoomed_in = zoom >= PIXEL_GRID_ZOOM;
+            let response = ui
+                .add_enabled(zoomed_in, egui::Checkbox::new(&mut snap, "Snap to pixels"))
+                .on_hover_text("Snap stroke points and moves to whole pixels")
+                .on_disabled_hover_text(format!(
+                    "Zoom in past {:.0}% to snap to pixels",
+                    PIXEL_GRID_ZOOM * 100.0
+                ));
+            if response.changed() {
+                app.set_snap_to_pixels(snap);
+            }
+            ui.separator();
+
+            let degrees = app.view_rotation().to_degrees();
+            ui.label(format!("Rotation: {:.0}°", degrees))
+                .on_hover_text("Turn the canvas with Alt and the wheel, or with two fingers");
+            if ui
+                .add_enabled(degrees != 0.0, egui::Button::new("Reset Rotation"))
+                .clicked()
+            {
+                app.reset_rotation();
+            }
+        });
+    });
+}
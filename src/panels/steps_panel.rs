@@ -0,0 +1,77 @@
+use crate::PaintApp;
+use crate::command::Command;
+use crate::element::{Element, ElementId};
+use egui;
+
+/// List of the document's step badges for reordering and deleting them
+///
+/// Badges are listed in step order. Moving one renumbers the list from the top,
+/// and deleting one closes the gap, each as a single undoable command.
+pub fn steps_panel(app: &mut PaintApp, ui: &mut egui::Ui) {
+    let badges: Vec<(ElementId, u32, String, f64)> = app
+        .editor_model()
+        .step_badges()
+        .iter()
+        .map(|badge| {
+            (
+                badge.id(),
+                badge.number(),
+                badge.display_name(),
+                badge.metadata().created_at.unwrap_or_default(),
+            )
+        })
+        .collect();
+    if badges.is_empty() {
+        return;
+    }
+
+    egui::CollapsingHeader::new("Step badges").show(ui, |ui| {
+        let mut order: Vec<ElementId> = badges.iter().map(|(id, ..)| *id).collect();
+        let mut command = None;
+
+        for (index, (id, number, name, _)) in badges.iter().enumerate() {
+            ui.horizontal(|ui| {
+                ui.label(format!("{}.", number));
+                ui.label(name);
+                ui.with_layout(egui::Layout::right_to_left(egui::Align::Center), |ui| {
+                    if ui.small_button("🗑").on_hover_text("Delete").clicked() {
+                        command = Command::new_remove_step_badge(app.editor_model(), *id);
+                    }
+                    if ui
+                        .add_enabled(index + 1 < badges.len(), egui::Button::new("⏷").small())
+                        .on_hover_text("Move down")
+                        .clicked()
+                    {
+                        order.swap(index, index + 1);
+                        command =
+                            Command::new_number_steps(app.editor_model(), "Reorder Steps", &order);
+                    }
+                    if ui
+                        .add_enabled(index > 0, egui::Button::new("⏶").small())
+                        .on_hover_text("Move up")
+                        .clicked()
+                    {
+                        order.swap(index - 1, index);
+                        command =
+                            Command::new_number_steps(app.editor_model(), "Reorder Steps", &order);
+                    }
+                });
+            });
+        }
+
+        if ui
+            .button("Renumber by creation order")
+            .on_hover_text("Number the badges in the order they were placed, closing any gaps")
+            .clicked()
+        {
+            let mut by_creation = badges.clone();
+            by_creation.sort_by(|a, b| a.3.total_cmp(&b.3).then(a.0.cmp(&b.0)));
+            let order: Vec<ElementId> = by_creation.iter().map(|(id, ..)| *id).collect();
+            command = Command::new_number_steps(app.editor_model(), "Renumber Steps", &order);
+        }
+
+        if let Some(command) = command {
+            app.execute_command(command);
+        }
+    });
+}
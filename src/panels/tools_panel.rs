@@ -1,154 +1,778 @@
-use crate::PaintApp;
 use crate::command::Command;
-use crate::tools::Tool;
+use crate::app::{
+    ZOOM_ACTUAL_SIZE_SHORTCUT, ZOOM_IN_SHORTCUT, ZOOM_OUT_SHORTCUT, ZOOM_TO_FIT_SHORTCUT,
+    ZOOM_TO_SELECTION_SHORTCUT,
+};
+use crate::{DocumentAction, PaintApp};
+use crate::document::DocumentMetadata;
+use crate::element::{Element, ElementType};
+use crate::reference_image::ReferenceImage;
+use crate::units::{DocumentUnits, Unit};
+use crate::tools::{SWAP_COLORS_KEY, TOOL_HOTKEYS, Tool};
+use crate::view::ZOOM_PRESETS;
 use egui;
 
-pub fn tools_panel(app: &mut PaintApp, ctx: &egui::Context) {
-    egui::SidePanel::left("tools_panel")
-        .resizable(true)
-        .default_width(200.0)
-        .show(ctx, |ui| {
-            ui.heading("Tools");
-
-            // Get the active tool name for comparison
-            let active_tool_name = app.active_tool().name();
-
-            // Collect tool names first to avoid borrowing issues
-            let tool_names: Vec<&str> = app
-                .available_tools()
-                .iter()
-                .map(|tool| tool.name())
-                .collect();
-
-            // Create selectable buttons for each tool
-            for &tool_name in &tool_names {
-                let is_selected = active_tool_name == tool_name;
-
-                // Use selectable label for better visual feedback
-                if ui.selectable_label(is_selected, tool_name).clicked() {
-                    log::info!("Tool selected from UI: {}", tool_name);
-                    app.set_active_tool_by_name(tool_name);
-                }
+// Side of each color swatch, and how far the background one sits down and right
+const SWATCH_SIZE: f32 = 24.0;
+const SWATCH_OFFSET: f32 = 12.0;
+
+/// Tool picker, undo and redo, and buttons for the app's windows and actions
+pub fn tools_ui(app: &mut PaintApp, ui: &mut egui::Ui) {
+    // Get the active tool name for comparison
+    let active_tool_name = app.active_tool().name();
+
+    // Collect tool names first to avoid borrowing issues
+    let tool_names: Vec<&str> = app
+        .available_tools()
+        .iter()
+        .map(|tool| tool.name())
+        .collect();
+
+    // Create selectable buttons for each tool
+    for &tool_name in &tool_names {
+        let is_selected = active_tool_name == tool_name;
+
+        // Use selectable label for better visual feedback
+        let mut response = ui.selectable_label(is_selected, tool_name);
+        if let Some((key, _)) = TOOL_HOTKEYS.iter().find(|(_, name)| *name == tool_name) {
+            response = response.on_hover_text(format!("Shortcut: {}", key.name()));
+        }
+        if response.clicked() {
+            log::debug!(tool = tool_name; "Tool selected from UI");
+            app.set_active_tool_by_name(tool_name);
+        }
+    }
+    color_swatches_ui(app, ui);
+    ui.separator();
+
+    // Undo/Redo section
+    ui.horizontal(|ui| {
+        let can_undo = app.command_history().can_undo();
+        let can_redo = app.command_history().can_redo();
+
+        if ui
+            .add_enabled(can_undo, egui::Button::new("Undo"))
+            .clicked()
+        {
+            app.undo();
+        }
+        if ui
+            .add_enabled(can_redo, egui::Button::new("Redo"))
+            .clicked()
+        {
+            app.redo();
+        }
+    });
+
+    ui.horizontal(|ui| {
+        let has_selection = !app.editor_model().selected_ids().is_empty();
+        if ui
+            .add_enabled(has_selection, egui::Button::new("Transform…"))
+            .on_hover_text(format!(
+                "Move, scale and turn the selection by exact amounts ({})",
+                ui.ctx()
+                    .format_shortcut(&crate::app::TRANSFORM_SELECTION_SHORTCUT)
+            ))
+            .clicked()
+        {
+            app.open_transform_dialog();
+        }
+        if ui
+            .add_enabled(has_selection, egui::Button::new("Array…"))
+            .on_hover_text(
+                "Lay out copies of the selection along a line, in a grid or around a circle",
+            )
+            .clicked()
+        {
+            app.open_array_dialog();
+        }
+        if ui
+            .add_enabled(has_selection, egui::Button::new("Duplicate"))
+            .on_hover_text(format!(
+                "Copy the selection, a little down and to the right ({})",
+                ui.ctx()
+                    .format_shortcut(&crate::app::DUPLICATE_SELECTION_SHORTCUT)
+            ))
+            .clicked()
+        {
+            app.duplicate_selection();
+        }
+        if ui
+            .add_enabled(app.can_repeat(), egui::Button::new("Repeat"))
+            .on_hover_text(format!(
+                "Do the last move, transform or duplicate again to the selection ({})",
+                ui.ctx().format_shortcut(&crate::app::REPEAT_SHORTCUT)
+            ))
+            .clicked()
+        {
+            app.repeat_last_action();
+        }
+    });
+
+    ui.horizontal(|ui| {
+        if ui.button("Export…").clicked() {
+            app.open_export_dialog();
+        }
+        #[cfg(not(target_arch = "wasm32"))]
+        if ui.button("Print…").clicked() {
+            app.open_print_dialog();
+        }
+        if ui.button("Preferences…").clicked() {
+            app.open_preferences();
+        }
+        ui.menu_button("Edit", |ui| {
+            if ui
+                .add_enabled(
+                    !app.editor_model().elements.is_empty(),
+                    egui::Button::new("Replace colors…"),
+                )
+                .on_hover_text("Change every element using one color to another")
+                .clicked()
+            {
+                app.open_replace_colors_dialog();
+                ui.close_menu();
             }
-            ui.separator();
-
-            // Undo/Redo section
-            ui.horizontal(|ui| {
-                let can_undo = app.command_history().can_undo();
-                let can_redo = app.command_history().can_redo();
-
-                if ui
-                    .add_enabled(can_undo, egui::Button::new("Undo"))
-                    .clicked()
-                {
-                    app.undo();
+        });
+        ui.menu_button("View", |ui| view_menu_ui(app, ui));
+        ui.menu_button("Help", |ui| {
+            if ui.button("Report issue…").clicked() {
+                app.open_bug_report();
+                ui.close_menu();
+            }
+            if ui.button("Diagnostics (F12)").clicked() {
+                app.toggle_diagnostics();
+                ui.close_menu();
+            }
+            if ui.button("Log (Shift+F12)").clicked() {
+                app.toggle_log_console();
+                ui.close_menu();
+            }
+        });
+    });
+    ui.horizontal(|ui| {
+        if ui
+            .add_enabled(
+                !app.editor_model().elements.is_empty(),
+                egui::Button::new("Replay drawing"),
+            )
+            .clicked()
+        {
+            app.start_replay();
+        }
+        if ui
+            .button("Quick sketch")
+            .on_hover_text("Scribble in a small window that stays on top")
+            .clicked()
+        {
+            app.open_quick_sketch();
+        }
+        if ui
+            .button("Tutorial")
+            .on_hover_text("Learn the basics step by step in a practice document")
+            .clicked()
+        {
+            app.request_document_action(DocumentAction::Tutorial, ui.ctx());
+        }
+    });
+    #[cfg(not(target_arch = "wasm32"))]
+    screenshot_ui(app, ui);
+}
+
+/// Foreground and background colors as overlapping swatches; clicking one
+/// edits it, and the arrow beside them swaps them
+fn color_swatches_ui(app: &mut PaintApp, ui: &mut egui::Ui) {
+    let mut colors = app.colors();
+    ui.horizontal(|ui| {
+        let (rect, _) = ui.allocate_exact_size(
+            egui::Vec2::splat(SWATCH_SIZE + SWATCH_OFFSET),
+            egui::Sense::hover(),
+        );
+        let foreground_rect = egui::Rect::from_min_size(rect.min, egui::Vec2::splat(SWATCH_SIZE));
+        // The foreground swatch goes last, so it covers the background one and
+        // takes clicks where they overlap
+        swatch_ui(
+            ui,
+            foreground_rect.translate(egui::Vec2::splat(SWATCH_OFFSET)),
+            &mut colors.background,
+            "Background color",
+        );
+        swatch_ui(
+            ui,
+            foreground_rect,
+            &mut colors.foreground,
+            "Foreground color",
+        );
+        if ui
+            .small_button("⇄")
+            .on_hover_text(format!("Swap colors ({})", SWAP_COLORS_KEY.name()))
+            .clicked()
+        {
+            colors.swap();
+        }
+    });
+    app.set_colors(colors);
+}
+
+/// A color swatch at `rect` opening a color picker when clicked
+fn swatch_ui(ui: &mut egui::Ui, rect: egui::Rect, color: &mut egui::Color32, label: &str) {
+    let id = ui.make_persistent_id(label);
+    let response = ui
+        .interact(rect, id, egui::Sense::click())
+        .on_hover_text(label);
+    egui::color_picker::show_color_at(ui.painter(), *color, rect);
+    ui.painter()
+        .rect_stroke(rect, 0.0, ui.visuals().widgets.noninteractive.fg_stroke);
+
+    let popup_id = id.with("picker");
+    if response.clicked() {
+        ui.memory_mut(|memory| memory.toggle_popup(popup_id));
+    }
+    egui::popup_below_widget(
+        ui,
+        popup_id,
+        &response,
+        egui::PopupCloseBehavior::CloseOnClickOutside,
+        |ui| {
+            egui::color_picker::color_picker_color32(
+                ui,
+                color,
+                egui::color_picker::Alpha::OnlyBlend,
+            );
+        },
+    );
+}
+
+/// Zooming the canvas: to fit, to the selection, to actual size or a preset;
+/// then the rulers and the guides dragged out of them
+fn view_menu_ui(app: &mut PaintApp, ui: &mut egui::Ui) {
+    let ctx = ui.ctx().clone();
+    let shortcut = |shortcut: &egui::KeyboardShortcut| ctx.format_shortcut(shortcut);
+    let has_selection = !app.editor_model().selected_ids().is_empty();
+
+    let button = |ui: &mut egui::Ui, label: &str, keys: &egui::KeyboardShortcut| {
+        ui.add(egui::Button::new(label).shortcut_text(shortcut(keys)))
+    };
+    if button(ui, "Zoom In", &ZOOM_IN_SHORTCUT).clicked() {
+        app.zoom_step(1);
+        ui.close_menu();
+    }
+    if button(ui, "Zoom Out", &ZOOM_OUT_SHORTCUT).clicked() {
+        app.zoom_step(-1);
+        ui.close_menu();
+    }
+    ui.separator();
+    if button(ui, "Zoom to Fit", &ZOOM_TO_FIT_SHORTCUT).clicked() {
+        app.zoom_to_fit();
+        ui.close_menu();
+    }
+    if ui
+        .add_enabled(
+            has_selection,
+            egui::Button::new("Zoom to Selection")
+                .shortcut_text(shortcut(&ZOOM_TO_SELECTION_SHORTCUT)),
+        )
+        .clicked()
+    {
+        app.zoom_to_selection();
+        ui.close_menu();
+    }
+    if button(ui, "Actual Size", &ZOOM_ACTUAL_SIZE_SHORTCUT).clicked() {
+        app.zoom_to_actual_size();
+        ui.close_menu();
+    }
+    ui.separator();
+    let zoom = app.zoom();
+    for preset in ZOOM_PRESETS {
+        let selected = (preset - zoom).abs() < preset * 0.001;
+        if ui
+            .selectable_label(selected, format!("{:.0}%", preset * 100.0))
+            .clicked()
+        {
+            app.set_zoom(preset);
+            ui.close_menu();
+        }
+    }
+
+    ui.separator();
+    let settings = app.settings();
+    let (mut rulers, mut guides, mut locked) = (
+        settings.rulers,
+        settings.ruler_guides,
+        settings.lock_ruler_guides,
+    );
+    if ui
+        .checkbox(&mut rulers, "Rulers")
+        .on_hover_text("Drag guides out of the rulers onto the canvas")
+        .changed()
+    {
+        app.set_show_rulers(rulers);
+    }
+    if ui.checkbox(&mut guides, "Guides").changed() {
+        app.set_show_ruler_guides(guides);
+    }
+    if ui
+        .checkbox(&mut locked, "Lock Guides")
+        .on_hover_text("Keep guides from being moved with the Selection tool")
+        .changed()
+    {
+        app.set_lock_ruler_guides(locked);
+    }
+    let has_guides = !app.editor_model().ruler_guides.is_empty();
+    if ui
+        .add_enabled(has_guides, egui::Button::new("Clear Guides"))
+        .clicked()
+    {
+        app.clear_ruler_guides();
+        ui.close_menu();
+    }
+}
+
+/// Settings of the active tool, then the document's file and info
+pub fn properties_ui(app: &mut PaintApp, ui: &mut egui::Ui) {
+    // Get the active tool name before entering the UI group
+    let tool_name = app.active_tool().name().to_string();
+
+    ui.strong(format!("{} Tool", tool_name));
+
+    // Show tool-specific UI using the handle_tool_ui method
+    ui.group(|ui| {
+        if let Some(cmd) = app.handle_tool_ui(ui) {
+            app.execute_command(cmd);
+        }
+    });
+
+    ui.separator();
+    document_file_ui(app, ui);
+    document_info_ui(app, ui);
+    reference_image_ui(app, ui);
+}
+
+/// Checkpoints, the undo and redo stacks, oldest first, then the branches kept
+/// beside them
+pub fn history_ui(app: &mut PaintApp, ui: &mut egui::Ui) {
+    checkpoints_ui(app, ui);
+    ui.separator();
+
+    let history = app.command_history();
+
+    // Show the command history (undo stack)
+    let undo_stack = history.undo_stack();
+    let redo_stack = history.redo_stack();
+
+    if undo_stack.is_empty() && redo_stack.is_empty() {
+        ui.weak("Nothing to undo or redo yet");
+    } else {
+        egui::Grid::new("history_grid").show(ui, |ui| {
+            ui.label("Undo Stack");
+            ui.label("Redo Stack");
+            ui.end_row();
+
+            let max_rows = undo_stack.len().max(redo_stack.len());
+
+            for i in 0..max_rows {
+                // Undo Stack Column
+                if i < undo_stack.len() {
+                    ui.label(undo_stack[i].label());
+                } else {
+                    ui.label("");
                 }
-                if ui
-                    .add_enabled(can_redo, egui::Button::new("Redo"))
-                    .clicked()
-                {
-                    app.redo();
+
+                // Redo Stack Column
+                if i < redo_stack.len() {
+                    ui.label(redo_stack[i].label());
+                } else {
+                    ui.label("");
                 }
-            });
 
-            ui.separator();
-
-            let history = app.command_history();
-            
-            // Show the command history (undo stack)
-            let undo_stack = history.undo_stack();
-            let redo_stack = history.redo_stack();
-            
-            if !undo_stack.is_empty() || !redo_stack.is_empty() {
-                ui.label("Command History:");
-                egui::Grid::new("history_grid").show(ui, |ui| {
-                    ui.label("Undo Stack");
-                    ui.label("Redo Stack");
-                    ui.end_row();
-
-                    let max_rows = undo_stack.len().max(redo_stack.len());
-
-                    for i in 0..max_rows {
-                        // Undo Stack Column
-                        if i < undo_stack.len() {
-                            match &undo_stack[i] {
-                                Command::AddElement { .. } => {
-                                    ui.label("Add Element");
-                                }
-                                Command::RemoveElement { .. } => {
-                                    ui.label("Remove Element");
-                                }
-                                Command::ResizeElement { .. } => {
-                                    ui.label("Resize Element");
-                                }
-                                Command::MoveElement { .. } => {
-                                    ui.label("Move Element");
-                                }
-                                Command::SelectElement(_) => {
-                                    ui.label("Select Element");
-                                }
-                                Command::DeselectElement(_) => {
-                                    ui.label("Deselect Element");
-                                }
-                                Command::ClearSelection { .. } => {
-                                    ui.label("Clear Selection");
-                                }
-                                Command::ToggleSelection(_) => {
-                                    ui.label("Toggle Selection");
-                                }
-                            }
-                        } else {
-                            ui.label("");
-                        }
-
-                        // Redo Stack Column
-                        if i < redo_stack.len() {
-                            match &redo_stack[i] {
-                                Command::AddElement { .. } => {
-                                    ui.label("Add Element");
-                                }
-                                Command::RemoveElement { .. } => {
-                                    ui.label("Remove Element");
-                                }
-                                Command::ResizeElement { .. } => {
-                                    ui.label("Resize Element");
-                                }
-                                Command::MoveElement { .. } => {
-                                    ui.label("Move Element");
-                                }
-                                Command::SelectElement(_) => {
-                                    ui.label("Select Element");
-                                }
-                                Command::DeselectElement(_) => {
-                                    ui.label("Deselect Element");
-                                }
-                                Command::ClearSelection { .. } => {
-                                    ui.label("Clear Selection");
-                                }
-                                Command::ToggleSelection(_) => {
-                                    ui.label("Toggle Selection");
-                                }
-                            }
-                        } else {
-                            ui.label("");
-                        }
-
-                        ui.end_row();
-                    }
-                });
+                ui.end_row();
             }
+        });
+    }
+
+    let branches: Vec<String> = history
+        .branches()
+        .iter()
+        .map(|branch| {
+            let place = match branch.fork {
+                0 => "from the start".to_string(),
+                fork => format!("after step {}", fork),
+            };
+            let mut text = format!("⑂ {} ({} steps), {}", branch.label, branch.len, place);
+            if !branch.branches.is_empty() {
+                text += &format!(", {} more within", branch.branches.len());
+            }
+            text
+        })
+        .collect();
+    if branches.is_empty() {
+        return;
+    }
+    ui.separator();
+    ui.label("Branches");
+    let mut switch_to = None;
+    for (index, text) in branches.iter().enumerate() {
+        ui.horizontal(|ui| {
+            ui.label(text);
+            if ui
+                .small_button("Switch")
+                .on_hover_text("Undo back to where the branch starts, then redo its steps")
+                .clicked()
+            {
+                switch_to = Some(index);
+            }
+        });
+    }
+    if let Some(index) = switch_to {
+        app.switch_undo_branch(index);
+    }
+}
+
+/// Snapshots of the document to revert to, and a field naming the next one
+fn checkpoints_ui(app: &mut PaintApp, ui: &mut egui::Ui) {
+    let name_id = egui::Id::new("checkpoint_name");
+    let mut name: String = ui.data_mut(|data| data.get_temp(name_id).unwrap_or_default());
+    ui.horizontal(|ui| {
+        let default_name = format!(
+            "Checkpoint {}",
+            app.command_history().checkpoints().len() + 1
+        );
+        ui.add(
+            egui::TextEdit::singleline(&mut name)
+                .hint_text(&default_name)
+                .desired_width(120.0),
+        );
+        if ui
+            .button("Add checkpoint")
+            .on_hover_text("Snapshot the whole document to revert to in one step")
+            .clicked()
+        {
+            let name = if name.trim().is_empty() {
+                default_name
+            } else {
+                name.trim().to_string()
+            };
+            app.add_checkpoint(&name);
+            ui.data_mut(|data| data.remove::<String>(name_id));
+        } else {
+            ui.data_mut(|data| data.insert_temp(name_id, name));
+        }
+    });
+
+    let names: Vec<String> = app
+        .command_history()
+        .checkpoints()
+        .iter()
+        .map(|checkpoint| checkpoint.name.clone())
+        .collect();
+    let mut revert = None;
+    let mut remove = None;
+    for (index, name) in names.iter().enumerate() {
+        ui.horizontal(|ui| {
+            ui.label(format!("📌 {}", name));
+            if ui
+                .small_button("Revert")
+                .on_hover_text("Put the document back as it was here; undo brings it back")
+                .clicked()
+            {
+                revert = Some(index);
+            }
+            if ui
+                .small_button("🗑")
+                .on_hover_text("Forget this checkpoint")
+                .clicked()
+            {
+                remove = Some(index);
+            }
+        });
+    }
+    if let Some(index) = revert {
+        app.revert_to_checkpoint(index);
+    }
+    if let Some(index) = remove {
+        app.remove_checkpoint(index);
+    }
+}
+
+/// Path field and New/Open/Save buttons for the document file
+fn document_file_ui(app: &mut PaintApp, ui: &mut egui::Ui) {
+    ui.horizontal(|ui| {
+        ui.label(if app.is_dirty() { "File: •" } else { "File:" })
+            .on_hover_text("• marks unsaved changes");
+        ui.text_edit_singleline(app.document_path_mut());
+    });
+    ui.horizontal(|ui| {
+        if ui
+            .button("New…")
+            .on_hover_text("Start a document from a canvas size and template")
+            .clicked()
+        {
+            app.open_new_document_dialog();
+        }
+        if ui
+            .button("Open")
+            .on_hover_text("Open a document, or start one over a PNG, JPEG or other image")
+            .clicked()
+        {
+            app.request_document_action(DocumentAction::Open, ui.ctx());
+        }
+        if ui.button("Save").clicked() {
+            app.save_document();
+        }
+        if app.is_document_protected() {
+            if ui
+                .button("🔒 Unprotect")
+                .on_hover_text("Save the document without encryption")
+                .clicked()
+            {
+                app.unprotect_document();
+            }
+        } else if ui
+            .button("Protect…")
+            .on_hover_text("Save the document encrypted with a passphrase")
+            .clicked()
+        {
+            app.protect_document();
+        }
+    });
+    if app.has_linked_images() {
+        ui.horizontal(|ui| {
+            if ui
+                .button("Linked images…")
+                .on_hover_text("Relink images whose files moved, or embed them")
+                .clicked()
+            {
+                app.open_relink_dialog();
+            }
+            #[cfg(not(target_arch = "wasm32"))]
+            if ui
+                .button("Collect assets")
+                .on_hover_text("Copy linked images into a folder beside the document and save")
+                .clicked()
+            {
+                app.collect_assets();
+            }
+        });
+    }
+    #[cfg(target_arch = "wasm32")]
+    if ui
+        .button("Copy share link")
+        .on_hover_text("Copy a link that opens this document; works for small sketches")
+        .clicked()
+    {
+        app.copy_share_link(ui.ctx());
+    }
+}
 
-            // Get the active tool name before entering the UI group
-            let tool_name = app.active_tool().name().to_string();
+/// Screenshot-and-annotate button and one-click copy of the result
+#[cfg(not(target_arch = "wasm32"))]
+fn screenshot_ui(app: &mut PaintApp, ui: &mut egui::Ui) {
+    ui.horizontal(|ui| {
+        let mut hover = "Capture the screen and open it as a new document to draw on".to_string();
+        if cfg!(feature = "global-hotkey") && app.settings().screenshot_hotkey {
+            hover.push_str(&format!(" ({})", crate::screenshot::HOTKEY_LABEL));
+        }
+        if ui
+            .add_enabled(
+                !app.is_capturing_screenshot(),
+                egui::Button::new("Screenshot & annotate"),
+            )
+            .on_hover_text(hover)
+            .clicked()
+        {
+            app.start_screenshot(ui.ctx());
+        }
+        if ui
+            .add_enabled(
+                !app.editor_model().elements.is_empty(),
+                egui::Button::new("Copy image"),
+            )
+            .on_hover_text("Copy the document, as it would be exported, to the clipboard")
+            .clicked()
+        {
+            app.copy_image_to_clipboard(ui.ctx());
+        }
+        if ui
+            .add_enabled(
+                !app.editor_model().selected_ids().is_empty(),
+                egui::Button::new("Copy selection"),
+            )
+            .on_hover_text(format!(
+                "Copy the selected elements as an image, cropped to them ({})",
+                ui.ctx()
+                    .format_shortcut(&crate::app::COPY_SELECTION_SHORTCUT)
+            ))
+            .clicked()
+        {
+            app.copy_selection_to_clipboard(ui.ctx());
+        }
+    });
+    match app.screenshot_status() {
+        Some(Ok(message)) => {
+            ui.label(message);
+        }
+        Some(Err(message)) => {
+            ui.colored_label(egui::Color32::RED, message);
+        }
+        None => {}
+    }
+}
+
+/// Editable document title, author and copyright, committed as one undoable command
+///
+/// Edits go into a draft kept in egui memory while a field has focus, so typing
+/// doesn't push a command per keystroke.
+fn document_info_ui(app: &mut PaintApp, ui: &mut egui::Ui) {
+    let draft_id = ui.make_persistent_id("document_info_draft");
+    let mut draft = ui
+        .data_mut(|data| data.get_temp::<DocumentMetadata>(draft_id))
+        .unwrap_or_else(|| app.editor_model().metadata.clone());
+    let mut editing = false;
+    let mut committed = false;
+
+    egui::CollapsingHeader::new("Document").show(ui, |ui| {
+        egui::Grid::new("document_info_grid").show(ui, |ui| {
+            for (label, value) in [
+                ("Title", &mut draft.title),
+                ("Author", &mut draft.author),
+                ("Copyright", &mut draft.copyright),
+            ] {
+                ui.label(label);
+                let response = ui.text_edit_singleline(value);
+                editing |= response.has_focus();
+                committed |= response.lost_focus();
+                ui.end_row();
+            }
+        });
+        document_units_ui(app, ui);
+        if ui
+            .button("Properties…")
+            .on_hover_text("Canvas size, resolution and background")
+            .clicked()
+        {
+            app.open_document_properties();
+        }
+    });
 
-            ui.separator();
-            ui.heading(format!("{} Tool", tool_name));
+    if editing {
+        ui.data_mut(|data| data.insert_temp(draft_id, draft));
+        return;
+    }
+    ui.data_mut(|data| data.remove::<DocumentMetadata>(draft_id));
 
-            // Show tool-specific UI using the handle_tool_ui method
-            ui.group(|ui| {
-                if let Some(cmd) = app.handle_tool_ui(ui) {
-                    app.execute_command(cmd);
+    if committed {
+        if let Some(command) = Command::new_set_document_metadata(app.editor_model(), draft) {
+            app.execute_command(command);
+        }
+    }
+}
+
+/// Unit and resolution the document is measured in
+fn document_units_ui(app: &mut PaintApp, ui: &mut egui::Ui) {
+    // The DPI is edited in a draft and committed when dragging or typing ends,
+    // so one change is one undo step
+    let draft_id = ui.make_persistent_id("document_units_draft");
+    let mut units = ui
+        .data_mut(|data| data.get_temp::<DocumentUnits>(draft_id))
+        .unwrap_or(app.editor_model().units);
+    let mut commit = false;
+
+    ui.horizontal(|ui| {
+        ui.label("Units");
+        egui::ComboBox::from_id_salt("document_unit")
+            .selected_text(units.unit.label())
+            .show_ui(ui, |ui| {
+                for unit in Unit::ALL {
+                    commit |= ui
+                        .selectable_value(&mut units.unit, unit, unit.label())
+                        .clicked();
                 }
             });
-        });
+    });
+    let dpi = ui.horizontal(|ui| {
+        ui.label("Resolution");
+        ui.add(
+            egui::DragValue::new(&mut units.dpi)
+                .range(1.0..=2400.0)
+                .suffix(" dpi"),
+        )
+    });
+    let dpi = dpi.inner;
+    commit |= dpi.drag_stopped() || dpi.lost_focus();
+
+    if dpi.dragged() || dpi.has_focus() {
+        ui.data_mut(|data| data.insert_temp(draft_id, units));
+        return;
+    }
+    ui.data_mut(|data| data.remove::<DocumentUnits>(draft_id));
+
+    if commit {
+        if let Some(command) = Command::new_set_document_units(app.editor_model(), units) {
+            app.execute_command(command);
+        }
+    }
+}
+
+/// The reference image traced over: taken from the selected image, then faded,
+/// tiled or removed
+///
+/// Opacity is edited in a draft while the slider is dragged, so one change is
+/// one undo step.
+fn reference_image_ui(app: &mut PaintApp, ui: &mut egui::Ui) {
+    egui::CollapsingHeader::new("Reference image").show(ui, |ui| {
+        if let Some(ElementType::Image(image)) = app.get_first_selected_element() {
+            if ui
+                .button("Use selected image")
+                .on_hover_text("Move the image beneath everything, faded, to trace over")
+                .clicked()
+            {
+                let reference = ReferenceImage::new(image.original_data().to_vec(), image.rect());
+                let remove = Command::new_remove_element(app.editor_model(), image.id());
+                let set_reference =
+                    Command::new_set_reference_image(app.editor_model(), Some(reference));
+                if let (Some(remove), Some(set_reference)) = (remove, set_reference) {
+                    app.execute_command(Command::Batch {
+                        description: "Use as Reference Image",
+                        commands: vec![remove, set_reference],
+                    });
+                }
+            }
+        }
+
+        let Some(mut reference) = app.editor_model().reference_image.clone() else {
+            ui.weak("Select an image to trace over it");
+            return;
+        };
+        let draft_id = ui.make_persistent_id("reference_opacity_draft");
+        if let Some(opacity) = ui.data_mut(|data| data.get_temp::<f32>(draft_id)) {
+            reference.opacity = opacity;
+        }
+        let opacity = ui.add(
+            egui::Slider::new(&mut reference.opacity, 0.05..=1.0)
+                .text("Opacity")
+                .custom_formatter(|value, _| format!("{:.0}%", value * 100.0)),
+        );
+        let mut commit = opacity.drag_stopped() || (opacity.changed() && !opacity.dragged());
+        if opacity.dragged() {
+            ui.data_mut(|data| data.insert_temp(draft_id, reference.opacity));
+        } else {
+            ui.data_mut(|data| data.remove::<f32>(draft_id));
+        }
+        commit |= ui
+            .checkbox(&mut reference.tiled, "Tile")
+            .on_hover_text("Repeat the image across the canvas")
+            .changed();
+
+        let mut new_reference = Some(reference);
+        if ui.button("Remove").clicked() {
+            new_reference = None;
+            commit = true;
+        }
+        if commit {
+            if let Some(command) =
+                Command::new_set_reference_image(app.editor_model(), new_reference)
+            {
+                app.execute_command(command);
+            }
+        }
+    });
 }
@@ -0,0 +1,188 @@
+use crate::state::EditorModel;
+use crate::units::{DocumentUnits, Unit};
+use egui;
+
+/// Smallest scale the Transform window takes, in percent
+const MIN_SCALE_PERCENT: f32 = 1.0;
+
+/// Exact move, scale and turn typed into the Transform window
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct TransformValues {
+    /// How far to move, in pixels
+    pub translation: egui::Vec2,
+    /// Scale across and down, in percent
+    pub scale: egui::Vec2,
+    /// Clockwise turn, in degrees
+    pub rotation: f32,
+}
+
+impl Default for TransformValues {
+    fn default() -> Self {
+        Self {
+            translation: egui::Vec2::ZERO,
+            scale: egui::Vec2::splat(100.0),
+            rotation: 0.0,
+        }
+    }
+}
+
+impl TransformValues {
+    /// The scale as factors rather than percentages
+    pub fn scale_factors(&self) -> egui::Vec2 {
+        self.scale / 100.0
+    }
+}
+
+/// Transform window: move, scale and turn the selection by exact amounts
+///
+/// Everything typed in is applied together, as one undoable step, about the
+/// middle of the box around the selection.
+pub struct TransformDialog {
+    pub open: bool,
+    values: TransformValues,
+    // Keep the scale the same both ways
+    keep_proportions: bool,
+    // Size of the box around the selection, to type a new size instead of a scale
+    bounds: egui::Vec2,
+    units: DocumentUnits,
+}
+
+impl Default for TransformDialog {
+    fn default() -> Self {
+        Self {
+            open: false,
+            values: TransformValues::default(),
+            keep_proportions: true,
+            bounds: egui::Vec2::ZERO,
+            units: DocumentUnits::default(),
+        }
+    }
+}
+
+impl TransformDialog {
+    /// Open the window on the current selection, starting from no change
+    ///
+    /// Does nothing without a selection.
+    pub fn open_for(&mut self, editor_model: &EditorModel) {
+        let Some(bounds) = editor_model.selection_rect() else {
+            return;
+        };
+        self.open = true;
+        self.values = TransformValues::default();
+        self.bounds = bounds.size();
+        self.units = editor_model.units;
+    }
+
+    /// Show the Transform window if it is open
+    ///
+    /// Returns the values typed in when the user applies them.
+    pub fn show(&mut self, ctx: &egui::Context) -> Option<TransformValues> {
+        let mut open = self.open;
+        let mut apply = false;
+        let mut cancel = false;
+        egui::Window::new("Transform")
+            .open(&mut open)
+            .resizable(false)
+            .show(ctx, |ui| {
+                egui::Grid::new("transform_grid")
+                    .num_columns(2)
+                    .show(ui, |ui| self.values_ui(ui));
+                ui.separator();
+                ui.horizontal(|ui| {
+                    apply = ui.button("Apply").clicked();
+                    cancel = ui.button("Cancel").clicked();
+                    if ui.button("Reset").clicked() {
+                        self.values = TransformValues::default();
+                    }
+                });
+            });
+
+        self.open = open && !apply && !cancel;
+        apply.then_some(self.values)
+    }
+
+    fn values_ui(&mut self, ui: &mut egui::Ui) {
+        let values = &mut self.values;
+        let units = self.units;
+
+        ui.label("Move");
+        ui.horizontal(|ui| {
+            ui.add(
+                egui::DragValue::new(&mut values.translation.x)
+                    .prefix("x: ")
+                    .suffix(" px"),
+            );
+            ui.add(
+                egui::DragValue::new(&mut values.translation.y)
+                    .prefix("y: ")
+                    .suffix(" px"),
+            );
+            if units.unit != Unit::Px {
+                ui.weak(units.format_pos(values.translation.to_pos2()));
+            }
+        });
+        ui.end_row();
+
+        ui.label("Scale");
+        ui.horizontal(|ui| {
+            let before = values.scale;
+            for scale in [&mut values.scale.x, &mut values.scale.y] {
+                ui.add(
+                    egui::DragValue::new(scale)
+                        .range(MIN_SCALE_PERCENT..=10_000.0)
+                        .suffix("%"),
+                );
+            }
+            ui.checkbox(&mut self.keep_proportions, "Keep proportions");
+            if self.keep_proportions {
+                // Whichever was changed sets both
+                if values.scale.x != before.x {
+                    values.scale.y = values.scale.x;
+                } else {
+                    values.scale.x = values.scale.y;
+                }
+            }
+        });
+        ui.end_row();
+
+        // The size the scale comes to, to type in instead
+        ui.label("Size");
+        ui.horizontal(|ui| {
+            let bounds = self.bounds.max(egui::Vec2::splat(f32::EPSILON));
+            let mut size = bounds * values.scale_factors();
+            let before = size;
+            ui.add(
+                egui::DragValue::new(&mut size.x)
+                    .range(0.0..=100_000.0)
+                    .suffix(" px"),
+            );
+            ui.label("×");
+            ui.add(
+                egui::DragValue::new(&mut size.y)
+                    .range(0.0..=100_000.0)
+                    .suffix(" px"),
+            );
+            if size != before {
+                let mut scale = size / bounds * 100.0;
+                if self.keep_proportions {
+                    let changed = if size.x != before.x { scale.x } else { scale.y };
+                    scale = egui::Vec2::splat(changed);
+                }
+                values.scale = scale.max(egui::Vec2::splat(MIN_SCALE_PERCENT));
+            }
+            if units.unit != Unit::Px {
+                ui.weak(units.format_size(size));
+            }
+        });
+        ui.end_row();
+
+        ui.label("Rotate");
+        ui.add(
+            egui::DragValue::new(&mut values.rotation)
+                .range(-360.0..=360.0)
+                .suffix("°"),
+        )
+        .on_hover_text("Clockwise, about the middle of the selection");
+        ui.end_row();
+    }
+}
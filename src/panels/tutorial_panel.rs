@@ -0,0 +1,47 @@
+use crate::tutorial::{Tutorial, TutorialTask};
+use egui;
+
+/// Step panel for the running tutorial
+///
+/// Lists the tasks with finished ones ticked and the instructions for the current
+/// one. Tasks tick themselves off as the tutorial sees them done, so the panel
+/// only has to redraw; closing it ends the tutorial.
+pub fn tutorial_window(ctx: &egui::Context, tutorial: &mut Option<Tutorial>) {
+    let Some(running) = tutorial.as_mut() else {
+        return;
+    };
+    running.update();
+
+    let mut open = true;
+    let mut exit = false;
+    egui::Window::new("Tutorial")
+        .open(&mut open)
+        .resizable(false)
+        .default_pos(ctx.screen_rect().right_top() + egui::vec2(-280.0, 40.0))
+        .show(ctx, |ui| {
+            let current = running.current_task();
+            for (index, task) in TutorialTask::ALL.iter().enumerate() {
+                let text = format!("{}. {}", index + 1, task.title());
+                if running.is_done(*task) {
+                    ui.label(format!("✔ {}", text));
+                } else if current == Some(*task) {
+                    ui.strong(text);
+                    ui.label(task.instructions());
+                } else {
+                    ui.weak(text);
+                }
+            }
+
+            ui.separator();
+            if running.is_finished() {
+                ui.label("All done! You know the basics now.");
+                exit = ui.button("Close").clicked();
+            } else {
+                exit = ui.button("Exit tutorial").clicked();
+            }
+        });
+
+    if !open || exit {
+        *tutorial = None;
+    }
+}
@@ -0,0 +1,50 @@
+use egui;
+
+/// Answer to the unsaved-changes prompt
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum UnsavedChoice {
+    Save,
+    Discard,
+    Cancel,
+}
+
+/// Ask whether to save changes that are about to be lost
+///
+/// Shown as a modal every frame until it returns the user's choice. Escape or
+/// clicking outside counts as Cancel.
+pub fn unsaved_changes_prompt(
+    ctx: &egui::Context,
+    document_name: &str,
+    error: Option<&str>,
+) -> Option<UnsavedChoice> {
+    let modal = egui::Modal::new(egui::Id::new("unsaved_changes_prompt")).show(ctx, |ui| {
+        ui.heading("Unsaved changes");
+        ui.label(format!(
+            "Do you want to save the changes to \"{}\"?",
+            document_name
+        ));
+        if let Some(error) = error {
+            ui.colored_label(egui::Color32::RED, error);
+        }
+
+        ui.separator();
+        ui.horizontal(|ui| {
+            if ui.button("Save").clicked() {
+                Some(UnsavedChoice::Save)
+            } else if ui.button("Discard").clicked() {
+                Some(UnsavedChoice::Discard)
+            } else if ui.button("Cancel").clicked() {
+                Some(UnsavedChoice::Cancel)
+            } else {
+                None
+            }
+        })
+        .inner
+    });
+
+    if modal.should_close() {
+        Some(UnsavedChoice::Cancel)
+    } else {
+        modal.inner
+    }
+}
@@ -0,0 +1,146 @@
+//! How pen pressure from a tablet shapes the brush
+//!
+//! Each brush tool has a response curve for size and one for opacity, mapping
+//! the pressure the pen reports, from 0 to 1, to a factor on the brush's size
+//! or opacity. Pointers that report no pressure, like mice, draw at full size
+//! and opacity whatever the curves.
+
+use crate::tools::ToolConfig;
+use serde::{Deserialize, Serialize};
+use std::any::Any;
+
+/// Tools whose brush follows pen pressure, by tool name
+pub const PRESSURE_TOOLS: [&str; 2] = ["Draw Stroke", "Pixel Brush"];
+
+/// Points on a curve closer together than this, in pressure, merge into one
+pub const MIN_POINT_SPACING: f32 = 0.02;
+
+/// A response curve from pressure to a factor, both from 0 to 1
+///
+/// A smooth spline through its points, which are sorted by pressure; the first
+/// is always at pressure 0 and the last at pressure 1.
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
+pub struct PressureCurve {
+    points: Vec<[f32; 2]>,
+}
+
+impl PressureCurve {
+    /// A curve through `points`, sorted, clamped and with its ends pinned to
+    /// pressures 0 and 1
+    pub fn new(points: impl IntoIterator<Item = [f32; 2]>) -> Self {
+        let mut points: Vec<[f32; 2]> = points
+            .into_iter()
+            .map(|[x, y]| [x.clamp(0.0, 1.0), y.clamp(0.0, 1.0)])
+            .collect();
+        points.sort_by(|a, b| a[0].total_cmp(&b[0]));
+        points.dedup_by(|b, a| b[0] - a[0] < MIN_POINT_SPACING);
+        match points.as_mut_slice() {
+            [] => return Self::linear(0.0, 1.0),
+            [only] => return Self::linear(only[1], only[1]),
+            [first, .., last] => {
+                first[0] = 0.0;
+                last[0] = 1.0;
+            }
+        }
+        Self { points }
+    }
+
+    /// A straight line from `low` at no pressure to `high` at full pressure
+    pub fn linear(low: f32, high: f32) -> Self {
+        Self {
+            points: vec![[0.0, low], [1.0, high]],
+        }
+    }
+
+    pub fn points(&self) -> &[[f32; 2]] {
+        &self.points
+    }
+
+    /// The factor at `pressure`
+    pub fn eval(&self, pressure: f32) -> f32 {
+        let x = pressure.clamp(0.0, 1.0);
+        let points = &self.points;
+        let Some(i) = points.windows(2).position(|pair| x <= pair[1][0]) else {
+            return points.last().map_or(1.0, |point| point[1]);
+        };
+        let (p1, p2) = (points[i], points[i + 1]);
+        let p0 = if i > 0 { points[i - 1] } else { p1 };
+        let p3 = points.get(i + 2).copied().unwrap_or(p2);
+        let t = (x - p1[0]) / (p2[0] - p1[0]).max(f32::EPSILON);
+        catmull_rom(p0[1], p1[1], p2[1], p3[1], t).clamp(0.0, 1.0)
+    }
+}
+
+/// Value `t` of the way from `y1` to `y2` on a Catmull-Rom spline through all four
+fn catmull_rom(y0: f32, y1: f32, y2: f32, y3: f32, t: f32) -> f32 {
+    let (t2, t3) = (t * t, t * t * t);
+    0.5 * (2.0 * y1
+        + (y2 - y0) * t
+        + (2.0 * y0 - 5.0 * y1 + 4.0 * y2 - y3) * t2
+        + (3.0 * y1 - y0 - 3.0 * y2 + y3) * t3)
+}
+
+/// A brush tool's response to pressure, applied to the tool as a config
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
+#[serde(default)]
+pub struct PressureResponse {
+    pub size: PressureCurve,
+    pub opacity: PressureCurve,
+}
+
+impl PressureResponse {
+    /// Size and opacity factors at `pressure`; full for pointers without pressure
+    pub fn factors(&self, pressure: Option<f32>) -> (f32, f32) {
+        pressure.map_or((1.0, 1.0), |pressure| {
+            (self.size.eval(pressure), self.opacity.eval(pressure))
+        })
+    }
+}
+
+impl Default for PressureResponse {
+    /// Light pressure thins the brush; opacity stays full
+    fn default() -> Self {
+        Self {
+            size: PressureCurve::linear(0.2, 1.0),
+            opacity: PressureCurve::linear(1.0, 1.0),
+        }
+    }
+}
+
+impl ToolConfig for PressureResponse {
+    fn tool_name(&self) -> &'static str {
+        "Pressure"
+    }
+
+    fn as_any(&self) -> &dyn Any {
+        self
+    }
+
+    fn as_any_mut(&mut self) -> &mut dyn Any {
+        self
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_curves_pass_through_their_points() {
+        let curve = PressureCurve::new([[0.1, 0.0], [0.5, 0.8], [0.9, 1.0]]);
+        assert_eq!(curve.points(), [[0.0, 0.0], [0.5, 0.8], [1.0, 1.0]]);
+        assert_eq!(curve.eval(0.0), 0.0);
+        assert!((curve.eval(0.5) - 0.8).abs() < 1e-6);
+        assert_eq!(curve.eval(1.0), 1.0);
+        // Rises smoothly in between and stays in range past the ends
+        assert!(curve.eval(0.25) > 0.0 && curve.eval(0.25) < 0.8);
+        assert_eq!(curve.eval(2.0), 1.0);
+
+        let linear = PressureCurve::linear(0.2, 1.0);
+        assert!((linear.eval(0.5) - 0.6).abs() < 1e-6);
+
+        let response = PressureResponse::default();
+        assert_eq!(response.factors(None), (1.0, 1.0));
+        assert_eq!(response.factors(Some(0.0)), (0.2, 1.0));
+    }
+}
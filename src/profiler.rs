@@ -0,0 +1,193 @@
+//! Per-frame timing of the editor's main phases, for the diagnostics window
+//!
+//! Phases nest: time spent in an inner phase (say texture generation while
+//! painting) counts towards it alone, so a frame's phases add up to no more
+//! than the frame.
+
+use std::collections::VecDeque;
+use std::time::Duration;
+use web_time::Instant;
+
+/// How many recent frames are kept for the frame-time graph
+pub const FRAME_HISTORY: usize = 240;
+
+/// A part of the frame's work that gets timed on its own
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Phase {
+    InputRouting,
+    CommandExecution,
+    TextureGeneration,
+    Painting,
+}
+
+impl Phase {
+    pub const ALL: [Phase; 4] = [
+        Phase::InputRouting,
+        Phase::CommandExecution,
+        Phase::TextureGeneration,
+        Phase::Painting,
+    ];
+
+    pub fn label(self) -> &'static str {
+        match self {
+            Phase::InputRouting => "Input routing",
+            Phase::CommandExecution => "Command execution",
+            Phase::TextureGeneration => "Texture generation",
+            Phase::Painting => "Painting",
+        }
+    }
+
+    fn index(self) -> usize {
+        self as usize
+    }
+}
+
+/// Timings of one finished frame
+#[derive(Clone, Copy, Debug, Default, PartialEq)]
+pub struct FrameTimings {
+    /// The whole frame, from `begin_frame` to `end_frame`
+    pub total: Duration,
+    /// Time in each phase, indexed like `Phase::ALL`
+    pub phases: [Duration; 4],
+}
+
+impl FrameTimings {
+    pub fn phase(&self, phase: Phase) -> Duration {
+        self.phases[phase.index()]
+    }
+}
+
+/// Collects phase timings for the current frame and keeps a rolling history
+#[derive(Debug)]
+pub struct FrameProfiler {
+    frame_start: Option<Instant>,
+    // When the innermost open phase was last charged
+    mark: Instant,
+    open_phases: Vec<Phase>,
+    current: FrameTimings,
+    history: VecDeque<FrameTimings>,
+}
+
+impl Default for FrameProfiler {
+    fn default() -> Self {
+        Self {
+            frame_start: None,
+            mark: Instant::now(),
+            open_phases: Vec::new(),
+            current: FrameTimings::default(),
+            history: VecDeque::with_capacity(FRAME_HISTORY),
+        }
+    }
+}
+
+impl FrameProfiler {
+    pub fn begin_frame(&mut self) {
+        let now = Instant::now();
+        self.frame_start = Some(now);
+        self.mark = now;
+        self.open_phases.clear();
+        self.current = FrameTimings::default();
+    }
+
+    /// Finish the frame and add it to the history
+    pub fn end_frame(&mut self) {
+        let Some(frame_start) = self.frame_start.take() else {
+            return;
+        };
+        self.charge_open_phase();
+        self.current.total = frame_start.elapsed();
+        if self.history.len() == FRAME_HISTORY {
+            self.history.pop_front();
+        }
+        self.history.push_back(self.current);
+    }
+
+    /// Start timing `phase`, pausing whichever phase was running
+    pub fn begin(&mut self, phase: Phase) {
+        self.charge_open_phase();
+        self.open_phases.push(phase);
+    }
+
+    /// Stop timing the phase started last, resuming the one it interrupted
+    pub fn end(&mut self) {
+        self.charge_open_phase();
+        self.open_phases.pop();
+    }
+
+    /// Time `work` as `phase`
+    pub fn time<R>(&mut self, phase: Phase, work: impl FnOnce() -> R) -> R {
+        self.begin(phase);
+        let result = work();
+        self.end();
+        result
+    }
+
+    fn charge_open_phase(&mut self) {
+        let now = Instant::now();
+        if let Some(phase) = self.open_phases.last() {
+            self.current.phases[phase.index()] += now - self.mark;
+        }
+        self.mark = now;
+    }
+
+    /// Finished frames, oldest first
+    pub fn history(&self) -> &VecDeque<FrameTimings> {
+        &self.history
+    }
+
+    /// Mean timings over the frames in the history
+    pub fn average(&self) -> FrameTimings {
+        let mut sum = FrameTimings::default();
+        if self.history.is_empty() {
+            return sum;
+        }
+        for frame in &self.history {
+            sum.total += frame.total;
+            for (total, phase) in sum.phases.iter_mut().zip(frame.phases) {
+                *total += phase;
+            }
+        }
+        let count = self.history.len() as u32;
+        FrameTimings {
+            total: sum.total / count,
+            phases: sum.phases.map(|phase| phase / count),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_nested_phases_are_charged_exclusively() {
+        let mut profiler = FrameProfiler::default();
+        profiler.begin_frame();
+        profiler.begin(Phase::Painting);
+        std::thread::sleep(Duration::from_millis(5));
+        profiler.time(Phase::TextureGeneration, || {
+            std::thread::sleep(Duration::from_millis(10));
+        });
+        profiler.end();
+        profiler.end_frame();
+
+        assert_eq!(profiler.history().len(), 1);
+        let frame = profiler.history()[0];
+        let painting = frame.phase(Phase::Painting);
+        let textures = frame.phase(Phase::TextureGeneration);
+        assert!(painting >= Duration::from_millis(5));
+        assert!(textures >= Duration::from_millis(10));
+        // Painting doesn't include the texture generation inside it
+        assert!(frame.total >= painting + textures);
+        assert_eq!(frame.phase(Phase::InputRouting), Duration::ZERO);
+
+        // Frames that never began aren't recorded, and the history stays bounded
+        profiler.end_frame();
+        for _ in 0..FRAME_HISTORY + 10 {
+            profiler.begin_frame();
+            profiler.end_frame();
+        }
+        assert_eq!(profiler.history().len(), FRAME_HISTORY);
+        assert!(profiler.average().total <= frame.total);
+    }
+}
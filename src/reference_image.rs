@@ -0,0 +1,169 @@
+//! An image to trace over
+//!
+//! A document can hold one reference image, drawn beneath every element at a
+//! chosen opacity and optionally tiled across the canvas. It isn't an element:
+//! it can't be selected or edited on the canvas, and exports leave it out
+//! unless their settings ask for it.
+
+use egui::{Color32, ColorImage, Context, Rect, TextureHandle, TextureOptions};
+use serde::{Deserialize, Serialize};
+
+/// Opacity a new reference image starts at, faint enough to draw over
+pub const DEFAULT_OPACITY: f32 = 0.5;
+/// Most copies drawn when tiling, against a tiny image tiled over a huge canvas
+const MAX_TILES: usize = 4096;
+
+#[derive(Clone, Serialize, Deserialize)]
+pub struct ReferenceImage {
+    /// The encoded image (PNG, JPEG, ...)
+    data: Vec<u8>,
+    /// Where the image is drawn; when tiled, the copy the others line up with
+    pub rect: Rect,
+    pub opacity: f32,
+    /// Repeat the image across the whole canvas
+    pub tiled: bool,
+    /// None until first drawn, then the texture, or None if the image couldn't be decoded
+    #[serde(skip)]
+    texture: Option<Option<TextureHandle>>,
+}
+
+// Custom Debug implementation since TextureHandle doesn't implement Debug
+impl std::fmt::Debug for ReferenceImage {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("ReferenceImage")
+            .field("data_len", &self.data.len())
+            .field("rect", &self.rect)
+            .field("opacity", &self.opacity)
+            .field("tiled", &self.tiled)
+            .finish()
+    }
+}
+
+impl PartialEq for ReferenceImage {
+    fn eq(&self, other: &Self) -> bool {
+        self.rect == other.rect
+            && self.opacity == other.opacity
+            && self.tiled == other.tiled
+            && self.data == other.data
+    }
+}
+
+impl ReferenceImage {
+    /// A reference image of the encoded image `data`, drawn over `rect`
+    pub fn new(data: Vec<u8>, rect: Rect) -> Self {
+        Self {
+            data,
+            rect,
+            opacity: DEFAULT_OPACITY,
+            tiled: false,
+            texture: None,
+        }
+    }
+
+    /// The encoded image
+    pub fn data(&self) -> &[u8] {
+        &self.data
+    }
+
+    /// The same reference image showing the encoded image `data` instead
+    pub fn with_data(&self, data: Vec<u8>) -> Self {
+        Self {
+            data,
+            rect: self.rect,
+            opacity: self.opacity,
+            tiled: self.tiled,
+            texture: None,
+        }
+    }
+
+    /// Decode the image at its own resolution
+    pub fn decode(&self) -> Result<ColorImage, image::ImageError> {
+        let rgba = image::load_from_memory(&self.data)?.to_rgba8();
+        let size = [rgba.width() as usize, rgba.height() as usize];
+        Ok(ColorImage::from_rgba_unmultiplied(size, rgba.as_raw()))
+    }
+
+    /// The texture to draw the image with, decoded and uploaded on first use
+    pub fn texture(&mut self, ctx: &Context) -> Option<&TextureHandle> {
+        if self.texture.is_none() {
+            let texture = match self.decode() {
+                Ok(image) => {
+                    Some(ctx.load_texture("reference_image", image, TextureOptions::LINEAR))
+                }
+                Err(err) => {
+                    log::warn!("Failed to decode the reference image: {}", err);
+                    None
+                }
+            };
+            self.texture = Some(texture);
+        }
+        self.texture.as_ref().and_then(Option::as_ref)
+    }
+
+    /// Color to tint the texture with for the image's opacity
+    pub fn tint(&self) -> Color32 {
+        Color32::WHITE.gamma_multiply(self.opacity)
+    }
+
+    /// Where copies of the image go to cover `area`: just its own rect unless
+    /// it's tiled
+    pub fn tiles(&self, area: Rect) -> Vec<Rect> {
+        let size = self.rect.size();
+        if !self.tiled || size.x <= 0.0 || size.y <= 0.0 {
+            return if self.rect.intersects(area) {
+                vec![self.rect]
+            } else {
+                Vec::new()
+            };
+        }
+
+        let first = ((area.min - self.rect.min) / size).floor();
+        let last = ((area.max - self.rect.min) / size).ceil();
+        let mut tiles = Vec::new();
+        'rows: for row in first.y as i64..last.y as i64 {
+            for column in first.x as i64..last.x as i64 {
+                if tiles.len() == MAX_TILES {
+                    break 'rows;
+                }
+                let min = self.rect.min + size * egui::vec2(column as f32, row as f32);
+                tiles.push(Rect::from_min_size(min, size));
+            }
+        }
+        tiles
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use egui::{pos2, vec2};
+
+    #[test]
+    fn test_tiles_cover_the_area() {
+        let mut reference = ReferenceImage::new(
+            Vec::new(),
+            Rect::from_min_size(pos2(10.0, 10.0), vec2(20.0, 10.0)),
+        );
+        let area = Rect::from_min_max(pos2(0.0, 0.0), pos2(50.0, 25.0));
+        assert_eq!(reference.tiles(area), vec![reference.rect]);
+        assert!(
+            reference
+                .tiles(Rect::from_min_size(pos2(100.0, 100.0), vec2(5.0, 5.0)))
+                .is_empty()
+        );
+
+        reference.tiled = true;
+        let tiles = reference.tiles(area);
+        // Columns from x = -10 to 50 and rows from y = 0 to 30, lined up with the image
+        assert_eq!(tiles.len(), 3 * 3);
+        assert_eq!(
+            tiles[0],
+            Rect::from_min_size(pos2(-10.0, 0.0), vec2(20.0, 10.0))
+        );
+        assert!(tiles.contains(&reference.rect));
+        let covered = tiles
+            .iter()
+            .fold(Rect::NOTHING, |acc, tile| acc.union(*tile));
+        assert!(covered.contains_rect(area));
+    }
+}
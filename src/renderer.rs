@@ -1,16 +1,67 @@
 // src/renderer.rs
-use crate::element::{Element, ElementType};
-use crate::state::EditorModel;
-use crate::texture_manager::TextureManager;
-use crate::widgets::{Corner, ResizeHandle};
+use crate::element::image::Image;
+use crate::element::{Element, ElementType, Obb, PathNode, PixelRegion, Resampling, StrokeStyle};
+use crate::guides::Guides;
+use crate::profiler::{FRAME_HISTORY, FrameProfiler, Phase};
+use crate::reference_image::ReferenceImage;
+use crate::ruler_guides::GUIDE_SNAP_DISTANCE;
+use crate::settings::Settings;
+use crate::state::{EditorModel, ElementId};
+use crate::tessellation;
+use crate::texture_manager::{TextureGenerationError, TextureManager, size_bucket};
+use crate::view;
+use crate::widgets::{TransformHandle, TransformHandleStyle, TransformHandles};
 use eframe::egui;
+use egui::emath::RectTransform;
 use std::collections::HashMap;
 
+// Canvas area around the artboards
+const PASTEBOARD_COLOR: egui::Color32 = egui::Color32::from_gray(110);
+const ARTBOARD_BORDER_COLOR: egui::Color32 = egui::Color32::from_gray(60);
+const ACTIVE_ARTBOARD_BORDER_COLOR: egui::Color32 = egui::Color32::from_rgb(0, 120, 215);
+// Gap between an artboard's top edge and its name
+const ARTBOARD_LABEL_OFFSET: f32 = 4.0;
+// Export slices are outlined dashed, named just below their bottom edge
+const SLICE_BORDER_COLOR: egui::Color32 = egui::Color32::from_rgb(230, 120, 20);
+const SLICE_DASH: f32 = 4.0;
+// Anchors and control handles shown while drawing or editing a path
+const PATH_NODE_COLOR: egui::Color32 = egui::Color32::from_rgb(30, 120, 255);
+const PATH_ANCHOR_SIZE: f32 = 7.0;
+const PATH_HANDLE_RADIUS: f32 = 3.5;
+// Dashes of the lasso drawn while freeform selecting
+const LASSO_COLOR: egui::Color32 = egui::Color32::from_rgb(30, 120, 255);
+const LASSO_DASH: f32 = 6.0;
+// Brush outlines never shrink below this radius, so thin brushes stay findable
+const MIN_BRUSH_OUTLINE_RADIUS: f32 = 3.0;
+// How long the brush size stays on the canvas after it changes, fading out over the last half
+const BRUSH_SIZE_HUD_SECONDS: f32 = 1.0;
+// Laser pointer trails: a bright core in a soft glow, the same width on screen at
+// any zoom, held for a moment once let go and then faded out
+const LASER_COLOR: egui::Color32 = egui::Color32::from_rgb(255, 30, 60);
+const LASER_CORE_WIDTH: f32 = 3.0;
+const LASER_GLOW_WIDTH: f32 = 12.0;
+const LASER_HOLD_SECONDS: f32 = 1.5;
+const LASER_FADE_SECONDS: f32 = 0.5;
+// Outline of selected elements and of the box around a multiple selection
+const SELECTION_COLOR: egui::Color32 = egui::Color32::from_rgb(30, 120, 255);
+// Smallest width or height a resize handle can shrink an element to
+const MIN_RESIZE_SIZE: f32 = 10.0;
+// Fewest screen points between canvas grid lines; denser grids skip lines
+const MIN_GRID_SPACING: f32 = 8.0;
+// Colors of the phases in the diagnostics frame-time graph, in `Phase::ALL` order
+const PHASE_COLORS: [egui::Color32; 4] = [
+    egui::Color32::from_rgb(90, 170, 255),
+    egui::Color32::from_rgb(255, 170, 60),
+    egui::Color32::from_rgb(220, 90, 200),
+    egui::Color32::from_rgb(100, 200, 120),
+];
+
 /// Represents a stroke being previewed as it's drawn
 pub struct StrokePreview {
     points: Vec<egui::Pos2>,
     thickness: f32,
     color: egui::Color32,
+    style: StrokeStyle,
 }
 
 impl StrokePreview {
@@ -19,9 +70,15 @@ impl StrokePreview {
             points,
             thickness,
             color,
+            style: StrokeStyle::default(),
         }
     }
 
+    pub fn with_style(mut self, style: StrokeStyle) -> Self {
+        self.style = style;
+        self
+    }
+
     pub fn points(&self) -> &[egui::Pos2] {
         &self.points
     }
@@ -33,21 +90,40 @@ impl StrokePreview {
     pub fn color(&self) -> egui::Color32 {
         self.color
     }
+
+    pub fn style(&self) -> &StrokeStyle {
+        &self.style
+    }
 }
 
 pub struct Renderer {
     _gl: Option<std::sync::Arc<eframe::glow::Context>>,
     preview_stroke: Option<StrokePreview>,
     // Track active resize handles
-    active_handles: HashMap<usize, Corner>,
+    active_handles: HashMap<ElementId, TransformHandle>,
+    // Look of the handles around selected elements
+    handle_style: TransformHandleStyle,
     // Track resize preview rectangle
     resize_preview: Option<egui::Rect>,
+    // Box around a multiple selection being resized, from before the drag; the
+    // elements are drawn scaled from it onto `resize_preview`
+    selection_resize_origin: Option<egui::Rect>,
+    // Handle of a multiple selection's box that's hovered or dragged
+    selection_handle: Option<TransformHandle>,
     // Track drag preview rectangle
     drag_preview: Option<egui::Rect>,
+    // Nodes of the path being drawn or edited, shown with their handles
+    path_node_preview: Option<Vec<PathNode>>,
+    // Freeform selection path being drawn
+    lasso_preview: Option<Vec<egui::Pos2>>,
+    // Pixels of an image being painted on, drawn over the image's bounds
+    pixel_preview: Option<(egui::TextureHandle, Obb)>,
+    // Copies the Array window would add, drawn with their outlines
+    array_preview: Option<Vec<ElementType>>,
     // Frame counter for debugging and unique texture names
     frame_counter: u64,
     // Track elements rendered this frame to prevent duplicates
-    elements_rendered_this_frame: std::collections::HashSet<usize>,
+    elements_rendered_this_frame: std::collections::HashSet<ElementId>,
     // Store a reference to the egui context for repaint requests
     ctx: Option<egui::Context>,
     // Texture manager for caching element textures
@@ -56,31 +132,144 @@ pub struct Renderer {
     editor_model: Option<*const EditorModel>,
     // Flag to suppress selection drawing during resize/drag operations
     suppress_selection_drawing: bool,
+    // Canvas fill behind all elements
+    background_color: egui::Color32,
+    // Show a checkerboard through a transparent background
+    checkerboard: bool,
+    // Spacing of the canvas grid, 0 to hide it
+    grid_size: f32,
+    // Outline each document pixel when zoomed in far enough
+    pixel_grid: bool,
+    // Snap positions handed to tools to pixels when zoomed in far enough
+    snap_to_pixels: bool,
+    // Perspective or isometric guides drawn over the elements
+    guides: Guides,
+    // Rulers along the canvas, and the ruler guides: shown, and locked
+    rulers: bool,
+    ruler_guides: bool,
+    lock_ruler_guides: bool,
+    // Hide elements outside the document's artboards
+    clip_to_artboard: bool,
+    // When set, only these elements are drawn (used by replay)
+    visible_elements: Option<std::collections::HashSet<ElementId>>,
+    // Draw opaque strokes and paths as meshes instead of textures
+    vector_strokes: bool,
+    // Outline the brush at the pointer for tools that have one
+    brush_outline: bool,
+    // How images are resampled to their size on screen
+    image_resampling: Resampling,
+    // Screen points per document unit of the canvas layer, as of the last render
+    canvas_scale: f32,
+    // Brush size just set, shown briefly as a circle: center, diameter and when it was set
+    brush_size_hud: Option<(egui::Pos2, f32, web_time::Instant)>,
+    // Laser pointer trail being drawn, and trails let go of with when they were
+    laser_trail: Option<Vec<egui::Pos2>>,
+    fading_laser_trails: Vec<(Vec<egui::Pos2>, web_time::Instant)>,
+    // Shapes queued while drawing elements, kept to reuse the allocation each frame
+    frame_shapes: Vec<egui::Shape>,
+    // Timings of each frame's phases, for the diagnostics window
+    profiler: FrameProfiler,
 }
 
 impl Renderer {
     pub fn new(cc: &eframe::CreationContext<'_>) -> Self {
-        let gl = cc.gl.clone();
-        let ctx = cc.egui_ctx.clone();
+        Self {
+            _gl: cc.gl.clone(),
+            ..Self::headless(cc.egui_ctx.clone())
+        }
+    }
 
+    /// A renderer without a GL context, drawing through `ctx` alone, as when
+    /// running the editor in tests
+    pub fn headless(ctx: egui::Context) -> Self {
         // Initialize texture manager with a reasonable cache size
         let texture_manager = TextureManager::new(100);
 
         Self {
-            _gl: gl,
+            _gl: None,
             preview_stroke: None,
             active_handles: HashMap::new(),
+            handle_style: TransformHandleStyle::default(),
             resize_preview: None,
+            selection_resize_origin: None,
+            selection_handle: None,
             drag_preview: None,
+            path_node_preview: None,
+            lasso_preview: None,
+            pixel_preview: None,
+            array_preview: None,
             frame_counter: 0,
             elements_rendered_this_frame: std::collections::HashSet::new(),
             ctx: Some(ctx),
             texture_manager,
             editor_model: None,
             suppress_selection_drawing: false,
+            background_color: egui::Color32::WHITE,
+            checkerboard: true,
+            grid_size: 0.0,
+            pixel_grid: true,
+            snap_to_pixels: false,
+            guides: Guides::default(),
+            rulers: false,
+            ruler_guides: true,
+            lock_ruler_guides: false,
+            clip_to_artboard: true,
+            visible_elements: None,
+            vector_strokes: true,
+            brush_outline: true,
+            image_resampling: Resampling::default(),
+            canvas_scale: 1.0,
+            brush_size_hud: None,
+            laser_trail: None,
+            fading_laser_trails: Vec::new(),
+            frame_shapes: Vec::new(),
+            profiler: FrameProfiler::default(),
         }
     }
 
+    /// Restrict drawing to the given elements, or draw everything with None
+    pub fn set_visible_elements(
+        &mut self,
+        visible_elements: Option<std::collections::HashSet<ElementId>>,
+    ) {
+        self.visible_elements = visible_elements;
+    }
+
+    /// Pick up the canvas background and grid from the user's settings
+    pub fn apply_settings(&mut self, settings: &Settings) {
+        self.background_color = settings.background_color;
+        self.checkerboard = settings.checkerboard;
+        self.grid_size = settings.grid_size;
+        self.pixel_grid = settings.pixel_grid;
+        self.snap_to_pixels = settings.snap_to_pixels;
+        self.guides = settings.guides.clone();
+        self.rulers = settings.rulers;
+        self.ruler_guides = settings.ruler_guides;
+        self.lock_ruler_guides = settings.lock_ruler_guides;
+        self.handle_style.angle_snap = settings.snap.angle.to_radians();
+        self.clip_to_artboard = settings.clip_to_artboard;
+        self.vector_strokes = settings.vector_strokes;
+        self.brush_outline = settings.brush_outline;
+        if self.image_resampling != settings.image_resampling {
+            self.image_resampling = settings.image_resampling;
+            self.texture_manager.clear_cache();
+        }
+    }
+
+    /// Change how the handles around selected elements look and which ones are offered
+    pub fn set_handle_style(&mut self, handle_style: TransformHandleStyle) {
+        self.handle_style = handle_style;
+    }
+
+    pub fn handle_style(&self) -> &TransformHandleStyle {
+        &self.handle_style
+    }
+
+    /// The handles around `rect`, styled as this renderer draws them
+    pub fn transform_handles(&self, rect: egui::Rect) -> TransformHandles {
+        TransformHandles::new(rect).with_style(self.handle_style.clone())
+    }
+
     /// Set a reference to the editor model for element lookups
     pub fn set_editor_model_ref(&mut self, editor_model: &EditorModel) {
         // Store a raw pointer to the editor model for element lookups
@@ -92,7 +281,7 @@ impl Renderer {
     /// Find an element by ID in the editor model
     ///
     /// Returns None if no editor model is set or the element doesn't exist
-    pub fn find_element(&self, element_id: usize) -> Option<&ElementType> {
+    pub fn find_element(&self, element_id: ElementId) -> Option<&ElementType> {
         // Safety: We only dereference the pointer if it's valid
         // And we only read from it, never modify
         self.editor_model
@@ -110,6 +299,7 @@ impl Renderer {
 
         // Start a new frame in the texture manager
         self.texture_manager.begin_frame();
+        self.profiler.begin_frame();
 
         // Clear element tracking for this frame
         self.elements_rendered_this_frame.clear();
@@ -122,7 +312,13 @@ impl Renderer {
     }
 
     pub fn end_frame(&mut self, _ctx: &egui::Context) {
-        // Nothing to do here - texture cleanup handled by TextureManager
+        // Texture cleanup is handled by the TextureManager
+        self.profiler.end_frame();
+    }
+
+    /// Phase timings, which the input and command code report into too
+    pub fn profiler_mut(&mut self) -> &mut FrameProfiler {
+        &mut self.profiler
     }
 
     /// Set a stroke preview for the renderer to display.
@@ -132,7 +328,12 @@ impl Renderer {
     /// @param thickness The thickness of the stroke
     /// @param color The color of the stroke
     pub fn set_stroke_preview(&mut self, points: Vec<egui::Pos2>, thickness: f32, color: egui::Color32) {
-        self.preview_stroke = Some(StrokePreview::new(points, thickness, color));
+        self.set_styled_stroke_preview(StrokePreview::new(points, thickness, color));
+    }
+
+    /// Set a stroke preview drawn with its own taper, caps and joins
+    pub fn set_styled_stroke_preview(&mut self, preview: StrokePreview) {
+        self.preview_stroke = Some(preview);
         
         // Request a repaint to ensure the preview is rendered immediately
         if let Some(ctx) = &self.ctx {
@@ -151,6 +352,7 @@ impl Renderer {
     /// @param rect Optional rectangle representing the resize preview, or None to clear
     pub fn set_resize_preview(&mut self, rect: Option<egui::Rect>) {
         self.resize_preview = rect;
+        self.selection_resize_origin = None;
         
         // Update selection drawing suppression based on preview state
         self.suppress_selection_drawing = rect.is_some();
@@ -161,6 +363,77 @@ impl Renderer {
         }
     }
     
+    /// Preview a multiple selection scaled from its box `from` onto `to`, or stop with None
+    pub fn set_selection_resize_preview(&mut self, from_to: Option<(egui::Rect, egui::Rect)>) {
+        self.set_resize_preview(from_to.map(|(_, to)| to));
+        self.selection_resize_origin = from_to.map(|(from, _)| from);
+    }
+
+    /// Highlight a handle of a multiple selection's box, or none
+    pub fn set_selection_handle(&mut self, handle: Option<TransformHandle>) {
+        if self.selection_handle != handle {
+            self.selection_handle = handle;
+            if let Some(ctx) = &self.ctx {
+                ctx.request_repaint();
+            }
+        }
+    }
+
+    /// Show the anchors and control handles of a path, or hide them with None
+    pub fn set_path_node_preview(&mut self, nodes: Option<Vec<PathNode>>) {
+        self.path_node_preview = nodes;
+    }
+
+    /// Show the pixels of an image being painted on, stretched over `frame`
+    pub fn set_pixel_preview(&mut self, frame: Obb, pixels: &image::RgbaImage) {
+        let Some(ctx) = &self.ctx else {
+            return;
+        };
+        let size = [pixels.width() as usize, pixels.height() as usize];
+        let image = egui::ColorImage::from_rgba_unmultiplied(size, pixels.as_raw());
+        let texture = ctx.load_texture("pixel_preview", image, egui::TextureOptions::LINEAR);
+        self.pixel_preview = Some((texture, frame));
+        ctx.request_repaint();
+    }
+
+    /// Upload just the pixels that changed since the preview was shown
+    pub fn update_pixel_preview(&mut self, region: &PixelRegion) {
+        let Some((texture, _)) = &mut self.pixel_preview else {
+            return;
+        };
+        if region.width == 0 || region.height == 0 {
+            return;
+        }
+        let size = [region.width as usize, region.height as usize];
+        let image = egui::ColorImage::from_rgba_unmultiplied(size, &region.rgba);
+        texture.set_partial(
+            [region.x as usize, region.y as usize],
+            image,
+            egui::TextureOptions::LINEAR,
+        );
+        if let Some(ctx) = &self.ctx {
+            ctx.request_repaint();
+        }
+    }
+
+    pub fn clear_pixel_preview(&mut self) {
+        self.pixel_preview = None;
+    }
+
+    /// Show the copies an array would add, or hide them with None
+    pub fn set_array_preview(&mut self, copies: Option<Vec<ElementType>>) {
+        self.array_preview = copies;
+    }
+
+    /// Show the path of a freeform selection, or hide it with None
+    pub fn set_lasso_preview(&mut self, points: Option<Vec<egui::Pos2>>) {
+        self.lasso_preview = points;
+
+        if let Some(ctx) = &self.ctx {
+            ctx.request_repaint();
+        }
+    }
+
     /// Get the current resize preview rectangle, if any.
     pub fn get_resize_preview(&self) -> Option<egui::Rect> {
         self.resize_preview
@@ -185,10 +458,10 @@ impl Renderer {
     /// Set an active resize handle for the renderer to highlight.
     ///
     /// @param element_id The ID of the element being resized
-    /// @param corner The corner that should be highlighted, or None to clear
-    pub fn set_active_handle(&mut self, element_id: usize, corner: Option<Corner>) {
-        if let Some(c) = corner {
-            self.active_handles.insert(element_id, c);
+    /// @param handle The handle that should be highlighted, or None to clear
+    pub fn set_active_handle(&mut self, element_id: ElementId, handle: Option<TransformHandle>) {
+        if let Some(h) = handle {
+            self.active_handles.insert(element_id, h);
         } else {
             self.active_handles.remove(&element_id);
         }
@@ -203,15 +476,15 @@ impl Renderer {
     ///
     /// @param element_id The ID of the element to check
     /// @return True if the element has any active handles
-    pub fn is_handle_active(&self, element_id: usize) -> bool {
+    pub fn is_handle_active(&self, element_id: ElementId) -> bool {
         self.active_handles.contains_key(&element_id)
     }
     
     /// Get the active handle for an element, if any.
     ///
     /// @param element_id The ID of the element to check
-    /// @return The active corner handle, if any
-    pub fn get_active_handle(&self, element_id: usize) -> Option<&Corner> {
+    /// @return The active handle, if any
+    pub fn get_active_handle(&self, element_id: ElementId) -> Option<&TransformHandle> {
         self.active_handles.get(&element_id)
     }
     
@@ -232,7 +505,12 @@ impl Renderer {
     pub fn clear_all_previews(&mut self) {
         self.preview_stroke = None;
         self.resize_preview = None;
+        self.selection_resize_origin = None;
+        self.selection_handle = None;
         self.drag_preview = None;
+        self.path_node_preview = None;
+        self.lasso_preview = None;
+        self.pixel_preview = None;
         self.active_handles.clear();
         
         // Reset the suppress selection drawing flag
@@ -244,13 +522,59 @@ impl Renderer {
         }
     }
 
-    /// Draw any element through the TextureManager
+    /// Draw any element through the TextureManager, or as a mesh for opaque lines
     pub fn draw_element(
         &mut self,
         ctx: &egui::Context,
         painter: &egui::Painter,
-        element: &mut dyn Element,
+        element: &mut ElementType,
         force_draw: bool,  // New parameter to force drawing even if already rendered
+    ) {
+        let mut shapes = std::mem::take(&mut self.frame_shapes);
+        self.element_shapes(ctx, element, None, force_draw, &mut shapes);
+        painter.extend(shapes.drain(..));
+        self.frame_shapes = shapes;
+    }
+
+    /// Add the shapes drawing an element to `shapes`
+    ///
+    /// Previews pass a `transform` mapping the element's bounds to where it is
+    /// being dragged or resized to, rather than moving a clone of the element.
+    /// The element is borrowed mutably so generating its texture marks it
+    /// up to date.
+    fn element_shapes(
+        &mut self,
+        ctx: &egui::Context,
+        element: &mut ElementType,
+        transform: Option<RectTransform>,
+        force_draw: bool,
+        shapes: &mut Vec<egui::Shape>,
+    ) {
+        let start = shapes.len();
+        self.unrotated_element_shapes(ctx, element, transform, force_draw, shapes);
+
+        // Elements are drawn unrotated, then turned about their center
+        let rotation = element.rotation();
+        if rotation != 0.0 && shapes.len() > start {
+            let center = element.rect().center();
+            let center = transform.map_or(center, |transform| transform.transform_pos(center));
+            tessellation::rotate_shapes(
+                ctx,
+                &mut shapes[start..],
+                center,
+                rotation,
+                ctx.pixels_per_point(),
+            );
+        }
+    }
+
+    fn unrotated_element_shapes(
+        &mut self,
+        ctx: &egui::Context,
+        element: &mut ElementType,
+        transform: Option<RectTransform>,
+        force_draw: bool,
+        shapes: &mut Vec<egui::Shape>,
     ) {
         let element_id = element.id();
         let texture_version = element.texture_version();
@@ -259,45 +583,154 @@ impl Renderer {
         if !force_draw && self.elements_rendered_this_frame.contains(&element_id) {
             return;
         }
+        // Only mark as rendered if not force_draw
+        if !force_draw {
+            self.elements_rendered_this_frame.insert(element_id);
+        }
+
+        // Lines scale their thickness with the element, as resizing them does
+        let map_line = |(points, thickness, color): (Vec<egui::Pos2>, f32, egui::Color32)| {
+            let Some(transform) = transform else {
+                return (points, thickness, color);
+            };
+            let scale = transform.scale();
+            let points = points
+                .into_iter()
+                .map(|p| transform.transform_pos(p))
+                .collect();
+            (points, thickness * (scale.x + scale.y) / 2.0, color)
+        };
+
+        // Opaque lines are tessellated; translucent ones keep the texture path so
+        // overlapping triangles don't darken their joins
+        if self.vector_strokes {
+            if let Some((points, thickness, color)) = element.polyline().map(map_line) {
+                if color.a() == 255 {
+                    let style = element.stroke_style();
+                    let mesh = tessellation::styled_stroke_mesh(&points, thickness, color, &style);
+                    shapes.push(mesh.into());
+                    return;
+                }
+            }
+        }
+
+        // Notes are drawn as shapes, so their text stays sharp at any zoom
+        if let ElementType::StickyNote(note) = element {
+            let rect = note.rect();
+            let rect = transform.map_or(rect, |transform| transform.transform_rect(rect));
+            shapes.extend(note.shapes(ctx, rect));
+            return;
+        }
 
         // Get the element's rectangle
         let rect = element.rect();
+        let rect = transform.map_or(rect, |transform| transform.transform_rect(rect));
+        let uv = egui::Rect::from_min_max(egui::pos2(0.0, 0.0), egui::pos2(1.0, 1.0));
+
+        // Images are resampled to their size on screen rather than left to the
+        // GPU to shrink; previews stretch the texture of the size the image has
+        if let ElementType::Image(image) = element {
+            let size = image.rect().size();
+            if let Some(texture_id) = self.resampled_image_texture(ctx, image, size) {
+                shapes.push(egui::Shape::image(
+                    texture_id,
+                    rect,
+                    uv,
+                    egui::Color32::WHITE,
+                ));
+                return;
+            }
+        }
 
         // Get or create a texture for this element
+        let profiler = &mut self.profiler;
         match self.texture_manager.get_or_create_texture(
             element_id,
             texture_version,
-            || element.generate_texture(ctx),
+            || {
+                profiler.begin(Phase::TextureGeneration);
+                let texture = element.generate_texture(ctx);
+                profiler.end();
+                texture
+            },
             ctx,
         ) {
             Ok(texture_id) => {
                 // Draw the element as a textured rectangle
-                painter.image(
+                shapes.push(egui::Shape::image(
                     texture_id,
                     rect,
-                    egui::Rect::from_min_max(egui::pos2(0.0, 0.0), egui::pos2(1.0, 1.0)),
+                    uv,
                     egui::Color32::WHITE,
-                );
+                ));
             }
             Err(_) => {
                 // Fallback drawing if texture generation failed
                 // Draw a placeholder rectangle
-                painter.rect_filled(rect, 0.0, egui::Color32::from_gray(200));
-                painter.rect_stroke(rect, 0.0, egui::Stroke::new(1.0, egui::Color32::RED));
-
-                // Use direct drawing method if available
-                element.draw(painter);
+                shapes.push(egui::Shape::rect_filled(
+                    rect,
+                    0.0,
+                    egui::Color32::from_gray(200),
+                ));
+                shapes.push(egui::Shape::rect_stroke(
+                    rect,
+                    0.0,
+                    egui::Stroke::new(1.0, egui::Color32::RED),
+                ));
+
+                // Draw lines directly
+                if let Some((points, thickness, color)) = element.polyline().map(map_line) {
+                    shapes.push(egui::Shape::line(
+                        points,
+                        egui::Stroke::new(thickness, color),
+                    ));
+                }
             }
         }
+    }
 
-        // Only mark as rendered if not force_draw
-        if !force_draw {
-            self.elements_rendered_this_frame.insert(element_id);
-        }
+    /// Texture of `image` resampled for its size on screen when `size` units
+    /// across, or None if the image data can't be read
+    ///
+    /// Enlarging past the image's own pixels is left to the GPU, so zooming in
+    /// never makes textures bigger than the image.
+    fn resampled_image_texture(
+        &mut self,
+        ctx: &egui::Context,
+        image: &Image,
+        size: egui::Vec2,
+    ) -> Option<egui::TextureId> {
+        let [width, height] = image.pixel_size()?;
+        let pixels = egui::vec2(width as f32, height as f32);
+        let on_screen = size * self.canvas_scale * ctx.pixels_per_point();
+        let (bucket, [target_width, target_height]) = size_bucket(on_screen.min(pixels));
+        let target = [target_width.min(width), target_height.min(height)];
+
+        let resampling = self.image_resampling;
+        let profiler = &mut self.profiler;
+        self.texture_manager
+            .get_or_create_resampled_texture(
+                image.id(),
+                image.texture_version(),
+                bucket,
+                resampling.texture_options(),
+                || {
+                    profiler.begin(Phase::TextureGeneration);
+                    let resampled = image.resampled(target, resampling);
+                    profiler.end();
+                    let resampled = resampled.ok_or(TextureGenerationError::GenerationFailed)?;
+                    Ok(egui::ColorImage::from_rgba_premultiplied(
+                        [target[0] as usize, target[1] as usize],
+                        resampled.as_raw(),
+                    ))
+                },
+                ctx,
+            )
+            .ok()
     }
 
     /// Invalidate texture for an element
-    pub fn invalidate_element_texture(&mut self, element_id: usize) {
+    pub fn invalidate_element_texture(&mut self, element_id: ElementId) {
         self.texture_manager.invalidate_element(element_id);
     }
 
@@ -308,98 +741,332 @@ impl Renderer {
             return;
         }
 
-        for points in points.windows(2) {
-            painter.line_segment(
-                [points[0], points[1]],
-                egui::Stroke::new(preview.thickness(), preview.color()),
-            );
+        // Opaque and styled previews are tessellated like finished strokes.
+        // Plain translucent ones stay one path rather than separate segments, so
+        // their color doesn't build up where segments overlap at the joints
+        if preview.color().a() == 255 || *preview.style() != StrokeStyle::default() {
+            painter.add(tessellation::styled_stroke_mesh(
+                points,
+                preview.thickness(),
+                preview.color(),
+                preview.style(),
+            ));
+            return;
         }
+        painter.add(egui::Shape::line(
+            points.to_vec(),
+            egui::Stroke::new(preview.thickness(), preview.color()),
+        ));
     }
 
-    fn draw_selection_box(&self, ui: &mut egui::Ui, element: &ElementType) -> Vec<egui::Response> {
-        // Get the element's bounding rectangle using compute_element_rect
-        let rect = crate::element::compute_element_rect(element);
+    /// Draw path anchors as squares, with lines out to their control handles
+    fn draw_path_node_preview(&self, painter: &egui::Painter, nodes: &[PathNode]) {
+        let line = egui::Stroke::new(1.0, PATH_NODE_COLOR);
+        for node in nodes {
+            for handle in [node.handle_in, node.handle_out] {
+                if handle != node.anchor {
+                    painter.line_segment([node.anchor, handle], line);
+                    painter.circle_filled(handle, PATH_HANDLE_RADIUS, PATH_NODE_COLOR);
+                }
+            }
+            let anchor =
+                egui::Rect::from_center_size(node.anchor, egui::Vec2::splat(PATH_ANCHOR_SIZE));
+            painter.rect_filled(anchor, 0.0, egui::Color32::WHITE);
+            painter.rect_stroke(anchor, 0.0, line);
+        }
+    }
 
-        // Draw the selection box with a more visible stroke
-        ui.painter().rect_stroke(
-            rect,
-            0.0,                                                           // no rounding
-            egui::Stroke::new(2.0, egui::Color32::from_rgb(30, 120, 255)), // Thicker, brighter blue
+    /// Draw the copies an array would add, each outlined
+    fn draw_array_preview(&mut self, ctx: &egui::Context, painter: &egui::Painter) {
+        let Some(mut copies) = self.array_preview.take() else {
+            return;
+        };
+        let mut shapes = std::mem::take(&mut self.frame_shapes);
+        for copy in &mut copies {
+            self.element_shapes(ctx, copy, None, true, &mut shapes);
+            let mut corners = copy.obb().corners().to_vec();
+            corners.extend(corners.first().copied());
+            shapes.push(egui::Shape::line(
+                corners,
+                egui::Stroke::new(1.0, SELECTION_COLOR),
+            ));
+        }
+        painter.extend(shapes.drain(..));
+        self.frame_shapes = shapes;
+        self.array_preview = Some(copies);
+    }
+
+    /// Draw a lasso as a dashed outline, closed back to where it started
+    fn draw_lasso_preview(&self, painter: &egui::Painter, points: &[egui::Pos2]) {
+        let mut closed = points.to_vec();
+        closed.extend(points.first());
+        painter.extend(egui::Shape::dashed_line(
+            &closed,
+            egui::Stroke::new(1.0, LASSO_COLOR),
+            LASSO_DASH,
+            LASSO_DASH,
+        ));
+    }
+
+    /// Whether tools with a brush show its outline instead of the pointer
+    pub fn shows_brush_outline(&self) -> bool {
+        self.brush_outline
+    }
+
+    pub fn shows_rulers(&self) -> bool {
+        self.rulers
+    }
+
+    pub fn shows_ruler_guides(&self) -> bool {
+        self.ruler_guides
+    }
+
+    /// Whether ruler guides stay put rather than being picked up by the pointer
+    pub fn ruler_guides_locked(&self) -> bool {
+        self.lock_ruler_guides
+    }
+
+    /// How close, in document units at the current zoom, things have to come
+    /// to a ruler guide to snap to it; None while the guides are hidden
+    pub fn guide_snap_distance(&self) -> Option<f32> {
+        self.ruler_guides
+            .then(|| GUIDE_SNAP_DISTANCE / self.canvas_scale)
+    }
+
+    /// Whether pointer positions snap to pixels, which they only do at the
+    /// zoom the pixel grid shows from
+    pub fn snaps_to_pixels(&self) -> bool {
+        self.snap_to_pixels && self.canvas_scale >= view::PIXEL_GRID_ZOOM
+    }
+
+    /// Outline the brush at the pointer, standing in for the system cursor
+    ///
+    /// A light ring around a dark one keeps it visible on any background.
+    pub fn draw_brush_outline(&self, painter: &egui::Painter, pos: egui::Pos2, diameter: f32) {
+        let radius = (diameter / 2.0).max(MIN_BRUSH_OUTLINE_RADIUS);
+        painter.circle_stroke(
+            pos,
+            radius + 1.0,
+            egui::Stroke::new(1.0, egui::Color32::WHITE),
         );
+        painter.circle_stroke(pos, radius, egui::Stroke::new(1.0, egui::Color32::BLACK));
+    }
 
-        // Draw the resize handles at each corner
-        let handle_size = crate::element::RESIZE_HANDLE_RADIUS / 2.0;
+    /// Show the laser pointer trail being drawn through `points`
+    pub fn set_laser_trail(&mut self, points: Vec<egui::Pos2>) {
+        self.laser_trail = Some(points);
+    }
 
-        let corners = [
-            (rect.left_top(), Corner::TopLeft),
-            (rect.right_top(), Corner::TopRight),
-            (rect.left_bottom(), Corner::BottomLeft),
-            (rect.right_bottom(), Corner::BottomRight),
-        ];
+    /// Let go of the laser pointer trail being drawn, leaving it to fade out
+    pub fn release_laser_trail(&mut self) {
+        if let Some(points) = self.laser_trail.take() {
+            self.fading_laser_trails
+                .push((points, web_time::Instant::now()));
+        }
+    }
 
-        for (pos, corner) in corners {
-            // Create a temporary handle for drawing
-            let _handle = ResizeHandle::new(0, corner, pos, handle_size);
-            ui.painter()
-                .circle_filled(pos, handle_size, egui::Color32::from_rgb(200, 200, 200));
+    /// The laser pointer trail being drawn and those still fading out
+    fn draw_laser_trails(&mut self, ui: &egui::Ui) {
+        let lifetime = LASER_HOLD_SECONDS + LASER_FADE_SECONDS;
+        self.fading_laser_trails
+            .retain(|(_, released)| released.elapsed().as_secs_f32() < lifetime);
+        if self.laser_trail.is_none() && self.fading_laser_trails.is_empty() {
+            return;
+        }
+        ui.ctx().request_repaint();
+
+        // Widths are in screen points, so undo the canvas zoom
+        let scale = ui
+            .ctx()
+            .layer_transform_to_global(ui.layer_id())
+            .map_or(1.0, |transform| transform.scaling);
+        let fading = self.fading_laser_trails.iter().map(|(points, released)| {
+            let age = released.elapsed().as_secs_f32();
+            let opacity = 1.0 - ((age - LASER_HOLD_SECONDS) / LASER_FADE_SECONDS).clamp(0.0, 1.0);
+            (points, opacity)
+        });
+        let painter = ui.painter();
+        for (points, opacity) in self
+            .laser_trail
+            .iter()
+            .map(|points| (points, 1.0))
+            .chain(fading)
+        {
+            for (width, alpha) in [(LASER_GLOW_WIDTH, 0.3), (LASER_CORE_WIDTH, 1.0)] {
+                let color = LASER_COLOR.gamma_multiply(alpha * opacity);
+                let width = width / scale;
+                match points.as_slice() {
+                    [point] => painter.circle_filled(*point, width / 2.0, color),
+                    _ => painter.line(points.clone(), egui::Stroke::new(width, color)),
+                };
+            }
+        }
+    }
 
-            ui.painter().circle_stroke(
-                pos,
-                handle_size,
-                egui::Stroke::new(1.0, egui::Color32::BLACK),
-            );
+    /// Show a brush of `diameter` centered at `center` for a moment, after its size changes
+    pub fn show_brush_size_hud(&mut self, center: egui::Pos2, diameter: f32) {
+        self.brush_size_hud = Some((center, diameter, web_time::Instant::now()));
+    }
+
+    /// The brush size set last, as a circle with its size in the middle, while
+    /// it is still showing
+    fn draw_brush_size_hud(&mut self, ui: &egui::Ui) {
+        let Some((center, diameter, shown_at)) = self.brush_size_hud else {
+            return;
+        };
+        let age = shown_at.elapsed().as_secs_f32();
+        if age >= BRUSH_SIZE_HUD_SECONDS {
+            self.brush_size_hud = None;
+            return;
+        }
+        ui.ctx().request_repaint();
+
+        let opacity = ((BRUSH_SIZE_HUD_SECONDS - age) * 2.0 / BRUSH_SIZE_HUD_SECONDS).min(1.0);
+        let fade = |color: egui::Color32| color.gamma_multiply(opacity);
+        let radius = (diameter / 2.0).max(MIN_BRUSH_OUTLINE_RADIUS);
+        let painter = ui.painter();
+        painter.circle(
+            center,
+            radius,
+            fade(egui::Color32::from_black_alpha(40)),
+            egui::Stroke::new(1.0, fade(egui::Color32::WHITE)),
+        );
+        painter.circle_stroke(
+            center,
+            radius + 1.0,
+            egui::Stroke::new(1.0, fade(egui::Color32::BLACK)),
+        );
+        let galley = painter.layout_no_wrap(
+            format!("{:.0} px", diameter),
+            egui::FontId::proportional(12.0),
+            fade(egui::Color32::WHITE),
+        );
+        let text_rect = egui::Align2::CENTER_TOP.anchor_size(
+            center + egui::vec2(0.0, radius + ARTBOARD_LABEL_OFFSET),
+            galley.size(),
+        );
+        painter.rect_filled(
+            text_rect.expand(2.0),
+            2.0,
+            fade(egui::Color32::from_black_alpha(160)),
+        );
+        painter.galley(text_rect.min, galley, egui::Color32::WHITE);
+    }
+
+    /// Outline a selected element, with resize handles unless it's part of a
+    /// multiple selection, whose handles go on the box around all of it
+    fn draw_selection_box(
+        &self,
+        ui: &mut egui::Ui,
+        element: &ElementType,
+        with_handles: bool,
+    ) -> Vec<egui::Response> {
+        // Outline the element as it's turned, which compute_element_rect bounds
+        let obb = crate::element::compute_element_obb(element);
+        let outline = |width: f32| {
+            egui::Shape::closed_line(
+                obb.corners().to_vec(),
+                egui::Stroke::new(width, SELECTION_COLOR),
+            )
+        };
+
+        if !with_handles {
+            ui.painter().add(outline(1.0));
+            return Vec::new();
         }
 
+        // Draw the selection box with a more visible stroke
+        ui.painter().add(outline(2.0)); // Thicker, brighter blue
+
+        // Draw the resize handles, highlighting the one being hovered or dragged
+        self.transform_handles(obb.rect)
+            .with_rotation(obb.rotation)
+            .paint(ui.painter(), self.get_active_handle(element.id()).copied());
+
         Vec::new()
     }
 
+    /// Draw the box around a multiple selection with its resize handles
+    fn draw_selection_bounds(&self, painter: &egui::Painter, rect: egui::Rect) {
+        painter.rect_stroke(rect, 0.0, egui::Stroke::new(2.0, SELECTION_COLOR));
+        self.transform_handles(rect)
+            .paint(painter, self.selection_handle);
+    }
+
+    /// Draw a multiple selection scaled from its box `from` onto `to`
+    fn draw_selection_resize_preview(
+        &mut self,
+        ctx: &egui::Context,
+        painter: &egui::Painter,
+        editor_model: &mut EditorModel,
+        from: egui::Rect,
+        to: egui::Rect,
+    ) {
+        let transform = RectTransform::from_to(from, to);
+        let mut shapes = std::mem::take(&mut self.frame_shapes);
+        for element in editor_model.elements.iter_mut() {
+            if editor_model.selected_element_ids.contains(&element.id()) {
+                self.element_shapes(ctx, element, Some(transform), true, &mut shapes);
+            }
+        }
+        painter.extend(shapes.drain(..));
+        self.frame_shapes = shapes;
+
+        self.draw_selection_bounds(painter, to);
+    }
+
     /// Render all active previews (stroke, resize, drag, handles)
     /// This is called by the main render method to display all preview visuals
-    fn render_previews(&mut self, ui: &mut egui::Ui, _panel_rect: egui::Rect) {
+    fn render_previews(&mut self, ui: &mut egui::Ui, editor_model: &mut EditorModel) {
+        if let Some((texture, frame)) = &self.pixel_preview {
+            let uv = egui::Rect::from_min_max(egui::pos2(0.0, 0.0), egui::pos2(1.0, 1.0));
+            let mut mesh = egui::Mesh::with_texture(texture.id());
+            mesh.add_rect_with_uv(frame.rect, uv, egui::Color32::WHITE);
+            for vertex in &mut mesh.vertices {
+                vertex.pos = frame.to_world(vertex.pos);
+            }
+            ui.painter().add(mesh);
+        }
         // Render stroke preview if active
         if let Some(preview) = &self.preview_stroke {
             self.draw_stroke_preview(ui.painter(), preview);
         }
+        if let Some(nodes) = &self.path_node_preview {
+            self.draw_path_node_preview(ui.painter(), nodes);
+        }
+        if let Some(points) = &self.lasso_preview {
+            self.draw_lasso_preview(ui.painter(), points);
+        }
+        self.draw_array_preview(ui.ctx(), ui.painter());
         
         // Only draw one type of preview at a time, prioritizing resize over drag
-        if let Some(rect) = self.resize_preview {
+        if let (Some(from), Some(to)) = (self.selection_resize_origin, self.resize_preview) {
+            self.draw_selection_resize_preview(ui.ctx(), ui.painter(), editor_model, from, to);
+        } else if let Some(rect) = self.resize_preview {
             // Find the element being resized
-            let mut active_element_id = None;
-            for (element_id, _) in &self.active_handles {
-                active_element_id = Some(*element_id);
-                break;
-            }
+            let active_element_id = self.active_handles.keys().next().copied();
             
             // Draw the resize preview for this element
             if let Some(element_id) = active_element_id {
-                if let Some(editor_model) = self.editor_model {
-                    // Safety: We only dereference the pointer if it's valid
-                    let editor_model = unsafe { &*editor_model };
-                    self.draw_resize_preview(
-                        ui.ctx(),
-                        ui.painter(),
-                        editor_model,
-                        element_id,
-                        rect,
-                    );
-                }
+                self.draw_resize_preview(ui.ctx(), ui.painter(), editor_model, element_id, rect);
             }
         } else if let Some(rect) = self.drag_preview {
             // For drag preview, first draw the element texture at the preview position
-            if let Some(editor_model) = self.editor_model {
-                let editor_model = unsafe { &*editor_model };
-                // Get the first selected element
-                if let Some(element_id) = editor_model.selected_ids().iter().next() {
-                    if let Some(mut element) = editor_model.get_element_by_id(*element_id).cloned() {
-                        // Temporarily move the element to the preview position
-                        // Use compute_element_rect to match exactly what the selection tool uses
-                        let original_rect = crate::element::compute_element_rect(&element);
-                        let offset = rect.min - original_rect.min;
-                        element.translate(offset).ok();
-                        
-                        // Draw the element at the preview position
-                        self.draw_element(ui.ctx(), ui.painter(), &mut element, true);
-                    }
+            // Get the first selected element
+            if let Some(element_id) = editor_model.selected_ids().iter().next().copied() {
+                if let Some(element) = editor_model.get_element_mut_by_id(element_id) {
+                    // Move the element to the preview position as it is drawn
+                    // Use compute_element_rect to match exactly what the selection tool uses
+                    let original_rect = crate::element::compute_element_rect(element);
+                    let offset = rect.min - original_rect.min;
+                    let transform =
+                        RectTransform::from_to(original_rect, original_rect.translate(offset));
+
+                    // Draw the element at the preview position
+                    let mut shapes = std::mem::take(&mut self.frame_shapes);
+                    self.element_shapes(ui.ctx(), element, Some(transform), true, &mut shapes);
+                    ui.painter().extend(shapes.drain(..));
+                    self.frame_shapes = shapes;
                 }
             }
             
@@ -417,57 +1084,8 @@ impl Renderer {
                 egui::Stroke::new(2.0, egui::Color32::from_rgb(30, 120, 255)),
             );
             
-            // Draw handles at the corners for consistency with resize
-            let handle_size = crate::element::RESIZE_HANDLE_RADIUS / 2.0;
-            let corners = [
-                (rect.left_top(), Corner::TopLeft),
-                (rect.right_top(), Corner::TopRight),
-                (rect.left_bottom(), Corner::BottomLeft),
-                (rect.right_bottom(), Corner::BottomRight),
-            ];
-            
-            for (pos, _corner) in corners {
-                ui.painter().circle_filled(
-                    pos,
-                    handle_size,
-                    egui::Color32::from_rgb(200, 200, 200),
-                );
-                ui.painter().circle_stroke(
-                    pos,
-                    handle_size,
-                    egui::Stroke::new(1.0, egui::Color32::BLACK),
-                );
-            }
-        }
-        
-        // Only draw active handles if we're not showing any other preview
-        if !self.suppress_selection_drawing && !self.active_handles.is_empty() {
-            for (element_id, corner) in &self.active_handles {
-                if let Some(element) = self.find_element(*element_id) {
-                    let element_rect = crate::element::compute_element_rect(element);
-                    let handle_size = crate::element::RESIZE_HANDLE_RADIUS;
-                    
-                    // Get the position of the corner
-                    let pos = match corner {
-                        Corner::TopLeft => element_rect.left_top(),
-                        Corner::TopRight => element_rect.right_top(),
-                        Corner::BottomLeft => element_rect.left_bottom(),
-                        Corner::BottomRight => element_rect.right_bottom(),
-                    };
-                    
-                    // Draw active handle with a highlight color
-                    ui.painter().circle_filled(
-                        pos,
-                        handle_size,
-                        egui::Color32::from_rgb(100, 200, 255), // Bright blue for active handle
-                    );
-                    ui.painter().circle_stroke(
-                        pos,
-                        handle_size,
-                        egui::Stroke::new(2.0, egui::Color32::WHITE),
-                    );
-                }
-            }
+            // Draw handles for consistency with resize
+            self.transform_handles(rect).paint(ui.painter(), None);
         }
     }
 
@@ -477,104 +1095,314 @@ impl Renderer {
         ui: &mut egui::Ui,
         editor_model: &mut EditorModel,
         rect: egui::Rect,
-    ) -> Option<(usize, Corner, egui::Pos2)> {
+    ) -> Option<(ElementId, TransformHandle, egui::Pos2)> {
         // Update our reference to the editor model
         self.set_editor_model_ref(editor_model);
+        self.profiler.begin(Phase::Painting);
         
         // Get the selected elements from the editor_model
-        let selected_ids: Vec<usize> = editor_model.selected_ids().iter().copied().collect();
+        let selected_ids: Vec<ElementId> = editor_model.selected_ids().iter().copied().collect();
 
         // Process interactions first before drawing
         let resize_info = self.process_resize_interactions_for_ids(ui, editor_model, &selected_ids);
 
-        // Draw background
-        ui.painter().rect_filled(rect, 0.0, egui::Color32::WHITE);
+        // Draw background, only inside the artboards if the document has any
+        let canvas_rects: Vec<egui::Rect> = if editor_model.artboards.is_empty() {
+            vec![rect]
+        } else {
+            ui.painter().rect_filled(rect, 0.0, PASTEBOARD_COLOR);
+            editor_model
+                .artboards
+                .iter()
+                .map(|artboard| artboard.rect.intersect(rect))
+                .collect()
+        };
+        let background = editor_model.background.unwrap_or(self.background_color);
+        for &canvas_rect in &canvas_rects {
+            if self.checkerboard && background.a() < 255 {
+                Self::draw_checkerboard(ui.painter(), canvas_rect);
+            }
+            ui.painter().rect_filled(canvas_rect, 0.0, background);
+            if let Some(reference) = &mut editor_model.reference_image {
+                Self::draw_reference_image(ui.painter(), reference, canvas_rect);
+            }
+            self.draw_grid(ui.painter(), canvas_rect, background);
+        }
 
         // Get the context for rendering
         let ctx = self.get_ctx().clone();
+        self.canvas_scale = ctx
+            .layer_transform_to_global(ui.layer_id())
+            .map_or(1.0, |transform| transform.scaling);
 
         // Check if we have any active previews
         let has_preview = self.resize_preview.is_some() || self.drag_preview.is_some();
 
-        // Draw non-selected elements first
-        for element_id in editor_model.all_element_ids() {
-            if !selected_ids.contains(&element_id) {
-                if let Some(element) = editor_model.get_element_mut_by_id(element_id) {
-                    self.draw_element(&ctx, ui.painter(), element, false);
+        // Queue the elements' shapes into one reused list, handing them to egui
+        // whenever the clip rect changes rather than one call per element
+        let mut shapes = std::mem::take(&mut self.frame_shapes);
+        let mut clip_rect = ui.clip_rect();
+
+        // Draw non-selected elements first, then the selected ones on top unless
+        // a preview is standing in for them. Selected elements are always drawn.
+        for selected in [false, true] {
+            if selected && has_preview {
+                break;
+            }
+            for index in 0..editor_model.elements.len() {
+                let element_id = editor_model.elements[index].id();
+                if editor_model.selected_element_ids.contains(&element_id) != selected
+                    || !self.is_visible(element_id)
+                {
+                    continue;
                 }
+                let Some(element_clip) = self.element_clip_rect(ui, editor_model, element_id, rect)
+                else {
+                    continue;
+                };
+                if element_clip != clip_rect {
+                    ui.painter()
+                        .with_clip_rect(clip_rect)
+                        .extend(shapes.drain(..));
+                    clip_rect = element_clip;
+                }
+                let element = &mut editor_model.elements[index];
+                self.element_shapes(&ctx, element, None, selected, &mut shapes);
+            }
+        }
+        ui.painter()
+            .with_clip_rect(clip_rect)
+            .extend(shapes.drain(..));
+        self.frame_shapes = shapes;
+
+        // Outline the pixels over the elements when zoomed in on them
+        if self.pixel_grid && self.canvas_scale >= view::PIXEL_GRID_ZOOM {
+            for &canvas_rect in &canvas_rects {
+                self.draw_pixel_grid(ui.painter(), canvas_rect, background);
             }
         }
+        self.guides
+            .paint(ui.painter(), ui.clip_rect(), self.canvas_scale);
 
-        // Only draw selected elements and selection boxes if there's no preview active
+        // Draw selection boxes for selected elements; a multiple selection is
+        // resized by the handles of the box around all of it
         if !has_preview {
-            // Draw selected elements
+            let multiple = selected_ids.len() > 1;
             for element_id in &selected_ids {
-                if let Some(element) = editor_model.get_element_mut_by_id(*element_id) {
-                    self.draw_element(&ctx, ui.painter(), element, true);
+                if !self.is_visible(*element_id) {
+                    continue;
                 }
-            }
-
-            // Draw selection boxes for selected elements
-            for element_id in &selected_ids {
                 if let Some(element) = editor_model.find_element_by_id(*element_id) {
-                    self.draw_selection_box(ui, element);
+                    self.draw_selection_box(ui, element, !multiple);
                 }
             }
+            if let Some(bounds) = editor_model.selection_rect().filter(|_| multiple) {
+                self.draw_selection_bounds(ui.painter(), bounds);
+            }
+        }
+
+        // Outline the artboards over any elements that spill past them
+        let active_artboard_id = editor_model.active_artboard().map(|artboard| artboard.id);
+        for artboard in &editor_model.artboards {
+            let color = if Some(artboard.id) == active_artboard_id {
+                ACTIVE_ARTBOARD_BORDER_COLOR
+            } else {
+                ARTBOARD_BORDER_COLOR
+            };
+            ui.painter()
+                .rect_stroke(artboard.rect, 0.0, egui::Stroke::new(1.0, color));
+            ui.painter().text(
+                artboard.rect.left_top() - egui::vec2(0.0, ARTBOARD_LABEL_OFFSET),
+                egui::Align2::LEFT_BOTTOM,
+                &artboard.name,
+                egui::FontId::proportional(12.0),
+                color,
+            );
+        }
+
+        for slice in &editor_model.slices {
+            let corners = [
+                slice.rect.left_top(),
+                slice.rect.right_top(),
+                slice.rect.right_bottom(),
+                slice.rect.left_bottom(),
+                slice.rect.left_top(),
+            ];
+            ui.painter().extend(egui::Shape::dashed_line(
+                &corners,
+                egui::Stroke::new(1.0, SLICE_BORDER_COLOR),
+                SLICE_DASH,
+                SLICE_DASH,
+            ));
+            ui.painter().text(
+                slice.rect.left_bottom() + egui::vec2(0.0, ARTBOARD_LABEL_OFFSET),
+                egui::Align2::LEFT_TOP,
+                &slice.name,
+                egui::FontId::proportional(12.0),
+                SLICE_BORDER_COLOR,
+            );
         }
 
         // Render all previews (stroke, resize, drag, handles) on top
-        self.render_previews(ui, rect);
+        self.render_previews(ui, editor_model);
+        self.draw_brush_size_hud(ui);
+        self.draw_laser_trails(ui);
+        self.profiler.end();
 
         // Return resize info
         resize_info
     }
 
+    /// Clip rect for an element: its artboard when clipping is on
+    ///
+    /// Returns None for elements that lie outside every artboard and are hidden.
+    fn element_clip_rect(
+        &self,
+        ui: &egui::Ui,
+        editor_model: &EditorModel,
+        element_id: ElementId,
+        rect: egui::Rect,
+    ) -> Option<egui::Rect> {
+        if !self.clip_to_artboard || editor_model.artboards.is_empty() {
+            return Some(ui.clip_rect());
+        }
+        let artboard = editor_model.element_artboard(element_id)?;
+        Some(artboard.rect.intersect(rect).intersect(ui.clip_rect()))
+    }
+
+    fn is_visible(&self, element_id: ElementId) -> bool {
+        self.visible_elements
+            .as_ref()
+            .is_none_or(|visible| visible.contains(&element_id))
+    }
+
+    /// Draw the reference image, tiled if it's set to, beneath everything on `canvas_rect`
+    fn draw_reference_image(
+        painter: &egui::Painter,
+        reference: &mut ReferenceImage,
+        canvas_rect: egui::Rect,
+    ) {
+        let Some(texture_id) = reference.texture(painter.ctx()).map(|texture| texture.id()) else {
+            return;
+        };
+        let painter = painter.with_clip_rect(canvas_rect.intersect(painter.clip_rect()));
+        let uv = egui::Rect::from_min_max(egui::pos2(0.0, 0.0), egui::pos2(1.0, 1.0));
+        for tile in reference.tiles(canvas_rect) {
+            painter.image(texture_id, tile, uv, reference.tint());
+        }
+    }
+
+    /// Draw the usual light/dark checkerboard that marks transparent areas
+    fn draw_checkerboard(painter: &egui::Painter, rect: egui::Rect) {
+        const CELL: f32 = 8.0;
+        let light = egui::Color32::from_gray(204);
+        let dark = egui::Color32::from_gray(153);
+
+        painter.rect_filled(rect, 0.0, light);
+        // Batch the dark cells into one mesh rather than thousands of shapes
+        let mut mesh = egui::Mesh::default();
+        let columns = (rect.width() / CELL).ceil() as usize;
+        let rows = (rect.height() / CELL).ceil() as usize;
+        for row in 0..rows {
+            for column in (row % 2..columns).step_by(2) {
+                let min = rect.min + egui::vec2(column as f32 * CELL, row as f32 * CELL);
+                let cell = egui::Rect::from_min_size(min, egui::Vec2::splat(CELL)).intersect(rect);
+                mesh.add_colored_rect(cell, dark);
+            }
+        }
+        painter.add(mesh);
+    }
+
+    /// Draw grid lines every `grid_size` points, aligned to element coordinates,
+    /// one screen point wide
+    ///
+    /// Zoomed out far enough that the lines would crowd together, only every
+    /// second line is drawn, or every fourth, and so on.
+    fn draw_grid(&self, painter: &egui::Painter, rect: egui::Rect, background: egui::Color32) {
+        if self.grid_size <= 0.0 || self.canvas_scale <= 0.0 {
+            return;
+        }
+
+        let mut spacing = self.grid_size;
+        while spacing * self.canvas_scale < MIN_GRID_SPACING {
+            spacing *= 2.0;
+        }
+        let stroke = egui::Stroke::new(1.0 / self.canvas_scale, grid_line_color(background));
+        Self::draw_grid_lines(painter, rect, spacing, stroke);
+    }
+
+    /// Outline every document pixel with lines one screen point wide
+    fn draw_pixel_grid(
+        &self,
+        painter: &egui::Painter,
+        rect: egui::Rect,
+        background: egui::Color32,
+    ) {
+        let stroke = egui::Stroke::new(1.0 / self.canvas_scale, grid_line_color(background));
+        Self::draw_grid_lines(painter, rect, 1.0, stroke);
+    }
+
+    /// Lines every `spacing` across `rect`, on multiples of `spacing`
+    fn draw_grid_lines(
+        painter: &egui::Painter,
+        rect: egui::Rect,
+        spacing: f32,
+        stroke: egui::Stroke,
+    ) {
+        let mut x = (rect.min.x / spacing).ceil() * spacing;
+        while x <= rect.max.x {
+            painter.vline(x, rect.y_range(), stroke);
+            x += spacing;
+        }
+        let mut y = (rect.min.y / spacing).ceil() * spacing;
+        while y <= rect.max.y {
+            painter.hline(rect.x_range(), y, stroke);
+            y += spacing;
+        }
+    }
+
     /// Draw a preview of an element being resized
     fn draw_resize_preview(
         &mut self,
         ctx: &egui::Context,
         painter: &egui::Painter,
-        editor_model: &EditorModel,
-        element_id: usize,
+        editor_model: &mut EditorModel,
+        element_id: ElementId,
         preview_rect: egui::Rect,
     ) {
         // Get the element
-        if let Some(element) = editor_model.get_element_by_id(element_id) {
-            // Clone the element so we can modify it
-            if let Some(mut cloned_element) = editor_model.get_element_by_id(element_id).cloned() {
-                // We need to account for padding differences
-                // The original element rect with padding
-                let original_padded_rect = crate::element::compute_element_rect(element);
-                // The original element rect without padding
-                let original_raw_rect = element.rect();
-                
-                // Calculate the padding on each side
-                let padding_left = original_raw_rect.min.x - original_padded_rect.min.x;
-                let padding_top = original_raw_rect.min.y - original_padded_rect.min.y;
-                let padding_right = original_padded_rect.max.x - original_raw_rect.max.x;
-                let padding_bottom = original_padded_rect.max.y - original_raw_rect.max.y;
-                
-                // Create a preview rect that accounts for the padding
-                // (subtract padding from the preview rect to get the raw rect for resize)
-                let resize_rect = egui::Rect::from_min_max(
-                    egui::pos2(
-                        preview_rect.min.x + padding_left,
-                        preview_rect.min.y + padding_top
-                    ),
-                    egui::pos2(
-                        preview_rect.max.x - padding_right,
-                        preview_rect.max.y - padding_bottom
-                    )
-                );
-                
-                // Resize the cloned element using the adjusted rect
-                if let Err(err) = cloned_element.resize(resize_rect) {
-                    log::error!("Failed to resize element for preview: {}", err);
-                }
-                
-                // Draw the transformed element using the texture system
-                self.draw_element(ctx, painter, &mut cloned_element, true);
-            }
+        if let Some(element) = editor_model.get_element_mut_by_id(element_id) {
+            // We need to account for padding differences
+            // The original element rect with padding
+            let original_padded_rect = crate::element::compute_element_rect(element);
+            // The original element rect without padding
+            let original_raw_rect = element.rect();
+            
+            // Calculate the padding on each side
+            let padding_left = original_raw_rect.min.x - original_padded_rect.min.x;
+            let padding_top = original_raw_rect.min.y - original_padded_rect.min.y;
+            let padding_right = original_padded_rect.max.x - original_raw_rect.max.x;
+            let padding_bottom = original_padded_rect.max.y - original_raw_rect.max.y;
+            
+            // Create a preview rect that accounts for the padding
+            // (subtract padding from the preview rect to get the raw rect for resize)
+            let resize_rect = egui::Rect::from_min_max(
+                egui::pos2(
+                    preview_rect.min.x + padding_left,
+                    preview_rect.min.y + padding_top
+                ),
+                egui::pos2(
+                    preview_rect.max.x - padding_right,
+                    preview_rect.max.y - padding_bottom
+                )
+            );
+            
+            // Draw the element stretched over the adjusted rect
+            let transform = RectTransform::from_to(original_raw_rect, resize_rect);
+            let mut shapes = std::mem::take(&mut self.frame_shapes);
+            self.element_shapes(ctx, element, Some(transform), true, &mut shapes);
+            painter.extend(shapes.drain(..));
+            self.frame_shapes = shapes;
             
             // Draw the preview outline using the full padded rect
             painter.rect_stroke(
@@ -583,27 +1411,9 @@ impl Renderer {
                 egui::Stroke::new(2.0, egui::Color32::from_rgb(30, 120, 255)),
             );
 
-            // Draw resize handles at preview rect corners
-            let handle_size = crate::element::RESIZE_HANDLE_RADIUS / 2.0;
-            let corners = [
-                (preview_rect.left_top(), Corner::TopLeft),
-                (preview_rect.right_top(), Corner::TopRight),
-                (preview_rect.left_bottom(), Corner::BottomLeft),
-                (preview_rect.right_bottom(), Corner::BottomRight),
-            ];
-
-            for (pos, _corner) in corners {
-                painter.circle_filled(
-                    pos,
-                    handle_size,
-                    egui::Color32::from_rgb(200, 200, 200),
-                );
-                painter.circle_stroke(
-                    pos,
-                    handle_size,
-                    egui::Stroke::new(1.0, egui::Color32::BLACK),
-                );
-            }
+            // Draw the handles on the preview rect, keeping the dragged one highlighted
+            self.transform_handles(preview_rect)
+                .paint(painter, self.get_active_handle(element_id).copied());
         }
     }
 
@@ -611,8 +1421,14 @@ impl Renderer {
         &mut self,
         ui: &mut egui::Ui,
         editor_model: &EditorModel,
-        selected_ids: &[usize],
-    ) -> Option<(usize, Corner, egui::Pos2)> {
+        selected_ids: &[ElementId],
+    ) -> Option<(ElementId, TransformHandle, egui::Pos2)> {
+        // A multiple selection has no handles of its own; the selection tool
+        // resizes it by the box around all of it
+        if selected_ids.len() > 1 {
+            return None;
+        }
+
         // Convert IDs to elements
         let selected_elements: Vec<&ElementType> = selected_ids
             .iter()
@@ -626,16 +1442,13 @@ impl Renderer {
         &mut self,
         ui: &mut egui::Ui,
         selected_elements: &[&ElementType],
-    ) -> Option<(usize, Corner, egui::Pos2)> {
+    ) -> Option<(ElementId, TransformHandle, egui::Pos2)> {
         let mut resize_info = None;
 
         if selected_elements.is_empty() {
             return None;
         }
 
-        // Handle size in screen pixels
-        let handle_size = 8.0;
-
         // Process each selected element
         for element in selected_elements {
             let element_id = element.id();
@@ -648,71 +1461,40 @@ impl Renderer {
                 continue;
             }
 
-            // Process each corner
-            for corner in &[
-                Corner::TopLeft,
-                Corner::TopRight,
-                Corner::BottomLeft,
-                Corner::BottomRight,
-            ] {
-                // Calculate the position of this corner
-                let corner_pos = match corner {
-                    Corner::TopLeft => rect.left_top(),
-                    Corner::TopRight => rect.right_top(),
-                    Corner::BottomLeft => rect.left_bottom(),
-                    Corner::BottomRight => rect.right_bottom(),
-                };
-
-                // Create a resize handle for this corner
-                let handle = ResizeHandle::new(element_id, *corner, corner_pos, handle_size);
-
-                // Show the handle and get interaction response
-                let response = handle.show(ui);
-
-                // Check for active drag more explicitly
-                if response.dragged() {
-                    // If this is a new drag (no active handles yet), set this as the active handle
-                    if !self.is_handle_active(element_id) {
-                        self.set_active_handle(element_id, Some(*corner));
-                    }
+            let handles = self.transform_handles(rect).id_salt(element_id);
+            let Some((handle, response)) = handles.interact(ui) else {
+                continue;
+            };
 
-                    // Always update active handle to the current corner being dragged
-                    if self.is_handle_active(element_id) {
-                        self.set_active_handle(element_id, Some(*corner));
+            if response.dragged() {
+                // Keep the dragged handle highlighted while the drag lasts
+                self.set_active_handle(element_id, Some(handle));
 
-                        // Get the current mouse position for the resize
-                        let mouse_pos = response
-                            .hover_pos()
-                            .or_else(|| ui.ctx().pointer_hover_pos())
-                            .unwrap_or(corner_pos);
+                // Get the current mouse position for the resize
+                let mouse_pos = response
+                    .hover_pos()
+                    .or_else(|| response.interact_pointer_pos())
+                    .map_or(rect.center(), |pos| view::layer_to_document(ui.ctx(), pos));
 
-                        // Compute the new rectangle based on this drag position
-                        let new_rect = Self::compute_resized_rect(rect, *corner, mouse_pos);
+                // Compute the new rectangle based on this drag position
+                let new_rect = handle.resize_rect(rect, mouse_pos, MIN_RESIZE_SIZE);
+                self.set_resize_preview(Some(new_rect));
 
-                        // Update the resize preview
-                        self.set_resize_preview(Some(new_rect));
+                // Return the resize information (element ID, handle, new position)
+                resize_info = Some((element_id, handle, mouse_pos));
+            }
 
-                        // Return the resize information (element ID, corner, new position)
-                        resize_info = Some((element_id, *corner, mouse_pos));
+            // Handle drag release - clear active handle for this element
+            if response.drag_stopped() {
+                if self.resize_preview.is_some() {
+                    // Return the resize info so the selection tool can update the element
+                    if let Some(pos) = response.interact_pointer_pos() {
+                        let pos = view::layer_to_document(ui.ctx(), pos);
+                        resize_info = Some((element_id, handle, pos));
                     }
                 }
 
-                // Handle drag release - clear active handle for this element
-                if response.drag_stopped() {
-                    // Get the final resize preview rect
-                    if let Some(_final_rect) = self.resize_preview {
-                        // Return the resize info so the selection tool can update the element
-                        resize_info = Some((
-                            element_id,
-                            *corner,
-                            response
-                                .hover_pos()
-                                .unwrap_or(response.interact_pointer_pos().unwrap()),
-                        ));
-                    }
-
-                    self.set_active_handle(element_id, None);
-                }
+                self.set_active_handle(element_id, None);
             }
         }
 
@@ -729,35 +1511,14 @@ impl Renderer {
 
     pub fn compute_resized_rect(
         original: egui::Rect,
-        corner: Corner,
+        handle: TransformHandle,
         new_pos: egui::Pos2,
     ) -> egui::Rect {
-        let mut rect = original;
-
-        match corner {
-            Corner::TopLeft => {
-                rect.min.x = new_pos.x.min(rect.max.x - 10.0);
-                rect.min.y = new_pos.y.min(rect.max.y - 10.0);
-            }
-            Corner::TopRight => {
-                rect.max.x = new_pos.x.max(rect.min.x + 10.0);
-                rect.min.y = new_pos.y.min(rect.max.y - 10.0);
-            }
-            Corner::BottomLeft => {
-                rect.min.x = new_pos.x.min(rect.max.x - 10.0);
-                rect.max.y = new_pos.y.max(rect.min.y + 10.0);
-            }
-            Corner::BottomRight => {
-                rect.max.x = new_pos.x.max(rect.min.x + 10.0);
-                rect.max.y = new_pos.y.max(rect.min.y + 10.0);
-            }
-        }
-
-        rect
+        handle.resize_rect(original, new_pos, MIN_RESIZE_SIZE)
     }
 
     // Enhanced method to clear the renderer's state for a specific element
-    pub fn clear_element_state(&mut self, element_id: usize) {
+    pub fn clear_element_state(&mut self, element_id: ElementId) {
         // Check if this element has active handles before removing them
         let had_active_handles = self.active_handles.contains_key(&element_id);
 
@@ -794,6 +1555,16 @@ impl Renderer {
         self.frame_counter = 0;
     }
 
+    /// Bytes of texture memory the element texture cache holds
+    pub fn texture_cache_bytes(&self) -> usize {
+        self.texture_manager.byte_size()
+    }
+
+    /// Drop cached textures of elements not drawn this frame
+    pub fn trim_texture_cache(&mut self) -> usize {
+        self.texture_manager.trim()
+    }
+
     // Add a method to handle element updates
     pub fn handle_element_update(&mut self, element: &ElementType) {
         // Use the element ID
@@ -801,7 +1572,7 @@ impl Renderer {
     }
 
     // Method specifically for clearing textures for an element
-    pub fn invalidate_texture(&mut self, element_id: usize) {
+    pub fn invalidate_texture(&mut self, element_id: ElementId) {
         // Invalidate the texture in the texture manager
         self.texture_manager.invalidate_element(element_id);
 
@@ -811,21 +1582,112 @@ impl Renderer {
         }
     }
 
-    // Add a debug visualization for texture state
-    pub fn draw_debug_overlay(&self, ui: &mut egui::Ui) {
+    /// Diagnostics: phase timings, element counts, texture cache stats and a
+    /// graph of recent frame times
+    pub fn draw_debug_overlay(&self, ui: &mut egui::Ui, editor_model: &EditorModel) {
+        let average = self.profiler.average();
+        let last = self.profiler.history().back().copied().unwrap_or_default();
+        let millis =
+            |duration: std::time::Duration| format!("{:.2} ms", duration.as_secs_f32() * 1000.0);
+
+        egui::Grid::new("frame_timings")
+            .num_columns(3)
+            .striped(true)
+            .show(ui, |ui| {
+                ui.strong("Phase");
+                ui.strong("Last");
+                ui.strong("Average");
+                ui.end_row();
+                for (phase, color) in Phase::ALL.into_iter().zip(PHASE_COLORS) {
+                    ui.colored_label(color, phase.label());
+                    ui.label(millis(last.phase(phase)));
+                    ui.label(millis(average.phase(phase)));
+                    ui.end_row();
+                }
+                ui.label("Whole frame");
+                ui.label(millis(last.total));
+                ui.label(millis(average.total));
+                ui.end_row();
+            });
+
+        ui.separator();
+        self.draw_frame_time_graph(ui);
+
+        ui.separator();
         ui.label(format!("Frame counter: {}", self.frame_counter));
         ui.label(format!(
-            "Texture cache size: {}",
-            self.texture_manager.cache_size()
+            "Elements: {} in the document, {} drawn this frame, {} selected",
+            editor_model.elements.len(),
+            self.elements_rendered_this_frame.len(),
+            editor_model.selected_ids().len(),
         ));
+        let stats = self.texture_manager.stats();
+        ui.label(format!(
+            "Texture cache: {} of {} textures",
+            self.texture_manager.cache_size(),
+            self.texture_manager.max_cache_size(),
+        ));
+        ui.label(format!(
+            "{} hits, {} misses ({:.0}% hit rate), {} evictions",
+            stats.hits,
+            stats.misses,
+            stats.hit_rate() * 100.0,
+            stats.evictions,
+        ));
+    }
 
-        // Show the top 5 elements in the texture cache
-        if self.texture_manager.cache_size() > 0 {
-            ui.label("Recent texture updates:");
-
-            // We can't actually see inside the texture manager's cache from here,
-            // but in a real implementation, you could provide methods to inspect the cache
+    /// Bars of recent frame times, split into the phases, against a 60 fps line
+    fn draw_frame_time_graph(&self, ui: &mut egui::Ui) {
+        let size = egui::vec2(FRAME_HISTORY as f32 * 1.5, 80.0);
+        let (rect, _) = ui.allocate_exact_size(size, egui::Sense::hover());
+        let painter = ui.painter_at(rect);
+        painter.rect_filled(rect, 0.0, ui.visuals().extreme_bg_color);
+
+        // Scale to the slowest frame, but never zoom in past two 60 fps frames
+        let target = 1.0 / 60.0;
+        let history = self.profiler.history();
+        let slowest = history
+            .iter()
+            .map(|frame| frame.total.as_secs_f32())
+            .fold(2.0 * target, f32::max);
+        let height = |seconds: f32| seconds / slowest * rect.height();
+
+        // One mesh for all the bars rather than a shape per bar
+        let mut mesh = egui::Mesh::default();
+        let bar_width = rect.width() / FRAME_HISTORY as f32;
+        for (i, frame) in history.iter().enumerate() {
+            let x = rect.left() + i as f32 * bar_width;
+            let mut bottom = rect.bottom();
+            let mut add_bar = |seconds: f32, color: egui::Color32| {
+                let top = bottom - height(seconds);
+                let bar = egui::Rect::from_x_y_ranges(x..=x + bar_width, top..=bottom);
+                mesh.add_colored_rect(bar, color);
+                bottom = top;
+            };
+            for (phase, color) in Phase::ALL.into_iter().zip(PHASE_COLORS) {
+                add_bar(frame.phase(phase).as_secs_f32(), color);
+            }
+            let phases: std::time::Duration = frame.phases.iter().sum();
+            add_bar(
+                frame.total.saturating_sub(phases).as_secs_f32(),
+                egui::Color32::GRAY,
+            );
         }
+        painter.add(mesh);
+
+        let target_y = rect.bottom() - height(target);
+        painter.hline(
+            rect.x_range(),
+            target_y,
+            egui::Stroke::new(1.0, egui::Color32::RED),
+        );
+        painter.text(
+            egui::pos2(rect.left() + 2.0, target_y),
+            egui::Align2::LEFT_BOTTOM,
+            "60 fps",
+            egui::FontId::proportional(10.0),
+            egui::Color32::RED,
+        );
     }
 
     /// Get access to the editor model reference
@@ -833,3 +1695,14 @@ impl Renderer {
         self.editor_model
     }
 }
+
+/// Faint lines contrasting with `background`, whether it's light or dark
+fn grid_line_color(background: egui::Color32) -> egui::Color32 {
+    let [r, g, b, _] = background.to_array();
+    let luminance = 0.299 * r as f32 + 0.587 * g as f32 + 0.114 * b as f32;
+    if luminance > 128.0 {
+        egui::Color32::from_black_alpha(24)
+    } else {
+        egui::Color32::from_white_alpha(24)
+    }
+}
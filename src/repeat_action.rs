@@ -0,0 +1,193 @@
+//! Repeat: doing the last move, transform or duplicate of the selection again
+//!
+//! Moving or transforming copies right after duplicating them repeats as one
+//! step, so duplicating, nudging the copy and pressing Repeat a few times lays
+//! out a row of copies, and the same with a turn lays out a ring.
+
+use crate::command::Command;
+use crate::element::{Element, ElementId};
+use crate::state::EditorModel;
+use egui::Vec2;
+
+/// How far Duplicate moves the copies, so they don't hide the originals
+pub const DUPLICATE_OFFSET: Vec2 = Vec2::splat(10.0);
+
+/// The last action taken on the selection, to do again
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct RepeatAction {
+    /// Copy the selection first and act on the copies
+    pub duplicate: bool,
+    pub translation: Vec2,
+    /// Scale across and down, as factors
+    pub scale: Vec2,
+    /// Clockwise turn about the middle of the selection, in radians
+    pub rotation: f32,
+}
+
+impl RepeatAction {
+    pub fn transform(translation: Vec2, scale: Vec2, rotation: f32) -> Self {
+        Self {
+            duplicate: false,
+            translation,
+            scale,
+            rotation,
+        }
+    }
+
+    /// Copy the selection, moving the copies by `offset`
+    pub fn duplicate(offset: Vec2) -> Self {
+        Self {
+            duplicate: true,
+            ..Self::transform(offset, Vec2::splat(1.0), 0.0)
+        }
+    }
+
+    /// The move `command` made and what it moved, if all it did was move
+    /// elements by the same amount
+    pub fn from_moves(command: &Command) -> Option<(Self, Vec<ElementId>)> {
+        match command {
+            Command::MoveElement {
+                element_id,
+                old_position,
+                new_position,
+                ..
+            } => {
+                let translation = *new_position - *old_position;
+                let action = Self::transform(translation, Vec2::splat(1.0), 0.0);
+                Some((action, vec![*element_id]))
+            }
+            Command::Batch { commands, .. } => {
+                let mut moves = commands.iter().map(Self::from_moves);
+                let (action, mut ids) = moves.next()??;
+                for next in moves {
+                    let (next, next_ids) = next?;
+                    if next != action {
+                        return None;
+                    }
+                    ids.extend(next_ids);
+                }
+                Some((action, ids))
+            }
+            _ => None,
+        }
+    }
+
+    /// This action followed by `next` on what this one left selected, as one
+    ///
+    /// Moves add up and scales and turns about the middle of the selection
+    /// build on each other. A second duplicate can't be folded in.
+    pub fn then(self, next: Self) -> Option<Self> {
+        if next.duplicate {
+            return None;
+        }
+        Some(Self {
+            duplicate: self.duplicate,
+            translation: self.translation + next.translation,
+            scale: self.scale * next.scale,
+            rotation: self.rotation + next.rotation,
+        })
+    }
+
+    /// The command doing this to `element_ids`, and the elements to select after it
+    ///
+    /// Returns None if it wouldn't change anything.
+    pub fn command(
+        &self,
+        editor_model: &EditorModel,
+        element_ids: &[ElementId],
+    ) -> Option<(Command, Vec<ElementId>)> {
+        if !self.duplicate {
+            let command = Command::new_transform_selection(
+                editor_model,
+                element_ids,
+                self.translation,
+                self.scale,
+                self.rotation,
+            )?;
+            return Some((command, element_ids.to_vec()));
+        }
+
+        // Copy in stacking order, then transform the copies where they'd be
+        let mut with_copies = editor_model.clone();
+        let mut commands = Vec::new();
+        let mut copy_ids = Vec::new();
+        for element in &editor_model.elements {
+            if !element_ids.contains(&element.id()) {
+                continue;
+            }
+            let copy = element.with_id(editor_model.reserve_id());
+            copy_ids.push(copy.id());
+            with_copies.add_element(copy.clone()).ok()?;
+            commands.push(Command::AddElement { element: copy });
+        }
+        if copy_ids.is_empty() {
+            return None;
+        }
+        if let Some(Command::Batch {
+            commands: transform,
+            ..
+        }) = Command::new_transform_selection(
+            &with_copies,
+            &copy_ids,
+            self.translation,
+            self.scale,
+            self.rotation,
+        ) {
+            commands.extend(transform);
+        }
+
+        let command = Command::Batch {
+            description: "Duplicate Selection",
+            commands,
+        };
+        Some((command, copy_ids))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::command::CommandHistory;
+    use crate::element::factory;
+    use egui::{Pos2, Rect};
+
+    #[test]
+    fn test_duplicate_then_move_repeats_as_one_step() {
+        let mut model = EditorModel::new();
+        let id = model.reserve_id();
+        let rect = Rect::from_min_size(Pos2::ZERO, Vec2::new(40.0, 20.0));
+        model
+            .add_element(factory::create_image(id, Vec::new(), rect.size(), rect.min))
+            .unwrap();
+        let mut history = CommandHistory::new();
+
+        let duplicate = RepeatAction::duplicate(Vec2::ZERO);
+        let (command, copies) = duplicate.command(&model, &[id]).unwrap();
+        history.execute(command, &mut model).unwrap();
+        assert_eq!(copies.len(), 1);
+        assert_ne!(copies[0], id);
+
+        // Moving the copy folds into the duplicate
+        let nudge = Command::MoveElement {
+            element_id: copies[0],
+            _element_type: String::new(),
+            old_position: rect.min,
+            new_position: rect.min + Vec2::new(50.0, 0.0),
+        };
+        let (moved, moved_ids) = RepeatAction::from_moves(&nudge).unwrap();
+        assert_eq!(moved_ids, copies);
+        history.execute(nudge, &mut model).unwrap();
+        let repeat = duplicate.then(moved).unwrap();
+        assert!(repeat.duplicate);
+
+        let (command, next) = repeat.command(&model, &copies).unwrap();
+        history.execute(command, &mut model).unwrap();
+        assert_eq!(model.elements.len(), 3);
+        let third = model.find_element_by_id(next[0]).unwrap().rect();
+        assert_eq!(third, rect.translate(Vec2::new(100.0, 0.0)));
+
+        history.undo(&mut model).unwrap();
+        assert!(model.find_element_by_id(next[0]).is_none());
+        assert_eq!(model.elements.len(), 2);
+    }
+}
@@ -0,0 +1,184 @@
+//! Replaying a document in the order its elements were drawn
+
+use crate::element::Element;
+use crate::state::{EditorModel, ElementId};
+use std::collections::HashSet;
+
+/// Shortest pause between two replayed elements, in seconds at 1x speed
+pub const MIN_STEP_DELAY: f32 = 0.1;
+/// Longest pause between two replayed elements, so breaks while drawing don't stall the replay
+pub const MAX_STEP_DELAY: f32 = 1.0;
+
+/// One element appearing in the replay
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct ReplayStep {
+    pub element_id: ElementId,
+    /// Pause before this element appears, in seconds at 1x speed
+    pub delay: f32,
+}
+
+/// Elements in the order they were created, with the pauses between them
+///
+/// Elements are ordered by creation timestamp; untimed elements sort first and
+/// ties fall back to ID, which increases with creation as well.
+pub fn drawing_order(editor_model: &EditorModel) -> Vec<ReplayStep> {
+    let mut elements: Vec<(Option<f64>, ElementId)> = editor_model
+        .elements
+        .iter()
+        .map(|element| (element.metadata().created_at, element.id()))
+        .collect();
+    elements.sort_by(|(a_time, a_id), (b_time, b_id)| {
+        let by_time = match (a_time, b_time) {
+            (Some(a), Some(b)) => a.total_cmp(b),
+            _ => a_time.is_some().cmp(&b_time.is_some()),
+        };
+        by_time.then(a_id.cmp(b_id))
+    });
+
+    let mut previous_time = None;
+    elements
+        .into_iter()
+        .map(|(created_at, element_id)| {
+            let delay = match (previous_time, created_at) {
+                (Some(previous), Some(current)) => {
+                    ((current - previous) as f32).clamp(MIN_STEP_DELAY, MAX_STEP_DELAY)
+                }
+                _ => MIN_STEP_DELAY,
+            };
+            previous_time = created_at.or(previous_time);
+            ReplayStep { element_id, delay }
+        })
+        .collect()
+}
+
+/// Playback state for animating a document being drawn element by element
+#[derive(Clone, Debug)]
+pub struct Replay {
+    steps: Vec<ReplayStep>,
+    // Time (at 1x speed) at which each step appears
+    appear_at: Vec<f32>,
+    elapsed: f32,
+    pub speed: f32,
+    pub playing: bool,
+}
+
+impl Replay {
+    pub fn new(editor_model: &EditorModel) -> Self {
+        let steps = drawing_order(editor_model);
+        let appear_at = steps
+            .iter()
+            .scan(0.0, |time, step| {
+                *time += step.delay;
+                Some(*time)
+            })
+            .collect();
+
+        Self {
+            steps,
+            appear_at,
+            elapsed: 0.0,
+            speed: 1.0,
+            playing: true,
+        }
+    }
+
+    pub fn steps(&self) -> &[ReplayStep] {
+        &self.steps
+    }
+
+    /// Total length of the replay at 1x speed, in seconds
+    pub fn duration(&self) -> f32 {
+        self.appear_at.last().copied().unwrap_or(0.0)
+    }
+
+    /// Playback position at 1x speed, in seconds
+    pub fn elapsed(&self) -> f32 {
+        self.elapsed
+    }
+
+    /// Jump to a playback position (at 1x speed)
+    pub fn seek(&mut self, elapsed: f32) {
+        self.elapsed = elapsed.clamp(0.0, self.duration());
+    }
+
+    /// Advance playback by `dt` seconds of wall-clock time, pausing at the end
+    pub fn advance(&mut self, dt: f32) {
+        if !self.playing {
+            return;
+        }
+        self.seek(self.elapsed + dt * self.speed);
+        if self.is_finished() {
+            self.playing = false;
+        }
+    }
+
+    pub fn is_finished(&self) -> bool {
+        self.elapsed >= self.duration()
+    }
+
+    /// Start again from the beginning
+    pub fn restart(&mut self) {
+        self.elapsed = 0.0;
+        self.playing = true;
+    }
+
+    /// Number of elements visible at the current position
+    pub fn visible_count(&self) -> usize {
+        self.appear_at.partition_point(|&time| time <= self.elapsed)
+    }
+
+    /// IDs of the elements visible at the current position
+    pub fn visible_ids(&self) -> HashSet<ElementId> {
+        self.steps[..self.visible_count()]
+            .iter()
+            .map(|step| step.element_id)
+            .collect()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::element::factory;
+    use egui::{Color32, Pos2};
+
+    fn add_stroke_at(model: &mut EditorModel, id: usize, created_at: Option<f64>) {
+        let points = vec![Pos2::new(0.0, 0.0), Pos2::new(10.0, 10.0)];
+        let mut element = factory::create_stroke(ElementId::new(id), points, 2.0, Color32::BLACK);
+        element.metadata_mut().created_at = created_at;
+        model.elements.push(element);
+    }
+
+    #[test]
+    fn test_drawing_order_follows_timestamps() {
+        let mut model = EditorModel::new();
+        add_stroke_at(&mut model, 1, Some(100.0));
+        add_stroke_at(&mut model, 2, Some(50.0));
+        add_stroke_at(&mut model, 3, Some(160.0));
+
+        let steps = drawing_order(&model);
+        let ids: Vec<_> = steps.iter().map(|step| step.element_id).collect();
+        assert_eq!(ids, [2, 1, 3].map(ElementId::new));
+        // Long gaps are clamped
+        assert_eq!(steps[1].delay, MAX_STEP_DELAY);
+    }
+
+    #[test]
+    fn test_replay_reveals_elements_over_time() {
+        let mut model = EditorModel::new();
+        add_stroke_at(&mut model, 1, Some(0.0));
+        add_stroke_at(&mut model, 2, Some(0.5));
+
+        let mut replay = Replay::new(&model);
+        assert_eq!(replay.visible_count(), 0);
+
+        replay.advance(MIN_STEP_DELAY);
+        assert_eq!(replay.visible_ids(), HashSet::from([ElementId::new(1)]));
+
+        replay.speed = 2.0;
+        replay.advance(0.3);
+        assert_eq!(replay.visible_count(), 2);
+        assert!(replay.is_finished());
+        assert!(!replay.playing);
+    }
+}
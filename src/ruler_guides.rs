@@ -0,0 +1,126 @@
+//! Guide lines dragged out of the rulers onto the canvas
+//!
+//! Ruler guides are horizontal or vertical lines saved with the document. Like
+//! slices they're drawn over the canvas but never exported. The selection tool
+//! snaps the edges and middle of what it moves onto them, and the draw tool
+//! pulls points close to one onto it.
+
+use egui::{Pos2, Rect, Vec2};
+use serde::{Deserialize, Serialize};
+
+/// How close, in screen points, positions and edges have to come to a guide
+/// to snap to it, and the pointer to pick one up
+pub const GUIDE_SNAP_DISTANCE: f32 = 6.0;
+
+/// Which way a guide runs
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Serialize, Deserialize)]
+pub enum GuideAxis {
+    /// Across the canvas at a fixed y, dragged from the top ruler
+    Horizontal,
+    /// Down the canvas at a fixed x, dragged from the left ruler
+    Vertical,
+}
+
+impl GuideAxis {
+    /// The coordinate of `pos` a guide along this axis fixes
+    pub fn coordinate(self, pos: Pos2) -> f32 {
+        match self {
+            GuideAxis::Horizontal => pos.y,
+            GuideAxis::Vertical => pos.x,
+        }
+    }
+}
+
+/// A guide line at `position` along the axis it fixes, in document coordinates
+#[derive(Clone, Copy, Debug, PartialEq, Serialize, Deserialize)]
+pub struct RulerGuide {
+    pub id: usize,
+    pub axis: GuideAxis,
+    pub position: f32,
+}
+
+impl RulerGuide {
+    pub fn new(axis: GuideAxis, position: f32) -> Self {
+        Self {
+            id: crate::id_generator::generate_id(),
+            axis,
+            position,
+        }
+    }
+
+    /// How far `pos` is from the guide
+    pub fn distance(&self, pos: Pos2) -> f32 {
+        (self.axis.coordinate(pos) - self.position).abs()
+    }
+}
+
+/// The guide nearest `pos` within `tolerance`, to pick up and move
+pub fn guide_at(guides: &[RulerGuide], pos: Pos2, tolerance: f32) -> Option<&RulerGuide> {
+    guides
+        .iter()
+        .filter(|guide| guide.distance(pos) <= tolerance)
+        .min_by(|a, b| a.distance(pos).total_cmp(&b.distance(pos)))
+}
+
+/// `pos` moved onto the guides within `tolerance` of it, each way separately
+pub fn snap_position(guides: &[RulerGuide], pos: Pos2, tolerance: f32) -> Pos2 {
+    pos + Vec2::new(
+        nearest_offset(guides, GuideAxis::Vertical, &[pos.x], tolerance),
+        nearest_offset(guides, GuideAxis::Horizontal, &[pos.y], tolerance),
+    )
+}
+
+/// How far to move `rect` to bring its nearest edge or middle onto a guide
+/// within `tolerance`, each way separately
+pub fn snap_rect(guides: &[RulerGuide], rect: Rect, tolerance: f32) -> Vec2 {
+    let xs = [rect.min.x, rect.center().x, rect.max.x];
+    let ys = [rect.min.y, rect.center().y, rect.max.y];
+    Vec2::new(
+        nearest_offset(guides, GuideAxis::Vertical, &xs, tolerance),
+        nearest_offset(guides, GuideAxis::Horizontal, &ys, tolerance),
+    )
+}
+
+/// The shortest move within `tolerance` putting one of `coordinates` on a
+/// guide along `axis`, or zero
+fn nearest_offset(
+    guides: &[RulerGuide],
+    axis: GuideAxis,
+    coordinates: &[f32],
+    tolerance: f32,
+) -> f32 {
+    guides
+        .iter()
+        .filter(|guide| guide.axis == axis)
+        .flat_map(|guide| coordinates.iter().map(move |&at| guide.position - at))
+        .filter(|offset| offset.abs() <= tolerance)
+        .min_by(|a, b| a.abs().total_cmp(&b.abs()))
+        .unwrap_or(0.0)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_positions_and_edges_snap_to_nearby_guides() {
+        let guides = [
+            RulerGuide::new(GuideAxis::Vertical, 100.0),
+            RulerGuide::new(GuideAxis::Horizontal, 50.0),
+            RulerGuide::new(GuideAxis::Horizontal, 58.0),
+        ];
+
+        let pos = snap_position(&guides, Pos2::new(97.0, 55.0), 5.0);
+        assert_eq!(pos, Pos2::new(100.0, 58.0));
+        let far = Pos2::new(90.0, 70.0);
+        assert_eq!(snap_position(&guides, far, 5.0), far);
+
+        // The right edge is closest to the vertical guide, the middle to the horizontal one
+        let rect = Rect::from_min_size(Pos2::new(0.0, 32.0), Vec2::new(98.0, 40.0));
+        assert_eq!(snap_rect(&guides, rect, 5.0), Vec2::new(2.0, -2.0));
+
+        let picked = guide_at(&guides, Pos2::new(0.0, 56.0), 5.0).unwrap();
+        assert_eq!(picked.position, 58.0);
+        assert!(guide_at(&guides, Pos2::new(0.0, 80.0), 5.0).is_none());
+    }
+}
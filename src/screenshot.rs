@@ -0,0 +1,145 @@
+//! Grabbing the screen to annotate it (native builds)
+//!
+//! The capture itself is left to the platform's screenshot tool, run through
+//! the shell like export hooks are. With the `global-hotkey` feature a
+//! system-wide shortcut starts a capture even while the app is in the background.
+
+use crate::export::{PATH_PLACEHOLDER, hook_command_line, shell_command};
+use std::sync::mpsc::{Receiver, channel};
+use std::time::Duration;
+
+/// Capture command used when none is configured
+#[cfg(target_os = "macos")]
+pub const DEFAULT_CAPTURE_COMMAND: &str = "screencapture -x {path}";
+/// Capture command used when none is configured
+#[cfg(windows)]
+pub const DEFAULT_CAPTURE_COMMAND: &str = "powershell -NoProfile -Command \"\
+    Add-Type -AssemblyName System.Windows.Forms,System.Drawing; \
+    $b = [System.Windows.Forms.SystemInformation]::VirtualScreen; \
+    $i = New-Object System.Drawing.Bitmap $b.Width, $b.Height; \
+    [System.Drawing.Graphics]::FromImage($i)\
+        .CopyFromScreen($b.Location, [System.Drawing.Point]::Empty, $b.Size); \
+    $i.Save($env:PAINT_SCREENSHOT_PATH)\"";
+/// Capture command used when none is configured
+#[cfg(not(any(target_os = "macos", windows)))]
+pub const DEFAULT_CAPTURE_COMMAND: &str = "grim {path} 2>/dev/null \
+    || gnome-screenshot -f {path} 2>/dev/null \
+    || import -window root {path}";
+
+/// Environment variable holding the file the capture command should write
+pub const CAPTURE_PATH_ENV_VAR: &str = "PAINT_SCREENSHOT_PATH";
+
+/// Shortcut registered when the `global-hotkey` feature is enabled
+pub const HOTKEY_LABEL: &str = "Ctrl+Shift+A";
+
+/// Time given to the app window to get out of the way before capturing
+const CAPTURE_DELAY: Duration = Duration::from_millis(400);
+
+/// A screen capture running in the background
+pub struct ScreenCapture {
+    result: Receiver<Result<Vec<u8>, String>>,
+}
+
+impl ScreenCapture {
+    /// Start capturing with `command`, or the platform default if it is empty
+    ///
+    /// The UI is woken up once the capture is done.
+    pub fn start(command: &str, ctx: &egui::Context) -> Self {
+        let command = match command.trim() {
+            "" => DEFAULT_CAPTURE_COMMAND.to_string(),
+            command => command.to_string(),
+        };
+        let ctx = ctx.clone();
+        let (sender, result) = channel();
+        std::thread::spawn(move || {
+            std::thread::sleep(CAPTURE_DELAY);
+            let _ = sender.send(capture(&command));
+            ctx.request_repaint();
+        });
+        Self { result }
+    }
+
+    /// The captured PNG, once the capture has finished
+    pub fn poll(&self) -> Option<Result<Vec<u8>, String>> {
+        self.result.try_recv().ok()
+    }
+}
+
+/// Run the capture command and read back the image it wrote
+fn capture(command: &str) -> Result<Vec<u8>, String> {
+    let path = std::env::temp_dir().join(format!("paint-screenshot-{}.png", std::process::id()));
+    // Unlike export hooks the path is not appended: commands may read it from the environment
+    let command_line = if command.contains(PATH_PLACEHOLDER) {
+        hook_command_line(command, &path)
+    } else {
+        command.to_string()
+    };
+    log::info!("Capturing the screen: {}", command_line);
+
+    let status = shell_command(&command_line)
+        .env(CAPTURE_PATH_ENV_VAR, &path)
+        .status()
+        .map_err(|err| format!("Failed to run the screenshot command: {}", err))?;
+    if !status.success() {
+        return Err(format!("Screenshot command exited with {}", status));
+    }
+
+    let bytes =
+        std::fs::read(&path).map_err(|err| format!("No screenshot was written: {}", err))?;
+    if let Err(err) = std::fs::remove_file(&path) {
+        log::warn!("Failed to remove {}: {}", path.display(), err);
+    }
+    Ok(bytes)
+}
+
+/// The system-wide screenshot shortcut, active for as long as this is alive
+#[cfg(feature = "global-hotkey")]
+pub struct ScreenshotHotkey {
+    _manager: global_hotkey::GlobalHotKeyManager,
+    presses: Receiver<()>,
+}
+
+#[cfg(feature = "global-hotkey")]
+impl ScreenshotHotkey {
+    /// Register the shortcut; presses wake the UI
+    pub fn register(ctx: &egui::Context) -> Result<Self, String> {
+        use global_hotkey::hotkey::{Code, HotKey, Modifiers};
+        use global_hotkey::{GlobalHotKeyEvent, GlobalHotKeyManager, HotKeyState};
+
+        let manager = GlobalHotKeyManager::new().map_err(|err| err.to_string())?;
+        let hotkey = HotKey::new(Some(Modifiers::CONTROL | Modifiers::SHIFT), Code::KeyA);
+        manager.register(hotkey).map_err(|err| err.to_string())?;
+
+        let (sender, presses) = channel();
+        let ctx = ctx.clone();
+        let hotkey_id = hotkey.id();
+        GlobalHotKeyEvent::set_event_handler(Some(move |event: GlobalHotKeyEvent| {
+            if event.id == hotkey_id && event.state == HotKeyState::Pressed {
+                let _ = sender.send(());
+                ctx.request_repaint();
+            }
+        }));
+
+        Ok(Self {
+            _manager: manager,
+            presses,
+        })
+    }
+
+    /// Whether the shortcut was pressed since the last call
+    pub fn pressed(&self) -> bool {
+        self.presses.try_iter().count() > 0
+    }
+}
+
+#[cfg(all(test, not(windows)))]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_capture_reads_back_the_written_file() {
+        let bytes = capture("printf 'not really a png' > {path}").unwrap();
+        assert_eq!(bytes, b"not really a png");
+        assert!(capture("true").is_err());
+    }
+}
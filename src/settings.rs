@@ -0,0 +1,278 @@
+use crate::element::Resampling;
+use crate::guides::Guides;
+use crate::pressure::{PRESSURE_TOOLS, PressureResponse};
+use crate::tools::ToolConfig;
+use egui::Color32;
+use serde::{Deserialize, Serialize};
+use std::any::Any;
+use std::collections::BTreeMap;
+
+/// UI color scheme preference
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq, Serialize, Deserialize)]
+pub enum Theme {
+    #[default]
+    System,
+    Light,
+    Dark,
+}
+
+impl Theme {
+    pub const ALL: [Theme; 3] = [Theme::System, Theme::Light, Theme::Dark];
+
+    pub fn label(self) -> &'static str {
+        match self {
+            Theme::System => "System",
+            Theme::Light => "Light",
+            Theme::Dark => "Dark",
+        }
+    }
+
+    fn preference(self) -> egui::ThemePreference {
+        match self {
+            Theme::System => egui::ThemePreference::System,
+            Theme::Light => egui::ThemePreference::Light,
+            Theme::Dark => egui::ThemePreference::Dark,
+        }
+    }
+}
+
+/// How closely finished strokes are converted into curves, if at all
+///
+/// Curves store a fraction of a dense stroke's points; looser fits store fewer.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq, Serialize, Deserialize)]
+pub enum CurveFitting {
+    #[default]
+    Off,
+    Compact,
+    Balanced,
+    Precise,
+}
+
+impl CurveFitting {
+    pub const ALL: [CurveFitting; 4] = [
+        CurveFitting::Off,
+        CurveFitting::Compact,
+        CurveFitting::Balanced,
+        CurveFitting::Precise,
+    ];
+
+    pub fn label(self) -> &'static str {
+        match self {
+            CurveFitting::Off => "Off",
+            CurveFitting::Compact => "Compact",
+            CurveFitting::Balanced => "Balanced",
+            CurveFitting::Precise => "Precise",
+        }
+    }
+
+    /// Furthest the curves may stray from the drawn points, in points; None when off
+    pub fn tolerance(self) -> Option<f32> {
+        match self {
+            CurveFitting::Off => None,
+            CurveFitting::Compact => Some(2.0),
+            CurveFitting::Balanced => Some(1.0),
+            CurveFitting::Precise => Some(0.4),
+        }
+    }
+}
+
+/// How many edits make up one undo step
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq, Serialize, Deserialize)]
+pub enum UndoGranularity {
+    /// Every command is its own step, so a stroke and its auto-shape undo apart
+    #[default]
+    PerStroke,
+    /// Everything from pressing the pointer to letting it go is one step
+    PerGesture,
+    /// Edits following each other within the undo window are one step
+    TimeWindow,
+}
+
+impl UndoGranularity {
+    pub const ALL: [UndoGranularity; 3] = [
+        UndoGranularity::PerStroke,
+        UndoGranularity::PerGesture,
+        UndoGranularity::TimeWindow,
+    ];
+
+    pub fn label(self) -> &'static str {
+        match self {
+            UndoGranularity::PerStroke => "Each stroke",
+            UndoGranularity::PerGesture => "Each pointer-up",
+            UndoGranularity::TimeWindow => "Time window",
+        }
+    }
+}
+
+/// How far the arrow keys nudge the selection and what moves and turns snap to
+#[derive(Clone, Copy, Debug, PartialEq, Serialize, Deserialize)]
+#[serde(default)]
+pub struct SnapIncrements {
+    /// Distance an arrow key moves the selection, in points
+    pub nudge: f32,
+    /// Distance an arrow key moves the selection with Shift held, in points
+    pub large_nudge: f32,
+    /// Spacing of the grid moves snap to with Ctrl held, in points
+    pub grid: f32,
+    /// Angle rotations snap to multiples of, in degrees
+    pub angle: f32,
+}
+
+impl Default for SnapIncrements {
+    fn default() -> Self {
+        Self {
+            nudge: 1.0,
+            large_nudge: 10.0,
+            grid: 10.0,
+            angle: 45.0,
+        }
+    }
+}
+
+impl ToolConfig for SnapIncrements {
+    fn tool_name(&self) -> &'static str {
+        "Snapping"
+    }
+
+    fn as_any(&self) -> &dyn Any {
+        self
+    }
+
+    fn as_any_mut(&mut self) -> &mut dyn Any {
+        self
+    }
+}
+
+/// Named canvas background presets offered next to the color picker
+pub const CANVAS_BACKGROUNDS: [(&str, Color32); 3] = [
+    ("Light", Color32::WHITE),
+    ("Dark", Color32::from_rgb(30, 30, 30)),
+    ("Transparent", Color32::TRANSPARENT),
+];
+
+/// User preferences, persisted with eframe storage
+///
+/// Missing fields fall back to their defaults so older saved settings still load.
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
+#[serde(default)]
+pub struct Settings {
+    pub theme: Theme,
+    /// Seconds between automatic saves of app state
+    pub autosave_interval_secs: u64,
+    /// Thickness new strokes start with
+    pub stroke_thickness: f32,
+    /// Whether the draw tool starts with auto-shape on
+    pub auto_shape: bool,
+    /// Whether the draw tool converts finished strokes into curves, and how closely
+    pub curve_fitting: CurveFitting,
+    /// Spacing of the canvas grid in points; zero hides the grid
+    pub grid_size: f32,
+    /// Outline each document pixel when zoomed in past `view::PIXEL_GRID_ZOOM`
+    pub pixel_grid: bool,
+    /// Snap pointer positions to pixels when zoomed in past `view::PIXEL_GRID_ZOOM`
+    pub snap_to_pixels: bool,
+    /// Nudge distances and snapping steps for moving and turning the selection
+    pub snap: SnapIncrements,
+    /// Perspective or isometric guides drawn over the canvas
+    pub guides: Guides,
+    /// Show rulers along the top and left of the canvas to drag guides out of
+    pub rulers: bool,
+    /// Show the document's ruler guides and snap to them
+    pub ruler_guides: bool,
+    /// Keep ruler guides where they are, out of reach of the pointer
+    pub lock_ruler_guides: bool,
+    /// Maximum number of undo steps kept; zero means unlimited
+    pub undo_limit: usize,
+    /// How many edits make up one undo step
+    pub undo_granularity: UndoGranularity,
+    /// Longest pause between edits grouped under `UndoGranularity::TimeWindow`, in seconds
+    pub undo_window_secs: f32,
+    /// Keep undone steps as branches of the history instead of dropping them for new work
+    pub keep_undo_branches: bool,
+    pub background_color: Color32,
+    /// Show a checkerboard through transparent parts of the canvas background
+    pub checkerboard: bool,
+    /// Hide whatever lies outside the document's artboards
+    pub clip_to_artboard: bool,
+    /// Draw opaque strokes as meshes rather than textures, keeping them sharp when zoomed
+    pub vector_strokes: bool,
+    /// How images are resampled to the size they're shown at on the canvas
+    pub image_resampling: Resampling,
+    /// Replace the pointer with an outline of the brush while drawing
+    pub brush_outline: bool,
+    /// Ease the canvas into place when zooming to fit or to a preset, or when
+    /// undo scrolls to a change; off jumps straight there
+    pub animate_view: bool,
+    /// Link images dropped from disk to their files instead of embedding them
+    pub link_dropped_images: bool,
+    /// Shell command run after each export; `{path}` stands for the exported file
+    pub post_export_command: String,
+    /// Show a tray icon for quick sketches (builds with the `tray` feature only)
+    pub tray_icon: bool,
+    /// Folder quick sketches are saved into; empty for the app's data folder
+    pub scratch_dir: String,
+    /// Shell command capturing the screen into `{path}`; empty for the platform default
+    pub screenshot_command: String,
+    /// Listen for the system-wide screenshot shortcut (`global-hotkey` feature only)
+    pub screenshot_hotkey: bool,
+    /// How each brush tool follows pen pressure, by tool name
+    pub pressure: BTreeMap<String, PressureResponse>,
+}
+
+impl Default for Settings {
+    fn default() -> Self {
+        Self {
+            theme: Theme::System,
+            autosave_interval_secs: 30,
+            stroke_thickness: 2.0,
+            auto_shape: false,
+            curve_fitting: CurveFitting::Off,
+            grid_size: 0.0,
+            pixel_grid: true,
+            snap_to_pixels: false,
+            snap: SnapIncrements::default(),
+            guides: Guides::default(),
+            rulers: false,
+            ruler_guides: true,
+            lock_ruler_guides: false,
+            undo_limit: 100,
+            undo_granularity: UndoGranularity::PerStroke,
+            undo_window_secs: 2.0,
+            keep_undo_branches: false,
+            background_color: Color32::WHITE,
+            checkerboard: true,
+            clip_to_artboard: true,
+            vector_strokes: true,
+            image_resampling: Resampling::Lanczos,
+            brush_outline: true,
+            animate_view: true,
+            link_dropped_images: false,
+            post_export_command: String::new(),
+            tray_icon: false,
+            scratch_dir: String::new(),
+            screenshot_command: String::new(),
+            screenshot_hotkey: true,
+            pressure: PRESSURE_TOOLS
+                .iter()
+                .map(|tool| (tool.to_string(), PressureResponse::default()))
+                .collect(),
+        }
+    }
+}
+
+impl Settings {
+    /// Apply the theme preference to the egui context
+    pub fn apply_theme(&self, ctx: &egui::Context) {
+        ctx.set_theme(self.theme.preference());
+    }
+
+    /// Longest pause between edits grouped into one undo step
+    pub fn undo_window(&self) -> std::time::Duration {
+        std::time::Duration::from_secs_f32(self.undo_window_secs.max(0.0))
+    }
+
+    /// Time between automatic saves of app state
+    pub fn autosave_interval(&self) -> std::time::Duration {
+        std::time::Duration::from_secs(self.autosave_interval_secs.max(1))
+    }
+}
@@ -0,0 +1,126 @@
+//! Sharing small documents as links
+//!
+//! The whole document is compressed and base64-encoded into the link's
+//! fragment, so opening the link in the web build opens the document without a
+//! server in between. Fragments aren't sent to servers, but browsers and chat
+//! apps limit how long links may be, so only small documents fit.
+
+use crate::document::{DocumentError, DocumentFile};
+use base64::Engine as _;
+
+/// What starts the fragment of a share link
+const FRAGMENT_PREFIX: &str = "doc=";
+/// Longest fragment a share link may have, in characters
+pub const MAX_FRAGMENT_LEN: usize = 8_000;
+/// Largest document a share link may unpack to, against links made to blow up
+const MAX_DOCUMENT_LEN: usize = 4 * 1024 * 1024;
+
+#[derive(Debug, thiserror::Error)]
+pub enum ShareLinkError {
+    #[error(transparent)]
+    Document(#[from] DocumentError),
+    #[error(
+        "The document is too large to share as a link ({len} characters, at most {max}); \
+         images in particular take a lot of room"
+    )]
+    TooLarge { len: usize, max: usize },
+    #[error("Not a share link")]
+    NotALink,
+    #[error("The share link is damaged: {0}")]
+    Damaged(String),
+}
+
+/// The fragment of a share link to `file`, without the leading '#'
+pub fn encode_fragment(file: &DocumentFile) -> Result<String, ShareLinkError> {
+    let text = file.to_ron()?;
+    let compressed = miniz_oxide::deflate::compress_to_vec(text.as_bytes(), 9);
+    let fragment = format!(
+        "{}{}",
+        FRAGMENT_PREFIX,
+        base64::engine::general_purpose::URL_SAFE_NO_PAD.encode(compressed)
+    );
+    if fragment.len() > MAX_FRAGMENT_LEN {
+        return Err(ShareLinkError::TooLarge {
+            len: fragment.len(),
+            max: MAX_FRAGMENT_LEN,
+        });
+    }
+    Ok(fragment)
+}
+
+/// The document in the fragment of a share link, with or without the leading '#'
+pub fn decode_fragment(fragment: &str) -> Result<DocumentFile, ShareLinkError> {
+    let encoded = fragment
+        .trim_start_matches('#')
+        .strip_prefix(FRAGMENT_PREFIX)
+        .ok_or(ShareLinkError::NotALink)?;
+    let compressed = base64::engine::general_purpose::URL_SAFE_NO_PAD
+        .decode(encoded)
+        .map_err(|err| ShareLinkError::Damaged(err.to_string()))?;
+    let text = miniz_oxide::inflate::decompress_to_vec_with_limit(&compressed, MAX_DOCUMENT_LEN)
+        .map_err(|err| ShareLinkError::Damaged(err.to_string()))?;
+    let text = String::from_utf8(text).map_err(|err| ShareLinkError::Damaged(err.to_string()))?;
+    Ok(DocumentFile::from_ron(&text)?)
+}
+
+/// A link to `file` on the page at `page_url`, replacing any fragment it has
+pub fn share_link(page_url: &str, file: &DocumentFile) -> Result<String, ShareLinkError> {
+    let page_url = page_url.split('#').next().unwrap_or(page_url);
+    Ok(format!("{}#{}", page_url, encode_fragment(file)?))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::element::{ElementId, factory};
+    use egui::{Color32, Pos2, Vec2};
+
+    #[test]
+    fn test_share_links_round_trip_small_documents_only() {
+        let stroke = factory::create_stroke(
+            ElementId::new(1),
+            vec![Pos2::new(10.0, 20.0), Pos2::new(30.0, 40.0)],
+            2.0,
+            Color32::RED,
+        );
+        let file = DocumentFile {
+            elements: vec![stroke],
+            ..DocumentFile::default()
+        };
+
+        let link = share_link("https://example.com/paint/#doc=old", &file).unwrap();
+        let (page, fragment) = link.split_once('#').unwrap();
+        assert_eq!(page, "https://example.com/paint/");
+        let decoded = decode_fragment(fragment).unwrap();
+        assert_eq!(decoded.to_ron().unwrap(), file.to_ron().unwrap());
+
+        assert!(matches!(
+            decode_fragment("#other"),
+            Err(ShareLinkError::NotALink)
+        ));
+        assert!(matches!(
+            decode_fragment("#doc=!!!"),
+            Err(ShareLinkError::Damaged(_))
+        ));
+
+        // Noise doesn't compress, so a big image won't fit in a link
+        let mut seed = 0x2545_f491_u32;
+        let noise: Vec<u8> = (0..20_000)
+            .map(|_| {
+                seed ^= seed << 13;
+                seed ^= seed >> 17;
+                seed ^= seed << 5;
+                seed as u8
+            })
+            .collect();
+        let image = factory::create_image(ElementId::new(2), noise, Vec2::splat(100.0), Pos2::ZERO);
+        let file = DocumentFile {
+            elements: vec![image],
+            ..DocumentFile::default()
+        };
+        assert!(matches!(
+            encode_fragment(&file),
+            Err(ShareLinkError::TooLarge { .. })
+        ));
+    }
+}
@@ -1,10 +1,18 @@
+use crate::artboard::{self, Artboard};
+use crate::document::{DocumentFile, DocumentMetadata, DocumentProperties};
+use crate::element::step_badge::StepBadge;
 use crate::element::{Element, ElementType};
+use crate::export_slice::ExportSlice;
+use crate::reference_image::ReferenceImage;
+use crate::ruler_guides::RulerGuide;
 use crate::tools::{Tool, ToolType};
+use crate::units::DocumentUnits;
+use std::cell::Cell;
 use std::collections::HashSet;
 use egui;
 use log;
 
-pub type ElementId = usize;
+pub use crate::element::ElementId;
 
 #[derive(Clone)]
 pub struct EditorModel {
@@ -12,6 +20,24 @@ pub struct EditorModel {
     pub version: usize,
     pub selected_element_ids: HashSet<ElementId>,
     pub active_tool: ToolType,
+    pub metadata: DocumentMetadata,
+    pub units: DocumentUnits,
+    /// Fixed canvas rectangles; empty to fit the canvas to the content
+    pub artboards: Vec<Artboard>,
+    /// Artboard exported and edited by default; like the selection, not part of the content
+    pub active_artboard_id: Option<usize>,
+    /// Image drawn beneath every element to trace over
+    pub reference_image: Option<ReferenceImage>,
+    /// Canvas color for this document, or None to use the one in the preferences
+    pub background: Option<egui::Color32>,
+    /// Named rectangles exported as images of their own
+    pub slices: Vec<ExportSlice>,
+    /// Horizontal and vertical lines dragged out of the rulers to line things up on
+    pub ruler_guides: Vec<RulerGuide>,
+    /// Id `reserve_id` hands out next, always past every element's id
+    ///
+    /// A cell so tools and commands, which only see the model, can reserve ids.
+    next_id: Cell<ElementId>,
 }
 
 impl EditorModel {
@@ -24,6 +50,15 @@ impl EditorModel {
             version: 0,
             selected_element_ids: HashSet::new(),
             active_tool: default_tool,
+            metadata: DocumentMetadata::default(),
+            units: DocumentUnits::default(),
+            artboards: Vec::new(),
+            active_artboard_id: None,
+            reference_image: None,
+            background: None,
+            slices: Vec::new(),
+            ruler_guides: Vec::new(),
+            next_id: Cell::new(ElementId::new(1)),
         }
     }
 
@@ -31,18 +66,188 @@ impl EditorModel {
         self.version += 1;
     }
 
+    /// Replace the whole document, e.g. after opening a file
+    ///
+    /// The active tool is kept; the selection is cleared.
+    pub fn load_document(&mut self, file: DocumentFile) {
+        self.elements = file.elements;
+        self.metadata = file.metadata;
+        self.units = file.units;
+        self.artboards = file.artboards;
+        self.active_artboard_id = None;
+        self.reference_image = file.reference_image;
+        self.background = file.background;
+        self.slices = file.slices;
+        self.ruler_guides = file.ruler_guides;
+        self.selected_element_ids.clear();
+        self.mark_modified();
+
+        // Carry on numbering past whatever the document already uses
+        let max_id = self
+            .elements
+            .iter()
+            .map(|e| e.id())
+            .max()
+            .unwrap_or_default();
+        self.next_id.set(max_id.next());
+        let artboard_ids = self.artboards.iter().map(|artboard| artboard.id);
+        let other_ids = self.slices.iter().map(|slice| slice.id);
+        let other_ids = other_ids.chain(self.ruler_guides.iter().map(|guide| guide.id));
+        if let Some(max_id) = artboard_ids.chain(other_ids).max() {
+            crate::id_generator::reserve_past(max_id);
+        }
+    }
+
+    /// Hand out an id no element of this document uses or will be given again
+    pub fn reserve_id(&self) -> ElementId {
+        let id = self.next_id.get();
+        self.next_id.set(id.next());
+        id
+    }
+
+    /// The id [`Self::reserve_id`] hands out next
+    pub fn next_id(&self) -> ElementId {
+        self.next_id.get()
+    }
+
+    /// Never hand out ids before `id`, e.g. ones a restored undo history refers to
+    pub fn reserve_ids_before(&self, id: ElementId) {
+        if id > self.next_id.get() {
+            self.next_id.set(id);
+        }
+    }
+
+    /// Canvas size, resolution and background as one value
+    pub fn properties(&self) -> DocumentProperties {
+        DocumentProperties {
+            units: self.units,
+            artboards: self.artboards.clone(),
+            background: self.background,
+        }
+    }
+
+    pub fn set_properties(&mut self, properties: DocumentProperties) {
+        self.units = properties.units;
+        self.artboards = properties.artboards;
+        self.background = properties.background;
+    }
+
+    // Artboards
+
+    /// The active artboard, falling back to the first one
+    pub fn active_artboard(&self) -> Option<&Artboard> {
+        self.active_artboard_id
+            .and_then(|id| self.find_artboard(id))
+            .or_else(|| self.artboards.first())
+    }
+
+    pub fn set_active_artboard(&mut self, artboard_id: Option<usize>) {
+        self.active_artboard_id = artboard_id;
+    }
+
+    pub fn find_artboard(&self, artboard_id: usize) -> Option<&Artboard> {
+        self.artboards
+            .iter()
+            .find(|artboard| artboard.id == artboard_id)
+    }
+
+    /// The artboard an element belongs to, if any
+    pub fn element_artboard(&self, element_id: ElementId) -> Option<&Artboard> {
+        let element = self.find_element_by_id(element_id)?;
+        artboard::artboard_for_rect(&self.artboards, element.rect())
+    }
+
+    /// IDs of the elements on an artboard, in document order
+    pub fn artboard_element_ids(&self, artboard_id: usize) -> Vec<ElementId> {
+        self.elements
+            .iter()
+            .filter(|element| {
+                artboard::artboard_for_rect(&self.artboards, element.rect())
+                    .is_some_and(|artboard| artboard.id == artboard_id)
+            })
+            .map(|element| element.id())
+            .collect()
+    }
+
+    // Step badges
+
+    /// The document's step badges in step order, ties kept in document order
+    pub fn step_badges(&self) -> Vec<&StepBadge> {
+        let mut badges: Vec<&StepBadge> = self
+            .elements
+            .iter()
+            .filter_map(|element| match element {
+                ElementType::StepBadge(badge) => Some(badge),
+                _ => None,
+            })
+            .collect();
+        badges.sort_by_key(|badge| badge.number());
+        badges
+    }
+
+    /// Number the next step badge gets: one past the highest in the document
+    pub fn next_step_number(&self) -> u32 {
+        self.step_badges()
+            .last()
+            .map_or(1, |badge| badge.number() + 1)
+    }
+
     // Element management with new ownership transfer pattern
 
     /// Add an element to the document
-    pub fn add_element(&mut self, element: ElementType) {
-        self.elements.push(element);
+    ///
+    /// Elements added for the first time are stamped with their creation time;
+    /// elements re-added by undo/redo keep their original timestamp. Fails if
+    /// another element already has the same id.
+    pub fn add_element(&mut self, element: ElementType) -> Result<(), String> {
+        self.insert_element(self.elements.len(), element)
+    }
+
+    /// Add an element at `index` in the stack, or on top if that's past the end
+    ///
+    /// Stamps and checks the element as [`Self::add_element`] does.
+    pub fn insert_element(&mut self, index: usize, mut element: ElementType) -> Result<(), String> {
+        let id = element.id();
+        if self.contains_element(id) {
+            return Err(format!("Element id {} is already in use", id));
+        }
+        // Ids chosen elsewhere must never be reserved again
+        if id >= self.next_id.get() {
+            self.next_id.set(id.next());
+        }
+
+        self.stamp_element(&mut element);
+        self.elements.insert(index.min(self.elements.len()), element);
         self.mark_modified();
+        Ok(())
+    }
+
+    /// Stamp an element about to be added with its creation time, and with the
+    /// `uuid` feature a uuid no other element has, unless it has them already
+    pub fn stamp_element(&self, element: &mut ElementType) {
+        let metadata = element.metadata_mut();
+        if metadata.created_at.is_none() {
+            metadata.created_at = Some(unix_time_now());
+        }
+        #[cfg(feature = "uuid")]
+        {
+            // Elements built from another's metadata would otherwise share its uuid
+            let taken = |uuid: &String| {
+                self.elements
+                    .iter()
+                    .any(|other| other.metadata().uuid.as_ref() == Some(uuid))
+            };
+            if metadata.uuid.as_ref().is_none_or(taken) {
+                metadata.uuid = Some(uuid::Uuid::new_v4().to_string());
+            }
+        }
     }
 
     /// Take ownership of an element from the document
     pub fn take_element_by_id(&mut self, id: ElementId) -> Option<ElementType> {
         let pos = self.elements.iter().position(|e| e.id() == id)?;
-        let element = self.elements.swap_remove(pos);
+        // Keep the rest in their order in the stack
+        let element = self.elements.remove(pos);
         self.mark_modified();
         Some(element)
     }
@@ -77,9 +282,7 @@ impl EditorModel {
         element.translate(delta)?;
 
         // Return ownership to the model
-        self.add_element(element);
-
-        Ok(())
+        self.add_element(element)
     }
 
     /// Resize an element to the given rectangle
@@ -97,9 +300,7 @@ impl EditorModel {
         element.resize(new_rect)?;
 
         // Return ownership to the model
-        self.add_element(element);
-
-        Ok(())
+        self.add_element(element)
     }
 
     /// Removes an element by ID
@@ -140,16 +341,13 @@ impl EditorModel {
         }
 
         // If we have a current tool, deactivate it
-        log::info!("Deactivating tool: {}", self.active_tool.name());
+        log::debug!(tool = self.active_tool.name(); "Deactivating tool");
 
         // Set the new tool
         self.active_tool = new_tool;
 
         // Log that we're activating the new tool
-        log::info!("Activating tool: {}", self.active_tool.name());
-
-        // Mark as modified
-        self.mark_modified();
+        log::debug!(tool = self.active_tool.name(); "Activating tool");
     }
 
     // Selection Management methods
@@ -175,6 +373,15 @@ impl EditorModel {
             .and_then(|id| self.find_element_by_id(*id))
     }
 
+    /// The box around every selected element, including their selection padding
+    pub fn selection_rect(&self) -> Option<egui::Rect> {
+        self.selected_element_ids
+            .iter()
+            .filter_map(|id| self.find_element_by_id(*id))
+            .map(crate::element::compute_element_rect)
+            .reduce(|bounds, rect| bounds.union(rect))
+    }
+
     /// Updates the selection
     pub fn update_selection<F>(&mut self, f: F)
     where
@@ -191,15 +398,11 @@ impl EditorModel {
 
         // Update the selection
         self.selected_element_ids = new_ids_set;
-
-        // Mark as modified
-        self.mark_modified();
     }
 
     /// Sets the selected elements by ID
     pub fn with_selected_elements_by_id(&mut self, ids: Vec<ElementId>) {
         self.selected_element_ids = ids.into_iter().collect();
-        self.mark_modified();
     }
 
     /// Sets a single selected element by ID (or none)
@@ -214,25 +417,21 @@ impl EditorModel {
                 self.selected_element_ids.clear();
             }
         }
-        self.mark_modified();
     }
 
     /// Selects an element by ID
     pub fn select_element(&mut self, id: ElementId) {
         self.selected_element_ids.insert(id);
-        self.mark_modified();
     }
 
     /// Deselects an element by ID
     pub fn deselect_element(&mut self, id: ElementId) {
         self.selected_element_ids.remove(&id);
-        self.mark_modified();
     }
 
     /// Clears all selection
     pub fn clear_selection(&mut self) {
         self.selected_element_ids.clear();
-        self.mark_modified();
     }
 
     /// Toggles selection of an element by ID
@@ -242,23 +441,37 @@ impl EditorModel {
         } else {
             self.selected_element_ids.insert(id);
         }
-        self.mark_modified();
     }
 
     /// Gets current version
+    ///
+    /// The version only changes when the document content changes; selection
+    /// and tool changes leave it alone, so it can tell whether there is
+    /// anything unsaved.
     pub fn version(&self) -> usize {
         self.version
     }
 
+    /// What undo has to bring back of the model, to compare it with another
+    pub fn digest(&self) -> ModelDigest {
+        let mut selection: Vec<ElementId> = self.selected_element_ids.iter().copied().collect();
+        selection.sort();
+        ModelDigest {
+            document: DocumentFile::from_model(self)
+                .to_ron()
+                .unwrap_or_else(|e| e.to_string()),
+            selection,
+            active_artboard_id: self.active_artboard_id,
+        }
+    }
+
     /// Finds element at a given position
     pub fn element_at_position(&self, point: egui::Pos2) -> Option<&ElementType> {
-        // Check all elements (front to back)
-        for element in self.elements.iter().rev() {
-            if element.hit_test(point) {
-                return Some(element);
-            }
-        }
-        None
+        // Check all elements (front to back), looking through locked ones
+        self.elements
+            .iter()
+            .rev()
+            .find(|element| !element.metadata().is_locked() && element.hit_test(point))
     }
 
     // Legacy compatibility methods
@@ -290,6 +503,78 @@ impl EditorModel {
     // Legacy with_selected_element method has been removed
 }
 
+/// The state of a model that commands change and undo brings back: the
+/// document as saved, the selection and the active artboard
+///
+/// Two models with equal digests are equivalent, whatever their tools, version
+/// counters or cached textures. Decimal numbers only have to be close, so float
+/// error from scaling something and back doesn't count.
+#[derive(Debug)]
+pub struct ModelDigest {
+    document: String,
+    selection: Vec<ElementId>,
+    active_artboard_id: Option<usize>,
+}
+
+/// How far apart decimal numbers in equal digests may be: a hundredth, or a
+/// ten-thousandth of their size when that's more
+const DIGEST_TOLERANCE: (f64, f64) = (0.01, 1e-4);
+
+impl PartialEq for ModelDigest {
+    fn eq(&self, other: &Self) -> bool {
+        let (text, numbers) = split_decimals(&self.document);
+        let (other_text, other_numbers) = split_decimals(&other.document);
+        let (absolute, relative) = DIGEST_TOLERANCE;
+        let close =
+            |(a, b): (f64, f64)| (a - b).abs() <= absolute.max(relative * a.abs().max(b.abs()));
+        self.selection == other.selection
+            && self.active_artboard_id == other.active_artboard_id
+            && text == other_text
+            && numbers.len() == other_numbers.len()
+            && numbers.into_iter().zip(other_numbers).all(close)
+    }
+}
+
+/// `text` with each decimal number in it swapped for `#`, and the numbers
+fn split_decimals(text: &str) -> (String, Vec<f64>) {
+    let mut rest = String::with_capacity(text.len());
+    let mut numbers = Vec::new();
+    let mut token = String::new();
+    let mut end_token = |token: &mut String, rest: &mut String| {
+        match token.parse::<f64>() {
+            Ok(number) if token.contains('.') => {
+                numbers.push(number);
+                rest.push('#');
+            }
+            _ => rest.push_str(token),
+        }
+        token.clear();
+    };
+    for c in text.chars() {
+        if c.is_ascii_digit() || c == '.' || (c == '-' && token.is_empty()) {
+            token.push(c);
+        } else {
+            end_token(&mut token, &mut rest);
+            rest.push(c);
+        }
+    }
+    end_token(&mut token, &mut rest);
+    (rest, numbers)
+}
+
+impl Default for EditorModel {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+/// Current wall-clock time in seconds since the Unix epoch
+pub(crate) fn unix_time_now() -> f64 {
+    web_time::SystemTime::now()
+        .duration_since(web_time::UNIX_EPOCH)
+        .map_or(0.0, |duration| duration.as_secs_f64())
+}
+
 // Define a test module to test the model
 #[cfg(test)]
 mod tests {
@@ -302,15 +587,15 @@ mod tests {
 
         // Add a stroke
         let points = vec![Pos2::new(10.0, 10.0), Pos2::new(30.0, 30.0)];
-        let stroke = factory::create_stroke(1, points, 2.0, Color32::RED);
-        model.add_element(stroke);
+        let stroke = factory::create_stroke(ElementId::new(1), points, 2.0, Color32::RED);
+        model.add_element(stroke).unwrap();
 
         // Add an image
         let data = vec![0u8; 100]; // Dummy data
         let size = Vec2::new(100.0, 100.0);
         let position = Pos2::new(50.0, 50.0);
-        let image = factory::create_image(2, data, size, position);
-        model.add_element(image);
+        let image = factory::create_image(ElementId::new(2), data, size, position);
+        model.add_element(image).unwrap();
 
         model
     }
@@ -323,20 +608,20 @@ mod tests {
         assert_eq!(model.elements.len(), 2);
 
         // Find element by ID
-        let element = model.find_element_by_id(1);
+        let element = model.find_element_by_id(ElementId::new(1));
         assert!(element.is_some());
-        assert_eq!(element.unwrap().id(), 1);
+        assert_eq!(element.unwrap().id(), ElementId::new(1));
 
         // Take element ownership
-        let element = model.take_element_by_id(1);
+        let element = model.take_element_by_id(ElementId::new(1));
         assert!(element.is_some());
-        assert_eq!(element.unwrap().id(), 1);
+        assert_eq!(element.unwrap().id(), ElementId::new(1));
 
         // Check element count after removal
         assert_eq!(model.elements.len(), 1);
 
         // Check that the element is gone
-        assert!(model.find_element_by_id(1).is_none());
+        assert!(model.find_element_by_id(ElementId::new(1)).is_none());
     }
 
     #[test]
@@ -347,17 +632,17 @@ mod tests {
         assert!(model.selected_element_ids.is_empty());
 
         // Select an element
-        model.select_element(1);
+        model.select_element(ElementId::new(1));
         assert_eq!(model.selected_element_ids.len(), 1);
-        assert!(model.selected_element_ids.contains(&1));
+        assert!(model.selected_element_ids.contains(&ElementId::new(1)));
 
         // Toggle selection should deselect
-        model.toggle_selection(1);
+        model.toggle_selection(ElementId::new(1));
         assert!(model.selected_element_ids.is_empty());
 
         // Select multiple elements
-        model.select_element(1);
-        model.select_element(2);
+        model.select_element(ElementId::new(1));
+        model.select_element(ElementId::new(2));
         assert_eq!(model.selected_element_ids.len(), 2);
 
         // Clear selection
@@ -370,16 +655,16 @@ mod tests {
         let mut model = create_test_model();
 
         // Get initial position
-        let element = model.find_element_by_id(1).unwrap();
+        let element = model.find_element_by_id(ElementId::new(1)).unwrap();
         let initial_rect = element.rect();
 
         // Translate
         let delta = Vec2::new(10.0, 20.0);
-        let result = model.translate_element(1, delta);
+        let result = model.translate_element(ElementId::new(1), delta);
         assert!(result.is_ok());
 
         // Check new position
-        let element = model.find_element_by_id(1).unwrap();
+        let element = model.find_element_by_id(ElementId::new(1)).unwrap();
         let new_rect = element.rect();
 
         assert!(
@@ -387,4 +672,62 @@ mod tests {
                 && (new_rect.min.y - initial_rect.min.y - 20.0).abs() < 0.001
         );
     }
+
+    #[test]
+    fn test_ids_continue_past_loaded_and_added_elements() {
+        let mut model = EditorModel::new();
+        model.load_document(DocumentFile {
+            elements: create_test_model().elements,
+            ..DocumentFile::default()
+        });
+        assert_eq!(model.reserve_id(), ElementId::new(3));
+
+        // Ids picked elsewhere are skipped too
+        let points = vec![Pos2::new(0.0, 0.0), Pos2::new(10.0, 10.0)];
+        model
+            .add_element(factory::create_stroke(
+                ElementId::new(10),
+                points.clone(),
+                2.0,
+                Color32::RED,
+            ))
+            .unwrap();
+        assert_eq!(model.reserve_id(), ElementId::new(11));
+
+        // An id that's already taken is refused
+        let duplicate = factory::create_stroke(ElementId::new(1), points, 2.0, Color32::BLUE);
+        assert!(model.add_element(duplicate).is_err());
+        assert_eq!(model.elements.len(), 3);
+    }
+
+    #[cfg(feature = "uuid")]
+    #[test]
+    fn test_added_elements_get_their_own_uuid() {
+        let mut model = create_test_model();
+        let original = model.find_element_by_id(ElementId::new(1)).unwrap();
+        assert!(original.metadata().uuid.is_some());
+
+        // A copy of the metadata doesn't copy the uuid along with it
+        let points = vec![Pos2::new(0.0, 0.0), Pos2::new(10.0, 10.0)];
+        let mut copy = factory::create_stroke(model.reserve_id(), points, 2.0, Color32::RED);
+        *copy.metadata_mut() = original.metadata().clone();
+        let copy_id = copy.id();
+        model.add_element(copy).unwrap();
+        let uuid = |model: &EditorModel, id| {
+            model
+                .find_element_by_id(id)
+                .unwrap()
+                .metadata()
+                .uuid
+                .clone()
+        };
+        let before = uuid(&model, copy_id);
+        assert_ne!(before, uuid(&model, ElementId::new(1)));
+
+        // Moving an element keeps its uuid
+        model
+            .translate_element(copy_id, Vec2::new(5.0, 5.0))
+            .unwrap();
+        assert_eq!(uuid(&model, copy_id), before);
+    }
 }
@@ -0,0 +1,1207 @@
+//! Importing SVG files as editable elements
+//!
+//! Outlines in paths, lines and basic shapes become path and stroke elements
+//! with their Bézier curves intact, fills become shape elements, and embedded
+//! images become image elements. Transforms are applied to the points, so
+//! nothing is lost except the rotation and skew of images, which can only be
+//! placed upright. Text, gradients, `<use>` references and clipping aren't
+//! imported.
+
+use crate::command::Command;
+use crate::element::path::flatten_nodes;
+use crate::element::{Element, ElementType, PathNode, factory};
+use crate::state::EditorModel;
+use base64::Engine as _;
+use egui::{Color32, Pos2, Rect, Vec2, pos2, vec2};
+use quick_xml::events::{BytesStart, Event};
+use std::collections::HashMap;
+
+/// Size of the SVG canvas when the file gives neither a size nor a view box
+const DEFAULT_SIZE: Vec2 = vec2(300.0, 150.0);
+
+#[derive(Debug, thiserror::Error)]
+pub enum SvgImportError {
+    #[error("The SVG file couldn't be read: {0}")]
+    Xml(String),
+    #[error("Not an SVG file")]
+    NotSvg,
+    #[error("The SVG has no paths, shapes or images that can be imported")]
+    Empty,
+}
+
+/// Whether a dropped file looks like an SVG, from its MIME type or name
+pub fn is_svg(mime: &str, name: &str) -> bool {
+    mime == "image/svg+xml" || name.to_lowercase().ends_with(".svg")
+}
+
+/// A command adding the contents of the SVG in `bytes`, with the middle of its
+/// canvas at `center`
+pub fn import_svg(
+    bytes: &[u8],
+    editor_model: &EditorModel,
+    center: Pos2,
+) -> Result<Command, SvgImportError> {
+    let text = std::str::from_utf8(bytes).map_err(|err| SvgImportError::Xml(err.to_string()))?;
+    let mut importer = Importer {
+        editor_model,
+        center,
+        elements: Vec::new(),
+    };
+    importer.read(text)?;
+    if importer.elements.is_empty() {
+        return Err(SvgImportError::Empty);
+    }
+    Ok(Command::Batch {
+        description: "Import SVG",
+        commands: importer
+            .elements
+            .into_iter()
+            .map(|element| Command::AddElement { element })
+            .collect(),
+    })
+}
+
+/// 2D affine transform, as in SVG's `matrix(a b c d e f)`
+#[derive(Clone, Copy, Debug, PartialEq)]
+struct Transform {
+    a: f32,
+    b: f32,
+    c: f32,
+    d: f32,
+    e: f32,
+    f: f32,
+}
+
+impl Transform {
+    const IDENTITY: Self = Self {
+        a: 1.0,
+        b: 0.0,
+        c: 0.0,
+        d: 1.0,
+        e: 0.0,
+        f: 0.0,
+    };
+
+    fn translate(x: f32, y: f32) -> Self {
+        Self {
+            e: x,
+            f: y,
+            ..Self::IDENTITY
+        }
+    }
+
+    fn scale(x: f32, y: f32) -> Self {
+        Self {
+            a: x,
+            d: y,
+            ..Self::IDENTITY
+        }
+    }
+
+    fn rotate(degrees: f32) -> Self {
+        let (sin, cos) = degrees.to_radians().sin_cos();
+        Self {
+            a: cos,
+            b: sin,
+            c: -sin,
+            d: cos,
+            ..Self::IDENTITY
+        }
+    }
+
+    /// `inner` followed by `self`
+    fn then(self, inner: Self) -> Self {
+        Self {
+            a: self.a * inner.a + self.c * inner.b,
+            b: self.b * inner.a + self.d * inner.b,
+            c: self.a * inner.c + self.c * inner.d,
+            d: self.b * inner.c + self.d * inner.d,
+            e: self.a * inner.e + self.c * inner.f + self.e,
+            f: self.b * inner.e + self.d * inner.f + self.f,
+        }
+    }
+
+    fn apply(&self, p: Pos2) -> Pos2 {
+        pos2(
+            self.a * p.x + self.c * p.y + self.e,
+            self.b * p.x + self.d * p.y + self.f,
+        )
+    }
+
+    /// How much lengths grow on average, for line widths
+    fn scale_factor(&self) -> f32 {
+        (self.a * self.d - self.b * self.c).abs().sqrt()
+    }
+
+    /// Parse a transform list such as `translate(10 20) rotate(45)`
+    fn parse(list: &str) -> Option<Self> {
+        let mut transform = Self::IDENTITY;
+        let mut rest = list.trim();
+        while !rest.is_empty() {
+            let (name, after) = rest.split_once('(')?;
+            let (args, after) = after.split_once(')')?;
+            let args = numbers(args);
+            let arg = |i: usize| args.get(i).copied();
+            let next = match name.trim_matches(|c: char| c.is_whitespace() || c == ',') {
+                "matrix" if args.len() == 6 => Self {
+                    a: args[0],
+                    b: args[1],
+                    c: args[2],
+                    d: args[3],
+                    e: args[4],
+                    f: args[5],
+                },
+                "translate" => Self::translate(arg(0)?, arg(1).unwrap_or(0.0)),
+                "scale" => Self::scale(arg(0)?, arg(1).or(arg(0))?),
+                "rotate" => match (arg(0)?, arg(1), arg(2)) {
+                    (angle, Some(x), Some(y)) => Self::translate(x, y)
+                        .then(Self::rotate(angle))
+                        .then(Self::translate(-x, -y)),
+                    (angle, _, _) => Self::rotate(angle),
+                },
+                "skewX" => Self {
+                    c: arg(0)?.to_radians().tan(),
+                    ..Self::IDENTITY
+                },
+                "skewY" => Self {
+                    b: arg(0)?.to_radians().tan(),
+                    ..Self::IDENTITY
+                },
+                _ => return None,
+            };
+            transform = transform.then(next);
+            rest = after.trim_start_matches(|c: char| c.is_whitespace() || c == ',');
+        }
+        Some(transform)
+    }
+}
+
+/// Unmultiplied RGBA color of a fill or outline, or `None` for no paint
+type Paint = Option<[u8; 4]>;
+
+/// Presentation properties, inherited from group to child
+#[derive(Clone, Copy, Debug)]
+struct Style {
+    fill: Paint,
+    stroke: Paint,
+    stroke_width: f32,
+    fill_opacity: f32,
+    stroke_opacity: f32,
+    /// Product of the `opacity` of the element and its groups
+    opacity: f32,
+}
+
+impl Default for Style {
+    fn default() -> Self {
+        Self {
+            fill: Some([0, 0, 0, 255]),
+            stroke: None,
+            stroke_width: 1.0,
+            fill_opacity: 1.0,
+            stroke_opacity: 1.0,
+            opacity: 1.0,
+        }
+    }
+}
+
+impl Style {
+    /// The style of an element with these attributes inside a parent styled `self`
+    fn child(mut self, attributes: &Attributes) -> Self {
+        if let Some(paint) = attributes.get("fill").and_then(parse_paint) {
+            self.fill = paint;
+        }
+        if let Some(paint) = attributes.get("stroke").and_then(parse_paint) {
+            self.stroke = paint;
+        }
+        if let Some(width) = attributes.get("stroke-width").and_then(parse_length) {
+            self.stroke_width = width;
+        }
+        if let Some(opacity) = attributes.get("fill-opacity").and_then(parse_opacity) {
+            self.fill_opacity = opacity;
+        }
+        if let Some(opacity) = attributes.get("stroke-opacity").and_then(parse_opacity) {
+            self.stroke_opacity = opacity;
+        }
+        if let Some(opacity) = attributes.get("opacity").and_then(parse_opacity) {
+            self.opacity *= opacity;
+        }
+        self
+    }
+
+    fn fill_color(&self) -> Option<Color32> {
+        paint_color(self.fill?, self.fill_opacity * self.opacity)
+    }
+
+    fn stroke_color(&self) -> Option<Color32> {
+        if self.stroke_width <= 0.0 {
+            return None;
+        }
+        paint_color(self.stroke?, self.stroke_opacity * self.opacity)
+    }
+}
+
+fn paint_color([r, g, b, a]: [u8; 4], opacity: f32) -> Option<Color32> {
+    let alpha = (a as f32 * opacity).round().clamp(0.0, 255.0) as u8;
+    (alpha > 0).then(|| Color32::from_rgba_unmultiplied(r, g, b, alpha))
+}
+
+/// Attributes of an element by local name, with `style` declarations taking
+/// precedence over presentation attributes as in CSS
+struct Attributes(HashMap<String, String>);
+
+impl Attributes {
+    fn read(start: &BytesStart<'_>) -> Result<Self, SvgImportError> {
+        let mut map = HashMap::new();
+        for attribute in start.attributes() {
+            let attribute = attribute.map_err(|err| SvgImportError::Xml(err.to_string()))?;
+            let name = String::from_utf8_lossy(attribute.key.local_name().as_ref()).into_owned();
+            let value = attribute
+                .unescape_value()
+                .map_err(|err| SvgImportError::Xml(err.to_string()))?;
+            map.insert(name, value.into_owned());
+        }
+        if let Some(style) = map.get("style").cloned() {
+            for declaration in style.split(';') {
+                if let Some((name, value)) = declaration.split_once(':') {
+                    map.insert(name.trim().to_string(), value.trim().to_string());
+                }
+            }
+        }
+        Ok(Self(map))
+    }
+
+    fn get(&self, name: &str) -> Option<&str> {
+        self.0.get(name).map(String::as_str)
+    }
+
+    /// A length attribute, zero when missing
+    fn length(&self, name: &str) -> f32 {
+        self.get(name).and_then(parse_length).unwrap_or(0.0)
+    }
+}
+
+/// One run of connected curves, started by a move
+#[derive(Debug)]
+struct Subpath {
+    nodes: Vec<PathNode>,
+    closed: bool,
+}
+
+impl Subpath {
+    fn is_straight(&self) -> bool {
+        self.nodes
+            .iter()
+            .all(|node| node.handle_in == node.anchor && node.handle_out == node.anchor)
+    }
+}
+
+/// Where the elements read so far go
+struct Importer<'a> {
+    editor_model: &'a EditorModel,
+    center: Pos2,
+    elements: Vec<ElementType>,
+}
+
+impl Importer<'_> {
+    fn read(&mut self, text: &str) -> Result<(), SvgImportError> {
+        let mut reader = quick_xml::Reader::from_str(text);
+        // Transform and style of each open element
+        let mut stack: Vec<(Transform, Style)> = Vec::new();
+        // How deep inside an element that isn't drawn the reader is
+        let mut skipping = 0_usize;
+        loop {
+            let event = reader
+                .read_event()
+                .map_err(|err| SvgImportError::Xml(err.to_string()))?;
+            let (start, is_empty) = match &event {
+                Event::Start(start) => (start, false),
+                Event::Empty(start) => (start, true),
+                Event::End(_) => {
+                    if skipping > 0 {
+                        skipping -= 1;
+                    } else {
+                        stack.pop();
+                    }
+                    continue;
+                }
+                Event::Eof => break,
+                _ => continue,
+            };
+            if skipping > 0 {
+                skipping += usize::from(!is_empty);
+                continue;
+            }
+
+            let name = start.local_name();
+            let name = String::from_utf8_lossy(name.as_ref());
+            let attributes = Attributes::read(start)?;
+            let Some(&(parent_transform, parent_style)) = stack.last() else {
+                if name != "svg" {
+                    return Err(SvgImportError::NotSvg);
+                }
+                let transform = self.canvas_transform(&attributes);
+                if !is_empty {
+                    stack.push((transform, Style::default().child(&attributes)));
+                }
+                continue;
+            };
+
+            let hidden = attributes.get("display") == Some("none")
+                || attributes.get("visibility") == Some("hidden");
+            let drawn = matches!(
+                name.as_ref(),
+                "svg"
+                    | "g"
+                    | "a"
+                    | "switch"
+                    | "path"
+                    | "line"
+                    | "polyline"
+                    | "polygon"
+                    | "rect"
+                    | "circle"
+                    | "ellipse"
+                    | "image"
+            );
+            if hidden || !drawn {
+                skipping += usize::from(!is_empty);
+                continue;
+            }
+
+            let transform = match attributes.get("transform").map(Transform::parse) {
+                Some(Some(transform)) => parent_transform.then(transform),
+                Some(None) => {
+                    log::warn!("Ignoring an SVG transform that couldn't be read");
+                    parent_transform
+                }
+                None => parent_transform,
+            };
+            let style = parent_style.child(&attributes);
+            match name.as_ref() {
+                "image" => self.add_image(&attributes, &transform),
+                shape => {
+                    if let Some(data) = shape_path_data(shape, &attributes) {
+                        self.add_subpaths(parse_path_data(&data), &attributes, &transform, &style);
+                    }
+                }
+            }
+            if !is_empty {
+                stack.push((transform, style));
+            }
+        }
+        Ok(())
+    }
+
+    /// The transform from the root `<svg>`'s view box to the document, putting
+    /// the middle of its canvas at `center`
+    fn canvas_transform(&self, attributes: &Attributes) -> Transform {
+        let view_box = attributes
+            .get("viewBox")
+            .map(numbers)
+            .filter(|v| v.len() == 4 && v[2] > 0.0 && v[3] > 0.0)
+            .map(|v| Rect::from_min_size(pos2(v[0], v[1]), vec2(v[2], v[3])));
+        let width = attributes.get("width").and_then(parse_length);
+        let height = attributes.get("height").and_then(parse_length);
+        let size = vec2(
+            width
+                .or(view_box.map(|v| v.width()))
+                .unwrap_or(DEFAULT_SIZE.x),
+            height
+                .or(view_box.map(|v| v.height()))
+                .unwrap_or(DEFAULT_SIZE.y),
+        );
+
+        let top_left = self.center - size / 2.0;
+        let mut transform = Transform::translate(top_left.x, top_left.y);
+        if let Some(view_box) = view_box {
+            // Fit the view box inside the canvas, centered, as with the
+            // default `preserveAspectRatio`
+            let scale = (size.x / view_box.width()).min(size.y / view_box.height());
+            let offset = (size - view_box.size() * scale) / 2.0 - view_box.min.to_vec2() * scale;
+            transform = transform
+                .then(Transform::translate(offset.x, offset.y))
+                .then(Transform::scale(scale, scale));
+        }
+        match attributes.get("transform").and_then(Transform::parse) {
+            Some(own) => transform.then(own),
+            None => transform,
+        }
+    }
+
+    /// Add fills as shapes, then outlines as strokes where they're straight
+    /// and open, and as paths otherwise
+    fn add_subpaths(
+        &mut self,
+        subpaths: Vec<Subpath>,
+        attributes: &Attributes,
+        transform: &Transform,
+        style: &Style,
+    ) {
+        let subpaths: Vec<Subpath> = subpaths
+            .into_iter()
+            .map(|subpath| Subpath {
+                nodes: subpath
+                    .nodes
+                    .into_iter()
+                    .map(|node| PathNode {
+                        anchor: transform.apply(node.anchor),
+                        handle_in: transform.apply(node.handle_in),
+                        handle_out: transform.apply(node.handle_out),
+                    })
+                    .collect(),
+                closed: subpath.closed,
+            })
+            .collect();
+
+        if let Some(fill) = style.fill_color() {
+            for subpath in &subpaths {
+                // Open subpaths are filled as if closed
+                let points = flatten_nodes(&subpath.nodes, true);
+                if points.len() >= 3 {
+                    let element =
+                        factory::create_shape(self.editor_model.reserve_id(), points, fill);
+                    self.push(element, attributes);
+                }
+            }
+        }
+
+        if let Some(color) = style.stroke_color() {
+            let thickness = style.stroke_width * transform.scale_factor();
+            for subpath in subpaths {
+                let id = self.editor_model.reserve_id();
+                let element = if subpath.is_straight() && !subpath.closed {
+                    let points = subpath.nodes.iter().map(|node| node.anchor).collect();
+                    factory::create_stroke(id, points, thickness, color)
+                } else {
+                    factory::create_path(id, subpath.nodes, subpath.closed, thickness, color)
+                };
+                self.push(element, attributes);
+            }
+        }
+    }
+
+    /// Add an image embedded as a data URL, upright over the box its
+    /// transformed corners cover
+    fn add_image(&mut self, attributes: &Attributes, transform: &Transform) {
+        let Some(href) = attributes.get("href") else {
+            return;
+        };
+        let Some(bytes) = decode_data_url(href) else {
+            log::warn!("Skipping an SVG image that isn't embedded as base64 data");
+            return;
+        };
+        let image = match image::load_from_memory(&bytes) {
+            Ok(image) => image,
+            Err(err) => {
+                log::warn!("Skipping an SVG image that couldn't be decoded: {}", err);
+                return;
+            }
+        };
+
+        let x = attributes.length("x");
+        let y = attributes.length("y");
+        let width = attributes.get("width").and_then(parse_length);
+        let height = attributes.get("height").and_then(parse_length);
+        let size = vec2(
+            width.unwrap_or(image.width() as f32),
+            height.unwrap_or(image.height() as f32),
+        );
+        let rect = Rect::from_min_size(pos2(x, y), size);
+        let corners = [
+            rect.left_top(),
+            rect.right_top(),
+            rect.left_bottom(),
+            rect.right_bottom(),
+        ];
+        let bounds = Rect::from_points(&corners.map(|corner| transform.apply(corner)));
+        if bounds.width() <= 0.0 || bounds.height() <= 0.0 {
+            return;
+        }
+        let element = factory::create_image(
+            self.editor_model.reserve_id(),
+            bytes,
+            bounds.size(),
+            bounds.min,
+        );
+        self.push(element, attributes);
+    }
+
+    /// Add `element`, named after the SVG element's `id`
+    fn push(&mut self, mut element: ElementType, attributes: &Attributes) {
+        if let Some(id) = attributes.get("id") {
+            element.metadata_mut().name = id.to_string();
+        }
+        self.elements.push(element);
+    }
+}
+
+/// Path data tracing a basic shape, as SVG defines them
+fn shape_path_data(shape: &str, attributes: &Attributes) -> Option<String> {
+    let length = |name: &str| attributes.length(name);
+    match shape {
+        "path" => attributes.get("d").map(str::to_string),
+        "line" => Some(format!(
+            "M{} {} L{} {}",
+            length("x1"),
+            length("y1"),
+            length("x2"),
+            length("y2")
+        )),
+        "polyline" | "polygon" => {
+            let points = numbers(attributes.get("points")?);
+            let mut data = String::new();
+            for (i, point) in points.chunks_exact(2).enumerate() {
+                let command = if i == 0 { 'M' } else { 'L' };
+                data.push_str(&format!("{}{} {} ", command, point[0], point[1]));
+            }
+            if shape == "polygon" {
+                data.push('Z');
+            }
+            Some(data)
+        }
+        "rect" => {
+            let (x, y, w, h) = (length("x"), length("y"), length("width"), length("height"));
+            if w <= 0.0 || h <= 0.0 {
+                return None;
+            }
+            let rx = attributes.get("rx").and_then(parse_length);
+            let ry = attributes.get("ry").and_then(parse_length);
+            let rx = rx.or(ry).unwrap_or(0.0).clamp(0.0, w / 2.0);
+            let ry = ry.or(Some(rx)).unwrap_or(0.0).clamp(0.0, h / 2.0);
+            if rx == 0.0 || ry == 0.0 {
+                return Some(format!("M{x} {y} H{} V{} H{x} Z", x + w, y + h));
+            }
+            let arc = format!("A{rx} {ry} 0 0 1");
+            Some(format!(
+                "M{} {y} H{} {arc} {} {} V{} {arc} {} {} H{} {arc} {x} {} V{} {arc} {} {y} Z",
+                x + rx,
+                x + w - rx,
+                x + w,
+                y + ry,
+                y + h - ry,
+                x + w - rx,
+                y + h,
+                x + rx,
+                y + h - ry,
+                y + ry,
+                x + rx,
+            ))
+        }
+        "circle" | "ellipse" => {
+            let (cx, cy) = (length("cx"), length("cy"));
+            let (rx, ry) = if shape == "circle" {
+                (length("r"), length("r"))
+            } else {
+                (length("rx"), length("ry"))
+            };
+            if rx <= 0.0 || ry <= 0.0 {
+                return None;
+            }
+            Some(format!(
+                "M{} {cy} A{rx} {ry} 0 1 1 {} {cy} A{rx} {ry} 0 1 1 {} {cy} Z",
+                cx - rx,
+                cx + rx,
+                cx - rx
+            ))
+        }
+        _ => None,
+    }
+}
+
+/// Parse SVG path data into subpaths of cubic Bézier nodes
+///
+/// Quadratic curves are raised to cubics and elliptical arcs are approximated
+/// by them. Reading stops at the first error, keeping what came before, as
+/// SVG renderers do.
+fn parse_path_data(data: &str) -> Vec<Subpath> {
+    let mut lexer = Lexer::new(data);
+    let mut subpaths = Vec::new();
+    let mut current: Option<Subpath> = None;
+    let mut pos = Pos2::ZERO;
+    let mut start = Pos2::ZERO;
+    // Second control point of the last cubic, or the control point of the
+    // last quadratic, for the shorthand curves that mirror it
+    let mut last_cubic: Option<Pos2> = None;
+    let mut last_quadratic: Option<Pos2> = None;
+    let mut command: Option<u8> = None;
+
+    loop {
+        if let Some(letter) = lexer.command() {
+            command = Some(letter);
+        } else if lexer.at_end() {
+            break;
+        }
+        let Some(letter) = command else {
+            break;
+        };
+        let origin = if letter.is_ascii_lowercase() {
+            pos.to_vec2()
+        } else {
+            Vec2::ZERO
+        };
+        let (previous_cubic, previous_quadratic) = (last_cubic.take(), last_quadratic.take());
+        let subpath = |current: &mut Option<Subpath>, pos: Pos2| {
+            current.get_or_insert_with(|| Subpath {
+                nodes: vec![PathNode::corner(pos)],
+                closed: false,
+            });
+        };
+
+        match letter.to_ascii_uppercase() {
+            b'M' => {
+                let Some(p) = lexer.point() else { break };
+                subpaths.extend(current.take());
+                pos = p + origin;
+                start = pos;
+                current = Some(Subpath {
+                    nodes: vec![PathNode::corner(pos)],
+                    closed: false,
+                });
+                // Further coordinates are lines
+                command = Some(if letter == b'm' { b'l' } else { b'L' });
+            }
+            b'L' | b'H' | b'V' => {
+                let p = match letter.to_ascii_uppercase() {
+                    b'L' => lexer.point().map(|p| p + origin),
+                    b'H' => lexer.number().map(|x| pos2(x + origin.x, pos.y)),
+                    _ => lexer.number().map(|y| pos2(pos.x, y + origin.y)),
+                };
+                let Some(p) = p else { break };
+                subpath(&mut current, pos);
+                line_to(&mut current, p);
+                pos = p;
+            }
+            b'C' | b'S' => {
+                let c1 = if letter.eq_ignore_ascii_case(&b'C') {
+                    let Some(c1) = lexer.point() else { break };
+                    c1 + origin
+                } else {
+                    previous_cubic.map_or(pos, |c| pos + (pos - c))
+                };
+                let (Some(c2), Some(p)) = (lexer.point(), lexer.point()) else {
+                    break;
+                };
+                let (c2, p) = (c2 + origin, p + origin);
+                subpath(&mut current, pos);
+                cubic_to(&mut current, c1, c2, p);
+                last_cubic = Some(c2);
+                pos = p;
+            }
+            b'Q' | b'T' => {
+                let q = if letter.eq_ignore_ascii_case(&b'Q') {
+                    let Some(q) = lexer.point() else { break };
+                    q + origin
+                } else {
+                    previous_quadratic.map_or(pos, |q| pos + (pos - q))
+                };
+                let Some(p) = lexer.point() else { break };
+                let p = p + origin;
+                subpath(&mut current, pos);
+                cubic_to(
+                    &mut current,
+                    pos + (q - pos) * (2.0 / 3.0),
+                    p + (q - p) * (2.0 / 3.0),
+                    p,
+                );
+                last_quadratic = Some(q);
+                pos = p;
+            }
+            b'A' => {
+                let (Some(rx), Some(ry), Some(rotation)) =
+                    (lexer.number(), lexer.number(), lexer.number())
+                else {
+                    break;
+                };
+                let (Some(large), Some(sweep), Some(p)) =
+                    (lexer.flag(), lexer.flag(), lexer.point())
+                else {
+                    break;
+                };
+                let p = p + origin;
+                subpath(&mut current, pos);
+                for [c1, c2, to] in arc_to_cubics(pos, vec2(rx, ry), rotation, large, sweep, p) {
+                    cubic_to(&mut current, c1, c2, to);
+                }
+                pos = p;
+            }
+            b'Z' => {
+                if let Some(mut subpath) = current.take() {
+                    close(&mut subpath);
+                    subpaths.push(subpath);
+                }
+                pos = start;
+                // Z takes no coordinates, so numbers after it are an error
+                command = None;
+            }
+            _ => break,
+        }
+    }
+    subpaths.extend(current);
+    subpaths.retain(|subpath| subpath.nodes.len() >= 2);
+    subpaths
+}
+
+fn line_to(current: &mut Option<Subpath>, p: Pos2) {
+    if let Some(subpath) = current {
+        subpath.nodes.push(PathNode::corner(p));
+    }
+}
+
+fn cubic_to(current: &mut Option<Subpath>, c1: Pos2, c2: Pos2, p: Pos2) {
+    if let Some(subpath) = current {
+        if let Some(last) = subpath.nodes.last_mut() {
+            last.handle_out = c1;
+        }
+        subpath.nodes.push(PathNode {
+            anchor: p,
+            handle_in: c2,
+            handle_out: p,
+        });
+    }
+}
+
+/// Mark `subpath` closed, folding a last node that returns to the start into the first
+fn close(subpath: &mut Subpath) {
+    subpath.closed = true;
+    if subpath.nodes.len() > 2 {
+        let (first, last) = (subpath.nodes[0], subpath.nodes[subpath.nodes.len() - 1]);
+        if first.anchor.distance(last.anchor) < 1e-3 {
+            subpath.nodes.pop();
+            subpath.nodes[0].handle_in = last.handle_in;
+        }
+    }
+}
+
+/// Cubic curves `[control, control, end]` approximating the elliptical arc from
+/// `from` to `to`, following the SVG implementation notes
+fn arc_to_cubics(
+    from: Pos2,
+    radii: Vec2,
+    rotation_degrees: f32,
+    large_arc: bool,
+    sweep: bool,
+    to: Pos2,
+) -> Vec<[Pos2; 3]> {
+    if from == to {
+        return Vec::new();
+    }
+    let (mut rx, mut ry) = (radii.x.abs(), radii.y.abs());
+    if rx == 0.0 || ry == 0.0 {
+        return vec![[from, to, to]];
+    }
+
+    // The endpoints in the ellipse's frame, relative to their midpoint
+    let (sin, cos) = rotation_degrees.to_radians().sin_cos();
+    let half = (from - to) / 2.0;
+    let x1 = cos * half.x + sin * half.y;
+    let y1 = -sin * half.x + cos * half.y;
+
+    // Grow radii too small to reach between the endpoints
+    let lambda = (x1 * x1) / (rx * rx) + (y1 * y1) / (ry * ry);
+    if lambda > 1.0 {
+        rx *= lambda.sqrt();
+        ry *= lambda.sqrt();
+    }
+
+    let numerator = rx * rx * ry * ry - rx * rx * y1 * y1 - ry * ry * x1 * x1;
+    let denominator = rx * rx * y1 * y1 + ry * ry * x1 * x1;
+    let mut coefficient = (numerator / denominator).max(0.0).sqrt();
+    if large_arc == sweep {
+        coefficient = -coefficient;
+    }
+    let cx1 = coefficient * rx * y1 / ry;
+    let cy1 = -coefficient * ry * x1 / rx;
+    let mid = from + (to - from) / 2.0;
+    let center = pos2(cos * cx1 - sin * cy1 + mid.x, sin * cx1 + cos * cy1 + mid.y);
+
+    let angle = |u: Vec2, v: Vec2| (u.x * v.y - u.y * v.x).atan2(u.dot(v));
+    let u = vec2((x1 - cx1) / rx, (y1 - cy1) / ry);
+    let v = vec2((-x1 - cx1) / rx, (-y1 - cy1) / ry);
+    let start_angle = angle(Vec2::X, u);
+    let mut sweep_angle = angle(u, v);
+    if !sweep && sweep_angle > 0.0 {
+        sweep_angle -= std::f32::consts::TAU;
+    } else if sweep && sweep_angle < 0.0 {
+        sweep_angle += std::f32::consts::TAU;
+    }
+
+    // At most a quarter turn per curve keeps the approximation close
+    let segments = (sweep_angle.abs() / std::f32::consts::FRAC_PI_2)
+        .ceil()
+        .max(1.0) as usize;
+    let step = sweep_angle / segments as f32;
+    let handle = 4.0 / 3.0 * (step / 4.0).tan();
+    let point = |t: f32| {
+        let (s, c) = t.sin_cos();
+        pos2(
+            center.x + rx * c * cos - ry * s * sin,
+            center.y + rx * c * sin + ry * s * cos,
+        )
+    };
+    let tangent = |t: f32| {
+        let (s, c) = t.sin_cos();
+        vec2(-rx * s * cos - ry * c * sin, -rx * s * sin + ry * c * cos)
+    };
+    (0..segments)
+        .map(|i| {
+            let t0 = start_angle + step * i as f32;
+            let t1 = t0 + step;
+            let end = if i + 1 == segments { to } else { point(t1) };
+            [
+                point(t0) + tangent(t0) * handle,
+                end - tangent(t1) * handle,
+                end,
+            ]
+        })
+        .collect()
+}
+
+/// Reads the commands, numbers and flags of path data and number lists
+struct Lexer<'a> {
+    bytes: &'a [u8],
+    pos: usize,
+}
+
+impl<'a> Lexer<'a> {
+    fn new(text: &'a str) -> Self {
+        Self {
+            bytes: text.as_bytes(),
+            pos: 0,
+        }
+    }
+
+    fn skip_separators(&mut self) {
+        while self
+            .bytes
+            .get(self.pos)
+            .is_some_and(|b| b.is_ascii_whitespace() || *b == b',')
+        {
+            self.pos += 1;
+        }
+    }
+
+    fn at_end(&mut self) -> bool {
+        self.skip_separators();
+        self.pos >= self.bytes.len()
+    }
+
+    fn command(&mut self) -> Option<u8> {
+        self.skip_separators();
+        let letter = *self.bytes.get(self.pos)?;
+        // 'e' and 'E' only appear inside numbers
+        (letter.is_ascii_alphabetic() && !matches!(letter, b'e' | b'E')).then(|| {
+            self.pos += 1;
+            letter
+        })
+    }
+
+    fn number(&mut self) -> Option<f32> {
+        self.skip_separators();
+        let start = self.pos;
+        let digits = |lexer: &mut Self| {
+            let before = lexer.pos;
+            while lexer.bytes.get(lexer.pos).is_some_and(u8::is_ascii_digit) {
+                lexer.pos += 1;
+            }
+            lexer.pos > before
+        };
+        if matches!(self.bytes.get(self.pos), Some(b'+' | b'-')) {
+            self.pos += 1;
+        }
+        let mut any = digits(self);
+        if self.bytes.get(self.pos) == Some(&b'.') {
+            self.pos += 1;
+            any |= digits(self);
+        }
+        if !any {
+            self.pos = start;
+            return None;
+        }
+        if matches!(self.bytes.get(self.pos), Some(b'e' | b'E')) {
+            let mantissa_end = self.pos;
+            self.pos += 1;
+            if matches!(self.bytes.get(self.pos), Some(b'+' | b'-')) {
+                self.pos += 1;
+            }
+            if !digits(self) {
+                self.pos = mantissa_end;
+            }
+        }
+        std::str::from_utf8(&self.bytes[start..self.pos])
+            .ok()?
+            .parse()
+            .ok()
+    }
+
+    fn point(&mut self) -> Option<Pos2> {
+        Some(pos2(self.number()?, self.number()?))
+    }
+
+    /// An arc flag, which may be written without a separator before the next value
+    fn flag(&mut self) -> Option<bool> {
+        self.skip_separators();
+        let flag = match self.bytes.get(self.pos)? {
+            b'0' => false,
+            b'1' => true,
+            _ => return None,
+        };
+        self.pos += 1;
+        Some(flag)
+    }
+}
+
+/// The numbers in a list separated by spaces or commas
+fn numbers(text: &str) -> Vec<f32> {
+    let mut lexer = Lexer::new(text);
+    std::iter::from_fn(|| lexer.number()).collect()
+}
+
+/// A length in pixels, at 96 per inch; percentages aren't supported
+fn parse_length(text: &str) -> Option<f32> {
+    let text = text.trim();
+    let split = text
+        .find(|c: char| c.is_ascii_alphabetic() || c == '%')
+        .unwrap_or(text.len());
+    let (number, unit) = text.split_at(split);
+    let number: f32 = number.trim().parse().ok()?;
+    let scale = match unit.trim() {
+        "" | "px" => 1.0,
+        "pt" => 96.0 / 72.0,
+        "pc" => 16.0,
+        "in" => 96.0,
+        "cm" => 96.0 / 2.54,
+        "mm" => 96.0 / 25.4,
+        "em" => 16.0,
+        _ => return None,
+    };
+    Some(number * scale)
+}
+
+fn parse_opacity(text: &str) -> Option<f32> {
+    let text = text.trim();
+    let opacity = match text.strip_suffix('%') {
+        Some(percent) => percent.trim().parse::<f32>().ok()? / 100.0,
+        None => text.parse().ok()?,
+    };
+    Some(opacity.clamp(0.0, 1.0))
+}
+
+/// A fill or stroke value: `Some(None)` for `none`, `None` when it can't be read
+fn parse_paint(text: &str) -> Option<Paint> {
+    let text = text.trim();
+    if let Some(reference) = text.strip_prefix("url(") {
+        // Gradients and patterns fall back to their fallback color, or gray
+        let fallback = reference
+            .split_once(')')
+            .map_or("", |(_, rest)| rest.trim());
+        return Some(parse_color(fallback).or(Some([128, 128, 128, 255])));
+    }
+    match text {
+        "none" | "transparent" => Some(None),
+        "currentColor" => Some(Some([0, 0, 0, 255])),
+        _ => parse_color(text).map(Some),
+    }
+}
+
+fn parse_color(text: &str) -> Option<[u8; 4]> {
+    if let Some(hex) = text.strip_prefix('#') {
+        let digit = |i: usize| u8::from_str_radix(hex.get(i..i + 1)?, 16).ok();
+        let byte = |i: usize| u8::from_str_radix(hex.get(i..i + 2)?, 16).ok();
+        return match hex.len() {
+            3 | 4 => Some([
+                digit(0)? * 17,
+                digit(1)? * 17,
+                digit(2)? * 17,
+                if hex.len() == 4 { digit(3)? * 17 } else { 255 },
+            ]),
+            6 | 8 => Some([
+                byte(0)?,
+                byte(2)?,
+                byte(4)?,
+                if hex.len() == 8 { byte(6)? } else { 255 },
+            ]),
+            _ => None,
+        };
+    }
+    if let Some(args) = text
+        .strip_prefix("rgba(")
+        .or_else(|| text.strip_prefix("rgb("))
+    {
+        let args: Vec<&str> = args
+            .strip_suffix(')')?
+            .split(|c: char| c == ',' || c == '/' || c.is_whitespace())
+            .filter(|arg| !arg.is_empty())
+            .collect();
+        let channel = |arg: &str| -> Option<u8> {
+            let value = match arg.strip_suffix('%') {
+                Some(percent) => percent.parse::<f32>().ok()? * 2.55,
+                None => arg.parse::<f32>().ok()?,
+            };
+            Some(value.round().clamp(0.0, 255.0) as u8)
+        };
+        let alpha = match args.get(3) {
+            Some(alpha) => (parse_opacity(alpha)? * 255.0).round() as u8,
+            None => 255,
+        };
+        return Some([
+            channel(args.first()?)?,
+            channel(args.get(1)?)?,
+            channel(args.get(2)?)?,
+            alpha,
+        ]);
+    }
+    let [r, g, b] = match text.to_lowercase().as_str() {
+        "black" => [0, 0, 0],
+        "white" => [255, 255, 255],
+        "red" => [255, 0, 0],
+        "lime" => [0, 255, 0],
+        "green" => [0, 128, 0],
+        "blue" => [0, 0, 255],
+        "yellow" => [255, 255, 0],
+        "cyan" | "aqua" => [0, 255, 255],
+        "magenta" | "fuchsia" => [255, 0, 255],
+        "gray" | "grey" => [128, 128, 128],
+        "silver" => [192, 192, 192],
+        "maroon" => [128, 0, 0],
+        "olive" => [128, 128, 0],
+        "navy" => [0, 0, 128],
+        "purple" => [128, 0, 128],
+        "teal" => [0, 128, 128],
+        "orange" => [255, 165, 0],
+        _ => return None,
+    };
+    Some([r, g, b, 255])
+}
+
+/// The bytes of a base64 `data:` URL
+fn decode_data_url(url: &str) -> Option<Vec<u8>> {
+    let (header, data) = url.trim().strip_prefix("data:")?.split_once(',')?;
+    if !header.ends_with(";base64") {
+        return None;
+    }
+    let data: String = data.chars().filter(|c| !c.is_whitespace()).collect();
+    base64::engine::general_purpose::STANDARD.decode(data).ok()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn imported(svg: &str) -> Vec<ElementType> {
+        let editor_model = EditorModel::new();
+        match import_svg(svg.as_bytes(), &editor_model, pos2(100.0, 100.0)) {
+            Ok(Command::Batch { commands, .. }) => commands
+                .into_iter()
+                .map(|command| match command {
+                    Command::AddElement { element } => element,
+                    other => panic!("unexpected command {:?}", other),
+                })
+                .collect(),
+            other => panic!("unexpected import result {:?}", other),
+        }
+    }
+
+    #[test]
+    fn test_svg_outlines_become_paths_and_strokes_in_place() {
+        let elements = imported(
+            r##"<svg xmlns="http://www.w3.org/2000/svg" width="200" height="100" viewBox="0 0 100 50">
+                <g transform="translate(10 0)" stroke="#ff0000" fill="none" stroke-width="2">
+                    <path id="curve" d="M0 0 C10 0 20 10 20 20 s10 20 20 20"/>
+                    <polyline points="0,40 10,45 20,40"/>
+                    <circle cx="50" cy="25" r="5"/>
+                </g>
+                <defs><rect width="10" height="10"/></defs>
+            </svg>"##,
+        );
+        assert_eq!(elements.len(), 3);
+
+        // The canvas is centered on (100, 100) and the view box doubles everything
+        let ElementType::Path(curve) = &elements[0] else {
+            panic!("expected a path, got {:?}", elements[0]);
+        };
+        assert_eq!(curve.name(), "curve");
+        assert_eq!(curve.thickness(), 4.0);
+        assert_eq!(curve.color(), Color32::RED);
+        let nodes = curve.nodes();
+        assert_eq!(nodes.len(), 3);
+        assert_eq!(nodes[0].anchor, pos2(20.0, 50.0));
+        assert_eq!(nodes[0].handle_out, pos2(40.0, 50.0));
+        assert_eq!(nodes[1].handle_in, pos2(60.0, 70.0));
+        assert_eq!(nodes[1].anchor, pos2(60.0, 90.0));
+        // The shorthand curve mirrors the previous control point
+        assert_eq!(nodes[1].handle_out, pos2(60.0, 110.0));
+        assert_eq!(nodes[2].anchor, pos2(100.0, 130.0));
+
+        assert!(matches!(&elements[1], ElementType::Stroke(_)));
+
+        let ElementType::Path(circle) = &elements[2] else {
+            panic!("expected a path, got {:?}", elements[2]);
+        };
+        assert!(circle.closed());
+        assert_eq!(circle.nodes().len(), 4);
+        let bounds = Rect::from_points(&flatten_nodes(circle.nodes(), true));
+        assert!((bounds.center() - pos2(120.0, 100.0)).length() < 0.01);
+        assert!((bounds.width() - 20.0).abs() < 0.1);
+    }
+
+    #[test]
+    fn test_svg_fills_and_embedded_images() {
+        let mut png = Vec::new();
+        image::RgbaImage::new(4, 2)
+            .write_to(
+                &mut std::io::Cursor::new(&mut png),
+                image::ImageOutputFormat::Png,
+            )
+            .unwrap();
+        let href = base64::engine::general_purpose::STANDARD.encode(&png);
+        let elements = imported(&format!(
+            r#"<svg width="100" height="100">
+                <rect x="10" y="10" width="20" height="20" style="fill: rgb(0, 0, 255); opacity: 0.5"/>
+                <image x="50" y="50" width="8" height="4" transform="scale(2)"
+                       xlink:href="data:image/png;base64,{href}"/>
+                <image href="picture.png" width="10" height="10"/>
+            </svg>"#
+        ));
+        assert_eq!(elements.len(), 2);
+        assert!(matches!(&elements[0], ElementType::Shape(_)));
+        let rect = elements[0].rect();
+        assert!((rect.min - pos2(60.0, 60.0)).length() < 1e-3);
+        assert!((rect.max - pos2(80.0, 80.0)).length() < 1e-3);
+        assert!(matches!(&elements[1], ElementType::Image(_)));
+        assert_eq!(
+            elements[1].rect(),
+            Rect::from_min_size(pos2(150.0, 150.0), vec2(16.0, 8.0))
+        );
+
+        let editor_model = EditorModel::new();
+        assert!(matches!(
+            import_svg(b"<html></html>", &editor_model, Pos2::ZERO),
+            Err(SvgImportError::NotSvg)
+        ));
+        assert!(matches!(
+            import_svg(b"<svg><text>Hi</text></svg>", &editor_model, Pos2::ZERO),
+            Err(SvgImportError::Empty)
+        ));
+    }
+
+    #[test]
+    fn test_path_data_shorthand() {
+        let subpaths = parse_path_data("M1.5.5l2-1e1h3V4zm1 1 2 2");
+        assert_eq!(subpaths.len(), 2);
+        assert!(subpaths[0].closed);
+        let anchors: Vec<Pos2> = subpaths[0].nodes.iter().map(|node| node.anchor).collect();
+        assert_eq!(
+            anchors,
+            vec![
+                pos2(1.5, 0.5),
+                pos2(3.5, -9.5),
+                pos2(6.5, -9.5),
+                pos2(6.5, 4.0)
+            ]
+        );
+        // After closing, relative moves start from the start of the subpath
+        assert_eq!(subpaths[1].nodes[0].anchor, pos2(2.5, 1.5));
+        assert_eq!(subpaths[1].nodes[1].anchor, pos2(4.5, 3.5));
+        assert!(subpaths[1].is_straight());
+    }
+}
@@ -0,0 +1,485 @@
+//! Turning stroke polylines into triangle meshes
+//!
+//! Drawing a stroke as a mesh keeps it sharp at any zoom and skips rasterizing
+//! a texture for it, which gets slow for long or thick strokes. Stroke textures
+//! are filled from the same mesh, so both look the same.
+
+use crate::arrowhead::{ArrowheadShape, with_arrowheads};
+use crate::element::{LineCap, LineJoin, MAX_TAPER, StrokeStyle};
+use egui::emath::lerp;
+use egui::{Color32, ColorImage, Mesh, Pos2, Vec2};
+
+/// Interior points whose segments turn less than this (as the cosine of the
+/// angle between them) are close enough to straight to need no join
+const STRAIGHT_JOIN_COS: f32 = 0.9995;
+
+/// Longest a miter join gets, in half-thicknesses from its corner, before it's
+/// beveled instead; the same limit SVG uses by default, and within the padding
+/// of stroke textures
+pub const MITER_LIMIT: f32 = 4.0;
+
+/// How many triangles to use for a full circle of the given radius
+fn circle_segments(radius: f32) -> usize {
+    (radius.sqrt() * 6.0).ceil().clamp(8.0, 64.0) as usize
+}
+
+/// Tessellate a polyline of the given thickness, with round joins and caps
+///
+/// Triangles overlap at the joins, so the mesh only looks right for opaque
+/// colors.
+pub fn stroke_mesh(points: &[Pos2], thickness: f32, color: Color32) -> Mesh {
+    styled_stroke_mesh(points, thickness, color, &StrokeStyle::default())
+}
+
+/// Tessellate a polyline of the given thickness with the taper, caps, joins,
+/// dashes and arrowheads of `style`
+///
+/// Like [`stroke_mesh`], only right for opaque colors. A single point is a dot
+/// whatever the caps, so clicks still leave a mark.
+pub fn styled_stroke_mesh(
+    points: &[Pos2],
+    thickness: f32,
+    color: Color32,
+    style: &StrokeStyle,
+) -> Mesh {
+    let mut mesh = Mesh::default();
+    let mut points = points.to_vec();
+    points.dedup();
+    let radius = thickness / 2.0;
+    if points.is_empty() || radius <= 0.0 {
+        return mesh;
+    }
+    if points.len() == 1 {
+        add_circle(&mut mesh, points[0], radius, circle_segments(radius), color);
+        return mesh;
+    }
+    let (mut points, heads) =
+        with_arrowheads(&points, thickness, style.start_arrow, style.end_arrow);
+    points.dedup();
+    for head in heads {
+        match head {
+            ArrowheadShape::Polygon(corners) => {
+                let first = mesh.vertices.len() as u32;
+                for &corner in &corners {
+                    mesh.colored_vertex(corner, color);
+                }
+                for i in 1..corners.len() as u32 - 1 {
+                    mesh.add_triangle(first, first + i, first + i + 1);
+                }
+            }
+            ArrowheadShape::Circle { center, radius } => {
+                add_circle(&mut mesh, center, radius, circle_segments(radius), color);
+            }
+        }
+    }
+    let radii = tapered_radii(&points, radius, style);
+
+    let intervals = style.dash.intervals();
+    if intervals.is_empty() {
+        add_polyline(&mut mesh, &points, &radii, style, color);
+    } else {
+        for (points, radii) in dashes(&points, &radii, intervals, thickness) {
+            add_polyline(&mut mesh, &points, &radii, style, color);
+        }
+    }
+    mesh
+}
+
+/// Add one unbroken run of a line, with half its thickness at each point
+///
+/// A run of a single point is a dash of no length, which only round caps show.
+fn add_polyline(
+    mesh: &mut Mesh,
+    points: &[Pos2],
+    radii: &[f32],
+    style: &StrokeStyle,
+    color: Color32,
+) {
+    // A quad along every segment, narrowing where the stroke tapers
+    for (pair, widths) in points.windows(2).zip(radii.windows(2)) {
+        let normal = (pair[1] - pair[0]).normalized().rot90();
+        let first = mesh.vertices.len() as u32;
+        mesh.colored_vertex(pair[0] + normal * widths[0], color);
+        mesh.colored_vertex(pair[0] - normal * widths[0], color);
+        mesh.colored_vertex(pair[1] + normal * widths[1], color);
+        mesh.colored_vertex(pair[1] - normal * widths[1], color);
+        mesh.add_triangle(first, first + 1, first + 2);
+        mesh.add_triangle(first + 1, first + 3, first + 2);
+    }
+
+    if style.cap == LineCap::Round {
+        let last = points.len() - 1;
+        for i in std::iter::once(0).chain((last > 0).then_some(last)) {
+            if radii[i] > 0.0 {
+                add_circle(mesh, points[i], radii[i], circle_segments(radii[i]), color);
+            }
+        }
+    }
+
+    // Joins wherever the line turns
+    for i in 1..points.len() - 1 {
+        let (point, radius) = (points[i], radii[i]);
+        let incoming = (point - points[i - 1]).normalized();
+        let outgoing = (points[i + 1] - point).normalized();
+        if radius <= 0.0 || incoming.dot(outgoing) > STRAIGHT_JOIN_COS {
+            continue;
+        }
+        match style.join {
+            LineJoin::Round => {
+                add_circle(mesh, point, radius, circle_segments(radius), color);
+            }
+            LineJoin::Miter | LineJoin::Bevel => {
+                add_corner(mesh, point, radius, incoming, outgoing, style.join, color);
+            }
+        }
+    }
+}
+
+/// The dashes a line breaks into, each as its points and half its thickness
+/// at them
+///
+/// `intervals` are dash and gap lengths in turn, in multiples of `thickness`,
+/// with some length in them; `points` has no repeats.
+fn dashes(
+    points: &[Pos2],
+    radii: &[f32],
+    intervals: &[f32],
+    thickness: f32,
+) -> Vec<(Vec<Pos2>, Vec<f32>)> {
+    let mut dashes = Vec::new();
+    let mut dash = Some((vec![points[0]], vec![radii[0]]));
+    let mut index = 0;
+    let mut left = intervals[0] * thickness;
+    for (pair, widths) in points.windows(2).zip(radii.windows(2)) {
+        let length = pair[0].distance(pair[1]);
+        let mut along = 0.0;
+        // Every interval ending within the segment flips between dash and gap
+        while along + left < length {
+            along += left;
+            let t = along / length;
+            let (point, radius) = (pair[0].lerp(pair[1], t), lerp(widths[0]..=widths[1], t));
+            match dash.take() {
+                Some((mut dash_points, mut dash_radii)) => {
+                    if dash_points.last() != Some(&point) {
+                        dash_points.push(point);
+                        dash_radii.push(radius);
+                    }
+                    dashes.push((dash_points, dash_radii));
+                }
+                None => dash = Some((vec![point], vec![radius])),
+            }
+            index = (index + 1) % intervals.len();
+            left = intervals[index] * thickness;
+        }
+        left -= length - along;
+        if let Some((dash_points, dash_radii)) = &mut dash {
+            if dash_points.last() != Some(&pair[1]) {
+                dash_points.push(pair[1]);
+                dash_radii.push(widths[1]);
+            }
+        }
+    }
+    dashes.extend(dash);
+    dashes
+}
+
+/// Half the thickness at each point, narrowing to nothing over the tapers
+fn tapered_radii(points: &[Pos2], radius: f32, style: &StrokeStyle) -> Vec<f32> {
+    let mut lengths = Vec::with_capacity(points.len());
+    let mut length = 0.0;
+    lengths.push(0.0);
+    for pair in points.windows(2) {
+        length += pair[0].distance(pair[1]);
+        lengths.push(length);
+    }
+    let taper_start = style.taper_start.clamp(0.0, MAX_TAPER) * length;
+    let taper_end = style.taper_end.clamp(0.0, MAX_TAPER) * length;
+    let ramp = |distance: f32, taper: f32| {
+        if taper > 0.0 {
+            (distance / taper).min(1.0)
+        } else {
+            1.0
+        }
+    };
+    lengths
+        .iter()
+        .map(|&along| radius * ramp(along, taper_start).min(ramp(length - along, taper_end)))
+        .collect()
+}
+
+/// Fill the outside of a corner with a bevel, or a miter unless it would stick
+/// out past [`MITER_LIMIT`]
+fn add_corner(
+    mesh: &mut Mesh,
+    point: Pos2,
+    radius: f32,
+    incoming: Vec2,
+    outgoing: Vec2,
+    join: LineJoin,
+    color: Color32,
+) {
+    // The outer side is the one the line turns away from
+    let mut side_in = incoming.rot90();
+    let mut side_out = outgoing.rot90();
+    if side_in.dot(outgoing - incoming) > 0.0 {
+        side_in = -side_in;
+        side_out = -side_out;
+    }
+
+    let first = mesh.vertices.len() as u32;
+    mesh.colored_vertex(point, color);
+    mesh.colored_vertex(point + side_in * radius, color);
+    mesh.colored_vertex(point + side_out * radius, color);
+    mesh.add_triangle(first, first + 1, first + 2);
+
+    let bisector = (side_in + side_out).normalized();
+    let cos_half = bisector.dot(side_in);
+    if join == LineJoin::Miter && cos_half * MITER_LIMIT >= 1.0 {
+        mesh.colored_vertex(point + bisector * (radius / cos_half), color);
+        mesh.add_triangle(first + 1, first + 3, first + 2);
+    }
+}
+
+/// Add a filled circle as a triangle fan
+fn add_circle(mesh: &mut Mesh, center: Pos2, radius: f32, segments: usize, color: Color32) {
+    let first = mesh.vertices.len() as u32;
+    mesh.colored_vertex(center, color);
+    for i in 0..segments {
+        let angle = i as f32 / segments as f32 * std::f32::consts::TAU;
+        mesh.colored_vertex(center + Vec2::angled(angle) * radius, color);
+    }
+    for i in 0..segments as u32 {
+        let next = (i + 1) % segments as u32;
+        mesh.add_triangle(first, first + 1 + i, first + 1 + next);
+    }
+}
+
+/// Fill the triangles of `mesh` into `image`, in the color of each triangle's
+/// first vertex, with positions in pixels
+///
+/// Pixels take the color outright rather than blending, so overlapping
+/// triangles don't build up. Triangles are grown by half a pixel so thin
+/// strokes don't break up into gaps.
+pub fn rasterize_mesh(mesh: &Mesh, image: &mut ColorImage) {
+    let [width, height] = image.size;
+    for triangle in mesh.indices.chunks_exact(3) {
+        let [a, b, c] = [0, 1, 2].map(|i| mesh.vertices[triangle[i] as usize].pos);
+        let color = mesh.vertices[triangle[0] as usize].color;
+        let area = (b - a).x * (c - a).y - (b - a).y * (c - a).x;
+        if area.abs() < f32::EPSILON {
+            continue;
+        }
+        // Edges facing the same way whichever way the triangle winds, each
+        // with the distance from it that's still inside
+        let edges = [(a, b), (b, c), (c, a)].map(|(from, to)| {
+            let (from, to) = if area > 0.0 { (from, to) } else { (to, from) };
+            let length = from.distance(to).max(f32::EPSILON);
+            (from, (to - from) / length)
+        });
+
+        let min = a.min(b).min(c) - Vec2::splat(0.5);
+        let max = a.max(b).max(c) + Vec2::splat(0.5);
+        let x_range =
+            (min.x.floor().max(0.0) as usize)..(max.x.ceil().max(0.0) as usize).min(width);
+        let y_range =
+            (min.y.floor().max(0.0) as usize)..(max.y.ceil().max(0.0) as usize).min(height);
+        for y in y_range {
+            for x in x_range.clone() {
+                let center = Pos2::new(x as f32 + 0.5, y as f32 + 0.5);
+                let inside = edges.iter().all(|(from, direction)| {
+                    let offset = center - *from;
+                    direction.x * offset.y - direction.y * offset.x >= -0.5
+                });
+                if inside {
+                    image.pixels[y * width + x] = color;
+                }
+            }
+        }
+    }
+}
+
+/// Turn `shapes` clockwise by `rotation` radians about `center`, in place
+///
+/// Anything but a mesh is tessellated first, since rectangles and text can't
+/// be turned as they are, at `pixels_per_point` physical pixels to a unit of
+/// `shapes`.
+pub fn rotate_shapes(
+    ctx: &egui::Context,
+    shapes: &mut [egui::Shape],
+    center: egui::Pos2,
+    rotation: f32,
+    pixels_per_point: f32,
+) {
+    let rotation = egui::emath::Rot2::from_angle(rotation);
+    let mut tessellator = None;
+    for shape in shapes {
+        let mut mesh = match std::mem::replace(shape, egui::Shape::Noop) {
+            egui::Shape::Mesh(mesh) => mesh,
+            shape => {
+                let tessellator = tessellator.get_or_insert_with(|| {
+                    let (font_size, discs) = ctx.fonts(|fonts| {
+                        (
+                            fonts.font_image_size(),
+                            fonts.texture_atlas().lock().prepared_discs(),
+                        )
+                    });
+                    let options = ctx.tessellation_options(|options| *options);
+                    egui::epaint::Tessellator::new(pixels_per_point, options, font_size, discs)
+                });
+                let mut mesh = egui::Mesh::default();
+                tessellator.tessellate_shape(shape, &mut mesh);
+                mesh
+            }
+        };
+        for vertex in &mut mesh.vertices {
+            vertex.pos = center + rotation * (vertex.pos - center);
+        }
+        *shape = egui::Shape::mesh(mesh);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::element::DashPattern;
+
+    #[test]
+    fn test_stroke_mesh_covers_the_line_and_its_caps() {
+        let points = [
+            Pos2::new(0.0, 0.0),
+            Pos2::new(50.0, 0.0),
+            Pos2::new(100.0, 0.0),
+            Pos2::new(100.0, 50.0),
+        ];
+        let mesh = stroke_mesh(&points, 10.0, Color32::RED);
+        assert!(mesh.is_valid());
+        assert!(mesh.vertices.iter().all(|v| v.color == Color32::RED));
+
+        // Round caps reach past the ends; the straight middle point gets no join
+        let bounds = mesh.calc_bounds();
+        assert!((bounds.min.x + 5.0).abs() < 0.01 && (bounds.max.y - 55.0).abs() < 0.5);
+        let segments = circle_segments(5.0);
+        assert_eq!(mesh.vertices.len(), 3 * 4 + 3 * (segments + 1));
+
+        // A single point is a dot
+        let dot = stroke_mesh(&points[..1], 10.0, Color32::RED);
+        assert_eq!(dot.indices.len(), segments * 3);
+        assert!(stroke_mesh(&[], 10.0, Color32::RED).is_empty());
+    }
+
+    #[test]
+    fn test_styled_mesh_tapers_and_caps() {
+        let points = [Pos2::new(0.0, 0.0), Pos2::new(100.0, 0.0)];
+        let flat = StrokeStyle {
+            cap: LineCap::Flat,
+            ..Default::default()
+        };
+        let bounds = styled_stroke_mesh(&points, 10.0, Color32::RED, &flat).calc_bounds();
+        assert_eq!((bounds.min.x, bounds.max.x), (0.0, 100.0));
+        assert_eq!((bounds.min.y, bounds.max.y), (-5.0, 5.0));
+
+        // Tapering the whole stroke from the middle out narrows both ends to points
+        let points = [
+            Pos2::new(0.0, 0.0),
+            Pos2::new(50.0, 0.0),
+            Pos2::new(100.0, 0.0),
+        ];
+        let tapered = StrokeStyle {
+            taper_start: 0.5,
+            taper_end: 0.5,
+            ..flat
+        };
+        assert_eq!(tapered_radii(&points, 5.0, &tapered), [0.0, 5.0, 0.0]);
+
+        // A right angle mitered reaches the outer corner of the square around
+        // it; beveled, it's cut across
+        let corner = [
+            Pos2::new(0.0, 0.0),
+            Pos2::new(50.0, 0.0),
+            Pos2::new(50.0, 50.0),
+        ];
+        let reaches_corner = |join| {
+            let style = StrokeStyle { join, ..flat };
+            let mesh = styled_stroke_mesh(&corner, 10.0, Color32::RED, &style);
+            let outer = Pos2::new(55.0, -5.0);
+            mesh.vertices.iter().any(|v| v.pos.distance(outer) < 0.01)
+        };
+        assert!(reaches_corner(LineJoin::Miter));
+        assert!(!reaches_corner(LineJoin::Bevel));
+    }
+
+    #[test]
+    fn test_dashes_follow_the_pattern() {
+        let points = [
+            Pos2::new(0.0, 0.0),
+            Pos2::new(10.0, 0.0),
+            Pos2::new(20.0, 0.0),
+        ];
+        let radii = [1.0; 3];
+
+        // Dashes 3 long with gaps of 2, carried across the middle point
+        let pieces = dashes(&points, &radii, &[1.5, 1.0], 2.0);
+        let spans: Vec<(f32, f32)> = pieces
+            .iter()
+            .map(|(points, _)| (points[0].x, points.last().unwrap().x))
+            .collect();
+        assert_eq!(spans, [(0.0, 3.0), (5.0, 8.0), (10.0, 13.0), (15.0, 18.0)]);
+
+        // Dots are single points, which only round caps draw
+        let dots = dashes(&points, &radii, &[0.0, 2.0], 2.0);
+        assert_eq!(dots.len(), 5);
+        assert!(dots.iter().all(|(points, _)| points.len() == 1));
+        let dotted = |cap| {
+            let style = StrokeStyle {
+                cap,
+                dash: DashPattern::Dotted,
+                ..Default::default()
+            };
+            styled_stroke_mesh(&points, 2.0, Color32::RED, &style)
+        };
+        assert_eq!(
+            dotted(LineCap::Round).indices.len(),
+            5 * circle_segments(1.0) * 3
+        );
+        assert!(dotted(LineCap::Flat).is_empty());
+    }
+
+    #[test]
+    fn test_rasterized_mesh_fills_its_triangles() {
+        let mut image = ColorImage::new([20, 20], Color32::TRANSPARENT);
+        let style = StrokeStyle {
+            cap: LineCap::Flat,
+            ..Default::default()
+        };
+        let points = [Pos2::new(2.0, 10.0), Pos2::new(18.0, 10.0)];
+        rasterize_mesh(
+            &styled_stroke_mesh(&points, 4.0, Color32::RED, &style),
+            &mut image,
+        );
+        assert_eq!(image.pixels[10 * 20 + 10], Color32::RED);
+        assert_eq!(image.pixels[10 * 20], Color32::TRANSPARENT);
+        assert_eq!(image.pixels[3 * 20 + 10], Color32::TRANSPARENT);
+    }
+
+    #[test]
+    fn test_rotating_turns_the_shapes_in_place() {
+        let points = [Pos2::new(0.0, 0.0), Pos2::new(10.0, 0.0)];
+        let mesh = stroke_mesh(&points, 2.0, Color32::RED);
+        let mut shapes = [egui::Shape::Noop, egui::Shape::mesh(mesh)];
+        let quarter = std::f32::consts::FRAC_PI_2;
+        rotate_shapes(
+            &egui::Context::default(),
+            &mut shapes[1..],
+            Pos2::ZERO,
+            quarter,
+            1.0,
+        );
+
+        assert!(matches!(shapes[0], egui::Shape::Noop));
+        let egui::Shape::Mesh(mesh) = &shapes[1] else {
+            panic!("rotated shapes are meshes");
+        };
+        let bounds = mesh.calc_bounds();
+        assert!(bounds.height() > 10.0 && bounds.width() < 4.0);
+    }
+}
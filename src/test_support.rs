@@ -0,0 +1,416 @@
+//! Running the editor headlessly in tests
+//!
+//! [`TestHarness`] shows the central panel in a bare [`egui::Context`] and
+//! feeds it pointer and key events the way the window would. Tests then go
+//! from input to [`EditorModel`] state through the same tools, commands and
+//! undo history a user does.
+//!
+//! [`TestHarness::screenshot`] paints the last frame in software, for tests
+//! comparing what the canvas shows against stored images.
+
+use crate::command::CommandHistory;
+use crate::panels::central_panel;
+use crate::renderer::Renderer;
+use crate::state::EditorModel;
+use crate::tools::new_tool;
+use crate::view::View;
+use egui::epaint::{ClippedShape, ImageData, Primitive, TextureId};
+use egui::{Color32, ColorImage, Event, Key, Modifiers, PointerButton, Pos2, Rect};
+use std::collections::HashMap;
+
+/// Size of the screen the harness shows the canvas on
+const SCREEN_SIZE: egui::Vec2 = egui::vec2(800.0, 600.0);
+/// Time between frames
+const FRAME_TIME: f64 = 1.0 / 60.0;
+/// Frames a drag moves the pointer through on its way
+const DRAG_STEPS: usize = 8;
+
+/// The editor running in a bare egui context, driven by synthesized input
+///
+/// Positions are on screen, which in the default view are document positions too.
+pub struct TestHarness {
+    pub ctx: egui::Context,
+    pub editor_model: EditorModel,
+    pub command_history: CommandHistory,
+    pub renderer: Renderer,
+    pub view: View,
+    // Events for the next frame
+    events: Vec<Event>,
+    modifiers: Modifiers,
+    time: f64,
+    // What the last frame painted, and the textures it painted with
+    shapes: Vec<ClippedShape>,
+    textures: HashMap<TextureId, ColorImage>,
+}
+
+impl TestHarness {
+    /// An empty document, with the first frame already shown
+    pub fn new() -> Self {
+        let ctx = egui::Context::default();
+        let mut harness = Self {
+            renderer: Renderer::headless(ctx.clone()),
+            ctx,
+            editor_model: EditorModel::new(),
+            command_history: CommandHistory::new(),
+            view: View::default(),
+            events: Vec::new(),
+            modifiers: Modifiers::NONE,
+            time: 0.0,
+            shapes: Vec::new(),
+            textures: HashMap::new(),
+        };
+        harness.run_frame();
+        harness
+    }
+
+    /// Switch to the tool named `name`, as in [`new_tool`]
+    pub fn set_tool(&mut self, name: &str) {
+        let tool = new_tool(name).unwrap_or_else(|| panic!("no tool named {}", name));
+        self.editor_model.update_tool(|_| tool);
+    }
+
+    /// Hold `modifiers` down for the events that follow
+    pub fn set_modifiers(&mut self, modifiers: Modifiers) {
+        self.modifiers = modifiers;
+    }
+
+    /// Show one frame, handing the events queued since the last one to the editor
+    pub fn run_frame(&mut self) {
+        let input = egui::RawInput {
+            screen_rect: Some(Rect::from_min_size(Pos2::ZERO, SCREEN_SIZE)),
+            time: Some(self.time),
+            predicted_dt: FRAME_TIME as f32,
+            modifiers: self.modifiers,
+            events: std::mem::take(&mut self.events),
+            ..Default::default()
+        };
+        let ctx = self.ctx.clone();
+        let output = ctx.run(input, |ctx| {
+            // As the app's update does, so elements drawn last frame are drawn again
+            self.renderer.begin_frame();
+            central_panel(
+                &mut self.editor_model,
+                &mut self.command_history,
+                &mut self.renderer,
+                &self.view,
+                ctx,
+            );
+            self.renderer.end_frame(ctx);
+        });
+        for (id, delta) in output.textures_delta.set {
+            let image = match delta.image {
+                ImageData::Color(image) => (*image).clone(),
+                ImageData::Font(font) => ColorImage {
+                    size: font.size,
+                    pixels: font.srgba_pixels(None).collect(),
+                },
+            };
+            match (delta.pos, self.textures.get_mut(&id)) {
+                (Some([x, y]), Some(texture)) => {
+                    let width = texture.size[0];
+                    for (row, pixels) in image.pixels.chunks_exact(image.size[0]).enumerate() {
+                        let start = (y + row) * width + x;
+                        texture.pixels[start..start + pixels.len()].copy_from_slice(pixels);
+                    }
+                }
+                _ => {
+                    self.textures.insert(id, image);
+                }
+            }
+        }
+        self.shapes = output.shapes;
+        // Freed only after the frame is painted, as a real painter would
+        for id in output.textures_delta.free {
+            self.textures.remove(&id);
+        }
+        self.time += FRAME_TIME;
+    }
+
+    /// Paint the last frame in software, one pixel per point
+    ///
+    /// Triangles are filled without anti-aliasing beyond egui's own feathering,
+    /// sampling textures at the nearest texel, so the result is close to what
+    /// a GPU shows but not exact.
+    pub fn screenshot(&self) -> ColorImage {
+        let size = [SCREEN_SIZE.x as usize, SCREEN_SIZE.y as usize];
+        let mut image = ColorImage::new(size, Color32::BLACK);
+        for clipped in self.ctx.tessellate(self.shapes.clone(), 1.0) {
+            if let Primitive::Mesh(mesh) = &clipped.primitive {
+                if let Some(texture) = self.textures.get(&mesh.texture_id) {
+                    paint_mesh(&mut image, mesh, texture, clipped.clip_rect);
+                }
+            }
+        }
+        image
+    }
+
+    /// Move the pointer to `pos` over one frame
+    pub fn move_pointer(&mut self, pos: Pos2) {
+        self.events.push(Event::PointerMoved(pos));
+        self.run_frame();
+    }
+
+    /// Press the primary button at `pos`
+    pub fn press(&mut self, pos: Pos2) {
+        self.move_pointer(pos);
+        self.push_button(pos, true);
+        self.run_frame();
+    }
+
+    /// Let go of the primary button at `pos`
+    pub fn release(&mut self, pos: Pos2) {
+        self.move_pointer(pos);
+        self.push_button(pos, false);
+        self.run_frame();
+    }
+
+    /// Press at `pos` and let go straight away
+    ///
+    /// Waits long enough afterwards that a following click isn't a double click.
+    pub fn click(&mut self, pos: Pos2) {
+        self.press(pos);
+        self.release(pos);
+        self.time += 1.0;
+    }
+
+    /// Press at `from`, move to `to` over several frames and let go there
+    pub fn drag(&mut self, from: Pos2, to: Pos2) {
+        self.press(from);
+        for step in 1..=DRAG_STEPS {
+            self.move_pointer(from.lerp(to, step as f32 / DRAG_STEPS as f32));
+        }
+        self.release(to);
+    }
+
+    /// Press and let go of `key` in one frame
+    pub fn key(&mut self, key: Key) {
+        for pressed in [true, false] {
+            self.events.push(Event::Key {
+                key,
+                physical_key: None,
+                pressed,
+                repeat: false,
+                modifiers: self.modifiers,
+            });
+        }
+        self.run_frame();
+    }
+
+    fn push_button(&mut self, pos: Pos2, pressed: bool) {
+        self.events.push(Event::PointerButton {
+            pos,
+            button: PointerButton::Primary,
+            pressed,
+            modifiers: self.modifiers,
+        });
+    }
+}
+
+impl Default for TestHarness {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+/// Blend the triangles of `mesh` into `image` as a GPU would, within `clip_rect`
+///
+/// Vertex colors and texels are premultiplied, as egui gives them. A pixel on
+/// an edge two triangles share is only filled by one of them, so translucent
+/// fills don't darken along their seams.
+fn paint_mesh(image: &mut ColorImage, mesh: &egui::Mesh, texture: &ColorImage, clip_rect: Rect) {
+    let [width, height] = image.size;
+    let clip = clip_rect.intersect(Rect::from_min_size(
+        Pos2::ZERO,
+        egui::vec2(width as f32, height as f32),
+    ));
+    if !clip.is_positive() {
+        return;
+    }
+    for triangle in mesh.indices.chunks_exact(3) {
+        let vertices = [0, 1, 2].map(|i| &mesh.vertices[triangle[i] as usize]);
+        let [a, b, c] = vertices.map(|vertex| vertex.pos);
+        let area = cross(b - a, c - a);
+        if area.abs() < f32::EPSILON {
+            continue;
+        }
+        // Each edge, going around the triangle the same way whichever way it
+        // winds; the corner opposite an edge is weighted by the side of it a
+        // pixel is on
+        let edges = [(b, c), (c, a), (a, b)].map(
+            |(from, to)| {
+                if area > 0.0 { (from, to) } else { (to, from) }
+            },
+        );
+
+        let min = a.min(b).min(c).max(clip.min);
+        let max = a.max(b).max(c).min(clip.max);
+        for y in (min.y.floor() as usize)..(max.y.ceil() as usize).min(height) {
+            for x in (min.x.floor() as usize)..(max.x.ceil() as usize).min(width) {
+                let center = Pos2::new(x as f32 + 0.5, y as f32 + 0.5);
+                if !clip.contains(center) {
+                    continue;
+                }
+                let mut weights = [0.0; 3];
+                let inside = edges.iter().zip(&mut weights).all(|(&(from, to), weight)| {
+                    let direction = to - from;
+                    *weight = cross(direction, center - from) / area.abs();
+                    // Of the two triangles sharing an edge, which go along it
+                    // opposite ways, only one takes the pixels on it
+                    *weight > 0.0
+                        || (*weight == 0.0
+                            && (direction.y > 0.0 || (direction.y == 0.0 && direction.x < 0.0)))
+                });
+                if !inside {
+                    continue;
+                }
+
+                let mut color = [0.0_f32; 4];
+                let mut uv = egui::Vec2::ZERO;
+                for (vertex, weight) in vertices.iter().zip(weights) {
+                    for (channel, value) in color.iter_mut().zip(vertex.color.to_array()) {
+                        *channel += value as f32 * weight;
+                    }
+                    uv += vertex.uv.to_vec2() * weight;
+                }
+                let texel = sample_nearest(texture, uv);
+                let source = color.map(|channel| channel / 255.0);
+                let texel = texel.to_array().map(|channel| channel as f32 / 255.0);
+                let pixel = &mut image.pixels[y * width + x];
+                let below = pixel.to_array();
+                let alpha = source[3] * texel[3];
+                let blended: [u8; 4] = std::array::from_fn(|i| {
+                    let value = source[i] * texel[i] * 255.0 + below[i] as f32 * (1.0 - alpha);
+                    value.round().clamp(0.0, 255.0) as u8
+                });
+                *pixel = Color32::from_rgba_premultiplied(
+                    blended[0], blended[1], blended[2], blended[3],
+                );
+            }
+        }
+    }
+}
+
+/// The 2D cross product of `a` and `b`
+fn cross(a: egui::Vec2, b: egui::Vec2) -> f32 {
+    a.x * b.y - a.y * b.x
+}
+
+/// The texel of `texture` at `uv`, from 0 to 1 across it
+fn sample_nearest(texture: &ColorImage, uv: egui::Vec2) -> Color32 {
+    let [width, height] = texture.size;
+    let x = ((uv.x * width as f32) as usize).min(width - 1);
+    let y = ((uv.y * height as f32) as usize).min(height - 1);
+    texture.pixels[y * width + x]
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::element::{Element, ElementType};
+    use egui::pos2;
+
+    /// A harness with one stroke drawn from (100, 100) to (200, 160)
+    fn harness_with_stroke() -> TestHarness {
+        let mut harness = TestHarness::new();
+        harness.set_tool("DrawStroke");
+        harness.drag(pos2(100.0, 100.0), pos2(200.0, 160.0));
+        harness
+    }
+
+    #[test]
+    fn test_dragging_with_the_stroke_tool_draws_an_undoable_stroke() {
+        let mut harness = harness_with_stroke();
+
+        let [ElementType::Stroke(stroke)] = &harness.editor_model.elements[..] else {
+            panic!(
+                "expected one stroke, got {:?}",
+                harness.editor_model.elements
+            );
+        };
+        let rect = stroke.rect();
+        assert!(rect.contains(pos2(100.0, 100.0)) && rect.contains(pos2(200.0, 160.0)));
+
+        harness
+            .command_history
+            .undo(&mut harness.editor_model)
+            .unwrap();
+        assert!(harness.editor_model.elements.is_empty());
+    }
+
+    #[test]
+    fn test_dragging_while_panning_leaves_the_tool_alone() {
+        let mut harness = TestHarness::new();
+        harness.set_tool("DrawStroke");
+        harness.view.set_panning(true);
+        harness.drag(pos2(100.0, 100.0), pos2(200.0, 160.0));
+        assert!(harness.editor_model.elements.is_empty());
+    }
+
+    #[test]
+    fn test_clicking_selects_and_dragging_moves_the_element() {
+        let mut harness = harness_with_stroke();
+        let id = harness.editor_model.elements[0].id();
+        let before = harness.editor_model.elements[0].rect();
+
+        harness.set_tool("Selection");
+        harness.click(pos2(150.0, 130.0));
+        assert!(harness.editor_model.is_element_selected(id));
+
+        harness.drag(pos2(150.0, 130.0), pos2(190.0, 150.0));
+        let after = harness.editor_model.get_element_by_id(id).unwrap().rect();
+        assert_eq!(after.center() - before.center(), egui::vec2(40.0, 20.0));
+        assert_eq!(after.size(), before.size());
+
+        harness
+            .command_history
+            .undo(&mut harness.editor_model)
+            .unwrap();
+        assert_eq!(harness.editor_model.elements[0].rect(), before);
+
+        // Shift-clicking toggles the element out of the selection and back in
+        harness.set_modifiers(Modifiers::SHIFT);
+        harness.click(pos2(150.0, 130.0));
+        assert!(!harness.editor_model.is_element_selected(id));
+        harness.click(pos2(150.0, 130.0));
+        assert!(harness.editor_model.is_element_selected(id));
+        harness.set_modifiers(Modifiers::NONE);
+
+        // Clicking empty canvas lets go of the selection
+        harness.click(pos2(600.0, 500.0));
+        assert!(harness.editor_model.selected_ids().is_empty());
+    }
+
+    #[test]
+    fn test_dragging_a_corner_handle_resizes_the_selection() {
+        let mut harness = harness_with_stroke();
+        let id = harness.editor_model.elements[0].id();
+        harness.set_tool("Selection");
+        harness.click(pos2(150.0, 130.0));
+        let before = harness.editor_model.selection_rect().unwrap();
+
+        harness.drag(before.max, before.max + egui::vec2(50.0, 30.0));
+        let after = harness.editor_model.get_element_by_id(id).unwrap().rect();
+        assert!((after.min - before.min).length() < 1.0);
+        assert!(after.width() > before.width() + 40.0);
+        assert!(after.height() > before.height() + 20.0);
+    }
+
+    #[test]
+    fn test_escape_drops_the_path_being_placed() {
+        let mut harness = TestHarness::new();
+        harness.set_tool("Pen");
+        harness.click(pos2(100.0, 100.0));
+        harness.click(pos2(200.0, 100.0));
+        assert_ne!(
+            harness.editor_model.active_tool().current_state_name(),
+            "Idle"
+        );
+
+        harness.key(Key::Escape);
+        assert_eq!(
+            harness.editor_model.active_tool().current_state_name(),
+            "Idle"
+        );
+        assert!(harness.editor_model.elements.is_empty());
+    }
+}
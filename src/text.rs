@@ -0,0 +1,83 @@
+//! Text rasterized on the CPU from egui's font atlas, for textures and exports
+//! that can't go through the GPU painter
+
+use egui::{Color32, ColorImage, Context, FontId, Rect};
+
+/// Rasterize text using egui's font atlas, wrapped to `wrap_width` pixels
+///
+/// `pixel_height` is the font size in output pixels; pass `f32::INFINITY` as
+/// the width to keep each line of the text on one row.
+pub fn render_text(
+    ctx: &Context,
+    text: &str,
+    pixel_height: f32,
+    color: Color32,
+    wrap_width: f32,
+) -> ColorImage {
+    let pixels_per_point = ctx.pixels_per_point();
+    let font_id = FontId::proportional(pixel_height / pixels_per_point);
+
+    let (galley, atlas) = ctx.fonts(|fonts| {
+        let galley = fonts.layout(
+            text.to_string(),
+            font_id,
+            color,
+            wrap_width / pixels_per_point,
+        );
+        (galley, fonts.image())
+    });
+
+    let width = (galley.size().x * pixels_per_point).ceil().max(1.0) as usize;
+    let height = (galley.size().y * pixels_per_point).ceil().max(1.0) as usize;
+    let mut image = ColorImage::new([width, height], Color32::TRANSPARENT);
+
+    for row in &galley.rows {
+        // Each glyph is an axis-aligned quad of four vertices with texel UVs into the atlas
+        for quad in row.visuals.mesh.vertices.chunks_exact(4) {
+            let pos = Rect::from_points(&quad.iter().map(|v| v.pos).collect::<Vec<_>>());
+            let uv = Rect::from_points(&quad.iter().map(|v| v.uv).collect::<Vec<_>>());
+
+            let x_start = (pos.min.x * pixels_per_point).round().max(0.0) as usize;
+            let y_start = (pos.min.y * pixels_per_point).round().max(0.0) as usize;
+            let glyph_width = uv.width().round() as usize;
+            let glyph_height = uv.height().round() as usize;
+
+            for dy in 0..glyph_height {
+                for dx in 0..glyph_width {
+                    let (x, y) = (x_start + dx, y_start + dy);
+                    if x >= width || y >= height {
+                        continue;
+                    }
+
+                    let atlas_x = uv.min.x as usize + dx;
+                    let atlas_y = uv.min.y as usize + dy;
+                    let coverage = atlas.pixels[atlas_y * atlas.width() + atlas_x];
+                    if coverage > 0.0 {
+                        image.pixels[y * width + x] = color.gamma_multiply(coverage);
+                    }
+                }
+            }
+        }
+    }
+
+    image
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_text_wraps_to_the_given_width() {
+        let ctx = Context::default();
+        // Fonts are only loaded once a frame has run
+        let _ = ctx.run(Default::default(), |_| {});
+
+        let text = "a few words to wrap";
+        let line = render_text(&ctx, text, 16.0, Color32::BLACK, f32::INFINITY);
+        let wrapped = render_text(&ctx, text, 16.0, Color32::BLACK, 60.0);
+        assert!(wrapped.size[0] <= 60);
+        assert!(wrapped.size[1] > line.size[1]);
+        assert!(wrapped.pixels.iter().any(|pixel| pixel.a() > 0));
+    }
+}
@@ -1,4 +1,5 @@
-use egui::{ColorImage, Context, TextureHandle, TextureId, TextureOptions};
+use crate::element::ElementId;
+use egui::{ColorImage, Context, TextureHandle, TextureId, TextureOptions, Vec2};
 use std::collections::HashMap;
 use thiserror::Error;
 
@@ -11,16 +12,57 @@ pub enum TextureGenerationError {
     InvalidDimensions,
 }
 
+// Resampled sizes grow by a quarter octave from one bucket to the next
+const BUCKETS_PER_OCTAVE: f32 = 4.0;
+
+/// Cached textures by (element_id, version, size bucket of resampled ones)
+type CacheKey = (ElementId, u64, Option<i32>);
+
+/// The bucket of sizes `size` (in pixels) falls in, and the size textures
+/// resampled for that bucket are made at
+///
+/// Zooming only resamples an image again once its size on screen has changed
+/// by a fifth or so; in between, the GPU stretches the nearest bucket's texture.
+pub fn size_bucket(size: Vec2) -> (i32, [u32; 2]) {
+    let longest = size.max_elem().max(1.0);
+    let bucket = (longest.log2() * BUCKETS_PER_OCTAVE).ceil() as i32;
+    let scale = (bucket as f32 / BUCKETS_PER_OCTAVE).exp2() / longest;
+    let sized = (size * scale).round().max(Vec2::splat(1.0));
+    (bucket, [sized.x as u32, sized.y as u32])
+}
+
+/// Running totals of how the texture cache has been used
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub struct CacheStats {
+    pub hits: u64,
+    pub misses: u64,
+    /// Textures dropped to make room for new ones
+    pub evictions: u64,
+}
+
+impl CacheStats {
+    /// Fraction of lookups that found a cached texture
+    pub fn hit_rate(&self) -> f32 {
+        let lookups = self.hits + self.misses;
+        if lookups == 0 {
+            return 0.0;
+        }
+        self.hits as f32 / lookups as f32
+    }
+}
+
 /// Manages textures for elements, providing caching and invalidation
 pub struct TextureManager {
-    /// Cache of textures by (element_id, version)
-    texture_cache: HashMap<(usize, u64), TextureHandle>,
+    /// Cache of textures by (element_id, version, size bucket)
+    texture_cache: HashMap<CacheKey, TextureHandle>,
     /// Tracks when each texture was last used
-    last_used: HashMap<(usize, u64), u64>,
+    last_used: HashMap<CacheKey, u64>,
     /// Current frame counter for LRU tracking
     current_frame: u64,
     /// Maximum number of textures to cache
     max_cache_size: usize,
+    /// Hits, misses and evictions so far
+    stats: CacheStats,
 }
 
 impl TextureManager {
@@ -31,6 +73,7 @@ impl TextureManager {
             last_used: HashMap::new(),
             current_frame: 0,
             max_cache_size,
+            stats: CacheStats::default(),
         }
     }
 
@@ -42,7 +85,7 @@ impl TextureManager {
     /// Gets or creates a texture for the given element
     pub fn get_or_create_texture<F>(
         &mut self,
-        element_id: usize,
+        element_id: ElementId,
         texture_version: u64,
         generator: F,
         ctx: &Context,
@@ -50,14 +93,48 @@ impl TextureManager {
     where
         F: FnOnce() -> Result<ColorImage, TextureGenerationError>,
     {
-        let cache_key = (element_id, texture_version);
+        let cache_key = (element_id, texture_version, None);
+        self.get_or_create(cache_key, TextureOptions::LINEAR, generator, ctx)
+    }
 
+    /// Gets or creates a texture for the given element resampled for the size
+    /// bucket `bucket` from [`size_bucket`]
+    ///
+    /// Each bucket is cached on its own, so zooming back and forth reuses them.
+    pub fn get_or_create_resampled_texture<F>(
+        &mut self,
+        element_id: ElementId,
+        texture_version: u64,
+        bucket: i32,
+        options: TextureOptions,
+        generator: F,
+        ctx: &Context,
+    ) -> Result<TextureId, TextureGenerationError>
+    where
+        F: FnOnce() -> Result<ColorImage, TextureGenerationError>,
+    {
+        let cache_key = (element_id, texture_version, Some(bucket));
+        self.get_or_create(cache_key, options, generator, ctx)
+    }
+
+    fn get_or_create<F>(
+        &mut self,
+        cache_key: CacheKey,
+        options: TextureOptions,
+        generator: F,
+        ctx: &Context,
+    ) -> Result<TextureId, TextureGenerationError>
+    where
+        F: FnOnce() -> Result<ColorImage, TextureGenerationError>,
+    {
         // Check if the texture is already in the cache
         if let Some(handle) = self.texture_cache.get(&cache_key) {
             // Update last used time
             self.last_used.insert(cache_key, self.current_frame);
+            self.stats.hits += 1;
             return Ok(handle.id());
         }
+        self.stats.misses += 1;
 
         // Prune cache if needed
         self.prune_cache_if_needed();
@@ -66,8 +143,12 @@ impl TextureManager {
         let image = generator()?;
 
         // Create the texture
-        let name = format!("element_{}_v{}", element_id, texture_version);
-        let handle = ctx.load_texture(&name, image, TextureOptions::LINEAR);
+        let (element_id, texture_version, bucket) = cache_key;
+        let name = match bucket {
+            Some(bucket) => format!("element_{}_v{}_b{}", element_id, texture_version, bucket),
+            None => format!("element_{}_v{}", element_id, texture_version),
+        };
+        let handle = ctx.load_texture(&name, image, options);
 
         // Store in cache
         self.texture_cache.insert(cache_key, handle.clone());
@@ -77,11 +158,11 @@ impl TextureManager {
     }
 
     /// Invalidates all textures for a specific element
-    pub fn invalidate_element(&mut self, element_id: usize) {
-        let keys_to_remove: Vec<(usize, u64)> = self
+    pub fn invalidate_element(&mut self, element_id: ElementId) {
+        let keys_to_remove: Vec<CacheKey> = self
             .texture_cache
             .keys()
-            .filter(|(id, _)| *id == element_id)
+            .filter(|(id, _, _)| *id == element_id)
             .cloned()
             .collect();
 
@@ -99,7 +180,7 @@ impl TextureManager {
         }
 
         // Collect keys and their last-used frames
-        let mut entries: Vec<((usize, u64), u64)> =
+        let mut entries: Vec<(CacheKey, u64)> =
             self.last_used.iter().map(|(k, v)| (*k, *v)).collect();
 
         // Sort by last-used frame (oldest first)
@@ -107,9 +188,10 @@ impl TextureManager {
 
         // Remove oldest entries until we're at max_cache_size - 1 (to make room for new one)
         let to_remove = self.texture_cache.len() - (self.max_cache_size - 1);
-        for ((id, version), _) in entries.iter().take(to_remove) {
-            self.texture_cache.remove(&(*id, *version));
-            self.last_used.remove(&(*id, *version));
+        for (key, _) in entries.iter().take(to_remove) {
+            self.texture_cache.remove(key);
+            self.last_used.remove(key);
+            self.stats.evictions += 1;
         }
     }
 
@@ -119,14 +201,53 @@ impl TextureManager {
         self.last_used.clear();
     }
 
+    /// Drop every texture not drawn this frame, returning how many were dropped
+    ///
+    /// Textures for elements scrolled back into view are generated again.
+    pub fn trim(&mut self) -> usize {
+        let current_frame = self.current_frame;
+        let stale: Vec<CacheKey> = self
+            .last_used
+            .iter()
+            .filter(|(_, frame)| **frame < current_frame)
+            .map(|(key, _)| *key)
+            .collect();
+        for key in &stale {
+            self.texture_cache.remove(key);
+            self.last_used.remove(key);
+        }
+        stale.len()
+    }
+
+    /// Bytes of texture memory the cached textures take, at four per pixel
+    pub fn byte_size(&self) -> usize {
+        self.texture_cache
+            .values()
+            .map(|handle| {
+                let [width, height] = handle.size();
+                width * height * 4
+            })
+            .sum()
+    }
+
     /// Returns the number of textures currently in the cache
     pub fn cache_size(&self) -> usize {
         self.texture_cache.len()
     }
 
+    /// How the cache has been used since the manager was created
+    pub fn stats(&self) -> CacheStats {
+        self.stats
+    }
+
+    /// Most textures the cache holds before evicting the least recently used
+    pub fn max_cache_size(&self) -> usize {
+        self.max_cache_size
+    }
+
     #[cfg(test)]
-    pub fn get_texture(&self, element_id: usize, version: u64) -> Option<&TextureHandle> {
-        self.texture_cache.get(&(element_id, version))
+    pub fn get_texture(&self, element_id: ElementId, version: u64) -> Option<&TextureHandle> {
+        self.texture_cache.get(&(element_id, version, None))
     }
 }
 
@@ -146,17 +267,26 @@ mod tests {
 
         // First call should create a new texture
         let texture_id1 = manager
-            .get_or_create_texture(1, 1, mock_texture_generator, &ctx)
+            .get_or_create_texture(ElementId::new(1), 1, mock_texture_generator, &ctx)
             .unwrap();
 
         // Second call with same params should hit the cache
         let texture_id2 = manager
-            .get_or_create_texture(1, 1, mock_texture_generator, &ctx)
+            .get_or_create_texture(ElementId::new(1), 1, mock_texture_generator, &ctx)
             .unwrap();
 
         // IDs should be the same
         assert_eq!(texture_id1, texture_id2);
         assert_eq!(manager.cache_size(), 1);
+        assert_eq!(
+            manager.stats(),
+            CacheStats {
+                hits: 1,
+                misses: 1,
+                evictions: 0
+            }
+        );
+        assert_eq!(manager.stats().hit_rate(), 0.5);
     }
 
     #[test]
@@ -166,13 +296,13 @@ mod tests {
 
         // Create texture
         manager
-            .get_or_create_texture(1, 1, mock_texture_generator, &ctx)
+            .get_or_create_texture(ElementId::new(1), 1, mock_texture_generator, &ctx)
             .unwrap();
 
         assert_eq!(manager.cache_size(), 1);
 
         // Invalidate all textures for element 1
-        manager.invalidate_element(1);
+        manager.invalidate_element(ElementId::new(1));
 
         assert_eq!(manager.cache_size(), 0);
     }
@@ -184,22 +314,43 @@ mod tests {
 
         // Create three textures to trigger eviction
         manager
-            .get_or_create_texture(1, 1, mock_texture_generator, &ctx)
+            .get_or_create_texture(ElementId::new(1), 1, mock_texture_generator, &ctx)
             .unwrap();
         manager.begin_frame();
         manager
-            .get_or_create_texture(2, 1, mock_texture_generator, &ctx)
+            .get_or_create_texture(ElementId::new(2), 1, mock_texture_generator, &ctx)
             .unwrap();
         manager.begin_frame();
         manager
-            .get_or_create_texture(3, 1, mock_texture_generator, &ctx)
+            .get_or_create_texture(ElementId::new(3), 1, mock_texture_generator, &ctx)
             .unwrap();
 
         // Cache should be at max size with most recent textures
         assert_eq!(manager.cache_size(), 2);
-        assert!(manager.get_texture(1, 1).is_none()); // This one should be evicted
-        assert!(manager.get_texture(2, 1).is_some());
-        assert!(manager.get_texture(3, 1).is_some());
+        assert!(manager.get_texture(ElementId::new(1), 1).is_none()); // This one should be evicted
+        assert!(manager.get_texture(ElementId::new(2), 1).is_some());
+        assert!(manager.get_texture(ElementId::new(3), 1).is_some());
+        assert_eq!(manager.stats().evictions, 1);
+    }
+
+    #[test]
+    fn test_trim_keeps_textures_drawn_this_frame() {
+        let ctx = Context::default();
+        let mut manager = TextureManager::new(10);
+
+        manager
+            .get_or_create_texture(ElementId::new(1), 1, mock_texture_generator, &ctx)
+            .unwrap();
+        manager.begin_frame();
+        manager
+            .get_or_create_texture(ElementId::new(2), 1, mock_texture_generator, &ctx)
+            .unwrap();
+        assert_eq!(manager.byte_size(), 2 * 10 * 10 * 4);
+
+        assert_eq!(manager.trim(), 1);
+        assert!(manager.get_texture(ElementId::new(1), 1).is_none());
+        assert!(manager.get_texture(ElementId::new(2), 1).is_some());
+        assert_eq!(manager.byte_size(), 10 * 10 * 4);
     }
 
     #[test]
@@ -209,17 +360,47 @@ mod tests {
 
         // Create texture version 1
         manager
-            .get_or_create_texture(1, 1, mock_texture_generator, &ctx)
+            .get_or_create_texture(ElementId::new(1), 1, mock_texture_generator, &ctx)
             .unwrap();
 
         // Create texture version 2
         manager
-            .get_or_create_texture(1, 2, mock_texture_generator, &ctx)
+            .get_or_create_texture(ElementId::new(1), 2, mock_texture_generator, &ctx)
             .unwrap();
 
         // Both versions should be cached
         assert_eq!(manager.cache_size(), 2);
-        assert!(manager.get_texture(1, 1).is_some());
-        assert!(manager.get_texture(1, 2).is_some());
+        assert!(manager.get_texture(ElementId::new(1), 1).is_some());
+        assert!(manager.get_texture(ElementId::new(1), 2).is_some());
+    }
+
+    #[test]
+    fn test_resampled_textures_are_cached_per_size_bucket() {
+        let (bucket, size) = size_bucket(Vec2::new(200.0, 100.0));
+        assert_eq!(size_bucket(Vec2::new(196.0, 98.0)).0, bucket);
+        assert_ne!(size_bucket(Vec2::new(300.0, 150.0)).0, bucket);
+        assert!(size[0] >= 200 && size[0] < 240);
+        assert!(size[0].abs_diff(size[1] * 2) <= 1);
+
+        let ctx = Context::default();
+        let mut manager = TextureManager::new(10);
+        let options = TextureOptions::LINEAR;
+        for bucket in [bucket, bucket, bucket + 1] {
+            manager
+                .get_or_create_resampled_texture(
+                    ElementId::new(1),
+                    1,
+                    bucket,
+                    options,
+                    mock_texture_generator,
+                    &ctx,
+                )
+                .unwrap();
+        }
+        assert_eq!(manager.cache_size(), 2);
+        assert_eq!(manager.stats().hits, 1);
+
+        manager.invalidate_element(ElementId::new(1));
+        assert_eq!(manager.cache_size(), 0);
     }
 }
@@ -0,0 +1,540 @@
+use crate::command::Command;
+use crate::element::sticky_note::{CALLOUT_CORNER_RADIUS, CALLOUT_TAIL_WIDTH};
+use crate::element::{Element, ElementType, REDACTION_PROPERTY, factory};
+use crate::geometry;
+use crate::renderer::Renderer;
+use crate::state::EditorModel;
+use crate::tools::{Tool, ToolConfig};
+use egui::{Color32, Pos2, Rect, Ui, Vec2};
+use log::{debug, trace};
+use std::any::Any;
+
+// Shared annotation styling, so every callout in a document looks alike
+const ANNOTATION_RED: Color32 = Color32::from_rgb(220, 40, 40);
+const HIGHLIGHT_YELLOW: Color32 = Color32::from_rgba_premultiplied(90, 75, 0, 90);
+const HIGHLIGHT_PREVIEW_COLOR: Color32 = Color32::from_rgb(230, 190, 0);
+const REDACTION_BLACK: Color32 = Color32::BLACK;
+const REDACTION_PREVIEW_COLOR: Color32 = Color32::from_rgb(120, 120, 120);
+const ARROW_SHAFT_WIDTH: f32 = 6.0;
+const ARROW_HEAD_LENGTH: f32 = 22.0;
+const ARROW_HEAD_WIDTH: f32 = 20.0;
+const BUBBLE_SIZE: Vec2 = Vec2::new(160.0, 80.0);
+
+// Drags shorter than this are taken as clicks and add nothing
+const MIN_DRAG_DISTANCE: f32 = 4.0;
+
+/// One-click annotation styles offered by the annotate tool
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum AnnotationPreset {
+    /// Red arrow dragged from its tail to its tip
+    Arrow,
+    /// Translucent yellow box dragged corner to corner
+    Highlight,
+    /// Opaque black box dragged corner to corner, burned in by privacy scrub exports
+    Redact,
+    /// Numbered circle placed with a click, counting up through the document
+    Step,
+    /// Speech bubble dragged from the point it calls out to where the bubble sits,
+    /// outlined around text edited like a sticky note's
+    Callout,
+}
+
+impl AnnotationPreset {
+    pub const ALL: [AnnotationPreset; 5] = [
+        AnnotationPreset::Arrow,
+        AnnotationPreset::Highlight,
+        AnnotationPreset::Redact,
+        AnnotationPreset::Step,
+        AnnotationPreset::Callout,
+    ];
+
+    pub fn label(&self) -> &'static str {
+        match self {
+            AnnotationPreset::Arrow => "➡ Arrow",
+            AnnotationPreset::Highlight => "▭ Highlight",
+            AnnotationPreset::Redact => "■ Redact",
+            AnnotationPreset::Step => "① Step",
+            AnnotationPreset::Callout => "💬 Callout",
+        }
+    }
+
+    /// Number key picking this preset while the tool is active
+    pub fn hotkey(&self) -> egui::Key {
+        match self {
+            AnnotationPreset::Arrow => egui::Key::Num1,
+            AnnotationPreset::Highlight => egui::Key::Num2,
+            AnnotationPreset::Redact => egui::Key::Num3,
+            AnnotationPreset::Step => egui::Key::Num4,
+            AnnotationPreset::Callout => egui::Key::Num5,
+        }
+    }
+}
+
+// Config for AnnotateTool
+#[derive(Clone, Debug)]
+pub struct AnnotateToolConfig {
+    pub preset: AnnotationPreset,
+}
+
+impl ToolConfig for AnnotateToolConfig {
+    fn tool_name(&self) -> &'static str {
+        "Annotate"
+    }
+
+    fn as_any(&self) -> &dyn Any {
+        self
+    }
+
+    fn as_any_mut(&mut self) -> &mut dyn Any {
+        self
+    }
+}
+
+// State enum for the AnnotateTool
+#[derive(Clone, Debug)]
+pub enum AnnotateState {
+    Idle,
+    Dragging { start_pos: Pos2, current_pos: Pos2 },
+}
+
+/// Tool adding arrows, highlight boxes and numbered step badges in a fixed style
+#[derive(Clone, Debug)]
+pub struct UnifiedAnnotateTool {
+    pub state: AnnotateState,
+    pub preset: AnnotationPreset,
+}
+
+impl UnifiedAnnotateTool {
+    pub fn new() -> Self {
+        Self {
+            state: AnnotateState::Idle,
+            preset: AnnotationPreset::Arrow,
+        }
+    }
+
+    // Get the current state name
+    pub fn current_state_name(&self) -> &'static str {
+        match self.state {
+            AnnotateState::Idle => "Idle",
+            AnnotateState::Dragging { .. } => "Dragging",
+        }
+    }
+}
+
+/// Outline of an arrow from `start` to `end`, or None if they are too close
+pub fn arrow_outline(start: Pos2, end: Pos2) -> Option<Vec<Pos2>> {
+    let length = start.distance(end);
+    if length < MIN_DRAG_DISTANCE {
+        return None;
+    }
+
+    let direction = (end - start) / length;
+    let normal = direction.rot90();
+    // Short arrows are all head
+    let base = end - direction * ARROW_HEAD_LENGTH.min(length);
+    let shaft = normal * ARROW_SHAFT_WIDTH / 2.0;
+    let head = normal * ARROW_HEAD_WIDTH / 2.0;
+    Some(vec![
+        start + shaft,
+        base + shaft,
+        base + head,
+        end,
+        base - head,
+        base - shaft,
+        start - shaft,
+    ])
+}
+
+/// Outline of a speech bubble centered on `center` with its tail pointing at `tip`
+///
+/// The tail leaves from the side of the bubble facing `tip`. Returns None if `tip`
+/// is inside the bubble, leaving nothing for the tail to point at.
+pub fn speech_bubble_outline(tip: Pos2, center: Pos2) -> Option<Vec<Pos2>> {
+    geometry::speech_bubble_outline(
+        Rect::from_center_size(center, BUBBLE_SIZE),
+        tip,
+        CALLOUT_CORNER_RADIUS,
+        CALLOUT_TAIL_WIDTH,
+    )
+}
+
+/// Build the command adding an annotation dragged from `start` to `end`
+///
+/// Steps ignore `end` and are placed at `start`. Returns None for drags too
+/// short to make an arrow or box.
+pub fn annotation_command(
+    editor_model: &EditorModel,
+    preset: AnnotationPreset,
+    start: Pos2,
+    end: Pos2,
+) -> Option<Command> {
+    let named = |mut element: ElementType, name: &str| {
+        element.metadata_mut().name = name.to_string();
+        element
+    };
+
+    match preset {
+        AnnotationPreset::Arrow => {
+            let outline = arrow_outline(start, end)?;
+            let element = factory::create_shape(editor_model.reserve_id(), outline, ANNOTATION_RED);
+            Some(Command::AddElement {
+                element: named(element, "Arrow"),
+            })
+        }
+        AnnotationPreset::Highlight | AnnotationPreset::Redact => {
+            let rect = Rect::from_two_pos(start, end);
+            if rect.width() < MIN_DRAG_DISTANCE || rect.height() < MIN_DRAG_DISTANCE {
+                return None;
+            }
+            let corners = vec![
+                rect.left_top(),
+                rect.right_top(),
+                rect.right_bottom(),
+                rect.left_bottom(),
+            ];
+            let redact = preset == AnnotationPreset::Redact;
+            let fill = if redact {
+                REDACTION_BLACK
+            } else {
+                HIGHLIGHT_YELLOW
+            };
+            let element = factory::create_shape(editor_model.reserve_id(), corners, fill);
+            let mut element = named(element, if redact { "Redaction" } else { "Highlight" });
+            if redact {
+                element.metadata_mut().set(REDACTION_PROPERTY, "true");
+            }
+            Some(Command::AddElement { element })
+        }
+        AnnotationPreset::Step => {
+            let number = editor_model.next_step_number();
+            debug!(step = number; "Adding step badge");
+            let element = factory::create_step_badge(
+                editor_model.reserve_id(),
+                start,
+                number,
+                ANNOTATION_RED,
+            );
+            Some(Command::AddElement { element })
+        }
+        AnnotationPreset::Callout => {
+            // A bubble with nowhere to point is no callout
+            speech_bubble_outline(start, end)?;
+            let element = factory::create_callout(
+                editor_model.reserve_id(),
+                Rect::from_center_size(end, BUBBLE_SIZE),
+                start,
+                ANNOTATION_RED,
+            );
+            Some(Command::AddElement {
+                element: named(element, "Callout"),
+            })
+        }
+    }
+}
+
+impl Tool for UnifiedAnnotateTool {
+    fn name(&self) -> &'static str {
+        "Annotate"
+    }
+
+    fn activate(&mut self, _editor_model: &EditorModel) {
+        self.state = AnnotateState::Idle;
+        trace!(tool = self.name(); "Tool activated");
+    }
+
+    fn deactivate(&mut self, _editor_model: &EditorModel) {
+        self.state = AnnotateState::Idle;
+        trace!(tool = self.name(); "Tool deactivated");
+    }
+
+    fn on_pointer_down(
+        &mut self,
+        pos: Pos2,
+        button: egui::PointerButton,
+        _modifiers: &egui::Modifiers,
+        editor_model: &EditorModel,
+        _renderer: &mut Renderer,
+    ) -> Option<Command> {
+        if button != egui::PointerButton::Primary {
+            return None;
+        }
+        if self.preset == AnnotationPreset::Step {
+            return annotation_command(editor_model, self.preset, pos, pos);
+        }
+        self.state = AnnotateState::Dragging {
+            start_pos: pos,
+            current_pos: pos,
+        };
+        None
+    }
+
+    fn on_pointer_move(
+        &mut self,
+        pos: Pos2,
+        held_buttons: &[egui::PointerButton],
+        _modifiers: &egui::Modifiers,
+        _editor_model: &mut EditorModel,
+        _ui: &egui::Ui,
+        _renderer: &mut Renderer,
+    ) -> Option<Command> {
+        if !held_buttons.contains(&egui::PointerButton::Primary) {
+            return None;
+        }
+        if let AnnotateState::Dragging { current_pos, .. } = &mut self.state {
+            *current_pos = pos;
+        }
+        None
+    }
+
+    fn on_pointer_up(
+        &mut self,
+        pos: Pos2,
+        button: egui::PointerButton,
+        _modifiers: &egui::Modifiers,
+        editor_model: &EditorModel,
+    ) -> Option<Command> {
+        if button != egui::PointerButton::Primary {
+            return None;
+        }
+
+        let AnnotateState::Dragging { start_pos, .. } = self.state else {
+            return None;
+        };
+        self.state = AnnotateState::Idle;
+        annotation_command(editor_model, self.preset, start_pos, pos)
+    }
+
+    fn on_key(
+        &mut self,
+        key: egui::Key,
+        pressed: bool,
+        modifiers: &egui::Modifiers,
+        _editor_model: &EditorModel,
+    ) {
+        if !pressed || !modifiers.is_none() {
+            return;
+        }
+        if let Some(preset) = AnnotationPreset::ALL
+            .into_iter()
+            .find(|preset| preset.hotkey() == key)
+        {
+            self.preset = preset;
+            self.state = AnnotateState::Idle;
+        }
+    }
+
+    fn cursor_icon(
+        &self,
+        _pos: Pos2,
+        _editor_model: &EditorModel,
+        _renderer: &Renderer,
+    ) -> Option<egui::CursorIcon> {
+        Some(egui::CursorIcon::Crosshair)
+    }
+
+    fn reset_interaction_state(&mut self) {
+        self.state = AnnotateState::Idle;
+    }
+
+    fn update_preview(&mut self, renderer: &mut Renderer) {
+        let AnnotateState::Dragging {
+            start_pos,
+            current_pos,
+        } = self.state
+        else {
+            renderer.clear_stroke_preview();
+            return;
+        };
+
+        match self.preset {
+            AnnotationPreset::Arrow | AnnotationPreset::Callout => {
+                let outline = if self.preset == AnnotationPreset::Arrow {
+                    arrow_outline(start_pos, current_pos)
+                } else {
+                    speech_bubble_outline(start_pos, current_pos)
+                };
+                match outline {
+                    Some(mut outline) => {
+                        outline.push(outline[0]);
+                        renderer.set_stroke_preview(outline, 1.0, ANNOTATION_RED);
+                    }
+                    None => renderer.clear_stroke_preview(),
+                }
+            }
+            AnnotationPreset::Highlight | AnnotationPreset::Redact => {
+                let color = if self.preset == AnnotationPreset::Redact {
+                    REDACTION_PREVIEW_COLOR
+                } else {
+                    HIGHLIGHT_PREVIEW_COLOR
+                };
+                let rect = Rect::from_two_pos(start_pos, current_pos);
+                renderer.set_stroke_preview(
+                    vec![
+                        rect.left_top(),
+                        rect.right_top(),
+                        rect.right_bottom(),
+                        rect.left_bottom(),
+                        rect.left_top(),
+                    ],
+                    1.0,
+                    color,
+                );
+            }
+            AnnotationPreset::Step => renderer.clear_stroke_preview(),
+        }
+    }
+
+    fn clear_preview(&mut self, renderer: &mut Renderer) {
+        renderer.clear_stroke_preview();
+    }
+
+    fn ui(&mut self, ui: &mut Ui, editor_model: &EditorModel) -> Option<Command> {
+        ui.label("Annotation style:");
+        ui.horizontal(|ui| {
+            for preset in AnnotationPreset::ALL {
+                ui.selectable_value(&mut self.preset, preset, preset.label())
+                    .on_hover_text(format!("Press {}", preset.hotkey().name()));
+            }
+        });
+
+        ui.separator();
+        match self.preset {
+            AnnotationPreset::Arrow => ui.label("Drag from the arrow's tail to its tip."),
+            AnnotationPreset::Highlight => ui.label("Drag a box over what to highlight."),
+            AnnotationPreset::Redact => {
+                ui.label("Drag a box over what to hide. Privacy scrub exports burn it in.")
+            }
+            AnnotationPreset::Step => ui.label(format!(
+                "Click to place step {}.",
+                editor_model.next_step_number()
+            )),
+            AnnotationPreset::Callout => {
+                ui.label("Drag from what to call out to where the bubble goes.")
+            }
+        };
+        None
+    }
+
+    fn get_config(&self) -> Box<dyn ToolConfig> {
+        Box::new(AnnotateToolConfig {
+            preset: self.preset,
+        })
+    }
+
+    fn apply_config(&mut self, config: &dyn ToolConfig) {
+        if let Some(config) = config.as_any().downcast_ref::<AnnotateToolConfig>() {
+            self.preset = config.preset;
+        }
+    }
+}
+
+impl Default for UnifiedAnnotateTool {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+// Factory function to create a new AnnotateTool
+pub fn new_annotate_tool() -> UnifiedAnnotateTool {
+    UnifiedAnnotateTool::new()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_step_badges_count_up_through_the_document() {
+        let mut model = EditorModel::new();
+        let place = |model: &mut EditorModel, x: f32| {
+            let command = annotation_command(
+                model,
+                AnnotationPreset::Step,
+                Pos2::new(x, 0.0),
+                Pos2::new(x, 0.0),
+            )
+            .unwrap();
+            command.execute(model).unwrap();
+            command
+        };
+
+        place(&mut model, 0.0);
+        let second = place(&mut model, 50.0);
+        assert_eq!(model.next_step_number(), 3);
+        assert!(
+            model
+                .elements
+                .iter()
+                .any(|element| element.name() == "Step 2")
+        );
+
+        // Undoing a badge frees its number
+        second.undo(&mut model).unwrap();
+        assert_eq!(model.next_step_number(), 2);
+        assert_eq!(model.elements.len(), 1);
+
+        assert!(
+            annotation_command(
+                &model,
+                AnnotationPreset::Arrow,
+                Pos2::ZERO,
+                Pos2::new(1.0, 1.0)
+            )
+            .is_none()
+        );
+    }
+
+    #[test]
+    fn test_speech_bubble_tail_points_at_the_callout() {
+        let center = Pos2::new(200.0, 200.0);
+        let tip = Pos2::new(200.0, 350.0);
+        let outline = speech_bubble_outline(tip, center).unwrap();
+        assert!(outline.contains(&tip));
+
+        // The tail leaves from the bottom edge, below the bubble
+        let tip_index = outline.iter().position(|point| *point == tip).unwrap();
+        for neighbor in [outline[tip_index - 1], outline[tip_index + 1]] {
+            assert_eq!(neighbor.y, center.y + BUBBLE_SIZE.y / 2.0);
+        }
+
+        // A tip inside the bubble leaves nothing to point at
+        assert!(speech_bubble_outline(center + Vec2::new(10.0, 5.0), center).is_none());
+    }
+
+    #[test]
+    fn test_callouts_are_outlined_notes_with_editable_text() {
+        let mut model = EditorModel::new();
+        let (tip, center) = (Pos2::new(200.0, 350.0), Pos2::new(200.0, 200.0));
+        let command = annotation_command(&model, AnnotationPreset::Callout, tip, center).unwrap();
+        command.execute(&mut model).unwrap();
+
+        let Some(ElementType::StickyNote(note)) = model.elements.first() else {
+            panic!("callout isn't a sticky note");
+        };
+        assert!(note.is_callout());
+        assert_eq!(note.text(), "");
+        assert_eq!(note.body(), Rect::from_center_size(center, BUBBLE_SIZE));
+        assert!(note.rect().contains(tip));
+        let id = note.id();
+
+        let edit =
+            Command::new_set_sticky_note(&model, id, "Look here".to_string(), ANNOTATION_RED)
+                .unwrap();
+        edit.execute(&mut model).unwrap();
+        let Some(ElementType::StickyNote(note)) = model.find_element_by_id(id) else {
+            panic!("callout is gone");
+        };
+        assert_eq!(note.text(), "Look here");
+
+        // Only the outline is drawn, leaving the inside of the bubble clear
+        let ctx = egui::Context::default();
+        // Fonts are only loaded once a frame has run
+        let _ = ctx.run(Default::default(), |_| {});
+        let mut note = note.clone();
+        let image = note.generate_texture(&ctx).unwrap();
+        let inside = note.body().left_bottom() + Vec2::new(20.0, -10.0) - note.texture_rect().min;
+        let pixel = image.pixels[inside.y as usize * image.size[0] + inside.x as usize];
+        assert_eq!(pixel, Color32::TRANSPARENT);
+        assert!(image.pixels.contains(&ANNOTATION_RED));
+    }
+}
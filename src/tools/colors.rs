@@ -0,0 +1,66 @@
+use crate::tools::ToolConfig;
+use egui::Color32;
+use serde::{Deserialize, Serialize};
+use std::any::Any;
+
+/// The foreground and background colors shared by the painting tools
+///
+/// Tools paint with the foreground color; the background color is the
+/// alternate one, e.g. for drawing with Shift held. Applied to the tools as a
+/// config whenever either color changes.
+#[derive(Clone, Copy, Debug, PartialEq, Serialize, Deserialize)]
+#[serde(default)]
+pub struct ColorPair {
+    pub foreground: Color32,
+    pub background: Color32,
+}
+
+impl ColorPair {
+    pub fn swap(&mut self) {
+        std::mem::swap(&mut self.foreground, &mut self.background);
+    }
+}
+
+impl Default for ColorPair {
+    fn default() -> Self {
+        Self {
+            foreground: Color32::BLACK,
+            background: Color32::WHITE,
+        }
+    }
+}
+
+impl ToolConfig for ColorPair {
+    fn tool_name(&self) -> &'static str {
+        "Colors"
+    }
+
+    fn as_any(&self) -> &dyn Any {
+        self
+    }
+
+    fn as_any_mut(&mut self) -> &mut dyn Any {
+        self
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::tools::{Tool, new_draw_stroke_tool, new_polygon_tool};
+
+    #[test]
+    fn test_swapped_colors_reach_the_tools() {
+        let mut colors = ColorPair::default();
+        colors.swap();
+        assert_eq!(colors.foreground, Color32::WHITE);
+
+        let mut draw = new_draw_stroke_tool();
+        let mut polygon = new_polygon_tool();
+        draw.apply_config(&colors);
+        polygon.apply_config(&colors);
+        assert_eq!(draw.default_color, Color32::WHITE);
+        assert_eq!(draw.alternate_color, Color32::BLACK);
+        assert_eq!(polygon.fill, Color32::WHITE);
+    }
+}
@@ -1,6 +1,11 @@
+use crate::element::StrokeStyle;
+use crate::pressure::PressureResponse;
 use crate::renderer::StrokePreview;
 use egui::{Color32, Pos2};
 
+/// Gaps between pointer samples longer than this are filled in along a curve
+const INTERPOLATION_SPACING: f32 = 4.0;
+
 /// Helper struct for creating strokes during drawing
 /// 
 /// This replaces the legacy MutableStroke and provides only the functionality
@@ -8,8 +13,15 @@ use egui::{Color32, Pos2};
 #[derive(Clone)]
 pub struct DrawStrokeHelper {
     points: Vec<Pos2>,
+    // The points as sampled from the pointer, before interpolation
+    samples: Vec<Pos2>,
     color: Color32,
     thickness: f32,
+    style: StrokeStyle,
+    // Firmest pen pressure so far, if the pen reports any
+    peak_pressure: Option<f32>,
+    // Size and opacity factors from the pressure response at the peak pressure
+    pressure_factors: (f32, f32),
 }
 
 impl DrawStrokeHelper {
@@ -17,33 +29,159 @@ impl DrawStrokeHelper {
     pub fn new(color: Color32, thickness: f32) -> Self {
         Self {
             points: Vec::new(),
+            samples: Vec::new(),
             color,
             thickness,
+            style: StrokeStyle::default(),
+            peak_pressure: None,
+            pressure_factors: (1.0, 1.0),
         }
     }
 
+    /// Draw the stroke with the given taper, caps and joins
+    pub fn with_style(mut self, style: StrokeStyle) -> Self {
+        self.style = style;
+        self
+    }
+
+    /// Take the pen's pressure into account
+    ///
+    /// A stroke has one thickness and color, so they follow `response` at the
+    /// firmest pressure the stroke was drawn with.
+    pub fn press(&mut self, pressure: f32, response: &PressureResponse) {
+        let peak = self
+            .peak_pressure
+            .map_or(pressure, |peak| peak.max(pressure));
+        self.peak_pressure = Some(peak);
+        self.pressure_factors = response.factors(Some(peak));
+    }
+
     /// Add a point to the stroke
+    ///
+    /// Fast pointer movement leaves long straight gaps between samples. Once a
+    /// sample arrives, the gap before the previous one has samples on both sides
+    /// and is filled in along a Catmull-Rom curve through them.
     pub fn add_point(&mut self, point: Pos2) {
+        if let [.., p1, p2] = self.samples[..] {
+            let p0 = self.samples.iter().rev().nth(2).copied().unwrap_or(p1);
+            let gap = p1.distance(p2);
+            if gap > INTERPOLATION_SPACING {
+                let end = self.points.pop();
+                let steps = (gap / INTERPOLATION_SPACING).ceil() as usize;
+                for step in 1..steps {
+                    let t = step as f32 / steps as f32;
+                    self.points.push(catmull_rom(p0, p1, p2, point, t));
+                }
+                self.points.extend(end);
+            }
+        }
+        self.samples.push(point);
+        self.points.push(point);
+    }
+
+    /// Add a point joined to the last by a straight segment rather than a curve
+    pub fn add_straight_point(&mut self, point: Pos2) {
+        // Later samples curve away from this point, not through the segment
+        self.samples.clear();
+        self.samples.push(point);
         self.points.push(point);
     }
 
+    /// Move the end of a segment added by [`Self::add_straight_point`]
+    pub fn move_last_point(&mut self, point: Pos2) {
+        if let Some(last) = self.points.last_mut() {
+            *last = point;
+        }
+        self.samples.clear();
+        self.samples.push(point);
+    }
+
     /// Get the current points
     pub fn points(&self) -> &[Pos2] {
         &self.points
     }
 
-    /// Get the stroke color
+    /// Get the stroke color, faded by the pressure
     pub fn color(&self) -> Color32 {
-        self.color
+        self.color.gamma_multiply(self.pressure_factors.1)
     }
 
-    /// Get the stroke thickness
+    /// Get the stroke thickness, thinned by the pressure
     pub fn thickness(&self) -> f32 {
-        self.thickness
+        self.thickness * self.pressure_factors.0
+    }
+
+    pub fn style(&self) -> StrokeStyle {
+        self.style
     }
 
     /// Convert to a StrokePreview for rendering
     pub fn to_stroke_preview(&self) -> StrokePreview {
-        StrokePreview::new(self.points.clone(), self.thickness, self.color)
+        StrokePreview::new(self.points.clone(), self.thickness(), self.color())
+            .with_style(self.style)
     }
-}
\ No newline at end of file
+}
+
+/// Point `t` of the way from `p1` to `p2` on a Catmull-Rom spline through all four
+fn catmull_rom(p0: Pos2, p1: Pos2, p2: Pos2, p3: Pos2, t: f32) -> Pos2 {
+    let (t2, t3) = (t * t, t * t * t);
+    let [p0, p1, p2, p3] = [p0, p1, p2, p3].map(|p| p.to_vec2());
+    let point = (p1 * 2.0
+        + (p2 - p0) * t
+        + (p0 * 2.0 - p1 * 5.0 + p2 * 4.0 - p3) * t2
+        + (p1 * 3.0 - p0 - p2 * 3.0 + p3) * t3)
+        * 0.5;
+    point.to_pos2()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_add_point_fills_in_long_gaps() {
+        let mut stroke = DrawStrokeHelper::new(Color32::BLACK, 2.0);
+        let samples = [
+            Pos2::new(0.0, 0.0),
+            Pos2::new(2.0, 0.0),
+            Pos2::new(42.0, 0.0),
+            Pos2::new(42.0, 40.0),
+        ];
+        for sample in samples {
+            stroke.add_point(sample);
+        }
+
+        // Every sample is kept, in order, with gaps around the spacing except
+        // the last one, which waits for the next sample
+        let points = stroke.points();
+        assert!(points.len() > samples.len());
+        let mut rest = points.iter();
+        assert!(samples.iter().all(|sample| rest.any(|p| p == sample)));
+        let (body, last) = points.split_at(points.len() - 1);
+        let longest = body.windows(2).map(|w| w[0].distance(w[1])).fold(0.0, f32::max);
+        assert!(longest <= INTERPOLATION_SPACING * 1.5);
+        assert_eq!(body.last().unwrap().distance(last[0]), 40.0);
+
+        // Short gaps are left alone
+        let mut slow = DrawStrokeHelper::new(Color32::BLACK, 2.0);
+        for i in 0..5 {
+            slow.add_point(Pos2::new(i as f32, 0.0));
+        }
+        assert_eq!(slow.points().len(), 5);
+    }
+
+    #[test]
+    fn test_stroke_follows_its_firmest_pressure() {
+        let response = PressureResponse {
+            size: crate::pressure::PressureCurve::linear(0.0, 1.0),
+            opacity: crate::pressure::PressureCurve::linear(0.5, 1.0),
+        };
+        let mut stroke = DrawStrokeHelper::new(Color32::BLACK, 10.0);
+        assert_eq!(stroke.thickness(), 10.0);
+
+        stroke.press(0.5, &response);
+        stroke.press(0.2, &response);
+        assert!((stroke.thickness() - 5.0).abs() < 1e-4);
+        assert!(stroke.color().a() < 255);
+    }
+}
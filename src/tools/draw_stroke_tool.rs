@@ -1,20 +1,42 @@
+use crate::arrowhead::Arrowhead;
 use crate::command::Command;
+use crate::element::path::Path;
+use crate::element::{
+    DashPattern, Element, ElementType, LineCap, LineJoin, MAX_TAPER, StrokeStyle,
+};
+use crate::geometry::snap_angle;
+use crate::guides::Guides;
+use crate::pressure::PressureResponse;
 use crate::renderer::Renderer;
+use crate::ruler_guides::snap_position;
+use crate::settings::{CurveFitting, SnapIncrements};
 use crate::state::EditorModel;
-use crate::tools::{Tool, ToolConfig};
+use crate::tools::{ColorPair, Tool, ToolConfig};
 use crate::tools::draw_stroke_helper::DrawStrokeHelper;
+use crate::tools::shape_recognition::recognize_shape;
 use egui::{Color32, Pos2, Ui};
-use log::info;
+use log::{debug, trace};
 use std::any::Any;
 use std::fmt;
+use std::ops::RangeInclusive;
 // Use web-time instead of std::time for cross-platform compatibility
 use web_time::Instant;
 
+const THICKNESS_RANGE: RangeInclusive<f32> = 1.0..=20.0;
+// Longest dash or gap of a custom pattern, in multiples of the thickness
+const MAX_DASH_LENGTH: f32 = 20.0;
+// Pattern a line switched to a custom one starts with: dash-dot
+const CUSTOM_DASH_START: [f32; 4] = [4.0, 2.0, 0.0, 2.0];
+
 // Config for DrawStrokeTool
 #[derive(Clone)]
 pub struct DrawStrokeConfig {
     pub color: Color32,
     pub thickness: f32,
+    /// Replace finished strokes that look like lines, arrows, rectangles or circles
+    pub auto_shape: bool,
+    /// Convert finished strokes into curves, and how closely
+    pub curve_fitting: CurveFitting,
 }
 
 impl ToolConfig for DrawStrokeConfig {
@@ -38,6 +60,8 @@ pub enum DrawStrokeState {
     Drawing { 
         stroke: DrawStrokeHelper,
         start_time: Instant, // Using web_time::Instant for WASM compatibility
+        // Start of the straight segment Shift is holding, whose end follows the pointer
+        anchor: Option<Pos2>,
     },
 }
 
@@ -46,7 +70,9 @@ impl fmt::Debug for DrawStrokeState {
     fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
         match self {
             Self::Idle => write!(f, "Idle"),
-            Self::Drawing { stroke, start_time } => f
+            Self::Drawing {
+                stroke, start_time, ..
+            } => f
                 .debug_struct("Drawing")
                 .field("stroke_points", &stroke.points().len())
                 .field("duration_ms", &start_time.elapsed().as_millis())
@@ -60,7 +86,28 @@ impl fmt::Debug for DrawStrokeState {
 pub struct UnifiedDrawStrokeTool {
     pub state: DrawStrokeState,
     pub default_color: Color32,
+    // Color drawn with while Shift is held
+    pub alternate_color: Color32,
     pub default_thickness: f32,
+    pub auto_shape: bool,
+    pub curve_fitting: CurveFitting,
+    /// Taper, caps and joins new strokes are drawn with
+    pub style: StrokeStyle,
+    /// How the stroke follows pen pressure
+    pub pressure_response: PressureResponse,
+    // Latest pen pressure, kept for the stroke the pen is about to start
+    pressure: Option<f32>,
+    // Angle segments drawn with Shift held are kept to multiples of, in degrees
+    pub angle_snap: f32,
+    // Guides strokes are drawn straight along when their snapping is on
+    pub guides: Guides,
+    // How close points have to come to a ruler guide to land on it, taken
+    // from the zoom the stroke started at; None while the guides are hidden
+    ruler_guide_snap: Option<f32>,
+    // Swap of the last finished stroke for the shape it was recognized as or
+    // the curves fitted to it, run as a separate step so undo brings the raw
+    // stroke back
+    pending_replacement: Option<Command>,
 }
 
 impl UnifiedDrawStrokeTool {
@@ -68,48 +115,134 @@ impl UnifiedDrawStrokeTool {
         Self {
             state: DrawStrokeState::Idle,
             default_color: Color32::BLACK,
+            alternate_color: Color32::WHITE,
             default_thickness: 2.0,
+            auto_shape: false,
+            curve_fitting: CurveFitting::Off,
+            style: StrokeStyle::default(),
+            pressure_response: PressureResponse::default(),
+            pressure: None,
+            angle_snap: SnapIncrements::default().angle,
+            guides: Guides::default(),
+            ruler_guide_snap: None,
+            pending_replacement: None,
         }
     }
 
     pub fn start_drawing(&mut self, pos: Pos2, color: Color32, thickness: f32) {
-        info!("start_drawing called at position: {:?}", pos);
-
-        let mut stroke = DrawStrokeHelper::new(color, thickness);
+        let mut stroke = DrawStrokeHelper::new(color, thickness).with_style(self.style);
         stroke.add_point(pos);
+        if let Some(pressure) = self.pressure {
+            stroke.press(pressure, &self.pressure_response);
+        }
 
         self.state = DrawStrokeState::Drawing { 
             stroke,
             start_time: Instant::now(),
+            anchor: None,
         };
 
-        info!(
-            "Successfully created Drawing state with initial point at {:?}",
-            pos
-        );
+        debug!(pos:? = pos; "Started stroke");
     }
 
     pub fn add_point(&mut self, pos: Pos2) {
         if let DrawStrokeState::Drawing { stroke, .. } = &mut self.state {
-            info!("add_point called with position: {:?}", pos);
             stroke.add_point(pos);
+            trace!(pos:? = pos, points = stroke.points().len(); "Added stroke point");
         }
     }
 
-    pub fn finish_drawing(&mut self) -> Option<Command> {
-        info!("finish_drawing called");
+    /// `pos` pulled onto the document's ruler guides close to it
+    fn snap_to_ruler_guides(&self, pos: Pos2, editor_model: &EditorModel) -> Pos2 {
+        self.ruler_guide_snap.map_or(pos, |tolerance| {
+            snap_position(&editor_model.ruler_guides, pos, tolerance)
+        })
+    }
 
-        if let DrawStrokeState::Drawing { stroke, .. } = &self.state {
+    /// Extend the stroke to `pos`
+    ///
+    /// With `constrain` the stroke runs straight on from where the constraint
+    /// began, at a multiple of the angle step; the end of that segment follows
+    /// the pointer until the constraint is let go. Otherwise, while strokes
+    /// snap to guides, the stroke is one straight line from where it started
+    /// along the guide closest to the way the pointer went.
+    pub fn extend_to(&mut self, pos: Pos2, constrain: bool) {
+        let step = self.angle_snap.to_radians();
+        let DrawStrokeState::Drawing { stroke, anchor, .. } = &mut self.state else {
+            return;
+        };
+        if !constrain {
+            *anchor = None;
+            let start = stroke.points().first().copied();
+            match start.filter(|_| self.guides.snaps()) {
+                Some(start) if stroke.points().len() > 1 => {
+                    stroke.move_last_point(self.guides.snap(start, pos));
+                }
+                Some(start) => stroke.add_straight_point(self.guides.snap(start, pos)),
+                None => stroke.add_point(pos),
+            }
+            return;
+        }
+        match *anchor {
+            Some(from) => stroke.move_last_point(snap_angle(from, pos, step)),
+            None => {
+                let from = stroke.points().last().copied().unwrap_or(pos);
+                *anchor = Some(from);
+                stroke.add_straight_point(snap_angle(from, pos, step));
+            }
+        }
+    }
+
+    pub fn finish_drawing(&mut self, editor_model: &EditorModel) -> Option<Command> {
+        if let DrawStrokeState::Drawing { stroke, anchor, .. } = &self.state {
             // Only finish if we have at least 2 points
             if stroke.points().len() >= 2 {
                 // Get the stroke data
-                let id = crate::id_generator::generate_id();
+                let id = editor_model.reserve_id();
                 let points = stroke.points().to_vec();
                 let color = stroke.color();
                 let thickness = stroke.thickness();
 
                 // Create a stroke element using the element factory
-                let element = crate::element::factory::create_stroke(id, points.clone(), thickness, color);
+                let element = crate::element::factory::create_styled_stroke(
+                    id,
+                    points.clone(),
+                    thickness,
+                    color,
+                    stroke.style(),
+                );
+
+                // Queue the clean shape to replace the stroke once it's been added
+                // Shapes finished with Shift held come out square, round or
+                // at an angle step, like the segments
+                let constrain = anchor.is_some();
+                let step = self.angle_snap.to_radians();
+                self.pending_replacement = self
+                    .auto_shape
+                    .then(|| recognize_shape(&points))
+                    .flatten()
+                    .map(|shape| {
+                        if constrain {
+                            shape.constrained(step)
+                        } else {
+                            shape
+                        }
+                    })
+                    .map(|shape| {
+                        debug!(id:% = id, shape = shape.name(); "Recognized stroke as a shape");
+                        let new_id = editor_model.reserve_id();
+                        Command::new_replace_elements(
+                            editor_model,
+                            "Auto Shape",
+                            vec![element.clone()],
+                            vec![shape.to_element(new_id, thickness, color)],
+                        )
+                    });
+
+                // Otherwise queue curves fitted to it, keeping its id
+                if self.pending_replacement.is_none() {
+                    self.pending_replacement = self.fit_curves(editor_model, &element);
+                }
 
                 // Create the command using the unified AddElement variant
                 let command = Command::AddElement { element };
@@ -117,21 +250,39 @@ impl UnifiedDrawStrokeTool {
                 // Reset to Idle state
                 self.state = DrawStrokeState::Idle;
 
-                info!(
-                    "Successfully finished stroke with ID {} and {} points, generated command",
-                    id,
-                    points.len()
-                );
+                debug!(id:% = id, points = points.len(); "Finished stroke");
                 return Some(command);
             }
         }
 
         // If we can't finish (not in Drawing state or not enough points), just reset
         self.state = DrawStrokeState::Idle;
-        info!("Reset to Idle state without generating command");
+        trace!("Finished without a stroke to add");
         None
     }
 
+    /// Replacement of a finished stroke by curves fitted to it, if curve
+    /// fitting is on and the curves store fewer points
+    fn fit_curves(&self, editor_model: &EditorModel, element: &ElementType) -> Option<Command> {
+        let tolerance = self.curve_fitting.tolerance()?;
+        let ElementType::Stroke(stroke) = element else {
+            return None;
+        };
+        let path = Path::fitted_to(stroke, tolerance)?;
+        debug!(
+            id:% = stroke.id(),
+            points = stroke.points().len(),
+            nodes = path.nodes().len();
+            "Fitted stroke to curves"
+        );
+        Some(Command::new_replace_elements(
+            editor_model,
+            "Fit Curves",
+            vec![element.clone()],
+            vec![ElementType::Path(path)],
+        ))
+    }
+
     // Get the current state name
     pub fn current_state_name(&self) -> &'static str {
         match self.state {
@@ -149,7 +300,8 @@ impl Tool for UnifiedDrawStrokeTool {
     fn activate(&mut self, _editor_model: &EditorModel) {
         // Reset to Idle state when activated
         self.state = DrawStrokeState::Idle;
-        info!("DrawStrokeTool activated and reset to Idle state");
+        self.pending_replacement = None;
+        trace!(tool = self.name(); "Tool activated");
     }
 
     fn deactivate(
@@ -158,7 +310,8 @@ impl Tool for UnifiedDrawStrokeTool {
     ) {
         // Reset to Idle state when deactivated
         self.state = DrawStrokeState::Idle;
-        info!("DrawStrokeTool deactivated and reset to Idle state");
+        self.pending_replacement = None;
+        trace!(tool = self.name(); "Tool deactivated");
     }
 
     fn on_pointer_down(
@@ -166,26 +319,25 @@ impl Tool for UnifiedDrawStrokeTool {
         pos: Pos2,
         button: egui::PointerButton,
         _modifiers: &egui::Modifiers,
-        _editor_model: &EditorModel,
-        _renderer: &mut Renderer
+        editor_model: &EditorModel,
+        renderer: &mut Renderer,
     ) -> Option<Command> {
-        info!(
-            "DrawStrokeTool::on_pointer_down called at position: {:?} with button: {:?}",
-            pos, button
-        );
+        trace!(pos:? = pos, button:? = button; "Pointer down");
 
         // Only handle primary button for drawing
         if button != egui::PointerButton::Primary {
             return None;
         }
+        self.ruler_guide_snap = renderer.guide_snap_distance();
+        let pos = self.snap_to_ruler_guides(pos, editor_model);
 
         // Determine stroke color and thickness based on tool settings and modifiers
         let mut color = self.default_color;
         let mut thickness = self.default_thickness;
 
-        // Example modifier: Shift for alternate color (red)
+        // Shift draws with the background color
         if _modifiers.shift {
-            color = Color32::RED;
+            color = self.alternate_color;
         }
 
         // Example modifier: Ctrl for thicker stroke
@@ -211,8 +363,8 @@ impl Tool for UnifiedDrawStrokeTool {
         &mut self,
         pos: Pos2,
         held_buttons: &[egui::PointerButton],
-        _modifiers: &egui::Modifiers,
-        _editor_model: &mut EditorModel,
+        modifiers: &egui::Modifiers,
+        editor_model: &mut EditorModel,
         _ui: &egui::Ui,
         _renderer: &mut Renderer
     ) -> Option<Command> {
@@ -220,31 +372,23 @@ impl Tool for UnifiedDrawStrokeTool {
         if !held_buttons.contains(&egui::PointerButton::Primary) {
             return None;
         }
+        let pos = self.snap_to_ruler_guides(pos, editor_model);
 
-        match &mut self.state {
-            DrawStrokeState::Drawing { stroke, .. } => {
-                // Add the point to the stroke
-                stroke.add_point(pos);
-                
-                // No need to call update_preview here as it will be called by the app
-                // after handling input events
-                None
-            }
-            _ => None,
-        }
+        // Shift keeps the segment straight at an angle step. No need to call
+        // update_preview here as it will be called by the app after handling
+        // input events
+        self.extend_to(pos, modifiers.shift);
+        None
     }
 
     fn on_pointer_up(
         &mut self,
         pos: Pos2,
         button: egui::PointerButton,
-        _modifiers: &egui::Modifiers,
-        _editor_model: &EditorModel
+        modifiers: &egui::Modifiers,
+        editor_model: &EditorModel,
     ) -> Option<Command> {
-        info!(
-            "DrawStrokeTool::on_pointer_up called at position: {:?} with button: {:?}",
-            pos, button
-        );
+        trace!(pos:? = pos, button:? = button; "Pointer up");
 
         // Only handle primary button for drawing
         if button != egui::PointerButton::Primary {
@@ -255,15 +399,28 @@ impl Tool for UnifiedDrawStrokeTool {
             DrawStrokeState::Idle => None,
             DrawStrokeState::Drawing { .. } => {
                 // Add the final point and finish the stroke
-                self.add_point(pos);
-                self.finish_drawing()
+                let pos = self.snap_to_ruler_guides(pos, editor_model);
+                self.extend_to(pos, modifiers.shift);
+                self.pressure = None;
+                self.finish_drawing(editor_model)
             }
         }
     }
 
+    fn follow_up_command(&mut self, _editor_model: &EditorModel) -> Option<Command> {
+        self.pending_replacement.take()
+    }
+
+    fn brush_outline(&self) -> Option<f32> {
+        match &self.state {
+            DrawStrokeState::Drawing { stroke, .. } => Some(stroke.thickness()),
+            DrawStrokeState::Idle => Some(self.default_thickness),
+        }
+    }
+
     fn reset_interaction_state(&mut self) {
         self.state = DrawStrokeState::Idle;
-        info!("Reset interaction state to Idle");
+        trace!("Reset interaction state");
     }
 
     fn update_preview(&mut self, renderer: &mut Renderer) {
@@ -273,47 +430,30 @@ impl Tool for UnifiedDrawStrokeTool {
                 renderer.clear_stroke_preview();
             }
             DrawStrokeState::Drawing { stroke, .. } => {
-                // Use the new renderer methods directly instead of creating a StrokePreview
-                renderer.set_stroke_preview(
-                    stroke.points().to_vec(),
-                    stroke.thickness(),
-                    stroke.color()
-                );
-                info!("Updated stroke preview with {} points", stroke.points().len());
+                renderer.set_styled_stroke_preview(stroke.to_stroke_preview());
+                trace!(points = stroke.points().len(); "Updated stroke preview");
             }
         }
     }
 
     fn clear_preview(&mut self, renderer: &mut Renderer) {
         renderer.clear_stroke_preview();
-        info!("Cleared stroke preview");
+        trace!("Cleared stroke preview");
     }
 
-    fn on_key(
-        &mut self,
-        key: egui::Key,
-        pressed: bool,
-        _modifiers: &egui::Modifiers,
-        _editor_model: &EditorModel
-    ) {
-        // Only handle key press events (not releases)
-        if !pressed {
-            return;
-        }
+    fn brush_size(&self) -> Option<(f32, RangeInclusive<f32>)> {
+        Some((self.default_thickness, THICKNESS_RANGE))
+    }
 
-        // Add keyboard shortcuts for adjusting stroke properties
-        match key {
-            egui::Key::ArrowUp if _modifiers.ctrl => {
-                // Increase stroke thickness
-                self.default_thickness = (self.default_thickness + 1.0).min(20.0);
-                info!("Increased stroke thickness to {}", self.default_thickness);
-            }
-            egui::Key::ArrowDown if _modifiers.ctrl => {
-                // Decrease stroke thickness
-                self.default_thickness = (self.default_thickness - 1.0).max(1.0);
-                info!("Decreased stroke thickness to {}", self.default_thickness);
-            }
-            _ => {}
+    fn set_brush_size(&mut self, size: f32) {
+        self.default_thickness = size;
+        debug!(thickness = size; "Set stroke thickness");
+    }
+
+    fn set_pressure(&mut self, pressure: f32) {
+        self.pressure = Some(pressure);
+        if let DrawStrokeState::Drawing { stroke, .. } = &mut self.state {
+            stroke.press(pressure, &self.pressure_response);
         }
     }
 
@@ -322,30 +462,64 @@ impl Tool for UnifiedDrawStrokeTool {
             DrawStrokeState::Idle => {
                 ui.label("Drawing Tool Settings:");
 
-                // Color picker
-                ui.horizontal(|ui| {
-                    ui.label("Stroke color:");
-                    ui.color_edit_button_srgba(&mut self.default_color);
-                });
-
                 // Thickness slider
                 ui.horizontal(|ui| {
                     ui.label("Thickness:");
-                    ui.add(egui::Slider::new(&mut self.default_thickness, 1.0..=20.0).text("px"));
+                    ui.add(
+                        egui::Slider::new(&mut self.default_thickness, THICKNESS_RANGE).text("px"),
+                    );
                 });
 
+                ui.checkbox(&mut self.auto_shape, "Auto-shape")
+                    .on_hover_text(
+                        "Replace strokes that look like lines, arrows, rectangles or circles \
+                         with clean shapes. Undo brings the stroke back",
+                    );
+
+                ui.horizontal(|ui| {
+                    ui.label("Fit to curves:");
+                    egui::ComboBox::from_id_salt("draw_stroke_curve_fitting")
+                        .selected_text(self.curve_fitting.label())
+                        .show_ui(ui, |ui| {
+                            for fitting in CurveFitting::ALL {
+                                ui.selectable_value(
+                                    &mut self.curve_fitting,
+                                    fitting,
+                                    fitting.label(),
+                                );
+                            }
+                        });
+                })
+                .response
+                .on_hover_text(
+                    "Store finished strokes as smooth curves with far fewer points. \
+                     Undo brings the stroke back",
+                );
+
+                ui.separator();
+                stroke_style_ui(ui, &mut self.style);
+
                 ui.separator();
                 ui.label("Use the mouse to draw on the canvas.");
                 
                 // Display keyboard shortcuts
                 ui.separator();
                 ui.label("Keyboard Shortcuts:");
-                ui.label("• Shift + Click: Draw with red color");
+                ui.label("• Shift + Click: Draw with the background color");
+                ui.label(format!(
+                    "• Hold Shift: Keep lines at {}° steps, shapes square or round",
+                    self.angle_snap
+                ));
                 ui.label("• Ctrl + Click: Double stroke thickness");
-                ui.label("• Ctrl + ↑: Increase thickness");
-                ui.label("• Ctrl + ↓: Decrease thickness");
+                ui.label("• ] or Ctrl + ↑: Increase thickness");
+                ui.label("• [ or Ctrl + ↓: Decrease thickness");
+                ui.label("• Ctrl + Alt + Drag: Change thickness");
+                ui.label("• Hold Ctrl: Select until it's let go");
+                ui.label("• Hold Space + Drag: Pan the view");
             }
-            DrawStrokeState::Drawing { stroke, start_time } => {
+            DrawStrokeState::Drawing {
+                stroke, start_time, ..
+            } => {
                 ui.label("Currently drawing...");
                 
                 // Show duration
@@ -368,6 +542,8 @@ impl Tool for UnifiedDrawStrokeTool {
         Box::new(DrawStrokeConfig {
             color: self.default_color,
             thickness: self.default_thickness,
+            auto_shape: self.auto_shape,
+            curve_fitting: self.curve_fitting,
         })
     }
 
@@ -375,8 +551,112 @@ impl Tool for UnifiedDrawStrokeTool {
         if let Some(config) = config.as_any().downcast_ref::<DrawStrokeConfig>() {
             self.default_color = config.color;
             self.default_thickness = config.thickness;
+            self.auto_shape = config.auto_shape;
+            self.curve_fitting = config.curve_fitting;
+        } else if let Some(colors) = config.as_any().downcast_ref::<ColorPair>() {
+            self.default_color = colors.foreground;
+            self.alternate_color = colors.background;
+        } else if let Some(snap) = config.as_any().downcast_ref::<SnapIncrements>() {
+            self.angle_snap = snap.angle;
+        } else if let Some(guides) = config.as_any().downcast_ref::<Guides>() {
+            self.guides = guides.clone();
+        } else if let Some(response) = config.as_any().downcast_ref::<PressureResponse>() {
+            self.pressure_response = response.clone();
+        }
+    }
+}
+
+/// Taper sliders and cap, join, dash and arrowhead choices for a stroke's style
+///
+/// Shared with the selection tool, which edits the style of selected strokes
+/// and paths.
+pub fn stroke_style_ui(ui: &mut Ui, style: &mut StrokeStyle) -> egui::Response {
+    let taper_response = ui
+        .add(taper(&mut style.taper_start, "Taper start"))
+        .on_hover_text("Share of the stroke's length it narrows over at each end")
+        | ui.add(taper(&mut style.taper_end, "Taper end"));
+    let cap_response = ui
+        .horizontal(|ui| {
+            let mut response = ui.label("Caps:");
+            for cap in LineCap::ALL {
+                response |= ui.selectable_value(&mut style.cap, cap, cap.label());
+            }
+            response
+        })
+        .inner;
+    let join_response = ui
+        .horizontal(|ui| {
+            let mut response = ui.label("Joins:");
+            for join in LineJoin::ALL {
+                response |= ui.selectable_value(&mut style.join, join, join.label());
+            }
+            response
+        })
+        .inner;
+    let arrow_response = arrowhead_ui(ui, "Start:", &mut style.start_arrow)
+        | arrowhead_ui(ui, "End:", &mut style.end_arrow);
+    taper_response | cap_response | join_response | dash_ui(ui, &mut style.dash) | arrow_response
+}
+
+/// Choice of the arrowhead at one end of a line
+fn arrowhead_ui(ui: &mut Ui, label: &str, head: &mut Arrowhead) -> egui::Response {
+    ui.horizontal(|ui| {
+        let mut response = ui.label(label);
+        for choice in Arrowhead::ALL {
+            response |= ui.selectable_value(head, choice, choice.label());
         }
+        response
+    })
+    .inner
+}
+
+/// Choice of a solid, dashed, dotted or custom line, with the custom pattern's lengths
+fn dash_ui(ui: &mut Ui, dash: &mut DashPattern) -> egui::Response {
+    let mut response = ui
+        .horizontal(|ui| {
+            let mut response = ui.label("Line:");
+            for preset in DashPattern::PRESETS {
+                let choice = ui.selectable_value(dash, preset, preset.label());
+                response |= match preset {
+                    DashPattern::Dotted => choice.on_hover_text("Dots need round caps"),
+                    _ => choice,
+                };
+            }
+            let is_custom = matches!(dash, DashPattern::Custom(_));
+            let mut custom = ui.selectable_label(is_custom, "Custom");
+            if custom.clicked() && !is_custom {
+                *dash = DashPattern::Custom(CUSTOM_DASH_START);
+                custom.mark_changed();
+            }
+            response | custom
+        })
+        .inner;
+
+    if let DashPattern::Custom(lengths) = dash {
+        ui.horizontal(|ui| {
+            ui.label("Dash, gap:")
+                .on_hover_text("Lengths in turn, in multiples of the thickness");
+            for length in lengths.iter_mut() {
+                response |= ui.add(
+                    egui::DragValue::new(length)
+                        .range(0.0..=MAX_DASH_LENGTH)
+                        .speed(0.1),
+                );
+            }
+        });
     }
+    response
+}
+
+/// Slider for a taper, shown as a percentage of the stroke's length
+fn taper<'a>(value: &'a mut f32, text: &str) -> egui::Slider<'a> {
+    egui::Slider::new(value, 0.0..=MAX_TAPER)
+        .text(text)
+        .custom_formatter(|value, _| format!("{:.0}%", value * 100.0))
+        .custom_parser(|text| {
+            let percent = text.trim().trim_end_matches('%').parse::<f64>().ok()?;
+            Some(percent / 100.0)
+        })
 }
 
 impl Default for UnifiedDrawStrokeTool {
@@ -389,3 +669,56 @@ impl Default for UnifiedDrawStrokeTool {
 pub fn new_draw_stroke_tool() -> UnifiedDrawStrokeTool {
     UnifiedDrawStrokeTool::new()
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::element::Element;
+
+    #[test]
+    fn test_shift_keeps_segments_at_angle_steps() {
+        let mut model = EditorModel::new();
+        let mut tool = new_draw_stroke_tool();
+        tool.start_drawing(Pos2::new(0.0, 0.0), Color32::BLACK, 2.0);
+        tool.extend_to(Pos2::new(10.0, 0.0), false);
+
+        // The constrained segment starts at the last point and its end follows
+        // the pointer, swung to the nearest 45° step
+        tool.extend_to(Pos2::new(40.0, 4.0), true);
+        tool.extend_to(Pos2::new(40.0, 33.0), true);
+        let DrawStrokeState::Drawing { stroke, .. } = &tool.state else {
+            panic!("expected a stroke being drawn");
+        };
+        let points = stroke.points().to_vec();
+        assert_eq!(points.len(), 3);
+        let segment = points[2] - points[1];
+        assert!((segment.x - segment.y).abs() < 0.001);
+        assert!((segment.length() - egui::Vec2::new(30.0, 33.0).length()).abs() < 0.001);
+
+        // Letting go of Shift carries on freehand from the segment's end
+        tool.extend_to(Pos2::new(60.0, 50.0), false);
+        let DrawStrokeState::Drawing { stroke, .. } = &tool.state else {
+            panic!("expected a stroke being drawn");
+        };
+        assert_eq!(stroke.points()[2], points[2]);
+        assert_eq!(stroke.points().last(), Some(&Pos2::new(60.0, 50.0)));
+
+        // Auto-shapes finished with Shift held come out square
+        tool.auto_shape = true;
+        tool.start_drawing(Pos2::new(0.0, 0.0), Color32::BLACK, 2.0);
+        for pos in [(100.0, 0.0), (100.0, 60.0), (0.0, 60.0), (0.0, 0.0)] {
+            tool.extend_to(Pos2::new(pos.0, pos.1), false);
+        }
+        tool.extend_to(Pos2::new(0.0, 1.0), true);
+        tool.finish_drawing(&model)
+            .unwrap()
+            .execute(&mut model)
+            .unwrap();
+        tool.follow_up_command(&model)
+            .unwrap()
+            .execute(&mut model)
+            .unwrap();
+        let square = model.elements.last().unwrap().rect();
+        assert!((square.width() - square.height()).abs() < 1.0);
+    }
+}
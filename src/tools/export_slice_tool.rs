@@ -0,0 +1,227 @@
+use crate::command::Command;
+use crate::export_slice::{ExportSlice, next_slice_name};
+use crate::renderer::Renderer;
+use crate::state::EditorModel;
+use crate::tools::{Tool, ToolConfig};
+use egui::{Pos2, Rect, Ui};
+use log::{debug, trace};
+use std::any::Any;
+
+// Smallest slice, in document units; shorter drags are taken as stray clicks
+const MIN_SLICE_SIZE: f32 = 4.0;
+
+// Config for ExportSliceTool (it has no settings yet)
+#[derive(Clone, Debug)]
+pub struct ExportSliceToolConfig;
+
+impl ToolConfig for ExportSliceToolConfig {
+    fn tool_name(&self) -> &'static str {
+        "Export Slice"
+    }
+
+    fn as_any(&self) -> &dyn Any {
+        self
+    }
+
+    fn as_any_mut(&mut self) -> &mut dyn Any {
+        self
+    }
+}
+
+// State enum for the ExportSliceTool
+#[derive(Clone, Debug)]
+pub enum ExportSliceState {
+    Idle,
+    Dragging { start_pos: Pos2, current_pos: Pos2 },
+}
+
+/// Tool that marks out a named export slice by dragging a rectangle
+#[derive(Clone, Debug)]
+pub struct UnifiedExportSliceTool {
+    pub state: ExportSliceState,
+}
+
+impl UnifiedExportSliceTool {
+    pub fn new() -> Self {
+        Self {
+            state: ExportSliceState::Idle,
+        }
+    }
+
+    // Get the current state name
+    pub fn current_state_name(&self) -> &'static str {
+        match self.state {
+            ExportSliceState::Idle => "Idle",
+            ExportSliceState::Dragging { .. } => "Dragging",
+        }
+    }
+}
+
+/// Build a command adding a slice covering `rect`, named after the slices so far
+///
+/// Returns None if the rectangle is too small to be meant as a slice.
+pub fn add_slice_command(editor_model: &EditorModel, rect: Rect) -> Option<Command> {
+    if rect.width() < MIN_SLICE_SIZE || rect.height() < MIN_SLICE_SIZE {
+        return None;
+    }
+    let mut slices = editor_model.slices.clone();
+    let slice = ExportSlice::new(next_slice_name(&slices), rect);
+    debug!(name:% = slice.name, rect:? = rect; "Adding slice");
+    slices.push(slice);
+    Command::new_set_export_slices(editor_model, "Add Slice", slices)
+}
+
+impl Tool for UnifiedExportSliceTool {
+    fn name(&self) -> &'static str {
+        "Export Slice"
+    }
+
+    fn activate(&mut self, _editor_model: &EditorModel) {
+        self.state = ExportSliceState::Idle;
+        trace!(tool = self.name(); "Tool activated");
+    }
+
+    fn deactivate(&mut self, _editor_model: &EditorModel) {
+        self.state = ExportSliceState::Idle;
+        trace!(tool = self.name(); "Tool deactivated");
+    }
+
+    fn on_pointer_down(
+        &mut self,
+        pos: Pos2,
+        button: egui::PointerButton,
+        _modifiers: &egui::Modifiers,
+        _editor_model: &EditorModel,
+        _renderer: &mut Renderer,
+    ) -> Option<Command> {
+        if button == egui::PointerButton::Primary {
+            self.state = ExportSliceState::Dragging {
+                start_pos: pos,
+                current_pos: pos,
+            };
+        }
+        None
+    }
+
+    fn on_pointer_move(
+        &mut self,
+        pos: Pos2,
+        held_buttons: &[egui::PointerButton],
+        _modifiers: &egui::Modifiers,
+        _editor_model: &mut EditorModel,
+        _ui: &egui::Ui,
+        _renderer: &mut Renderer,
+    ) -> Option<Command> {
+        if !held_buttons.contains(&egui::PointerButton::Primary) {
+            return None;
+        }
+        if let ExportSliceState::Dragging { current_pos, .. } = &mut self.state {
+            *current_pos = pos;
+        }
+        None
+    }
+
+    fn on_pointer_up(
+        &mut self,
+        pos: Pos2,
+        button: egui::PointerButton,
+        _modifiers: &egui::Modifiers,
+        editor_model: &EditorModel,
+    ) -> Option<Command> {
+        if button != egui::PointerButton::Primary {
+            return None;
+        }
+
+        let ExportSliceState::Dragging { start_pos, .. } = self.state else {
+            return None;
+        };
+        self.state = ExportSliceState::Idle;
+        add_slice_command(editor_model, Rect::from_two_pos(start_pos, pos))
+    }
+
+    fn cursor_icon(
+        &self,
+        _pos: Pos2,
+        _editor_model: &EditorModel,
+        _renderer: &Renderer,
+    ) -> Option<egui::CursorIcon> {
+        Some(egui::CursorIcon::Crosshair)
+    }
+
+    fn reset_interaction_state(&mut self) {
+        self.state = ExportSliceState::Idle;
+    }
+
+    fn update_preview(&mut self, renderer: &mut Renderer) {
+        match self.state {
+            ExportSliceState::Idle => renderer.set_lasso_preview(None),
+            ExportSliceState::Dragging {
+                start_pos,
+                current_pos,
+            } => {
+                let rect = Rect::from_two_pos(start_pos, current_pos);
+                renderer.set_lasso_preview(Some(vec![
+                    rect.left_top(),
+                    rect.right_top(),
+                    rect.right_bottom(),
+                    rect.left_bottom(),
+                ]));
+            }
+        }
+    }
+
+    fn clear_preview(&mut self, renderer: &mut Renderer) {
+        renderer.set_lasso_preview(None);
+    }
+
+    fn ui(&mut self, ui: &mut Ui, _editor_model: &EditorModel) -> Option<Command> {
+        ui.label("Export Slice Tool");
+        ui.label("Drag a rectangle to mark out a slice; the Slices panel exports them all.");
+        None
+    }
+
+    fn get_config(&self) -> Box<dyn ToolConfig> {
+        Box::new(ExportSliceToolConfig)
+    }
+
+    fn apply_config(&mut self, _config: &dyn ToolConfig) {}
+}
+
+impl Default for UnifiedExportSliceTool {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+// Factory function to create a new ExportSliceTool
+pub fn new_export_slice_tool() -> UnifiedExportSliceTool {
+    UnifiedExportSliceTool::new()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_dragged_slices_are_named_in_turn() {
+        let mut model = EditorModel::new();
+        let rect = Rect::from_min_max(Pos2::new(10.0, 10.0), Pos2::new(60.0, 40.0));
+        add_slice_command(&model, rect)
+            .unwrap()
+            .execute(&mut model)
+            .unwrap();
+        add_slice_command(&model, rect.translate(egui::vec2(100.0, 0.0)))
+            .unwrap()
+            .execute(&mut model)
+            .unwrap();
+
+        let names: Vec<&str> = model.slices.iter().map(|slice| slice.name.as_str()).collect();
+        assert_eq!(names, ["Slice 1", "Slice 2"]);
+        assert_eq!(model.slices[0].rect, rect);
+        assert_eq!(model.slices[1].file_name("@2x"), "slice-2@2x.png");
+
+        // A click isn't a slice
+        let click = Rect::from_min_size(Pos2::new(5.0, 5.0), egui::vec2(1.0, 1.0));
+        assert!(add_slice_command(&model, click).is_none());
+    }
+}
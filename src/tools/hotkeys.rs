@@ -0,0 +1,196 @@
+use egui::Key;
+use std::ops::RangeInclusive;
+
+/// Single-key shortcuts for switching tools, by tool name
+pub const TOOL_HOTKEYS: &[(Key, &str)] = &[
+    (Key::B, "Draw Stroke"),
+    (Key::V, "Selection"),
+    (Key::K, "Slice"),
+    (Key::A, "Annotate"),
+    (Key::P, "Pen"),
+    (Key::U, "Polygon"),
+    (Key::I, "Pixel Brush"),
+    (Key::N, "Sticky Note"),
+    (Key::L, "Laser Pointer"),
+];
+
+/// Key swapping the foreground and background colors
+pub const SWAP_COLORS_KEY: Key = Key::X;
+
+/// Keys shrinking and growing the active tool's brush
+pub const BRUSH_SMALLER_KEY: Key = Key::OpenBracket;
+pub const BRUSH_LARGER_KEY: Key = Key::CloseBracket;
+
+/// Keys shrinking and growing the active tool's brush with Ctrl held
+pub const BRUSH_SMALLER_CTRL_KEY: Key = Key::ArrowDown;
+pub const BRUSH_LARGER_CTRL_KEY: Key = Key::ArrowUp;
+
+/// Brush size `steps` bracket presses from `size`, kept within `range`
+///
+/// Steps are a point each up to 10, then a tenth of the size, so large
+/// brushes don't take dozens of presses.
+pub fn step_brush_size(size: f32, steps: i32, range: &RangeInclusive<f32>) -> f32 {
+    let mut size = size;
+    for _ in 0..steps.unsigned_abs() {
+        let step = (size * 0.1).round().max(1.0);
+        size += step * steps.signum() as f32;
+    }
+    size.clamp(*range.start(), *range.end())
+}
+
+/// Tool that holding Ctrl switches to until it's let go
+pub const QUICK_TOGGLE_TOOL: &str = "Selection";
+
+/// Seconds Ctrl has to be held on its own before [`QUICK_TOGGLE_TOOL`] takes over
+pub const QUICK_TOGGLE_DELAY: f64 = 0.3;
+
+/// Key held to scroll the view by dragging, whatever the tool
+pub const PAN_KEY: Key = Key::Space;
+
+/// Tracks the tool switch made while Ctrl is held
+///
+/// Ctrl takes over only once it has been held for [`QUICK_TOGGLE_DELAY`] with
+/// nothing else pressed meanwhile: no key, pointer button or scroll, and no
+/// other modifier. Ctrl shortcuts and Ctrl+click leave the tool alone that way.
+/// Switching, and switching back once Ctrl is let go, both wait for the active
+/// tool to finish what it's in the middle of.
+#[derive(Debug, Default)]
+pub struct QuickToggle {
+    previous_tool: Option<&'static str>,
+    /// Ctrl was down last frame
+    held: bool,
+    /// When Ctrl went down, if nothing else has been pressed since
+    armed_since: Option<f64>,
+}
+
+impl QuickToggle {
+    /// Advance the toggle to the time `now`, in seconds, returning the tool to
+    /// switch to, if any
+    ///
+    /// `interrupted` is whether anything besides Ctrl was pressed or held this
+    /// frame.
+    pub fn update(
+        &mut self,
+        ctrl_held: bool,
+        interrupted: bool,
+        now: f64,
+        active_tool: &'static str,
+        tool_idle: bool,
+    ) -> Option<&'static str> {
+        if ctrl_held && !self.held {
+            self.armed_since = Some(now);
+        }
+        if interrupted || !ctrl_held {
+            self.armed_since = None;
+        }
+        self.held = ctrl_held;
+        if !tool_idle {
+            return None;
+        }
+
+        match self.previous_tool {
+            Some(previous_tool) if !ctrl_held => {
+                self.previous_tool = None;
+                Some(previous_tool)
+            }
+            None if self.remaining_delay(now) == Some(0.0) && active_tool != QUICK_TOGGLE_TOOL => {
+                self.armed_since = None;
+                self.previous_tool = Some(active_tool);
+                Some(QUICK_TOGGLE_TOOL)
+            }
+            _ => None,
+        }
+    }
+
+    /// Seconds until Ctrl, held on its own since it went down, takes over
+    pub fn remaining_delay(&self, now: f64) -> Option<f64> {
+        self.armed_since
+            .map(|since| (since + QUICK_TOGGLE_DELAY - now).max(0.0))
+    }
+
+    /// Whether a temporary tool is currently active
+    pub fn is_active(&self) -> bool {
+        self.previous_tool.is_some()
+    }
+
+    /// Forget the tool to restore, e.g. after the user picks a tool explicitly
+    pub fn cancel(&mut self) {
+        self.previous_tool = None;
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_quick_toggle_switches_while_ctrl_is_held() {
+        let mut toggle = QuickToggle::default();
+
+        assert_eq!(toggle.update(true, false, 0.0, "Draw Stroke", true), None);
+        assert_eq!(toggle.remaining_delay(0.0), Some(QUICK_TOGGLE_DELAY));
+        assert_eq!(toggle.update(true, false, 0.1, "Draw Stroke", true), None);
+        assert_eq!(
+            toggle.update(true, false, 0.3, "Draw Stroke", true),
+            Some("Selection")
+        );
+        assert!(toggle.is_active());
+
+        // Clicks are the selection's while Ctrl stays down
+        assert_eq!(toggle.update(true, true, 0.5, "Selection", true), None);
+        // Letting go mid-drag waits for the drag to end
+        assert_eq!(toggle.update(false, false, 0.6, "Selection", false), None);
+        assert_eq!(
+            toggle.update(false, false, 0.7, "Selection", true),
+            Some("Draw Stroke")
+        );
+        assert!(!toggle.is_active());
+    }
+
+    #[test]
+    fn test_quick_toggle_ignores_ctrl_shortcuts_and_clicks() {
+        let mut toggle = QuickToggle::default();
+
+        // Ctrl+Z: a key is pressed while Ctrl is down
+        toggle.update(true, false, 0.0, "Draw Stroke", true);
+        toggle.update(true, true, 0.1, "Draw Stroke", true);
+        assert_eq!(toggle.update(true, false, 1.0, "Draw Stroke", true), None);
+        assert_eq!(toggle.update(false, false, 1.1, "Draw Stroke", true), None);
+
+        // Ctrl+click, however long Ctrl is held after it
+        toggle.update(true, false, 2.0, "Draw Stroke", true);
+        toggle.update(true, true, 2.1, "Draw Stroke", true);
+        assert_eq!(toggle.update(true, false, 3.0, "Draw Stroke", true), None);
+        assert_eq!(toggle.update(false, false, 3.1, "Draw Stroke", true), None);
+        assert!(!toggle.is_active());
+        assert_eq!(toggle.remaining_delay(3.1), None);
+    }
+
+    #[test]
+    fn test_quick_toggle_waits_for_the_tool_and_skips_its_own_tool() {
+        let mut toggle = QuickToggle::default();
+        toggle.update(true, false, 0.0, "Draw Stroke", false);
+        assert_eq!(toggle.update(true, false, 0.5, "Draw Stroke", false), None);
+        assert_eq!(
+            toggle.update(true, false, 0.6, "Draw Stroke", true),
+            Some("Selection")
+        );
+
+        let mut toggle = QuickToggle::default();
+        toggle.update(true, false, 0.0, "Selection", true);
+        assert_eq!(toggle.update(true, false, 0.5, "Selection", true), None);
+        assert_eq!(toggle.update(false, false, 0.6, "Selection", true), None);
+        assert!(!toggle.is_active());
+    }
+
+    #[test]
+    fn test_brush_steps_grow_with_the_brush() {
+        let range = 1.0..=128.0;
+        assert_eq!(step_brush_size(4.0, 1, &range), 5.0);
+        assert_eq!(step_brush_size(4.0, -1, &range), 3.0);
+        assert_eq!(step_brush_size(40.0, 1, &range), 44.0);
+        assert_eq!(step_brush_size(40.0, -2, &range), 32.0);
+        assert_eq!(step_brush_size(1.0, -1, &range), 1.0);
+        assert_eq!(step_brush_size(120.0, 3, &range), 128.0);
+    }
+}
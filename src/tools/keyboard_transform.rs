@@ -0,0 +1,260 @@
+use crate::command::Command;
+use crate::element::Element;
+use crate::renderer::Renderer;
+use crate::state::{EditorModel, ElementId};
+use crate::widgets::{Edge, TransformHandle};
+use egui::{Key, Modifiers, Vec2};
+
+// Distance arrow keys move or resize by, and with Shift held, unless set otherwise
+const STEP: f32 = 1.0;
+const LARGE_STEP: f32 = 10.0;
+
+/// Keyboard-only selection and transforms on the canvas
+///
+/// Tab and Shift+Tab cycle the selection through the elements in z-order, Enter
+/// enters transform mode on the selection, where arrow keys move it and Ctrl+arrows
+/// resize it from the right and bottom edges, and Esc leaves transform mode. Every
+/// change goes through a command so it can be undone.
+#[derive(Debug)]
+pub struct KeyboardTransform {
+    transforming: bool,
+    step: f32,
+    large_step: f32,
+}
+
+impl Default for KeyboardTransform {
+    fn default() -> Self {
+        Self {
+            transforming: false,
+            step: STEP,
+            large_step: LARGE_STEP,
+        }
+    }
+}
+
+impl KeyboardTransform {
+    /// Set how far arrow keys move or resize by, and with Shift held
+    pub fn set_steps(&mut self, step: f32, large_step: f32) {
+        self.step = step;
+        self.large_step = large_step;
+    }
+
+    /// Whether arrow keys currently move and resize the selection
+    pub fn is_transforming(&self) -> bool {
+        self.transforming
+    }
+
+    /// Leave transform mode, e.g. when the selection changes some other way
+    pub fn cancel(&mut self) {
+        self.transforming = false;
+    }
+
+    /// Whether Tab should cycle elements instead of moving focus between widgets
+    pub fn claims_tab(editor_model: &EditorModel) -> bool {
+        editor_model
+            .elements
+            .iter()
+            .any(|element| !element.metadata().is_locked())
+    }
+
+    /// Handle a key press, returning the command it triggers, if any
+    pub fn handle_key(
+        &mut self,
+        key: Key,
+        modifiers: &Modifiers,
+        editor_model: &EditorModel,
+    ) -> Option<Command> {
+        // Transforms apply to a single selected element
+        let selected = editor_model
+            .selected_element()
+            .filter(|_| editor_model.selected_ids().len() == 1);
+        if selected.is_none() {
+            self.transforming = false;
+        }
+
+        match key {
+            Key::Tab => {
+                self.transforming = false;
+                let element_id = Self::cycle(editor_model, !modifiers.shift)?;
+                Some(Command::Batch {
+                    description: "Select Element",
+                    commands: vec![
+                        Command::new_clear_selection(editor_model),
+                        Command::SelectElement(element_id),
+                    ],
+                })
+            }
+            Key::Enter if selected.is_some() => {
+                self.transforming = true;
+                None
+            }
+            Key::Escape => {
+                self.transforming = false;
+                None
+            }
+            Key::ArrowLeft | Key::ArrowRight | Key::ArrowUp | Key::ArrowDown
+                if self.transforming =>
+            {
+                let element = selected?;
+                let step = if modifiers.shift {
+                    self.large_step
+                } else {
+                    self.step
+                };
+                let delta = match key {
+                    Key::ArrowLeft => Vec2::new(-step, 0.0),
+                    Key::ArrowRight => Vec2::new(step, 0.0),
+                    Key::ArrowUp => Vec2::new(0.0, -step),
+                    _ => Vec2::new(0.0, step),
+                };
+                let rect = element.rect();
+
+                if modifiers.command {
+                    let edge = if delta.x != 0.0 {
+                        Edge::Right
+                    } else {
+                        Edge::Bottom
+                    };
+                    let handle = TransformHandle::Edge(edge);
+                    let new_rect =
+                        Renderer::compute_resized_rect(rect, handle, edge.pos(rect) + delta);
+                    (new_rect != rect).then(|| Command::ResizeElement {
+                        element_id: element.id(),
+                        _element_type: element.element_type().to_string(),
+                        old_rect: rect,
+                        new_rect,
+                        _scaling_handle: handle,
+                        _original_image: egui::Image::new((
+                            egui::TextureId::default(),
+                            egui::Vec2::new(10.0, 10.0),
+                        )),
+                    })
+                } else {
+                    Some(Command::MoveElement {
+                        element_id: element.id(),
+                        _element_type: element.element_type().to_string(),
+                        old_position: rect.min,
+                        new_position: rect.min + delta,
+                    })
+                }
+            }
+            _ => None,
+        }
+    }
+
+    /// The element after (or before) the selected one in z-order, wrapping around
+    fn cycle(editor_model: &EditorModel, forward: bool) -> Option<ElementId> {
+        let ids: Vec<ElementId> = editor_model
+            .elements
+            .iter()
+            .filter(|e| !e.metadata().is_locked())
+            .map(|e| e.id())
+            .collect();
+        let current = editor_model
+            .selected_element()
+            .and_then(|element| ids.iter().position(|id| *id == element.id()));
+        let index = match (current, forward) {
+            (Some(index), true) => (index + 1) % ids.len(),
+            (Some(index), false) => (index + ids.len() - 1) % ids.len(),
+            (None, true) => 0,
+            (None, false) => ids.len().checked_sub(1)?,
+        };
+        ids.get(index).copied()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::element::factory;
+    use egui::{Color32, Pos2};
+
+    fn model_with_two_shapes() -> EditorModel {
+        let mut model = EditorModel::new();
+        for (id, x) in [(1, 0.0), (2, 100.0)] {
+            let points = vec![
+                Pos2::new(x, 0.0),
+                Pos2::new(x + 50.0, 0.0),
+                Pos2::new(x + 50.0, 50.0),
+                Pos2::new(x, 50.0),
+            ];
+            model
+                .add_element(factory::create_shape(
+                    ElementId::new(id),
+                    points,
+                    Color32::RED,
+                ))
+                .unwrap();
+        }
+        model
+    }
+
+    #[test]
+    fn test_tab_cycles_and_arrows_move_in_transform_mode() {
+        let mut model = model_with_two_shapes();
+        let mut keyboard = KeyboardTransform::default();
+        let none = Modifiers::NONE;
+
+        // Tab selects the bottom element first, Shift+Tab wraps back to the top
+        let select = keyboard.handle_key(Key::Tab, &none, &model).unwrap();
+        select.execute(&mut model).unwrap();
+        assert_eq!(
+            model.selected_element().map(|e| e.id()),
+            Some(ElementId::new(1))
+        );
+        let select = keyboard
+            .handle_key(Key::Tab, &Modifiers::SHIFT, &model)
+            .unwrap();
+        select.execute(&mut model).unwrap();
+        assert_eq!(
+            model.selected_element().map(|e| e.id()),
+            Some(ElementId::new(2))
+        );
+        let select = keyboard
+            .handle_key(Key::Tab, &Modifiers::SHIFT, &model)
+            .unwrap();
+        select.execute(&mut model).unwrap();
+        assert_eq!(model.selected_ids().len(), 1);
+        assert_eq!(
+            model.selected_element().map(|e| e.id()),
+            Some(ElementId::new(1))
+        );
+
+        // Arrows do nothing until Enter starts transform mode
+        assert!(
+            keyboard
+                .handle_key(Key::ArrowRight, &none, &model)
+                .is_none()
+        );
+        keyboard.handle_key(Key::Enter, &none, &model);
+        assert!(keyboard.is_transforming());
+
+        let Some(Command::MoveElement { new_position, .. }) =
+            keyboard.handle_key(Key::ArrowRight, &Modifiers::SHIFT, &model)
+        else {
+            panic!("Arrow should move the selection");
+        };
+        let original = model.find_element_by_id(ElementId::new(1)).unwrap().rect();
+        assert_eq!(new_position, original.min + Vec2::new(LARGE_STEP, 0.0));
+
+        let Some(Command::ResizeElement { new_rect, .. }) =
+            keyboard.handle_key(Key::ArrowDown, &Modifiers::COMMAND, &model)
+        else {
+            panic!("Ctrl+arrow should resize the selection");
+        };
+        assert_eq!(new_rect.min, original.min);
+        assert_eq!(new_rect.height(), original.height() + STEP);
+
+        // Steps follow the preferences
+        keyboard.set_steps(0.5, 25.0);
+        let Some(Command::MoveElement { new_position, .. }) =
+            keyboard.handle_key(Key::ArrowUp, &Modifiers::SHIFT, &model)
+        else {
+            panic!("Arrow should move the selection");
+        };
+        assert_eq!(new_position, original.min - Vec2::new(0.0, 25.0));
+
+        keyboard.handle_key(Key::Escape, &none, &model);
+        assert!(!keyboard.is_transforming());
+    }
+}
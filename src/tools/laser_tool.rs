@@ -0,0 +1,205 @@
+use crate::command::Command;
+use crate::renderer::Renderer;
+use crate::state::EditorModel;
+use crate::tools::{Tool, ToolConfig};
+use egui::{Pos2, Ui};
+use log::trace;
+use std::any::Any;
+
+// Config for LaserTool (it has no settings yet)
+#[derive(Clone, Debug)]
+pub struct LaserToolConfig;
+
+impl ToolConfig for LaserToolConfig {
+    fn tool_name(&self) -> &'static str {
+        "Laser Pointer"
+    }
+
+    fn as_any(&self) -> &dyn Any {
+        self
+    }
+
+    fn as_any_mut(&mut self) -> &mut dyn Any {
+        self
+    }
+}
+
+// State enum for the LaserTool
+#[derive(Clone, Debug)]
+pub enum LaserState {
+    Idle,
+    Drawing { points: Vec<Pos2> },
+}
+
+/// Presentation tool drawing temporary ink: bright trails that fade out a
+/// couple of seconds after they're let go
+///
+/// Trails live only in the renderer; they never become elements, so they
+/// aren't saved, exported or undone.
+#[derive(Clone, Debug)]
+pub struct UnifiedLaserTool {
+    pub state: LaserState,
+}
+
+impl UnifiedLaserTool {
+    pub fn new() -> Self {
+        Self {
+            state: LaserState::Idle,
+        }
+    }
+
+    // Get the current state name
+    pub fn current_state_name(&self) -> &'static str {
+        match self.state {
+            LaserState::Idle => "Idle",
+            LaserState::Drawing { .. } => "Drawing",
+        }
+    }
+
+    /// Extend the trail being drawn to `pos`
+    fn add_point(&mut self, pos: Pos2) {
+        if let LaserState::Drawing { points } = &mut self.state {
+            if points.last() != Some(&pos) {
+                points.push(pos);
+            }
+        }
+    }
+}
+
+impl Tool for UnifiedLaserTool {
+    fn name(&self) -> &'static str {
+        "Laser Pointer"
+    }
+
+    fn activate(&mut self, _editor_model: &EditorModel) {
+        self.state = LaserState::Idle;
+        trace!(tool = self.name(); "Tool activated");
+    }
+
+    fn deactivate(&mut self, _editor_model: &EditorModel) {
+        self.state = LaserState::Idle;
+        trace!(tool = self.name(); "Tool deactivated");
+    }
+
+    fn on_pointer_down(
+        &mut self,
+        pos: Pos2,
+        button: egui::PointerButton,
+        _modifiers: &egui::Modifiers,
+        _editor_model: &EditorModel,
+        _renderer: &mut Renderer,
+    ) -> Option<Command> {
+        if button == egui::PointerButton::Primary {
+            self.state = LaserState::Drawing { points: vec![pos] };
+        }
+        None
+    }
+
+    fn on_pointer_move(
+        &mut self,
+        pos: Pos2,
+        held_buttons: &[egui::PointerButton],
+        _modifiers: &egui::Modifiers,
+        _editor_model: &mut EditorModel,
+        _ui: &egui::Ui,
+        _renderer: &mut Renderer,
+    ) -> Option<Command> {
+        if held_buttons.contains(&egui::PointerButton::Primary) {
+            self.add_point(pos);
+        }
+        None
+    }
+
+    fn on_pointer_up(
+        &mut self,
+        pos: Pos2,
+        button: egui::PointerButton,
+        _modifiers: &egui::Modifiers,
+        _editor_model: &EditorModel,
+    ) -> Option<Command> {
+        if button == egui::PointerButton::Primary {
+            self.add_point(pos);
+            // The renderer fades the trail out once the preview sees it let go
+            self.state = LaserState::Idle;
+        }
+        None
+    }
+
+    fn cursor_icon(
+        &self,
+        _pos: Pos2,
+        _editor_model: &EditorModel,
+        _renderer: &Renderer,
+    ) -> Option<egui::CursorIcon> {
+        Some(egui::CursorIcon::Crosshair)
+    }
+
+    fn reset_interaction_state(&mut self) {
+        self.state = LaserState::Idle;
+    }
+
+    fn update_preview(&mut self, renderer: &mut Renderer) {
+        match &self.state {
+            LaserState::Idle => renderer.release_laser_trail(),
+            LaserState::Drawing { points } => renderer.set_laser_trail(points.clone()),
+        }
+    }
+
+    fn clear_preview(&mut self, renderer: &mut Renderer) {
+        renderer.release_laser_trail();
+    }
+
+    fn ui(&mut self, ui: &mut Ui, _editor_model: &EditorModel) -> Option<Command> {
+        ui.label("Laser Pointer");
+        ui.label("Drag to point things out; trails fade away and never touch the document.");
+        None
+    }
+
+    fn get_config(&self) -> Box<dyn ToolConfig> {
+        Box::new(LaserToolConfig)
+    }
+
+    fn apply_config(&mut self, _config: &dyn ToolConfig) {}
+}
+
+impl Default for UnifiedLaserTool {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+// Factory function to create a new LaserTool
+pub fn new_laser_tool() -> UnifiedLaserTool {
+    UnifiedLaserTool::new()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_trails_never_reach_the_document() {
+        let model = EditorModel::new();
+        let mut tool = new_laser_tool();
+        tool.state = LaserState::Drawing {
+            points: vec![Pos2::new(10.0, 10.0)],
+        };
+        tool.add_point(Pos2::new(40.0, 20.0));
+        tool.add_point(Pos2::new(40.0, 20.0));
+        let LaserState::Drawing { points } = &tool.state else {
+            panic!("expected a trail being drawn");
+        };
+        assert_eq!(points.len(), 2);
+
+        let modifiers = egui::Modifiers::default();
+        let up = tool.on_pointer_up(
+            Pos2::new(80.0, 30.0),
+            egui::PointerButton::Primary,
+            &modifiers,
+            &model,
+        );
+        assert!(up.is_none());
+        assert!(matches!(tool.state, LaserState::Idle));
+        assert!(model.elements.is_empty());
+    }
+}
@@ -4,6 +4,7 @@ use crate::state::EditorModel;
 use egui::Pos2;
 use egui::Ui;
 use std::any::Any;
+use std::ops::RangeInclusive;
 
 /// Tool configuration trait for persisting tool settings
 pub trait ToolConfig: Send + Sync + 'static {
@@ -106,6 +107,14 @@ pub trait Tool: Send + Sync {
         editor_model: &EditorModel
     ) -> Option<Command>;
     
+    /// A command to run right after the one returned from `on_pointer_up`, as its own
+    /// undo step, so undoing it leaves the first in place.
+    /// 
+    /// @param editor_model The editor model with the first command applied
+    fn follow_up_command(&mut self, _editor_model: &EditorModel) -> Option<Command> {
+        None
+    }
+    
     /// Handle keyboard events specific to this tool.
     /// Return a Command if the event should trigger an action.
     /// 
@@ -123,6 +132,40 @@ pub trait Tool: Send + Sync {
         // Default implementation does nothing
     }
     
+    /// The cursor to show with the pointer over the canvas, from a hit test at its position.
+    /// Called every frame; None leaves the default arrow.
+    /// 
+    /// @param pos The position of the pointer
+    /// @param editor_model The current editor model
+    /// @param renderer The renderer, for the handles it draws around the selection
+    fn cursor_icon(
+        &self,
+        _pos: Pos2,
+        _editor_model: &EditorModel,
+        _renderer: &Renderer
+    ) -> Option<egui::CursorIcon> {
+        None
+    }
+
+    /// Diameter of the brush, to be outlined at the pointer over the canvas in
+    /// place of the system cursor. None keeps the cursor from `cursor_icon`.
+    fn brush_outline(&self) -> Option<f32> {
+        None
+    }
+
+    /// Size new strokes are drawn with and the range it can be set in, for
+    /// tools with a brush. The bracket keys and Ctrl+Alt+drag change it.
+    fn brush_size(&self) -> Option<(f32, RangeInclusive<f32>)> {
+        None
+    }
+
+    /// Change the size given by `brush_size`; `size` is within its range
+    fn set_brush_size(&mut self, _size: f32) {}
+
+    /// Pressure of the pen on a tablet, from 0 to 1, as it changes; pointers
+    /// without pressure never call this
+    fn set_pressure(&mut self, _pressure: f32) {}
+    
     /// Reset any transient interaction state in the tool.
     /// Called after command execution to clean up.
     fn reset_interaction_state(&mut self);
@@ -145,12 +188,57 @@ pub trait Tool: Send + Sync {
 }
 
 // Tool implementations
+mod annotate_tool;
+mod colors;
 mod draw_stroke_tool;
 mod draw_stroke_helper;
+mod export_slice_tool;
+mod hotkeys;
+mod keyboard_transform;
+mod laser_tool;
+mod pen_tool;
+mod pixel_tool;
+mod polygon_tool;
 mod selection_tool;
-
-pub use draw_stroke_tool::{DrawStrokeState, UnifiedDrawStrokeTool, new_draw_stroke_tool};
-pub use selection_tool::{SelectionState, UnifiedSelectionTool, new_selection_tool};
+mod shape_recognition;
+mod slice_tool;
+mod sticky_note_tool;
+
+pub use annotate_tool::{
+    AnnotateState, AnnotationPreset, UnifiedAnnotateTool, annotation_command, new_annotate_tool,
+};
+pub use colors::ColorPair;
+pub use draw_stroke_tool::{
+    DrawStrokeConfig, DrawStrokeState, UnifiedDrawStrokeTool, new_draw_stroke_tool, stroke_style_ui,
+};
+pub use export_slice_tool::{
+    ExportSliceState, UnifiedExportSliceTool, add_slice_command, new_export_slice_tool,
+};
+pub use hotkeys::{
+    BRUSH_LARGER_CTRL_KEY, BRUSH_LARGER_KEY, BRUSH_SMALLER_CTRL_KEY, BRUSH_SMALLER_KEY, PAN_KEY,
+    QUICK_TOGGLE_TOOL, QuickToggle, SWAP_COLORS_KEY, TOOL_HOTKEYS, step_brush_size,
+};
+pub use keyboard_transform::KeyboardTransform;
+pub use laser_tool::{LaserState, LaserToolConfig, UnifiedLaserTool, new_laser_tool};
+pub use pen_tool::{
+    NodePart, PenMode, PenState, PenToolConfig, UnifiedPenTool, drag_node_part, new_pen_tool,
+    node_part_at,
+};
+pub use pixel_tool::{
+    PixelState, PixelToolConfig, UnifiedPixelTool, new_pixel_tool, paint_segment,
+};
+pub use polygon_tool::{
+    PolygonState, PolygonToolConfig, UnifiedPolygonTool, new_polygon_tool, polygon_settings_ui,
+};
+pub use selection_tool::{
+    SelectMatching, SelectionMode, SelectionState, UnifiedSelectionTool, new_selection_tool,
+};
+pub use shape_recognition::{RecognizedShape, recognize_shape};
+pub use slice_tool::{SliceState, UnifiedSliceTool, new_slice_tool, slice_command};
+pub use sticky_note_tool::{
+    StickyNoteState, StickyNoteToolConfig, UnifiedStickyNoteTool, new_sticky_note_tool,
+    note_color_ui, sticky_note_command,
+};
 
 // Re-export any tool implementations we add later
 // Example: mod pencil_tool; pub use pencil_tool::PencilTool;
@@ -161,6 +249,14 @@ pub use selection_tool::{SelectionState, UnifiedSelectionTool, new_selection_too
 pub enum ToolType {
     DrawStroke(UnifiedDrawStrokeTool),
     Selection(UnifiedSelectionTool),
+    Slice(UnifiedSliceTool),
+    ExportSlice(UnifiedExportSliceTool),
+    Annotate(UnifiedAnnotateTool),
+    Pen(UnifiedPenTool),
+    Polygon(UnifiedPolygonTool),
+    Pixel(UnifiedPixelTool),
+    StickyNote(UnifiedStickyNoteTool),
+    Laser(UnifiedLaserTool),
     // Add more tools here as they are implemented
 }
 
@@ -169,6 +265,14 @@ impl Tool for ToolType {
         match self {
             Self::DrawStroke(tool) => tool.name(),
             Self::Selection(tool) => tool.name(),
+            Self::Slice(tool) => tool.name(),
+            Self::ExportSlice(tool) => tool.name(),
+            Self::Annotate(tool) => tool.name(),
+            Self::Pen(tool) => tool.name(),
+            Self::Polygon(tool) => tool.name(),
+            Self::Pixel(tool) => tool.name(),
+            Self::StickyNote(tool) => tool.name(),
+            Self::Laser(tool) => tool.name(),
         }
     }
 
@@ -183,6 +287,14 @@ impl Tool for ToolType {
         match self {
             Self::DrawStroke(tool) => tool.activate(editor_model),
             Self::Selection(tool) => tool.activate(editor_model),
+            Self::Slice(tool) => tool.activate(editor_model),
+            Self::ExportSlice(tool) => tool.activate(editor_model),
+            Self::Annotate(tool) => tool.activate(editor_model),
+            Self::Pen(tool) => tool.activate(editor_model),
+            Self::Polygon(tool) => tool.activate(editor_model),
+            Self::Pixel(tool) => tool.activate(editor_model),
+            Self::StickyNote(tool) => tool.activate(editor_model),
+            Self::Laser(tool) => tool.activate(editor_model),
         }
     }
 
@@ -190,6 +302,14 @@ impl Tool for ToolType {
         match self {
             Self::DrawStroke(tool) => tool.deactivate(editor_model),
             Self::Selection(tool) => tool.deactivate(editor_model),
+            Self::Slice(tool) => tool.deactivate(editor_model),
+            Self::ExportSlice(tool) => tool.deactivate(editor_model),
+            Self::Annotate(tool) => tool.deactivate(editor_model),
+            Self::Pen(tool) => tool.deactivate(editor_model),
+            Self::Polygon(tool) => tool.deactivate(editor_model),
+            Self::Pixel(tool) => tool.deactivate(editor_model),
+            Self::StickyNote(tool) => tool.deactivate(editor_model),
+            Self::Laser(tool) => tool.deactivate(editor_model),
         }
     }
 
@@ -197,6 +317,14 @@ impl Tool for ToolType {
         match self {
             Self::DrawStroke(tool) => tool.requires_selection(),
             Self::Selection(tool) => tool.requires_selection(),
+            Self::Slice(tool) => tool.requires_selection(),
+            Self::ExportSlice(tool) => tool.requires_selection(),
+            Self::Annotate(tool) => tool.requires_selection(),
+            Self::Pen(tool) => tool.requires_selection(),
+            Self::Polygon(tool) => tool.requires_selection(),
+            Self::Pixel(tool) => tool.requires_selection(),
+            Self::StickyNote(tool) => tool.requires_selection(),
+            Self::Laser(tool) => tool.requires_selection(),
         }
     }
 
@@ -211,6 +339,14 @@ impl Tool for ToolType {
         match self {
             Self::DrawStroke(tool) => tool.on_pointer_down(pos, button, modifiers, editor_model, renderer),
             Self::Selection(tool) => tool.on_pointer_down(pos, button, modifiers, editor_model, renderer),
+            Self::Slice(tool) => tool.on_pointer_down(pos, button, modifiers, editor_model, renderer),
+            Self::ExportSlice(tool) => tool.on_pointer_down(pos, button, modifiers, editor_model, renderer),
+            Self::Annotate(tool) => tool.on_pointer_down(pos, button, modifiers, editor_model, renderer),
+            Self::Pen(tool) => tool.on_pointer_down(pos, button, modifiers, editor_model, renderer),
+            Self::Polygon(tool) => tool.on_pointer_down(pos, button, modifiers, editor_model, renderer),
+            Self::Pixel(tool) => tool.on_pointer_down(pos, button, modifiers, editor_model, renderer),
+            Self::StickyNote(tool) => tool.on_pointer_down(pos, button, modifiers, editor_model, renderer),
+            Self::Laser(tool) => tool.on_pointer_down(pos, button, modifiers, editor_model, renderer),
         }
     }
 
@@ -226,6 +362,14 @@ impl Tool for ToolType {
         match self {
             Self::DrawStroke(tool) => tool.on_pointer_move(pos, held_buttons, modifiers, editor_model, ui, renderer),
             Self::Selection(tool) => tool.on_pointer_move(pos, held_buttons, modifiers, editor_model, ui, renderer),
+            Self::Slice(tool) => tool.on_pointer_move(pos, held_buttons, modifiers, editor_model, ui, renderer),
+            Self::ExportSlice(tool) => tool.on_pointer_move(pos, held_buttons, modifiers, editor_model, ui, renderer),
+            Self::Annotate(tool) => tool.on_pointer_move(pos, held_buttons, modifiers, editor_model, ui, renderer),
+            Self::Pen(tool) => tool.on_pointer_move(pos, held_buttons, modifiers, editor_model, ui, renderer),
+            Self::Polygon(tool) => tool.on_pointer_move(pos, held_buttons, modifiers, editor_model, ui, renderer),
+            Self::Pixel(tool) => tool.on_pointer_move(pos, held_buttons, modifiers, editor_model, ui, renderer),
+            Self::StickyNote(tool) => tool.on_pointer_move(pos, held_buttons, modifiers, editor_model, ui, renderer),
+            Self::Laser(tool) => tool.on_pointer_move(pos, held_buttons, modifiers, editor_model, ui, renderer),
         }
     }
 
@@ -239,23 +383,145 @@ impl Tool for ToolType {
         match self {
             Self::DrawStroke(tool) => tool.on_pointer_up(pos, button, modifiers, editor_model),
             Self::Selection(tool) => tool.on_pointer_up(pos, button, modifiers, editor_model),
+            Self::Slice(tool) => tool.on_pointer_up(pos, button, modifiers, editor_model),
+            Self::ExportSlice(tool) => tool.on_pointer_up(pos, button, modifiers, editor_model),
+            Self::Annotate(tool) => tool.on_pointer_up(pos, button, modifiers, editor_model),
+            Self::Pen(tool) => tool.on_pointer_up(pos, button, modifiers, editor_model),
+            Self::Polygon(tool) => tool.on_pointer_up(pos, button, modifiers, editor_model),
+            Self::Pixel(tool) => tool.on_pointer_up(pos, button, modifiers, editor_model),
+            Self::StickyNote(tool) => tool.on_pointer_up(pos, button, modifiers, editor_model),
+            Self::Laser(tool) => tool.on_pointer_up(pos, button, modifiers, editor_model),
+        }
+    }
+
+    fn follow_up_command(&mut self, editor_model: &EditorModel) -> Option<Command> {
+        match self {
+            Self::DrawStroke(tool) => tool.follow_up_command(editor_model),
+            Self::Selection(tool) => tool.follow_up_command(editor_model),
+            Self::Slice(tool) => tool.follow_up_command(editor_model),
+            Self::ExportSlice(tool) => tool.follow_up_command(editor_model),
+            Self::Annotate(tool) => tool.follow_up_command(editor_model),
+            Self::Pen(tool) => tool.follow_up_command(editor_model),
+            Self::Polygon(tool) => tool.follow_up_command(editor_model),
+            Self::Pixel(tool) => tool.follow_up_command(editor_model),
+            Self::StickyNote(tool) => tool.follow_up_command(editor_model),
+            Self::Laser(tool) => tool.follow_up_command(editor_model),
         }
     }
 
     fn on_key(
         &mut self,
-        _key: egui::Key,
-        _pressed: bool,
-        _modifiers: &egui::Modifiers,
-        _editor_model: &EditorModel
+        key: egui::Key,
+        pressed: bool,
+        modifiers: &egui::Modifiers,
+        editor_model: &EditorModel
     ) {
-        // Default implementation does nothing
+        match self {
+            Self::DrawStroke(tool) => tool.on_key(key, pressed, modifiers, editor_model),
+            Self::Selection(tool) => tool.on_key(key, pressed, modifiers, editor_model),
+            Self::Slice(tool) => tool.on_key(key, pressed, modifiers, editor_model),
+            Self::ExportSlice(tool) => tool.on_key(key, pressed, modifiers, editor_model),
+            Self::Annotate(tool) => tool.on_key(key, pressed, modifiers, editor_model),
+            Self::Pen(tool) => tool.on_key(key, pressed, modifiers, editor_model),
+            Self::Polygon(tool) => tool.on_key(key, pressed, modifiers, editor_model),
+            Self::Pixel(tool) => tool.on_key(key, pressed, modifiers, editor_model),
+            Self::StickyNote(tool) => tool.on_key(key, pressed, modifiers, editor_model),
+            Self::Laser(tool) => tool.on_key(key, pressed, modifiers, editor_model),
+        }
+    }
+
+    fn cursor_icon(
+        &self,
+        pos: Pos2,
+        editor_model: &EditorModel,
+        renderer: &Renderer
+    ) -> Option<egui::CursorIcon> {
+        match self {
+            Self::DrawStroke(tool) => tool.cursor_icon(pos, editor_model, renderer),
+            Self::Selection(tool) => tool.cursor_icon(pos, editor_model, renderer),
+            Self::Slice(tool) => tool.cursor_icon(pos, editor_model, renderer),
+            Self::ExportSlice(tool) => tool.cursor_icon(pos, editor_model, renderer),
+            Self::Annotate(tool) => tool.cursor_icon(pos, editor_model, renderer),
+            Self::Pen(tool) => tool.cursor_icon(pos, editor_model, renderer),
+            Self::Polygon(tool) => tool.cursor_icon(pos, editor_model, renderer),
+            Self::Pixel(tool) => tool.cursor_icon(pos, editor_model, renderer),
+            Self::StickyNote(tool) => tool.cursor_icon(pos, editor_model, renderer),
+            Self::Laser(tool) => tool.cursor_icon(pos, editor_model, renderer),
+        }
+    }
+
+    fn brush_outline(&self) -> Option<f32> {
+        match self {
+            Self::DrawStroke(tool) => tool.brush_outline(),
+            Self::Selection(tool) => tool.brush_outline(),
+            Self::Slice(tool) => tool.brush_outline(),
+            Self::ExportSlice(tool) => tool.brush_outline(),
+            Self::Annotate(tool) => tool.brush_outline(),
+            Self::Pen(tool) => tool.brush_outline(),
+            Self::Polygon(tool) => tool.brush_outline(),
+            Self::Pixel(tool) => tool.brush_outline(),
+            Self::StickyNote(tool) => tool.brush_outline(),
+            Self::Laser(tool) => tool.brush_outline(),
+        }
+    }
+
+    fn brush_size(&self) -> Option<(f32, RangeInclusive<f32>)> {
+        match self {
+            Self::DrawStroke(tool) => tool.brush_size(),
+            Self::Selection(tool) => tool.brush_size(),
+            Self::Slice(tool) => tool.brush_size(),
+            Self::ExportSlice(tool) => tool.brush_size(),
+            Self::Annotate(tool) => tool.brush_size(),
+            Self::Pen(tool) => tool.brush_size(),
+            Self::Polygon(tool) => tool.brush_size(),
+            Self::Pixel(tool) => tool.brush_size(),
+            Self::StickyNote(tool) => tool.brush_size(),
+            Self::Laser(tool) => tool.brush_size(),
+        }
+    }
+
+    fn set_brush_size(&mut self, size: f32) {
+        match self {
+            Self::DrawStroke(tool) => tool.set_brush_size(size),
+            Self::Selection(tool) => tool.set_brush_size(size),
+            Self::Slice(tool) => tool.set_brush_size(size),
+            Self::ExportSlice(tool) => tool.set_brush_size(size),
+            Self::Annotate(tool) => tool.set_brush_size(size),
+            Self::Pen(tool) => tool.set_brush_size(size),
+            Self::Polygon(tool) => tool.set_brush_size(size),
+            Self::Pixel(tool) => tool.set_brush_size(size),
+            Self::StickyNote(tool) => tool.set_brush_size(size),
+            Self::Laser(tool) => tool.set_brush_size(size),
+        }
+    }
+
+    fn set_pressure(&mut self, pressure: f32) {
+        match self {
+            Self::DrawStroke(tool) => tool.set_pressure(pressure),
+            Self::Selection(tool) => tool.set_pressure(pressure),
+            Self::Slice(tool) => tool.set_pressure(pressure),
+            Self::ExportSlice(tool) => tool.set_pressure(pressure),
+            Self::Annotate(tool) => tool.set_pressure(pressure),
+            Self::Pen(tool) => tool.set_pressure(pressure),
+            Self::Polygon(tool) => tool.set_pressure(pressure),
+            Self::Pixel(tool) => tool.set_pressure(pressure),
+            Self::StickyNote(tool) => tool.set_pressure(pressure),
+            Self::Laser(tool) => tool.set_pressure(pressure),
+        }
     }
 
     fn reset_interaction_state(&mut self) {
         match self {
             Self::DrawStroke(tool) => tool.reset_interaction_state(),
             Self::Selection(tool) => tool.reset_interaction_state(),
+            Self::Slice(tool) => tool.reset_interaction_state(),
+            Self::ExportSlice(tool) => tool.reset_interaction_state(),
+            Self::Annotate(tool) => tool.reset_interaction_state(),
+            Self::Pen(tool) => tool.reset_interaction_state(),
+            Self::Polygon(tool) => tool.reset_interaction_state(),
+            Self::Pixel(tool) => tool.reset_interaction_state(),
+            Self::StickyNote(tool) => tool.reset_interaction_state(),
+            Self::Laser(tool) => tool.reset_interaction_state(),
         }
     }
 
@@ -263,6 +529,14 @@ impl Tool for ToolType {
         match self {
             Self::DrawStroke(tool) => tool.update_preview(renderer),
             Self::Selection(tool) => tool.update_preview(renderer),
+            Self::Slice(tool) => tool.update_preview(renderer),
+            Self::ExportSlice(tool) => tool.update_preview(renderer),
+            Self::Annotate(tool) => tool.update_preview(renderer),
+            Self::Pen(tool) => tool.update_preview(renderer),
+            Self::Polygon(tool) => tool.update_preview(renderer),
+            Self::Pixel(tool) => tool.update_preview(renderer),
+            Self::StickyNote(tool) => tool.update_preview(renderer),
+            Self::Laser(tool) => tool.update_preview(renderer),
         }
     }
 
@@ -270,6 +544,14 @@ impl Tool for ToolType {
         match self {
             Self::DrawStroke(tool) => tool.clear_preview(renderer),
             Self::Selection(tool) => tool.clear_preview(renderer),
+            Self::Slice(tool) => tool.clear_preview(renderer),
+            Self::ExportSlice(tool) => tool.clear_preview(renderer),
+            Self::Annotate(tool) => tool.clear_preview(renderer),
+            Self::Pen(tool) => tool.clear_preview(renderer),
+            Self::Polygon(tool) => tool.clear_preview(renderer),
+            Self::Pixel(tool) => tool.clear_preview(renderer),
+            Self::StickyNote(tool) => tool.clear_preview(renderer),
+            Self::Laser(tool) => tool.clear_preview(renderer),
         }
     }
 
@@ -277,6 +559,14 @@ impl Tool for ToolType {
         match self {
             Self::DrawStroke(tool) => tool.ui(ui, editor_model),
             Self::Selection(tool) => tool.ui(ui, editor_model),
+            Self::Slice(tool) => tool.ui(ui, editor_model),
+            Self::ExportSlice(tool) => tool.ui(ui, editor_model),
+            Self::Annotate(tool) => tool.ui(ui, editor_model),
+            Self::Pen(tool) => tool.ui(ui, editor_model),
+            Self::Polygon(tool) => tool.ui(ui, editor_model),
+            Self::Pixel(tool) => tool.ui(ui, editor_model),
+            Self::StickyNote(tool) => tool.ui(ui, editor_model),
+            Self::Laser(tool) => tool.ui(ui, editor_model),
         }
     }
 
@@ -284,6 +574,14 @@ impl Tool for ToolType {
         match self {
             Self::DrawStroke(tool) => tool.get_config(),
             Self::Selection(tool) => tool.get_config(),
+            Self::Slice(tool) => tool.get_config(),
+            Self::ExportSlice(tool) => tool.get_config(),
+            Self::Annotate(tool) => tool.get_config(),
+            Self::Pen(tool) => tool.get_config(),
+            Self::Polygon(tool) => tool.get_config(),
+            Self::Pixel(tool) => tool.get_config(),
+            Self::StickyNote(tool) => tool.get_config(),
+            Self::Laser(tool) => tool.get_config(),
         }
     }
 
@@ -293,6 +591,14 @@ impl Tool for ToolType {
                 tool.apply_config(config);
             }
             Self::Selection(tool) => tool.apply_config(config),
+            Self::Slice(tool) => tool.apply_config(config),
+            Self::ExportSlice(tool) => tool.apply_config(config),
+            Self::Annotate(tool) => tool.apply_config(config),
+            Self::Pen(tool) => tool.apply_config(config),
+            Self::Polygon(tool) => tool.apply_config(config),
+            Self::Pixel(tool) => tool.apply_config(config),
+            Self::StickyNote(tool) => tool.apply_config(config),
+            Self::Laser(tool) => tool.apply_config(config),
         }
     }
 }
@@ -302,6 +608,14 @@ pub fn new_tool(tool_type: &str) -> Option<ToolType> {
     match tool_type {
         "DrawStroke" => Some(ToolType::DrawStroke(new_draw_stroke_tool())),
         "Selection" => Some(ToolType::Selection(new_selection_tool())),
+        "Slice" => Some(ToolType::Slice(new_slice_tool())),
+        "ExportSlice" => Some(ToolType::ExportSlice(new_export_slice_tool())),
+        "Annotate" => Some(ToolType::Annotate(new_annotate_tool())),
+        "Pen" => Some(ToolType::Pen(new_pen_tool())),
+        "Polygon" => Some(ToolType::Polygon(new_polygon_tool())),
+        "PixelBrush" => Some(ToolType::Pixel(new_pixel_tool())),
+        "StickyNote" => Some(ToolType::StickyNote(new_sticky_note_tool())),
+        "Laser" => Some(ToolType::Laser(new_laser_tool())),
         _ => None,
     }
 }
@@ -312,6 +626,14 @@ impl ToolType {
         match self {
             Self::DrawStroke(tool) => tool.current_state_name(),
             Self::Selection(tool) => tool.current_state_name(),
+            Self::Slice(tool) => tool.current_state_name(),
+            Self::ExportSlice(tool) => tool.current_state_name(),
+            Self::Annotate(tool) => tool.current_state_name(),
+            Self::Pen(tool) => tool.current_state_name(),
+            Self::Polygon(tool) => tool.current_state_name(),
+            Self::Pixel(tool) => tool.current_state_name(),
+            Self::StickyNote(tool) => tool.current_state_name(),
+            Self::Laser(tool) => tool.current_state_name(),
         }
     }
 }
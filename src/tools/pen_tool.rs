@@ -0,0 +1,610 @@
+use crate::command::Command;
+use crate::element::path::flatten_nodes;
+use crate::element::{Element, ElementType, Obb, PathNode, factory};
+use crate::renderer::Renderer;
+use crate::state::{EditorModel, ElementId};
+use crate::tools::{ColorPair, DrawStrokeConfig, Tool, ToolConfig};
+use egui::{Color32, Pos2, Ui};
+use log::{debug, trace};
+use std::any::Any;
+use std::ops::RangeInclusive;
+
+const THICKNESS_RANGE: RangeInclusive<f32> = 1.0..=20.0;
+// How close the pointer has to be to grab an anchor, a handle or a path
+const NODE_GRAB_RADIUS: f32 = 8.0;
+// Dragging less than this after placing an anchor leaves it a corner
+const MIN_HANDLE_LENGTH: f32 = 2.0;
+
+/// Whether the pen adds new paths or edits the nodes of an existing one
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum PenMode {
+    Draw,
+    EditNodes,
+}
+
+impl PenMode {
+    pub const ALL: [PenMode; 2] = [PenMode::Draw, PenMode::EditNodes];
+
+    pub fn label(&self) -> &'static str {
+        match self {
+            PenMode::Draw => "Draw",
+            PenMode::EditNodes => "Edit nodes",
+        }
+    }
+}
+
+/// The part of a path node being dragged
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum NodePart {
+    Anchor,
+    HandleIn,
+    HandleOut,
+    /// Pulling fresh mirrored handles out of the anchor
+    NewHandles,
+}
+
+// Config for PenTool
+#[derive(Clone, Debug)]
+pub struct PenToolConfig {
+    pub mode: PenMode,
+    pub color: Color32,
+    pub thickness: f32,
+}
+
+impl ToolConfig for PenToolConfig {
+    fn tool_name(&self) -> &'static str {
+        "Pen"
+    }
+
+    fn as_any(&self) -> &dyn Any {
+        self
+    }
+
+    fn as_any_mut(&mut self) -> &mut dyn Any {
+        self
+    }
+}
+
+// State enum for the PenTool
+#[derive(Clone, Debug)]
+pub enum PenState {
+    Idle,
+    /// Placing the anchors of a new path; `placing` while the pointer is
+    /// still down on the newest anchor, pulling out its handles
+    Drawing {
+        nodes: Vec<PathNode>,
+        placing: bool,
+    },
+    /// Dragging part of a node of the path being edited
+    MovingNode {
+        index: usize,
+        part: NodePart,
+        original: PathNode,
+        node: PathNode,
+        /// Frame of the path, which the node is dragged in
+        frame: Obb,
+    },
+}
+
+/// Tool building cubic Bézier paths from anchors and handles, and editing their nodes
+#[derive(Clone, Debug)]
+pub struct UnifiedPenTool {
+    pub state: PenState,
+    pub mode: PenMode,
+    pub color: Color32,
+    pub thickness: f32,
+    // Path whose nodes are shown for editing; kept across node edits
+    editing_path: Option<ElementId>,
+    // Path finished from the keyboard, handed out by the next `ui` call
+    pending_command: Option<Command>,
+}
+
+impl UnifiedPenTool {
+    pub fn new() -> Self {
+        Self {
+            state: PenState::Idle,
+            mode: PenMode::Draw,
+            color: Color32::BLACK,
+            thickness: 2.0,
+            editing_path: None,
+            pending_command: None,
+        }
+    }
+
+    // Get the current state name
+    pub fn current_state_name(&self) -> &'static str {
+        match self.state {
+            PenState::Idle => "Idle",
+            PenState::Drawing { .. } => "Drawing",
+            PenState::MovingNode { .. } => "Moving Node",
+        }
+    }
+
+    /// End the path being drawn, returning the command adding it
+    ///
+    /// Paths need at least two anchors; shorter ones are dropped.
+    fn finish_path(&mut self, editor_model: &EditorModel, closed: bool) -> Option<Command> {
+        let PenState::Drawing { nodes, .. } = std::mem::replace(&mut self.state, PenState::Idle)
+        else {
+            return None;
+        };
+        if nodes.len() < 2 {
+            return None;
+        }
+
+        debug!(closed = closed, nodes = nodes.len(); "Finishing path");
+        let element = factory::create_path(
+            editor_model.reserve_id(),
+            nodes,
+            closed,
+            self.thickness,
+            self.color,
+        );
+        Some(Command::AddElement { element })
+    }
+
+    fn set_mode(&mut self, mode: PenMode) {
+        if self.mode != mode {
+            self.mode = mode;
+            self.state = PenState::Idle;
+            self.editing_path = None;
+        }
+    }
+}
+
+/// The path with the given ID, if there is one
+fn find_path(
+    editor_model: &EditorModel,
+    element_id: ElementId,
+) -> Option<&crate::element::path::Path> {
+    match editor_model.find_element_by_id(element_id) {
+        Some(ElementType::Path(path)) => Some(path),
+        _ => None,
+    }
+}
+
+/// Which anchor or handle of `nodes` is within reach of `pos`
+///
+/// Handles win over anchors, as a short handle would otherwise be impossible to grab.
+pub fn node_part_at(nodes: &[PathNode], pos: Pos2) -> Option<(usize, NodePart)> {
+    for (index, node) in nodes.iter().enumerate() {
+        for (part, handle) in [
+            (NodePart::HandleIn, node.handle_in),
+            (NodePart::HandleOut, node.handle_out),
+        ] {
+            if handle != node.anchor && handle.distance(pos) <= NODE_GRAB_RADIUS {
+                return Some((index, part));
+            }
+        }
+    }
+    nodes
+        .iter()
+        .position(|node| node.anchor.distance(pos) <= NODE_GRAB_RADIUS)
+        .map(|index| (index, NodePart::Anchor))
+}
+
+/// `node` with `part` dragged to `pos`
+///
+/// Moving an anchor brings its handles along. Moving one handle of a smooth
+/// node turns the other with it, keeping that one's length, unless `break_handles`.
+pub fn drag_node_part(node: PathNode, part: NodePart, pos: Pos2, break_handles: bool) -> PathNode {
+    let smooth = !break_handles && {
+        let (handle_in, handle_out) = (node.handle_in - node.anchor, node.handle_out - node.anchor);
+        handle_in != egui::Vec2::ZERO
+            && handle_out != egui::Vec2::ZERO
+            && (handle_in.normalized() + handle_out.normalized()).length() < 0.01
+    };
+    let turned = |other: Pos2| {
+        let length = (other - node.anchor).length();
+        node.anchor + (node.anchor - pos).normalized() * length
+    };
+
+    match part {
+        NodePart::Anchor => node.translated(pos - node.anchor),
+        NodePart::NewHandles => PathNode::smooth(node.anchor, pos),
+        NodePart::HandleIn => PathNode {
+            handle_in: pos,
+            handle_out: if smooth {
+                turned(node.handle_out)
+            } else {
+                node.handle_out
+            },
+            ..node
+        },
+        NodePart::HandleOut => PathNode {
+            handle_out: pos,
+            handle_in: if smooth {
+                turned(node.handle_in)
+            } else {
+                node.handle_in
+            },
+            ..node
+        },
+    }
+}
+
+impl Tool for UnifiedPenTool {
+    fn name(&self) -> &'static str {
+        "Pen"
+    }
+
+    fn activate(&mut self, _editor_model: &EditorModel) {
+        self.state = PenState::Idle;
+        trace!(tool = self.name(); "Tool activated");
+    }
+
+    fn deactivate(&mut self, _editor_model: &EditorModel) {
+        self.state = PenState::Idle;
+        self.editing_path = None;
+        trace!(tool = self.name(); "Tool deactivated");
+    }
+
+    fn on_pointer_down(
+        &mut self,
+        pos: Pos2,
+        button: egui::PointerButton,
+        modifiers: &egui::Modifiers,
+        editor_model: &EditorModel,
+        _renderer: &mut Renderer,
+    ) -> Option<Command> {
+        if button != egui::PointerButton::Primary {
+            return None;
+        }
+
+        match self.mode {
+            PenMode::Draw => {
+                if let PenState::Drawing { nodes, placing } = &mut self.state {
+                    // Clicking the first anchor closes the path, the last one ends it
+                    if nodes.len() >= 2 && nodes[0].anchor.distance(pos) <= NODE_GRAB_RADIUS {
+                        return self.finish_path(editor_model, true);
+                    }
+                    if nodes
+                        .last()
+                        .is_some_and(|node| node.anchor.distance(pos) <= NODE_GRAB_RADIUS)
+                    {
+                        return self.finish_path(editor_model, false);
+                    }
+                    nodes.push(PathNode::corner(pos));
+                    *placing = true;
+                } else {
+                    self.state = PenState::Drawing {
+                        nodes: vec![PathNode::corner(pos)],
+                        placing: true,
+                    };
+                }
+                None
+            }
+            PenMode::EditNodes => {
+                let editing = self
+                    .editing_path
+                    .and_then(|element_id| find_path(editor_model, element_id));
+                if let Some(path) = editing {
+                    let frame = path.obb();
+                    if let Some((index, part)) = node_part_at(path.nodes(), frame.to_local(pos)) {
+                        let part = if part == NodePart::Anchor && modifiers.alt {
+                            NodePart::NewHandles
+                        } else {
+                            part
+                        };
+                        let node = path.nodes()[index];
+                        self.state = PenState::MovingNode {
+                            index,
+                            part,
+                            original: node,
+                            node,
+                            frame,
+                        };
+                        return None;
+                    }
+                }
+
+                // Pick the topmost path under the pointer, or stop editing
+                self.editing_path =
+                    editor_model
+                        .elements
+                        .iter()
+                        .rev()
+                        .find_map(|element| match element {
+                            ElementType::Path(path)
+                                if path.distance_to(path.obb().to_local(pos))
+                                    <= NODE_GRAB_RADIUS.max(path.thickness() / 2.0) =>
+                            {
+                                Some(path.id())
+                            }
+                            _ => None,
+                        });
+                None
+            }
+        }
+    }
+
+    fn on_pointer_move(
+        &mut self,
+        pos: Pos2,
+        held_buttons: &[egui::PointerButton],
+        modifiers: &egui::Modifiers,
+        _editor_model: &mut EditorModel,
+        _ui: &egui::Ui,
+        _renderer: &mut Renderer,
+    ) -> Option<Command> {
+        if !held_buttons.contains(&egui::PointerButton::Primary) {
+            return None;
+        }
+
+        match &mut self.state {
+            PenState::Drawing {
+                nodes,
+                placing: true,
+            } => {
+                if let Some(node) = nodes.last_mut() {
+                    *node = if node.anchor.distance(pos) < MIN_HANDLE_LENGTH {
+                        PathNode::corner(node.anchor)
+                    } else {
+                        PathNode::smooth(node.anchor, pos)
+                    };
+                }
+            }
+            PenState::MovingNode {
+                part,
+                original,
+                node,
+                frame,
+                ..
+            } => {
+                *node = drag_node_part(*original, *part, frame.to_local(pos), modifiers.alt);
+            }
+            _ => {}
+        }
+        None
+    }
+
+    fn on_pointer_up(
+        &mut self,
+        _pos: Pos2,
+        button: egui::PointerButton,
+        _modifiers: &egui::Modifiers,
+        editor_model: &EditorModel,
+    ) -> Option<Command> {
+        if button != egui::PointerButton::Primary {
+            return None;
+        }
+
+        match &mut self.state {
+            PenState::Drawing { placing, .. } => {
+                *placing = false;
+                None
+            }
+            PenState::MovingNode { index, node, .. } => {
+                let (index, node) = (*index, *node);
+                self.state = PenState::Idle;
+                Command::new_set_path_node(editor_model, self.editing_path?, index, node)
+            }
+            PenState::Idle => None,
+        }
+    }
+
+    fn on_key(
+        &mut self,
+        key: egui::Key,
+        pressed: bool,
+        _modifiers: &egui::Modifiers,
+        editor_model: &EditorModel,
+    ) {
+        if !pressed {
+            return;
+        }
+
+        match key {
+            egui::Key::Enter => self.pending_command = self.finish_path(editor_model, false),
+            egui::Key::Escape => {
+                self.state = PenState::Idle;
+                self.editing_path = None;
+            }
+            _ => {}
+        }
+    }
+
+    fn brush_size(&self) -> Option<(f32, RangeInclusive<f32>)> {
+        (self.mode == PenMode::Draw).then_some((self.thickness, THICKNESS_RANGE))
+    }
+
+    fn set_brush_size(&mut self, size: f32) {
+        self.thickness = size;
+    }
+
+    fn cursor_icon(
+        &self,
+        pos: Pos2,
+        editor_model: &EditorModel,
+        _renderer: &Renderer,
+    ) -> Option<egui::CursorIcon> {
+        match self.mode {
+            PenMode::Draw => Some(egui::CursorIcon::Crosshair),
+            PenMode::EditNodes => {
+                let over_node = matches!(self.state, PenState::MovingNode { .. })
+                    || self
+                        .editing_path
+                        .and_then(|element_id| find_path(editor_model, element_id))
+                        .is_some_and(|path| {
+                            node_part_at(path.nodes(), path.obb().to_local(pos)).is_some()
+                        });
+                over_node.then_some(egui::CursorIcon::Move)
+            }
+        }
+    }
+
+    fn reset_interaction_state(&mut self) {
+        self.state = PenState::Idle;
+    }
+
+    fn update_preview(&mut self, renderer: &mut Renderer) {
+        match &self.state {
+            PenState::Drawing { nodes, .. } => {
+                renderer.set_stroke_preview(
+                    flatten_nodes(nodes, false),
+                    self.thickness,
+                    self.color,
+                );
+                renderer.set_path_node_preview(Some(nodes.clone()));
+            }
+            state => {
+                let editing = self.editing_path.and_then(|element_id| {
+                    match renderer.find_element(element_id) {
+                        Some(ElementType::Path(path)) => Some(path.clone()),
+                        _ => None,
+                    }
+                });
+                let Some(path) = editing else {
+                    renderer.clear_stroke_preview();
+                    renderer.set_path_node_preview(None);
+                    return;
+                };
+
+                let mut nodes = path.nodes().to_vec();
+                let frame = match state {
+                    PenState::MovingNode { frame, .. } => *frame,
+                    _ => path.obb(),
+                };
+                if let PenState::MovingNode { index, node, .. } = state {
+                    if let Some(slot) = nodes.get_mut(*index) {
+                        *slot = *node;
+                    }
+                    let points = flatten_nodes(&nodes, path.closed());
+                    renderer.set_stroke_preview(
+                        points.into_iter().map(|pos| frame.to_world(pos)).collect(),
+                        path.thickness(),
+                        path.color(),
+                    );
+                } else {
+                    renderer.clear_stroke_preview();
+                }
+                let nodes = nodes
+                    .into_iter()
+                    .map(|node| node.mapped(|pos| frame.to_world(pos)))
+                    .collect();
+                renderer.set_path_node_preview(Some(nodes));
+            }
+        }
+    }
+
+    fn clear_preview(&mut self, renderer: &mut Renderer) {
+        renderer.clear_stroke_preview();
+        renderer.set_path_node_preview(None);
+    }
+
+    fn ui(&mut self, ui: &mut Ui, editor_model: &EditorModel) -> Option<Command> {
+        let mut command = self.pending_command.take();
+
+        ui.horizontal(|ui| {
+            for mode in PenMode::ALL {
+                if ui
+                    .selectable_label(self.mode == mode, mode.label())
+                    .clicked()
+                {
+                    self.set_mode(mode);
+                }
+            }
+        });
+
+        match self.mode {
+            PenMode::Draw => {
+                ui.horizontal(|ui| {
+                    ui.label("Thickness:");
+                    ui.add(egui::Slider::new(&mut self.thickness, THICKNESS_RANGE).text("px"));
+                });
+
+                ui.separator();
+                ui.label("Click to place anchors; drag to pull out curve handles.");
+                ui.label("Click the last anchor or press Enter to finish, the first to close.");
+                let can_finish =
+                    matches!(&self.state, PenState::Drawing { nodes, .. } if nodes.len() >= 2);
+                if ui
+                    .add_enabled(can_finish, egui::Button::new("Finish path"))
+                    .clicked()
+                {
+                    command = self.finish_path(editor_model, false);
+                }
+            }
+            PenMode::EditNodes => {
+                match self
+                    .editing_path
+                    .and_then(|element_id| find_path(editor_model, element_id))
+                {
+                    Some(path) => ui.label(format!(
+                        "Editing {} ({} nodes)",
+                        path.display_name(),
+                        path.nodes().len()
+                    )),
+                    None => ui.label("Click a path to edit its nodes."),
+                };
+                ui.separator();
+                ui.label("Drag anchors or handles to reshape the path.");
+                ui.label("• Alt + drag anchor: Pull out new handles");
+                ui.label("• Alt + drag handle: Move it on its own");
+            }
+        }
+
+        command
+    }
+
+    fn get_config(&self) -> Box<dyn ToolConfig> {
+        Box::new(PenToolConfig {
+            mode: self.mode,
+            color: self.color,
+            thickness: self.thickness,
+        })
+    }
+
+    fn apply_config(&mut self, config: &dyn ToolConfig) {
+        if let Some(config) = config.as_any().downcast_ref::<PenToolConfig>() {
+            self.set_mode(config.mode);
+            self.color = config.color;
+            self.thickness = config.thickness;
+        } else if let Some(config) = config.as_any().downcast_ref::<DrawStrokeConfig>() {
+            // Paths are drawn in the same color and thickness as strokes by default
+            self.color = config.color;
+            self.thickness = config.thickness;
+        } else if let Some(colors) = config.as_any().downcast_ref::<ColorPair>() {
+            self.color = colors.foreground;
+        }
+    }
+}
+
+impl Default for UnifiedPenTool {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+// Factory function to create a new PenTool
+pub fn new_pen_tool() -> UnifiedPenTool {
+    UnifiedPenTool::new()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_dragging_a_smooth_handle_turns_its_twin() {
+        let node = PathNode::smooth(Pos2::new(0.0, 0.0), Pos2::new(10.0, 0.0));
+        assert_eq!(
+            node_part_at(&[node], Pos2::new(9.0, 1.0)),
+            Some((0, NodePart::HandleOut))
+        );
+        assert_eq!(
+            node_part_at(&[node], Pos2::new(1.0, 1.0)),
+            Some((0, NodePart::Anchor))
+        );
+
+        let dragged = drag_node_part(node, NodePart::HandleOut, Pos2::new(0.0, 20.0), false);
+        assert_eq!(dragged.handle_out, Pos2::new(0.0, 20.0));
+        assert!((dragged.handle_in - Pos2::new(0.0, -10.0)).length() < 1e-4);
+
+        let broken = drag_node_part(node, NodePart::HandleOut, Pos2::new(0.0, 20.0), true);
+        assert_eq!(broken.handle_in, node.handle_in);
+
+        let moved = drag_node_part(node, NodePart::Anchor, Pos2::new(5.0, 5.0), false);
+        assert_eq!(moved, node.translated(egui::vec2(5.0, 5.0)));
+    }
+}
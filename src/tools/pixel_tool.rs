@@ -0,0 +1,475 @@
+use crate::command::Command;
+use crate::element::{Element, ElementType, Obb, PixelRegion};
+use crate::pressure::PressureResponse;
+use crate::renderer::Renderer;
+use crate::state::{EditorModel, ElementId};
+use crate::tools::{ColorPair, DrawStrokeConfig, Tool, ToolConfig};
+use egui::{Color32, Pos2, Rect, Ui, Vec2};
+use image::RgbaImage;
+use log::trace;
+use std::any::Any;
+use std::ops::RangeInclusive;
+
+// Largest brush the slider offers, in image pixels
+const MAX_BRUSH_SIZE: f32 = 128.0;
+
+// Config for PixelTool
+#[derive(Clone, Debug)]
+pub struct PixelToolConfig {
+    pub size: f32,
+    pub color: Color32,
+    pub erase: bool,
+}
+
+impl ToolConfig for PixelToolConfig {
+    fn tool_name(&self) -> &'static str {
+        "Pixel Brush"
+    }
+
+    fn as_any(&self) -> &dyn Any {
+        self
+    }
+
+    fn as_any_mut(&mut self) -> &mut dyn Any {
+        self
+    }
+}
+
+// State enum for the PixelTool
+#[derive(Clone)]
+pub enum PixelState {
+    Idle,
+    /// Painting on an image; `canvas` is `original` with the stroke so far
+    Painting {
+        element_id: ElementId,
+        image_frame: Obb,
+        original: RgbaImage,
+        canvas: RgbaImage,
+        /// Last brush position, in image pixels
+        last_pos: Pos2,
+        /// Pixels changed so far, as (x, y, width, height)
+        dirty: Option<[u32; 4]>,
+    },
+}
+
+// Custom Debug implementation to keep the pixel buffers out of the logs
+impl std::fmt::Debug for PixelState {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Self::Idle => write!(f, "Idle"),
+            Self::Painting {
+                element_id,
+                last_pos,
+                dirty,
+                ..
+            } => f
+                .debug_struct("Painting")
+                .field("element_id", element_id)
+                .field("last_pos", last_pos)
+                .field("dirty", dirty)
+                .finish(),
+        }
+    }
+}
+
+/// Tool painting straight into the pixels of image elements
+///
+/// Each stroke is one undo step, stored as the changed rectangle of pixels from
+/// before and after it.
+#[derive(Clone, Debug)]
+pub struct UnifiedPixelTool {
+    pub state: PixelState,
+    /// Brush diameter, in image pixels
+    pub size: f32,
+    pub color: Color32,
+    /// Paint transparency instead of the color
+    pub erase: bool,
+    /// How each dab of the brush follows pen pressure
+    pub pressure_response: PressureResponse,
+    // Latest pen pressure, if the pen reports any
+    pressure: Option<f32>,
+}
+
+impl UnifiedPixelTool {
+    pub fn new() -> Self {
+        Self {
+            state: PixelState::Idle,
+            size: 8.0,
+            color: Color32::BLACK,
+            erase: false,
+            pressure_response: PressureResponse::default(),
+            pressure: None,
+        }
+    }
+
+    // Get the current state name
+    pub fn current_state_name(&self) -> &'static str {
+        match self.state {
+            PixelState::Idle => "Idle",
+            PixelState::Painting { .. } => "Painting",
+        }
+    }
+
+    /// Radius and color of a dab at the current pressure
+    fn dab(&self) -> (f32, Option<Color32>) {
+        let (size, opacity) = self.pressure_response.factors(self.pressure);
+        let radius = (self.size * size / 2.0).max(0.5);
+        (
+            radius,
+            (!self.erase).then(|| self.color.gamma_multiply(opacity)),
+        )
+    }
+}
+
+/// Where document position `pos` falls in an image of `size` pixels shown over `frame`
+fn to_image_pixels(pos: Pos2, frame: Obb, size: Vec2) -> Pos2 {
+    ((frame.to_local(pos) - frame.rect.min) / frame.rect.size() * size).to_pos2()
+}
+
+/// Paint a round brush of `radius` along the segment from `from` to `to` (in image pixels)
+///
+/// Each pixel the brush covers becomes `color` laid over the pixel's `original`
+/// value, so going over a spot twice in one stroke doesn't build up translucent
+/// colors; None erases to transparent. Returns the rectangle of pixels touched,
+/// as (x, y, width, height).
+pub fn paint_segment(
+    canvas: &mut RgbaImage,
+    original: &RgbaImage,
+    from: Pos2,
+    to: Pos2,
+    radius: f32,
+    color: Option<Color32>,
+) -> Option<[u32; 4]> {
+    let bounds = Rect::from_two_pos(from, to).expand(radius);
+    let min_x = bounds.min.x.floor().max(0.0) as u32;
+    let min_y = bounds.min.y.floor().max(0.0) as u32;
+    let max_x = (bounds.max.x.ceil().max(0.0) as u32).min(canvas.width());
+    let max_y = (bounds.max.y.ceil().max(0.0) as u32).min(canvas.height());
+    if min_x >= max_x || min_y >= max_y {
+        return None;
+    }
+
+    for y in min_y..max_y {
+        for x in min_x..max_x {
+            let center = Pos2::new(x as f32 + 0.5, y as f32 + 0.5);
+            if crate::geometry::distance_to_segment(center, from, to) > radius {
+                continue;
+            }
+            let below = original.get_pixel(x, y).0;
+            canvas.put_pixel(x, y, image::Rgba(blend_over(color, below)));
+        }
+    }
+    Some([min_x, min_y, max_x - min_x, max_y - min_y])
+}
+
+/// Straight-alpha `color` over `below`, or transparent when erasing
+fn blend_over(color: Option<Color32>, below: [u8; 4]) -> [u8; 4] {
+    let Some(color) = color else {
+        return [0; 4];
+    };
+    let top = color.to_srgba_unmultiplied().map(|c| c as f32 / 255.0);
+    let below = below.map(|c| c as f32 / 255.0);
+    let alpha = top[3] + below[3] * (1.0 - top[3]);
+    if alpha <= 0.0 {
+        return [0; 4];
+    }
+    let channel = |i: usize| {
+        let value = (top[i] * top[3] + below[i] * below[3] * (1.0 - top[3])) / alpha;
+        (value * 255.0).round() as u8
+    };
+    [
+        channel(0),
+        channel(1),
+        channel(2),
+        (alpha * 255.0).round() as u8,
+    ]
+}
+
+/// The smallest rectangle covering both, as (x, y, width, height)
+fn union_rect(a: Option<[u32; 4]>, b: [u32; 4]) -> [u32; 4] {
+    let Some(a) = a else {
+        return b;
+    };
+    let min_x = a[0].min(b[0]);
+    let min_y = a[1].min(b[1]);
+    let max_x = (a[0] + a[2]).max(b[0] + b[2]);
+    let max_y = (a[1] + a[3]).max(b[1] + b[3]);
+    [min_x, min_y, max_x - min_x, max_y - min_y]
+}
+
+impl Tool for UnifiedPixelTool {
+    fn name(&self) -> &'static str {
+        "Pixel Brush"
+    }
+
+    fn activate(&mut self, _editor_model: &EditorModel) {
+        self.state = PixelState::Idle;
+        trace!(tool = self.name(); "Tool activated");
+    }
+
+    fn deactivate(&mut self, _editor_model: &EditorModel) {
+        self.state = PixelState::Idle;
+        trace!(tool = self.name(); "Tool deactivated");
+    }
+
+    fn on_pointer_down(
+        &mut self,
+        pos: Pos2,
+        button: egui::PointerButton,
+        _modifiers: &egui::Modifiers,
+        editor_model: &EditorModel,
+        renderer: &mut Renderer,
+    ) -> Option<Command> {
+        if button != egui::PointerButton::Primary {
+            return None;
+        }
+        let Some(ElementType::Image(image)) = editor_model.element_at_position(pos) else {
+            return None;
+        };
+        let Some(original) = image.decode_pixels() else {
+            log::warn!("Can't paint on image {}: it failed to decode", image.id());
+            return None;
+        };
+
+        let image_frame = image.obb();
+        let size = Vec2::new(original.width() as f32, original.height() as f32);
+        let start = to_image_pixels(pos, image_frame, size);
+        let mut canvas = original.clone();
+        let (radius, color) = self.dab();
+        let dirty = paint_segment(&mut canvas, &original, start, start, radius, color);
+        renderer.set_pixel_preview(image_frame, &canvas);
+
+        self.state = PixelState::Painting {
+            element_id: image.id(),
+            image_frame,
+            original,
+            canvas,
+            last_pos: start,
+            dirty,
+        };
+        None
+    }
+
+    fn on_pointer_move(
+        &mut self,
+        pos: Pos2,
+        held_buttons: &[egui::PointerButton],
+        _modifiers: &egui::Modifiers,
+        _editor_model: &mut EditorModel,
+        _ui: &egui::Ui,
+        renderer: &mut Renderer,
+    ) -> Option<Command> {
+        if !held_buttons.contains(&egui::PointerButton::Primary) {
+            return None;
+        }
+        let (radius, color) = self.dab();
+        let PixelState::Painting {
+            image_frame,
+            original,
+            canvas,
+            last_pos,
+            dirty,
+            ..
+        } = &mut self.state
+        else {
+            return None;
+        };
+
+        let size = Vec2::new(canvas.width() as f32, canvas.height() as f32);
+        let pos = to_image_pixels(pos, *image_frame, size);
+        if let Some([x, y, width, height]) =
+            paint_segment(canvas, original, *last_pos, pos, radius, color)
+        {
+            // Only the pixels under this segment go to the GPU
+            renderer.update_pixel_preview(&PixelRegion::copy_from(canvas, x, y, width, height));
+            *dirty = Some(union_rect(*dirty, [x, y, width, height]));
+        }
+        *last_pos = pos;
+        None
+    }
+
+    fn on_pointer_up(
+        &mut self,
+        _pos: Pos2,
+        button: egui::PointerButton,
+        _modifiers: &egui::Modifiers,
+        _editor_model: &EditorModel,
+    ) -> Option<Command> {
+        if button != egui::PointerButton::Primary {
+            return None;
+        }
+        let PixelState::Painting {
+            element_id,
+            original,
+            canvas,
+            dirty,
+            ..
+        } = std::mem::replace(&mut self.state, PixelState::Idle)
+        else {
+            return None;
+        };
+        self.pressure = None;
+
+        let [x, y, width, height] = dirty?;
+        Some(Command::PaintPixels {
+            element_id,
+            before: PixelRegion::copy_from(&original, x, y, width, height),
+            after: PixelRegion::copy_from(&canvas, x, y, width, height),
+        })
+    }
+
+    fn brush_size(&self) -> Option<(f32, RangeInclusive<f32>)> {
+        Some((self.size, 1.0..=MAX_BRUSH_SIZE))
+    }
+
+    fn set_brush_size(&mut self, size: f32) {
+        self.size = size;
+    }
+
+    fn set_pressure(&mut self, pressure: f32) {
+        self.pressure = Some(pressure);
+    }
+
+    fn cursor_icon(
+        &self,
+        _pos: Pos2,
+        _editor_model: &EditorModel,
+        _renderer: &Renderer,
+    ) -> Option<egui::CursorIcon> {
+        Some(egui::CursorIcon::Crosshair)
+    }
+
+    fn reset_interaction_state(&mut self) {
+        self.state = PixelState::Idle;
+    }
+
+    fn update_preview(&mut self, renderer: &mut Renderer) {
+        // The preview is kept up to date while painting; only clear it afterwards
+        if let PixelState::Idle = self.state {
+            renderer.clear_pixel_preview();
+        }
+    }
+
+    fn clear_preview(&mut self, renderer: &mut Renderer) {
+        renderer.clear_pixel_preview();
+    }
+
+    fn ui(&mut self, ui: &mut Ui, _editor_model: &EditorModel) -> Option<Command> {
+        ui.label("Pixel Brush");
+        ui.add(egui::Slider::new(&mut self.size, 1.0..=MAX_BRUSH_SIZE).text("Size (px)"));
+        ui.checkbox(&mut self.erase, "Erase")
+            .on_hover_text("Paint transparency instead of the foreground color");
+
+        ui.separator();
+        ui.label("Paint directly on an image's pixels.");
+        ui.label("Sizes are in the image's own pixels.");
+        None
+    }
+
+    fn get_config(&self) -> Box<dyn ToolConfig> {
+        Box::new(PixelToolConfig {
+            size: self.size,
+            color: self.color,
+            erase: self.erase,
+        })
+    }
+
+    fn apply_config(&mut self, config: &dyn ToolConfig) {
+        if let Some(config) = config.as_any().downcast_ref::<PixelToolConfig>() {
+            self.size = config.size;
+            self.color = config.color;
+            self.erase = config.erase;
+        } else if let Some(config) = config.as_any().downcast_ref::<DrawStrokeConfig>() {
+            // The brush paints with the stroke color by default
+            self.color = config.color;
+        } else if let Some(colors) = config.as_any().downcast_ref::<ColorPair>() {
+            self.color = colors.foreground;
+        } else if let Some(response) = config.as_any().downcast_ref::<PressureResponse>() {
+            self.pressure_response = response.clone();
+        }
+    }
+}
+
+impl Default for UnifiedPixelTool {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+// Factory function to create a new PixelTool
+pub fn new_pixel_tool() -> UnifiedPixelTool {
+    UnifiedPixelTool::new()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::element::factory;
+
+    #[test]
+    fn test_paint_segment_blends_over_the_original_once() {
+        let original = RgbaImage::from_pixel(8, 4, image::Rgba([255, 255, 255, 255]));
+        let mut canvas = original.clone();
+        let half_red = Some(Color32::from_rgba_unmultiplied(255, 0, 0, 128));
+        let from = Pos2::new(1.5, 1.5);
+        let to = Pos2::new(6.5, 1.5);
+
+        let dirty = paint_segment(&mut canvas, &original, from, to, 1.0, half_red).unwrap();
+        assert_eq!(dirty, [0, 0, 8, 3]);
+        // Painting the same segment again doesn't darken it
+        paint_segment(&mut canvas, &original, from, to, 1.0, half_red);
+        assert_eq!(canvas.get_pixel(3, 1).0, [255, 127, 127, 255]);
+        assert_eq!(canvas.get_pixel(3, 3).0, [255, 255, 255, 255]);
+
+        paint_segment(&mut canvas, &original, from, from, 0.5, None);
+        assert_eq!(canvas.get_pixel(1, 1).0, [0, 0, 0, 0]);
+        // Off the image
+        let outside = Pos2::new(-10.0, -10.0);
+        assert!(paint_segment(&mut canvas, &original, outside, outside, 1.0, None).is_none());
+    }
+
+    #[test]
+    fn test_paint_pixels_command_undoes_to_the_original() {
+        let pixels = RgbaImage::from_pixel(4, 4, image::Rgba([0, 0, 255, 255]));
+        let mut png = std::io::Cursor::new(Vec::new());
+        pixels
+            .write_to(&mut png, image::ImageOutputFormat::Png)
+            .unwrap();
+        let mut model = EditorModel::new();
+        let size = Vec2::new(40.0, 40.0);
+        model
+            .add_element(factory::create_image(
+                ElementId::new(1),
+                png.into_inner(),
+                size,
+                Pos2::ZERO,
+            ))
+            .unwrap();
+
+        let mut canvas = pixels.clone();
+        let [x, y, width, height] = paint_segment(
+            &mut canvas,
+            &pixels,
+            Pos2::new(0.5, 0.5),
+            Pos2::new(1.5, 0.5),
+            0.5,
+            Some(Color32::WHITE),
+        )
+        .unwrap();
+        let command = Command::PaintPixels {
+            element_id: ElementId::new(1),
+            before: PixelRegion::copy_from(&pixels, x, y, width, height),
+            after: PixelRegion::copy_from(&canvas, x, y, width, height),
+        };
+
+        let decoded = |model: &EditorModel| match model.find_element_by_id(ElementId::new(1)) {
+            Some(ElementType::Image(image)) => image.decode_pixels().unwrap(),
+            _ => panic!("image missing"),
+        };
+        command.execute(&mut model).unwrap();
+        assert_eq!(decoded(&model), canvas);
+        assert_eq!(decoded(&model).get_pixel(1, 0).0, [255, 255, 255, 255]);
+        command.undo(&mut model).unwrap();
+        assert_eq!(decoded(&model), pixels);
+    }
+}
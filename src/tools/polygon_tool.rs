@@ -0,0 +1,281 @@
+use crate::command::Command;
+use crate::element::{Element, PolygonParams, factory};
+use crate::renderer::Renderer;
+use crate::state::EditorModel;
+use crate::tools::{ColorPair, DrawStrokeConfig, Tool, ToolConfig};
+use egui::{Color32, Pos2, Ui, Vec2};
+use log::{debug, trace};
+use std::any::Any;
+
+// Drags shorter than this are taken as clicks and add nothing
+const MIN_RADIUS: f32 = 4.0;
+// Largest corner radius the sliders offer
+const MAX_CORNER_RADIUS: f32 = 100.0;
+
+// Config for PolygonTool
+#[derive(Clone, Debug)]
+pub struct PolygonToolConfig {
+    pub sides: u32,
+    pub star_ratio: f32,
+    pub corner_radius: f32,
+    pub fill: Color32,
+}
+
+impl ToolConfig for PolygonToolConfig {
+    fn tool_name(&self) -> &'static str {
+        "Polygon"
+    }
+
+    fn as_any(&self) -> &dyn Any {
+        self
+    }
+
+    fn as_any_mut(&mut self) -> &mut dyn Any {
+        self
+    }
+}
+
+// State enum for the PolygonTool
+#[derive(Clone, Debug)]
+pub enum PolygonState {
+    Idle,
+    /// Dragging out from the center; the drag sets the size and the first corner's direction
+    Dragging {
+        center: Pos2,
+        current_pos: Pos2,
+    },
+}
+
+/// Tool drawing regular polygons and stars as shapes that stay editable
+#[derive(Clone, Debug)]
+pub struct UnifiedPolygonTool {
+    pub state: PolygonState,
+    pub sides: u32,
+    pub star_ratio: f32,
+    pub corner_radius: f32,
+    pub fill: Color32,
+}
+
+impl UnifiedPolygonTool {
+    pub fn new() -> Self {
+        Self {
+            state: PolygonState::Idle,
+            sides: 5,
+            star_ratio: 1.0,
+            corner_radius: 0.0,
+            fill: Color32::BLACK,
+        }
+    }
+
+    // Get the current state name
+    pub fn current_state_name(&self) -> &'static str {
+        match self.state {
+            PolygonState::Idle => "Idle",
+            PolygonState::Dragging { .. } => "Dragging",
+        }
+    }
+
+    /// Parameters of the polygon dragged from `center` to `pos`, or None if the drag is too short
+    pub fn params(&self, center: Pos2, pos: Pos2) -> Option<PolygonParams> {
+        let drag = pos - center;
+        if drag.length() < MIN_RADIUS {
+            return None;
+        }
+        Some(PolygonParams {
+            center,
+            radius: Vec2::splat(drag.length()),
+            sides: self.sides,
+            star_ratio: self.star_ratio,
+            corner_radius: self.corner_radius,
+            rotation: drag.angle(),
+        })
+    }
+}
+
+/// Sliders for the sides, star ratio and corner radius of a polygon
+///
+/// Shared by the tool and the properties panel; the response reports changes
+/// and drags across all three.
+pub fn polygon_settings_ui(
+    ui: &mut Ui,
+    sides: &mut u32,
+    star_ratio: &mut f32,
+    corner_radius: &mut f32,
+) -> egui::Response {
+    let sides_response = ui.add(
+        egui::Slider::new(sides, PolygonParams::MIN_SIDES..=PolygonParams::MAX_SIDES).text("Sides"),
+    );
+    let star_response = ui
+        .add(egui::Slider::new(star_ratio, 0.1..=1.0).text("Star ratio"))
+        .on_hover_text("Inner corners relative to the outer ones; 1 for a plain polygon");
+    let corner_response = ui.add(
+        egui::Slider::new(corner_radius, 0.0..=MAX_CORNER_RADIUS)
+            .text("Corner radius")
+            .suffix(" px"),
+    );
+    sides_response | star_response | corner_response
+}
+
+impl Tool for UnifiedPolygonTool {
+    fn name(&self) -> &'static str {
+        "Polygon"
+    }
+
+    fn activate(&mut self, _editor_model: &EditorModel) {
+        self.state = PolygonState::Idle;
+        trace!(tool = self.name(); "Tool activated");
+    }
+
+    fn deactivate(&mut self, _editor_model: &EditorModel) {
+        self.state = PolygonState::Idle;
+        trace!(tool = self.name(); "Tool deactivated");
+    }
+
+    fn on_pointer_down(
+        &mut self,
+        pos: Pos2,
+        button: egui::PointerButton,
+        _modifiers: &egui::Modifiers,
+        _editor_model: &EditorModel,
+        _renderer: &mut Renderer,
+    ) -> Option<Command> {
+        if button != egui::PointerButton::Primary {
+            return None;
+        }
+        self.state = PolygonState::Dragging {
+            center: pos,
+            current_pos: pos,
+        };
+        None
+    }
+
+    fn on_pointer_move(
+        &mut self,
+        pos: Pos2,
+        held_buttons: &[egui::PointerButton],
+        _modifiers: &egui::Modifiers,
+        _editor_model: &mut EditorModel,
+        _ui: &egui::Ui,
+        _renderer: &mut Renderer,
+    ) -> Option<Command> {
+        if !held_buttons.contains(&egui::PointerButton::Primary) {
+            return None;
+        }
+        if let PolygonState::Dragging { current_pos, .. } = &mut self.state {
+            *current_pos = pos;
+        }
+        None
+    }
+
+    fn on_pointer_up(
+        &mut self,
+        pos: Pos2,
+        button: egui::PointerButton,
+        _modifiers: &egui::Modifiers,
+        editor_model: &EditorModel,
+    ) -> Option<Command> {
+        if button != egui::PointerButton::Primary {
+            return None;
+        }
+
+        let PolygonState::Dragging { center, .. } = self.state else {
+            return None;
+        };
+        self.state = PolygonState::Idle;
+
+        let params = self.params(center, pos)?;
+        debug!(sides = params.sides, radius = params.radius.x; "Adding polygon");
+        let mut element = factory::create_polygon(editor_model.reserve_id(), params, self.fill);
+        let name = if params.star_ratio < 1.0 {
+            "Star"
+        } else {
+            "Polygon"
+        };
+        element.metadata_mut().name = name.to_string();
+        Some(Command::AddElement { element })
+    }
+
+    fn cursor_icon(
+        &self,
+        _pos: Pos2,
+        _editor_model: &EditorModel,
+        _renderer: &Renderer,
+    ) -> Option<egui::CursorIcon> {
+        Some(egui::CursorIcon::Crosshair)
+    }
+
+    fn reset_interaction_state(&mut self) {
+        self.state = PolygonState::Idle;
+    }
+
+    fn update_preview(&mut self, renderer: &mut Renderer) {
+        let PolygonState::Dragging {
+            center,
+            current_pos,
+        } = self.state
+        else {
+            renderer.clear_stroke_preview();
+            return;
+        };
+
+        match self.params(center, current_pos) {
+            Some(params) => {
+                let mut outline = params.outline();
+                outline.push(outline[0]);
+                renderer.set_stroke_preview(outline, 1.0, self.fill);
+            }
+            None => renderer.clear_stroke_preview(),
+        }
+    }
+
+    fn clear_preview(&mut self, renderer: &mut Renderer) {
+        renderer.clear_stroke_preview();
+    }
+
+    fn ui(&mut self, ui: &mut Ui, _editor_model: &EditorModel) -> Option<Command> {
+        polygon_settings_ui(
+            ui,
+            &mut self.sides,
+            &mut self.star_ratio,
+            &mut self.corner_radius,
+        );
+
+        ui.separator();
+        ui.label("Drag from the center out to a corner.");
+        None
+    }
+
+    fn get_config(&self) -> Box<dyn ToolConfig> {
+        Box::new(PolygonToolConfig {
+            sides: self.sides,
+            star_ratio: self.star_ratio,
+            corner_radius: self.corner_radius,
+            fill: self.fill,
+        })
+    }
+
+    fn apply_config(&mut self, config: &dyn ToolConfig) {
+        if let Some(config) = config.as_any().downcast_ref::<PolygonToolConfig>() {
+            self.sides = config.sides;
+            self.star_ratio = config.star_ratio;
+            self.corner_radius = config.corner_radius;
+            self.fill = config.fill;
+        } else if let Some(config) = config.as_any().downcast_ref::<DrawStrokeConfig>() {
+            // Polygons are filled with the stroke color by default
+            self.fill = config.color;
+        } else if let Some(colors) = config.as_any().downcast_ref::<ColorPair>() {
+            self.fill = colors.foreground;
+        }
+    }
+}
+
+impl Default for UnifiedPolygonTool {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+// Factory function to create a new PolygonTool
+pub fn new_polygon_tool() -> UnifiedPolygonTool {
+    UnifiedPolygonTool::new()
+}
@@ -1,17 +1,30 @@
 use crate::command::Command;
 use crate::element::Element;
 use crate::element::ElementType;
-use crate::element::{RESIZE_HANDLE_RADIUS, compute_element_rect};
+use crate::element::shape::Shape;
+use crate::element::{MIN_ELEMENT_SIZE, PatternFill, PolygonParams, ResizeConstraints, StrokeStyle};
+use crate::element::compute_element_rect;
+use crate::geometry::BooleanOp;
 use crate::renderer::Renderer;
-use crate::state::EditorModel;
-use crate::tools::{Tool, ToolConfig};
-use crate::widgets::Corner;
-use egui::{Pos2, Ui};
-use log::info;
+use crate::ruler_guides::{snap_position, snap_rect};
+use crate::settings::SnapIncrements;
+use crate::state::{EditorModel, ElementId};
+use crate::tools::{Tool, ToolConfig, note_color_ui, polygon_settings_ui, stroke_style_ui};
+use crate::trace::{TraceJob, TraceSettings, trace_command};
+use crate::widgets::{Corner, TransformHandle};
+use egui::{Pos2, Ui, Vec2};
+use log::{debug, trace};
 use std::any::Any;
 
 // Constants
 const DEFAULT_HANDLE_SIZE: f32 = 10.0;
+const DEFAULT_JOIN_TOLERANCE: f32 = 10.0;
+const DEFAULT_SIMPLIFY_TOLERANCE: f32 = 2.0;
+const DEFAULT_SMOOTH_STRENGTH: f32 = 0.5;
+// Shortest distance between consecutive points of a lasso
+const MIN_LASSO_POINT_DISTANCE: f32 = 2.0;
+// Color of the simplify/smooth result drawn over the canvas while adjusting it
+const STROKE_ACTION_PREVIEW_COLOR: egui::Color32 = egui::Color32::from_rgb(255, 140, 0);
 
 // Config for SelectionTool
 #[derive(Clone, Debug)]
@@ -33,6 +46,84 @@ impl ToolConfig for SelectionToolConfig {
     }
 }
 
+/// How dragging over empty canvas picks elements
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub enum SelectionMode {
+    /// Select everything touching the dragged rectangle
+    #[default]
+    Box,
+    /// Select everything entirely inside a freeform path
+    Lasso,
+}
+
+impl SelectionMode {
+    pub fn label(self) -> &'static str {
+        match self {
+            SelectionMode::Box => "Box",
+            SelectionMode::Lasso => "Lasso",
+        }
+    }
+}
+
+/// Bulk selection actions picking elements across the whole document
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum SelectMatching {
+    AllStrokes,
+    /// Elements with the same color as any selected element
+    SameColor,
+    /// Elements of the same type as any selected element
+    SameType,
+}
+
+impl SelectMatching {
+    pub const ALL: [SelectMatching; 3] = [
+        SelectMatching::AllStrokes,
+        SelectMatching::SameColor,
+        SelectMatching::SameType,
+    ];
+
+    pub fn label(self) -> &'static str {
+        match self {
+            SelectMatching::AllStrokes => "All strokes",
+            SelectMatching::SameColor => "Same color",
+            SelectMatching::SameType => "Same type",
+        }
+    }
+
+    /// Whether the action needs something selected to compare against
+    pub fn needs_selection(self) -> bool {
+        !matches!(self, SelectMatching::AllStrokes)
+    }
+
+    /// Command replacing the selection with every unlocked element that matches
+    ///
+    /// Returns None if nothing matches or the selection wouldn't change.
+    pub fn command(self, editor_model: &EditorModel) -> Option<Command> {
+        let selected = editor_model.selected_elements();
+        let colors: Vec<_> = selected.iter().filter_map(|e| e.color()).collect();
+        let types: Vec<_> = selected.iter().map(|e| e.element_type()).collect();
+        let mut ids: Vec<ElementId> = editor_model
+            .elements
+            .iter()
+            .filter(|element| !element.metadata().is_locked())
+            .filter(|element| match self {
+                SelectMatching::AllStrokes => matches!(element, ElementType::Stroke(_)),
+                SelectMatching::SameColor => element.color().is_some_and(|c| colors.contains(&c)),
+                SelectMatching::SameType => types.contains(&element.element_type()),
+            })
+            .map(|element| element.id())
+            .collect();
+        ids.sort_unstable();
+
+        let unchanged = ids.len() == editor_model.selected_ids().len()
+            && ids.iter().all(|id| editor_model.is_element_selected(*id));
+        if ids.is_empty() || unchanged {
+            return None;
+        }
+        select_elements_command(editor_model, ids, false)
+    }
+}
+
 // New consolidated state enum for the refactored SelectionTool
 #[derive(Clone)]
 pub enum SelectionState {
@@ -42,20 +133,33 @@ pub enum SelectionState {
         current_pos: egui::Pos2,
         adding_to_selection: bool, // Tracks if Shift is held
     },
+    Lasso {
+        points: Vec<egui::Pos2>,
+        adding_to_selection: bool, // Tracks if Shift is held
+    },
     Dragging {
         start_pos: egui::Pos2,
         current_pos: egui::Pos2,
-        initial_element_positions: std::collections::HashMap<usize, egui::Pos2>,
+        initial_element_positions: std::collections::HashMap<ElementId, egui::Pos2>,
         original_rect: egui::Rect,  // Store the exact original rect
         grid_snap_enabled: bool, // Tracks if Ctrl is held
     },
     Resizing {
-        element_id: usize,
-        corner: Corner,
+        element_id: ElementId,
+        handle: TransformHandle,
         start_pos: egui::Pos2,
         current_pos: egui::Pos2,
         original_rect: egui::Rect,
-        preserve_aspect_ratio: bool, // Tracks if Shift is held
+        preserve_aspect_ratio: bool, // Flipped from the element's default by Shift
+        constraints: ResizeConstraints,
+    },
+    // Scaling a multiple selection by the handles of the box around all of it
+    ResizingSelection {
+        handle: TransformHandle,
+        current_pos: egui::Pos2,
+        original_rect: egui::Rect,
+        preserve_aspect_ratio: bool, // Flipped from the selection's default by Shift
+        constraints: ResizeConstraints,
     },
 }
 
@@ -70,21 +174,42 @@ impl std::fmt::Debug for SelectionState {
                 .field("current_pos", current_pos)
                 .field("adding_to_selection", adding_to_selection)
                 .finish(),
+            Self::Lasso { points, adding_to_selection } => f
+                .debug_struct("Lasso")
+                .field("point_count", &points.len())
+                .field("adding_to_selection", adding_to_selection)
+                .finish(),
             Self::Resizing {
                 element_id,
-                corner,
+                handle,
                 original_rect,
                 start_pos,
                 current_pos,
                 preserve_aspect_ratio,
+                constraints,
             } => f
                 .debug_struct("Resizing")
                 .field("element_id", element_id)
-                .field("corner", corner)
+                .field("handle", handle)
                 .field("original_rect", original_rect)
                 .field("start_pos", start_pos)
                 .field("current_pos", current_pos)
                 .field("preserve_aspect_ratio", preserve_aspect_ratio)
+                .field("constraints", constraints)
+                .finish(),
+            Self::ResizingSelection {
+                handle,
+                current_pos,
+                original_rect,
+                preserve_aspect_ratio,
+                constraints,
+            } => f
+                .debug_struct("ResizingSelection")
+                .field("handle", handle)
+                .field("original_rect", original_rect)
+                .field("current_pos", current_pos)
+                .field("preserve_aspect_ratio", preserve_aspect_ratio)
+                .field("constraints", constraints)
                 .finish(),
             Self::Dragging { 
                 start_pos, 
@@ -104,17 +229,102 @@ impl std::fmt::Debug for SelectionState {
     }
 }
 
+/// In-progress edit of the selected element's name in the tool panel
+#[derive(Debug, Clone)]
+struct NameEdit {
+    element_id: ElementId,
+    original_name: String,
+    buffer: String,
+}
+
+/// Polygon parameters being edited in the tool panel, committed when a slider is let go
+#[derive(Debug, Clone)]
+struct PolygonEdit {
+    element_id: ElementId,
+    original: PolygonParams,
+    draft: PolygonParams,
+}
+
+/// Style of the selected stroke or path being edited in the tool panel, committed when let go
+#[derive(Debug, Clone)]
+struct StrokeStyleEdit {
+    element_id: ElementId,
+    original: StrokeStyle,
+    draft: StrokeStyle,
+}
+
+/// Pattern scale and offset being edited in the tool panel, committed when a control is let go
+#[derive(Debug, Clone)]
+struct PatternEdit {
+    element_id: ElementId,
+    original: (f32, egui::Vec2),
+    scale: f32,
+    offset: egui::Vec2,
+}
+
+/// Rotation being edited in the tool panel, in degrees, committed when let go
+#[derive(Debug, Clone)]
+struct RotationEdit {
+    element_id: ElementId,
+    original: f32,
+    draft: f32,
+}
+
 #[derive(Debug, Clone)]
 pub struct UnifiedSelectionTool {
     pub state: SelectionState,
+    pub mode: SelectionMode,
     pub handle_size: f32,
+    name_edit: Option<NameEdit>,
+    polygon_edit: Option<PolygonEdit>,
+    stroke_style_edit: Option<StrokeStyleEdit>,
+    pattern_edit: Option<PatternEdit>,
+    rotation_edit: Option<RotationEdit>,
+    // Last flatten failure, shown until the next attempt
+    transform_error: Option<String>,
+    // Maximum endpoint gap bridged when joining strokes
+    join_tolerance: f32,
+    // Maximum deviation dropped when simplifying strokes
+    simplify_tolerance: f32,
+    // How strongly strokes are smoothed, from 0 to 1
+    smooth_strength: f32,
+    // Last stroke action failure, shown until the next attempt
+    stroke_action_error: Option<String>,
+    // Stroke to split at the next click on the canvas
+    split_stroke: Option<ElementId>,
+    // Last shape boolean failure, shown until the next attempt
+    shape_action_error: Option<String>,
+    trace_settings: TraceSettings,
+    // Image being traced in the background
+    trace_job: Option<TraceJob>,
+    // Last trace failure, shown until the next attempt
+    trace_error: Option<String>,
+    // Nudge distances and the grid and angle steps snapped to, from the preferences
+    snap: SnapIncrements,
 }
 
 impl UnifiedSelectionTool {
     pub fn new() -> Self {
         Self {
             state: SelectionState::Idle,
+            mode: SelectionMode::default(),
             handle_size: DEFAULT_HANDLE_SIZE,
+            name_edit: None,
+            polygon_edit: None,
+            stroke_style_edit: None,
+            pattern_edit: None,
+            rotation_edit: None,
+            transform_error: None,
+            join_tolerance: DEFAULT_JOIN_TOLERANCE,
+            simplify_tolerance: DEFAULT_SIMPLIFY_TOLERANCE,
+            smooth_strength: DEFAULT_SMOOTH_STRENGTH,
+            stroke_action_error: None,
+            split_stroke: None,
+            shape_action_error: None,
+            trace_settings: TraceSettings::default(),
+            trace_job: None,
+            trace_error: None,
+            snap: SnapIncrements::default(),
         }
     }
 
@@ -127,10 +337,640 @@ impl UnifiedSelectionTool {
         match self.state {
             SelectionState::Idle => "Idle",
             SelectionState::Selecting { .. } => "Selecting",
+            SelectionState::Lasso { .. } => "Lasso",
             SelectionState::Resizing { .. } => "Resizing",
+            SelectionState::ResizingSelection { .. } => "ResizingSelection",
             SelectionState::Dragging { .. } => "Dragging",
         }
     }
+
+    /// Actions on the selected strokes (only shown when a stroke is selected)
+    fn stroke_actions_ui(&mut self, ui: &mut Ui, editor_model: &EditorModel) -> Option<Command> {
+        let mut stroke_ids: Vec<ElementId> = editor_model
+            .selected_elements()
+            .into_iter()
+            .filter(|element| matches!(element, ElementType::Stroke(_)))
+            .map(|element| element.id())
+            .collect();
+        if stroke_ids.is_empty() {
+            self.stroke_action_error = None;
+            self.split_stroke = None;
+            return None;
+        }
+        stroke_ids.sort_unstable();
+        if stroke_ids.len() != 1 {
+            self.split_stroke = None;
+        }
+
+        ui.separator();
+        ui.label(format!("{} strokes selected", stroke_ids.len()));
+
+        let mut result = None;
+
+        let tolerance = self.simplify_tolerance;
+        let simplify = |points: &[Pos2]| crate::geometry::simplify_polyline(points, tolerance);
+        let row = ui.horizontal(|ui| {
+            let button = ui.button("Simplify");
+            let slider = ui.add(
+                egui::Slider::new(&mut self.simplify_tolerance, 0.1..=20.0)
+                    .logarithmic(true)
+                    .text("Tolerance"),
+            );
+            (button, slider)
+        });
+        let (button, slider) = row.inner;
+        if button.clicked() {
+            result = Some(Command::new_set_stroke_points(
+                editor_model,
+                &stroke_ids,
+                "Simplify Strokes",
+                simplify,
+            ));
+        } else if button.hovered() || slider.hovered() || slider.dragged() {
+            preview_stroke_action(ui, editor_model, &stroke_ids, simplify);
+        }
+
+        let strength = self.smooth_strength;
+        let smooth = |points: &[Pos2]| crate::geometry::smooth_polyline(points, strength);
+        let row = ui.horizontal(|ui| {
+            let button = ui.button("Smooth");
+            let slider =
+                ui.add(egui::Slider::new(&mut self.smooth_strength, 0.0..=1.0).text("Strength"));
+            (button, slider)
+        });
+        let (button, slider) = row.inner;
+        if button.clicked() {
+            result = Some(Command::new_set_stroke_points(
+                editor_model,
+                &stroke_ids,
+                "Smooth Strokes",
+                smooth,
+            ));
+        } else if button.hovered() || slider.hovered() || slider.dragged() {
+            preview_stroke_action(ui, editor_model, &stroke_ids, smooth);
+        }
+
+        if ui
+            .button("Outline")
+            .on_hover_text("Turn the strokes into filled shapes of their outlines")
+            .clicked()
+        {
+            result = Some(Command::new_outline_strokes(editor_model, &stroke_ids));
+        }
+
+        if let [stroke_id] = stroke_ids[..] {
+            let mut splitting = self.split_stroke == Some(stroke_id);
+            if ui
+                .toggle_value(&mut splitting, "Split")
+                .on_hover_text("Then click the stroke where it should be split")
+                .changed()
+            {
+                self.split_stroke = splitting.then_some(stroke_id);
+            }
+        }
+
+        if stroke_ids.len() >= 2 {
+            ui.horizontal(|ui| {
+                if ui.button("Join strokes").clicked() {
+                    result = Some(Command::new_join_strokes(
+                        editor_model,
+                        &stroke_ids,
+                        self.join_tolerance,
+                    ));
+                }
+                ui.add(egui::Slider::new(&mut self.join_tolerance, 0.0..=50.0).text("Tolerance"));
+            });
+        }
+
+        let command = match result {
+            Some(Ok(action)) => {
+                self.stroke_action_error = None;
+                Some(action)
+            }
+            Some(Err(err)) => {
+                self.stroke_action_error = Some(err);
+                None
+            }
+            None => None,
+        };
+
+        if let Some(err) = &self.stroke_action_error {
+            ui.colored_label(egui::Color32::RED, err);
+        }
+
+        command
+    }
+
+    /// Boolean operations on two selected shapes (only shown when exactly two are selected)
+    fn shape_actions_ui(&mut self, ui: &mut Ui, editor_model: &EditorModel) -> Option<Command> {
+        let shape_ids: Vec<ElementId> = editor_model
+            .selected_elements()
+            .into_iter()
+            .filter(|element| matches!(element, ElementType::Shape(_)))
+            .map(|element| element.id())
+            .collect();
+        if shape_ids.len() != 2 || editor_model.selected_ids().len() != 2 {
+            self.shape_action_error = None;
+            return None;
+        }
+
+        ui.separator();
+        ui.label("Combine shapes:");
+        let mut result = None;
+        ui.horizontal(|ui| {
+            for (label, op) in [
+                ("Union", BooleanOp::Union),
+                ("Subtract", BooleanOp::Subtract),
+                ("Intersect", BooleanOp::Intersect),
+            ] {
+                let button = ui.button(label);
+                let button = if op == BooleanOp::Subtract {
+                    button.on_hover_text("Cut the front shape out of the one behind it")
+                } else {
+                    button
+                };
+                if button.clicked() {
+                    result = Some(Command::new_shape_boolean(editor_model, &shape_ids, op));
+                }
+            }
+        });
+
+        let command = match result {
+            Some(Ok(action)) => {
+                self.shape_action_error = None;
+                Some(action)
+            }
+            Some(Err(err)) => {
+                self.shape_action_error = Some(err);
+                None
+            }
+            None => None,
+        };
+        if let Some(err) = &self.shape_action_error {
+            ui.colored_label(egui::Color32::RED, err);
+        }
+
+        command
+    }
+
+    /// Tracing the selected image into strokes, with the progress of a running trace
+    ///
+    /// Returns the command replacing the image once its trace finishes.
+    fn trace_ui(&mut self, ui: &mut Ui, editor_model: &EditorModel) -> Option<Command> {
+        if let Some(job) = &self.trace_job {
+            if let Some(result) = job.poll() {
+                let command = result.and_then(|contours| {
+                    trace_command(editor_model, job.image_id, &contours, &job.settings)
+                });
+                self.trace_job = None;
+                return match command {
+                    Ok(command) => {
+                        self.trace_error = None;
+                        Some(command)
+                    }
+                    Err(err) => {
+                        self.trace_error = Some(err);
+                        None
+                    }
+                };
+            }
+
+            ui.separator();
+            let cancel = ui.horizontal(|ui| {
+                ui.add(egui::ProgressBar::new(job.progress()).text("Tracing…"));
+                ui.button("Cancel").clicked()
+            });
+            if cancel.inner {
+                job.cancel();
+                self.trace_job = None;
+            }
+            return None;
+        }
+
+        let image = match editor_model.selected_elements().as_slice() {
+            [ElementType::Image(image)] => image,
+            _ => {
+                self.trace_error = None;
+                return None;
+            }
+        };
+
+        ui.separator();
+        ui.label("Trace to strokes:");
+        let settings = &mut self.trace_settings;
+        ui.add(egui::Slider::new(&mut settings.threshold, 1..=255).text("Threshold"))
+            .on_hover_text("Pixels darker than this are traced");
+        ui.add(egui::Slider::new(&mut settings.tolerance, 0.1..=5.0).text("Simplify"));
+        ui.horizontal(|ui| {
+            ui.add(egui::Slider::new(&mut settings.thickness, 0.5..=10.0).text("Thickness"));
+            ui.color_edit_button_srgba(&mut settings.color);
+        });
+        if ui.button("Trace").clicked() {
+            self.trace_error = None;
+            self.trace_job = Some(TraceJob::start(
+                image.id(),
+                image.original_data().to_vec(),
+                self.trace_settings,
+                ui.ctx(),
+            ));
+        }
+        if let Some(err) = &self.trace_error {
+            ui.colored_label(egui::Color32::RED, err);
+        }
+        None
+    }
+
+    /// Show an editable name field for the element, returning a RenameElement
+    /// command once the user commits a changed name
+    fn name_ui(
+        &mut self,
+        ui: &mut Ui,
+        editor_model: &EditorModel,
+        element: &ElementType,
+    ) -> Option<Command> {
+        let element_id = element.id();
+
+        // Restart the edit when the selection changes or the name changed underneath us (e.g. undo)
+        let is_stale = self.name_edit.as_ref().is_none_or(|edit| {
+            edit.element_id != element_id || edit.original_name != element.name()
+        });
+        if is_stale {
+            self.name_edit = Some(NameEdit {
+                element_id,
+                original_name: element.name().to_string(),
+                buffer: element.name().to_string(),
+            });
+        }
+
+        let edit = self.name_edit.as_mut()?;
+        let mut command = None;
+        ui.horizontal(|ui| {
+            ui.label("Name:");
+            let response = ui.add(
+                egui::TextEdit::singleline(&mut edit.buffer).hint_text(element.display_name()),
+            );
+            if response.lost_focus() {
+                command = Command::new_rename_element(editor_model, element_id, edit.buffer.trim());
+            }
+        });
+
+        command
+    }
+
+    /// Sliders editing the parameters of the selected polygon shape
+    fn polygon_ui(
+        &mut self,
+        ui: &mut Ui,
+        editor_model: &EditorModel,
+        element_id: ElementId,
+        params: PolygonParams,
+    ) -> Option<Command> {
+        // Start over when another shape is selected or the polygon changed underneath (undo)
+        let is_stale = self
+            .polygon_edit
+            .as_ref()
+            .is_none_or(|edit| edit.element_id != element_id || edit.original != params);
+        if is_stale {
+            self.polygon_edit = Some(PolygonEdit {
+                element_id,
+                original: params,
+                draft: params,
+            });
+        }
+
+        let draft = &mut self.polygon_edit.as_mut()?.draft;
+        let response = polygon_settings_ui(
+            ui,
+            &mut draft.sides,
+            &mut draft.star_ratio,
+            &mut draft.corner_radius,
+        );
+
+        // Slider drags become one undo step when released
+        let committed = response.drag_stopped() || (response.changed() && !response.dragged());
+        if committed {
+            Command::new_set_polygon(editor_model, element_id, *draft)
+        } else {
+            None
+        }
+    }
+
+    /// Rotation of the selected element, and flattening the transforms of the selection
+    fn transform_ui(
+        &mut self,
+        ui: &mut Ui,
+        editor_model: &EditorModel,
+        element: &ElementType,
+    ) -> Option<Command> {
+        ui.separator();
+        ui.label("Transform:");
+
+        // Start over when another element is selected or it turned underneath (undo)
+        let element_id = element.id();
+        let rotation = element.rotation().to_degrees();
+        let is_stale = self
+            .rotation_edit
+            .as_ref()
+            .is_none_or(|edit| edit.element_id != element_id || edit.original != rotation);
+        if is_stale {
+            self.rotation_edit = Some(RotationEdit {
+                element_id,
+                original: rotation,
+                draft: rotation,
+            });
+        }
+
+        let step = self.snap.angle;
+        let draft = &mut self.rotation_edit.as_mut()?.draft;
+        let (response, turn) = ui
+            .horizontal(|ui| {
+                ui.label("Rotation:");
+                let response = ui.add(
+                    egui::DragValue::new(draft)
+                        .range(-180.0..=180.0)
+                        .speed(0.5)
+                        .suffix("°"),
+                );
+                // Turn to the previous or next multiple of the angle step
+                let mut turn: Option<f32> = None;
+                let hover = format!("Turn to the next multiple of {step}°");
+                if ui.button("⟲").on_hover_text(&hover).clicked() {
+                    turn = Some(-1.0);
+                }
+                if ui.button("⟳").on_hover_text(&hover).clicked() {
+                    turn = Some(1.0);
+                }
+                (response, turn)
+            })
+            .inner;
+        let mut command = None;
+        if let Some(direction) = turn.filter(|_| step > 0.0) {
+            // Allow for rounding so a rotation on a multiple moves off it
+            let position = *draft / step;
+            let steps = if direction > 0.0 {
+                (position + 0.001).floor() + 1.0
+            } else {
+                (position - 0.001).ceil() - 1.0
+            };
+            *draft = (steps * step + 180.0).rem_euclid(360.0) - 180.0;
+        }
+        // Drags become one undo step when released
+        if response.drag_stopped() || (response.changed() && !response.dragged()) || turn.is_some()
+        {
+            command = Command::new_set_rotation(editor_model, element_id, draft.to_radians());
+        }
+
+        let selected: Vec<ElementId> = editor_model.selected_ids().iter().copied().collect();
+        let can_flatten = editor_model
+            .selected_elements()
+            .iter()
+            .any(|element| element.can_flatten());
+        let button = ui
+            .add_enabled(can_flatten, egui::Button::new("Flatten transform"))
+            .on_hover_text("Bake moves, resizes and rotation into the points and pixels");
+        if button.clicked() {
+            match Command::new_flatten_transforms(editor_model, &selected) {
+                Ok(flatten) => {
+                    self.transform_error = None;
+                    command = Some(flatten);
+                }
+                Err(err) => self.transform_error = Some(err),
+            }
+        }
+        if let Some(err) = &self.transform_error {
+            ui.colored_label(egui::Color32::RED, err);
+        }
+
+        command
+    }
+
+    /// Taper, cap, join and dash controls for the selected stroke or path
+    fn stroke_style_ui(
+        &mut self,
+        ui: &mut Ui,
+        editor_model: &EditorModel,
+        element_id: ElementId,
+        style: StrokeStyle,
+    ) -> Option<Command> {
+        // Start over when another line is selected or the style changed underneath (undo)
+        let is_stale = self
+            .stroke_style_edit
+            .as_ref()
+            .is_none_or(|edit| edit.element_id != element_id || edit.original != style);
+        if is_stale {
+            self.stroke_style_edit = Some(StrokeStyleEdit {
+                element_id,
+                original: style,
+                draft: style,
+            });
+        }
+
+        let draft = &mut self.stroke_style_edit.as_mut()?.draft;
+        let response = stroke_style_ui(ui, draft);
+
+        // Slider drags become one undo step when released
+        let committed = response.drag_stopped() || (response.changed() && !response.dragged());
+        if committed {
+            Command::new_set_stroke_style(editor_model, element_id, *draft)
+        } else {
+            None
+        }
+    }
+
+    /// Choice between the solid fill and an image pattern, with the pattern's scale and offset
+    fn pattern_ui(
+        &mut self,
+        ui: &mut Ui,
+        editor_model: &EditorModel,
+        shape: &Shape,
+    ) -> Option<Command> {
+        let element_id = shape.id();
+        let pattern = shape.pattern();
+        let mut command = None;
+
+        let selected_text = match pattern {
+            Some(pattern) => editor_model
+                .find_element_by_id(pattern.source_id)
+                .map_or("Image pattern".to_string(), |image| image.display_name()),
+            None => "Solid color".to_string(),
+        };
+        egui::ComboBox::from_label("Fill")
+            .selected_text(selected_text)
+            .show_ui(ui, |ui| {
+                if ui
+                    .selectable_label(pattern.is_none(), "Solid color")
+                    .clicked()
+                {
+                    command = Command::new_set_pattern_fill(editor_model, element_id, None);
+                }
+                for element in &editor_model.elements {
+                    let ElementType::Image(image) = element else {
+                        continue;
+                    };
+                    let selected = pattern.is_some_and(|pattern| pattern.source_id == image.id());
+                    if ui
+                        .selectable_label(selected, element.display_name())
+                        .clicked()
+                        && !selected
+                    {
+                        // Keep the scale and offset when switching images
+                        let mut new_pattern = PatternFill::new(
+                            image.id(),
+                            image.original_data().to_vec(),
+                            image.size(),
+                        );
+                        if let Some(pattern) = pattern {
+                            new_pattern.scale = pattern.scale;
+                            new_pattern.offset = pattern.offset;
+                        }
+                        command = Command::new_set_pattern_fill(
+                            editor_model,
+                            element_id,
+                            Some(new_pattern),
+                        );
+                    }
+                }
+            });
+
+        let Some(pattern) = pattern else {
+            self.pattern_edit = None;
+            return command;
+        };
+
+        // Start over when another shape is selected or the pattern changed underneath (undo)
+        let current = (pattern.scale, pattern.offset);
+        let is_stale = self
+            .pattern_edit
+            .as_ref()
+            .is_none_or(|edit| edit.element_id != element_id || edit.original != current);
+        if is_stale {
+            self.pattern_edit = Some(PatternEdit {
+                element_id,
+                original: current,
+                scale: pattern.scale,
+                offset: pattern.offset,
+            });
+        }
+        let edit = self.pattern_edit.as_mut()?;
+
+        let scale_response = ui.add(
+            egui::Slider::new(&mut edit.scale, PatternFill::MIN_SCALE..=4.0)
+                .logarithmic(true)
+                .text("Pattern scale"),
+        );
+        let offset_response = ui
+            .horizontal(|ui| {
+                ui.label("Offset:");
+                ui.add(egui::DragValue::new(&mut edit.offset.x).prefix("x: "))
+                    | ui.add(egui::DragValue::new(&mut edit.offset.y).prefix("y: "))
+            })
+            .inner;
+        let response = scale_response | offset_response;
+
+        // Drags become one undo step when released
+        let committed = response.drag_stopped() || (response.changed() && !response.dragged());
+        if committed {
+            let new_pattern = PatternFill {
+                scale: edit.scale,
+                offset: edit.offset,
+                ..pattern.clone()
+            };
+            command = Command::new_set_pattern_fill(editor_model, element_id, Some(new_pattern));
+        }
+        command
+    }
+}
+
+/// Command selecting `ids`, either added to the current selection or replacing it
+///
+/// Returns None if that wouldn't change anything.
+fn select_elements_command(
+    editor_model: &EditorModel,
+    ids: Vec<ElementId>,
+    adding_to_selection: bool,
+) -> Option<Command> {
+    let mut commands = Vec::new();
+    if !adding_to_selection && !editor_model.selected_ids().is_empty() {
+        commands.push(Command::new_clear_selection(editor_model));
+    }
+    commands.extend(
+        ids.into_iter()
+            .filter(|id| !adding_to_selection || !editor_model.is_element_selected(*id))
+            .map(Command::SelectElement),
+    );
+    (!commands.is_empty()).then_some(Command::Batch {
+        description: "Select Elements",
+        commands,
+    })
+}
+
+/// IDs of the unlocked elements lying entirely inside the closed `lasso`
+///
+/// Strokes and shapes are tested by their points, so a lasso can pick out a
+/// diagonal stroke without taking in its whole bounding box; other elements by
+/// the corners of their rect.
+fn lasso_selection(editor_model: &EditorModel, lasso: &[Pos2]) -> Vec<ElementId> {
+    let inside = |point: &Pos2| crate::geometry::polygon_contains(lasso, *point);
+    editor_model
+        .elements
+        .iter()
+        .filter(|element| !element.metadata().is_locked())
+        .filter(|element| match element {
+            // Points are stored unrotated; turn them to where they're drawn
+            ElementType::Stroke(stroke) => {
+                let obb = element.obb();
+                stroke
+                    .points()
+                    .iter()
+                    .all(|&point| inside(&obb.to_world(point)))
+            }
+            ElementType::Shape(shape) => {
+                let obb = element.obb();
+                shape
+                    .points()
+                    .iter()
+                    .all(|&point| inside(&obb.to_world(point)))
+            }
+            _ => {
+                let rect = compute_element_rect(element);
+                [
+                    rect.left_top(),
+                    rect.right_top(),
+                    rect.right_bottom(),
+                    rect.left_bottom(),
+                ]
+                .iter()
+                .all(inside)
+            }
+        })
+        .map(|element| element.id())
+        .collect()
+}
+
+/// Draw what `modify` would turn the strokes into over the canvas, along with
+/// the resulting point count, without changing the document
+fn preview_stroke_action(
+    ui: &mut Ui,
+    editor_model: &EditorModel,
+    stroke_ids: &[ElementId],
+    modify: impl Fn(&[Pos2]) -> Vec<Pos2>,
+) {
+    let painter = crate::view::canvas_overlay_painter(ui.ctx(), "stroke_action_preview");
+
+    let (mut before, mut after) = (0, 0);
+    for &element_id in stroke_ids {
+        let Some(ElementType::Stroke(stroke)) = editor_model.find_element_by_id(element_id) else {
+            continue;
+        };
+        let points = modify(stroke.points());
+        before += stroke.points().len();
+        after += points.len();
+        painter.add(egui::Shape::line(
+            points,
+            egui::Stroke::new(stroke.thickness(), STROKE_ACTION_PREVIEW_COLOR),
+        ));
+    }
+    ui.label(format!("Points: {} → {}", before, after));
 }
 
 impl Tool for UnifiedSelectionTool {
@@ -142,13 +982,61 @@ impl Tool for UnifiedSelectionTool {
         Some(&self.state)
     }
 
+    fn cursor_icon(
+        &self,
+        pos: Pos2,
+        editor_model: &EditorModel,
+        renderer: &Renderer,
+    ) -> Option<egui::CursorIcon> {
+        match &self.state {
+            SelectionState::Resizing { handle, .. }
+            | SelectionState::ResizingSelection { handle, .. } => {
+                return Some(handle.cursor_icon());
+            }
+            SelectionState::Dragging { .. } => return Some(egui::CursorIcon::Move),
+            SelectionState::Selecting { .. } | SelectionState::Lasso { .. } => return None,
+            SelectionState::Idle => {}
+        }
+        if self.split_stroke.is_some() {
+            return Some(egui::CursorIcon::Crosshair);
+        }
+
+        // Handles sit on top of everything, matching the order of on_pointer_down
+        let handle = match multiple_selection_rect(editor_model) {
+            Some(rect) => renderer.transform_handles(rect).handle_at(pos),
+            None => editor_model
+                .selected_ids()
+                .iter()
+                .filter_map(|&element_id| editor_model.find_element_by_id(element_id))
+                .find_map(|element| {
+                    let rect = compute_element_rect(element);
+                    renderer.transform_handles(rect).handle_at(pos)
+                }),
+        };
+        if let Some(handle) = handle {
+            return Some(handle.cursor_icon());
+        }
+
+        let element = editor_model.element_at_position(pos)?;
+        if element.element_type() == "text" {
+            Some(egui::CursorIcon::Text)
+        } else if editor_model.is_element_selected(element.id()) {
+            Some(egui::CursorIcon::Move)
+        } else {
+            None
+        }
+    }
+
     fn activate(&mut self, _editor_model: &EditorModel) {
-        info!("SelectionTool activated");
+        trace!(tool = self.name(); "Tool activated");
     }
 
     fn deactivate(&mut self, _editor_model: &EditorModel) {
-        info!("SelectionTool deactivated");
+        trace!(tool = self.name(); "Tool deactivated");
         self.reset_interaction_state();
+        if let Some(job) = self.trace_job.take() {
+            job.cancel();
+        }
     }
 
     fn requires_selection(&self) -> bool {
@@ -167,36 +1055,59 @@ impl Tool for UnifiedSelectionTool {
         if button != egui::PointerButton::Primary {
             return None;
         }
+
+        // A click while splitting goes to the split, whether or not it hits the stroke
+        if let Some(element_id) = self.split_stroke.take() {
+            return match Command::new_split_stroke(editor_model, element_id, pos) {
+                Ok(command) => {
+                    self.stroke_action_error = None;
+                    Some(command)
+                }
+                Err(err) => {
+                    self.stroke_action_error = Some(err);
+                    None
+                }
+            };
+        }
         
-        // First, check if we're clicking on a resize handle of a selected element
+        // First, check if we're clicking on a resize handle of the box around a
+        // multiple selection, or of a selected element
+        if let Some(rect) = multiple_selection_rect(editor_model) {
+            if let Some(handle) = renderer.transform_handles(rect).handle_at(pos) {
+                renderer.set_selection_resize_preview(Some((rect, rect)));
+                renderer.set_selection_handle(Some(handle));
+
+                let constraints = selection_resize_constraints(editor_model);
+                self.state = SelectionState::ResizingSelection {
+                    handle,
+                    current_pos: pos,
+                    original_rect: rect,
+                    preserve_aspect_ratio: constraints.keeps_aspect_ratio(modifiers.shift),
+                    constraints,
+                };
+                return None;
+            }
+        }
         for &element_id in editor_model.selected_ids() {
             if let Some(element) = editor_model.find_element_by_id(element_id) {
                 let rect = compute_element_rect(element);
                 
-                // Check all corners for potential resize handles
-                let handle_radius = RESIZE_HANDLE_RADIUS;
-                let corners = [
-                    (rect.left_top(), Corner::TopLeft),
-                    (rect.right_top(), Corner::TopRight),
-                    (rect.left_bottom(), Corner::BottomLeft),
-                    (rect.right_bottom(), Corner::BottomRight),
-                ];
-                
-                for (corner_pos, corner) in corners {
-                    if is_near_handle_position(pos, corner_pos, handle_radius) {
-                        // Start resizing this element from this corner
-                        renderer.set_resize_preview(Some(rect));
-                        
-                        self.state = SelectionState::Resizing {
-                            element_id,
-                            corner,
-                            start_pos: pos,
-                            current_pos: pos,
-                            original_rect: rect,
-                            preserve_aspect_ratio: modifiers.shift,
-                        };
-                        return None;
-                    }
+                // Check for a resize handle under the pointer
+                if let Some(handle) = renderer.transform_handles(rect).handle_at(pos) {
+                    // Start resizing this element from this handle
+                    renderer.set_resize_preview(Some(rect));
+
+                    let constraints = element.resize_constraints();
+                    self.state = SelectionState::Resizing {
+                        element_id,
+                        handle,
+                        start_pos: pos,
+                        current_pos: pos,
+                        original_rect: rect,
+                        preserve_aspect_ratio: constraints.keeps_aspect_ratio(modifiers.shift),
+                        constraints,
+                    };
+                    return None;
                 }
             }
         }
@@ -275,6 +1186,12 @@ impl Tool for UnifiedSelectionTool {
                     grid_snap_enabled: modifiers.ctrl,
                 };
             }
+        } else if self.mode == SelectionMode::Lasso {
+            // Start a lasso; a click without dragging clears the selection when released
+            self.state = SelectionState::Lasso {
+                points: vec![pos],
+                adding_to_selection: modifiers.shift,
+            };
         } else {
             // Clicked in empty space
             if !modifiers.shift && !editor_model.selected_ids().is_empty() {
@@ -306,6 +1223,7 @@ impl Tool for UnifiedSelectionTool {
     ) -> Option<Command> {
         // Check if primary button is held for drag operations
         let primary_held = held_buttons.contains(&egui::PointerButton::Primary);
+        let guide_snap = renderer.guide_snap_distance();
         
         // Update current position in state based on the interaction mode
         match &mut self.state {
@@ -315,47 +1233,73 @@ impl Tool for UnifiedSelectionTool {
                     *adding_to_selection = modifiers.shift; // Update for shift toggle
                 }
             }
+            SelectionState::Lasso { points, adding_to_selection } => {
+                // Skip points too close together to change the path's shape
+                let is_new = points
+                    .last()
+                    .is_none_or(|last| last.distance(pos) >= MIN_LASSO_POINT_DISTANCE);
+                if primary_held && is_new {
+                    points.push(pos);
+                    *adding_to_selection = modifiers.shift;
+                }
+            }
             SelectionState::Dragging { 
+                start_pos,
                 current_pos, 
+                original_rect,
                 grid_snap_enabled, 
                 .. 
             } => {
                 if primary_held {
-                    *current_pos = pos;
                     *grid_snap_enabled = modifiers.ctrl; // Update for grid snap toggle
+                    // Line the edges or middle of what's moved up with a ruler
+                    // guide, unless it's snapping to the grid
+                    let moved = original_rect.translate(pos - *start_pos);
+                    let snap = guide_snap
+                        .filter(|_| !*grid_snap_enabled)
+                        .map_or(Vec2::ZERO, |tolerance| {
+                            snap_rect(&editor_model.ruler_guides, moved, tolerance)
+                        });
+                    *current_pos = pos + snap;
                 }
             }
             SelectionState::Resizing { 
                 current_pos,
                 preserve_aspect_ratio,
+                constraints,
                 .. 
+            }
+            | SelectionState::ResizingSelection {
+                current_pos,
+                preserve_aspect_ratio,
+                constraints,
+                ..
             } => {
                 if primary_held {
-                    *current_pos = pos;
-                    *preserve_aspect_ratio = modifiers.shift; // Update for aspect ratio toggle
+                    *current_pos = guide_snap.map_or(pos, |tolerance| {
+                        snap_position(&editor_model.ruler_guides, pos, tolerance)
+                    });
+                    // Update for aspect ratio toggle
+                    *preserve_aspect_ratio = constraints.keeps_aspect_ratio(modifiers.shift);
                 }
             }
             SelectionState::Idle => {
                 // In idle state, highlight resize handles when hovering
+                if let Some(rect) = multiple_selection_rect(editor_model) {
+                    renderer.set_selection_handle(renderer.transform_handles(rect).handle_at(pos));
+                    renderer.clear_active_handles();
+                    self.update_preview(renderer);
+                    return None;
+                }
+                renderer.set_selection_handle(None);
                 let mut found_handle = false;
                 
                 for &element_id in editor_model.selected_ids() {
                     if let Some(element) = editor_model.find_element_by_id(element_id) {
                         let rect = compute_element_rect(element);
-                        let handle_radius = RESIZE_HANDLE_RADIUS;
-                        let corners = [
-                            (rect.left_top(), Corner::TopLeft),
-                            (rect.right_top(), Corner::TopRight),
-                            (rect.left_bottom(), Corner::BottomLeft),
-                            (rect.right_bottom(), Corner::BottomRight),
-                        ];
-                        
-                        for (corner_pos, corner) in corners {
-                            if is_near_handle_position(pos, corner_pos, handle_radius) {
-                                renderer.set_active_handle(element_id, Some(corner));
-                                found_handle = true;
-                                break;
-                            }
+                        if let Some(handle) = renderer.transform_handles(rect).handle_at(pos) {
+                            renderer.set_active_handle(element_id, Some(handle));
+                            found_handle = true;
                         }
                         
                         if found_handle {
@@ -388,6 +1332,7 @@ impl Tool for UnifiedSelectionTool {
             return None;
         }
 
+        let grid = self.snap.grid;
         let result = match &self.state {
             SelectionState::Selecting { 
                 start_pos, 
@@ -398,45 +1343,26 @@ impl Tool for UnifiedSelectionTool {
                 
                 // Only act if the selection has some size
                 if selection_rect.width() > 2.0 || selection_rect.height() > 2.0 {
-                    let mut ids = Vec::new();
-                    
                     // Find elements that intersect with the selection rectangle
-                    for &id in editor_model.selected_ids() {
-                        if let Some(element) = editor_model.find_element_by_id(id) {
-                            let element_rect = compute_element_rect(element);
-                            if selection_rect.intersects(element_rect) {
-                                ids.push(id);
-                            }
-                        }
-                    }
-                    
-                    if !ids.is_empty() {
-                        if *adding_to_selection {
-                            // Add to current selection - toggle each element
-                            let current_selection = editor_model.selected_ids();
-                            
-                            // Create a command to update selection
-                            let mut previous_selection = current_selection.clone();
-                            for id in ids {
-                                if current_selection.contains(&id) {
-                                    previous_selection.remove(&id);
-                                } else {
-                                    previous_selection.insert(id);
-                                }
-                            }
-                            
-                            Some(Command::ClearSelection {
-                                previous_selection
-                            })
-                        } else {
-                            // Replace current selection with new selection
-                            Some(Command::ClearSelection {
-                                previous_selection: ids.into_iter().collect()
-                            })
-                        }
-                    } else {
-                        None
-                    }
+                    let ids = editor_model
+                        .elements
+                        .iter()
+                        .filter(|element| !element.metadata().is_locked())
+                        .filter(|element| selection_rect.intersects(compute_element_rect(element)))
+                        .map(|element| element.id())
+                        .collect();
+                    select_elements_command(editor_model, ids, *adding_to_selection)
+                } else {
+                    None
+                }
+            }
+            SelectionState::Lasso { points, adding_to_selection } => {
+                let bounds = egui::Rect::from_points(points);
+                if points.len() >= 3 && (bounds.width() > 2.0 || bounds.height() > 2.0) {
+                    let ids = lasso_selection(editor_model, points);
+                    select_elements_command(editor_model, ids, *adding_to_selection)
+                } else if !*adding_to_selection && !editor_model.selected_ids().is_empty() {
+                    Some(Command::new_clear_selection(editor_model))
                 } else {
                     None
                 }
@@ -457,10 +1383,9 @@ impl Tool for UnifiedSelectionTool {
                         let mut new_pos = initial_pos + delta;
                         
                         // Apply grid snapping if enabled
-                        if *grid_snap_enabled {
-                            const GRID_SIZE: f32 = 10.0;
-                            new_pos.x = (new_pos.x / GRID_SIZE).round() * GRID_SIZE;
-                            new_pos.y = (new_pos.y / GRID_SIZE).round() * GRID_SIZE;
+                        if *grid_snap_enabled && grid > 0.0 {
+                            new_pos.x = (new_pos.x / grid).round() * grid;
+                            new_pos.y = (new_pos.y / grid).round() * grid;
                         }
                         
                         new_positions.insert(id, new_pos);
@@ -470,16 +1395,18 @@ impl Tool for UnifiedSelectionTool {
                     let mut commands = Vec::new();
                     for (id, new_pos) in new_positions {
                         if let Some(element) = editor_model.find_element_by_id(id) {
-                            let old_pos = compute_element_rect(element).min;
-                            let delta = new_pos - old_pos;
+                            // The drag moves the padded selection box, the command
+                            // the element's own bounds
+                            let delta = new_pos - compute_element_rect(element).min;
+                            let old_pos = element.rect().min;
                             
                             // Only add a move command if we actually moved this element
                             if delta.x.abs() > 0.1 || delta.y.abs() > 0.1 {
                                 commands.push(Command::MoveElement {
                                     element_id: id,
                                     _element_type: element.element_type().to_string(),
-                                    _old_position: old_pos,
-                                    new_position: new_pos,
+                                    old_position: old_pos,
+                                    new_position: old_pos + delta,
                                 });
                             }
                         }
@@ -493,35 +1420,72 @@ impl Tool for UnifiedSelectionTool {
             }
             SelectionState::Resizing { 
                 element_id, 
-                corner, 
+                handle, 
                 original_rect, 
                 current_pos,
                 preserve_aspect_ratio,
+                constraints,
                 .. 
             } => {
                 // Calculate the new rectangle
                 let new_rect = compute_resized_rect_with_constraints(
                     *original_rect, 
-                    *corner, 
+                    *handle, 
                     *current_pos,
-                    *preserve_aspect_ratio
+                    *preserve_aspect_ratio,
+                    constraints.min_size,
                 );
                 
                 // Only create a command if the size actually changed
                 if (new_rect.width() - original_rect.width()).abs() > 1.0 ||
                    (new_rect.height() - original_rect.height()).abs() > 1.0 {
+                    // Undo restores the element's own bounds, which the
+                    // padded selection box is not
+                    let old_rect = editor_model
+                        .find_element_by_id(*element_id)
+                        .map_or(*original_rect, |element| element.rect());
                     Some(Command::ResizeElement {
                         element_id: *element_id,
                         _element_type: "unknown".to_string(),
-                        _old_rect: *original_rect,
-                        new_rect: new_rect,
-                        _scaling_corner: *corner,
+                        old_rect,
+                        new_rect,
+                        _scaling_handle: *handle,
                         _original_image: egui::Image::new((egui::TextureId::default(), egui::Vec2::new(10.0, 10.0))),
                     })
                 } else {
                     None
                 }
             }
+            SelectionState::ResizingSelection {
+                handle,
+                current_pos,
+                original_rect,
+                preserve_aspect_ratio,
+                constraints,
+            } => {
+                let new_rect = compute_resized_rect_with_constraints(
+                    *original_rect,
+                    *handle,
+                    *current_pos,
+                    *preserve_aspect_ratio,
+                    constraints.min_size,
+                );
+                let selected: Vec<ElementId> =
+                    editor_model.selected_ids().iter().copied().collect();
+                if (new_rect.width() - original_rect.width()).abs() > 1.0
+                    || (new_rect.height() - original_rect.height()).abs() > 1.0
+                {
+                    Command::new_resize_selection(
+                        editor_model,
+                        &selected,
+                        *handle,
+                        *original_rect,
+                        new_rect,
+                    )
+                } else {
+                    None
+                }
+            }
             SelectionState::Idle => None,
         };
         
@@ -533,46 +1497,146 @@ impl Tool for UnifiedSelectionTool {
 
     fn ui(&mut self, ui: &mut Ui, editor_model: &EditorModel) -> Option<Command> {
         ui.label("Selection Tool");
+        ui.horizontal(|ui| {
+            ui.label("Drag to select:");
+            for mode in [SelectionMode::Box, SelectionMode::Lasso] {
+                ui.selectable_value(&mut self.mode, mode, mode.label());
+            }
+        });
+
+        let mut command = None;
 
         // Show information about the current selection
         if let Some(element) = editor_model.selected_element() {
             ui.label("Selected Element:");
+            command = self.name_ui(ui, editor_model, element);
 
+            let units = editor_model.units;
             match &element {
                 ElementType::Image(img) => {
-                    ui.label(format!("Type: Image"));
+                    ui.label("Type: Image");
                     ui.label(format!("ID: {}", img.id()));
-                    ui.label(format!("Size: {}x{}", img.size().x, img.size().y));
-                    ui.label(format!(
-                        "Position: {:.1},{:.1}",
-                        img.position().x,
-                        img.position().y
-                    ));
+                    ui.label(format!("Size: {}", units.format_size(img.size())));
+                    ui.label(format!("Position: {}", units.format_pos(img.position())));
                 }
                 ElementType::Stroke(stroke) => {
-                    ui.label(format!("Type: Stroke"));
+                    ui.label("Type: Stroke");
                     ui.label(format!("ID: {}", stroke.id()));
                     ui.label(format!("Points: {}", stroke.points().len()));
                     ui.label(format!("Color: {:?}", stroke.color()));
-                    ui.label(format!("Thickness: {:.1}", stroke.thickness()));
+                    ui.label(format!("Size: {}", units.format_size(stroke.rect().size())));
+                    ui.label(format!("Thickness: {}", units.format(stroke.thickness())));
+                    ui.separator();
+                    if let Some(style_command) =
+                        self.stroke_style_ui(ui, editor_model, stroke.id(), stroke.style())
+                    {
+                        command = Some(style_command);
+                    }
+                }
+                ElementType::Path(path) => {
+                    ui.label("Type: Path");
+                    ui.label(format!("ID: {}", path.id()));
+                    ui.label(format!("Nodes: {}", path.nodes().len()));
+                    ui.label(format!("Color: {:?}", path.color()));
+                    ui.label(format!("Size: {}", units.format_size(path.rect().size())));
+                    ui.label(format!("Thickness: {}", units.format(path.thickness())));
+                    ui.separator();
+                    if let Some(style_command) =
+                        self.stroke_style_ui(ui, editor_model, path.id(), path.style())
+                    {
+                        command = Some(style_command);
+                    }
+                }
+                ElementType::StepBadge(badge) => {
+                    ui.label("Type: Step Badge");
+                    ui.label(format!("ID: {}", badge.id()));
+                    ui.label(format!("Step: {}", badge.number()));
+                    ui.label(format!("Size: {}", units.format_size(badge.rect().size())));
+                    ui.label(format!("Color: {:?}", badge.color()));
+                }
+                ElementType::StickyNote(note) => {
+                    ui.label("Type: Sticky Note");
+                    ui.label(format!("ID: {}", note.id()));
+                    ui.label(format!("Size: {}", units.format_size(note.rect().size())));
+                    if let Some(color) = note_color_ui(ui, note.color()) {
+                        command = Command::new_set_sticky_note(
+                            editor_model,
+                            note.id(),
+                            note.text().to_string(),
+                            color,
+                        );
+                    }
+                    ui.weak("Double-click the note to edit its text");
+                }
+                ElementType::Shape(shape) => {
+                    ui.label("Type: Shape");
+                    ui.label(format!("ID: {}", shape.id()));
+                    ui.label(format!("Points: {}", shape.points().len()));
+                    ui.label(format!("Size: {}", units.format_size(shape.rect().size())));
+                    ui.label(format!("Fill: {:?}", shape.fill()));
+                    if let Some(pattern_command) = self.pattern_ui(ui, editor_model, shape) {
+                        command = Some(pattern_command);
+                    }
+                    if let Some(params) = shape.polygon() {
+                        ui.separator();
+                        ui.label("Polygon:");
+                        if let Some(polygon_command) =
+                            self.polygon_ui(ui, editor_model, shape.id(), params)
+                        {
+                            command = Some(polygon_command);
+                        }
+                    }
                 }
             }
 
+            if let Some(transform_command) = self.transform_ui(ui, editor_model, element) {
+                command = Some(transform_command);
+            }
+
             ui.separator();
             ui.label("Actions:");
             ui.label("• Drag to move");
-            ui.label("• Drag corners to resize");
+            ui.label("• Drag handles to resize");
             ui.label("• Click empty space to deselect");
+            ui.label("• Enter, then arrows to move, Ctrl+arrows to resize, Esc to finish");
         } else {
+            self.name_edit = None;
             ui.label("No element selected");
             ui.label("Click on an element to select it");
         }
+        ui.label("• Tab / Shift+Tab to select the next or previous element");
+        ui.label("• Shift+drag to add to the selection");
+
+        ui.separator();
+        ui.horizontal_wrapped(|ui| {
+            ui.label("Select:");
+            let has_selection = !editor_model.selected_ids().is_empty();
+            for matching in SelectMatching::ALL {
+                let enabled = has_selection || !matching.needs_selection();
+                let button = ui.add_enabled(enabled, egui::Button::new(matching.label()));
+                if button.clicked() {
+                    if let Some(select_command) = matching.command(editor_model) {
+                        command = Some(select_command);
+                    }
+                }
+            }
+        });
+
+        if let Some(stroke_command) = self.stroke_actions_ui(ui, editor_model) {
+            command = Some(stroke_command);
+        }
+        if let Some(shape_command) = self.shape_actions_ui(ui, editor_model) {
+            command = Some(shape_command);
+        }
+        if let Some(trace_command) = self.trace_ui(ui, editor_model) {
+            command = Some(trace_command);
+        }
 
         // Show current tool state
         ui.separator();
         ui.label(format!("Tool State: {}", self.current_state_name()));
 
-        None // No immediate command from UI
+        command
     }
 
     fn get_config(&self) -> Box<dyn ToolConfig> {
@@ -584,6 +1648,8 @@ impl Tool for UnifiedSelectionTool {
     fn apply_config(&mut self, config: &dyn ToolConfig) {
         if let Some(config) = config.as_any().downcast_ref::<SelectionToolConfig>() {
             self.handle_size = config.handle_size;
+        } else if let Some(snap) = config.as_any().downcast_ref::<SnapIncrements>() {
+            self.snap = *snap;
         }
     }
 
@@ -605,7 +1671,7 @@ impl Tool for UnifiedSelectionTool {
                         if let Some(&id) = selected_ids.iter().next() {
                             if let Some(_element) = editor_model.find_element_by_id(id) {
                                 // We can no longer return commands, so we need to handle deletion differently
-                                log::info!("Delete key pressed on element {}", id);
+                                debug!(id:% = id; "Delete key pressed");
                             }
                         }
                     }
@@ -614,7 +1680,7 @@ impl Tool for UnifiedSelectionTool {
                 egui::Key::A if _modifiers.ctrl => {
                     // Select all elements - for now, just use the already selected elements
                     // This is a simplified version until we have proper access to all elements
-                    log::info!("Ctrl+A pressed (select all)");
+                    debug!("Select all pressed");
                 }
                 // Arrow keys for nudging selected elements
                 egui::Key::ArrowLeft | egui::Key::ArrowRight | 
@@ -622,7 +1688,11 @@ impl Tool for UnifiedSelectionTool {
                     let selected_id = editor_model.selected_ids().iter().next().copied();
                     if let Some(id) = selected_id {
                         let mut delta = egui::Vec2::ZERO;
-                        let step = if _modifiers.shift { 10.0 } else { 1.0 };
+                        let step = if _modifiers.shift {
+                            self.snap.large_nudge
+                        } else {
+                            self.snap.nudge
+                        };
                         
                         match key {
                             egui::Key::ArrowLeft => delta.x = -step,
@@ -633,7 +1703,7 @@ impl Tool for UnifiedSelectionTool {
                         }
                         
                         if let Some(_element) = editor_model.find_element_by_id(id) {
-                            log::info!("Arrow key pressed on element {}, delta: {:?}", id, delta);
+                            debug!(id:% = id, delta:? = delta; "Nudging element");
                         }
                     }
                 }
@@ -648,6 +1718,9 @@ impl Tool for UnifiedSelectionTool {
                 let selection_rect = egui::Rect::from_two_pos(*start_pos, *current_pos);
                 renderer.set_resize_preview(Some(selection_rect));
             }
+            SelectionState::Lasso { points, .. } => {
+                renderer.set_lasso_preview(Some(points.clone()));
+            }
             SelectionState::Dragging { start_pos, current_pos, original_rect, .. } => {
                 // Calculate the offset from start to current position
                 let drag_offset = *current_pos - *start_pos;
@@ -660,22 +1733,50 @@ impl Tool for UnifiedSelectionTool {
                 
                 renderer.set_drag_preview(Some(preview_rect));
             }
-            SelectionState::Resizing { element_id, corner, current_pos, original_rect, preserve_aspect_ratio, .. } => {
+            SelectionState::Resizing {
+                element_id,
+                handle,
+                current_pos,
+                original_rect,
+                preserve_aspect_ratio,
+                constraints,
+                ..
+            } => {
                 // Calculate the new rectangle based on the resize operation
-                let new_rect = if *preserve_aspect_ratio {
-                    compute_resized_rect_with_constraints(*original_rect, *corner, *current_pos, true)
-                } else {
-                    Renderer::compute_resized_rect(*original_rect, *corner, *current_pos)
-                };
+                let new_rect = compute_resized_rect_with_constraints(
+                    *original_rect,
+                    *handle,
+                    *current_pos,
+                    *preserve_aspect_ratio,
+                    constraints.min_size,
+                );
                 
                 // Set the preview in the renderer
                 renderer.set_resize_preview(Some(new_rect));
-                renderer.set_active_handle(*element_id, Some(*corner));
+                renderer.set_active_handle(*element_id, Some(*handle));
+            }
+            SelectionState::ResizingSelection {
+                handle,
+                current_pos,
+                original_rect,
+                preserve_aspect_ratio,
+                constraints,
+            } => {
+                let new_rect = compute_resized_rect_with_constraints(
+                    *original_rect,
+                    *handle,
+                    *current_pos,
+                    *preserve_aspect_ratio,
+                    constraints.min_size,
+                );
+                renderer.set_selection_resize_preview(Some((*original_rect, new_rect)));
+                renderer.set_selection_handle(Some(*handle));
             }
             SelectionState::Idle => {
                 // Clear any previews
                 renderer.set_resize_preview(None);
                 renderer.set_drag_preview(None);
+                renderer.set_lasso_preview(None);
             }
         }
     }
@@ -689,23 +1790,52 @@ impl Tool for UnifiedSelectionTool {
     }
 }
 
+impl Default for UnifiedSelectionTool {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
 pub fn new_selection_tool() -> UnifiedSelectionTool {
     UnifiedSelectionTool::new()
 }
 
-fn is_near_handle_position(pos: Pos2, handle_pos: Pos2, radius: f32) -> bool {
-    let distance = (pos - handle_pos).length();
-    distance <= radius
+/// Limits on resizing a multiple selection as one
+///
+/// The box keeps its proportions if any element would on its own. Elements the
+/// box gets too small for move with it instead, so it only needs to stay valid.
+fn selection_resize_constraints(editor_model: &EditorModel) -> ResizeConstraints {
+    let keep_aspect_ratio = editor_model
+        .selected_ids()
+        .iter()
+        .filter_map(|&element_id| editor_model.find_element_by_id(element_id))
+        .any(|element| element.resize_constraints().keep_aspect_ratio);
+    ResizeConstraints {
+        min_size: MIN_ELEMENT_SIZE,
+        keep_aspect_ratio,
+    }
+}
+
+/// The box around the selection when more than one element is selected; its
+/// handles resize them all together in place of their own
+fn multiple_selection_rect(editor_model: &EditorModel) -> Option<egui::Rect> {
+    if editor_model.selected_ids().len() > 1 {
+        editor_model.selection_rect()
+    } else {
+        None
+    }
 }
 
 // Helper function to compute a resized rectangle with aspect ratio preservation
+// (only corner handles can keep the ratio; edge handles stretch one side)
 fn compute_resized_rect_with_constraints(
     original: egui::Rect,
-    corner: Corner,
+    handle: TransformHandle,
     new_pos: egui::Pos2,
-    preserve_aspect_ratio: bool
+    preserve_aspect_ratio: bool,
+    min_size: f32,
 ) -> egui::Rect {
-    if preserve_aspect_ratio {
+    if let (true, TransformHandle::Corner(corner)) = (preserve_aspect_ratio, handle) {
         // Calculate original aspect ratio
         let original_width = original.width();
         let original_height = original.height();
@@ -720,7 +1850,7 @@ fn compute_resized_rect_with_constraints(
         };
         
         // Calculate the proposed width and height
-        let proposed_rect = Renderer::compute_resized_rect(original, corner, new_pos);
+        let proposed_rect = handle.resize_rect(original, new_pos, min_size);
         let proposed_width = proposed_rect.width();
         let proposed_height = proposed_rect.height();
         
@@ -788,6 +1918,148 @@ fn compute_resized_rect_with_constraints(
         }
     } else {
         // Just use the standard resizing logic
-        Renderer::compute_resized_rect(original, corner, new_pos)
+        handle.resize_rect(original, new_pos, min_size)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::element::{LOCKED_PROPERTY, factory};
+    use egui::Color32;
+
+    #[test]
+    fn test_lasso_selects_enclosed_elements_and_shift_adds() {
+        let mut model = EditorModel::new();
+        let diagonal = vec![Pos2::new(0.0, 0.0), Pos2::new(100.0, 100.0)];
+        model
+            .add_element(factory::create_stroke(
+                ElementId::new(1),
+                diagonal,
+                2.0,
+                Color32::BLACK,
+            ))
+            .unwrap();
+        let square = vec![
+            Pos2::new(80.0, 0.0),
+            Pos2::new(100.0, 0.0),
+            Pos2::new(100.0, 20.0),
+            Pos2::new(80.0, 20.0),
+        ];
+        model
+            .add_element(factory::create_shape(
+                ElementId::new(2),
+                square,
+                Color32::RED,
+            ))
+            .unwrap();
+
+        // A thin lasso along the diagonal takes the stroke but not the shape in its rect
+        let along_stroke = [
+            Pos2::new(-5.0, -10.0),
+            Pos2::new(110.0, 95.0),
+            Pos2::new(95.0, 110.0),
+            Pos2::new(-10.0, 5.0),
+        ];
+        assert_eq!(
+            lasso_selection(&model, &along_stroke),
+            vec![ElementId::new(1)]
+        );
+        select_elements_command(&model, vec![ElementId::new(1)], false)
+            .unwrap()
+            .execute(&mut model)
+            .unwrap();
+        assert!(model.is_element_selected(ElementId::new(1)));
+
+        // Without Shift the lasso replaces the selection, with Shift it adds to it
+        let around_square = [
+            Pos2::new(75.0, -5.0),
+            Pos2::new(105.0, -5.0),
+            Pos2::new(105.0, 25.0),
+            Pos2::new(75.0, 25.0),
+        ];
+        let ids = lasso_selection(&model, &around_square);
+        assert_eq!(ids, vec![ElementId::new(2)]);
+        let mut replaced = model.clone();
+        select_elements_command(&replaced, ids.clone(), false)
+            .unwrap()
+            .execute(&mut replaced)
+            .unwrap();
+        assert_eq!(replaced.selected_ids().len(), 1);
+        assert!(replaced.is_element_selected(ElementId::new(2)));
+
+        select_elements_command(&model, ids, true)
+            .unwrap()
+            .execute(&mut model)
+            .unwrap();
+        assert_eq!(model.selected_ids().len(), 2);
+        assert!(select_elements_command(&model, vec![ElementId::new(2)], true).is_none());
+    }
+
+    #[test]
+    fn test_select_matching_by_type_and_color() {
+        let mut model = EditorModel::new();
+        let line = |y: f32| vec![Pos2::new(0.0, y), Pos2::new(50.0, y)];
+        let square = vec![
+            Pos2::new(0.0, 0.0),
+            Pos2::new(10.0, 0.0),
+            Pos2::new(10.0, 10.0),
+        ];
+        model
+            .add_element(factory::create_stroke(
+                ElementId::new(1),
+                line(0.0),
+                2.0,
+                Color32::RED,
+            ))
+            .unwrap();
+        model
+            .add_element(factory::create_stroke(
+                ElementId::new(2),
+                line(10.0),
+                2.0,
+                Color32::BLUE,
+            ))
+            .unwrap();
+        model
+            .add_element(factory::create_shape(
+                ElementId::new(3),
+                square,
+                Color32::RED,
+            ))
+            .unwrap();
+        let mut locked = factory::create_stroke(ElementId::new(4), line(20.0), 2.0, Color32::RED);
+        locked.metadata_mut().set(LOCKED_PROPERTY, "true");
+        model.add_element(locked).unwrap();
+
+        let select = |model: &mut EditorModel, matching: SelectMatching| {
+            matching.command(model).unwrap().execute(model).unwrap();
+            let mut ids: Vec<_> = model.selected_ids().iter().copied().collect();
+            ids.sort_unstable();
+            ids
+        };
+        assert!(SelectMatching::SameColor.command(&model).is_none());
+        assert_eq!(
+            select(&mut model, SelectMatching::AllStrokes),
+            [1, 2].map(ElementId::new)
+        );
+        // Selecting again changes nothing
+        assert!(SelectMatching::AllStrokes.command(&model).is_none());
+
+        model.clear_selection();
+        model.select_element(ElementId::new(3));
+        assert_eq!(
+            select(&mut model, SelectMatching::SameColor),
+            [1, 3].map(ElementId::new)
+        );
+        model.clear_selection();
+        model.select_element(ElementId::new(3));
+        // The only unlocked shape is already selected
+        assert!(SelectMatching::SameType.command(&model).is_none());
+        model.select_element(ElementId::new(2));
+        assert_eq!(
+            select(&mut model, SelectMatching::SameType),
+            [1, 2, 3].map(ElementId::new)
+        );
     }
 }
@@ -0,0 +1,371 @@
+use crate::element::{Element, ElementId, ElementType, factory};
+use crate::geometry::{distance_to_segment, outline_polyline, snap_angle};
+use egui::{Color32, Pos2, Rect, Vec2};
+
+// Number of evenly spaced points strokes are resampled to before fitting,
+// so slow and fast parts of a stroke count the same
+const SAMPLES: usize = 64;
+// Strokes with a smaller bounding box are left alone
+const MIN_SIZE: f32 = 12.0;
+// Largest gap between a stroke's ends, relative to its size, that still closes it
+const CLOSED_GAP: f32 = 0.25;
+// Largest average deviation from the fitted shape, relative to its size
+const LINE_TOLERANCE: f32 = 0.04;
+const RECTANGLE_TOLERANCE: f32 = 0.1;
+const ELLIPSE_TOLERANCE: f32 = 0.1;
+// Ellipses whose radii differ by less than this fraction become circles
+const CIRCLE_SNAP: f32 = 0.15;
+// Longest arrow head, relative to the shaft, and the angle of its barbs
+const ARROW_HEAD_LENGTH: f32 = 0.35;
+const ARROW_BARB_ANGLE: f32 = std::f32::consts::FRAC_PI_6;
+// Segments in a recognized ellipse's outline
+const ELLIPSE_SEGMENTS: usize = 64;
+
+/// Clean shape a hand-drawn stroke was recognized as
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum RecognizedShape {
+    Line {
+        start: Pos2,
+        end: Pos2,
+    },
+    Arrow {
+        start: Pos2,
+        end: Pos2,
+    },
+    Rectangle(Rect),
+    /// A circle when the rect is square
+    Ellipse(Rect),
+}
+
+impl RecognizedShape {
+    pub fn name(&self) -> &'static str {
+        match self {
+            RecognizedShape::Line { .. } => "Line",
+            RecognizedShape::Arrow { .. } => "Arrow",
+            RecognizedShape::Rectangle(_) => "Rectangle",
+            RecognizedShape::Ellipse(rect) if rect.width() == rect.height() => "Circle",
+            RecognizedShape::Ellipse(_) => "Ellipse",
+        }
+    }
+
+    /// The shape with lines and arrows turned to a multiple of `step` radians,
+    /// rectangles squared and ellipses made circles, each about its center
+    pub fn constrained(self, step: f32) -> Self {
+        let square = |rect: Rect| {
+            Rect::from_center_size(
+                rect.center(),
+                Vec2::splat((rect.width() + rect.height()) / 2.0),
+            )
+        };
+        match self {
+            RecognizedShape::Line { start, end } => RecognizedShape::Line {
+                start,
+                end: snap_angle(start, end, step),
+            },
+            RecognizedShape::Arrow { start, end } => RecognizedShape::Arrow {
+                start,
+                end: snap_angle(start, end, step),
+            },
+            RecognizedShape::Rectangle(rect) => RecognizedShape::Rectangle(square(rect)),
+            RecognizedShape::Ellipse(rect) => RecognizedShape::Ellipse(square(rect)),
+        }
+    }
+
+    /// A shape element tracing the clean shape at the stroke's thickness and color
+    pub fn to_element(&self, id: ElementId, thickness: f32, color: Color32) -> ElementType {
+        let outline = match *self {
+            RecognizedShape::Line { start, end } => outline_polyline(&[start, end], thickness),
+            RecognizedShape::Arrow { start, end } => {
+                let length = start.distance(end);
+                let back = (start - end) / length * (length * ARROW_HEAD_LENGTH).min(length / 2.0);
+                let barbs = [
+                    end + rotate(back, ARROW_BARB_ANGLE),
+                    end + rotate(back, -ARROW_BARB_ANGLE),
+                ];
+                union(&[
+                    outline_polyline(&[start, end], thickness),
+                    outline_polyline(&[barbs[0], end, barbs[1]], thickness),
+                ])
+            }
+            RecognizedShape::Rectangle(rect) => {
+                let corners = [
+                    rect.left_top(),
+                    rect.right_top(),
+                    rect.right_bottom(),
+                    rect.left_bottom(),
+                ];
+                outline_closed(&corners, thickness)
+            }
+            RecognizedShape::Ellipse(rect) => {
+                let points: Vec<Pos2> = (0..ELLIPSE_SEGMENTS)
+                    .map(|index| {
+                        let angle = std::f32::consts::TAU * index as f32 / ELLIPSE_SEGMENTS as f32;
+                        rect.center() + Vec2::angled(angle) * rect.size() / 2.0
+                    })
+                    .collect();
+                outline_closed(&points, thickness)
+            }
+        };
+
+        let mut element = factory::create_shape(id, outline, color);
+        element.metadata_mut().name = self.name().to_string();
+        element
+    }
+}
+
+/// The clean shape `points` approximate, if any
+///
+/// Open strokes can be straight lines, or arrows: a straight shaft ending in a
+/// short head drawn without lifting the pen. Closed strokes can be axis-aligned
+/// rectangles or ellipses, whichever fits better.
+pub fn recognize_shape(points: &[Pos2]) -> Option<RecognizedShape> {
+    let points = resample(points, SAMPLES)?;
+    let bounds = Rect::from_points(&points);
+    let size = bounds.size().length();
+    if bounds.width().max(bounds.height()) < MIN_SIZE {
+        return None;
+    }
+
+    let (first, last) = (points[0], points[points.len() - 1]);
+    if first.distance(last) > size * CLOSED_GAP {
+        return recognize_open(&points);
+    }
+
+    let rectangle_error = average(&points, |point| {
+        let to_edges = [
+            point.x - bounds.min.x,
+            bounds.max.x - point.x,
+            point.y - bounds.min.y,
+            bounds.max.y - point.y,
+        ];
+        to_edges.into_iter().fold(f32::INFINITY, f32::min).abs()
+    }) / bounds.width().min(bounds.height()).max(1.0);
+    let radius = bounds.size() / 2.0;
+    let ellipse_error = if radius.min_elem() > 0.0 {
+        average(&points, |point| {
+            ((point - bounds.center()) / radius).length() - 1.0
+        })
+    } else {
+        f32::INFINITY
+    };
+
+    if rectangle_error < ellipse_error && rectangle_error < RECTANGLE_TOLERANCE {
+        Some(RecognizedShape::Rectangle(bounds))
+    } else if ellipse_error < ELLIPSE_TOLERANCE {
+        let rect = if (radius.x - radius.y).abs() < radius.max_elem() * CIRCLE_SNAP {
+            Rect::from_center_size(bounds.center(), Vec2::splat(radius.x + radius.y))
+        } else {
+            bounds
+        };
+        Some(RecognizedShape::Ellipse(rect))
+    } else {
+        None
+    }
+}
+
+fn recognize_open(points: &[Pos2]) -> Option<RecognizedShape> {
+    let start = points[0];
+    let is_straight = |points: &[Pos2], end: Pos2| {
+        let length = start.distance(end);
+        length >= MIN_SIZE
+            && average(points, |point| distance_to_segment(point, start, end)) / length
+                < LINE_TOLERANCE
+    };
+
+    let end = points[points.len() - 1];
+    if is_straight(points, end) {
+        return Some(RecognizedShape::Line { start, end });
+    }
+
+    // An arrow's tip is the point farthest from its tail, with the head drawn after it
+    let tip_index = (0..points.len()).max_by(|a, b| {
+        start
+            .distance(points[*a])
+            .total_cmp(&start.distance(points[*b]))
+    })?;
+    let tip = points[tip_index];
+    let head = &points[tip_index..];
+    let shaft_length = start.distance(tip);
+    let head_reach = head
+        .iter()
+        .map(|point| point.distance(tip))
+        .fold(0.0, f32::max);
+    let barb_reach = head
+        .iter()
+        .map(|point| distance_to_segment(*point, start, tip))
+        .fold(0.0, f32::max);
+    let has_head = head.len() > 2
+        && head_reach < shaft_length * ARROW_HEAD_LENGTH * 1.5
+        && barb_reach > shaft_length * LINE_TOLERANCE * 2.0;
+    (has_head && is_straight(&points[..=tip_index], tip))
+        .then_some(RecognizedShape::Arrow { start, end: tip })
+}
+
+/// `count` points spaced evenly along the polyline
+fn resample(points: &[Pos2], count: usize) -> Option<Vec<Pos2>> {
+    let length: f32 = points
+        .windows(2)
+        .map(|pair| pair[0].distance(pair[1]))
+        .sum();
+    if points.len() < 2 || length <= 0.0 {
+        return None;
+    }
+
+    let step = length / (count - 1) as f32;
+    let mut resampled = vec![points[0]];
+    let mut walked = 0.0;
+    for pair in points.windows(2) {
+        let segment = pair[0].distance(pair[1]);
+        if segment == 0.0 {
+            continue;
+        }
+        while resampled.len() < count && walked + segment >= step * resampled.len() as f32 {
+            let t = (step * resampled.len() as f32 - walked) / segment;
+            resampled.push(pair[0].lerp(pair[1], t));
+        }
+        walked += segment;
+    }
+    // Rounding can leave the last point out or just short of the end
+    resampled.truncate(count - 1);
+    resampled.resize(count, points[points.len() - 1]);
+    Some(resampled)
+}
+
+fn average(points: &[Pos2], error: impl Fn(Pos2) -> f32) -> f32 {
+    points.iter().map(|point| error(*point).abs()).sum::<f32>() / points.len() as f32
+}
+
+fn rotate(vector: Vec2, angle: f32) -> Vec2 {
+    let (sin, cos) = angle.sin_cos();
+    Vec2::new(
+        vector.x * cos - vector.y * sin,
+        vector.x * sin + vector.y * cos,
+    )
+}
+
+/// Outline of a closed polygon's edges, leaving its inside empty
+///
+/// Runs past the start onto the first edge again so every corner gets a join
+/// instead of a cap.
+fn outline_closed(points: &[Pos2], thickness: f32) -> Vec<Pos2> {
+    let mut path = points.to_vec();
+    path.extend_from_slice(&points[..2]);
+    outline_polyline(&path, thickness)
+}
+
+/// One polygon covering all of `outlines` under the nonzero winding rule
+///
+/// Each outline is visited from the first one's start and left back to it, so
+/// the connecting edges run both ways and cancel out.
+fn union(outlines: &[Vec<Pos2>]) -> Vec<Pos2> {
+    let Some(anchor) = outlines
+        .first()
+        .and_then(|outline| outline.first())
+        .copied()
+    else {
+        return Vec::new();
+    };
+    let mut polygon = Vec::new();
+    for outline in outlines.iter().filter(|outline| !outline.is_empty()) {
+        polygon.extend_from_slice(outline);
+        polygon.push(outline[0]);
+        polygon.push(anchor);
+    }
+    polygon.pop();
+    polygon
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn circle(center: Pos2, radius: f32) -> Vec<Pos2> {
+        (0..=40)
+            .map(|index| {
+                let angle = std::f32::consts::TAU * index as f32 / 40.0;
+                // A little wobble, as drawn by hand
+                let wobble = 1.0 + 0.03 * (angle * 7.0).sin();
+                center + Vec2::angled(angle) * radius * wobble
+            })
+            .collect()
+    }
+
+    #[test]
+    fn test_recognize_shape_finds_lines_arrows_rectangles_and_circles() {
+        let line = [
+            Pos2::new(0.0, 0.0),
+            Pos2::new(50.0, 1.0),
+            Pos2::new(100.0, 0.0),
+        ];
+        assert_eq!(
+            recognize_shape(&line),
+            Some(RecognizedShape::Line {
+                start: Pos2::ZERO,
+                end: Pos2::new(100.0, 0.0)
+            })
+        );
+
+        let arrow = [
+            Pos2::new(0.0, 0.0),
+            Pos2::new(100.0, 0.0),
+            Pos2::new(85.0, -10.0),
+            Pos2::new(100.0, 0.0),
+            Pos2::new(85.0, 10.0),
+        ];
+        let Some(RecognizedShape::Arrow { start, end }) = recognize_shape(&arrow) else {
+            panic!("Should be an arrow");
+        };
+        assert_eq!(start, Pos2::ZERO);
+        assert!(end.distance(Pos2::new(100.0, 0.0)) < 3.0);
+
+        let rectangle = [
+            Pos2::new(0.0, 0.0),
+            Pos2::new(100.0, 2.0),
+            Pos2::new(101.0, 60.0),
+            Pos2::new(1.0, 59.0),
+            Pos2::new(0.0, 3.0),
+        ];
+        let Some(RecognizedShape::Rectangle(rect)) = recognize_shape(&rectangle) else {
+            panic!("Should be a rectangle");
+        };
+        assert!((rect.width() - 101.0).abs() < 1.0);
+
+        let Some(RecognizedShape::Ellipse(rect)) = recognize_shape(&circle(Pos2::ZERO, 50.0))
+        else {
+            panic!("Should be a circle");
+        };
+        assert_eq!(rect.width(), rect.height());
+        assert!(rect.center().distance(Pos2::ZERO) < 3.0);
+
+        // Scribbles and tiny strokes stay strokes
+        let zigzag = [
+            Pos2::new(0.0, 0.0),
+            Pos2::new(30.0, 40.0),
+            Pos2::new(60.0, 0.0),
+            Pos2::new(90.0, 40.0),
+        ];
+        assert_eq!(recognize_shape(&zigzag), None);
+        assert_eq!(recognize_shape(&[Pos2::ZERO, Pos2::new(5.0, 0.0)]), None);
+    }
+
+    #[test]
+    fn test_recognized_outlines_leave_closed_shapes_hollow() {
+        let rect = Rect::from_min_size(Pos2::ZERO, Vec2::new(100.0, 60.0));
+        for shape in [
+            RecognizedShape::Rectangle(rect),
+            RecognizedShape::Ellipse(rect),
+        ] {
+            let ElementType::Shape(element) =
+                shape.to_element(ElementId::new(1), 4.0, Color32::BLACK)
+            else {
+                panic!("Should be a shape");
+            };
+            let outline = element.points();
+            assert!(!crate::geometry::polygon_contains(outline, rect.center()));
+            assert!(crate::geometry::polygon_contains(
+                outline,
+                rect.left_center()
+            ));
+        }
+    }
+}
@@ -0,0 +1,269 @@
+use crate::command::Command;
+use crate::element::{Element, ElementType};
+use crate::geometry::split_polyline;
+use crate::renderer::Renderer;
+use crate::state::EditorModel;
+use crate::tools::{Tool, ToolConfig};
+use egui::{Color32, Pos2, Ui};
+use log::{debug, trace};
+use std::any::Any;
+
+// Color and thickness of the cut line preview
+const CUT_PREVIEW_COLOR: Color32 = Color32::from_rgb(220, 40, 40);
+const CUT_PREVIEW_THICKNESS: f32 = 1.0;
+
+// Config for SliceTool (it has no settings yet)
+#[derive(Clone, Debug)]
+pub struct SliceToolConfig;
+
+impl ToolConfig for SliceToolConfig {
+    fn tool_name(&self) -> &'static str {
+        "Slice"
+    }
+
+    fn as_any(&self) -> &dyn Any {
+        self
+    }
+
+    fn as_any_mut(&mut self) -> &mut dyn Any {
+        self
+    }
+}
+
+// State enum for the SliceTool
+#[derive(Clone, Debug)]
+pub enum SliceState {
+    Idle,
+    Cutting { start_pos: Pos2, current_pos: Pos2 },
+}
+
+/// Tool that cuts every stroke crossed by a dragged line into separate strokes
+#[derive(Clone, Debug)]
+pub struct UnifiedSliceTool {
+    pub state: SliceState,
+}
+
+impl UnifiedSliceTool {
+    pub fn new() -> Self {
+        Self {
+            state: SliceState::Idle,
+        }
+    }
+
+    // Get the current state name
+    pub fn current_state_name(&self) -> &'static str {
+        match self.state {
+            SliceState::Idle => "Idle",
+            SliceState::Cutting { .. } => "Cutting",
+        }
+    }
+}
+
+/// Build a command splitting every stroke crossed by the cut line
+///
+/// Each crossed stroke is replaced by its pieces, all in one undoable command.
+/// Returns None if the line doesn't cross any stroke.
+pub fn slice_command(
+    editor_model: &EditorModel,
+    cut_start: Pos2,
+    cut_end: Pos2,
+) -> Option<Command> {
+    let mut removed = Vec::new();
+    let mut added = Vec::new();
+
+    for element in &editor_model.elements {
+        let ElementType::Stroke(stroke) = element else {
+            continue;
+        };
+
+        // Cut where the stroke is drawn, whatever its transform
+        let stroke = stroke.flattened();
+        let pieces = split_polyline(stroke.points(), cut_start, cut_end);
+        if pieces.len() < 2 {
+            continue;
+        }
+
+        debug!(id:% = stroke.id(), pieces = pieces.len(); "Slicing stroke");
+        removed.push(element.clone());
+        added.extend(pieces.into_iter().map(|points| {
+            let id = editor_model.reserve_id();
+            ElementType::Stroke(stroke.with_points(id, points))
+        }));
+    }
+
+    if removed.is_empty() {
+        return None;
+    }
+
+    Some(Command::new_replace_elements(
+        editor_model,
+        "Slice Strokes",
+        removed,
+        added,
+    ))
+}
+
+impl Tool for UnifiedSliceTool {
+    fn name(&self) -> &'static str {
+        "Slice"
+    }
+
+    fn activate(&mut self, _editor_model: &EditorModel) {
+        self.state = SliceState::Idle;
+        trace!(tool = self.name(); "Tool activated");
+    }
+
+    fn deactivate(&mut self, _editor_model: &EditorModel) {
+        self.state = SliceState::Idle;
+        trace!(tool = self.name(); "Tool deactivated");
+    }
+
+    fn on_pointer_down(
+        &mut self,
+        pos: Pos2,
+        button: egui::PointerButton,
+        _modifiers: &egui::Modifiers,
+        _editor_model: &EditorModel,
+        _renderer: &mut Renderer,
+    ) -> Option<Command> {
+        if button == egui::PointerButton::Primary {
+            self.state = SliceState::Cutting {
+                start_pos: pos,
+                current_pos: pos,
+            };
+        }
+        None
+    }
+
+    fn on_pointer_move(
+        &mut self,
+        pos: Pos2,
+        held_buttons: &[egui::PointerButton],
+        _modifiers: &egui::Modifiers,
+        _editor_model: &mut EditorModel,
+        _ui: &egui::Ui,
+        _renderer: &mut Renderer,
+    ) -> Option<Command> {
+        if !held_buttons.contains(&egui::PointerButton::Primary) {
+            return None;
+        }
+        if let SliceState::Cutting { current_pos, .. } = &mut self.state {
+            *current_pos = pos;
+        }
+        None
+    }
+
+    fn on_pointer_up(
+        &mut self,
+        pos: Pos2,
+        button: egui::PointerButton,
+        _modifiers: &egui::Modifiers,
+        editor_model: &EditorModel,
+    ) -> Option<Command> {
+        if button != egui::PointerButton::Primary {
+            return None;
+        }
+
+        let SliceState::Cutting { start_pos, .. } = self.state else {
+            return None;
+        };
+        self.state = SliceState::Idle;
+        slice_command(editor_model, start_pos, pos)
+    }
+
+    fn cursor_icon(
+        &self,
+        _pos: Pos2,
+        _editor_model: &EditorModel,
+        _renderer: &Renderer,
+    ) -> Option<egui::CursorIcon> {
+        Some(egui::CursorIcon::Crosshair)
+    }
+
+    fn reset_interaction_state(&mut self) {
+        self.state = SliceState::Idle;
+    }
+
+    fn update_preview(&mut self, renderer: &mut Renderer) {
+        match self.state {
+            SliceState::Idle => renderer.clear_stroke_preview(),
+            SliceState::Cutting {
+                start_pos,
+                current_pos,
+            } => renderer.set_stroke_preview(
+                vec![start_pos, current_pos],
+                CUT_PREVIEW_THICKNESS,
+                CUT_PREVIEW_COLOR,
+            ),
+        }
+    }
+
+    fn clear_preview(&mut self, renderer: &mut Renderer) {
+        renderer.clear_stroke_preview();
+    }
+
+    fn ui(&mut self, ui: &mut Ui, _editor_model: &EditorModel) -> Option<Command> {
+        ui.label("Slice Tool");
+        ui.label("Drag a line across strokes to cut them apart where it crosses.");
+        None
+    }
+
+    fn get_config(&self) -> Box<dyn ToolConfig> {
+        Box::new(SliceToolConfig)
+    }
+
+    fn apply_config(&mut self, _config: &dyn ToolConfig) {}
+}
+
+impl Default for UnifiedSliceTool {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+// Factory function to create a new SliceTool
+pub fn new_slice_tool() -> UnifiedSliceTool {
+    UnifiedSliceTool::new()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::element::factory;
+
+    #[test]
+    fn test_slice_command_splits_crossed_strokes_only() {
+        let mut model = EditorModel::new();
+        let (crossed_id, untouched_id) = (model.reserve_id(), model.reserve_id());
+        let crossed = vec![Pos2::new(0.0, 0.0), Pos2::new(100.0, 0.0)];
+        let untouched = vec![Pos2::new(0.0, 50.0), Pos2::new(100.0, 50.0)];
+        model
+            .add_element(factory::create_stroke(
+                crossed_id,
+                crossed,
+                2.0,
+                Color32::RED,
+            ))
+            .unwrap();
+        model
+            .add_element(factory::create_stroke(
+                untouched_id,
+                untouched,
+                2.0,
+                Color32::RED,
+            ))
+            .unwrap();
+
+        let command = slice_command(&model, Pos2::new(50.0, -10.0), Pos2::new(50.0, 10.0)).unwrap();
+        command.execute(&mut model).unwrap();
+        assert_eq!(model.elements.len(), 3);
+        assert!(!model.contains_element(crossed_id));
+        assert!(model.contains_element(untouched_id));
+
+        command.undo(&mut model).unwrap();
+        assert_eq!(model.elements.len(), 2);
+        assert!(model.contains_element(crossed_id));
+
+        assert!(slice_command(&model, Pos2::new(0.0, 20.0), Pos2::new(100.0, 20.0)).is_none());
+    }
+}
@@ -0,0 +1,295 @@
+use crate::command::Command;
+use crate::element::{DEFAULT_NOTE_SIZE, ElementType, MIN_FILLED_SIZE, NOTE_COLORS, factory};
+use crate::renderer::Renderer;
+use crate::state::EditorModel;
+use crate::tools::{Tool, ToolConfig};
+use egui::{Color32, Pos2, Rect, Ui, Vec2};
+use log::{debug, trace};
+use std::any::Any;
+
+// Side of each color choice in the palette
+const PALETTE_SWATCH_SIZE: f32 = 20.0;
+
+// Config for StickyNoteTool
+#[derive(Clone, Debug)]
+pub struct StickyNoteToolConfig {
+    pub color: Color32,
+}
+
+impl ToolConfig for StickyNoteToolConfig {
+    fn tool_name(&self) -> &'static str {
+        "Sticky Note"
+    }
+
+    fn as_any(&self) -> &dyn Any {
+        self
+    }
+
+    fn as_any_mut(&mut self) -> &mut dyn Any {
+        self
+    }
+}
+
+// State enum for the StickyNoteTool
+#[derive(Clone, Debug)]
+pub enum StickyNoteState {
+    Idle,
+    Dragging { start_pos: Pos2, current_pos: Pos2 },
+}
+
+/// Tool placing sticky notes: a click drops a note of the default size, a drag
+/// sizes one
+///
+/// New notes open for typing straight away; clicks on an existing note are
+/// left alone so double-clicking it edits its text instead.
+#[derive(Clone, Debug)]
+pub struct UnifiedStickyNoteTool {
+    pub state: StickyNoteState,
+    pub color: Color32,
+}
+
+impl UnifiedStickyNoteTool {
+    pub fn new() -> Self {
+        Self {
+            state: StickyNoteState::Idle,
+            color: NOTE_COLORS[0].1,
+        }
+    }
+
+    // Get the current state name
+    pub fn current_state_name(&self) -> &'static str {
+        match self.state {
+            StickyNoteState::Idle => "Idle",
+            StickyNoteState::Dragging { .. } => "Dragging",
+        }
+    }
+}
+
+/// Whether the topmost element at `pos` is a sticky note
+fn note_at(editor_model: &EditorModel, pos: Pos2) -> bool {
+    matches!(
+        editor_model.element_at_position(pos),
+        Some(ElementType::StickyNote(_))
+    )
+}
+
+/// Build the command adding an empty note dragged from `start` to `end`
+///
+/// Drags too small to make a usable note are taken as clicks, which center a
+/// note of the default size on `start`.
+pub fn sticky_note_command(
+    editor_model: &EditorModel,
+    start: Pos2,
+    end: Pos2,
+    color: Color32,
+) -> Command {
+    let dragged = Rect::from_two_pos(start, end);
+    let rect = if dragged.width() < MIN_FILLED_SIZE || dragged.height() < MIN_FILLED_SIZE {
+        Rect::from_center_size(start, DEFAULT_NOTE_SIZE)
+    } else {
+        dragged
+    };
+    debug!(rect:? = rect; "Adding sticky note");
+    let element =
+        factory::create_sticky_note(editor_model.reserve_id(), rect, String::new(), color);
+    Command::AddElement { element }
+}
+
+/// Row of the note colors; returns the one clicked, if it differs from `current`
+pub fn note_color_ui(ui: &mut Ui, current: Color32) -> Option<Color32> {
+    let mut picked = None;
+    ui.horizontal(|ui| {
+        ui.label("Color:");
+        for (name, color) in NOTE_COLORS {
+            let (rect, response) =
+                ui.allocate_exact_size(Vec2::splat(PALETTE_SWATCH_SIZE), egui::Sense::click());
+            ui.painter().rect_filled(rect, 3.0, color);
+            let stroke = if color == current {
+                ui.visuals().selection.stroke
+            } else {
+                ui.visuals().widgets.noninteractive.bg_stroke
+            };
+            ui.painter().rect_stroke(rect, 3.0, stroke);
+            if response.on_hover_text(name).clicked() && color != current {
+                picked = Some(color);
+            }
+        }
+    });
+    picked
+}
+
+impl Tool for UnifiedStickyNoteTool {
+    fn name(&self) -> &'static str {
+        "Sticky Note"
+    }
+
+    fn activate(&mut self, _editor_model: &EditorModel) {
+        self.state = StickyNoteState::Idle;
+        trace!(tool = self.name(); "Tool activated");
+    }
+
+    fn deactivate(&mut self, _editor_model: &EditorModel) {
+        self.state = StickyNoteState::Idle;
+        trace!(tool = self.name(); "Tool deactivated");
+    }
+
+    fn on_pointer_down(
+        &mut self,
+        pos: Pos2,
+        button: egui::PointerButton,
+        _modifiers: &egui::Modifiers,
+        editor_model: &EditorModel,
+        _renderer: &mut Renderer,
+    ) -> Option<Command> {
+        if button == egui::PointerButton::Primary && !note_at(editor_model, pos) {
+            self.state = StickyNoteState::Dragging {
+                start_pos: pos,
+                current_pos: pos,
+            };
+        }
+        None
+    }
+
+    fn on_pointer_move(
+        &mut self,
+        pos: Pos2,
+        held_buttons: &[egui::PointerButton],
+        _modifiers: &egui::Modifiers,
+        _editor_model: &mut EditorModel,
+        _ui: &egui::Ui,
+        _renderer: &mut Renderer,
+    ) -> Option<Command> {
+        if !held_buttons.contains(&egui::PointerButton::Primary) {
+            return None;
+        }
+        if let StickyNoteState::Dragging { current_pos, .. } = &mut self.state {
+            *current_pos = pos;
+        }
+        None
+    }
+
+    fn on_pointer_up(
+        &mut self,
+        pos: Pos2,
+        button: egui::PointerButton,
+        _modifiers: &egui::Modifiers,
+        editor_model: &EditorModel,
+    ) -> Option<Command> {
+        if button != egui::PointerButton::Primary {
+            return None;
+        }
+
+        let StickyNoteState::Dragging { start_pos, .. } = self.state else {
+            return None;
+        };
+        self.state = StickyNoteState::Idle;
+        Some(sticky_note_command(
+            editor_model,
+            start_pos,
+            pos,
+            self.color,
+        ))
+    }
+
+    fn cursor_icon(
+        &self,
+        pos: Pos2,
+        editor_model: &EditorModel,
+        _renderer: &Renderer,
+    ) -> Option<egui::CursorIcon> {
+        if matches!(self.state, StickyNoteState::Idle) && note_at(editor_model, pos) {
+            return Some(egui::CursorIcon::Text);
+        }
+        Some(egui::CursorIcon::Crosshair)
+    }
+
+    fn reset_interaction_state(&mut self) {
+        self.state = StickyNoteState::Idle;
+    }
+
+    fn update_preview(&mut self, renderer: &mut Renderer) {
+        match self.state {
+            StickyNoteState::Idle => renderer.set_lasso_preview(None),
+            StickyNoteState::Dragging {
+                start_pos,
+                current_pos,
+            } => {
+                let rect = Rect::from_two_pos(start_pos, current_pos);
+                renderer.set_lasso_preview(Some(vec![
+                    rect.left_top(),
+                    rect.right_top(),
+                    rect.right_bottom(),
+                    rect.left_bottom(),
+                ]));
+            }
+        }
+    }
+
+    fn clear_preview(&mut self, renderer: &mut Renderer) {
+        renderer.set_lasso_preview(None);
+    }
+
+    fn ui(&mut self, ui: &mut Ui, _editor_model: &EditorModel) -> Option<Command> {
+        ui.label("Sticky Note Tool");
+        if let Some(color) = note_color_ui(ui, self.color) {
+            self.color = color;
+        }
+        ui.label("Click to drop a note or drag to size one, then type.");
+        ui.label("Double-click a note to edit its text; Escape or a click outside finishes.");
+        None
+    }
+
+    fn get_config(&self) -> Box<dyn ToolConfig> {
+        Box::new(StickyNoteToolConfig { color: self.color })
+    }
+
+    fn apply_config(&mut self, config: &dyn ToolConfig) {
+        if let Some(config) = config.as_any().downcast_ref::<StickyNoteToolConfig>() {
+            self.color = config.color;
+        }
+    }
+}
+
+impl Default for UnifiedStickyNoteTool {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+// Factory function to create a new StickyNoteTool
+pub fn new_sticky_note_tool() -> UnifiedStickyNoteTool {
+    UnifiedStickyNoteTool::new()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::element::Element;
+
+    #[test]
+    fn test_clicks_drop_default_notes_and_drags_size_them() {
+        let mut model = EditorModel::new();
+        let click = Pos2::new(100.0, 100.0);
+        sticky_note_command(&model, click, click, NOTE_COLORS[0].1)
+            .execute(&mut model)
+            .unwrap();
+        let note = model.elements.last().unwrap();
+        assert_eq!(
+            note.rect(),
+            Rect::from_center_size(click, DEFAULT_NOTE_SIZE)
+        );
+        // Pressing on the note leaves it for double-click editing
+        assert!(note_at(&model, click));
+
+        let (start, end) = (Pos2::new(300.0, 300.0), Pos2::new(420.0, 380.0));
+        sticky_note_command(&model, start, end, NOTE_COLORS[1].1)
+            .execute(&mut model)
+            .unwrap();
+        let Some(ElementType::StickyNote(note)) = model.elements.last() else {
+            panic!("expected a sticky note");
+        };
+        assert_eq!(note.rect(), Rect::from_two_pos(start, end));
+        assert_eq!(note.color(), NOTE_COLORS[1].1);
+        assert_eq!(note.text(), "");
+    }
+}
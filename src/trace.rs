@@ -0,0 +1,387 @@
+//! Turning bitmap images into editable strokes (trace bitmap)
+//!
+//! Pixels darker than a threshold count as ink; the outline of every ink region
+//! (and of every hole in one) becomes a closed stroke. Tracing runs on a worker
+//! thread on native builds so large images don't freeze the UI.
+
+use crate::command::Command;
+use crate::element::{Element, ElementType, factory};
+use crate::state::{EditorModel, ElementId};
+use egui::{Color32, Pos2, Rect, Vec2};
+use std::collections::HashMap;
+use std::sync::atomic::{AtomicBool, AtomicU32, Ordering};
+use std::sync::{Arc, Mutex};
+
+/// Images are scaled down to fit this many pixels across before tracing
+const TRACE_MAX_SIZE: u32 = 512;
+/// Outlines enclosing fewer pixels than this are specks and are dropped
+const MIN_CONTOUR_AREA: f32 = 4.0;
+
+/// Options for tracing an image
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct TraceSettings {
+    /// Pixels with a brightness below this are ink
+    pub threshold: u8,
+    /// How far, in traced pixels, outlines may stray from the pixel edges when simplified
+    pub tolerance: f32,
+    pub thickness: f32,
+    pub color: Color32,
+}
+
+impl Default for TraceSettings {
+    fn default() -> Self {
+        Self {
+            threshold: 128,
+            tolerance: 1.0,
+            thickness: 2.0,
+            color: Color32::BLACK,
+        }
+    }
+}
+
+type TraceResult = Result<Vec<Vec<Pos2>>, String>;
+
+/// A trace running in the background
+///
+/// Clones share the same trace, so it can live in a tool that gets cloned.
+#[derive(Clone)]
+pub struct TraceJob {
+    pub image_id: ElementId,
+    pub settings: TraceSettings,
+    // Fraction done, as the bits of an f32
+    progress: Arc<AtomicU32>,
+    cancelled: Arc<AtomicBool>,
+    result: Arc<Mutex<Option<TraceResult>>>,
+}
+
+impl std::fmt::Debug for TraceJob {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("TraceJob")
+            .field("image_id", &self.image_id)
+            .field("settings", &self.settings)
+            .field("progress", &self.progress())
+            .finish()
+    }
+}
+
+impl TraceJob {
+    /// Start tracing the encoded image `data`; the UI is woken up as it progresses
+    pub fn start(
+        image_id: ElementId,
+        data: Vec<u8>,
+        settings: TraceSettings,
+        ctx: &egui::Context,
+    ) -> Self {
+        let progress = Arc::new(AtomicU32::new(0.0f32.to_bits()));
+        let cancelled = Arc::new(AtomicBool::new(false));
+        let result = Arc::new(Mutex::new(None));
+
+        let work = {
+            let progress = progress.clone();
+            let cancelled = cancelled.clone();
+            let result = result.clone();
+            let ctx = ctx.clone();
+            move || {
+                let mut reported = 0.0;
+                let mut report = |done: f32| {
+                    // Repaint for every percent rather than every row
+                    if done - reported >= 0.01 {
+                        reported = done;
+                        progress.store(done.to_bits(), Ordering::Relaxed);
+                        ctx.request_repaint();
+                    }
+                    !cancelled.load(Ordering::Relaxed)
+                };
+                let traced = trace_image(&data, &settings, &mut report);
+                if let Ok(mut result) = result.lock() {
+                    *result = Some(traced);
+                }
+                ctx.request_repaint();
+            }
+        };
+        #[cfg(not(target_arch = "wasm32"))]
+        std::thread::spawn(work);
+        // No threads on the web; trace right away
+        #[cfg(target_arch = "wasm32")]
+        work();
+
+        Self {
+            image_id,
+            settings,
+            progress,
+            cancelled,
+            result,
+        }
+    }
+
+    /// How much of the trace is done, from 0 to 1
+    pub fn progress(&self) -> f32 {
+        f32::from_bits(self.progress.load(Ordering::Relaxed))
+    }
+
+    /// The traced outlines, scaled to the unit square, once the trace has finished
+    pub fn poll(&self) -> Option<TraceResult> {
+        self.result.lock().ok()?.take()
+    }
+
+    /// Stop the trace; it finishes with an error soon after
+    pub fn cancel(&self) {
+        self.cancelled.store(true, Ordering::Relaxed);
+    }
+}
+
+/// Decode and trace an image, returning outlines scaled to the unit square
+///
+/// `progress` is told the fraction done now and then; returning false stops the trace.
+pub fn trace_image(
+    data: &[u8],
+    settings: &TraceSettings,
+    progress: &mut dyn FnMut(f32) -> bool,
+) -> TraceResult {
+    let image = image::load_from_memory(data)
+        .map_err(|err| format!("Failed to decode the image: {}", err))?;
+    let image = if image.width().max(image.height()) > TRACE_MAX_SIZE {
+        image.thumbnail(TRACE_MAX_SIZE, TRACE_MAX_SIZE)
+    } else {
+        image
+    };
+    let (width, height) = (image.width() as usize, image.height() as usize);
+    if width == 0 || height == 0 {
+        return Err("The image is empty".to_string());
+    }
+
+    // Transparent pixels are paper, whatever their color
+    let ink: Vec<bool> = image
+        .to_rgba8()
+        .pixels()
+        .map(|pixel| {
+            let [r, g, b, a] = pixel.0;
+            let brightness = (r as u32 * 299 + g as u32 * 587 + b as u32 * 114) / 1000;
+            a >= 128 && brightness < settings.threshold as u32
+        })
+        .collect();
+
+    let contours = trace_contours(&ink, width, height, settings.tolerance, progress)
+        .ok_or_else(|| "Tracing was cancelled".to_string())?;
+    if contours.is_empty() {
+        return Err("Nothing darker than the threshold to trace".to_string());
+    }
+    let scale = Vec2::new(1.0 / width as f32, 1.0 / height as f32);
+    Ok(contours
+        .into_iter()
+        .map(|contour| {
+            contour
+                .into_iter()
+                .map(|p| (p.to_vec2() * scale).to_pos2())
+                .collect()
+        })
+        .collect())
+}
+
+/// Outlines of the ink regions of a `width` × `height` mask, in pixel units
+///
+/// Each outline runs along pixel edges and closes back on its first point before
+/// being simplified to `tolerance`. Returns None if `progress` asks to stop.
+pub fn trace_contours(
+    ink: &[bool],
+    width: usize,
+    height: usize,
+    tolerance: f32,
+    progress: &mut dyn FnMut(f32) -> bool,
+) -> Option<Vec<Vec<Pos2>>> {
+    let is_ink = |x: isize, y: isize| {
+        x >= 0
+            && y >= 0
+            && (x as usize) < width
+            && (y as usize) < height
+            && ink[y as usize * width + x as usize]
+    };
+
+    // Every edge between an ink pixel and paper, directed with the ink on its left
+    // (screen coordinates), keyed by the grid corner it starts from
+    type Corner = (isize, isize);
+    let mut edges: HashMap<Corner, Vec<Corner>> = HashMap::new();
+    let mut edge_count = 0;
+    for y in 0..height as isize {
+        for x in 0..width as isize {
+            if !is_ink(x, y) {
+                continue;
+            }
+            let sides = [
+                (!is_ink(x, y - 1), (x + 1, y), (x, y)),
+                (!is_ink(x - 1, y), (x, y), (x, y + 1)),
+                (!is_ink(x, y + 1), (x, y + 1), (x + 1, y + 1)),
+                (!is_ink(x + 1, y), (x + 1, y + 1), (x + 1, y)),
+            ];
+            for (is_boundary, start, end) in sides {
+                if is_boundary {
+                    edges.entry(start).or_default().push(end);
+                    edge_count += 1;
+                }
+            }
+        }
+        if !progress(0.5 * (y + 1) as f32 / height as f32) {
+            return None;
+        }
+    }
+
+    // Chain the edges into closed loops
+    let mut contours = Vec::new();
+    let mut chained = 0;
+    let starts: Vec<Corner> = edges.keys().copied().collect();
+    for start in starts {
+        while let Some(first) = edges.get_mut(&start).and_then(Vec::pop) {
+            let mut contour = vec![Pos2::new(start.0 as f32, start.1 as f32)];
+            let mut corner = first;
+            chained += 1;
+            while corner != start {
+                contour.push(Pos2::new(corner.0 as f32, corner.1 as f32));
+                let Some(next) = edges.get_mut(&corner).and_then(Vec::pop) else {
+                    break;
+                };
+                corner = next;
+                chained += 1;
+            }
+            contour.push(contour[0]);
+
+            if polygon_area(&contour) >= MIN_CONTOUR_AREA {
+                contours.push(crate::geometry::simplify_polyline(&contour, tolerance));
+            }
+            if !progress(0.5 + 0.5 * chained as f32 / edge_count as f32) {
+                return None;
+            }
+        }
+    }
+    Some(contours)
+}
+
+fn polygon_area(polygon: &[Pos2]) -> f32 {
+    polygon
+        .windows(2)
+        .map(|pair| pair[0].x * pair[1].y - pair[1].x * pair[0].y)
+        .sum::<f32>()
+        .abs()
+        / 2.0
+}
+
+/// Build a command replacing an image with strokes along the traced outlines
+///
+/// `contours` are in the unit square and are stretched over the image's
+/// current bounds, so the image may have moved while it was being traced.
+pub fn trace_command(
+    editor_model: &EditorModel,
+    image_id: ElementId,
+    contours: &[Vec<Pos2>],
+    settings: &TraceSettings,
+) -> Result<Command, String> {
+    let image = match editor_model.find_element_by_id(image_id) {
+        Some(image @ ElementType::Image(_)) => image,
+        Some(_) => return Err(format!("Element {} is not an image", image_id)),
+        None => return Err(format!("Element with id {} not found", image_id)),
+    };
+    let bounds: Rect = image.rect();
+
+    let added = contours
+        .iter()
+        .filter(|contour| contour.len() >= 2)
+        .map(|contour| {
+            let points = contour
+                .iter()
+                .map(|point| bounds.min + point.to_vec2() * bounds.size())
+                .collect();
+            factory::create_stroke(
+                editor_model.reserve_id(),
+                points,
+                settings.thickness,
+                settings.color,
+            )
+        })
+        .collect::<Vec<_>>();
+    if added.is_empty() {
+        return Err("Nothing to trace".to_string());
+    }
+
+    Ok(Command::new_replace_elements(
+        editor_model,
+        "Trace Image",
+        vec![image.clone()],
+        added,
+    ))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_trace_contours_outlines_regions_and_holes() {
+        // A 6x6 ring of ink around a 2x2 hole, plus a single-pixel speck
+        let (width, height) = (10, 8);
+        let mut ink = vec![false; width * height];
+        for y in 1..7 {
+            for x in 1..7 {
+                ink[y * width + x] = !((3..5).contains(&x) && (3..5).contains(&y));
+            }
+        }
+        ink[width + 8] = true;
+
+        let mut calls = 0;
+        let mut progress = |_| {
+            calls += 1;
+            true
+        };
+        let contours = trace_contours(&ink, width, height, 0.1, &mut progress).unwrap();
+        assert!(calls > 0);
+
+        // The outside and the hole; the speck is dropped
+        let mut areas: Vec<f32> = contours.iter().map(|c| polygon_area(c)).collect();
+        areas.sort_by(f32::total_cmp);
+        assert_eq!(areas, vec![4.0, 36.0]);
+        for contour in &contours {
+            assert_eq!(contour.first(), contour.last());
+            // Straight runs of pixel edges collapse to the corners (and the start)
+            assert!(contour.len() <= 6);
+        }
+
+        // Cancelling stops the trace
+        assert!(trace_contours(&ink, width, height, 0.1, &mut |_| false).is_none());
+    }
+
+    #[test]
+    fn test_trace_command_replaces_image_with_strokes() {
+        let mut model = EditorModel::new();
+        let position = Pos2::new(100.0, 50.0);
+        model
+            .add_element(factory::create_image(
+                ElementId::new(7),
+                Vec::new(),
+                Vec2::new(200.0, 100.0),
+                position,
+            ))
+            .unwrap();
+        let contours = vec![vec![Pos2::new(0.0, 0.0), Pos2::new(1.0, 1.0)]];
+
+        let command = trace_command(
+            &model,
+            ElementId::new(7),
+            &contours,
+            &TraceSettings::default(),
+        )
+        .unwrap();
+        command.execute(&mut model).unwrap();
+        assert!(!model.contains_element(ElementId::new(7)));
+        let [ElementType::Stroke(stroke)] = model.elements.as_slice() else {
+            panic!("expected a single stroke");
+        };
+        assert_eq!(stroke.points(), &[position, Pos2::new(300.0, 150.0)]);
+        assert!(
+            trace_command(
+                &model,
+                ElementId::new(7),
+                &contours,
+                &TraceSettings::default()
+            )
+            .is_err()
+        );
+    }
+}
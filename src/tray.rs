@@ -0,0 +1,110 @@
+//! System tray icon offering a quick sketch (native builds with the `tray` feature)
+//!
+//! On Linux the icon lives on its own GTK thread, as the tray needs a GTK event
+//! loop; elsewhere it is created on the UI thread once the event loop runs.
+//! Menu clicks come back as [`TrayAction`]s and wake the UI.
+
+use std::sync::mpsc::{Receiver, channel};
+use tray_icon::menu::{Menu, MenuEvent, MenuItem, PredefinedMenuItem};
+use tray_icon::{Icon, TrayIcon, TrayIconBuilder};
+
+const ICON_SIZE: u32 = 32;
+const ICON_COLOR: [u8; 4] = [0, 120, 215, 255];
+
+const QUICK_SKETCH_ID: &str = "quick_sketch";
+const SHOW_WINDOW_ID: &str = "show_window";
+const QUIT_ID: &str = "quit";
+
+/// Something picked from the tray menu
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum TrayAction {
+    QuickSketch,
+    ShowWindow,
+    Quit,
+}
+
+pub struct Tray {
+    actions: Receiver<TrayAction>,
+    // The icon disappears when dropped
+    #[cfg(not(target_os = "linux"))]
+    _icon: TrayIcon,
+}
+
+impl Tray {
+    /// Add the tray icon; must be called from the UI thread once the app is running
+    pub fn new(ctx: &egui::Context, tooltip: &str) -> Result<Self, String> {
+        let (sender, actions) = channel();
+        let ctx = ctx.clone();
+        MenuEvent::set_event_handler(Some(move |event: MenuEvent| {
+            let action = match event.id().as_ref() {
+                QUICK_SKETCH_ID => TrayAction::QuickSketch,
+                SHOW_WINDOW_ID => TrayAction::ShowWindow,
+                QUIT_ID => TrayAction::Quit,
+                _ => return,
+            };
+            let _ = sender.send(action);
+            ctx.request_repaint();
+        }));
+
+        #[cfg(target_os = "linux")]
+        {
+            let tooltip = tooltip.to_string();
+            std::thread::spawn(move || {
+                if let Err(err) = gtk::init() {
+                    log::warn!("Failed to start GTK for the tray icon: {}", err);
+                    return;
+                }
+                match build_icon(&tooltip) {
+                    Ok(_icon) => gtk::main(),
+                    Err(err) => log::warn!("Failed to create tray icon: {}", err),
+                }
+            });
+            Ok(Self { actions })
+        }
+
+        #[cfg(not(target_os = "linux"))]
+        Ok(Self {
+            actions,
+            _icon: build_icon(tooltip)?,
+        })
+    }
+
+    /// The next menu action, if one is waiting
+    pub fn poll(&self) -> Option<TrayAction> {
+        self.actions.try_recv().ok()
+    }
+}
+
+fn build_icon(tooltip: &str) -> Result<TrayIcon, String> {
+    let menu = Menu::new();
+    menu.append_items(&[
+        &MenuItem::with_id(QUICK_SKETCH_ID, "Quick sketch", true, None),
+        &MenuItem::with_id(SHOW_WINDOW_ID, "Show window", true, None),
+        &PredefinedMenuItem::separator(),
+        &MenuItem::with_id(QUIT_ID, "Quit", true, None),
+    ])
+    .map_err(|err| err.to_string())?;
+
+    TrayIconBuilder::new()
+        .with_menu(Box::new(menu))
+        .with_tooltip(tooltip)
+        .with_icon(icon_image()?)
+        .build()
+        .map_err(|err| err.to_string())
+}
+
+/// A round brush tip in the accent color
+fn icon_image() -> Result<Icon, String> {
+    let center = (ICON_SIZE as f32 - 1.0) / 2.0;
+    let radius = ICON_SIZE as f32 * 0.4;
+    let mut rgba = Vec::with_capacity((ICON_SIZE * ICON_SIZE * 4) as usize);
+    for y in 0..ICON_SIZE {
+        for x in 0..ICON_SIZE {
+            let distance = (x as f32 - center).hypot(y as f32 - center);
+            let coverage = (radius - distance + 0.5).clamp(0.0, 1.0);
+            let [r, g, b, a] = ICON_COLOR;
+            rgba.extend_from_slice(&[r, g, b, (a as f32 * coverage) as u8]);
+        }
+    }
+    Icon::from_rgba(rgba, ICON_SIZE, ICON_SIZE).map_err(|err| err.to_string())
+}
@@ -0,0 +1,252 @@
+use crate::artboard::Artboard;
+use crate::command::Command;
+use crate::document::{DocumentFile, DocumentMetadata};
+use crate::element::{Element, ElementType, LOCKED_PROPERTY, factory};
+use crate::events::EditorEvent;
+use crate::state::EditorModel;
+use egui::{Color32, Rect, Vec2};
+use std::sync::mpsc::Receiver;
+
+// Look of the guides in the tutorial document
+const GUIDE_COLOR: Color32 = Color32::from_rgb(220, 40, 40);
+const DRAWING_AREA_COLOR: Color32 = Color32::from_rgba_premultiplied(90, 75, 0, 90);
+const SQUARE_COLOR: Color32 = Color32::from_rgb(30, 120, 255);
+const SQUARE_SIZE: f32 = 120.0;
+// Gap between the artboard edge, the guides and the drawing area
+const MARGIN: f32 = 40.0;
+
+/// One thing the tutorial asks the user to do
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum TutorialTask {
+    DrawStroke,
+    Select,
+    Resize,
+    Undo,
+}
+
+impl TutorialTask {
+    pub const ALL: [TutorialTask; 4] = [
+        TutorialTask::DrawStroke,
+        TutorialTask::Select,
+        TutorialTask::Resize,
+        TutorialTask::Undo,
+    ];
+
+    pub fn title(&self) -> &'static str {
+        match self {
+            TutorialTask::DrawStroke => "Draw a stroke",
+            TutorialTask::Select => "Select an element",
+            TutorialTask::Resize => "Resize it",
+            TutorialTask::Undo => "Undo",
+        }
+    }
+
+    pub fn instructions(&self) -> &'static str {
+        match self {
+            TutorialTask::DrawStroke => {
+                "With the Draw Stroke tool (B), drag inside the yellow area next to badge 1."
+            }
+            TutorialTask::Select => {
+                "Switch to the Selection tool (V) and click the blue square next to badge 2."
+            }
+            TutorialTask::Resize => "Drag one of the handles around the square to resize it.",
+            TutorialTask::Undo => "Press the Undo button to take the resize back.",
+        }
+    }
+
+    /// Whether `event` shows the user did what the task asks
+    fn is_completed_by(&self, event: &EditorEvent) -> bool {
+        match (self, event) {
+            (TutorialTask::Undo, EditorEvent::CommandUndone(_)) => true,
+            (task, EditorEvent::CommandExecuted(command)) => task.is_completed_by_command(command),
+            _ => false,
+        }
+    }
+
+    fn is_completed_by_command(&self, command: &Command) -> bool {
+        match (self, command) {
+            (_, Command::Batch { commands, .. }) => commands
+                .iter()
+                .any(|command| self.is_completed_by_command(command)),
+            (TutorialTask::DrawStroke, Command::AddElement { element }) => {
+                matches!(element, ElementType::Stroke(_))
+            }
+            (TutorialTask::Select, Command::SelectElement(_) | Command::ToggleSelection(_)) => true,
+            (TutorialTask::Resize, Command::ResizeElement { .. }) => true,
+            _ => false,
+        }
+    }
+}
+
+/// Progress through the tutorial tasks, in order
+///
+/// Follows the document's command history through the event bus and moves on to
+/// the next task as soon as the current one is done.
+#[derive(Debug)]
+pub struct Tutorial {
+    events: Receiver<EditorEvent>,
+    completed: usize,
+}
+
+impl Tutorial {
+    pub fn new(events: Receiver<EditorEvent>) -> Self {
+        Self {
+            events,
+            completed: 0,
+        }
+    }
+
+    /// Check the events published since the last update, advancing past finished tasks
+    pub fn update(&mut self) {
+        for event in self.events.try_iter() {
+            if let Some(task) = TutorialTask::ALL.get(self.completed) {
+                if task.is_completed_by(&event) {
+                    self.completed += 1;
+                }
+            }
+        }
+    }
+
+    /// The task the user is working on, None once all are done
+    pub fn current_task(&self) -> Option<TutorialTask> {
+        TutorialTask::ALL.get(self.completed).copied()
+    }
+
+    pub fn is_done(&self, task: TutorialTask) -> bool {
+        TutorialTask::ALL
+            .iter()
+            .position(|t| *t == task)
+            .is_some_and(|index| index < self.completed)
+    }
+
+    pub fn is_finished(&self) -> bool {
+        self.current_task().is_none()
+    }
+}
+
+/// The bundled tutorial document, laid out to fill `area`
+///
+/// Badges and the drawing area are locked guides that can't be picked by accident;
+/// only the blue square is there to be selected and resized.
+pub fn tutorial_document(area: Rect) -> DocumentFile {
+    let locked = |mut element: ElementType, name: &str| {
+        let metadata = element.metadata_mut();
+        metadata.name = name.to_string();
+        metadata.set(LOCKED_PROPERTY, "true");
+        element
+    };
+    // Number the elements the way a new document would
+    let ids = EditorModel::new();
+    let id = || ids.reserve_id();
+    let rectangle = |rect: Rect| {
+        vec![
+            rect.left_top(),
+            rect.right_top(),
+            rect.right_bottom(),
+            rect.left_bottom(),
+        ]
+    };
+
+    let inner = area.shrink(MARGIN);
+    let (left, right) = inner.split_left_right_at_fraction(0.5);
+    // Leave room on the left for the first badge
+    let drawing_area = Rect::from_min_max(
+        left.min + Vec2::new(MARGIN * 1.5, 0.0),
+        left.max - Vec2::new(MARGIN / 2.0, 0.0),
+    );
+    let square = Rect::from_center_size(right.center(), Vec2::splat(SQUARE_SIZE));
+
+    let elements = vec![
+        locked(
+            factory::create_shape(id(), rectangle(drawing_area), DRAWING_AREA_COLOR),
+            "Drawing area",
+        ),
+        locked(
+            factory::create_step_badge(
+                id(),
+                left.left_top() + Vec2::splat(MARGIN / 2.0),
+                1,
+                GUIDE_COLOR,
+            ),
+            "Step 1",
+        ),
+        locked(
+            factory::create_step_badge(
+                id(),
+                square.left_top() - Vec2::splat(MARGIN / 2.0),
+                2,
+                GUIDE_COLOR,
+            ),
+            "Step 2",
+        ),
+        {
+            let mut element = factory::create_shape(id(), rectangle(square), SQUARE_COLOR);
+            element.metadata_mut().name = "Blue square".to_string();
+            element
+        },
+    ];
+
+    DocumentFile {
+        metadata: DocumentMetadata {
+            title: "Tutorial".to_string(),
+            ..DocumentMetadata::default()
+        },
+        artboards: vec![Artboard::new("Tutorial", area)],
+        elements,
+        ..DocumentFile::default()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::command::CommandHistory;
+    use crate::widgets::{Edge, TransformHandle};
+    use egui::Pos2;
+
+    #[test]
+    fn test_tutorial_advances_through_tasks_and_skips_locked_guides() {
+        let area = Rect::from_min_size(Pos2::ZERO, Vec2::new(800.0, 600.0));
+        let mut model = EditorModel::new();
+        model.load_document(tutorial_document(area));
+        let mut history = CommandHistory::new();
+        let mut tutorial = Tutorial::new(history.subscribe());
+        assert_eq!(tutorial.current_task(), Some(TutorialTask::DrawStroke));
+
+        // Guides can't be picked; the square can
+        let badge_pos = area.min + Vec2::splat(MARGIN + MARGIN / 2.0);
+        assert!(model.element_at_position(badge_pos).is_none());
+        let square = model.element_at_position(area.right_center() - Vec2::new(200.0, 0.0));
+        let square_id = square.map(|element| element.id()).unwrap();
+
+        // Selecting out of order doesn't count
+        let select = Command::SelectElement(square_id);
+        history.execute(select.clone(), &mut model).unwrap();
+        tutorial.update();
+        assert_eq!(tutorial.current_task(), Some(TutorialTask::DrawStroke));
+
+        let points = vec![Pos2::new(100.0, 100.0), Pos2::new(150.0, 120.0)];
+        let element = factory::create_stroke(model.reserve_id(), points, 2.0, Color32::BLACK);
+        history
+            .execute(Command::AddElement { element }, &mut model)
+            .unwrap();
+        history.execute(select, &mut model).unwrap();
+        let rect = model.find_element_by_id(square_id).unwrap().rect();
+        let resize = Command::ResizeElement {
+            element_id: square_id,
+            _element_type: "shape".to_string(),
+            old_rect: rect,
+            new_rect: rect.expand(10.0),
+            _scaling_handle: TransformHandle::Edge(Edge::Right),
+            _original_image: egui::Image::new((egui::TextureId::default(), Vec2::splat(10.0))),
+        };
+        history.execute(resize, &mut model).unwrap();
+        tutorial.update();
+        assert!(tutorial.is_done(TutorialTask::Resize));
+        assert_eq!(tutorial.current_task(), Some(TutorialTask::Undo));
+
+        history.undo(&mut model).unwrap();
+        tutorial.update();
+        assert!(tutorial.is_finished());
+    }
+}
@@ -0,0 +1,188 @@
+//! Branches of the undo history, and the history as saved with the session
+//!
+//! The history keeps the path to the current state as its undo and redo
+//! stacks. With the undo tree on, steps that were undone and then left behind
+//! for new work are kept as branches forking off that path instead of being
+//! dropped. A branch holds only the steps it doesn't share with the path, and
+//! keeps them deflated since they're seldom gone back to.
+
+use crate::command::{Checkpoint, Command};
+use crate::state::ElementId;
+use base64::Engine as _;
+use serde::de::DeserializeOwned;
+use serde::{Deserialize, Serialize};
+use std::borrow::Cow;
+
+/// How hard branches and saved histories are deflated; they're packed often
+/// enough that the slowest levels aren't worth it
+const PACK_LEVEL: u8 = 6;
+
+/// Undone steps left behind for new work
+#[derive(Clone, Debug)]
+pub struct Branch {
+    /// How many steps of the path it forks from come before it
+    pub fork: usize,
+    /// Label of its first step
+    pub label: &'static str,
+    /// How many steps it holds, not counting the branches forking from it
+    pub len: usize,
+    // The steps, oldest first, as deflated RON
+    packed: Vec<u8>,
+    /// Branches forking from this one, `fork` counting its steps
+    pub branches: Vec<Branch>,
+}
+
+impl Branch {
+    /// Pack `steps`, oldest first, into a branch forking after `fork` steps
+    pub fn new(fork: usize, steps: &[Command], branches: Vec<Branch>) -> Result<Self, String> {
+        let label = steps.first().ok_or("A branch needs a step")?.label();
+        Ok(Self {
+            fork,
+            label,
+            len: steps.len(),
+            packed: pack(steps)?,
+            branches,
+        })
+    }
+
+    /// The branch's steps, oldest first
+    pub fn unpack(&self) -> Result<Vec<Command>, String> {
+        unpack(&self.packed)
+    }
+
+    /// Steps in this branch and the branches forking from it
+    pub fn total_len(&self) -> usize {
+        self.len + self.branches.iter().map(Branch::total_len).sum::<usize>()
+    }
+
+    /// Bytes this branch and the branches forking from it take, packed
+    pub fn total_bytes(&self) -> usize {
+        self.packed.len() + self.branches.iter().map(Branch::total_bytes).sum::<usize>()
+    }
+
+    fn to_session(&self) -> Result<SessionBranch, String> {
+        Ok(SessionBranch {
+            fork: self.fork,
+            steps: self.unpack()?,
+            branches: self
+                .branches
+                .iter()
+                .map(Branch::to_session)
+                .collect::<Result<_, _>>()?,
+        })
+    }
+
+    fn from_session(branch: SessionBranch) -> Result<Self, String> {
+        let branches = branch
+            .branches
+            .into_iter()
+            .map(Branch::from_session)
+            .collect::<Result<_, _>>()?;
+        Self::new(branch.fork, &branch.steps, branches)
+    }
+}
+
+/// Take the branches forking after more than `fork` steps out of `branches`,
+/// rebased onto the steps past `fork`
+pub fn split_off(branches: &mut Vec<Branch>, fork: usize) -> Vec<Branch> {
+    let (past, kept): (Vec<Branch>, Vec<Branch>) = std::mem::take(branches)
+        .into_iter()
+        .partition(|branch| branch.fork > fork);
+    *branches = kept;
+    past.into_iter()
+        .map(|mut branch| {
+            branch.fork -= fork;
+            branch
+        })
+        .collect()
+}
+
+/// Rebase `branches` past the `count` oldest steps of the path, which are
+/// gone; branches forking from among those steps go with them
+pub fn drop_oldest(branches: &mut Vec<Branch>, count: usize) {
+    branches.retain(|branch| branch.fork >= count);
+    for branch in branches {
+        branch.fork -= count;
+    }
+}
+
+/// A branch as saved with the session
+#[derive(Serialize, Deserialize)]
+struct SessionBranch {
+    fork: usize,
+    steps: Vec<Command>,
+    branches: Vec<SessionBranch>,
+}
+
+/// The undo history as saved with the session
+///
+/// The ids to hand out next are kept too, so redone steps never add elements
+/// with ids the restored document has since given out again.
+#[derive(Serialize, Deserialize)]
+pub struct SessionHistory<'a> {
+    pub undo_stack: Cow<'a, [Command]>,
+    pub redo_stack: Cow<'a, [Command]>,
+    branches: Vec<SessionBranch>,
+    #[serde(default)]
+    pub checkpoints: Cow<'a, [Checkpoint]>,
+    pub next_element_id: ElementId,
+    pub next_generated_id: usize,
+}
+
+impl<'a> SessionHistory<'a> {
+    pub fn new(
+        undo_stack: &'a [Command],
+        redo_stack: &'a [Command],
+        branches: &[Branch],
+        checkpoints: &'a [Checkpoint],
+        next_element_id: ElementId,
+        next_generated_id: usize,
+    ) -> Result<Self, String> {
+        Ok(Self {
+            undo_stack: Cow::Borrowed(undo_stack),
+            redo_stack: Cow::Borrowed(redo_stack),
+            branches: branches
+                .iter()
+                .map(Branch::to_session)
+                .collect::<Result<_, _>>()?,
+            checkpoints: Cow::Borrowed(checkpoints),
+            next_element_id,
+            next_generated_id,
+        })
+    }
+
+    /// The branches, packed again
+    pub fn branches(&mut self) -> Result<Vec<Branch>, String> {
+        std::mem::take(&mut self.branches)
+            .into_iter()
+            .map(Branch::from_session)
+            .collect()
+    }
+
+    /// Pack the history into text to store with the session
+    pub fn encode(&self) -> Result<String, String> {
+        Ok(base64::engine::general_purpose::STANDARD.encode(pack(self)?))
+    }
+
+    /// Read a history packed by [`Self::encode`]
+    pub fn decode(text: &str) -> Result<SessionHistory<'static>, String> {
+        let packed = base64::engine::general_purpose::STANDARD
+            .decode(text)
+            .map_err(|err| err.to_string())?;
+        unpack(&packed)
+    }
+}
+
+fn pack<T: Serialize + ?Sized>(value: &T) -> Result<Vec<u8>, String> {
+    let text = ron::ser::to_string(value).map_err(|err| err.to_string())?;
+    Ok(miniz_oxide::deflate::compress_to_vec(
+        text.as_bytes(),
+        PACK_LEVEL,
+    ))
+}
+
+fn unpack<T: DeserializeOwned>(packed: &[u8]) -> Result<T, String> {
+    let text = miniz_oxide::inflate::decompress_to_vec(packed).map_err(|err| err.to_string())?;
+    let text = String::from_utf8(text).map_err(|err| err.to_string())?;
+    ron::from_str(&text).map_err(|err| err.to_string())
+}
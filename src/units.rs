@@ -0,0 +1,157 @@
+//! Document units and conversions between them
+//!
+//! Element geometry is always stored in pixels; units only change how lengths
+//! are shown and entered.
+
+use serde::{Deserialize, Serialize};
+
+/// Resolution used when a document doesn't set one
+pub const DEFAULT_DPI: f32 = 96.0;
+pub const MM_PER_INCH: f32 = 25.4;
+
+/// Length unit a document is measured in
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq, Serialize, Deserialize)]
+pub enum Unit {
+    #[default]
+    Px,
+    Mm,
+    In,
+}
+
+impl Unit {
+    pub const ALL: [Unit; 3] = [Unit::Px, Unit::Mm, Unit::In];
+
+    pub fn label(self) -> &'static str {
+        match self {
+            Unit::Px => "Pixels",
+            Unit::Mm => "Millimeters",
+            Unit::In => "Inches",
+        }
+    }
+
+    /// Short suffix shown after values
+    pub fn suffix(self) -> &'static str {
+        match self {
+            Unit::Px => "px",
+            Unit::Mm => "mm",
+            Unit::In => "in",
+        }
+    }
+
+    /// Decimal places worth showing for values in this unit
+    pub fn decimals(self) -> usize {
+        match self {
+            Unit::Px | Unit::Mm => 1,
+            Unit::In => 2,
+        }
+    }
+}
+
+pub fn px_to_in(px: f32, dpi: f32) -> f32 {
+    px / dpi
+}
+
+pub fn in_to_px(inches: f32, dpi: f32) -> f32 {
+    inches * dpi
+}
+
+pub fn px_to_mm(px: f32, dpi: f32) -> f32 {
+    px_to_in(px, dpi) * MM_PER_INCH
+}
+
+pub fn mm_to_px(mm: f32, dpi: f32) -> f32 {
+    in_to_px(mm / MM_PER_INCH, dpi)
+}
+
+/// Per-document unit settings: the display unit and how many pixels make an inch
+#[derive(Clone, Copy, Debug, PartialEq, Serialize, Deserialize)]
+#[serde(default)]
+pub struct DocumentUnits {
+    pub unit: Unit,
+    pub dpi: f32,
+}
+
+impl Default for DocumentUnits {
+    fn default() -> Self {
+        Self {
+            unit: Unit::Px,
+            dpi: DEFAULT_DPI,
+        }
+    }
+}
+
+impl DocumentUnits {
+    /// Convert a length in pixels to the document unit
+    pub fn from_px(&self, px: f32) -> f32 {
+        match self.unit {
+            Unit::Px => px,
+            Unit::Mm => px_to_mm(px, self.dpi),
+            Unit::In => px_to_in(px, self.dpi),
+        }
+    }
+
+    /// Convert a length in the document unit to pixels
+    pub fn to_px(&self, value: f32) -> f32 {
+        match self.unit {
+            Unit::Px => value,
+            Unit::Mm => mm_to_px(value, self.dpi),
+            Unit::In => in_to_px(value, self.dpi),
+        }
+    }
+
+    /// Format a length in pixels as a value in the document unit, e.g. "12.7 mm"
+    pub fn format(&self, px: f32) -> String {
+        format!(
+            "{:.*} {}",
+            self.unit.decimals(),
+            self.from_px(px),
+            self.unit.suffix()
+        )
+    }
+
+    /// Format a size in pixels, e.g. "210.0 × 297.0 mm"
+    pub fn format_size(&self, size: egui::Vec2) -> String {
+        let decimals = self.unit.decimals();
+        format!(
+            "{:.*} × {:.*} {}",
+            decimals,
+            self.from_px(size.x),
+            decimals,
+            self.from_px(size.y),
+            self.unit.suffix()
+        )
+    }
+
+    /// Format a position in pixels, e.g. "10.0, 20.0 mm"
+    pub fn format_pos(&self, pos: egui::Pos2) -> String {
+        let decimals = self.unit.decimals();
+        format!(
+            "{:.*}, {:.*} {}",
+            decimals,
+            self.from_px(pos.x),
+            decimals,
+            self.from_px(pos.y),
+            self.unit.suffix()
+        )
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_conversions_roundtrip() {
+        assert_eq!(px_to_in(192.0, 96.0), 2.0);
+        assert!((px_to_mm(96.0, 96.0) - MM_PER_INCH).abs() < 1e-4);
+        assert!((mm_to_px(25.4, 300.0) - 300.0).abs() < 1e-3);
+
+        let units = DocumentUnits {
+            unit: Unit::Mm,
+            dpi: 300.0,
+        };
+        assert!((units.to_px(units.from_px(123.0)) - 123.0).abs() < 1e-3);
+        assert_eq!(units.format(300.0), "25.4 mm");
+        assert_eq!(DocumentUnits::default().format(12.44), "12.4 px");
+    }
+}
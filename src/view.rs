@@ -0,0 +1,514 @@
+//! Zooming, scrolling and rotating the canvas
+//!
+//! The canvas is drawn in its own egui layer, which the view transforms from
+//! document to screen coordinates. Elements, tools and hit tests all stay in
+//! document coordinates; egui maps the pointer into them for widgets on the
+//! layer, and the central panel does the same for the positions it hands to
+//! tools.
+//!
+//! egui can only scale and move a layer, so rotating the view turns what's
+//! drawn on the canvas about the document origin before the layer's transform
+//! applies. Positions on the layer then differ from document positions by
+//! that turn; [`document_to_layer`] and [`layer_to_document`] go between them.
+
+use egui::emath::{Rot2, TSTransform};
+use egui::{Context, Id, LayerId, Order, Pos2, Rect, Shape, Vec2};
+
+/// Zoom levels stepped through by zooming in and out
+pub const ZOOM_PRESETS: [f32; 13] = [
+    0.05, 0.1, 0.25, 0.5, 0.75, 1.0, 1.5, 2.0, 3.0, 4.0, 8.0, 16.0, 32.0,
+];
+pub const MIN_ZOOM: f32 = ZOOM_PRESETS[0];
+pub const MAX_ZOOM: f32 = ZOOM_PRESETS[ZOOM_PRESETS.len() - 1];
+/// Zoom from which document pixels are outlined and positions can snap to them
+pub const PIXEL_GRID_ZOOM: f32 = 8.0;
+/// Screen space left around what zooming to fit shows
+const FIT_MARGIN: f32 = 24.0;
+/// How long zooming to a preset or to fit takes to settle
+const ANIMATION_SECS: f64 = 0.15;
+/// Time between repaints while the view is moving
+const ANIMATION_FRAME: std::time::Duration = std::time::Duration::from_millis(16);
+
+/// The layer the canvas is drawn on
+pub fn canvas_layer() -> LayerId {
+    LayerId::new(Order::Background, Id::new("canvas"))
+}
+
+/// Where the view's rotation is kept in egui's memory for the frame
+fn rotation_id() -> Id {
+    Id::new("canvas_rotation")
+}
+
+/// Where the overlays drawn this frame are kept, to be turned with the canvas
+fn overlays_id() -> Id {
+    Id::new("canvas_overlays")
+}
+
+/// A painter above the canvas, zoomed, scrolled and rotated along with it
+pub fn canvas_overlay_painter(ctx: &Context, id: impl Into<Id>) -> egui::Painter {
+    let layer = LayerId::new(Order::Foreground, id.into());
+    ctx.set_transform_layer(
+        layer,
+        ctx.layer_transform_to_global(canvas_layer())
+            .unwrap_or_default(),
+    );
+    ctx.data_mut(|data| {
+        let overlays = data.get_temp_mut_or_default::<Vec<LayerId>>(overlays_id());
+        if !overlays.contains(&layer) {
+            overlays.push(layer);
+        }
+    });
+    ctx.layer_painter(layer)
+}
+
+/// The view's rotation as of its last `update`, clockwise in radians
+pub fn canvas_rotation(ctx: &Context) -> f32 {
+    ctx.data(|data| data.get_temp(rotation_id()))
+        .unwrap_or_default()
+}
+
+/// Where a document position lies on the canvas layer, turned by the view
+pub fn document_to_layer(ctx: &Context, pos: Pos2) -> Pos2 {
+    turn(pos, canvas_rotation(ctx))
+}
+
+/// The document position under a position on the canvas layer, such as
+/// where a widget on it was dragged to
+pub fn layer_to_document(ctx: &Context, pos: Pos2) -> Pos2 {
+    turn(pos, -canvas_rotation(ctx))
+}
+
+/// The middle of the document pixel `pos` lies in
+///
+/// Strokes through pixel centers cover whole pixels, and drags between them
+/// move things by whole pixels.
+pub fn snap_to_pixel(pos: Pos2) -> Pos2 {
+    pos.floor() + Vec2::splat(0.5)
+}
+
+/// `pos` turned clockwise by `rotation` about the document origin
+fn turn(pos: Pos2, rotation: f32) -> Pos2 {
+    if rotation == 0.0 {
+        return pos;
+    }
+    (Rot2::from_angle(rotation) * pos.to_vec2()).to_pos2()
+}
+
+/// The bounding rect of `rect`'s corners put through `map`
+fn bounding_rect(rect: Rect, map: impl Fn(Pos2) -> Pos2) -> Rect {
+    Rect::from_points(&[
+        map(rect.left_top()),
+        map(rect.right_top()),
+        map(rect.right_bottom()),
+        map(rect.left_bottom()),
+    ])
+}
+
+/// `rotation` brought within half a turn either way
+fn normalize_rotation(rotation: f32) -> f32 {
+    let rotation = rotation.rem_euclid(std::f32::consts::TAU);
+    if rotation > std::f32::consts::PI {
+        rotation - std::f32::consts::TAU
+    } else {
+        rotation
+    }
+}
+
+/// Zoom, scroll position and rotation of the canvas, moving smoothly to where
+/// it was last sent
+#[derive(Clone, Debug)]
+pub struct View {
+    /// Canvas layer to screen, as of the last `update`
+    transform: TSTransform,
+    /// Clockwise turn of the document about its origin, in radians, within
+    /// half a turn either way; changes apply straight away
+    rotation: f32,
+    animation: Option<Animation>,
+    /// Ease into place rather than jumping
+    animated: bool,
+    /// The pan key is held, so dragging scrolls the view rather than reaching
+    /// the tools
+    panning: bool,
+}
+
+#[derive(Clone, Copy, Debug)]
+struct Animation {
+    from: TSTransform,
+    to: TSTransform,
+    /// When the animation started; None until the next `update`
+    start: Option<f64>,
+}
+
+impl Default for View {
+    fn default() -> Self {
+        Self {
+            transform: TSTransform::IDENTITY,
+            rotation: 0.0,
+            animation: None,
+            animated: true,
+            panning: false,
+        }
+    }
+}
+
+impl View {
+    /// Canvas layer to screen coordinates, as currently shown; the same as
+    /// document to screen unless the view is rotated
+    pub fn transform(&self) -> TSTransform {
+        self.transform
+    }
+
+    /// How far the view is turned, clockwise in radians
+    pub fn rotation(&self) -> f32 {
+        self.rotation
+    }
+
+    /// Where the view is headed: the end of the animation, if one is running
+    pub fn target(&self) -> TSTransform {
+        self.animation
+            .map_or(self.transform, |animation| animation.to)
+    }
+
+    /// The zoom level the view is headed for, 1.0 being actual size
+    pub fn zoom(&self) -> f32 {
+        self.target().scaling
+    }
+
+    pub fn screen_to_document(&self, pos: Pos2) -> Pos2 {
+        turn(self.transform.inverse() * pos, -self.rotation)
+    }
+
+    pub fn document_to_screen(&self, pos: Pos2) -> Pos2 {
+        self.transform * turn(pos, self.rotation)
+    }
+
+    /// The document rect covering all of `rect` on screen
+    pub fn screen_rect_to_document(&self, rect: Rect) -> Rect {
+        bounding_rect(rect, |pos| self.screen_to_document(pos))
+    }
+
+    /// The canvas layer rect covering all of `rect` in the document
+    fn document_rect_to_layer(&self, rect: Rect) -> Rect {
+        bounding_rect(rect, |pos| turn(pos, self.rotation))
+    }
+
+    /// Whether changes to the view ease into place rather than jumping
+    pub fn set_animated(&mut self, animated: bool) {
+        self.animated = animated;
+    }
+
+    /// Whether dragging on the canvas scrolls the view, keeping the pointer
+    /// from the tools
+    pub fn is_panning(&self) -> bool {
+        self.panning
+    }
+
+    pub fn set_panning(&mut self, panning: bool) {
+        self.panning = panning;
+    }
+
+    /// Advance the animation to the frame's time and apply the view to the
+    /// canvas layer, asking for the next frame while the view is still moving
+    pub fn update(&mut self, ctx: &Context) {
+        if let Some(animation) = &mut self.animation {
+            let now = ctx.input(|i| i.time);
+            let start = *animation.start.get_or_insert(now);
+            let t = ((now - start) / ANIMATION_SECS).clamp(0.0, 1.0) as f32;
+            self.transform = lerp(animation.from, animation.to, ease_out(t));
+            if t >= 1.0 {
+                self.animation = None;
+            } else {
+                ctx.request_repaint_after(ANIMATION_FRAME);
+            }
+        }
+        ctx.set_transform_layer(canvas_layer(), self.transform);
+        ctx.data_mut(|data| data.insert_temp(rotation_id(), self.rotation));
+    }
+
+    /// Turn what was drawn on the canvas and its overlays this frame by the
+    /// view's rotation, clipped to `screen`
+    ///
+    /// Call once everything has been drawn. Shapes are tessellated at the
+    /// zoom they're shown at, so they keep sharp edges once turned.
+    pub fn turn_drawn(&self, ctx: &Context, screen: Rect) {
+        let overlays = ctx
+            .data_mut(|data| data.remove_temp::<Vec<LayerId>>(overlays_id()))
+            .unwrap_or_default();
+        if self.rotation == 0.0 {
+            return;
+        }
+
+        let clip_rect = self.transform.inverse() * screen;
+        let pixels_per_point = ctx.pixels_per_point() * self.transform.scaling;
+        for layer in std::iter::once(canvas_layer()).chain(overlays) {
+            let mut drawn: Vec<Shape> = ctx.graphics_mut(|graphics| {
+                let list = graphics.entry(layer);
+                (0..list.next_idx().0)
+                    .map(|idx| {
+                        let mut shape = Shape::Noop;
+                        list.mutate_shape(egui::layers::ShapeIdx(idx), |clipped| {
+                            std::mem::swap(&mut shape, &mut clipped.shape)
+                        });
+                        shape
+                    })
+                    .collect()
+            });
+            crate::tessellation::rotate_shapes(
+                ctx,
+                &mut drawn,
+                Pos2::ZERO,
+                self.rotation,
+                pixels_per_point,
+            );
+            ctx.graphics_mut(|graphics| {
+                let list = graphics.entry(layer);
+                for (idx, shape) in drawn.into_iter().enumerate() {
+                    list.set(egui::layers::ShapeIdx(idx), clip_rect, shape);
+                }
+            });
+        }
+    }
+
+    /// Move to `target` straight away, as when scrolling with the wheel
+    pub fn set(&mut self, target: TSTransform) {
+        self.transform = target;
+        self.animation = None;
+    }
+
+    /// Move smoothly to `target` from the next frame on, or straight there
+    /// with animation turned off
+    pub fn animate_to(&mut self, target: TSTransform) {
+        if !self.animated {
+            self.set(target);
+            return;
+        }
+        self.animation = Some(Animation {
+            from: self.transform,
+            to: target,
+            start: None,
+        });
+    }
+
+    /// Turn the view clockwise by `delta` radians, keeping the document point
+    /// under `anchor` on screen in place
+    pub fn rotate_by(&mut self, delta: f32, anchor: Pos2) {
+        if delta == 0.0 {
+            return;
+        }
+        let target = self.target();
+        let document_anchor = turn(target.inverse() * anchor, -self.rotation);
+        self.rotation = normalize_rotation(self.rotation + delta);
+        let layer_anchor = turn(document_anchor, self.rotation);
+        self.set(TSTransform::new(
+            anchor.to_vec2() - layer_anchor.to_vec2() * target.scaling,
+            target.scaling,
+        ));
+    }
+
+    /// Turn the view to `rotation` radians clockwise around `anchor`
+    pub fn set_rotation(&mut self, rotation: f32, anchor: Pos2) {
+        self.rotate_by(normalize_rotation(rotation) - self.rotation, anchor);
+    }
+
+    /// Scroll the view by `delta` screen points
+    pub fn pan_by(&mut self, delta: Vec2) {
+        let mut target = self.target();
+        target.translation += delta;
+        self.set(target);
+    }
+
+    /// Zoom by `factor`, keeping the document point under `anchor` on screen in place
+    pub fn zoom_by(&mut self, factor: f32, anchor: Pos2) {
+        let target = self.target();
+        self.set(zoom_around(target, target.scaling * factor, anchor));
+    }
+
+    /// Zoom smoothly to `zoom`, keeping the point under `anchor` on screen in place
+    pub fn zoom_to(&mut self, zoom: f32, anchor: Pos2) {
+        self.animate_to(zoom_around(self.target(), zoom, anchor));
+    }
+
+    /// Zoom to the next preset in (`steps` > 0) or out (`steps` < 0) around `anchor`
+    pub fn zoom_step(&mut self, steps: i32, anchor: Pos2) {
+        let mut zoom = self.zoom();
+        for _ in 0..steps.unsigned_abs() {
+            zoom = next_preset(zoom, steps > 0);
+        }
+        self.zoom_to(zoom, anchor);
+    }
+
+    /// Zoom and scroll smoothly so `content` fills `screen`, centered
+    pub fn zoom_to_rect(&mut self, content: Rect, screen: Rect) {
+        self.animate_to(fit_transform(self.document_rect_to_layer(content), screen));
+    }
+
+    /// Scroll smoothly to bring `content` into `screen` if it isn't all showing,
+    /// zooming out only if it doesn't fit at the current zoom
+    pub fn reveal(&mut self, content: Rect, screen: Rect) {
+        let target = self.target();
+        let shown = target.inverse() * screen.shrink(FIT_MARGIN);
+        let content = self.document_rect_to_layer(content);
+        if shown.contains_rect(content) {
+            return;
+        }
+        if content.width() > shown.width() || content.height() > shown.height() {
+            self.animate_to(fit_transform(content, screen));
+            return;
+        }
+        let offset = screen.center().to_vec2() - (target * content.center()).to_vec2();
+        self.animate_to(TSTransform::new(
+            target.translation + offset,
+            target.scaling,
+        ));
+    }
+}
+
+/// The preset after `zoom` going in, or before it going out
+pub fn next_preset(zoom: f32, zoom_in: bool) -> f32 {
+    // Allow for rounding so a zoom sitting on a preset moves off it
+    let tolerance = zoom * 0.01;
+    if zoom_in {
+        ZOOM_PRESETS
+            .into_iter()
+            .find(|&preset| preset > zoom + tolerance)
+            .unwrap_or(MAX_ZOOM)
+    } else {
+        ZOOM_PRESETS
+            .into_iter()
+            .rev()
+            .find(|&preset| preset < zoom - tolerance)
+            .unwrap_or(MIN_ZOOM)
+    }
+}
+
+/// The view showing all of `content` centered in `screen`, with a margin
+pub fn fit_transform(content: Rect, screen: Rect) -> TSTransform {
+    let available = (screen.size() - Vec2::splat(2.0 * FIT_MARGIN)).max(Vec2::splat(1.0));
+    let size = content.size().max(Vec2::splat(1.0));
+    let zoom = (available / size).min_elem().clamp(MIN_ZOOM, MAX_ZOOM);
+    TSTransform::new(
+        screen.center().to_vec2() - content.center().to_vec2() * zoom,
+        zoom,
+    )
+}
+
+/// `transform` changed to `zoom`, with the point under `anchor` staying put
+fn zoom_around(transform: TSTransform, zoom: f32, anchor: Pos2) -> TSTransform {
+    let zoom = zoom.clamp(MIN_ZOOM, MAX_ZOOM);
+    let document_anchor = transform.inverse() * anchor;
+    TSTransform::new(anchor.to_vec2() - document_anchor.to_vec2() * zoom, zoom)
+}
+
+fn lerp(from: TSTransform, to: TSTransform, t: f32) -> TSTransform {
+    // Interpolate the zoom geometrically so zooming in and out feel alike, and
+    // move the translation along so the blend keeps steady on screen
+    let zoom = from.scaling * (to.scaling / from.scaling).powf(t);
+    let progress = if (to.scaling - from.scaling).abs() > f32::EPSILON {
+        (zoom - from.scaling) / (to.scaling - from.scaling)
+    } else {
+        t
+    };
+    TSTransform::new(
+        from.translation + (to.translation - from.translation) * progress,
+        zoom,
+    )
+}
+
+fn ease_out(t: f32) -> f32 {
+    1.0 - (1.0 - t).powi(3)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use egui::{pos2, vec2};
+
+    #[test]
+    fn test_zoom_presets_fit_and_anchor() {
+        assert_eq!(next_preset(1.0, true), 1.5);
+        assert_eq!(next_preset(1.0, false), 0.75);
+        assert_eq!(next_preset(1.2, false), 1.0);
+        assert_eq!(next_preset(MAX_ZOOM, true), MAX_ZOOM);
+
+        let screen = Rect::from_min_size(pos2(100.0, 50.0), vec2(848.0, 648.0));
+        let content = Rect::from_min_size(pos2(-200.0, 0.0), vec2(400.0, 200.0));
+        let fit = fit_transform(content, screen);
+        assert_eq!(fit.scaling, 2.0);
+        assert_eq!(fit * content.center(), screen.center());
+
+        // Zooming keeps the document point under the anchor where it was
+        let mut view = View::default();
+        view.set(fit);
+        let anchor = pos2(300.0, 200.0);
+        let under = view.screen_to_document(anchor);
+        view.zoom_by(2.0, anchor);
+        assert_eq!(view.zoom(), 4.0);
+        assert!((view.transform() * under - anchor).length() < 0.001);
+
+        // Showing a change off screen scrolls to it without zooming
+        let off_screen =
+            Rect::from_min_size(view.screen_to_document(pos2(2000.0, 0.0)), vec2(10.0, 10.0));
+        view.set_animated(false);
+        view.reveal(off_screen, screen);
+        assert_eq!(view.zoom(), 4.0);
+        assert_eq!(view.transform() * off_screen.center(), screen.center());
+        let shown = view.transform();
+        view.reveal(off_screen.translate(vec2(5.0, 0.0)), screen);
+        assert_eq!(view.transform(), shown);
+
+        // Animations start on the next frame and land exactly on their target
+        let ctx = Context::default();
+        view.set_animated(true);
+        view.zoom_step(-1, anchor);
+        assert_eq!(view.transform(), shown);
+        for time in [10.0, 10.05, 10.0 + ANIMATION_SECS] {
+            let input = egui::RawInput {
+                time: Some(time),
+                ..Default::default()
+            };
+            let _ = ctx.run(input, |ctx| view.update(ctx));
+        }
+        assert_eq!(view.zoom(), 3.0);
+        assert_eq!(view.transform().scaling, 3.0);
+    }
+
+    #[test]
+    fn test_positions_snap_to_the_middle_of_their_pixel() {
+        assert_eq!(snap_to_pixel(pos2(3.2, 7.9)), pos2(3.5, 7.5));
+        assert_eq!(snap_to_pixel(pos2(-0.25, 4.0)), pos2(-0.5, 4.5));
+        // Drags between snapped positions move by whole pixels
+        let delta = snap_to_pixel(pos2(12.7, 3.1)) - snap_to_pixel(pos2(2.2, 9.9));
+        assert_eq!(delta, vec2(10.0, -6.0));
+    }
+
+    #[test]
+    fn test_rotation_turns_the_view_around_its_anchor() {
+        let mut view = View::default();
+        view.set(TSTransform::new(vec2(40.0, -30.0), 2.0));
+        let anchor = pos2(400.0, 300.0);
+        let under = view.screen_to_document(anchor);
+        let elsewhere = pos2(10.0, 20.0);
+
+        view.rotate_by(std::f32::consts::FRAC_PI_2, anchor);
+        assert!((view.document_to_screen(under) - anchor).length() < 0.001);
+        let doc = view.screen_to_document(elsewhere);
+        assert!((view.document_to_screen(doc) - elsewhere).length() < 0.001);
+        // A quarter turn clockwise takes the document's right to screen down
+        let right = view.document_to_screen(under + vec2(10.0, 0.0)) - anchor;
+        assert!((right - vec2(0.0, 20.0)).length() < 0.001);
+
+        // Zooming and scrolling work the same on a turned view
+        view.zoom_by(2.0, elsewhere);
+        assert!((view.document_to_screen(doc) - elsewhere).length() < 0.01);
+        view.pan_by(vec2(5.0, 0.0));
+        assert!((view.document_to_screen(doc) - elsewhere - vec2(5.0, 0.0)).length() < 0.01);
+
+        // Turning past half a turn wraps around, and resetting undoes it all
+        view.rotate_by(std::f32::consts::PI, anchor);
+        assert!((view.rotation() + std::f32::consts::FRAC_PI_2).abs() < 0.0001);
+        let under = view.screen_to_document(anchor);
+        view.set_rotation(0.0, anchor);
+        assert_eq!(view.rotation(), 0.0);
+        assert!((view.transform() * under - anchor).length() < 0.01);
+    }
+}
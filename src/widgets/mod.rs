@@ -1,3 +1,9 @@
-pub mod resize_handle;
+pub mod pressure_curve;
+pub mod toasts;
+pub mod transform_handles;
 
-pub use resize_handle::{Corner, ResizeHandle};
+pub use pressure_curve::pressure_curve_editor;
+pub use toasts::{ToastLevel, Toasts};
+pub use transform_handles::{
+    Corner, Edge, TransformHandle, TransformHandleStyle, TransformHandles,
+};
@@ -0,0 +1,130 @@
+//! Editor for a pressure response curve: a square graph with the curve and its
+//! points, which can be dragged, added and removed
+
+use crate::pressure::{MIN_POINT_SPACING, PressureCurve};
+use egui::{Color32, Pos2, Rect, Sense, Stroke, Vec2};
+
+/// Side of the graph
+const GRAPH_SIZE: f32 = 140.0;
+/// Radius of the points, and how close the pointer has to be to grab one
+const POINT_RADIUS: f32 = 4.0;
+const GRAB_RADIUS: f32 = 8.0;
+/// Segments the curve is drawn with
+const CURVE_SEGMENTS: usize = 48;
+
+/// Edit `curve` in a graph of pressure (across) against its factor (up)
+///
+/// Dragging a point moves it, double-clicking the graph adds one and
+/// right-clicking a point removes it; the end points only move up and down.
+/// The response is marked changed when the curve changes.
+pub fn pressure_curve_editor(ui: &mut egui::Ui, curve: &mut PressureCurve) -> egui::Response {
+    let (rect, mut response) =
+        ui.allocate_exact_size(Vec2::splat(GRAPH_SIZE), Sense::click_and_drag());
+    let to_screen = |[x, y]: [f32; 2]| rect.lerp_inside(Vec2::new(x, 1.0 - y));
+    let from_screen = |pos: Pos2| {
+        let t = (pos - rect.min) / rect.size();
+        [t.x.clamp(0.0, 1.0), (1.0 - t.y).clamp(0.0, 1.0)]
+    };
+
+    let mut points = curve.points().to_vec();
+    let dragged_id = response.id.with("dragged_point");
+    let nearest = response.hover_pos().and_then(|pos| {
+        points
+            .iter()
+            .map(|&point| to_screen(point).distance(pos))
+            .enumerate()
+            .filter(|(_, distance)| *distance <= GRAB_RADIUS)
+            .min_by(|a, b| a.1.total_cmp(&b.1))
+            .map(|(index, _)| index)
+    });
+
+    let mut changed = false;
+    if response.drag_started() {
+        ui.data_mut(|data| data.insert_temp(dragged_id, nearest));
+    }
+    let dragged = ui
+        .data(|data| data.get_temp::<Option<usize>>(dragged_id))
+        .flatten();
+    if let (Some(index), Some(pos)) = (dragged, response.interact_pointer_pos()) {
+        if response.dragged() && index < points.len() {
+            let [mut x, y] = from_screen(pos);
+            let last = points.len() - 1;
+            x = match index {
+                0 => 0.0,
+                _ if index == last => 1.0,
+                _ => x.clamp(
+                    points[index - 1][0] + MIN_POINT_SPACING,
+                    points[index + 1][0] - MIN_POINT_SPACING,
+                ),
+            };
+            changed |= points[index] != [x, y];
+            points[index] = [x, y];
+        }
+    }
+    if response.drag_stopped() {
+        ui.data_mut(|data| data.remove::<Option<usize>>(dragged_id));
+    }
+
+    if response.double_clicked() && nearest.is_none() {
+        if let Some(pos) = response.interact_pointer_pos() {
+            points.push(from_screen(pos));
+            changed = true;
+        }
+    }
+    if response.secondary_clicked() {
+        if let Some(index) = nearest.filter(|&index| index > 0 && index < points.len() - 1) {
+            points.remove(index);
+            changed = true;
+        }
+    }
+    if changed {
+        *curve = PressureCurve::new(points);
+        response.mark_changed();
+    }
+
+    paint_graph(ui, rect, curve, nearest.or(dragged), to_screen);
+    response.on_hover_text(
+        "Drag points to shape the curve. Double-click to add a point, right-click to remove one",
+    )
+}
+
+fn paint_graph(
+    ui: &egui::Ui,
+    rect: Rect,
+    curve: &PressureCurve,
+    highlighted: Option<usize>,
+    to_screen: impl Fn([f32; 2]) -> Pos2,
+) {
+    let visuals = ui.visuals();
+    let painter = ui.painter_at(rect.expand(POINT_RADIUS));
+    painter.rect_filled(rect, 2.0, visuals.extreme_bg_color);
+    let grid = Stroke::new(1.0, visuals.faint_bg_color);
+    for step in 1..4 {
+        let t = step as f32 / 4.0;
+        painter.vline(rect.lerp_inside(Vec2::new(t, 0.0)).x, rect.y_range(), grid);
+        painter.hline(rect.x_range(), rect.lerp_inside(Vec2::new(0.0, t)).y, grid);
+    }
+    painter.rect_stroke(rect, 2.0, visuals.widgets.noninteractive.bg_stroke);
+
+    let line: Vec<Pos2> = (0..=CURVE_SEGMENTS)
+        .map(|step| {
+            let x = step as f32 / CURVE_SEGMENTS as f32;
+            to_screen([x, curve.eval(x)])
+        })
+        .collect();
+    painter.line(line, Stroke::new(2.0, visuals.selection.bg_fill));
+
+    for (index, &point) in curve.points().iter().enumerate() {
+        let fill = if highlighted == Some(index) {
+            visuals.selection.stroke.color
+        } else {
+            Color32::WHITE
+        };
+        painter.circle(
+            to_screen(point),
+            POINT_RADIUS,
+            fill,
+            Stroke::new(1.0, visuals.widgets.noninteractive.fg_stroke.color),
+        );
+    }
+}
@@ -0,0 +1,177 @@
+//! Short messages that pop up over the canvas and go away on their own
+//!
+//! Toasts tell the user how an action went (a failed command, a saved file)
+//! without a dialog to dismiss. Errors stay up longer than other messages, and
+//! clicking a toast dismisses it early.
+
+use egui::Color32;
+use std::collections::VecDeque;
+use std::time::Duration;
+use web_time::Instant;
+
+/// How long info and success toasts stay up
+const MESSAGE_DURATION: Duration = Duration::from_secs(3);
+/// How long error toasts stay up
+const ERROR_DURATION: Duration = Duration::from_secs(6);
+/// Most toasts shown at once; older ones make room for new ones
+const MAX_TOASTS: usize = 5;
+/// Gap between the toasts and the window corner
+const TOAST_MARGIN: f32 = 12.0;
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum ToastLevel {
+    Info,
+    Success,
+    Error,
+}
+
+impl ToastLevel {
+    fn color(self) -> Color32 {
+        match self {
+            ToastLevel::Info => Color32::from_rgb(90, 170, 255),
+            ToastLevel::Success => Color32::from_rgb(100, 200, 120),
+            ToastLevel::Error => Color32::from_rgb(235, 80, 80),
+        }
+    }
+
+    fn icon(self) -> &'static str {
+        match self {
+            ToastLevel::Info => "ℹ",
+            ToastLevel::Success => "✔",
+            ToastLevel::Error => "⚠",
+        }
+    }
+
+    fn duration(self) -> Duration {
+        match self {
+            ToastLevel::Info | ToastLevel::Success => MESSAGE_DURATION,
+            ToastLevel::Error => ERROR_DURATION,
+        }
+    }
+}
+
+#[derive(Clone, Debug)]
+struct Toast {
+    level: ToastLevel,
+    message: String,
+    expires_at: Instant,
+}
+
+/// The toasts currently up, oldest first
+#[derive(Debug, Default)]
+pub struct Toasts {
+    toasts: VecDeque<Toast>,
+}
+
+impl Toasts {
+    pub fn info(&mut self, message: impl Into<String>) {
+        self.add(ToastLevel::Info, message.into());
+    }
+
+    pub fn success(&mut self, message: impl Into<String>) {
+        self.add(ToastLevel::Success, message.into());
+    }
+
+    pub fn error(&mut self, message: impl Into<String>) {
+        self.add(ToastLevel::Error, message.into());
+    }
+
+    /// Show a message; one that's already up is moved to the end and stays longer
+    /// rather than being shown twice
+    pub fn add(&mut self, level: ToastLevel, message: String) {
+        self.toasts
+            .retain(|toast| toast.level != level || toast.message != message);
+        if self.toasts.len() == MAX_TOASTS {
+            self.toasts.pop_front();
+        }
+        self.toasts.push_back(Toast {
+            level,
+            message,
+            expires_at: Instant::now() + level.duration(),
+        });
+    }
+
+    /// The messages up, oldest first
+    pub fn messages(&self) -> impl Iterator<Item = (ToastLevel, &str)> {
+        self.toasts
+            .iter()
+            .map(|toast| (toast.level, toast.message.as_str()))
+    }
+
+    fn remove_expired(&mut self, now: Instant) {
+        self.toasts.retain(|toast| toast.expires_at > now);
+    }
+
+    /// Draw the toasts in the bottom right corner, dropping expired ones
+    pub fn show(&mut self, ctx: &egui::Context) {
+        let now = Instant::now();
+        self.remove_expired(now);
+        let Some(next_expiry) = self.toasts.iter().map(|toast| toast.expires_at).min() else {
+            return;
+        };
+        // Wake up to take the next toast down even if nothing else happens
+        ctx.request_repaint_after(next_expiry - now);
+
+        let mut dismissed = None;
+        egui::Area::new(egui::Id::new("toasts"))
+            .anchor(
+                egui::Align2::RIGHT_BOTTOM,
+                egui::vec2(-TOAST_MARGIN, -TOAST_MARGIN),
+            )
+            .order(egui::Order::Foreground)
+            .show(ctx, |ui| {
+                for (index, toast) in self.toasts.iter().enumerate() {
+                    let response = egui::Frame::popup(ui.style())
+                        .stroke(egui::Stroke::new(1.0, toast.level.color()))
+                        .show(ui, |ui| {
+                            ui.set_max_width(320.0);
+                            ui.horizontal(|ui| {
+                                ui.colored_label(toast.level.color(), toast.level.icon());
+                                ui.label(&toast.message);
+                            });
+                        })
+                        .response
+                        .interact(egui::Sense::click())
+                        .on_hover_text("Click to dismiss");
+                    if response.clicked() {
+                        dismissed = Some(index);
+                    }
+                }
+            });
+        if let Some(index) = dismissed {
+            self.toasts.remove(index);
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_toasts_expire_and_are_not_repeated() {
+        let mut toasts = Toasts::default();
+        toasts.error("Element too small");
+        toasts.success("Saved");
+        toasts.error("Element too small");
+        assert_eq!(
+            toasts.messages().collect::<Vec<_>>(),
+            vec![
+                (ToastLevel::Success, "Saved"),
+                (ToastLevel::Error, "Element too small")
+            ]
+        );
+
+        // Errors outlast other messages
+        toasts.remove_expired(Instant::now() + MESSAGE_DURATION);
+        assert_eq!(toasts.messages().count(), 1);
+        toasts.remove_expired(Instant::now() + ERROR_DURATION);
+        assert_eq!(toasts.messages().count(), 0);
+
+        for i in 0..MAX_TOASTS + 2 {
+            toasts.info(format!("Message {}", i));
+        }
+        assert_eq!(toasts.messages().count(), MAX_TOASTS);
+        assert_eq!(toasts.messages().next().unwrap().1, "Message 2");
+    }
+}
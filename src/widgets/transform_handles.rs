@@ -0,0 +1,429 @@
+use crate::element::Obb;
+use egui::{Color32, CursorIcon, Id, Painter, Pos2, Rect, Response, Stroke, Ui, Vec2};
+
+/// Represents a corner of a selection box
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum Corner {
+    TopLeft,
+    TopRight,
+    BottomLeft,
+    BottomRight,
+}
+
+impl Corner {
+    pub const ALL: [Corner; 4] = [
+        Corner::TopLeft,
+        Corner::TopRight,
+        Corner::BottomLeft,
+        Corner::BottomRight,
+    ];
+
+    pub fn cursor_icon(&self) -> CursorIcon {
+        match self {
+            Corner::TopLeft => CursorIcon::ResizeNwSe,
+            Corner::TopRight => CursorIcon::ResizeNeSw,
+            Corner::BottomLeft => CursorIcon::ResizeNeSw,
+            Corner::BottomRight => CursorIcon::ResizeNwSe,
+        }
+    }
+
+    /// Position of this corner on `rect`
+    pub fn pos(&self, rect: Rect) -> Pos2 {
+        match self {
+            Corner::TopLeft => rect.left_top(),
+            Corner::TopRight => rect.right_top(),
+            Corner::BottomLeft => rect.left_bottom(),
+            Corner::BottomRight => rect.right_bottom(),
+        }
+    }
+
+    /// The corner across the rectangle, which stays put while this one is dragged
+    pub fn opposite(&self) -> Corner {
+        match self {
+            Corner::TopLeft => Corner::BottomRight,
+            Corner::TopRight => Corner::BottomLeft,
+            Corner::BottomLeft => Corner::TopRight,
+            Corner::BottomRight => Corner::TopLeft,
+        }
+    }
+}
+
+/// Represents an edge of a selection box
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum Edge {
+    Top,
+    Bottom,
+    Left,
+    Right,
+}
+
+impl Edge {
+    pub const ALL: [Edge; 4] = [Edge::Top, Edge::Bottom, Edge::Left, Edge::Right];
+
+    pub fn cursor_icon(&self) -> CursorIcon {
+        match self {
+            Edge::Top | Edge::Bottom => CursorIcon::ResizeVertical,
+            Edge::Left | Edge::Right => CursorIcon::ResizeHorizontal,
+        }
+    }
+
+    /// Position of the middle of this edge on `rect`
+    pub fn pos(&self, rect: Rect) -> Pos2 {
+        match self {
+            Edge::Top => rect.center_top(),
+            Edge::Bottom => rect.center_bottom(),
+            Edge::Left => rect.left_center(),
+            Edge::Right => rect.right_center(),
+        }
+    }
+}
+
+/// One of the handles around a selection: a corner, the middle of an edge, or the rotation knob
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum TransformHandle {
+    Corner(Corner),
+    Edge(Edge),
+    Rotate,
+}
+
+impl TransformHandle {
+    /// Cursor shown while hovering the handle; the rotation knob shows a closed hand while dragged
+    pub fn cursor_icon(&self) -> CursorIcon {
+        match self {
+            TransformHandle::Corner(corner) => corner.cursor_icon(),
+            TransformHandle::Edge(edge) => edge.cursor_icon(),
+            TransformHandle::Rotate => CursorIcon::Grab,
+        }
+    }
+
+    /// The rectangle `original` resized by dragging this handle to `pos`
+    ///
+    /// The sides not attached to the handle stay put, and the result is never
+    /// smaller than `min_size` in the dimensions the handle changes. The rotation
+    /// knob doesn't resize and returns `original`.
+    pub fn resize_rect(&self, original: Rect, pos: Pos2, min_size: f32) -> Rect {
+        let mut rect = original;
+        let (left, right, top, bottom) = match self {
+            TransformHandle::Corner(Corner::TopLeft) => (true, false, true, false),
+            TransformHandle::Corner(Corner::TopRight) => (false, true, true, false),
+            TransformHandle::Corner(Corner::BottomLeft) => (true, false, false, true),
+            TransformHandle::Corner(Corner::BottomRight) => (false, true, false, true),
+            TransformHandle::Edge(Edge::Top) => (false, false, true, false),
+            TransformHandle::Edge(Edge::Bottom) => (false, false, false, true),
+            TransformHandle::Edge(Edge::Left) => (true, false, false, false),
+            TransformHandle::Edge(Edge::Right) => (false, true, false, false),
+            TransformHandle::Rotate => return original,
+        };
+
+        if left {
+            rect.min.x = pos.x.min(rect.max.x - min_size);
+        }
+        if right {
+            rect.max.x = pos.x.max(rect.min.x + min_size);
+        }
+        if top {
+            rect.min.y = pos.y.min(rect.max.y - min_size);
+        }
+        if bottom {
+            rect.max.y = pos.y.max(rect.min.y + min_size);
+        }
+        rect
+    }
+}
+
+/// Colors, sizes and which handles to show
+#[derive(Clone, Debug, PartialEq)]
+pub struct TransformHandleStyle {
+    /// Radius handles are drawn with
+    pub radius: f32,
+    /// Radius of the handle being hovered or dragged
+    pub active_radius: f32,
+    /// How far from a handle's center the pointer still grabs it
+    pub hit_radius: f32,
+    pub fill: Color32,
+    pub stroke: Stroke,
+    pub active_fill: Color32,
+    pub active_stroke: Stroke,
+    /// Show handles in the middle of each edge, resizing in one direction only
+    pub edge_handles: bool,
+    /// Show a knob above the top edge for rotating
+    pub rotation_knob: bool,
+    /// Distance between the top edge and the rotation knob
+    pub rotation_offset: f32,
+    /// Angle the rotation knob snaps to multiples of, in radians
+    pub angle_snap: f32,
+}
+
+impl Default for TransformHandleStyle {
+    fn default() -> Self {
+        Self {
+            radius: 7.5,
+            active_radius: 15.0,
+            hit_radius: 15.0,
+            fill: Color32::from_rgb(200, 200, 200),
+            stroke: Stroke::new(1.0, Color32::BLACK),
+            active_fill: Color32::from_rgb(100, 200, 255),
+            active_stroke: Stroke::new(2.0, Color32::WHITE),
+            edge_handles: true,
+            rotation_knob: false,
+            rotation_offset: 24.0,
+            angle_snap: std::f32::consts::FRAC_PI_4,
+        }
+    }
+}
+
+/// Handles for resizing (and optionally rotating) a rectangle
+///
+/// The rectangle may be turned about its center, as a rotated element's is; the
+/// handles then sit on its rotated corners and edges, and `resize_rect` expects
+/// positions mapped back with `to_local`.
+///
+/// Hit testing and painting work on positions alone, so tools that handle pointer
+/// events themselves can use `handle_at` and `paint`; `show` does both through
+/// egui interaction, setting the cursor to match the hovered handle.
+#[derive(Clone, Debug)]
+pub struct TransformHandles {
+    rect: Rect,
+    /// Clockwise turn of `rect` about its center, in radians
+    rotation: f32,
+    style: TransformHandleStyle,
+    id: Id,
+}
+
+impl TransformHandles {
+    pub fn new(rect: Rect) -> Self {
+        Self {
+            rect,
+            rotation: 0.0,
+            style: TransformHandleStyle::default(),
+            id: Id::new("transform_handles"),
+        }
+    }
+
+    /// Handles around `obb`'s unrotated rectangle, turned with it
+    pub fn from_obb(obb: Obb) -> Self {
+        Self::new(obb.rect).with_rotation(obb.rotation)
+    }
+
+    /// Turn the handles with the rectangle by `rotation` radians clockwise
+    pub fn with_rotation(mut self, rotation: f32) -> Self {
+        self.rotation = rotation;
+        self
+    }
+
+    pub fn with_style(mut self, style: TransformHandleStyle) -> Self {
+        self.style = style;
+        self
+    }
+
+    /// Distinguish these handles' interactions from other sets shown in the same frame
+    pub fn id_salt(mut self, id_salt: impl std::hash::Hash) -> Self {
+        self.id = Id::new(("transform_handles", id_salt));
+        self
+    }
+
+    pub fn rect(&self) -> Rect {
+        self.rect
+    }
+
+    pub fn style(&self) -> &TransformHandleStyle {
+        &self.style
+    }
+
+    fn obb(&self) -> Obb {
+        Obb::new(self.rect, self.rotation)
+    }
+
+    /// Map a position into the rectangle's unrotated frame, for `resize_rect`
+    pub fn to_local(&self, pos: Pos2) -> Pos2 {
+        self.obb().to_local(pos)
+    }
+
+    /// The handles the style enables, with their positions
+    pub fn handles(&self) -> Vec<(TransformHandle, Pos2)> {
+        let mut handles: Vec<(TransformHandle, Pos2)> = Corner::ALL
+            .iter()
+            .map(|corner| (TransformHandle::Corner(*corner), corner.pos(self.rect)))
+            .collect();
+        if self.style.edge_handles {
+            handles.extend(
+                Edge::ALL
+                    .iter()
+                    .map(|edge| (TransformHandle::Edge(*edge), edge.pos(self.rect))),
+            );
+        }
+        if self.style.rotation_knob {
+            handles.push((TransformHandle::Rotate, self.rotation_knob_pos()));
+        }
+        let obb = self.obb();
+        for (_, pos) in &mut handles {
+            *pos = obb.to_world(*pos);
+        }
+        handles
+    }
+
+    /// Where the knob is before the rectangle is turned
+    fn rotation_knob_pos(&self) -> Pos2 {
+        self.rect.center_top() - Vec2::new(0.0, self.style.rotation_offset)
+    }
+
+    /// The handle under `pos`, preferring the closest when several overlap
+    pub fn handle_at(&self, pos: Pos2) -> Option<TransformHandle> {
+        self.handles()
+            .into_iter()
+            .map(|(handle, handle_pos)| (handle, handle_pos.distance(pos)))
+            .filter(|(_, distance)| *distance <= self.style.hit_radius)
+            .min_by(|a, b| a.1.total_cmp(&b.1))
+            .map(|(handle, _)| handle)
+    }
+
+    /// Rotation of the knob dragged to `pos`, in radians clockwise from straight up
+    pub fn rotation_angle(&self, pos: Pos2) -> f32 {
+        (pos - self.rect.center()).angle() + std::f32::consts::FRAC_PI_2
+    }
+
+    /// [`Self::rotation_angle`] rounded to the nearest multiple of the style's
+    /// angle snap, as when dragging the knob with Shift held
+    pub fn snapped_rotation_angle(&self, pos: Pos2) -> f32 {
+        let angle = self.rotation_angle(pos);
+        let snap = self.style.angle_snap;
+        if snap > 0.0 {
+            (angle / snap).round() * snap
+        } else {
+            angle
+        }
+    }
+
+    /// Draw the handles, highlighting `active`
+    pub fn paint(&self, painter: &Painter, active: Option<TransformHandle>) {
+        let style = &self.style;
+        if style.rotation_knob {
+            let obb = self.obb();
+            painter.line_segment(
+                [
+                    obb.to_world(self.rect.center_top()),
+                    obb.to_world(self.rotation_knob_pos()),
+                ],
+                style.stroke,
+            );
+        }
+        for (handle, pos) in self.handles() {
+            if Some(handle) == active {
+                painter.circle_filled(pos, style.active_radius, style.active_fill);
+                painter.circle_stroke(pos, style.active_radius, style.active_stroke);
+            } else {
+                painter.circle_filled(pos, style.radius, style.fill);
+                painter.circle_stroke(pos, style.radius, style.stroke);
+            }
+        }
+    }
+
+    /// Sense hovering and dragging on every handle without drawing anything
+    ///
+    /// Returns the handle being dragged, else the one hovered, with its response,
+    /// and sets the cursor for it. The handles are sensed where they show on a
+    /// rotated canvas; map the response's positions back with
+    /// [`crate::view::layer_to_document`].
+    pub fn interact(&self, ui: &mut Ui) -> Option<(TransformHandle, Response)> {
+        let mut active: Option<(TransformHandle, Response)> = None;
+        for (handle, pos) in self.handles() {
+            let pos = crate::view::document_to_layer(ui.ctx(), pos);
+            let rect = Rect::from_center_size(pos, Vec2::splat(self.style.hit_radius * 2.0));
+            let response = ui.interact(rect, self.id.with(handle), egui::Sense::drag());
+            let takes_over = match &active {
+                None => response.hovered() || response.dragged() || response.drag_stopped(),
+                Some((_, current)) => response.dragged() && !current.dragged(),
+            };
+            if takes_over {
+                active = Some((handle, response));
+            }
+        }
+
+        if let Some((handle, response)) = &active {
+            let cursor = if *handle == TransformHandle::Rotate && response.dragged() {
+                CursorIcon::Grabbing
+            } else {
+                handle.cursor_icon()
+            };
+            ui.ctx().set_cursor_icon(cursor);
+        }
+        active
+    }
+
+    /// Sense and draw the handles, highlighting the one hovered or dragged
+    pub fn show(&self, ui: &mut Ui) -> Option<(TransformHandle, Response)> {
+        let active = self.interact(ui);
+        self.paint(ui.painter(), active.as_ref().map(|(handle, _)| *handle));
+        active
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_handles_hit_and_resize_their_sides() {
+        let rect = Rect::from_min_max(Pos2::new(0.0, 0.0), Pos2::new(100.0, 50.0));
+        let handles = TransformHandles::new(rect);
+        assert_eq!(handles.handles().len(), 8);
+        assert_eq!(
+            handles.handle_at(Pos2::new(52.0, 48.0)),
+            Some(TransformHandle::Edge(Edge::Bottom))
+        );
+        assert_eq!(
+            handles.handle_at(Pos2::new(98.0, 2.0)),
+            Some(TransformHandle::Corner(Corner::TopRight))
+        );
+        assert_eq!(handles.handle_at(Pos2::new(30.0, 25.0)), None);
+
+        let resized =
+            TransformHandle::Edge(Edge::Left).resize_rect(rect, Pos2::new(-20.0, 99.0), 10.0);
+        assert_eq!(resized, Rect::from_min_max(Pos2::new(-20.0, 0.0), rect.max));
+        let clamped =
+            TransformHandle::Corner(Corner::BottomRight).resize_rect(rect, Pos2::ZERO, 10.0);
+        assert_eq!(clamped.size(), Vec2::splat(10.0));
+
+        let style = TransformHandleStyle {
+            edge_handles: false,
+            rotation_knob: true,
+            ..TransformHandleStyle::default()
+        };
+        let handles = TransformHandles::new(rect).with_style(style);
+        assert_eq!(handles.handles().len(), 5);
+        assert_eq!(
+            handles.handle_at(Pos2::new(50.0, -24.0)),
+            Some(TransformHandle::Rotate)
+        );
+        assert!(
+            (handles.rotation_angle(Pos2::new(200.0, 25.0)) - std::f32::consts::FRAC_PI_2).abs()
+                < 1e-5
+        );
+        assert!(
+            (handles.snapped_rotation_angle(Pos2::new(200.0, 0.0)) - std::f32::consts::FRAC_PI_2)
+                .abs()
+                < 1e-5
+        );
+    }
+
+    #[test]
+    fn test_rotated_handles_sit_on_turned_corners() {
+        let rect = Rect::from_min_max(Pos2::new(0.0, 0.0), Pos2::new(100.0, 50.0));
+        let handles = TransformHandles::from_obb(Obb::new(rect, std::f32::consts::PI));
+
+        // Half a turn swaps opposite corners and edges
+        assert_eq!(
+            handles.handle_at(Pos2::new(98.0, 48.0)),
+            Some(TransformHandle::Corner(Corner::TopLeft))
+        );
+        assert_eq!(
+            handles.handle_at(Pos2::new(50.0, 2.0)),
+            Some(TransformHandle::Edge(Edge::Bottom))
+        );
+
+        // Resizing happens in the unrotated frame
+        let local = handles.to_local(Pos2::new(50.0, -20.0));
+        let resized = TransformHandle::Edge(Edge::Bottom).resize_rect(rect, local, 10.0);
+        assert!((resized.max.y - 70.0).abs() < 1e-3);
+    }
+}
@@ -0,0 +1,438 @@
+//! Property tests for undo: random command sequences, executed, undone and
+//! redone, must bring the model back to each state it went through
+//!
+//! Sequences come from a seeded generator, each step from a seed of its own.
+//! A failing sequence is shrunk by dropping steps while it still fails, and
+//! the failure names the seeds of the smallest one left. Each command is built
+//! the way the editor builds it, against the model as it stands when the
+//! command runs. Models are compared by [`EditorModel::digest`].
+
+use eframe_paint::color_replace::ColorReplacement;
+use eframe_paint::command::{Command, CommandHistory};
+use eframe_paint::element::{DashPattern, Element, ElementId, ElementType, StrokeStyle, factory};
+use eframe_paint::geometry::{BooleanOp, simplify_polyline, smooth_polyline};
+use eframe_paint::ruler_guides::{GuideAxis, RulerGuide};
+use eframe_paint::state::{EditorModel, ModelDigest};
+use eframe_paint::tools::slice_command;
+use eframe_paint::widgets::{Corner, TransformHandle};
+use egui::{Color32, Pos2, Vec2, pos2, vec2};
+
+/// Random sequences tried by each test
+const CASES: u64 = 200;
+/// Most commands in one sequence
+const MAX_COMMANDS: usize = 12;
+/// Elements each model starts with
+const START_ELEMENTS: usize = 3;
+/// Least width and height of the elements and of what's halved, as float
+/// error from resizing grows as elements get thinner
+const MIN_SIZE: f32 = 16.0;
+const COLORS: [Color32; 3] = [Color32::RED, Color32::BLUE, Color32::from_rgb(40, 160, 40)];
+
+/// A position on a quarter pixel grid, which moves and doublings keep exact
+fn position(rng: &mut fastrand::Rng) -> Pos2 {
+    pos2(rng.i32(0..4000) as f32 / 4.0, rng.i32(0..4000) as f32 / 4.0)
+}
+
+fn color(rng: &mut fastrand::Rng) -> Color32 {
+    COLORS[rng.usize(..COLORS.len())]
+}
+
+/// A size at least [`MIN_SIZE`] each way, on the quarter pixel grid
+fn size(rng: &mut fastrand::Rng) -> Vec2 {
+    let side = |rng: &mut fastrand::Rng| rng.i32(MIN_SIZE as i32 * 4..800) as f32 / 4.0;
+    vec2(side(rng), side(rng))
+}
+
+/// A new stroke or rectangle, with the next free id
+fn random_element(rng: &mut fastrand::Rng, model: &EditorModel) -> ElementType {
+    let id = model.reserve_id();
+    let min = position(rng);
+    let size = size(rng);
+    if rng.bool() {
+        // Corner to corner, by way of points anywhere
+        let mut points = vec![min];
+        points.extend((0..rng.usize(..4)).map(|_| position(rng)));
+        points.push(min + size);
+        let thickness = [1.0, 2.0, 4.0][rng.usize(..3)];
+        factory::create_stroke(id, points, thickness, color(rng))
+    } else {
+        let corners = vec![
+            min,
+            min + vec2(size.x, 0.0),
+            min + size,
+            min + vec2(0.0, size.y),
+        ];
+        factory::create_shape(id, corners, color(rng))
+    }
+}
+
+fn random_element_id(rng: &mut fastrand::Rng, model: &EditorModel) -> Option<ElementId> {
+    let ids = model.all_element_ids();
+    (!ids.is_empty()).then(|| ids[rng.usize(..ids.len())])
+}
+
+/// Some of the elements `is_kind` picks out, in random order, or None if
+/// there are fewer than `least`
+fn random_elements_of_kind(
+    rng: &mut fastrand::Rng,
+    model: &EditorModel,
+    least: usize,
+    is_kind: fn(&ElementType) -> bool,
+) -> Option<Vec<ElementId>> {
+    let mut ids: Vec<ElementId> = model
+        .elements
+        .iter()
+        .filter(|element| is_kind(element))
+        .map(|element| element.id())
+        .collect();
+    rng.shuffle(&mut ids);
+    let count = rng.usize(least.min(ids.len())..=ids.len());
+    ids.truncate(count);
+    (count >= least.max(1)).then_some(ids)
+}
+
+fn is_stroke(element: &ElementType) -> bool {
+    matches!(element, ElementType::Stroke(_))
+}
+
+fn is_shape(element: &ElementType) -> bool {
+    matches!(element, ElementType::Shape(_))
+}
+
+/// A command the editor could run on `model` next, or None if the kind picked
+/// doesn't apply to it
+fn random_command(rng: &mut fastrand::Rng, model: &EditorModel) -> Option<Command> {
+    let kind = rng.u8(..18);
+    if kind == 0 {
+        return Some(Command::AddElement {
+            element: random_element(rng, model),
+        });
+    }
+    if kind == 1 {
+        let guide = RulerGuide::new(
+            [GuideAxis::Horizontal, GuideAxis::Vertical][rng.usize(..2)],
+            position(rng).x,
+        );
+        let mut guides = model.ruler_guides.clone();
+        guides.push(guide);
+        return Command::new_set_ruler_guides(model, "Add Guide", guides);
+    }
+    if kind == 2 {
+        return Some(Command::new_clear_selection(model));
+    }
+    if kind == 11 {
+        // Two commands as one, the second built against the model the first leaves
+        let first = random_command(rng, model).filter(|command| !is_batch(command))?;
+        let mut after = model.clone();
+        first.execute(&mut after).ok()?;
+        let second = random_command(rng, &after).filter(|command| !is_batch(command))?;
+        return Some(Command::Batch {
+            description: "Random Batch",
+            commands: vec![first, second],
+        });
+    }
+    if kind == 12 {
+        let ids = random_elements_of_kind(rng, model, 2, is_stroke)?;
+        // Far enough to join any two strokes
+        return Command::new_join_strokes(model, &ids, 10_000.0).ok();
+    }
+    if kind == 13 {
+        let ids = random_elements_of_kind(rng, model, 1, is_stroke)?;
+        return Command::new_outline_strokes(model, &ids).ok();
+    }
+    if kind == 14 {
+        let mut ids = random_elements_of_kind(rng, model, 2, is_shape)?;
+        ids.truncate(2);
+        let op = [BooleanOp::Union, BooleanOp::Subtract, BooleanOp::Intersect][rng.usize(..3)];
+        return Command::new_shape_boolean(model, &ids, op).ok();
+    }
+    if kind == 15 {
+        let ids = random_elements_of_kind(rng, model, 1, is_stroke)?;
+        return if rng.bool() {
+            Command::new_set_stroke_points(model, &ids, "Simplify Strokes", |points| {
+                simplify_polyline(points, 8.0)
+            })
+        } else {
+            Command::new_set_stroke_points(model, &ids, "Smooth Strokes", |points| {
+                smooth_polyline(points, 0.5)
+            })
+        }
+        .ok();
+    }
+
+    let element_id = random_element_id(rng, model)?;
+    let element = model.find_element_by_id(element_id)?;
+    match kind {
+        3 => Some(Command::new_remove_element(model, element_id)?),
+        4 => {
+            let old_position = element.rect().min;
+            Some(Command::MoveElement {
+                element_id,
+                _element_type: element.element_type().to_string(),
+                old_position,
+                new_position: old_position + (position(rng) - pos2(500.0, 500.0)),
+            })
+        }
+        5 => {
+            // Double or halve some of the elements as one, from the top left
+            let ids: Vec<ElementId> = model
+                .all_element_ids()
+                .into_iter()
+                .filter(|&id| id == element_id || rng.bool())
+                .collect();
+            let rects: Vec<egui::Rect> = ids
+                .iter()
+                .filter_map(|&id| model.find_element_by_id(id))
+                .map(|element| element.rect())
+                .collect();
+            let old_bounds = rects.iter().copied().reduce(egui::Rect::union)?;
+            let thinnest = rects.iter().map(|rect| rect.size().min_elem());
+            let factor = if rng.bool() || thinnest.fold(f32::INFINITY, f32::min) < MIN_SIZE * 2.0 {
+                2.0
+            } else {
+                0.5
+            };
+            let new_bounds = egui::Rect::from_min_size(old_bounds.min, old_bounds.size() * factor);
+            Command::new_resize_selection(
+                model,
+                &ids,
+                TransformHandle::Corner(Corner::BottomRight),
+                old_bounds,
+                new_bounds,
+            )
+        }
+        6 => Command::new_rename_element(model, element_id, format!("Part {}", rng.u8(..4))),
+        7 => {
+            let quarter_turns = rng.u8(..4) as f32;
+            Command::new_set_rotation(
+                model,
+                element_id,
+                quarter_turns * std::f32::consts::FRAC_PI_2,
+            )
+        }
+        8 => {
+            let dash =
+                [DashPattern::Solid, DashPattern::Dashed, DashPattern::Dotted][rng.usize(..3)];
+            let style = StrokeStyle {
+                dash,
+                ..element.stroke_style()
+            };
+            Command::new_set_stroke_style(model, element_id, style)
+        }
+        16 => {
+            // Across the element's middle, cutting any stroke that spans it
+            let rect = element.rect();
+            let reach = vec2(rect.width() / 2.0 + 10.0, 0.0);
+            slice_command(model, rect.center() - reach, rect.center() + reach)
+        }
+        17 => {
+            // Halfway along the first piece of the stroke, where it's drawn
+            let mut flat = element.clone();
+            flat.flatten_transform().ok()?;
+            let (points, _, _) = flat.polyline()?;
+            let (from, to) = points
+                .windows(2)
+                .map(|pair| (pair[0], pair[1]))
+                .find(|(from, to)| from.distance(*to) > 1.0)?;
+            Command::new_split_stroke(model, element_id, from.lerp(to, 0.5)).ok()
+        }
+        9 => Command::new_replace_colors(
+            model,
+            &ColorReplacement {
+                find: color(rng),
+                replace: color(rng),
+                tolerance: 0,
+            },
+        ),
+        _ => Some(match (model.is_element_selected(element_id), rng.bool()) {
+            (_, true) => Command::ToggleSelection(element_id),
+            (false, false) => Command::SelectElement(element_id),
+            (true, false) => Command::DeselectElement(element_id),
+        }),
+    }
+}
+
+fn is_batch(command: &Command) -> bool {
+    matches!(command, Command::Batch { .. })
+}
+
+/// One random sequence: the seed of the model it starts from, and a seed for
+/// each of its steps
+#[derive(Clone, Debug)]
+struct Case {
+    seed: u64,
+    steps: Vec<u64>,
+}
+
+impl Case {
+    fn new(seed: u64, max_steps: usize) -> Self {
+        let mut rng = fastrand::Rng::with_seed(seed);
+        let steps = (0..rng.usize(1..=max_steps)).map(|_| rng.u64(..)).collect();
+        Self { seed, steps }
+    }
+
+    /// A model with a few elements in it already
+    fn start(&self) -> EditorModel {
+        let mut rng = fastrand::Rng::with_seed(self.seed);
+        let mut model = EditorModel::new();
+        for _ in 0..START_ELEMENTS {
+            let element = random_element(&mut rng, &model);
+            model.add_element(element).unwrap();
+        }
+        model
+    }
+}
+
+/// Check `property` against the sequences from each seed, shrinking the
+/// first that fails and panicking with what's left of it
+fn check_cases(max_steps: usize, property: fn(&Case) -> Result<(), String>) {
+    for seed in 0..CASES {
+        let case = Case::new(seed, max_steps);
+        if let Err(failure) = property(&case) {
+            let (case, failure) = shrink(case, failure, property);
+            panic!("{}\nsmallest failing case: {:?}", failure, case);
+        }
+    }
+}
+
+/// Drop steps from a failing `case` one at a time, for as long as it keeps
+/// failing, returning the smallest case found and how it fails
+fn shrink(
+    mut case: Case,
+    mut failure: String,
+    property: fn(&Case) -> Result<(), String>,
+) -> (Case, String) {
+    let mut step = 0;
+    while step < case.steps.len() {
+        let mut smaller = case.clone();
+        smaller.steps.remove(step);
+        match property(&smaller) {
+            Err(smaller_failure) => (case, failure) = (smaller, smaller_failure),
+            Ok(()) => step += 1,
+        }
+    }
+    (case, failure)
+}
+
+/// Run the random command the step's seed picks on `model`, returning its
+/// label, or None if the kind picked didn't apply
+fn execute_random(
+    rng: &mut fastrand::Rng,
+    model: &mut EditorModel,
+    history: &mut CommandHistory,
+) -> Result<Option<&'static str>, String> {
+    let Some(command) = random_command(rng, model) else {
+        return Ok(None);
+    };
+    let label = command.label();
+    history
+        .execute(command, model)
+        .map_err(|e| format!("{} failed: {}", label, e))?;
+    Ok(Some(label))
+}
+
+/// Undoing the commands one by one brings back each state in turn, and redoing
+/// them brings back the states after
+fn undo_and_redo_retrace_the_states(case: &Case) -> Result<(), String> {
+    let mut model = case.start();
+    let mut history = CommandHistory::new();
+    let mut states: Vec<ModelDigest> = vec![model.digest()];
+    let mut labels = Vec::new();
+    for &step in &case.steps {
+        let mut rng = fastrand::Rng::with_seed(step);
+        if let Some(label) = execute_random(&mut rng, &mut model, &mut history)? {
+            labels.push(label);
+            states.push(model.digest());
+        }
+    }
+
+    for (step, before) in states.iter().enumerate().rev().skip(1) {
+        history.undo(&mut model)?;
+        if &model.digest() != before {
+            return Err(format!(
+                "undoing {} after {:?} left {:?}, not {:?}",
+                labels[step],
+                &labels[..step],
+                model.digest(),
+                before
+            ));
+        }
+    }
+    if history.can_undo() {
+        return Err("more to undo than was done".to_string());
+    }
+
+    for (step, after) in states.iter().enumerate().skip(1) {
+        history.redo(&mut model)?;
+        if &model.digest() != after {
+            return Err(format!(
+                "redoing {} after {:?} left {:?}, not {:?}",
+                labels[step - 1],
+                &labels[..step - 1],
+                model.digest(),
+                after
+            ));
+        }
+    }
+    if history.can_redo() {
+        return Err("more to redo than was undone".to_string());
+    }
+    Ok(())
+}
+
+/// Executing, undoing and redoing in any mix moves through the states a model
+/// of the history says it should
+fn any_mix_follows_the_history(case: &Case) -> Result<(), String> {
+    let mut model = case.start();
+    let mut history = CommandHistory::new();
+    // The states undo and redo lead to, nearest last
+    let mut past: Vec<ModelDigest> = Vec::new();
+    let mut future: Vec<ModelDigest> = Vec::new();
+
+    for &step in &case.steps {
+        let mut rng = fastrand::Rng::with_seed(step);
+        let before = model.digest();
+        let action = match rng.u8(..4) {
+            0 if history.can_undo() => {
+                history.undo(&mut model)?;
+                if Some(&model.digest()) != past.last() {
+                    return Err("undo didn't bring back the state before".to_string());
+                }
+                past.pop();
+                future.push(before);
+                "undo"
+            }
+            1 if history.can_redo() => {
+                history.redo(&mut model)?;
+                if Some(&model.digest()) != future.last() {
+                    return Err("redo didn't bring back the state after".to_string());
+                }
+                future.pop();
+                past.push(before);
+                "redo"
+            }
+            _ => match execute_random(&mut rng, &mut model, &mut history)? {
+                Some(label) => {
+                    past.push(before);
+                    future.clear();
+                    label
+                }
+                None => continue,
+            },
+        };
+        if history.can_undo() == past.is_empty() || history.can_redo() == future.is_empty() {
+            return Err(format!("the history lost track after {}", action));
+        }
+    }
+    Ok(())
+}
+
+#[test]
+fn test_undo_brings_back_each_state_and_redo_reproduces_it() {
+    check_cases(MAX_COMMANDS, undo_and_redo_retrace_the_states);
+}
+
+#[test]
+fn test_any_mix_of_execute_undo_and_redo_follows_the_history() {
+    check_cases(MAX_COMMANDS * 2, any_mix_follows_the_history);
+}